@@ -0,0 +1,427 @@
+//! Attention requests from background tabs.
+//!
+//! Each Neovide is started with its nvim listening on a named pipe. A background
+//! thread connects to that pipe and installs a small Lua hook that sends an RPC
+//! notification when `vim.notify` is called with the ERROR level, or when the user
+//! runs `:NeovideTabsAttention` (e.g. from a build job's exit callback). The
+//! notification is forwarded to the wrapper window, which pulses the tab.
+//!
+//! The terminal bell itself is only reported to attached UIs, and attaching a
+//! second UI would resize nvim's grid, so it cannot be observed this way.
+//!
+//! The pipe also lets the wrapper tell when a close was cancelled: after nvim is
+//! asked to quit, it is polled until it exits or stops showing the save prompt,
+//! and turn off Neovide's animations while a tab is in the background.
+
+#![cfg(target_os = "windows")]
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rmpv::Value;
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::UI::WindowsAndMessaging::{PostMessageW, WM_APP};
+
+/// Posted to the wrapper when a tab asks for attention; WPARAM is the tab ID
+pub const WM_TAB_ATTENTION: u32 = WM_APP + 13;
+
+/// Ex command that quits nvim, asking about unsaved changes
+const QUIT_COMMAND: &str = "confirm qa";
+
+/// Name of the RPC notification sent by the Lua hook
+const ATTENTION_EVENT: &str = "neovide_tabs_attention";
+
+/// How long the wrapper keeps trying to connect while nvim starts up
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(60);
+/// Delay between two connection attempts
+const CONNECT_RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// How long nvim gets to quit or show its save prompt after being asked to close
+const CLOSE_PROMPT_DELAY: Duration = Duration::from_secs(2);
+/// Delay between two checks whether nvim still shows the save prompt
+const CLOSE_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Lua installed in nvim; forwards error notifications and `:NeovideTabsAttention`
+const HOOK_LUA: &str = r#"
+local chan = vim.api.nvim_get_api_info()[1]
+local function attention(kind)
+  pcall(vim.rpcnotify, chan, "neovide_tabs_attention", kind)
+end
+local notify = vim.notify
+vim.notify = function(msg, level, opts)
+  if level == vim.log.levels.ERROR then
+    attention("error")
+  end
+  return notify(msg, level, opts)
+end
+vim.api.nvim_create_user_command("NeovideTabsAttention", function()
+  attention("user")
+end, { desc = "Highlight this tab in neovide-tabs" })
+"#;
+
+/// Lua that turns Neovide's animations off (argument true) and lets it idle, or
+/// restores the settings it had before (false)
+const PAUSE_ANIMATIONS_LUA: &str = r#"
+local paused = ...
+local settings = {
+  neovide_no_idle = false,
+  neovide_refresh_rate_idle = 1,
+  neovide_cursor_animation_length = 0,
+  neovide_cursor_trail_size = 0,
+  neovide_cursor_vfx_mode = "",
+  neovide_scroll_animation_length = 0,
+  neovide_position_animation_length = 0,
+}
+if paused and _G.neovide_tabs_animations == nil then
+  local saved = {}
+  for name, value in pairs(settings) do
+    saved[name] = { vim.g[name] }
+    vim.g[name] = value
+  end
+  _G.neovide_tabs_animations = saved
+elseif not paused and _G.neovide_tabs_animations ~= nil then
+  for name, value in pairs(_G.neovide_tabs_animations) do
+    vim.g[name] = value[1]
+  end
+  _G.neovide_tabs_animations = nil
+end
+"#;
+
+/// Named pipe nvim listens on for the tab `tab_id` of this wrapper process
+pub fn pipe_name(tab_id: usize) -> String {
+    format!(r"\\.\pipe\neovide-tabs-{}-{}", std::process::id(), tab_id)
+}
+
+/// Connect to the tab's nvim in the background and forward its attention requests
+/// to `parent_hwnd`. The thread ends when nvim exits.
+pub fn watch(tab_id: usize, parent_hwnd: HWND) {
+    let parent_hwnd_raw = parent_hwnd.0 as usize;
+    thread::spawn(move || {
+        let Some(pipe) = connect(&pipe_name(tab_id)) else {
+            eprintln!(
+                "Could not connect to nvim of tab {} for attention requests",
+                tab_id
+            );
+            return;
+        };
+        if let Err(e) = forward_attention(pipe, tab_id, HWND(parent_hwnd_raw as *mut _)) {
+            eprintln!("Attention forwarding for tab {} stopped: {}", tab_id, e);
+        }
+    });
+}
+
+/// Open the named pipe, retrying while nvim is still starting
+fn connect(name: &str) -> Option<File> {
+    let started = Instant::now();
+    while started.elapsed() < CONNECT_TIMEOUT {
+        if let Ok(pipe) = OpenOptions::new().read(true).write(true).open(name) {
+            return Some(pipe);
+        }
+        thread::sleep(CONNECT_RETRY_DELAY);
+    }
+    None
+}
+
+/// Install the Lua hook, then post WM_TAB_ATTENTION for every attention notification
+fn forward_attention(mut pipe: File, tab_id: usize, parent_hwnd: HWND) -> anyhow::Result<()> {
+    let request = Value::Array(vec![
+        Value::from(0),
+        Value::from(1),
+        Value::from("nvim_exec_lua"),
+        Value::Array(vec![Value::from(HOOK_LUA), Value::Array(Vec::new())]),
+    ]);
+    let mut buffer = Vec::new();
+    rmpv::encode::write_value(&mut buffer, &request)?;
+    pipe.write_all(&buffer)?;
+
+    let mut reader = BufReader::new(pipe);
+    loop {
+        // Fails once nvim exits and the pipe closes
+        let message = rmpv::decode::read_value(&mut reader)?;
+        if is_attention_notification(&message) {
+            unsafe {
+                let _ = PostMessageW(parent_hwnd, WM_TAB_ATTENTION, WPARAM(tab_id), LPARAM(0));
+            }
+        }
+    }
+}
+
+/// Ask the nvim listening on `address` to quit, prompting for unsaved changes the
+/// way closing the Neovide window does. Used when the window refuses WM_CLOSE.
+pub fn request_quit(address: &str) -> anyhow::Result<()> {
+    send_command(address, QUIT_COMMAND)
+}
+
+/// Watch the nvim listening on `address` after it was asked to quit, and set
+/// `cancelled` once it showed the save prompt and then went on running without
+/// waiting for input: the user cancelled the prompt. An nvim that never prompts
+/// is just slow to exit. The thread ends when nvim exits.
+pub fn watch_close(address: &str, cancelled: Arc<AtomicBool>) {
+    let address = address.to_string();
+    thread::spawn(move || {
+        thread::sleep(CLOSE_PROMPT_DELAY);
+        // Fails once nvim exits and the pipe closes
+        if wait_for_close_cancel(&address).is_ok() {
+            eprintln!("nvim at {} stayed open after being asked to quit", address);
+            cancelled.store(true, Ordering::Relaxed);
+        }
+    });
+}
+
+/// Ask nvim for its mode until the save prompt was shown and left without
+/// quitting. `nvim_get_mode` is answered even while a prompt is shown.
+fn wait_for_close_cancel(address: &str) -> anyhow::Result<()> {
+    let pipe = OpenOptions::new().read(true).write(true).open(address)?;
+    let mut reader = BufReader::new(&pipe);
+    let mut watch = CloseWatch::default();
+    let mut id = 0;
+    loop {
+        id += 1;
+        let mut buffer = Vec::new();
+        rmpv::encode::write_value(&mut buffer, &mode_request(id))?;
+        (&pipe).write_all(&buffer)?;
+        let blocking = loop {
+            let message = rmpv::decode::read_value(&mut reader)?;
+            if let Some(blocking) = mode_response_blocking(&message, id) {
+                break blocking;
+            }
+        };
+        if watch.cancelled(blocking) {
+            return Ok(());
+        }
+        thread::sleep(CLOSE_CHECK_INTERVAL);
+    }
+}
+
+/// What the mode replies after a quit request said so far
+#[derive(Debug, Default)]
+struct CloseWatch {
+    /// nvim waited for input (the save prompt) at some point
+    prompted: bool,
+}
+
+impl CloseWatch {
+    /// Take the next reply's `blocking` flag. True once the close was cancelled:
+    /// the prompt was shown, and nvim no longer waits on it.
+    fn cancelled(&mut self, blocking: bool) -> bool {
+        if blocking {
+            self.prompted = true;
+            false
+        } else {
+            self.prompted
+        }
+    }
+}
+
+/// RPC request for nvim's mode
+fn mode_request(id: u64) -> Value {
+    Value::Array(vec![
+        Value::from(0),
+        Value::from(id),
+        Value::from("nvim_get_mode"),
+        Value::Array(Vec::new()),
+    ])
+}
+
+/// Whether nvim waits for input according to the response to the mode request
+/// `id`; None for other messages. A response without the flag counts as waiting.
+fn mode_response_blocking(message: &Value, id: u64) -> Option<bool> {
+    let [kind, response_id, _, result] = message.as_array()?.as_slice() else {
+        return None;
+    };
+    if kind.as_u64() != Some(1) || response_id.as_u64() != Some(id) {
+        return None;
+    }
+    let blocking = result.as_map().and_then(|map| {
+        map.iter()
+            .find(|(key, _)| key.as_str() == Some("blocking"))
+            .and_then(|(_, value)| value.as_bool())
+    });
+    Some(blocking.unwrap_or(true))
+}
+
+/// Have the nvim listening on `address` edit `path` (`neovide-tabs open`)
+pub fn request_edit(address: &str, path: &Path) -> anyhow::Result<()> {
+    send_command(address, &edit_command(path))
+}
+
+/// Turn Neovide's animations off (or back on) in the nvim listening on `address`
+pub fn request_animations_paused(address: &str, paused: bool) -> anyhow::Result<()> {
+    send_notification(address, &lua_notification(PAUSE_ANIMATIONS_LUA, paused))
+}
+
+/// Run the Ex `command` in the nvim listening on `address`
+fn send_command(address: &str, command: &str) -> anyhow::Result<()> {
+    send_notification(address, &command_notification(command))
+}
+
+/// Send an RPC notification to the nvim listening on `address`
+fn send_notification(address: &str, notification: &Value) -> anyhow::Result<()> {
+    let mut pipe = OpenOptions::new().read(true).write(true).open(address)?;
+    let mut buffer = Vec::new();
+    rmpv::encode::write_value(&mut buffer, notification)?;
+    pipe.write_all(&buffer)?;
+    Ok(())
+}
+
+/// RPC notification that runs Lua `code` with a boolean argument
+fn lua_notification(code: &str, argument: bool) -> Value {
+    Value::Array(vec![
+        Value::from(2),
+        Value::from("nvim_exec_lua"),
+        Value::Array(vec![
+            Value::from(code),
+            Value::Array(vec![Value::from(argument)]),
+        ]),
+    ])
+}
+
+/// RPC notification that runs an Ex command (a notification, so the wrapper doesn't
+/// wait while nvim shows a prompt such as the save prompt of `:confirm qa`)
+fn command_notification(command: &str) -> Value {
+    Value::Array(vec![
+        Value::from(2),
+        Value::from("nvim_command"),
+        Value::Array(vec![Value::from(command)]),
+    ])
+}
+
+/// `:edit` command for `path`, with the characters Ex treats specially escaped
+/// (backslashes stay path separators, as in `fnameescape()` on Windows)
+fn edit_command(path: &Path) -> String {
+    let mut command = String::from("edit ");
+    for c in path.to_string_lossy().chars() {
+        if matches!(
+            c,
+            ' ' | '\t' | '%' | '#' | '|' | '"' | '[' | '{' | '`' | '$' | '!' | '<' | '*' | '?'
+        ) {
+            command.push('\\');
+        }
+        command.push(c);
+    }
+    command
+}
+
+/// Whether an RPC message is the attention notification sent by the Lua hook
+fn is_attention_notification(message: &Value) -> bool {
+    match message.as_array().map(Vec::as_slice) {
+        Some([kind, method, ..]) => {
+            kind.as_u64() == Some(2) && method.as_str() == Some(ATTENTION_EVENT)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_attention_notification() {
+        let notification = Value::Array(vec![
+            Value::from(2),
+            Value::from(ATTENTION_EVENT),
+            Value::Array(vec![Value::from("error")]),
+        ]);
+        assert!(is_attention_notification(&notification));
+
+        // The response to the hook installation is not a notification
+        let response = Value::Array(vec![Value::from(1), Value::from(1), Value::Nil, Value::Nil]);
+        assert!(!is_attention_notification(&response));
+        let other = Value::Array(vec![Value::from(2), Value::from("redraw")]);
+        assert!(!is_attention_notification(&other));
+        assert!(!is_attention_notification(&Value::Nil));
+    }
+
+    #[test]
+    fn test_quit_notification() {
+        // A notification (type 2) has no message ID, so nvim sends no response
+        let notification = command_notification(QUIT_COMMAND);
+        let parts = notification.as_array().unwrap();
+        assert_eq!(parts[0].as_u64(), Some(2));
+        assert_eq!(parts[1].as_str(), Some("nvim_command"));
+        assert_eq!(parts[2].as_array().unwrap()[0].as_str(), Some("confirm qa"));
+    }
+
+    #[test]
+    fn test_pause_animations_notification() {
+        let notification = lua_notification(PAUSE_ANIMATIONS_LUA, true);
+        let parts = notification.as_array().unwrap();
+        assert_eq!(parts[0].as_u64(), Some(2));
+        assert_eq!(parts[1].as_str(), Some("nvim_exec_lua"));
+        let params = parts[2].as_array().unwrap();
+        assert_eq!(params[0].as_str(), Some(PAUSE_ANIMATIONS_LUA));
+        assert_eq!(params[1].as_array().unwrap()[0].as_bool(), Some(true));
+    }
+
+    #[test]
+    fn test_mode_response_blocking() {
+        let response = |id: u64, blocking: Value| {
+            Value::Array(vec![
+                Value::from(1),
+                Value::from(id),
+                Value::Nil,
+                Value::Map(vec![
+                    (Value::from("mode"), Value::from("n")),
+                    (Value::from("blocking"), blocking),
+                ]),
+            ])
+        };
+        assert_eq!(
+            mode_response_blocking(&response(3, Value::from(false)), 3),
+            Some(false)
+        );
+        assert_eq!(
+            mode_response_blocking(&response(3, Value::from(true)), 3),
+            Some(true)
+        );
+        // Responses to other requests and notifications are skipped
+        assert_eq!(
+            mode_response_blocking(&response(2, Value::from(false)), 3),
+            None
+        );
+        let notification = Value::Array(vec![Value::from(2), Value::from("redraw")]);
+        assert_eq!(mode_response_blocking(&notification, 3), None);
+        // An error response keeps the watcher waiting
+        let error = Value::Array(vec![
+            Value::from(1),
+            Value::from(3),
+            Value::from("error"),
+            Value::Nil,
+        ]);
+        assert_eq!(mode_response_blocking(&error, 3), Some(true));
+    }
+
+    #[test]
+    fn test_close_watch() {
+        // No prompt: nvim is slow to exit (autocmds, shada, LSP shutdown)
+        let mut watch = CloseWatch::default();
+        assert!(!watch.cancelled(false));
+        assert!(!watch.cancelled(false));
+        assert!(!watch.cancelled(false));
+
+        // The prompt shows, then nvim is back to normal: cancelled
+        let mut watch = CloseWatch::default();
+        assert!(!watch.cancelled(false));
+        assert!(!watch.cancelled(true));
+        assert!(!watch.cancelled(true));
+        assert!(watch.cancelled(false));
+    }
+
+    #[test]
+    fn test_edit_command() {
+        assert_eq!(
+            edit_command(Path::new(r"C:\src\app\main.rs")),
+            r"edit C:\src\app\main.rs"
+        );
+        assert_eq!(
+            edit_command(Path::new(r"C:\My Projects\#notes% [draft].md")),
+            r"edit C:\My\ Projects\\#notes\%\ \[draft].md"
+        );
+    }
+}
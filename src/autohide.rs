@@ -0,0 +1,112 @@
+//! Auto-hiding title bar (`auto_hide_titlebar` config option).
+//!
+//! The title bar collapses to a thin strip while the pointer is away from the
+//! top of the window, and Neovide grows to cover it. Moving the pointer onto the
+//! strip, or holding Ctrl+Alt, slides it back down. This module tracks how far
+//! the title bar is shown; the window polls the pointer and moves Neovide.
+
+use std::time::{Duration, Instant};
+
+/// Height of the strip left of a collapsed title bar
+pub const COLLAPSED_HEIGHT: i32 = 3;
+/// How long the title bar takes to slide in or out
+const SLIDE_DURATION: Duration = Duration::from_millis(120);
+/// Longest time one step of the slide accounts for, so a late first timer tick
+/// doesn't skip most of it
+const MAX_STEP: Duration = Duration::from_millis(32);
+/// How long the title bar stays after the pointer left it
+const HIDE_DELAY: Duration = Duration::from_millis(500);
+
+/// How far the title bar is shown, and where it is heading
+#[derive(Debug)]
+pub struct AutoHide {
+    /// 0.0 collapsed to 1.0 fully shown
+    shown: f32,
+    /// Whether the title bar is (or stays) shown rather than collapsing
+    revealing: bool,
+    /// When something last wanted the title bar shown
+    last_wanted: Instant,
+    /// When `shown` was last advanced
+    last_step: Instant,
+}
+
+impl AutoHide {
+    /// Starts shown; it collapses once the pointer is away from it
+    pub fn new(now: Instant) -> Self {
+        AutoHide {
+            shown: 1.0,
+            revealing: true,
+            last_wanted: now,
+            last_step: now,
+        }
+    }
+
+    /// Advance to `now`. `wanted` says whether the pointer is near the top, or
+    /// something else needs the title bar (a popup, a drag, the reveal keys).
+    /// Without `animate` the title bar jumps instead of sliding. Returns true if
+    /// it moved (Neovide has to follow).
+    pub fn update(&mut self, wanted: bool, animate: bool, now: Instant) -> bool {
+        if wanted {
+            self.last_wanted = now;
+        }
+        self.revealing = wanted || now.duration_since(self.last_wanted) < HIDE_DELAY;
+        let step = if animate {
+            let elapsed = now.duration_since(self.last_step).min(MAX_STEP);
+            elapsed.as_secs_f32() / SLIDE_DURATION.as_secs_f32()
+        } else {
+            1.0
+        };
+        self.last_step = now;
+
+        let shown = if self.revealing {
+            (self.shown + step).min(1.0)
+        } else {
+            (self.shown - step).max(0.0)
+        };
+        let moved = shown != self.shown;
+        self.shown = shown;
+        moved
+    }
+
+    /// Whether the title bar is sliding in or out (the window polls faster then)
+    pub fn is_sliding(&self) -> bool {
+        self.shown != if self.revealing { 1.0 } else { 0.0 }
+    }
+
+    /// Top of Neovide's area below a `titlebar_height` tall title bar
+    pub fn content_top(&self, titlebar_height: i32) -> i32 {
+        let hidden = titlebar_height - COLLAPSED_HEIGHT;
+        COLLAPSED_HEIGHT + (hidden as f32 * self.shown).round() as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_hide() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut auto_hide = AutoHide::new(start);
+        assert_eq!(auto_hide.content_top(32), 32);
+
+        // Stays a while after the pointer left, then slides out
+        assert!(!auto_hide.update(false, true, at(100)));
+        assert!(!auto_hide.is_sliding());
+        assert!(auto_hide.update(false, true, at(520)));
+        assert!(auto_hide.is_sliding());
+        let top = auto_hide.content_top(32);
+        assert!(top > COLLAPSED_HEIGHT && top < 32);
+        for ms in (536..=700).step_by(16) {
+            auto_hide.update(false, true, at(ms));
+        }
+        assert!(!auto_hide.is_sliding());
+        assert_eq!(auto_hide.content_top(32), COLLAPSED_HEIGHT);
+
+        // Back right away without animations
+        assert!(auto_hide.update(true, false, at(800)));
+        assert!(!auto_hide.is_sliding());
+        assert_eq!(auto_hide.content_top(44), 44);
+    }
+}
@@ -0,0 +1,143 @@
+//! Stand-in for Neovide in the integration tests (tests/lifecycle.rs), built
+//! only with the `test-harness` feature.
+//!
+//! Shows a borderless window with Neovide's class name and title, sized by
+//! `--size WxH` like Neovide, and exits when the window gets WM_CLOSE. Other
+//! arguments (--frame, --no-fork, files, nvim arguments after "--") are ignored.
+//! With FAKE_NEOVIDE_LOG set, it appends "<pid> <event> ..." lines to that file
+//! so a test can tell what the wrapper asked of it:
+//!
+//! - `started <working directory>` when the window is shown
+//! - `close` when the window gets WM_CLOSE
+
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+#![cfg_attr(not(target_os = "windows"), allow(dead_code))]
+
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+
+#[cfg(target_os = "windows")]
+use anyhow::Result;
+#[cfg(target_os = "windows")]
+use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+#[cfg(target_os = "windows")]
+use windows::Win32::Graphics::Gdi::{BLACK_BRUSH, GetStockObject, HBRUSH};
+#[cfg(target_os = "windows")]
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+#[cfg(target_os = "windows")]
+use windows::Win32::UI::WindowsAndMessaging::{
+    CW_USEDEFAULT, CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, GetMessageW,
+    IDC_ARROW, LoadCursorW, MSG, PostQuitMessage, RegisterClassW, SW_SHOW, ShowWindow,
+    TranslateMessage, WM_CLOSE, WM_DESTROY, WNDCLASSW, WS_EX_APPWINDOW, WS_POPUP,
+};
+#[cfg(target_os = "windows")]
+use windows::core::w;
+
+/// Environment variable naming the event log file
+const LOG_ENV_VAR: &str = "FAKE_NEOVIDE_LOG";
+
+/// Window size when --size is missing or malformed
+const DEFAULT_SIZE: (i32, i32) = (800, 600);
+
+#[cfg(not(target_os = "windows"))]
+fn main() {
+    eprintln!("fake-neovide only runs on Windows");
+    std::process::exit(1);
+}
+
+#[cfg(target_os = "windows")]
+fn main() -> Result<()> {
+    let args: Vec<String> = env::args().collect();
+    let (width, height) = args
+        .iter()
+        .position(|arg| arg == "--size")
+        .and_then(|i| args.get(i + 1))
+        .and_then(|size| parse_size(size))
+        .unwrap_or(DEFAULT_SIZE);
+
+    unsafe {
+        let instance = GetModuleHandleW(None)?;
+        let class = WNDCLASSW {
+            lpfnWndProc: Some(window_proc),
+            hInstance: instance.into(),
+            hCursor: LoadCursorW(None, IDC_ARROW)?,
+            hbrBackground: HBRUSH(GetStockObject(BLACK_BRUSH).0),
+            // Neovide's window class (see config::DEFAULT_NEOVIDE_WINDOW_CLASS)
+            lpszClassName: w!("Window Class"),
+            ..Default::default()
+        };
+        if RegisterClassW(&class) == 0 {
+            return Err(windows::core::Error::from_win32().into());
+        }
+        let hwnd = CreateWindowExW(
+            WS_EX_APPWINDOW,
+            w!("Window Class"),
+            w!("Neovide"),
+            WS_POPUP,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            width,
+            height,
+            None,
+            None,
+            instance,
+            None,
+        )?;
+        let _ = ShowWindow(hwnd, SW_SHOW);
+
+        let cwd = env::current_dir().unwrap_or_default();
+        log_event(&format!("started {}", cwd.display()));
+
+        let mut msg = MSG::default();
+        while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+unsafe extern "system" fn window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match msg {
+            WM_CLOSE => {
+                log_event("close");
+                DestroyWindow(hwnd).ok();
+                LRESULT(0)
+            }
+            WM_DESTROY => {
+                PostQuitMessage(0);
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+        }
+    }
+}
+
+/// Append an event line to the log file, if there is one
+fn log_event(event: &str) {
+    let Some(path) = env::var_os(LOG_ENV_VAR).filter(|path| !path.is_empty()) else {
+        return;
+    };
+    // Each line is written at once, so tabs logging together don't interleave
+    let line = format!("{} {}\n", std::process::id(), event);
+    match OpenOptions::new().create(true).append(true).open(&path) {
+        Ok(mut file) => {
+            let _ = file.write_all(line.as_bytes());
+        }
+        Err(e) => eprintln!("fake-neovide: Failed to open {:?}: {}", path, e),
+    }
+}
+
+/// Width and height of a "WxH" size
+fn parse_size(size: &str) -> Option<(i32, i32)> {
+    let (width, height) = size.split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
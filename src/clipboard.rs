@@ -0,0 +1,49 @@
+//! Putting text on the Windows clipboard.
+
+#![cfg(target_os = "windows")]
+
+use windows::Win32::Foundation::{GlobalFree, HANDLE, HWND};
+use windows::Win32::System::DataExchange::{
+    CloseClipboard, EmptyClipboard, OpenClipboard, SetClipboardData,
+};
+use windows::Win32::System::Memory::{GMEM_MOVEABLE, GlobalAlloc, GlobalLock, GlobalUnlock};
+use windows::Win32::System::Ole::CF_UNICODETEXT;
+
+/// Replace the clipboard's contents with `text`, owned by `owner`.
+/// Returns false if the clipboard could not be opened (another application
+/// holds it) or the text could not be stored.
+pub fn set_text(owner: HWND, text: &str) -> bool {
+    let wide: Vec<u16> = text.encode_utf16().chain(std::iter::once(0)).collect();
+    unsafe {
+        if OpenClipboard(owner).is_err() {
+            return false;
+        }
+        let stored = EmptyClipboard().is_ok() && store_text(&wide);
+        let _ = CloseClipboard();
+        stored
+    }
+}
+
+/// Copy null-terminated UTF-16 `wide` into global memory and hand it to the open
+/// clipboard, which owns the memory from then on
+unsafe fn store_text(wide: &[u16]) -> bool {
+    unsafe {
+        let Ok(memory) = GlobalAlloc(GMEM_MOVEABLE, std::mem::size_of_val(wide)) else {
+            return false;
+        };
+        let buffer = GlobalLock(memory) as *mut u16;
+        if buffer.is_null() {
+            let _ = GlobalFree(memory);
+            return false;
+        }
+        std::ptr::copy_nonoverlapping(wide.as_ptr(), buffer, wide.len());
+        // Fails (with no error) once the memory is unlocked, which is expected
+        let _ = GlobalUnlock(memory);
+
+        if SetClipboardData(CF_UNICODETEXT.0 as u32, HANDLE(memory.0)).is_err() {
+            let _ = GlobalFree(memory);
+            return false;
+        }
+        true
+    }
+}
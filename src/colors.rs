@@ -0,0 +1,206 @@
+//! Color scheme import from terminal theme files.
+//!
+//! `color_scheme` in the config points at a file exported by another terminal and
+//! its colors are mapped onto the wrapper's theme keys, so the tab bar can match the
+//! terminal without copying hex values by hand. Supported formats:
+//!
+//! - Windows Terminal `settings.json` (a scheme from `"schemes"` chosen by name) or a
+//!   single exported scheme object
+//! - iTerm2 `.itermcolors` property lists
+//! - base16/base24 YAML palettes (`base00` is the background)
+
+use anyhow::{Context, Result, anyhow, bail};
+use std::fs;
+use std::path::Path;
+
+use crate::config::{parse_hex_color, strip_jsonc_comments};
+
+/// Colors taken from an imported scheme (0x00RRGGBB)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImportedColors {
+    /// Becomes `background_color`
+    pub background: u32,
+}
+
+/// Read `path` and import the scheme called `name` (the first scheme if None).
+/// The format is detected from the contents.
+pub fn import_color_scheme(path: &Path, name: Option<&str>) -> Result<ImportedColors> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read color scheme file {}", path.display()))?;
+    parse_color_scheme(&contents, name)
+}
+
+/// Import a scheme from the contents of a theme file
+pub fn parse_color_scheme(contents: &str, name: Option<&str>) -> Result<ImportedColors> {
+    let trimmed = contents.trim_start();
+    if trimmed.starts_with('<') {
+        parse_itermcolors(contents)
+    } else if trimmed.starts_with('{') || trimmed.starts_with("//") {
+        parse_windows_terminal(contents, name)
+    } else {
+        parse_base16(contents)
+    }
+}
+
+/// Windows Terminal settings.json or a single scheme object
+fn parse_windows_terminal(contents: &str, name: Option<&str>) -> Result<ImportedColors> {
+    let value: serde_json::Value = serde_json::from_str(&strip_jsonc_comments(contents))
+        .context("Color scheme file is not valid JSON")?;
+
+    let scheme = match value.get("schemes").and_then(|s| s.as_array()) {
+        Some(schemes) => match name {
+            Some(name) => schemes
+                .iter()
+                .find(|s| {
+                    s.get("name")
+                        .and_then(|n| n.as_str())
+                        .is_some_and(|n| n.eq_ignore_ascii_case(name))
+                })
+                .ok_or_else(|| anyhow!("No color scheme named '{}'", name))?,
+            None => schemes
+                .first()
+                .ok_or_else(|| anyhow!("The file has no color schemes"))?,
+        },
+        None => &value,
+    };
+
+    let background = scheme
+        .get("background")
+        .and_then(|b| b.as_str())
+        .ok_or_else(|| anyhow!("Color scheme has no background color"))?;
+    Ok(ImportedColors {
+        background: parse_hex_color(background)
+            .ok_or_else(|| anyhow!("Invalid background color '{}'", background))?,
+    })
+}
+
+/// iTerm2 .itermcolors property list (color components are 0.0-1.0 reals)
+fn parse_itermcolors(contents: &str) -> Result<ImportedColors> {
+    let start = contents
+        .find("<key>Background Color</key>")
+        .ok_or_else(|| anyhow!("iTerm color file has no Background Color"))?;
+    let rest = &contents[start..];
+    let dict_end = rest
+        .find("</dict>")
+        .ok_or_else(|| anyhow!("Malformed Background Color entry"))?;
+    let dict = &rest[..dict_end];
+
+    let mut rgb = 0u32;
+    for component in ["Red", "Green", "Blue"] {
+        let value = plist_real(dict, &format!("{} Component", component))
+            .ok_or_else(|| anyhow!("Background Color has no {} component", component))?;
+        let channel = (value.clamp(0.0, 1.0) * 255.0).round() as u32;
+        rgb = (rgb << 8) | channel;
+    }
+    Ok(ImportedColors { background: rgb })
+}
+
+/// The `<real>` value following `<key>key</key>` in a plist dict
+fn plist_real(dict: &str, key: &str) -> Option<f64> {
+    let after_key = &dict[dict.find(&format!("<key>{}</key>", key))?..];
+    let value = &after_key[after_key.find("<real>")? + "<real>".len()..];
+    value[..value.find("</real>")?].trim().parse().ok()
+}
+
+/// base16/base24 YAML: `base00: "1a1b26"`, either top level or under `palette:`
+fn parse_base16(contents: &str) -> Result<ImportedColors> {
+    for line in contents.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        if key.trim() != "base00" {
+            continue;
+        }
+        // A YAML comment starts with " #"; a "#" inside the quotes is part of the color
+        let value = value.trim();
+        let value = value.find(" #").map_or(value, |i| &value[..i]);
+        let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+        return Ok(ImportedColors {
+            background: parse_hex_color(value)
+                .ok_or_else(|| anyhow!("Invalid base00 color '{}'", value))?,
+        });
+    }
+    bail!("Color scheme file is not a Windows Terminal, iTerm or base16 scheme")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows_terminal_scheme_by_name() {
+        let settings = r##"{
+            // Windows Terminal settings
+            "profiles": {},
+            "schemes": [
+                { "name": "Campbell", "background": "#0C0C0C", "foreground": "#CCCCCC" },
+                { "name": "One Half Dark", "background": "#282C34", "foreground": "#DCDFE4" }
+            ]
+        }"##;
+        assert_eq!(
+            parse_color_scheme(settings, Some("one half dark"))
+                .unwrap()
+                .background,
+            0x282c34
+        );
+        assert_eq!(
+            parse_color_scheme(settings, None).unwrap().background,
+            0x0c0c0c
+        );
+        assert!(parse_color_scheme(settings, Some("Missing")).is_err());
+
+        let single = r##"{ "name": "Tokyo Night", "background": "#1a1b26" }"##;
+        assert_eq!(
+            parse_color_scheme(single, None).unwrap().background,
+            0x1a1b26
+        );
+    }
+
+    #[test]
+    fn test_itermcolors() {
+        let plist = r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0">
+<dict>
+    <key>Ansi 0 Color</key>
+    <dict>
+        <key>Blue Component</key>
+        <real>1</real>
+        <key>Green Component</key>
+        <real>1</real>
+        <key>Red Component</key>
+        <real>1</real>
+    </dict>
+    <key>Background Color</key>
+    <dict>
+        <key>Blue Component</key>
+        <real>0.14901961386203766</real>
+        <key>Green Component</key>
+        <real>0.10588235408067703</real>
+        <key>Red Component</key>
+        <real>0.10196078568696976</real>
+    </dict>
+</dict>
+</plist>"#;
+        assert_eq!(
+            parse_color_scheme(plist, None).unwrap().background,
+            0x1a1b26
+        );
+    }
+
+    #[test]
+    fn test_base16_yaml() {
+        let legacy = "scheme: \"Tokyo Night\"\nauthor: \"someone\"\nbase00: \"1a1b26\" # background\nbase01: \"16161e\"\n";
+        assert_eq!(
+            parse_color_scheme(legacy, None).unwrap().background,
+            0x1a1b26
+        );
+
+        let tinted = "system: \"base16\"\nname: \"Nord\"\npalette:\n  base00: \"#2E3440\"\n  base01: \"#3B4252\"\n";
+        assert_eq!(
+            parse_color_scheme(tinted, None).unwrap().background,
+            0x2e3440
+        );
+
+        assert!(parse_color_scheme("just some text", None).is_err());
+    }
+}
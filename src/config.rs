@@ -4,21 +4,105 @@
 //! or `~/.config/neovide-tabs/config.json` (fallback).
 //! Both files support JSONC format (JSON with // comments).
 //! Falls back to defaults if the file is missing or invalid.
+//! In portable mode the config and data directories are both `config\` next to the
+//! executable instead; `--config`/`NEOVIDE_TABS_CONFIG` and `--data-dir` override
+//! the config file and the data directory.
 
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::colors::{self, ImportedColors};
 
 /// Default background color (Tokyo Night dark theme)
 pub const DEFAULT_BACKGROUND_COLOR: u32 = 0x1a1b26;
 
+/// Default inset between the window edges and the Neovide content area (pixels)
+pub const DEFAULT_CONTENT_INSET: i32 = 12;
+
+/// Largest accepted content inset (pixels)
+pub const MAX_CONTENT_INSET: i32 = 64;
+
+/// Default font of the tab bar and popups
+pub const DEFAULT_FONT_FAMILY: &str = "Segoe UI";
+
+/// Default tab label font size (pixels); popups use slightly larger text
+pub const DEFAULT_FONT_SIZE: i32 = 11;
+
+/// Font sizes that still fit the titlebar and popup rows (pixels)
+pub const FONT_SIZE_RANGE: std::ops::RangeInclusive<i32> = 8..=16;
+
+/// Default interval for polling the Neovide processes, which bounds how long it
+/// takes to notice one exited (ms)
+pub const DEFAULT_PROCESS_POLL_INTERVAL_MS: u32 = 250;
+
+/// Accepted process polling intervals (ms)
+pub const PROCESS_POLL_INTERVAL_RANGE: std::ops::RangeInclusive<u32> = 20..=2000;
+
+/// Default delay before bringing Neovide to the foreground when the wrapper is
+/// activated, so a drag of the wrapper can start first (ms)
+pub const DEFAULT_FOREGROUND_DELAY_MS: u32 = 50;
+
+/// Default delay before following an external move or resize of the wrapper (ms)
+pub const DEFAULT_POSITION_UPDATE_DELAY_MS: u32 = 100;
+
+/// Accepted foreground and position update delays (ms)
+pub const TIMER_DELAY_RANGE: std::ops::RangeInclusive<u32> = 0..=1000;
+
+/// File in a project directory whose settings override the profile's for tabs
+/// opened in that directory
+pub const DIRECTORY_CONFIG_FILE: &str = ".neovide-tabs.jsonc";
+
+/// Default memory (MB) of all tabs together that shows the high memory banner
+pub const DEFAULT_MEMORY_WARNING_MB: u64 = 4096;
+
+/// Default number of Neovide processes that may be starting at the same time
+pub const DEFAULT_MAX_CONCURRENT_SPAWNS: usize = 2;
+
+/// Default interval between automatic snapshots of the active session (minutes)
+pub const DEFAULT_SESSION_AUTOSAVE_MINUTES: u64 = 5;
+
+/// Default command of a tab's "Open in Terminal" (Windows Terminal in the
+/// directory the command runs in, the tab's working directory)
+pub const DEFAULT_TERMINAL_COMMAND: &str = "wt -d .";
+
+/// Window class of Neovide windows (winit's default class name)
+pub const DEFAULT_NEOVIDE_WINDOW_CLASS: &str = "Window Class";
+
+/// Title text of Neovide windows, for builds with another window class
+pub const DEFAULT_NEOVIDE_WINDOW_TITLE: &str = "Neovide";
+
+/// Default window class name of the wrapper window
+pub const DEFAULT_WINDOW_CLASS: &str = "NeovideTabsWindow";
+
+/// Default title of the wrapper window
+pub const DEFAULT_WINDOW_TITLE: &str = "neovide-tabs";
+
+/// Longest window class name Windows registers (UTF-16 units)
+const MAX_WINDOW_CLASS_LENGTH: usize = 256;
+
 /// Default tab icon filename (Neovide icon for profiles)
 pub const DEFAULT_ICON: &str = "neovide.png";
 
+/// Profile `icon` value that uses the logo of the project in the tab's working
+/// directory
+pub const AUTO_ICON: &str = "auto";
+
 /// Application window icon filename
 pub const APP_ICON: &str = "neovide-tabs.png";
 
+/// File next to the executable that turns on portable mode
+pub const PORTABLE_MARKER: &str = "portable";
+
+/// Directory next to the executable holding config and data in portable mode
+const PORTABLE_DIR: &str = "config";
+
+/// Environment variable naming the config file to use (like `--config`)
+pub const CONFIG_ENV_VAR: &str = "NEOVIDE_TABS_CONFIG";
+
 /// Default profile name
 pub const DEFAULT_PROFILE_NAME: &str = "Default";
 
@@ -36,6 +120,160 @@ const DEFAULT_CONFIG_TEMPLATE: &str = r##"// neovide-tabs configuration file
     // This color is used for the title bar and to fill exposed areas during resize
     // "background_color": "#1a1b26",
 
+    // Take the colors from a terminal color scheme: a Windows Terminal settings.json
+    // (pick the scheme by name) or exported scheme, an iTerm2 .itermcolors file or a
+    // base16 YAML palette. Colors set explicitly above win over the scheme
+    // "color_scheme": { "path": "~/AppData/Local/Packages/Microsoft.WindowsTerminal_8wekyb3d8bbwe/LocalState/settings.json", "name": "One Half Dark" },
+
+    // Font of the tab bar and popups, and the tab label size in pixels (8-16).
+    // Titles with characters the font lacks (e.g. Japanese file names) fall back to
+    // a font that has them
+    // "font_family": "Segoe UI",
+    // "font_size": 11,
+
+    // Gap in pixels between the window edges and the Neovide window (0-64)
+    // Set to 0 for a gapless layout where Neovide fills the window completely
+    // "content_inset": 12,
+
+    // Hide the Neovide windows from Alt-Tab and the taskbar so only neovide-tabs shows
+    // "hide_neovide_from_alt_tab": true,
+
+    // Suspend Neovide in tabs that have been in the background for this many minutes
+    // to save CPU/GPU; a tab resumes as soon as it is selected. 0 or unset = never
+    // "suspend_background_tabs_after_minutes": 10,
+
+    // When the Neovide processes of all tabs together use this much memory (MB), a
+    // banner in the tab bar offers to hibernate (suspend) the least recently used
+    // background tabs. 0 = never
+    // "memory_warning_mb": 4096,
+
+    // How many Neovide processes may start at the same time when several tabs are
+    // opened at once; the rest wait in a queue and launch in tab order
+    // "max_concurrent_spawns": 2,
+
+    // Most tabs open at once; opening another selects a tab of its profile instead
+    // of launching yet another Neovide. 0 or unset = no limit
+    // "max_tabs": 10,
+
+    // While a named session is active (--session <name>), save it every this many
+    // minutes when its tabs changed, in addition to saving on exit. 0 = only on exit
+    // "session_autosave_minutes": 5,
+
+    // Tab title formats tried in order while a tab's own title format comes out empty,
+    // e.g. while Neovide's window title is still "Neovide" during startup.
+    // "%w_basename" is the last component of the working directory; the profile
+    // name is used if every entry is empty
+    // "title_fallback": ["%w_basename", "%p"],
+
+    // When opening a new tab for a profile and directory that already has a tab,
+    // select the existing tab instead of starting a duplicate
+    // "reuse_existing_tab": false,
+
+    // What double-clicking a tab does: "rename", "duplicate", "pin" (pin/unpin) or "close"
+    // "tab_double_click": "rename",
+
+    // Mouse buttons on the title bar: "[Ctrl+][Shift+][Alt+]<Middle|Right|X1|X2>", with
+    // "@tab" or "@caption" to only bind it over a tab or the empty title bar. Actions:
+    // "next_tab", "prev_tab", "new_tab", "close_tab", "tab_menu", "profile_menu",
+    // "window_menu", "minimize", "maximize" or "none" (Windows' own behavior).
+    // Tab actions on the caption apply to the selected tab. "Right@tab" opens the
+    // tab menu unless bound otherwise.
+    // "mouse_bindings": {
+    //     "X1": "prev_tab",
+    //     "X2": "next_tab",
+    //     "Middle@tab": "close_tab",
+    //     "Right@caption": "profile_menu"
+    // },
+
+    // Tab layout: "normal", "two_line" for a taller tab bar showing the abbreviated
+    // working directory below each tab's title, or "compact" for icon-only tabs
+    // (titles in the tooltip) that fit many more tabs before the overflow button
+    // "tab_style": "normal",
+
+    // Side of the minimize, maximize and close buttons: "right" or "left" (in macOS
+    // order, before the tabs). traffic_lights draws them as macOS-style colored dots
+    // "window_controls": "right",
+    // "traffic_lights": false,
+
+    // Window buttons to show, and hiding the whole title bar for kiosk-like setups:
+    // Neovide gets the whole window, and tabs are only reachable through hotkeys
+    // and `neovide-tabs open`
+    // "show_minimize": true,
+    // "show_maximize": true,
+    // "show_close": true,
+    // "hide_titlebar": false,
+
+    // Collapse the title bar to a thin strip while the pointer is away from the top
+    // of the window; hovering the strip or holding Ctrl+Alt slides it back down
+    // "auto_hide_titlebar": false,
+
+    // How the window starts: "normal", "maximized" or "minimized"
+    // (--maximized / --minimized override this for one run)
+    // "start_state": "normal",
+
+    // Display the window opens on: its number, counting left to right (1 = the
+    // leftmost), or its name, e.g. "DELL U2720Q" or "DISPLAY2". Profiles can set
+    // their own "monitor". Unset: where Windows places it
+    // "monitor": 2,
+
+    // Reopen the window where it was, remembered for each set of connected
+    // displays: docking or undocking a laptop puts it back in its place there
+    // "remember_window_layout": true,
+
+    // Window class name and title of the wrapper window, for AutoHotkey and tiling
+    // window manager rules (Komorebi, GlazeWM). The title gets " - <session>" while
+    // a named session is active. The class is only read at startup
+    // "window_class": "NeovideTabsWindow",
+    // "window_title": "neovide-tabs",
+
+    // Show each tab's hotkey number on it: "off", "hold" (while the modifiers of
+    // the tab hotkeys, Ctrl+Shift by default, are held) or "always"
+    // "tab_numbers": "off",
+
+    // Switch to the adjacent tab with a sideways two-finger touchpad swipe over
+    // the title bar, or a touch swipe across a tab: "off", "low", "medium" or "high"
+    // "swipe_sensitivity": "medium",
+
+    // Pulse a background tab when its Neovim reports an error through vim.notify or
+    // runs :NeovideTabsAttention (through the private pipe nvim listens on)
+    // "tab_attention": true,
+    // Also play the system notification sound
+    // "tab_attention_sound": false,
+
+    // When the last tab closes, give the focus back to the application that was
+    // active before neovide-tabs instead of leaving it on the desktop
+    // "restore_foreground_on_exit": true,
+
+    // Command run by "Open in Terminal" in a tab's context menu, in the tab's
+    // working directory (through cmd /C, without a console window: start console
+    // programs with start, e.g. "start pwsh")
+    // "terminal_command": "wt -d .",
+
+    // Tabs of profiles without an icon show the icon of the project in their working
+    // directory: Rust (Cargo.toml), Node (package.json), Go (go.mod) or git (.git)
+    // "project_icons": true,
+
+    // Mark tabs whose working directory is a git repository with uncommitted changes
+    // (amber dot) or with commits to push or pull (blue dot); checked every 30
+    // seconds and when a tab is selected
+    // "git_status": false,
+
+    // Hovering a background tab for a second shows a thumbnail of its Neovide window
+    // "tab_previews": true,
+
+    // Look for a newer release on GitHub at startup and show "Update available" in
+    // the title bar if there is one (--skip-update-check skips it for one run)
+    // "check_updates": false,
+
+    // Turn off the wrapper's animations (startup spinner, attention pulse) and show
+    // the selected Neovide without the short activation delay. By default this
+    // follows Windows' "Animation effects" setting
+    // "reduce_motion": false,
+
+    // Instead of opening the first profile at startup, ask which profile to open.
+    // The last choice is preselected
+    // "prompt_profile_on_start": false,
+
     // Hotkey configuration
     // "hotkeys": {
     //     // Tab switching hotkeys: maps key combination to tab number (1-based)
@@ -52,7 +290,98 @@ const DEFAULT_CONFIG_TEMPLATE: &str = r##"// neovide-tabs configuration file
     //         "Ctrl+Shift+8": 8,
     //         "Ctrl+Shift+9": 9,
     //         "Ctrl+Shift+0": 10
-    //     }
+    //     },
+    //     // Open the folder picker and start a new tab in the chosen directory
+    //     // Not bound by default
+    //     "new_tab_in_folder": "Ctrl+Shift+O",
+    //     // Toggle the task manager popup (memory/CPU usage of every tab)
+    //     // Not bound by default
+    //     "task_manager": "Ctrl+Shift+U",
+    //     // Gracefully close every tab but the selected one / the tabs to its right
+    //     // Not bound by default
+    //     "close_other_tabs": "Ctrl+Shift+Alt+W",
+    //     "close_tabs_to_right": "Ctrl+Shift+Alt+R",
+    //     // Go back / forward through the tabs selected before, like browser history
+    //     // Not bound by default
+    //     "tab_back": "Alt+Left",
+    //     "tab_forward": "Alt+Right",
+    //     // Move the keyboard focus to the tab bar (Left/Right move between tabs and
+    //     // buttons, Enter activates, Delete closes, Escape returns to Neovide)
+    //     // Not bound by default
+    //     "focus_tab_bar": "Ctrl+Shift+F6",
+    //     // Cycle to the next / previous tab. While the modifiers stay held, a
+    //     // switcher lists the tabs; the highlighted one is selected on release
+    //     // Not bound by default
+    //     "next_tab": "Ctrl+Alt+Right",
+    //     "prev_tab": "Ctrl+Alt+Left",
+    //     // Toggle the debug HUD (paint and timer timings, tab states) over the
+    //     // title bar. Not bound by default
+    //     "debug_hud": "Ctrl+Shift+Alt+D"
+    // },
+
+    // Indicators at the right end of the tab bar, before the window buttons
+    // "widgets": {
+    //     // Current time
+    //     "clock": false,
+    //     // Name of the active session
+    //     "session": true,
+    //     // "Update available" badge, while there is an update (see check_updates)
+    //     "update": true,
+    //     // Gear button with a menu to edit or reload this file, open the data
+    //     // folder (icons, sessions) and show the version
+    //     "settings": true
+    // },
+
+    // FancyZones / window snapping compatibility
+    // "fancyzones": {
+    //     // Resync Neovide as soon as the window is moved or resized by a snapping tool
+    //     "enabled": true,
+    //     // Keep Neovide windows pinned to the wrapper so zones only target the wrapper
+    //     "wrapper_only": true
+    // },
+
+    // Tiling window manager compatibility: "komorebi", "glazewm", "other" or "off".
+    // The wrapper follows moves by the window manager right away (no delays) and
+    // puts Neovide windows it moves back. The settings menu copies rules that make
+    // the window manager ignore the Neovide windows; with "komorebi" they are
+    // added to the running Komorebi at startup
+    // "tiling_wm": "off",
+
+    // Timer tuning for slow or fast machines (milliseconds)
+    // "advanced": {
+    //     // How often the Neovide processes are checked, e.g. whether one exited
+    //     // (20-2000)
+    //     "process_poll_interval_ms": 250,
+    //     // Delay before Neovide is brought to the front when the wrapper is
+    //     // activated; raise it if dragging the window sometimes fails (0-1000)
+    //     "foreground_delay_ms": 50,
+    //     // Delay before Neovide follows a move or resize by another tool (0-1000)
+    //     "position_update_delay_ms": 100
+    // },
+
+    // How the Neovide window of a tab is recognized among the windows of its
+    // process, for custom Neovide builds: a window with one of these class names,
+    // or a visible one whose title contains one of these texts
+    // "neovide_window": {
+    //     "classes": ["Window Class"],
+    //     "titles": ["Neovide"]
+    // },
+
+    // Shell commands run through "cmd /C" when a tab's Neovide window appears,
+    // when a tab closes and when a tab is selected. They run in the tab's working
+    // directory with NEOVIDE_TABS_EVENT, NEOVIDE_TABS_TAB_ID, NEOVIDE_TABS_PROFILE,
+    // NEOVIDE_TABS_CWD and NEOVIDE_TABS_PID set
+    // "hooks": {
+    //     "on_tab_open": "if exist package.json start /b npm run dev",
+    //     "on_tab_close": "",
+    //     "on_tab_select": ""
+    // },
+
+    // Environment variables for every Neovide process. ${VAR} is replaced with
+    // the variable from the environment neovide-tabs was started in
+    // "env": {
+    //     "PATH": "C:\\tools\\bin;${PATH}",
+    //     "RUST_LOG": "info"
     // },
 
     // Profile definitions for tabs
@@ -70,10 +399,13 @@ const DEFAULT_CONFIG_TEMPLATE: &str = r##"// neovide-tabs configuration file
         //     // Profile name (required) - displayed in the tab
         //     "name": "Work",
         //     // Icon file path (optional) - full path to a PNG file
-        //     // Defaults to neovide.png in the data directory
+        //     // Defaults to neovide.png in the data directory. "auto" uses the
+        //     // project's .neovide-icon.png, logo.png or .github/logo.svg if the
+        //     // working directory has one
         //     "icon": "C:/path/to/icon.png",
         //     // Working directory (optional) - where Neovide starts
-        //     // Supports ~ for home directory. Defaults to home directory
+        //     // Supports ~ for home directory and environment variables such as
+        //     // %USERPROFILE% or ${HOME}. Defaults to home directory
         //     "working_directory": "~/projects/work",
         //     // Global hotkey (optional) - opens or activates a tab with this profile
         //     // Format: Modifier+Key (e.g., Ctrl+Shift+F2, Alt+Shift+W)
@@ -82,12 +414,39 @@ const DEFAULT_CONFIG_TEMPLATE: &str = r##"// neovide-tabs configuration file
         //     // Supported tokens:
         //     //   %p - Profile name
         //     //   %w - Working directory (uses ~/xxx for paths under home)
+        //     //   %w_basename - Last component of the working directory
         //     //   %t - Neovide window title (current file/buffer)
         //     //   %f - Relative file path (path from title relative to working_directory,
         //     //        or window title as-is if not under working_directory)
         //     // Defaults to "%t" (Neovide window title)
         //     // Examples: "%t", "%p: %w", "%p - %t", "%f"
-        //     "title": "%t"
+        //     "title": "%t",
+        //     // Run Neovide as administrator (optional) - for editing protected files.
+        //     // Each tab asks for confirmation (UAC prompt). Defaults to false
+        //     "elevated": false,
+        //     // Environment variables for this profile's Neovide, overriding the
+        //     // top-level "env" (optional)
+        //     "env": { "HTTPS_PROXY": "http://proxy.example.com:8080" },
+        //     // Files a new tab of this profile opens (optional) - relative to the
+        //     // working directory; not used when the tab is opened for other files
+        //     "open": ["TODO.md"],
+        //     // Command nvim runs when a new tab of this profile starts (optional),
+        //     // like nvim's +cmd argument
+        //     "nvim_cmd": "Telescope find_files",
+        //     // Most tabs of this profile open at once (optional); opening
+        //     // another selects one of them instead. Defaults to no limit
+        //     "max_instances": 1,
+        //     // Display the window opens on when this profile opens the first tab
+        //     // (optional) - overrides the top-level "monitor"
+        //     "monitor": "DELL U2720Q"
+        // },
+        // {
+        //     // Profile per directory matching "discover" (optional), named after
+        //     // the directory and listed in a "Projects" submenu of the dropdown.
+        //     // The other settings apply to each of them (except hotkey)
+        //     "name": "Projects",
+        //     "discover": "~/projects/*",
+        //     "title": "%w_basename: %t"
         // },
         // {
         //     // Minimal profile example - only name is required
@@ -100,6 +459,12 @@ const DEFAULT_CONFIG_TEMPLATE: &str = r##"// neovide-tabs configuration file
 /// Default title format for profiles (Neovide window title)
 pub const DEFAULT_TITLE_FORMAT: &str = "%t";
 
+/// Default title formats tried when a tab's title comes out empty
+pub const DEFAULT_TITLE_FALLBACK: [&str; 2] = ["%w_basename", "%p"];
+
+/// Window title Neovide shows before it has opened a buffer
+const NEOVIDE_PLACEHOLDER_TITLE: &str = "Neovide";
+
 /// Raw profile as read from JSON file
 #[derive(Debug, Deserialize, Clone)]
 struct ProfileFile {
@@ -115,6 +480,42 @@ struct ProfileFile {
     /// Supports tokens: %p (profile name), %w (working directory), %t (Neovide window title),
     /// %f (relative file path from window title)
     title: Option<String>,
+    /// Run Neovide as administrator (optional, defaults to false)
+    elevated: Option<bool>,
+    /// Environment variables for Neovide, overriding the top-level `env` (optional)
+    env: Option<BTreeMap<String, String>>,
+    /// Files a new tab opens, relative to the working directory (optional)
+    open: Option<Vec<String>>,
+    /// Command nvim runs at startup, e.g. "Telescope find_files" (optional)
+    nvim_cmd: Option<String>,
+    /// Glob of directories (e.g. "~/projects/*") that each get a profile like
+    /// this one, named after the directory (optional)
+    discover: Option<String>,
+    /// Most tabs of this profile open at once (optional, 0 = no limit)
+    max_instances: Option<usize>,
+    /// Display the wrapper opens on with this profile's tab (optional)
+    monitor: Option<MonitorFile>,
+}
+
+/// Raw display choice: its number or its name
+#[derive(Debug, Deserialize, Clone)]
+#[serde(untagged)]
+enum MonitorFile {
+    Number(usize),
+    Name(String),
+}
+
+/// Raw per-directory overrides as read from `.neovide-tabs.jsonc`. Settings that
+/// run code (nvim_cmd, env, elevated) stay with the global config, since the file
+/// comes with a repository that may not be trusted.
+#[derive(Debug, Deserialize, Default)]
+struct DirectoryConfigFile {
+    /// Tab title format
+    title: Option<String>,
+    /// Icon, relative to the directory
+    icon: Option<String>,
+    /// Files a new tab opens, relative to the directory and inside it
+    open: Option<Vec<String>>,
 }
 
 /// Raw hotkey configuration as read from JSON file
@@ -122,6 +523,88 @@ struct ProfileFile {
 struct HotkeyConfigFile {
     /// Tab hotkey mappings: hotkey string -> tab number (1-based)
     tab: Option<HashMap<String, u32>>,
+    /// Hotkey for "New tab in folder..." (optional, unbound by default)
+    new_tab_in_folder: Option<String>,
+    /// Hotkey that toggles the task manager popup (optional, unbound by default)
+    task_manager: Option<String>,
+    /// Hotkey that closes all tabs but the selected one (optional, unbound by default)
+    close_other_tabs: Option<String>,
+    /// Hotkey that closes the tabs right of the selected one (optional, unbound by default)
+    close_tabs_to_right: Option<String>,
+    /// Hotkey that goes back in the tab selection history (optional, unbound by default)
+    tab_back: Option<String>,
+    /// Hotkey that goes forward in the tab selection history (optional, unbound by default)
+    tab_forward: Option<String>,
+    /// Hotkey that moves the keyboard focus to the tab bar (optional, unbound by default)
+    focus_tab_bar: Option<String>,
+    /// Hotkey that cycles to the next tab (optional, unbound by default)
+    next_tab: Option<String>,
+    /// Hotkey that cycles to the previous tab (optional, unbound by default)
+    prev_tab: Option<String>,
+    /// Hotkey that toggles the debug HUD (optional, unbound by default)
+    debug_hud: Option<String>,
+}
+
+/// Raw timer tuning as read from JSON file
+#[derive(Debug, Deserialize, Default, Clone)]
+struct AdvancedConfigFile {
+    /// Interval for polling the Neovide processes (ms)
+    process_poll_interval_ms: Option<u32>,
+    /// Delay before bringing Neovide to the foreground on activation (ms)
+    foreground_delay_ms: Option<u32>,
+    /// Delay before following an external move or resize (ms)
+    position_update_delay_ms: Option<u32>,
+}
+
+/// Raw FancyZones / window snapping compatibility settings as read from JSON file
+#[derive(Debug, Deserialize, Default, Clone)]
+struct FancyZonesConfigFile {
+    /// Resync Neovide immediately when the wrapper is moved or resized externally
+    enabled: Option<bool>,
+    /// Keep managed Neovide windows pinned to the wrapper so only the wrapper is snapped
+    wrapper_only: Option<bool>,
+}
+
+/// Raw tab bar widget toggles as read from JSON file
+#[derive(Debug, Deserialize, Default, Clone)]
+struct WidgetsConfigFile {
+    /// Show the current time
+    clock: Option<bool>,
+    /// Show the active session's name
+    session: Option<bool>,
+    /// Show the "Update available" badge
+    update: Option<bool>,
+    /// Show the settings button
+    settings: Option<bool>,
+}
+
+/// Raw tab lifecycle hooks as read from JSON file
+#[derive(Debug, Deserialize, Default, Clone)]
+struct TabHooksFile {
+    /// Command run when a tab's Neovide window appears
+    on_tab_open: Option<String>,
+    /// Command run when a tab is closed
+    on_tab_close: Option<String>,
+    /// Command run when a tab is selected
+    on_tab_select: Option<String>,
+}
+
+/// Raw Neovide window recognition overrides as read from JSON file
+#[derive(Debug, Deserialize, Default, Clone)]
+struct NeovideWindowFile {
+    /// Window class names of Neovide windows
+    classes: Option<Vec<String>>,
+    /// Texts the title of a (visible) Neovide window contains
+    titles: Option<Vec<String>>,
+}
+
+/// Raw reference to a terminal color scheme to import
+#[derive(Debug, Deserialize, Default, Clone)]
+struct ColorSchemeFile {
+    /// Theme file (Windows Terminal settings.json, .itermcolors or base16 YAML)
+    path: String,
+    /// Scheme to use from a file with several schemes (first one if unset)
+    name: Option<String>,
 }
 
 /// Raw configuration as read from JSON file
@@ -129,10 +612,102 @@ struct HotkeyConfigFile {
 struct ConfigFile {
     /// Background color as hex string (with or without # prefix)
     background_color: Option<String>,
+    /// Terminal color scheme providing the colors not set explicitly
+    color_scheme: Option<ColorSchemeFile>,
+    /// Font family of the tab bar and popups
+    font_family: Option<String>,
+    /// Tab label font size in pixels
+    font_size: Option<i32>,
+    /// Content inset in pixels (0 = gapless)
+    content_inset: Option<i32>,
+    /// Hide managed Neovide windows from Alt-Tab and the taskbar
+    hide_neovide_from_alt_tab: Option<bool>,
+    /// Suspend Neovide in tabs that have been in the background this many minutes (0 = never)
+    suspend_background_tabs_after_minutes: Option<u64>,
+    /// Memory (MB) of all tabs together that shows the high memory banner (0 = never)
+    memory_warning_mb: Option<u64>,
+    /// Maximum number of Neovide processes starting at the same time (0 = default)
+    max_concurrent_spawns: Option<usize>,
+    /// Most tabs open at once (0 = no limit)
+    max_tabs: Option<usize>,
+    /// Minutes between automatic snapshots of the active session (0 = only on exit)
+    session_autosave_minutes: Option<u64>,
+    /// Title formats tried in order when a tab's title format expands to nothing
+    title_fallback: Option<Vec<String>>,
+    /// Select an existing tab with the same profile and directory instead of opening a duplicate
+    reuse_existing_tab: Option<bool>,
+    /// Action for double-clicking a tab ("rename", "duplicate", "pin" or "close")
+    tab_double_click: Option<String>,
+    /// Actions for mouse buttons on the title bar, keyed by "[modifiers+]button[@area]"
+    mouse_bindings: Option<BTreeMap<String, String>>,
+    /// Tab layout ("normal", "two_line" or "compact")
+    tab_style: Option<String>,
+    /// Side of the window buttons ("right" or "left")
+    window_controls: Option<String>,
+    /// Draw the window buttons as macOS-style traffic lights
+    traffic_lights: Option<bool>,
+    /// Show the minimize button
+    show_minimize: Option<bool>,
+    /// Show the maximize button
+    show_maximize: Option<bool>,
+    /// Show the close button
+    show_close: Option<bool>,
+    /// Hide the title bar entirely
+    hide_titlebar: Option<bool>,
+    /// Collapse the title bar while the pointer is away from it
+    auto_hide_titlebar: Option<bool>,
+    /// Initial window state ("normal", "maximized" or "minimized")
+    start_state: Option<String>,
+    /// Display the wrapper opens on (number or name)
+    monitor: Option<MonitorFile>,
+    /// Remember the window's place per monitor setup
+    remember_window_layout: Option<bool>,
+    /// Window class name of the wrapper window
+    window_class: Option<String>,
+    /// Title of the wrapper window (before the session name)
+    window_title: Option<String>,
+    /// When to show the tab hotkey numbers ("off", "hold" or "always")
+    tab_numbers: Option<String>,
+    /// How short a swipe switches tabs ("off", "low", "medium" or "high")
+    swipe_sensitivity: Option<String>,
+    /// Pulse background tabs whose Neovim asks for attention
+    tab_attention: Option<bool>,
+    /// Play the system notification sound when a background tab asks for attention
+    tab_attention_sound: Option<bool>,
+    /// Reactivate the previously active application when the wrapper exits
+    restore_foreground_on_exit: Option<bool>,
+    /// Command opening a terminal in a tab's working directory
+    terminal_command: Option<String>,
+    /// Show the project type's icon on tabs of profiles without an icon
+    project_icons: Option<bool>,
+    /// Show a git status dot on tabs
+    git_status: Option<bool>,
+    /// Show a thumbnail of a background tab's window when hovering the tab
+    tab_previews: Option<bool>,
+    /// Look for a newer release at startup
+    check_updates: Option<bool>,
+    /// Turn off animations (default: follow Windows' animation setting)
+    reduce_motion: Option<bool>,
+    /// Ask which profile to open at startup
+    prompt_profile_on_start: Option<bool>,
+    /// Environment variables set for every Neovide process
+    env: Option<BTreeMap<String, String>>,
     /// List of profiles
     profiles: Option<Vec<ProfileFile>>,
     /// Hotkey configuration
     hotkeys: Option<HotkeyConfigFile>,
+    /// Tab bar widgets
+    widgets: Option<WidgetsConfigFile>,
+    /// FancyZones / window snapping compatibility settings
+    fancyzones: Option<FancyZonesConfigFile>,
+    /// Tiling window manager compatibility ("komorebi", "glazewm", "other", "off")
+    tiling_wm: Option<String>,
+    /// How Neovide windows are recognized (for custom builds)
+    neovide_window: Option<NeovideWindowFile>,
+    /// Shell commands run on tab lifecycle events
+    hooks: Option<TabHooksFile>,
+    /// Timer tuning
+    advanced: Option<AdvancedConfigFile>,
 }
 
 /// A tab profile with resolved paths
@@ -148,6 +723,27 @@ pub struct Profile {
     pub hotkey: Option<String>,
     /// Tab title format string (supports %p, %w, %t, %f tokens)
     pub title: String,
+    /// Whether Neovide runs as administrator (UAC prompt per tab)
+    pub elevated: bool,
+    /// Environment variables for Neovide (top-level `env` merged with the
+    /// profile's, `${VAR}` references expanded)
+    pub env: Vec<(String, String)>,
+    /// Files a new tab opens (relative to its working directory)
+    pub open: Vec<PathBuf>,
+    /// Command nvim runs at startup, without the leading '+'
+    pub nvim_cmd: Option<String>,
+    /// Name of the `discover` profile this one was generated from; such profiles
+    /// are listed in a submenu named after it
+    pub group: Option<String>,
+    /// Tabs show the logo of the project in their working directory, if there is
+    /// one (`icon: "auto"`; `icon` is the default icon then)
+    pub auto_icon: bool,
+    /// Most tabs of this profile open at once; opening another selects one of
+    /// them instead
+    pub max_instances: Option<usize>,
+    /// Display the wrapper opens on when this profile opens the first tab,
+    /// overriding the top-level `monitor`
+    pub monitor: Option<MonitorTarget>,
 }
 
 /// Parsed hotkey configuration
@@ -155,6 +751,314 @@ pub struct Profile {
 pub struct HotkeyConfig {
     /// Tab hotkey mappings: hotkey string -> tab number (1-based)
     pub tab: HashMap<String, u32>,
+    /// Hotkey that opens the folder picker for a new tab
+    pub new_tab_in_folder: Option<String>,
+    /// Hotkey that toggles the task manager popup
+    pub task_manager: Option<String>,
+    /// Hotkey that gracefully closes all tabs but the selected one
+    pub close_other_tabs: Option<String>,
+    /// Hotkey that gracefully closes the tabs right of the selected one
+    pub close_tabs_to_right: Option<String>,
+    /// Hotkey that selects the previously selected tab
+    pub tab_back: Option<String>,
+    /// Hotkey that undoes a `tab_back`
+    pub tab_forward: Option<String>,
+    /// Hotkey that moves the keyboard focus to the tab bar
+    pub focus_tab_bar: Option<String>,
+    /// Hotkey that cycles to the next tab (through the switcher while held)
+    pub next_tab: Option<String>,
+    /// Hotkey that cycles to the previous tab
+    pub prev_tab: Option<String>,
+    /// Hotkey that toggles the debug HUD
+    pub debug_hud: Option<String>,
+}
+
+/// What double-clicking a tab does
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TabDoubleClickAction {
+    /// Edit the tab's name in place
+    #[default]
+    Rename,
+    /// Open another tab with the same profile and working directory
+    Duplicate,
+    /// Pin or unpin the tab
+    TogglePin,
+    /// Close the tab
+    Close,
+}
+
+/// Mouse button that can be bound on the title bar (the left button stays for
+/// clicking and dragging)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+    Middle,
+    Right,
+    X1,
+    X2,
+}
+
+/// Part of the title bar a mouse binding applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseArea {
+    /// A tab (including its close button)
+    Tab,
+    /// The empty title bar the window is dragged by
+    Caption,
+    /// Either of them
+    Any,
+}
+
+/// What a bound mouse button does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseAction {
+    NextTab,
+    PrevTab,
+    NewTab,
+    /// Close the tab under the pointer (the selected one on the caption)
+    CloseTab,
+    /// Context menu of the tab under the pointer (the selected one on the caption)
+    TabMenu,
+    ProfileMenu,
+    WindowMenu,
+    Minimize,
+    Maximize,
+    /// Unbound: whatever Windows does with the button
+    None,
+}
+
+/// A mouse button with modifiers over part of the title bar, and its action
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseBinding {
+    pub button: MouseButton,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    pub area: MouseArea,
+    pub action: MouseAction,
+}
+
+impl MouseBinding {
+    /// Whether both bind the same button and modifiers over the same area
+    fn same_chord(&self, other: &MouseBinding) -> bool {
+        self.button == other.button
+            && self.ctrl == other.ctrl
+            && self.shift == other.shift
+            && self.alt == other.alt
+            && self.area == other.area
+    }
+}
+
+impl Config {
+    /// Action bound to `button` with the (ctrl, shift, alt) modifiers over `area`
+    /// (a tab or the caption); a binding for that area wins over one for both
+    pub fn mouse_action(
+        &self,
+        button: MouseButton,
+        (ctrl, shift, alt): (bool, bool, bool),
+        area: MouseArea,
+    ) -> Option<MouseAction> {
+        let find = |area| {
+            self.mouse_bindings.iter().find(|b| {
+                b.button == button
+                    && b.ctrl == ctrl
+                    && b.shift == shift
+                    && b.alt == alt
+                    && b.area == area
+            })
+        };
+        find(area)
+            .or_else(|| find(MouseArea::Any))
+            .map(|binding| binding.action)
+            .filter(|&action| action != MouseAction::None)
+    }
+}
+
+/// How tabs are laid out in the tab bar
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TabStyle {
+    /// Title only
+    #[default]
+    Normal,
+    /// Title with the abbreviated working directory below it (taller tab bar)
+    TwoLine,
+    /// Square icon-only tabs, with the title in the tooltip
+    Compact,
+}
+
+/// Which side of the title bar the minimize, maximize and close buttons are on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WindowControls {
+    #[default]
+    Right,
+    /// Left of the tabs, in macOS order (close, minimize, maximize)
+    Left,
+}
+
+/// How the window is first shown
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StartState {
+    #[default]
+    Normal,
+    Maximized,
+    Minimized,
+}
+
+/// A display, as chosen by the `monitor` option
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MonitorTarget {
+    /// 1-based, counting left to right
+    Number(usize),
+    /// Friendly name ("DELL U2720Q") or device name ("DISPLAY2")
+    Name(String),
+}
+
+/// Tiling window manager the wrapper cooperates with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TilingWm {
+    #[default]
+    Off,
+    Komorebi,
+    GlazeWm,
+    /// Another tiling window manager (no rules in its format)
+    Other,
+}
+
+impl TilingWm {
+    /// Whether a tiling window manager positions the wrapper
+    pub fn is_enabled(self) -> bool {
+        self != TilingWm::Off
+    }
+}
+
+/// When the tab hotkey numbers are drawn on the tabs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TabNumbers {
+    #[default]
+    Off,
+    /// While the tab hotkeys' modifiers are held down
+    Hold,
+    Always,
+}
+
+/// How short a swipe switches to the adjacent tab (see gestures.rs)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SwipeSensitivity {
+    Off,
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+/// Shell commands run (through `cmd /C`) on tab lifecycle events
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TabHooks {
+    /// Run when a tab's Neovide window appears
+    pub on_tab_open: Option<String>,
+    /// Run when a tab is closed or its Neovide exits
+    pub on_tab_close: Option<String>,
+    /// Run when a tab is selected
+    pub on_tab_select: Option<String>,
+}
+
+/// Timer tuning: the right values depend on how fast the machine is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdvancedConfig {
+    /// Interval for polling the Neovide processes (ms)
+    pub process_poll_interval_ms: u32,
+    /// Delay before bringing Neovide to the foreground on activation (ms)
+    pub foreground_delay_ms: u32,
+    /// Delay before following an external move or resize of the wrapper (ms)
+    pub position_update_delay_ms: u32,
+}
+
+impl Default for AdvancedConfig {
+    fn default() -> Self {
+        Self {
+            process_poll_interval_ms: DEFAULT_PROCESS_POLL_INTERVAL_MS,
+            foreground_delay_ms: DEFAULT_FOREGROUND_DELAY_MS,
+            position_update_delay_ms: DEFAULT_POSITION_UPDATE_DELAY_MS,
+        }
+    }
+}
+
+/// Indicators shown at the right end of the tab bar, before the window buttons
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WidgetsConfig {
+    /// Current time
+    pub clock: bool,
+    /// Name of the active session
+    pub session: bool,
+    /// "Update available" badge, while there is an update
+    pub update: bool,
+    /// Settings (gear) button
+    pub settings: bool,
+}
+
+impl Default for WidgetsConfig {
+    fn default() -> Self {
+        Self {
+            clock: false,
+            session: true,
+            update: true,
+            settings: true,
+        }
+    }
+}
+
+/// FancyZones / window snapping compatibility settings
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FancyZonesConfig {
+    /// Listen for move/size WinEvents on the wrapper and resync Neovide immediately
+    /// instead of waiting for the debounce timer
+    pub enabled: bool,
+    /// Snap managed Neovide windows back into the wrapper whenever something else
+    /// moves them, so zone layouts only ever target the wrapper
+    pub wrapper_only: bool,
+}
+
+impl FancyZonesConfig {
+    /// Whether any option needs the location-change WinEvent hooks
+    pub fn tracks_locations(&self) -> bool {
+        self.enabled || self.wrapper_only
+    }
+}
+
+/// How the Neovide window of a tab is recognized among the top-level windows of
+/// its process
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NeovideWindowMatch {
+    /// Window class names of Neovide windows (winit's "Window Class" by default)
+    pub classes: Vec<String>,
+    /// Texts the title of a visible Neovide window contains (case-insensitive), for
+    /// builds with a different class name
+    pub titles: Vec<String>,
+}
+
+impl Default for NeovideWindowMatch {
+    fn default() -> Self {
+        Self {
+            classes: vec![DEFAULT_NEOVIDE_WINDOW_CLASS.to_string()],
+            titles: vec![DEFAULT_NEOVIDE_WINDOW_TITLE.to_string()],
+        }
+    }
+}
+
+impl NeovideWindowMatch {
+    /// Whether an unowned top-level window of the tab's process is a Neovide window.
+    /// Hidden windows only match by class, since the title of e.g. a hidden
+    /// helper window says nothing about it.
+    pub fn matches(&self, class_name: &str, title: &str, visible: bool) -> bool {
+        if self.classes.iter().any(|class| class == class_name) {
+            return true;
+        }
+        let title = title.to_lowercase();
+        visible
+            && self
+                .titles
+                .iter()
+                .any(|text| title.contains(&text.to_lowercase()))
+    }
 }
 
 impl Profile {
@@ -166,7 +1070,27 @@ impl Profile {
             working_directory: dirs::home_dir().unwrap_or_else(|| PathBuf::from(".")),
             hotkey: Some(DEFAULT_PROFILE_HOTKEY.to_string()),
             title: DEFAULT_TITLE_FORMAT.to_string(),
+            elevated: false,
+            env: Vec::new(),
+            open: Vec::new(),
+            nvim_cmd: None,
+            group: None,
+            auto_icon: false,
+            max_instances: None,
+            monitor: None,
+        }
+    }
+}
+
+impl Profile {
+    /// This profile with the overrides of `directory`'s DIRECTORY_CONFIG_FILE (if
+    /// there is one) merged over it
+    pub fn with_directory_overrides(&self, directory: &Path) -> Profile {
+        let mut profile = self.clone();
+        if let Some(file) = read_directory_config(directory) {
+            apply_directory_config(&mut profile, file, directory);
         }
+        profile
     }
 }
 
@@ -174,6 +1098,16 @@ impl Default for HotkeyConfig {
     fn default() -> Self {
         Self {
             tab: default_tab_hotkeys(),
+            new_tab_in_folder: None,
+            task_manager: None,
+            close_other_tabs: None,
+            close_tabs_to_right: None,
+            tab_back: None,
+            tab_forward: None,
+            focus_tab_bar: None,
+            next_tab: None,
+            prev_tab: None,
+            debug_hud: None,
         }
     }
 }
@@ -195,18 +1129,156 @@ fn default_tab_hotkeys() -> HashMap<String, u32> {
 pub struct Config {
     /// Background color as RGB value (0x00RRGGBB format)
     pub background_color: u32,
+    /// Font family of the tab bar and popups
+    pub font_family: String,
+    /// Tab label font size in pixels (within FONT_SIZE_RANGE)
+    pub font_size: i32,
+    /// Inset between the window edges and the Neovide window in pixels (0 = gapless)
+    pub content_inset: i32,
+    /// Hide managed Neovide windows from Alt-Tab and the taskbar (only the wrapper shows)
+    pub hide_neovide_from_alt_tab: bool,
+    /// Suspend background tabs after this much time unselected (None = never)
+    pub suspend_background_after: Option<Duration>,
+    /// Memory (bytes) of all tabs together that shows the high memory banner;
+    /// None: never
+    pub memory_warning_threshold: Option<u64>,
+    /// Maximum number of Neovide processes starting at the same time (at least 1)
+    pub max_concurrent_spawns: usize,
+    /// Most tabs open at once; None for no limit
+    pub max_tabs: Option<usize>,
+    /// Interval between automatic snapshots of the active session (None = only on exit)
+    pub session_autosave_interval: Option<Duration>,
+    /// Title formats tried in order when a tab's title format expands to nothing
+    pub title_fallback: Vec<String>,
+    /// Select an existing tab with the same profile and working directory instead of
+    /// opening a duplicate
+    pub reuse_existing_tab: bool,
+    /// What double-clicking a tab does
+    pub tab_double_click: TabDoubleClickAction,
+    /// Actions for mouse buttons on the title bar
+    pub mouse_bindings: Vec<MouseBinding>,
+    /// How tabs are laid out in the tab bar
+    pub tab_style: TabStyle,
+    /// Side of the title bar the window buttons are on
+    pub window_controls: WindowControls,
+    /// Draw the window buttons as macOS-style traffic lights
+    pub traffic_lights: bool,
+    /// Show the minimize button
+    pub show_minimize: bool,
+    /// Show the maximize button
+    pub show_maximize: bool,
+    /// Show the close button
+    pub show_close: bool,
+    /// Hide the title bar, giving Neovide the whole window
+    pub hide_titlebar: bool,
+    /// Collapse the title bar to a strip while the pointer is away from it
+    pub auto_hide_titlebar: bool,
+    /// How the window is first shown
+    pub start_state: StartState,
+    /// Display the wrapper opens on (None: where Windows places it)
+    pub monitor: Option<MonitorTarget>,
+    /// Put the window back where it was on the connected displays
+    pub remember_window_layout: bool,
+    /// Window class name of the wrapper window (registered at startup)
+    pub window_class: String,
+    /// Title of the wrapper window, followed by the session name if any
+    pub window_title: String,
+    /// When the tab hotkey numbers are drawn on the tabs
+    pub tab_numbers: TabNumbers,
+    /// How short a touchpad or touch swipe switches tabs
+    pub swipe_sensitivity: SwipeSensitivity,
+    /// Pulse background tabs whose Neovim reports an error or runs :NeovideTabsAttention
+    pub tab_attention: bool,
+    /// Play the system notification sound along with the attention pulse
+    pub tab_attention_sound: bool,
+    /// Reactivate the application that was active before the wrapper when it exits
+    pub restore_foreground_on_exit: bool,
+    /// Command run in a tab's working directory by "Open in Terminal"
+    pub terminal_command: String,
+    /// Show a Rust/Node/Go/git icon on tabs of profiles without an icon when the
+    /// working directory is such a project
+    pub project_icons: bool,
+    /// Show a dot on tabs whose working directory has uncommitted changes or is
+    /// ahead of or behind its upstream branch
+    pub git_status: bool,
+    /// Show a thumbnail of a background tab's Neovide window when its tab is hovered
+    pub tab_previews: bool,
+    /// Look for a newer release on GitHub at startup
+    pub check_updates: bool,
+    /// Turn off the wrapper's animations; None follows Windows' "Animation effects"
+    pub reduce_motion: Option<bool>,
+    /// Ask which profile the first tab opens instead of opening the first profile
+    pub prompt_profile_on_start: bool,
     /// List of profiles (always has at least one - the Default profile)
     pub profiles: Vec<Profile>,
     /// Hotkey configuration
     pub hotkeys: HotkeyConfig,
+    /// Tab bar widgets
+    pub widgets: WidgetsConfig,
+    /// FancyZones / window snapping compatibility settings
+    pub fancyzones: FancyZonesConfig,
+    /// Tiling window manager the wrapper cooperates with
+    pub tiling_wm: TilingWm,
+    /// How Neovide windows are recognized
+    pub neovide_window: NeovideWindowMatch,
+    /// Commands run on tab lifecycle events
+    pub hooks: TabHooks,
+    /// Timer tuning
+    pub advanced: AdvancedConfig,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             background_color: DEFAULT_BACKGROUND_COLOR,
+            font_family: DEFAULT_FONT_FAMILY.to_string(),
+            font_size: DEFAULT_FONT_SIZE,
+            content_inset: DEFAULT_CONTENT_INSET,
+            hide_neovide_from_alt_tab: false,
+            suspend_background_after: None,
+            memory_warning_threshold: Some(DEFAULT_MEMORY_WARNING_MB * 1024 * 1024),
+            max_concurrent_spawns: DEFAULT_MAX_CONCURRENT_SPAWNS,
+            max_tabs: None,
+            session_autosave_interval: Some(Duration::from_secs(
+                DEFAULT_SESSION_AUTOSAVE_MINUTES * 60,
+            )),
+            title_fallback: default_title_fallback(),
+            reuse_existing_tab: false,
+            tab_double_click: TabDoubleClickAction::default(),
+            mouse_bindings: parse_mouse_bindings(None),
+            tab_style: TabStyle::default(),
+            window_controls: WindowControls::default(),
+            traffic_lights: false,
+            show_minimize: true,
+            show_maximize: true,
+            show_close: true,
+            hide_titlebar: false,
+            auto_hide_titlebar: false,
+            start_state: StartState::default(),
+            monitor: None,
+            remember_window_layout: true,
+            window_class: DEFAULT_WINDOW_CLASS.to_string(),
+            window_title: DEFAULT_WINDOW_TITLE.to_string(),
+            tab_numbers: TabNumbers::default(),
+            swipe_sensitivity: SwipeSensitivity::default(),
+            tab_attention: true,
+            tab_attention_sound: false,
+            restore_foreground_on_exit: true,
+            terminal_command: DEFAULT_TERMINAL_COMMAND.to_string(),
+            project_icons: true,
+            git_status: false,
+            tab_previews: true,
+            check_updates: false,
+            reduce_motion: None,
+            prompt_profile_on_start: false,
             profiles: vec![Profile::default_profile()],
             hotkeys: HotkeyConfig::default(),
+            widgets: WidgetsConfig::default(),
+            fancyzones: FancyZonesConfig::default(),
+            tiling_wm: TilingWm::default(),
+            neovide_window: NeovideWindowMatch::default(),
+            hooks: TabHooks::default(),
+            advanced: AdvancedConfig::default(),
         }
     }
 }
@@ -264,19 +1336,100 @@ impl Config {
 
     /// Convert raw config file to validated Config
     fn from_config_file(file: ConfigFile) -> Self {
+        // Explicit colors win over the imported scheme
+        let scheme = file.color_scheme.as_ref().and_then(import_color_scheme);
         let background_color = file
             .background_color
             .as_deref()
             .and_then(parse_hex_color)
+            .or(scheme.map(|colors| colors.background))
             .unwrap_or(DEFAULT_BACKGROUND_COLOR);
 
-        let profiles = parse_profiles(file.profiles);
+        let content_inset = parse_content_inset(file.content_inset);
+        let env = parse_env(file.env);
+        let mut profiles = parse_profiles(file.profiles);
+        for profile in &mut profiles {
+            profile.env = merge_env(&env, &profile.env);
+        }
         let hotkeys = parse_hotkey_config(file.hotkeys);
+        let widgets = parse_widgets_config(file.widgets);
+        let fancyzones = parse_fancyzones_config(file.fancyzones);
+        let neovide_window = parse_neovide_window(file.neovide_window);
+        let hooks = parse_tab_hooks(file.hooks);
+        let advanced = parse_advanced_config(file.advanced);
 
         Self {
             background_color,
+            font_family: file
+                .font_family
+                .filter(|family| !family.trim().is_empty())
+                .unwrap_or_else(|| DEFAULT_FONT_FAMILY.to_string()),
+            font_size: parse_font_size(file.font_size),
+            content_inset,
+            hide_neovide_from_alt_tab: file.hide_neovide_from_alt_tab.unwrap_or(false),
+            memory_warning_threshold: Some(
+                file.memory_warning_mb.unwrap_or(DEFAULT_MEMORY_WARNING_MB),
+            )
+            .filter(|mb| *mb > 0)
+            .map(|mb| mb * 1024 * 1024),
+            suspend_background_after: file
+                .suspend_background_tabs_after_minutes
+                .filter(|minutes| *minutes > 0)
+                .map(|minutes| Duration::from_secs(minutes * 60)),
+            max_concurrent_spawns: file
+                .max_concurrent_spawns
+                .filter(|limit| *limit > 0)
+                .unwrap_or(DEFAULT_MAX_CONCURRENT_SPAWNS),
+            max_tabs: file.max_tabs.filter(|max| *max > 0),
+            session_autosave_interval: Some(
+                file.session_autosave_minutes
+                    .unwrap_or(DEFAULT_SESSION_AUTOSAVE_MINUTES),
+            )
+            .filter(|minutes| *minutes > 0)
+            .map(|minutes| Duration::from_secs(minutes * 60)),
+            title_fallback: file.title_fallback.unwrap_or_else(default_title_fallback),
+            reuse_existing_tab: file.reuse_existing_tab.unwrap_or(false),
+            tab_double_click: parse_tab_double_click(file.tab_double_click.as_deref()),
+            mouse_bindings: parse_mouse_bindings(file.mouse_bindings),
+            tab_style: parse_tab_style(file.tab_style.as_deref()),
+            window_controls: parse_window_controls(file.window_controls.as_deref()),
+            traffic_lights: file.traffic_lights.unwrap_or(false),
+            show_minimize: file.show_minimize.unwrap_or(true),
+            show_maximize: file.show_maximize.unwrap_or(true),
+            show_close: file.show_close.unwrap_or(true),
+            hide_titlebar: file.hide_titlebar.unwrap_or(false),
+            auto_hide_titlebar: file.auto_hide_titlebar.unwrap_or(false),
+            start_state: parse_start_state(file.start_state.as_deref()),
+            monitor: parse_monitor(file.monitor),
+            remember_window_layout: file.remember_window_layout.unwrap_or(true),
+            window_class: parse_window_class(file.window_class.as_deref()),
+            window_title: file
+                .window_title
+                .filter(|title| !title.trim().is_empty())
+                .unwrap_or_else(|| DEFAULT_WINDOW_TITLE.to_string()),
+            tab_numbers: parse_tab_numbers(file.tab_numbers.as_deref()),
+            swipe_sensitivity: parse_swipe_sensitivity(file.swipe_sensitivity.as_deref()),
+            tab_attention: file.tab_attention.unwrap_or(true),
+            tab_attention_sound: file.tab_attention_sound.unwrap_or(false),
+            restore_foreground_on_exit: file.restore_foreground_on_exit.unwrap_or(true),
+            terminal_command: file
+                .terminal_command
+                .filter(|command| !command.trim().is_empty())
+                .unwrap_or_else(|| DEFAULT_TERMINAL_COMMAND.to_string()),
+            project_icons: file.project_icons.unwrap_or(true),
+            git_status: file.git_status.unwrap_or(false),
+            tab_previews: file.tab_previews.unwrap_or(true),
+            check_updates: file.check_updates.unwrap_or(false),
+            reduce_motion: file.reduce_motion,
+            prompt_profile_on_start: file.prompt_profile_on_start.unwrap_or(false),
             profiles,
             hotkeys,
+            widgets,
+            fancyzones,
+            tiling_wm: parse_tiling_wm(file.tiling_wm.as_deref()),
+            neovide_window,
+            hooks,
+            advanced,
         }
     }
 
@@ -293,6 +1446,17 @@ impl Config {
         self.profiles.get(index)
     }
 
+    /// Names of the groups of discovered profiles, in config order
+    pub fn profile_groups(&self) -> Vec<&str> {
+        let mut groups: Vec<&str> = Vec::new();
+        for group in self.profiles.iter().filter_map(|p| p.group.as_deref()) {
+            if !groups.contains(&group) {
+                groups.push(group);
+            }
+        }
+        groups
+    }
+
     /// Reload configuration from disk.
     /// Returns Some(new_config) if successfully loaded and parsed.
     /// Returns None if loading or parsing fails (caller should keep current config).
@@ -334,13 +1498,24 @@ impl Config {
     }
 
     /// Find a profile by name. Returns the index and a reference to the profile.
-    #[allow(dead_code)]
     pub fn find_profile_by_name(&self, name: &str) -> Option<(usize, &Profile)> {
         self.profiles
             .iter()
             .enumerate()
             .find(|(_, p)| p.name == name)
     }
+
+    /// Display the wrapper opens on when `profile` opens the first tab: the
+    /// profile's `monitor`, else the top-level one
+    pub fn monitor_for<'a>(&'a self, profile: &'a Profile) -> Option<&'a MonitorTarget> {
+        profile.monitor.as_ref().or(self.monitor.as_ref())
+    }
+
+    /// Whether external moves of the wrapper and Neovide windows are followed
+    /// (FancyZones or tiling window manager compatibility)
+    pub fn tracks_window_locations(&self) -> bool {
+        self.fancyzones.tracks_locations() || self.tiling_wm.is_enabled()
+    }
 }
 
 /// Parse profiles from config file.
@@ -360,26 +1535,22 @@ fn parse_profiles(profiles_opt: Option<Vec<ProfileFile>>) -> Vec<Profile> {
                 "Config: Processing {} user-defined profiles",
                 profile_files.len()
             );
-            profile_files
+            let (templates, profile_files): (Vec<_>, Vec<_>) = profile_files
                 .into_iter()
-                .map(|pf| {
-                    eprintln!("Config: Processing profile '{}'", pf.name);
-                    let working_directory = pf
-                        .working_directory
-                        .map(|wd| resolve_working_directory(&wd, &home_dir))
-                        .unwrap_or_else(|| home_dir.clone());
-                    let icon = resolve_icon_path(pf.icon, &home_dir);
-                    let title = pf.title.unwrap_or_else(|| DEFAULT_TITLE_FORMAT.to_string());
-
-                    Profile {
-                        name: pf.name,
-                        icon,
-                        working_directory,
-                        hotkey: pf.hotkey,
-                        title,
-                    }
-                })
-                .collect()
+                .partition(|pf| pf.discover.is_some());
+            let mut profiles: Vec<Profile> = profile_files
+                .into_iter()
+                .map(|pf| parse_profile(pf, &home_dir))
+                .collect();
+            // With only `discover` profiles, the Default profile opens the first tab
+            if profiles.is_empty() {
+                profiles.push(Profile::default_profile());
+            }
+            // Discovered profiles go last, so they never become the default profile
+            for template in templates {
+                profiles.extend(discover_profiles(template, &home_dir));
+            }
+            profiles
         }
         // No profiles defined - use internal Default profile as fallback
         _ => {
@@ -396,19 +1567,281 @@ fn parse_profiles(profiles_opt: Option<Vec<ProfileFile>>) -> Vec<Profile> {
     profiles
 }
 
+/// Resolve the paths and defaults of a profile from the config file
+fn parse_profile(pf: ProfileFile, home_dir: &Path) -> Profile {
+    eprintln!("Config: Processing profile '{}'", pf.name);
+    let working_directory = pf
+        .working_directory
+        .map(|wd| resolve_working_directory(&wd, home_dir))
+        .unwrap_or_else(|| home_dir.to_path_buf());
+    let auto_icon = pf
+        .icon
+        .as_deref()
+        .is_some_and(|icon| icon.trim().eq_ignore_ascii_case(AUTO_ICON));
+    let icon = if auto_icon {
+        DEFAULT_ICON.to_string()
+    } else {
+        resolve_icon_path(pf.icon, home_dir)
+    };
+    let title = pf.title.unwrap_or_else(|| DEFAULT_TITLE_FORMAT.to_string());
+
+    Profile {
+        name: pf.name,
+        icon,
+        working_directory,
+        hotkey: pf.hotkey,
+        title,
+        elevated: pf.elevated.unwrap_or(false),
+        env: parse_env(pf.env),
+        open: pf
+            .open
+            .unwrap_or_default()
+            .iter()
+            .map(|file| expand_path(file, home_dir))
+            .collect(),
+        nvim_cmd: parse_nvim_cmd(pf.nvim_cmd.as_deref()),
+        group: None,
+        auto_icon,
+        max_instances: pf.max_instances.filter(|max| *max > 0),
+        monitor: parse_monitor(pf.monitor),
+    }
+}
+
+/// One profile per directory matching the `discover` glob of `template`, named
+/// after the directory. The template's hotkey and working directory are not used.
+fn discover_profiles(template: ProfileFile, home_dir: &Path) -> Vec<Profile> {
+    let pattern = template.discover.clone().unwrap_or_default();
+    let directories = discover_directories(&expand_path(&pattern, home_dir));
+    if directories.is_empty() {
+        eprintln!(
+            "Config: No directories match '{}' of profile '{}'",
+            pattern, template.name
+        );
+    }
+    let base = parse_profile(
+        ProfileFile {
+            working_directory: None,
+            hotkey: None,
+            ..template
+        },
+        home_dir,
+    );
+    directories
+        .into_iter()
+        .map(|directory| Profile {
+            name: directory
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| directory.to_string_lossy().to_string()),
+            working_directory: directory,
+            group: Some(base.name.clone()),
+            ..base.clone()
+        })
+        .collect()
+}
+
+/// Directories matching `pattern`, whose components may contain `*` and `?`
+/// wildcards, sorted by path. Like in shells, wildcards skip hidden (dot) names.
+fn discover_directories(pattern: &Path) -> Vec<PathBuf> {
+    let mut matches = vec![PathBuf::new()];
+    for component in pattern.components() {
+        let part = component.as_os_str().to_string_lossy();
+        if !part.contains(['*', '?']) {
+            for path in &mut matches {
+                path.push(component);
+            }
+            continue;
+        }
+        matches = matches
+            .iter()
+            .filter_map(|dir| fs::read_dir(dir).ok())
+            .flat_map(|entries| entries.flatten())
+            .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+            .filter(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                (part.starts_with('.') || !name.starts_with('.')) && wildcard_match(&part, &name)
+            })
+            .map(|entry| entry.path())
+            .collect();
+    }
+    matches.retain(|path| path.is_dir());
+    matches.sort();
+    matches
+}
+
+/// Whether `name` matches `pattern` (`*`: any run of characters, `?`: one
+/// character), ignoring case like Windows file names
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it was tried at
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            p += 1;
+            star = Some((p, n));
+        } else if let Some((after_star, tried)) = star {
+            // Let the `*` take one more character
+            p = after_star;
+            n = tried + 1;
+            star = Some((after_star, n));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
 /// Parse hotkey configuration from config file
 fn parse_hotkey_config(config_opt: Option<HotkeyConfigFile>) -> HotkeyConfig {
     match config_opt {
         Some(config) => {
-            // If hotkeys section exists, use it (even if empty, which disables defaults)
-            let tab = config.tab.unwrap_or_default();
-            HotkeyConfig { tab }
+            // Only an explicit (empty) tab map replaces the default tab hotkeys;
+            // a section setting other hotkeys keeps them
+            let tab = config.tab.unwrap_or_else(|| HotkeyConfig::default().tab);
+            HotkeyConfig {
+                tab,
+                new_tab_in_folder: config.new_tab_in_folder,
+                task_manager: config.task_manager,
+                close_other_tabs: config.close_other_tabs,
+                close_tabs_to_right: config.close_tabs_to_right,
+                tab_back: config.tab_back,
+                tab_forward: config.tab_forward,
+                focus_tab_bar: config.focus_tab_bar,
+                next_tab: config.next_tab,
+                prev_tab: config.prev_tab,
+                debug_hud: config.debug_hud,
+            }
         }
         // No hotkeys section - use defaults
         None => HotkeyConfig::default(),
     }
 }
 
+/// Parse the Neovide window recognition overrides. Empty entries are dropped; a
+/// list left empty falls back to the default.
+fn parse_neovide_window(file: Option<NeovideWindowFile>) -> NeovideWindowMatch {
+    let file = file.unwrap_or_default();
+    let defaults = NeovideWindowMatch::default();
+    let parse = |list: Option<Vec<String>>, default: Vec<String>| {
+        list.map(|entries| {
+            entries
+                .into_iter()
+                .filter(|entry| !entry.trim().is_empty())
+                .collect::<Vec<_>>()
+        })
+        .filter(|entries| !entries.is_empty())
+        .unwrap_or(default)
+    };
+    NeovideWindowMatch {
+        classes: parse(file.classes, defaults.classes),
+        titles: parse(file.titles, defaults.titles),
+    }
+}
+
+/// Parse the tab lifecycle hooks; blank commands count as unset
+fn parse_tab_hooks(file: Option<TabHooksFile>) -> TabHooks {
+    let file = file.unwrap_or_default();
+    let command = |command: Option<String>| command.filter(|c| !c.trim().is_empty());
+    TabHooks {
+        on_tab_open: command(file.on_tab_open),
+        on_tab_close: command(file.on_tab_close),
+        on_tab_select: command(file.on_tab_select),
+    }
+}
+
+/// Parse FancyZones settings; missing options default to off
+/// Parse the timer tuning; values out of range fall back to the defaults
+fn parse_advanced_config(file: Option<AdvancedConfigFile>) -> AdvancedConfig {
+    let file = file.unwrap_or_default();
+    AdvancedConfig {
+        process_poll_interval_ms: parse_timer_ms(
+            "process_poll_interval_ms",
+            file.process_poll_interval_ms,
+            PROCESS_POLL_INTERVAL_RANGE,
+            DEFAULT_PROCESS_POLL_INTERVAL_MS,
+        ),
+        foreground_delay_ms: parse_timer_ms(
+            "foreground_delay_ms",
+            file.foreground_delay_ms,
+            TIMER_DELAY_RANGE,
+            DEFAULT_FOREGROUND_DELAY_MS,
+        ),
+        position_update_delay_ms: parse_timer_ms(
+            "position_update_delay_ms",
+            file.position_update_delay_ms,
+            TIMER_DELAY_RANGE,
+            DEFAULT_POSITION_UPDATE_DELAY_MS,
+        ),
+    }
+}
+
+/// Validate a timer value in milliseconds against `range`
+fn parse_timer_ms(
+    name: &str,
+    value: Option<u32>,
+    range: std::ops::RangeInclusive<u32>,
+    default: u32,
+) -> u32 {
+    match value {
+        Some(ms) if range.contains(&ms) => ms,
+        Some(ms) => {
+            eprintln!(
+                "Config: advanced.{} {} out of range ({}-{}), using default",
+                name,
+                ms,
+                range.start(),
+                range.end()
+            );
+            default
+        }
+        None => default,
+    }
+}
+
+/// Parse the widget toggles; missing options keep their defaults
+fn parse_widgets_config(config_opt: Option<WidgetsConfigFile>) -> WidgetsConfig {
+    let config = config_opt.unwrap_or_default();
+    let defaults = WidgetsConfig::default();
+    WidgetsConfig {
+        clock: config.clock.unwrap_or(defaults.clock),
+        session: config.session.unwrap_or(defaults.session),
+        update: config.update.unwrap_or(defaults.update),
+        settings: config.settings.unwrap_or(defaults.settings),
+    }
+}
+
+fn parse_fancyzones_config(config_opt: Option<FancyZonesConfigFile>) -> FancyZonesConfig {
+    let config = config_opt.unwrap_or_default();
+    FancyZonesConfig {
+        enabled: config.enabled.unwrap_or(false),
+        wrapper_only: config.wrapper_only.unwrap_or(false),
+    }
+}
+
+/// Import the configured color scheme. A relative path is relative to the config
+/// directory. Returns None (after logging why) if the scheme can't be used.
+fn import_color_scheme(scheme: &ColorSchemeFile) -> Option<ImportedColors> {
+    let home_dir = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    let mut path = expand_path(&scheme.path, &home_dir);
+    if path.is_relative()
+        && let Some(config_dir) = config_dir_path()
+    {
+        path = config_dir.join(path);
+    }
+    match colors::import_color_scheme(&path, scheme.name.as_deref()) {
+        Ok(colors) => Some(colors),
+        Err(e) => {
+            eprintln!("Config: Failed to import color scheme: {:#}", e);
+            None
+        }
+    }
+}
+
 /// Expand ~ to home directory in a path string
 fn expand_tilde(path_str: &str, home_dir: &Path) -> PathBuf {
     if path_str.starts_with('~') {
@@ -427,10 +1860,45 @@ fn expand_tilde(path_str: &str, home_dir: &Path) -> PathBuf {
     }
 }
 
-/// Resolve a working directory path string, expanding ~ to home directory.
-/// Falls back to home directory if the path doesn't exist.
+/// Expand environment variables (`%VAR%`, `${VAR}`) and then ~ in a path string
+fn expand_path(path_str: &str, home_dir: &Path) -> PathBuf {
+    let expanded = expand_path_vars(path_str, |var| std::env::var(var).ok());
+    expand_tilde(&expanded, home_dir)
+}
+
+/// Replace `%VAR%` and `${VAR}` references in `value` with `lookup(VAR)`.
+/// Unknown `%VAR%` references are kept as-is, like cmd.exe does.
+fn expand_path_vars(value: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let value = expand_env_vars(value, &lookup);
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value.as_str();
+    while let Some(start) = rest.find('%') {
+        let after = &rest[start + 1..];
+        let Some(len) = after.find('%') else {
+            break;
+        };
+        let name = &after[..len];
+        match lookup(name).filter(|_| !name.is_empty()) {
+            Some(var) => {
+                result.push_str(&rest[..start]);
+                result.push_str(&var);
+                rest = &after[len + 1..];
+            }
+            // Keep the first % and try again from the second
+            None => {
+                result.push_str(&rest[..=start]);
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Resolve a working directory path string, expanding environment variables
+/// and ~. Falls back to home directory if the path doesn't exist.
 fn resolve_working_directory(path_str: &str, home_dir: &Path) -> PathBuf {
-    let path = expand_tilde(path_str, home_dir);
+    let path = expand_path(path_str, home_dir);
     // Validate the directory exists, fall back to home if not
     if path.is_dir() {
         path
@@ -443,52 +1911,223 @@ fn resolve_working_directory(path_str: &str, home_dir: &Path) -> PathBuf {
     }
 }
 
-/// Resolve an icon path string, expanding ~ to home directory.
-/// Returns the expanded path as a string, or the default icon if not specified.
-fn resolve_icon_path(icon_opt: Option<String>, home_dir: &Path) -> String {
-    match icon_opt {
-        Some(icon_str) if icon_str.starts_with('~') => {
-            let expanded = expand_tilde(&icon_str, home_dir);
-            expanded.to_string_lossy().to_string()
-        }
-        Some(icon_str) => icon_str,
-        None => DEFAULT_ICON.to_string(),
-    }
+/// Environment variables of an `env` map with `${VAR}` references expanded
+/// from the wrapper's own environment
+fn parse_env(env_opt: Option<BTreeMap<String, String>>) -> Vec<(String, String)> {
+    env_opt
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(name, _)| !name.is_empty() && !name.contains('='))
+        .map(|(name, value)| {
+            let value = expand_env_vars(&value, |var| std::env::var(var).ok());
+            (name, value)
+        })
+        .collect()
 }
 
-/// Get the path to the config directory: `~/.config/neovide-tabs/`
-pub fn config_dir_path() -> Option<PathBuf> {
-    let home = dirs::home_dir()?;
-    Some(home.join(".config").join("neovide-tabs"))
+/// `base` with the variables of `overrides` replacing those of the same name
+/// (names are case-insensitive on Windows)
+fn merge_env(base: &[(String, String)], overrides: &[(String, String)]) -> Vec<(String, String)> {
+    let mut env: Vec<(String, String)> = base
+        .iter()
+        .filter(|(name, _)| {
+            !overrides
+                .iter()
+                .any(|(other, _)| other.eq_ignore_ascii_case(name))
+        })
+        .cloned()
+        .collect();
+    env.extend(overrides.iter().cloned());
+    env
 }
 
-/// Get the path to the preferred config file: `~/.config/neovide-tabs/config.jsonc`
-fn config_file_path_jsonc() -> Option<PathBuf> {
-    let home = dirs::home_dir()?;
-    Some(
-        home.join(".config")
-            .join("neovide-tabs")
-            .join("config.jsonc"),
-    )
+/// Replace `${VAR}` references in `value` with `lookup(VAR)`. Unknown variables
+/// expand to nothing; text without a closing brace is kept as-is.
+pub fn expand_env_vars(value: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        result.push_str(&rest[..start]);
+        let name = &rest[start + 2..start + 2 + len];
+        result.push_str(&lookup(name).unwrap_or_default());
+        rest = &rest[start + 3 + len..];
+    }
+    result.push_str(rest);
+    result
 }
 
-/// Get the path to the fallback config file: `~/.config/neovide-tabs/config.json`
-fn config_file_path_json() -> Option<PathBuf> {
-    let home = dirs::home_dir()?;
-    Some(
-        home.join(".config")
-            .join("neovide-tabs")
-            .join("config.json"),
-    )
+/// Read the overrides file in `directory`; None if there is none or it is invalid
+fn read_directory_config(directory: &Path) -> Option<DirectoryConfigFile> {
+    let path = directory.join(DIRECTORY_CONFIG_FILE);
+    let contents = fs::read_to_string(&path).ok()?;
+    match serde_json::from_str(&strip_jsonc_comments(&contents)) {
+        Ok(file) => Some(file),
+        Err(e) => {
+            eprintln!("Config: Failed to parse {}: {}", path.display(), e);
+            None
+        }
+    }
 }
 
-/// Find the config file to load. Prefers .jsonc, falls back to .json.
-/// Returns None if neither exists.
-fn find_config_file() -> Option<PathBuf> {
-    // Check for .jsonc first (preferred)
-    if let Some(jsonc_path) = config_file_path_jsonc()
-        && jsonc_path.exists()
-    {
+/// Merge the overrides of `directory` over `profile`; blank values are ignored
+fn apply_directory_config(profile: &mut Profile, file: DirectoryConfigFile, directory: &Path) {
+    let home_dir = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    if let Some(title) = file.title.filter(|title| !title.trim().is_empty()) {
+        profile.title = title;
+    }
+    if let Some(icon) = file.icon.filter(|icon| !icon.trim().is_empty()) {
+        profile.icon = directory
+            .join(expand_path(&icon, &home_dir))
+            .to_string_lossy()
+            .to_string();
+        profile.auto_icon = false;
+    }
+    if let Some(open) = file.open {
+        profile.open = open
+            .iter()
+            .filter_map(|entry| {
+                let file = directory_open_file(directory, entry);
+                if file.is_none() {
+                    eprintln!(
+                        "Config: Ignoring \"{}\" in the open list of {}: not a file inside it",
+                        entry,
+                        directory.join(DIRECTORY_CONFIG_FILE).display()
+                    );
+                }
+                file
+            })
+            .collect();
+    }
+}
+
+/// File `entry` of a directory config's `open` list, inside `directory`. The
+/// entries end up on Neovide's command line, so anything leaving the directory
+/// (absolute, drive and UNC paths, `..`) or reading like an option (`-`, `+`) is
+/// rejected with None. Both slashes count as separators, as on Windows.
+fn directory_open_file(directory: &Path, entry: &str) -> Option<PathBuf> {
+    let entry = entry.trim();
+    if entry.is_empty() || entry.starts_with(['-', '+', '/', '\\']) || entry.contains(':') {
+        return None;
+    }
+    if entry.split(['/', '\\']).any(|part| part == "..") {
+        return None;
+    }
+    Some(directory.join(entry))
+}
+
+/// Resolve an icon path string, expanding environment variables and ~.
+/// Returns the expanded path as a string, or the default icon if not specified.
+fn resolve_icon_path(icon_opt: Option<String>, home_dir: &Path) -> String {
+    match icon_opt {
+        Some(icon_str) => expand_path(&icon_str, home_dir)
+            .to_string_lossy()
+            .to_string(),
+        None => DEFAULT_ICON.to_string(),
+    }
+}
+
+/// Where config and data are stored when not under the user profile
+#[derive(Debug, Clone, Default)]
+pub struct StorageDirs {
+    /// Directory of the config file
+    pub config_dir: Option<PathBuf>,
+    /// Directory of icons and sessions
+    pub data_dir: Option<PathBuf>,
+    /// Config file used instead of config.jsonc/config.json in the config directory
+    pub config_file: Option<PathBuf>,
+}
+
+impl StorageDirs {
+    /// Config and data both in `dir` (portable mode)
+    pub fn portable(dir: PathBuf) -> Self {
+        Self {
+            config_dir: Some(dir.clone()),
+            data_dir: Some(dir),
+            config_file: None,
+        }
+    }
+
+    /// Config directory to use instead of the default; a custom config file's
+    /// directory wins so the watcher follows that file
+    fn config_dir_override(&self) -> Option<PathBuf> {
+        match &self.config_file {
+            Some(file) => file.parent().map(Path::to_path_buf),
+            None => self.config_dir.clone(),
+        }
+    }
+}
+
+/// Storage directories chosen at startup; unset means the user profile defaults
+static STORAGE_DIRS: OnceLock<StorageDirs> = OnceLock::new();
+
+/// Choose where config and data are stored. Must be called at startup, before any
+/// path below is used.
+pub fn set_storage_dirs(dirs: StorageDirs) {
+    if STORAGE_DIRS.set(dirs).is_err() {
+        eprintln!("Warning: Storage directories were already set");
+    }
+}
+
+/// Portable mode directory (`config\` next to the executable) if `--portable` was
+/// given or a `portable` file sits next to the executable
+pub fn portable_dir(exe_path: &Path, portable_flag: bool) -> Option<PathBuf> {
+    let exe_dir = exe_path.parent()?;
+    (portable_flag || exe_dir.join(PORTABLE_MARKER).is_file()).then(|| exe_dir.join(PORTABLE_DIR))
+}
+
+/// Get the path to the config directory: `~/.config/neovide-tabs/`
+pub fn config_dir_path() -> Option<PathBuf> {
+    if let Some(dir) = STORAGE_DIRS
+        .get()
+        .and_then(StorageDirs::config_dir_override)
+    {
+        return Some(dir);
+    }
+    let home = dirs::home_dir()?;
+    Some(home.join(".config").join("neovide-tabs"))
+}
+
+/// Get the path to the preferred config file: `~/.config/neovide-tabs/config.jsonc`
+fn config_file_path_jsonc() -> Option<PathBuf> {
+    Some(config_dir_path()?.join("config.jsonc"))
+}
+
+/// Get the path to the fallback config file: `~/.config/neovide-tabs/config.json`
+fn config_file_path_json() -> Option<PathBuf> {
+    Some(config_dir_path()?.join("config.json"))
+}
+
+/// Config file given with `--config` or NEOVIDE_TABS_CONFIG, if any
+fn config_file_override() -> Option<PathBuf> {
+    STORAGE_DIRS.get()?.config_file.clone()
+}
+
+/// Files whose changes reload the config
+pub fn config_file_candidates() -> Vec<PathBuf> {
+    match config_file_override() {
+        Some(file) => vec![file],
+        None => [config_file_path_jsonc(), config_file_path_json()]
+            .into_iter()
+            .flatten()
+            .collect(),
+    }
+}
+
+/// Find the config file to load. Prefers .jsonc, falls back to .json.
+/// Returns None if neither exists.
+fn find_config_file() -> Option<PathBuf> {
+    // A custom config file replaces both
+    if let Some(file) = config_file_override() {
+        return file.exists().then_some(file);
+    }
+
+    // Check for .jsonc first (preferred)
+    if let Some(jsonc_path) = config_file_path_jsonc()
+        && jsonc_path.exists()
+    {
         return Some(jsonc_path);
     }
 
@@ -502,6 +2141,12 @@ fn find_config_file() -> Option<PathBuf> {
     None
 }
 
+/// The config file in use, created from the template if there is none yet
+pub fn active_config_file() -> Option<PathBuf> {
+    ensure_config_file();
+    find_config_file()
+}
+
 /// Ensure the config directory exists, creating it if necessary.
 /// Returns true if the directory exists (or was created), false on error.
 fn ensure_config_dir() -> bool {
@@ -526,8 +2171,8 @@ fn ensure_config_file() {
         return;
     }
 
-    // Generate new config as .jsonc
-    let path = match config_file_path_jsonc() {
+    // Generate new config as .jsonc (or at the custom config path)
+    let path = match config_file_override().or_else(config_file_path_jsonc) {
         Some(p) => p,
         None => return,
     };
@@ -549,7 +2194,7 @@ fn ensure_config_file() {
 
 /// Strip JSONC comments from content, returning valid JSON.
 /// Supports // line comments. Comments inside strings are preserved.
-fn strip_jsonc_comments(content: &str) -> String {
+pub(crate) fn strip_jsonc_comments(content: &str) -> String {
     let mut result = String::with_capacity(content.len());
     let mut in_string = false;
     let mut escape_next = false;
@@ -601,17 +2246,290 @@ pub fn generate_default_config() -> &'static str {
 
 /// Get the path to the data directory: `~/.local/share/neovide-tabs/`
 pub fn data_dir_path() -> Option<PathBuf> {
+    if let Some(dir) = STORAGE_DIRS.get().and_then(|dirs| dirs.data_dir.clone()) {
+        return Some(dir);
+    }
     let home = dirs::home_dir()?;
     Some(home.join(".local").join("share").join("neovide-tabs"))
 }
 
+/// Default title fallback chain as owned strings
+fn default_title_fallback() -> Vec<String> {
+    DEFAULT_TITLE_FALLBACK
+        .iter()
+        .map(|f| f.to_string())
+        .collect()
+}
+
+/// Parse the `tab_double_click` action, falling back to renaming for unknown values
+fn parse_tab_double_click(action: Option<&str>) -> TabDoubleClickAction {
+    match action.map(|a| a.trim().to_ascii_lowercase()).as_deref() {
+        None | Some("rename") => TabDoubleClickAction::Rename,
+        Some("duplicate") => TabDoubleClickAction::Duplicate,
+        Some("pin") => TabDoubleClickAction::TogglePin,
+        Some("close") => TabDoubleClickAction::Close,
+        Some(other) => {
+            eprintln!(
+                "Config: Unknown tab_double_click action '{}', using \"rename\"",
+                other
+            );
+            TabDoubleClickAction::Rename
+        }
+    }
+}
+
+/// Parse the `mouse_bindings` table on top of the default one (right-clicking a tab
+/// opens its menu); malformed entries are skipped
+fn parse_mouse_bindings(bindings: Option<BTreeMap<String, String>>) -> Vec<MouseBinding> {
+    let mut parsed: Vec<MouseBinding> = Vec::new();
+    for (chord, action) in bindings.unwrap_or_default() {
+        let Some(binding) = parse_mouse_binding(&chord, &action) else {
+            eprintln!(
+                "Config: Invalid mouse binding '{}': '{}', ignoring",
+                chord, action
+            );
+            continue;
+        };
+        parsed.retain(|b| !b.same_chord(&binding));
+        parsed.push(binding);
+    }
+    let default = MouseBinding {
+        button: MouseButton::Right,
+        ctrl: false,
+        shift: false,
+        alt: false,
+        area: MouseArea::Tab,
+        action: MouseAction::TabMenu,
+    };
+    if !parsed.iter().any(|b| b.same_chord(&default)) {
+        parsed.push(default);
+    }
+    parsed
+}
+
+/// Parse one `mouse_bindings` entry like "Ctrl+Middle@tab": "close_tab"
+fn parse_mouse_binding(chord: &str, action: &str) -> Option<MouseBinding> {
+    let chord = chord.trim().to_ascii_lowercase();
+    let (keys, area) = match chord.split_once('@') {
+        Some((keys, area)) => (keys, area.trim()),
+        None => (chord.as_str(), ""),
+    };
+    let area = match area {
+        "" => MouseArea::Any,
+        "tab" => MouseArea::Tab,
+        "caption" => MouseArea::Caption,
+        _ => return None,
+    };
+    let mut parts: Vec<&str> = keys.split('+').map(str::trim).collect();
+    let button = match parts.pop()? {
+        "middle" => MouseButton::Middle,
+        "right" => MouseButton::Right,
+        "x1" => MouseButton::X1,
+        "x2" => MouseButton::X2,
+        _ => return None,
+    };
+    let (mut ctrl, mut shift, mut alt) = (false, false, false);
+    for modifier in parts {
+        match modifier {
+            "ctrl" | "control" => ctrl = true,
+            "shift" => shift = true,
+            "alt" => alt = true,
+            _ => return None,
+        }
+    }
+    let action = match action.trim().to_ascii_lowercase().as_str() {
+        "next_tab" => MouseAction::NextTab,
+        "prev_tab" => MouseAction::PrevTab,
+        "new_tab" => MouseAction::NewTab,
+        "close_tab" => MouseAction::CloseTab,
+        "tab_menu" => MouseAction::TabMenu,
+        "profile_menu" => MouseAction::ProfileMenu,
+        "window_menu" => MouseAction::WindowMenu,
+        "minimize" => MouseAction::Minimize,
+        "maximize" => MouseAction::Maximize,
+        "none" => MouseAction::None,
+        _ => return None,
+    };
+    Some(MouseBinding {
+        button,
+        ctrl,
+        shift,
+        alt,
+        area,
+        action,
+    })
+}
+
+/// Parse the `tab_style`, falling back to the normal layout for unknown values
+fn parse_tab_style(style: Option<&str>) -> TabStyle {
+    match style.map(|s| s.trim().to_ascii_lowercase()).as_deref() {
+        None | Some("normal") => TabStyle::Normal,
+        Some("two_line") => TabStyle::TwoLine,
+        Some("compact") => TabStyle::Compact,
+        Some(other) => {
+            eprintln!("Config: Unknown tab_style '{}', using \"normal\"", other);
+            TabStyle::Normal
+        }
+    }
+}
+
+/// Parse `window_controls`, falling back to the right side for unknown values
+fn parse_window_controls(side: Option<&str>) -> WindowControls {
+    match side.map(|s| s.trim().to_ascii_lowercase()).as_deref() {
+        None | Some("right") => WindowControls::Right,
+        Some("left") => WindowControls::Left,
+        Some(other) => {
+            eprintln!(
+                "Config: Unknown window_controls '{}', using \"right\"",
+                other
+            );
+            WindowControls::Right
+        }
+    }
+}
+
+/// The profile's `nvim_cmd` without the leading '+' nvim's syntax needs; None if empty
+fn parse_nvim_cmd(cmd: Option<&str>) -> Option<String> {
+    let cmd = cmd?.trim();
+    let cmd = cmd.strip_prefix('+').unwrap_or(cmd).trim();
+    (!cmd.is_empty()).then(|| cmd.to_string())
+}
+
+/// Parse the `window_class`, falling back to the default for empty names and
+/// names longer than Windows allows
+fn parse_window_class(class: Option<&str>) -> String {
+    match class.map(str::trim) {
+        None | Some("") => DEFAULT_WINDOW_CLASS.to_string(),
+        Some(class) if class.encode_utf16().count() > MAX_WINDOW_CLASS_LENGTH => {
+            eprintln!(
+                "Config: window_class is longer than {} characters, using \"{}\"",
+                MAX_WINDOW_CLASS_LENGTH, DEFAULT_WINDOW_CLASS
+            );
+            DEFAULT_WINDOW_CLASS.to_string()
+        }
+        Some(class) => class.to_string(),
+    }
+}
+
+/// Parse the `tiling_wm`, falling back to off for unknown values
+fn parse_tiling_wm(wm: Option<&str>) -> TilingWm {
+    match wm.map(|s| s.trim().to_ascii_lowercase()).as_deref() {
+        None | Some("off") => TilingWm::Off,
+        Some("komorebi") => TilingWm::Komorebi,
+        Some("glazewm") => TilingWm::GlazeWm,
+        Some("other") => TilingWm::Other,
+        Some(other) => {
+            eprintln!("Config: Unknown tiling_wm '{}', using \"off\"", other);
+            TilingWm::Off
+        }
+    }
+}
+
+/// Parse the `start_state`, falling back to a normal window for unknown values
+fn parse_start_state(state: Option<&str>) -> StartState {
+    match state.map(|s| s.trim().to_ascii_lowercase()).as_deref() {
+        None | Some("normal") => StartState::Normal,
+        Some("maximized") => StartState::Maximized,
+        Some("minimized") => StartState::Minimized,
+        Some(other) => {
+            eprintln!("Config: Unknown start_state '{}', using \"normal\"", other);
+            StartState::Normal
+        }
+    }
+}
+
+/// Parse a `monitor`; a number in a string counts as a number. 0 and empty names
+/// leave the placement to Windows.
+fn parse_monitor(monitor: Option<MonitorFile>) -> Option<MonitorTarget> {
+    let target = match monitor? {
+        MonitorFile::Number(number) => MonitorTarget::Number(number),
+        MonitorFile::Name(name) => match name.trim().parse() {
+            Ok(number) => MonitorTarget::Number(number),
+            Err(_) => MonitorTarget::Name(name.trim().to_string()),
+        },
+    };
+    match target {
+        MonitorTarget::Number(0) => {
+            eprintln!("Config: Monitors are numbered from 1, ignoring monitor 0");
+            None
+        }
+        MonitorTarget::Name(name) if name.is_empty() => None,
+        target => Some(target),
+    }
+}
+
+/// Parse `tab_numbers`, falling back to no numbers for unknown values
+fn parse_tab_numbers(mode: Option<&str>) -> TabNumbers {
+    match mode.map(|m| m.trim().to_ascii_lowercase()).as_deref() {
+        None | Some("off") => TabNumbers::Off,
+        Some("hold") => TabNumbers::Hold,
+        Some("always") => TabNumbers::Always,
+        Some(other) => {
+            eprintln!("Config: Unknown tab_numbers '{}', using \"off\"", other);
+            TabNumbers::Off
+        }
+    }
+}
+
+/// Parse `swipe_sensitivity`, falling back to the default for unknown values
+fn parse_swipe_sensitivity(sensitivity: Option<&str>) -> SwipeSensitivity {
+    match sensitivity
+        .map(|s| s.trim().to_ascii_lowercase())
+        .as_deref()
+    {
+        None | Some("medium") => SwipeSensitivity::Medium,
+        Some("off") => SwipeSensitivity::Off,
+        Some("low") => SwipeSensitivity::Low,
+        Some("high") => SwipeSensitivity::High,
+        Some(other) => {
+            eprintln!(
+                "Config: Unknown swipe_sensitivity '{}', using \"medium\"",
+                other
+            );
+            SwipeSensitivity::Medium
+        }
+    }
+}
+
+/// Validate the configured content inset, falling back to the default when out of range
+fn parse_content_inset(inset_opt: Option<i32>) -> i32 {
+    match inset_opt {
+        Some(inset) if (0..=MAX_CONTENT_INSET).contains(&inset) => inset,
+        Some(inset) => {
+            eprintln!(
+                "Config: content_inset {} out of range (0-{}), using default",
+                inset, MAX_CONTENT_INSET
+            );
+            DEFAULT_CONTENT_INSET
+        }
+        None => DEFAULT_CONTENT_INSET,
+    }
+}
+
+/// Validate the font size, falling back to the default when out of range
+fn parse_font_size(size_opt: Option<i32>) -> i32 {
+    match size_opt {
+        Some(size) if FONT_SIZE_RANGE.contains(&size) => size,
+        Some(size) => {
+            eprintln!(
+                "Config: font_size {} out of range ({}-{}), using default",
+                size,
+                FONT_SIZE_RANGE.start(),
+                FONT_SIZE_RANGE.end()
+            );
+            DEFAULT_FONT_SIZE
+        }
+        None => DEFAULT_FONT_SIZE,
+    }
+}
+
 /// Parse a hex color string (with or without # prefix) to RGB u32.
 /// Returns None if the format is invalid.
 ///
 /// Accepts formats:
 /// - "1a1b26" (6 chars, no prefix)
 /// - "#1a1b26" (7 chars with # prefix)
-fn parse_hex_color(s: &str) -> Option<u32> {
+pub(crate) fn parse_hex_color(s: &str) -> Option<u32> {
     let hex = s.strip_prefix('#').unwrap_or(s);
 
     if hex.len() != 6 {
@@ -635,6 +2553,7 @@ pub struct TitleContext<'a> {
 /// Supports the following tokens:
 /// - `%p` - Profile name
 /// - `%w` - Working directory (with ~ substitution for home directory)
+/// - `%w_basename` - Last component of the working directory
 /// - `%t` - Neovide window title
 /// - `%f` - Relative file path from window title (relative to working directory)
 ///
@@ -655,6 +2574,21 @@ pub fn expand_title(format: &str, context: &TitleContext) -> String {
                     }
                     'w' => {
                         chars.next();
+                        // %w_basename: just the last path component
+                        const BASENAME_SUFFIX: &str = "_basename";
+                        if chars
+                            .clone()
+                            .take(BASENAME_SUFFIX.len())
+                            .eq(BASENAME_SUFFIX.chars())
+                        {
+                            for _ in 0..BASENAME_SUFFIX.len() {
+                                chars.next();
+                            }
+                            if let Some(name) = context.working_directory.file_name() {
+                                result.push_str(&name.to_string_lossy());
+                            }
+                            continue;
+                        }
                         let wd_display = format_working_directory(
                             context.working_directory,
                             home_dir.as_deref(),
@@ -697,6 +2631,30 @@ pub fn expand_title(format: &str, context: &TitleContext) -> String {
     sanitize_title(&result)
 }
 
+/// Expand a tab's title format, trying the `fallback` formats in order when it comes
+/// out empty and finally the profile name. Neovide's startup title ("Neovide") counts
+/// as no window title, so `%t` stays empty until a buffer is open.
+pub fn expand_title_with_fallback(
+    format: &str,
+    fallback: &[String],
+    context: &TitleContext,
+) -> String {
+    let window_title = if context.window_title.trim() == NEOVIDE_PLACEHOLDER_TITLE {
+        ""
+    } else {
+        context.window_title
+    };
+    let context = TitleContext {
+        window_title,
+        ..*context
+    };
+    std::iter::once(format)
+        .chain(fallback.iter().map(String::as_str))
+        .map(|f| expand_title(f, &context))
+        .find(|title| !title.is_empty())
+        .unwrap_or_else(|| context.profile_name.to_string())
+}
+
 /// Format a working directory path for display.
 /// Replaces home directory prefix with ~ for brevity.
 fn format_working_directory(path: &Path, home_dir: Option<&Path>) -> String {
@@ -714,6 +2672,28 @@ fn format_working_directory(path: &Path, home_dir: Option<&Path>) -> String {
     path.to_string_lossy().replace('\\', "/")
 }
 
+/// Shorten a working directory for display under a tab title: `~` for the home
+/// directory and every component but the last cut to its first character (two for
+/// hidden directories), e.g. `~/p/neovide-tabs` or `D:/w/.c/nvim`
+pub fn abbreviate_working_directory(path: &Path, home_dir: Option<&Path>) -> String {
+    let full = format_working_directory(path, home_dir);
+    let components: Vec<&str> = full.split('/').collect();
+    let last = components.len() - 1;
+    components
+        .iter()
+        .enumerate()
+        .map(|(i, component)| {
+            // Keep the last component, "~" and drive letters ("C:") whole
+            if i == last || *component == "~" || component.ends_with(':') {
+                return component.to_string();
+            }
+            let keep = if component.starts_with('.') { 2 } else { 1 };
+            component.chars().take(keep).collect()
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
 /// Extract the relative file path from the window title.
 ///
 /// If the window title contains a path string that begins with the working directory,
@@ -840,6 +2820,7 @@ fn sanitize_title(title: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testdir::TempDir;
 
     #[test]
     fn test_parse_hex_color_without_prefix() {
@@ -880,8 +2861,7 @@ mod tests {
     fn test_config_from_file_with_valid_color() {
         let file = ConfigFile {
             background_color: Some("#ff0000".to_string()),
-            profiles: None,
-            hotkeys: None,
+            ..Default::default()
         };
         let config = Config::from_config_file(file);
         assert_eq!(config.background_color, 0xff0000);
@@ -891,24 +2871,170 @@ mod tests {
     fn test_config_from_file_with_invalid_color() {
         let file = ConfigFile {
             background_color: Some("invalid".to_string()),
-            profiles: None,
-            hotkeys: None,
+            ..Default::default()
         };
         let config = Config::from_config_file(file);
         assert_eq!(config.background_color, DEFAULT_BACKGROUND_COLOR);
     }
 
+    #[test]
+    fn test_config_from_file_hide_neovide_from_alt_tab() {
+        assert!(!Config::default().hide_neovide_from_alt_tab);
+
+        let file = ConfigFile {
+            hide_neovide_from_alt_tab: Some(true),
+            ..Default::default()
+        };
+        let config = Config::from_config_file(file);
+        assert!(config.hide_neovide_from_alt_tab);
+    }
+
+    #[test]
+    fn test_config_from_file_suspend_background_tabs() {
+        assert_eq!(Config::default().suspend_background_after, None);
+
+        let file = ConfigFile {
+            suspend_background_tabs_after_minutes: Some(10),
+            ..Default::default()
+        };
+        let config = Config::from_config_file(file);
+        assert_eq!(
+            config.suspend_background_after,
+            Some(Duration::from_secs(600))
+        );
+
+        let file = ConfigFile {
+            suspend_background_tabs_after_minutes: Some(0),
+            ..Default::default()
+        };
+        let config = Config::from_config_file(file);
+        assert_eq!(config.suspend_background_after, None);
+    }
+
+    #[test]
+    fn test_config_from_file_memory_warning() {
+        assert_eq!(
+            Config::default().memory_warning_threshold,
+            Some(DEFAULT_MEMORY_WARNING_MB * 1024 * 1024)
+        );
+
+        let file = ConfigFile {
+            memory_warning_mb: Some(512),
+            ..Default::default()
+        };
+        assert_eq!(
+            Config::from_config_file(file).memory_warning_threshold,
+            Some(512 * 1024 * 1024)
+        );
+
+        let file = ConfigFile {
+            memory_warning_mb: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(
+            Config::from_config_file(file).memory_warning_threshold,
+            None
+        );
+    }
+
+    #[test]
+    fn test_config_from_file_max_concurrent_spawns() {
+        assert_eq!(
+            Config::default().max_concurrent_spawns,
+            DEFAULT_MAX_CONCURRENT_SPAWNS
+        );
+
+        let file = ConfigFile {
+            max_concurrent_spawns: Some(4),
+            ..Default::default()
+        };
+        assert_eq!(Config::from_config_file(file).max_concurrent_spawns, 4);
+    }
+
+    #[test]
+    fn test_config_from_file_max_tabs() {
+        assert_eq!(Config::default().max_tabs, None);
+
+        let file = ConfigFile {
+            max_tabs: Some(8),
+            ..Default::default()
+        };
+        assert_eq!(Config::from_config_file(file).max_tabs, Some(8));
+
+        let file = ConfigFile {
+            max_tabs: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(Config::from_config_file(file).max_tabs, None);
+
+        // 0 would never launch anything, so it falls back to the default
+        let file = ConfigFile {
+            max_concurrent_spawns: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(
+            Config::from_config_file(file).max_concurrent_spawns,
+            DEFAULT_MAX_CONCURRENT_SPAWNS
+        );
+    }
+
+    #[test]
+    fn test_config_from_file_session_autosave() {
+        let default_interval = Some(Duration::from_secs(DEFAULT_SESSION_AUTOSAVE_MINUTES * 60));
+        assert_eq!(
+            Config::default().session_autosave_interval,
+            default_interval
+        );
+        assert_eq!(
+            Config::from_config_file(ConfigFile::default()).session_autosave_interval,
+            default_interval
+        );
+
+        let file = ConfigFile {
+            session_autosave_minutes: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            Config::from_config_file(file).session_autosave_interval,
+            Some(Duration::from_secs(120))
+        );
+
+        let file = ConfigFile {
+            session_autosave_minutes: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(
+            Config::from_config_file(file).session_autosave_interval,
+            None
+        );
+    }
+
     #[test]
     fn test_config_from_file_with_no_color() {
         let file = ConfigFile {
             background_color: None,
-            profiles: None,
-            hotkeys: None,
+            ..Default::default()
         };
         let config = Config::from_config_file(file);
         assert_eq!(config.background_color, DEFAULT_BACKGROUND_COLOR);
     }
 
+    #[test]
+    fn test_parse_content_inset() {
+        assert_eq!(parse_content_inset(None), DEFAULT_CONTENT_INSET);
+        assert_eq!(parse_content_inset(Some(0)), 0);
+        assert_eq!(parse_content_inset(Some(4)), 4);
+        assert_eq!(
+            parse_content_inset(Some(MAX_CONTENT_INSET)),
+            MAX_CONTENT_INSET
+        );
+        assert_eq!(parse_content_inset(Some(-1)), DEFAULT_CONTENT_INSET);
+        assert_eq!(
+            parse_content_inset(Some(MAX_CONTENT_INSET + 1)),
+            DEFAULT_CONTENT_INSET
+        );
+    }
+
     #[test]
     fn test_config_file_path_jsonc() {
         let path = config_file_path_jsonc();
@@ -959,6 +3085,13 @@ mod tests {
             working_directory: Some("~".to_string()),
             hotkey: None,
             title: None,
+            elevated: None,
+            env: None,
+            open: None,
+            nvim_cmd: None,
+            discover: None,
+            max_instances: None,
+            monitor: None,
         }];
         let profiles = parse_profiles(Some(profile_files));
         assert_eq!(profiles.len(), 1);
@@ -979,6 +3112,13 @@ mod tests {
             working_directory: None,
             hotkey: None,
             title: None,
+            elevated: None,
+            env: None,
+            open: None,
+            nvim_cmd: None,
+            discover: None,
+            max_instances: None,
+            monitor: None,
         }];
         let profiles = parse_profiles(Some(profile_files));
         assert_eq!(profiles.len(), 1);
@@ -990,23 +3130,118 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_profiles_preserves_order() {
-        // User-defined profiles maintain their order
-        let profile_files = vec![
-            ProfileFile {
-                name: "Work".to_string(),
-                icon: None,
-                working_directory: None,
-                hotkey: None,
-                title: None,
-            },
-            ProfileFile {
-                name: "Personal".to_string(),
-                icon: None,
-                working_directory: None,
-                hotkey: Some("Ctrl+Shift+F2".to_string()),
-                title: Some("%p: %w".to_string()),
-            },
+    fn test_parse_profiles_open_and_nvim_cmd() {
+        let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+        let profile_files = vec![ProfileFile {
+            name: "Work".to_string(),
+            icon: None,
+            working_directory: None,
+            hotkey: None,
+            title: None,
+            elevated: None,
+            env: None,
+            open: Some(vec!["TODO.md".to_string(), "~/notes.md".to_string()]),
+            nvim_cmd: Some("+Telescope find_files".to_string()),
+            discover: None,
+            max_instances: None,
+            monitor: None,
+        }];
+        let profiles = parse_profiles(Some(profile_files));
+        assert_eq!(
+            profiles[0].open,
+            vec![PathBuf::from("TODO.md"), home.join("notes.md")]
+        );
+        assert_eq!(
+            profiles[0].nvim_cmd.as_deref(),
+            Some("Telescope find_files")
+        );
+
+        assert_eq!(parse_nvim_cmd(Some("Git")), Some("Git".to_string()));
+        assert_eq!(parse_nvim_cmd(Some(" + ")), None);
+        assert_eq!(parse_nvim_cmd(None), None);
+    }
+
+    #[test]
+    fn test_parse_profiles_auto_icon() {
+        let profile_files = vec![ProfileFile {
+            name: "Project".to_string(),
+            icon: Some("Auto".to_string()),
+            working_directory: None,
+            hotkey: None,
+            title: None,
+            elevated: None,
+            env: None,
+            open: None,
+            nvim_cmd: None,
+            discover: None,
+            max_instances: None,
+            monitor: None,
+        }];
+        let profiles = parse_profiles(Some(profile_files));
+        assert!(profiles[0].auto_icon);
+        assert_eq!(profiles[0].icon, DEFAULT_ICON);
+        assert!(!Profile::default_profile().auto_icon);
+    }
+
+    #[test]
+    fn test_parse_profiles_max_instances() {
+        let profile = |name: &str, max_instances| ProfileFile {
+            name: name.to_string(),
+            icon: None,
+            working_directory: None,
+            hotkey: None,
+            title: None,
+            elevated: None,
+            env: None,
+            open: None,
+            nvim_cmd: None,
+            discover: None,
+            max_instances,
+            monitor: None,
+        };
+        let profiles = parse_profiles(Some(vec![
+            profile("Once", Some(1)),
+            profile("Unlimited", Some(0)),
+            profile("Unset", None),
+        ]));
+        assert_eq!(profiles[0].max_instances, Some(1));
+        assert_eq!(profiles[1].max_instances, None);
+        assert_eq!(profiles[2].max_instances, None);
+        assert_eq!(Profile::default_profile().max_instances, None);
+    }
+
+    #[test]
+    fn test_parse_profiles_preserves_order() {
+        // User-defined profiles maintain their order
+        let profile_files = vec![
+            ProfileFile {
+                name: "Work".to_string(),
+                icon: None,
+                working_directory: None,
+                hotkey: None,
+                title: None,
+                elevated: None,
+                env: None,
+                open: None,
+                nvim_cmd: None,
+                discover: None,
+                max_instances: None,
+                monitor: None,
+            },
+            ProfileFile {
+                name: "Personal".to_string(),
+                icon: None,
+                working_directory: None,
+                hotkey: Some("Ctrl+Shift+F2".to_string()),
+                title: Some("%p: %w".to_string()),
+                elevated: Some(true),
+                env: None,
+                open: None,
+                nvim_cmd: None,
+                discover: None,
+                max_instances: None,
+                monitor: None,
+            },
         ];
         let profiles = parse_profiles(Some(profile_files));
         assert_eq!(profiles.len(), 2);
@@ -1014,9 +3249,11 @@ mod tests {
         assert_eq!(profiles[0].name, "Work");
         assert_eq!(profiles[0].hotkey, None);
         assert_eq!(profiles[0].title, DEFAULT_TITLE_FORMAT);
+        assert!(!profiles[0].elevated);
         assert_eq!(profiles[1].name, "Personal");
         assert_eq!(profiles[1].hotkey, Some("Ctrl+Shift+F2".to_string()));
         assert_eq!(profiles[1].title, "%p: %w");
+        assert!(profiles[1].elevated);
     }
 
     #[test]
@@ -1040,6 +3277,30 @@ mod tests {
         assert_eq!(resolved, PathBuf::from("/absolute/path"));
     }
 
+    #[test]
+    fn test_expand_path_vars() {
+        let lookup = |var: &str| match var {
+            "USERPROFILE" => Some("C:\\Users\\me".to_string()),
+            "HOME" => Some("/home/me".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            expand_path_vars("%USERPROFILE%\\projects", lookup),
+            "C:\\Users\\me\\projects"
+        );
+        assert_eq!(
+            expand_path_vars("${HOME}/icons/a.png", lookup),
+            "/home/me/icons/a.png"
+        );
+        // Unknown %VAR% references and lone percent signs are kept
+        assert_eq!(expand_path_vars("%NOPE%/100%", lookup), "%NOPE%/100%");
+        assert_eq!(
+            expand_path_vars("50%%USERPROFILE%", lookup),
+            "50%C:\\Users\\me"
+        );
+        assert_eq!(expand_path_vars("~/plain", lookup), "~/plain");
+    }
+
     #[test]
     fn test_resolve_icon_path_with_tilde() {
         let home = PathBuf::from("/home/test");
@@ -1104,6 +3365,7 @@ mod tests {
     fn test_parse_hotkey_config_empty() {
         let config = parse_hotkey_config(Some(HotkeyConfigFile {
             tab: Some(HashMap::new()),
+            ..Default::default()
         }));
         // Empty tab map disables tab hotkeys
         assert_eq!(config.tab.len(), 0);
@@ -1114,12 +3376,261 @@ mod tests {
         let mut tab = HashMap::new();
         tab.insert("Alt+1".to_string(), 1);
         tab.insert("Alt+2".to_string(), 2);
-        let config = parse_hotkey_config(Some(HotkeyConfigFile { tab: Some(tab) }));
+        let config = parse_hotkey_config(Some(HotkeyConfigFile {
+            tab: Some(tab),
+            ..Default::default()
+        }));
         assert_eq!(config.tab.len(), 2);
         assert_eq!(config.tab.get("Alt+1"), Some(&1));
         assert_eq!(config.tab.get("Alt+2"), Some(&2));
     }
 
+    #[test]
+    fn test_parse_hotkey_config_new_tab_in_folder() {
+        // Unbound unless configured
+        assert_eq!(parse_hotkey_config(None).new_tab_in_folder, None);
+
+        let config = parse_hotkey_config(Some(HotkeyConfigFile {
+            new_tab_in_folder: Some("Ctrl+Shift+O".to_string()),
+            ..Default::default()
+        }));
+        assert_eq!(config.new_tab_in_folder, Some("Ctrl+Shift+O".to_string()));
+        // The default tab hotkeys stay when the hotkeys section omits them
+        assert_eq!(config.tab, default_tab_hotkeys());
+    }
+
+    #[test]
+    fn test_parse_hotkey_config_task_manager() {
+        // Unbound unless configured
+        assert_eq!(parse_hotkey_config(None).task_manager, None);
+
+        let config = parse_hotkey_config(Some(HotkeyConfigFile {
+            task_manager: Some("Ctrl+Shift+U".to_string()),
+            ..Default::default()
+        }));
+        assert_eq!(config.task_manager, Some("Ctrl+Shift+U".to_string()));
+        assert_eq!(config.new_tab_in_folder, None);
+    }
+
+    #[test]
+    fn test_parse_hotkey_config_close_tabs() {
+        let config = parse_hotkey_config(None);
+        assert_eq!(config.close_other_tabs, None);
+        assert_eq!(config.close_tabs_to_right, None);
+
+        let config = parse_hotkey_config(Some(HotkeyConfigFile {
+            close_other_tabs: Some("Ctrl+Shift+Alt+W".to_string()),
+            close_tabs_to_right: Some("Ctrl+Shift+Alt+R".to_string()),
+            ..Default::default()
+        }));
+        assert_eq!(
+            config.close_other_tabs,
+            Some("Ctrl+Shift+Alt+W".to_string())
+        );
+        assert_eq!(
+            config.close_tabs_to_right,
+            Some("Ctrl+Shift+Alt+R".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_hotkey_config_tab_history() {
+        assert_eq!(parse_hotkey_config(None).tab_back, None);
+
+        let config = parse_hotkey_config(Some(HotkeyConfigFile {
+            tab_back: Some("Alt+Left".to_string()),
+            tab_forward: Some("Alt+Right".to_string()),
+            ..Default::default()
+        }));
+        assert_eq!(config.tab_back, Some("Alt+Left".to_string()));
+        assert_eq!(config.tab_forward, Some("Alt+Right".to_string()));
+    }
+
+    #[test]
+    fn test_parse_hotkey_config_focus_tab_bar() {
+        assert_eq!(parse_hotkey_config(None).focus_tab_bar, None);
+
+        let config = parse_hotkey_config(Some(HotkeyConfigFile {
+            focus_tab_bar: Some("Ctrl+Shift+F6".to_string()),
+            ..Default::default()
+        }));
+        assert_eq!(config.focus_tab_bar, Some("Ctrl+Shift+F6".to_string()));
+    }
+
+    #[test]
+    fn test_parse_hotkey_config_tab_cycling() {
+        assert_eq!(parse_hotkey_config(None).next_tab, None);
+
+        let config = parse_hotkey_config(Some(HotkeyConfigFile {
+            next_tab: Some("Ctrl+Alt+Right".to_string()),
+            prev_tab: Some("Ctrl+Alt+Left".to_string()),
+            ..Default::default()
+        }));
+        assert_eq!(config.next_tab, Some("Ctrl+Alt+Right".to_string()));
+        assert_eq!(config.prev_tab, Some("Ctrl+Alt+Left".to_string()));
+    }
+
+    #[test]
+    fn test_parse_hotkey_config_debug_hud() {
+        assert_eq!(parse_hotkey_config(None).debug_hud, None);
+
+        let config = parse_hotkey_config(Some(HotkeyConfigFile {
+            debug_hud: Some("Ctrl+Shift+Alt+D".to_string()),
+            ..Default::default()
+        }));
+        assert_eq!(config.debug_hud, Some("Ctrl+Shift+Alt+D".to_string()));
+    }
+
+    #[test]
+    fn test_neovide_window_match() {
+        let rules = parse_neovide_window(None);
+        assert_eq!(rules, NeovideWindowMatch::default());
+        assert!(rules.matches("Window Class", "", false));
+        assert!(rules.matches("SurfaceWindow", "main.rs - NEOVIDE", true));
+        // Hidden windows only match by class
+        assert!(!rules.matches("SurfaceWindow", "Neovide", false));
+        assert!(!rules.matches("IME", "Default IME", true));
+
+        let rules = parse_neovide_window(Some(NeovideWindowFile {
+            classes: Some(vec!["NvGpuSurface".to_string(), " ".to_string()]),
+            titles: Some(Vec::new()),
+        }));
+        assert_eq!(rules.classes, vec!["NvGpuSurface"]);
+        assert_eq!(rules.titles, vec!["Neovide"]);
+        assert!(rules.matches("NvGpuSurface", "", false));
+        assert!(!rules.matches("Window Class", "", false));
+    }
+
+    #[test]
+    fn test_expand_env_vars() {
+        let lookup = |name: &str| (name == "HOME").then(|| "C:\\Users\\me".to_string());
+        assert_eq!(
+            expand_env_vars("${HOME}\\bin", lookup),
+            "C:\\Users\\me\\bin"
+        );
+        assert_eq!(expand_env_vars("a${MISSING}b", lookup), "ab");
+        assert_eq!(
+            expand_env_vars("${HOME};${HOME}", lookup),
+            "C:\\Users\\me;C:\\Users\\me"
+        );
+        assert_eq!(expand_env_vars("$HOME ${HOME", lookup), "$HOME ${HOME");
+        assert_eq!(expand_env_vars("plain", lookup), "plain");
+    }
+
+    #[test]
+    fn test_profile_env_overrides_global_env() {
+        let config: ConfigFile = serde_json::from_str(
+            r#"{
+                "env": { "RUST_LOG": "info", "Path": "C:\\bin", "BAD=NAME": "x" },
+                "profiles": [
+                    { "name": "Work", "env": { "PATH": "D:\\bin", "HTTPS_PROXY": "proxy:8080" } },
+                    { "name": "Personal" }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let config = Config::from_config_file(config);
+
+        let env = |name: &str, profile: usize| {
+            config.profiles[profile]
+                .env
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.clone())
+        };
+        assert_eq!(env("RUST_LOG", 0).as_deref(), Some("info"));
+        assert_eq!(env("PATH", 0).as_deref(), Some("D:\\bin"));
+        assert_eq!(env("HTTPS_PROXY", 0).as_deref(), Some("proxy:8080"));
+        assert_eq!(config.profiles[0].env.len(), 3);
+        assert_eq!(env("PATH", 1).as_deref(), Some("C:\\bin"));
+        assert_eq!(env("HTTPS_PROXY", 1), None);
+        assert_eq!(config.profiles[1].env.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_tab_hooks() {
+        assert_eq!(parse_tab_hooks(None), TabHooks::default());
+
+        let hooks = parse_tab_hooks(Some(TabHooksFile {
+            on_tab_open: Some("devserver start".to_string()),
+            on_tab_close: Some("  ".to_string()),
+            on_tab_select: None,
+        }));
+        assert_eq!(hooks.on_tab_open.as_deref(), Some("devserver start"));
+        assert_eq!(hooks.on_tab_close, None);
+        assert_eq!(hooks.on_tab_select, None);
+    }
+
+    #[test]
+    fn test_parse_widgets_config() {
+        let config = parse_widgets_config(None);
+        assert_eq!(config, WidgetsConfig::default());
+        assert!(!config.clock);
+        assert!(config.session && config.update && config.settings);
+
+        let config = parse_widgets_config(Some(WidgetsConfigFile {
+            clock: Some(true),
+            session: None,
+            update: Some(false),
+            settings: None,
+        }));
+        assert!(config.clock);
+        assert!(config.session);
+        assert!(!config.update);
+        assert!(config.settings);
+    }
+
+    #[test]
+    fn test_parse_fancyzones_config() {
+        assert_eq!(parse_fancyzones_config(None), FancyZonesConfig::default());
+        assert!(!parse_fancyzones_config(None).tracks_locations());
+
+        let config = parse_fancyzones_config(Some(FancyZonesConfigFile {
+            enabled: Some(true),
+            wrapper_only: None,
+        }));
+        assert!(config.enabled);
+        assert!(!config.wrapper_only);
+        assert!(config.tracks_locations());
+
+        let config = parse_fancyzones_config(Some(FancyZonesConfigFile {
+            enabled: None,
+            wrapper_only: Some(true),
+        }));
+        assert!(!config.enabled);
+        assert!(config.tracks_locations());
+    }
+
+    #[test]
+    fn test_parse_advanced_config() {
+        assert_eq!(parse_advanced_config(None), AdvancedConfig::default());
+
+        let config = parse_advanced_config(Some(AdvancedConfigFile {
+            process_poll_interval_ms: Some(80),
+            foreground_delay_ms: Some(200),
+            position_update_delay_ms: None,
+        }));
+        assert_eq!(config.process_poll_interval_ms, 80);
+        assert_eq!(config.foreground_delay_ms, 200);
+        assert_eq!(
+            config.position_update_delay_ms,
+            DEFAULT_POSITION_UPDATE_DELAY_MS
+        );
+
+        // Out of range values fall back to the defaults
+        let config = parse_advanced_config(Some(AdvancedConfigFile {
+            process_poll_interval_ms: Some(5),
+            foreground_delay_ms: Some(5000),
+            position_update_delay_ms: Some(0),
+        }));
+        assert_eq!(
+            config.process_poll_interval_ms,
+            DEFAULT_PROCESS_POLL_INTERVAL_MS
+        );
+        assert_eq!(config.foreground_delay_ms, DEFAULT_FOREGROUND_DELAY_MS);
+        assert_eq!(config.position_update_delay_ms, 0);
+    }
+
     #[test]
     fn test_profile_with_hotkey() {
         let profile_files = vec![ProfileFile {
@@ -1128,6 +3639,13 @@ mod tests {
             working_directory: None,
             hotkey: Some("Ctrl+Shift+F2".to_string()),
             title: None,
+            elevated: None,
+            env: None,
+            open: None,
+            nvim_cmd: None,
+            discover: None,
+            max_instances: None,
+            monitor: None,
         }];
         let profiles = parse_profiles(Some(profile_files));
         assert_eq!(profiles.len(), 1);
@@ -1210,6 +3728,212 @@ mod tests {
         assert_eq!(json["profiles"][0]["name"], "Neovim");
     }
 
+    #[test]
+    fn test_portable_dir() {
+        let tmp = TempDir::new("portable");
+        let dir = tmp.path();
+        let exe = dir.join("neovide-tabs.exe");
+
+        assert_eq!(portable_dir(&exe, false), None);
+        assert_eq!(portable_dir(&exe, true), Some(dir.join("config")));
+
+        fs::write(dir.join(PORTABLE_MARKER), "").unwrap();
+        assert_eq!(portable_dir(&exe, false), Some(dir.join("config")));
+    }
+
+    #[test]
+    fn test_config_font() {
+        let config = Config::from_config_file(ConfigFile::default());
+        assert_eq!(config.font_family, DEFAULT_FONT_FAMILY);
+        assert_eq!(config.font_size, DEFAULT_FONT_SIZE);
+
+        let config = Config::from_config_file(ConfigFile {
+            font_family: Some("Yu Gothic UI".to_string()),
+            font_size: Some(13),
+            ..Default::default()
+        });
+        assert_eq!(config.font_family, "Yu Gothic UI");
+        assert_eq!(config.font_size, 13);
+
+        // Blank family and out-of-range sizes use the defaults
+        let config = Config::from_config_file(ConfigFile {
+            font_family: Some(" ".to_string()),
+            font_size: Some(40),
+            ..Default::default()
+        });
+        assert_eq!(config.font_family, DEFAULT_FONT_FAMILY);
+        assert_eq!(config.font_size, DEFAULT_FONT_SIZE);
+    }
+
+    #[test]
+    fn test_directory_overrides() {
+        let tmp = TempDir::new("overrides");
+        let dir = tmp.path();
+        let profile = Profile::default_profile();
+
+        // Without a file the profile is unchanged
+        let merged = profile.with_directory_overrides(dir);
+        assert_eq!(merged.title, profile.title);
+        assert_eq!(merged.icon, profile.icon);
+
+        fs::write(
+            dir.join(DIRECTORY_CONFIG_FILE),
+            "{\n  // Per-repo look\n  \"title\": \"api: %t\",\n  \"icon\": \"assets/icon.png\",\n  \"open\": [\"README.md\"],\n  \"nvim_cmd\": \"!rm -rf ~\"\n}",
+        )
+        .unwrap();
+        let merged = profile.with_directory_overrides(dir);
+        assert_eq!(merged.title, "api: %t");
+        assert_eq!(
+            PathBuf::from(&merged.icon),
+            dir.join("assets").join("icon.png")
+        );
+        assert_eq!(merged.open, vec![dir.join("README.md")]);
+        // Settings that run code are not taken from the directory
+        assert_eq!(merged.nvim_cmd, profile.nvim_cmd);
+        assert_eq!(merged.name, profile.name);
+
+        // An invalid file is ignored
+        fs::write(dir.join(DIRECTORY_CONFIG_FILE), "{ \"title\": ").unwrap();
+        assert_eq!(profile.with_directory_overrides(dir).title, profile.title);
+    }
+
+    #[test]
+    fn test_directory_open_file() {
+        let dir = Path::new("/repo");
+        assert_eq!(
+            directory_open_file(dir, "src/main.rs"),
+            Some(dir.join("src/main.rs"))
+        );
+        assert_eq!(
+            directory_open_file(dir, "./README.md"),
+            Some(dir.join("./README.md"))
+        );
+        // Options for Neovide or nvim, and paths leaving the directory
+        for hostile in [
+            "--neovim-bin=x.exe",
+            "+:!calc",
+            "-u",
+            "../secret.txt",
+            "docs/../../secret.txt",
+            "/etc/passwd",
+            r"\\host\share\x.exe",
+            r"docs\..\..\secret.txt",
+            r"C:\Windows\win.ini",
+            "C:x.exe",
+            "",
+        ] {
+            assert_eq!(directory_open_file(dir, hostile), None, "{}", hostile);
+        }
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*", "neovide-tabs"));
+        assert!(wildcard_match("*-api", "Billing-API"));
+        assert!(wildcard_match("a?c*", "abcdef"));
+        assert!(wildcard_match("*a*b", "xaxxab"));
+        assert!(!wildcard_match("*-api", "api-docs"));
+        assert!(!wildcard_match("a?c", "ac"));
+    }
+
+    #[test]
+    fn test_discover_profiles() {
+        let tmp = TempDir::new("discover");
+        let dir = tmp.path();
+        for name in ["beta", "alpha", ".hidden"] {
+            fs::create_dir_all(dir.join(name)).unwrap();
+        }
+        fs::write(dir.join("notes.txt"), "").unwrap();
+
+        let profile_files = vec![ProfileFile {
+            name: "Projects".to_string(),
+            icon: None,
+            working_directory: Some("~".to_string()),
+            hotkey: Some("Ctrl+Shift+F9".to_string()),
+            title: Some("%w_basename".to_string()),
+            elevated: None,
+            env: None,
+            open: None,
+            nvim_cmd: None,
+            discover: Some(format!("{}/*", dir.display())),
+            max_instances: None,
+            monitor: None,
+        }];
+        let profiles = parse_profiles(Some(profile_files));
+        // The Default profile comes first, then one profile per directory
+        let names: Vec<&str> = profiles.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec![DEFAULT_PROFILE_NAME, "alpha", "beta"]);
+        assert_eq!(profiles[1].working_directory, dir.join("alpha"));
+        assert_eq!(profiles[1].group.as_deref(), Some("Projects"));
+        assert_eq!(profiles[1].title, "%w_basename");
+        assert_eq!(profiles[1].hotkey, None);
+        assert_eq!(profiles[0].group, None);
+
+        let config = Config {
+            profiles,
+            ..Config::default()
+        };
+        assert_eq!(config.profile_groups(), vec!["Projects"]);
+    }
+
+    #[test]
+    fn test_config_color_scheme() {
+        let tmp = TempDir::new("scheme");
+        let path = tmp.path().join("nord.yaml");
+        fs::write(&path, "scheme: \"Nord\"\nbase00: \"2e3440\"\n").unwrap();
+        let scheme = ColorSchemeFile {
+            path: path.to_string_lossy().to_string(),
+            name: None,
+        };
+
+        let config = Config::from_config_file(ConfigFile {
+            color_scheme: Some(scheme.clone()),
+            ..Default::default()
+        });
+        assert_eq!(config.background_color, 0x2e3440);
+
+        // An explicit background color wins over the scheme
+        let config = Config::from_config_file(ConfigFile {
+            background_color: Some("#ff0000".to_string()),
+            color_scheme: Some(scheme),
+            ..Default::default()
+        });
+        assert_eq!(config.background_color, 0xff0000);
+
+        let _ = fs::remove_file(&path);
+
+        // A missing scheme file keeps the default
+        let config = Config::from_config_file(ConfigFile {
+            color_scheme: Some(ColorSchemeFile {
+                path: path.to_string_lossy().to_string(),
+                name: None,
+            }),
+            ..Default::default()
+        });
+        assert_eq!(config.background_color, DEFAULT_BACKGROUND_COLOR);
+    }
+
+    #[test]
+    fn test_storage_dirs_config_dir_override() {
+        assert_eq!(StorageDirs::default().config_dir_override(), None);
+
+        let portable = StorageDirs::portable(PathBuf::from(r"D:\tools\config"));
+        assert_eq!(
+            portable.config_dir_override(),
+            Some(PathBuf::from(r"D:\tools\config"))
+        );
+
+        // A custom config file decides the directory, even in portable mode
+        let custom = StorageDirs {
+            config_file: Some(PathBuf::from("/tmp/setup/custom.jsonc")),
+            ..portable
+        };
+        assert_eq!(
+            custom.config_dir_override(),
+            Some(PathBuf::from("/tmp/setup"))
+        );
+    }
+
     #[test]
     fn test_config_dir_path() {
         let path = config_dir_path();
@@ -1308,6 +4032,439 @@ mod tests {
         assert_eq!(result, "Work");
     }
 
+    #[test]
+    fn test_expand_title_working_directory_basename() {
+        let context = TitleContext {
+            profile_name: "Work",
+            working_directory: &PathBuf::from("/home/user/projects/myapp"),
+            window_title: "",
+        };
+        assert_eq!(expand_title("%w_basename", &context), "myapp");
+        assert_eq!(expand_title("[%w_basename]", &context), "[myapp]");
+    }
+
+    #[test]
+    fn test_expand_title_with_fallback() {
+        let fallback = default_title_fallback();
+        let mut context = TitleContext {
+            profile_name: "Work",
+            working_directory: &PathBuf::from("/home/user/projects/myapp"),
+            window_title: "Neovide",
+        };
+        // Neovide's startup title falls back to the directory name
+        assert_eq!(
+            expand_title_with_fallback("%t", &fallback, &context),
+            "myapp"
+        );
+
+        context.window_title = "";
+        assert_eq!(
+            expand_title_with_fallback("%t", &fallback, &context),
+            "myapp"
+        );
+
+        // A real window title wins
+        context.window_title = "main.rs - Neovim";
+        assert_eq!(
+            expand_title_with_fallback("%t", &fallback, &context),
+            "main.rs - Neovim"
+        );
+
+        // Directory without a last component falls through to the profile name
+        context.window_title = "";
+        context.working_directory = Path::new("/");
+        assert_eq!(
+            expand_title_with_fallback("%t", &fallback, &context),
+            "Work"
+        );
+        assert_eq!(expand_title_with_fallback("%t", &[], &context), "Work");
+    }
+
+    #[test]
+    fn test_config_from_file_reuse_existing_tab() {
+        assert!(!Config::default().reuse_existing_tab);
+
+        let file = ConfigFile {
+            reuse_existing_tab: Some(true),
+            ..Default::default()
+        };
+        assert!(Config::from_config_file(file).reuse_existing_tab);
+    }
+
+    #[test]
+    fn test_parse_tab_style() {
+        assert_eq!(parse_tab_style(None), TabStyle::Normal);
+        assert_eq!(parse_tab_style(Some("normal")), TabStyle::Normal);
+        assert_eq!(parse_tab_style(Some(" Two_Line ")), TabStyle::TwoLine);
+        assert_eq!(parse_tab_style(Some("compact")), TabStyle::Compact);
+        assert_eq!(parse_tab_style(Some("huge")), TabStyle::Normal);
+    }
+
+    #[test]
+    fn test_parse_window_controls() {
+        assert_eq!(parse_window_controls(None), WindowControls::Right);
+        assert_eq!(parse_window_controls(Some(" Left ")), WindowControls::Left);
+        assert_eq!(parse_window_controls(Some("top")), WindowControls::Right);
+
+        let file = ConfigFile {
+            window_controls: Some("left".to_string()),
+            traffic_lights: Some(true),
+            ..Default::default()
+        };
+        let config = Config::from_config_file(file);
+        assert_eq!(config.window_controls, WindowControls::Left);
+        assert!(config.traffic_lights);
+        assert!(!Config::default().traffic_lights);
+    }
+
+    #[test]
+    fn test_config_from_file_window_buttons() {
+        let config = Config::default();
+        assert!(config.show_minimize && config.show_maximize && config.show_close);
+        assert!(!config.hide_titlebar && !config.auto_hide_titlebar);
+
+        let file = ConfigFile {
+            show_minimize: Some(false),
+            show_close: Some(false),
+            hide_titlebar: Some(true),
+            auto_hide_titlebar: Some(true),
+            ..Default::default()
+        };
+        let config = Config::from_config_file(file);
+        assert!(!config.show_minimize && config.show_maximize && !config.show_close);
+        assert!(config.hide_titlebar && config.auto_hide_titlebar);
+    }
+
+    #[test]
+    fn test_parse_swipe_sensitivity() {
+        assert_eq!(parse_swipe_sensitivity(None), SwipeSensitivity::Medium);
+        assert_eq!(parse_swipe_sensitivity(Some("OFF")), SwipeSensitivity::Off);
+        assert_eq!(
+            parse_swipe_sensitivity(Some("high")),
+            SwipeSensitivity::High
+        );
+        assert_eq!(
+            parse_swipe_sensitivity(Some("extreme")),
+            SwipeSensitivity::Medium
+        );
+    }
+
+    #[test]
+    fn test_parse_tab_numbers() {
+        assert_eq!(parse_tab_numbers(None), TabNumbers::Off);
+        assert_eq!(parse_tab_numbers(Some("Hold")), TabNumbers::Hold);
+        assert_eq!(parse_tab_numbers(Some("always")), TabNumbers::Always);
+        assert_eq!(parse_tab_numbers(Some("sometimes")), TabNumbers::Off);
+    }
+
+    #[test]
+    fn test_parse_start_state() {
+        assert_eq!(parse_start_state(None), StartState::Normal);
+        assert_eq!(parse_start_state(Some("Maximized")), StartState::Maximized);
+        assert_eq!(
+            parse_start_state(Some(" minimized ")),
+            StartState::Minimized
+        );
+        assert_eq!(parse_start_state(Some("fullscreen")), StartState::Normal);
+    }
+
+    #[test]
+    fn test_monitor() {
+        let config: ConfigFile = serde_json::from_str(
+            r#"{
+                "monitor": 2,
+                "profiles": [
+                    { "name": "Docs", "monitor": " DELL U2720Q " },
+                    { "name": "Numbered", "monitor": "3" },
+                    { "name": "Zero", "monitor": 0 },
+                    { "name": "Unset" }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let config = Config::from_config_file(config);
+        assert_eq!(config.monitor, Some(MonitorTarget::Number(2)));
+        let monitor = |profile: usize| config.monitor_for(&config.profiles[profile]);
+        assert_eq!(
+            monitor(0),
+            Some(&MonitorTarget::Name("DELL U2720Q".to_string()))
+        );
+        assert_eq!(monitor(1), Some(&MonitorTarget::Number(3)));
+        // Profiles without a (valid) monitor use the top-level one
+        assert_eq!(monitor(2), Some(&MonitorTarget::Number(2)));
+        assert_eq!(monitor(3), Some(&MonitorTarget::Number(2)));
+
+        assert!(config.remember_window_layout);
+
+        assert_eq!(parse_monitor(None), None);
+        assert_eq!(
+            parse_monitor(Some(MonitorFile::Name(" ".to_string()))),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_tiling_wm() {
+        assert_eq!(parse_tiling_wm(None), TilingWm::Off);
+        assert_eq!(parse_tiling_wm(Some("Komorebi")), TilingWm::Komorebi);
+        assert_eq!(parse_tiling_wm(Some(" glazewm ")), TilingWm::GlazeWm);
+        assert_eq!(parse_tiling_wm(Some("other")), TilingWm::Other);
+        assert_eq!(parse_tiling_wm(Some("i3")), TilingWm::Off);
+        assert!(!TilingWm::Off.is_enabled());
+        assert!(TilingWm::Other.is_enabled());
+
+        let file = ConfigFile {
+            tiling_wm: Some("komorebi".to_string()),
+            ..Default::default()
+        };
+        assert!(Config::from_config_file(file).tracks_window_locations());
+        assert!(!Config::default().tracks_window_locations());
+    }
+
+    #[test]
+    fn test_config_from_file_window_class_and_title() {
+        let config = Config::default();
+        assert_eq!(config.window_class, DEFAULT_WINDOW_CLASS);
+        assert_eq!(config.window_title, DEFAULT_WINDOW_TITLE);
+
+        let file = ConfigFile {
+            window_class: Some(" NvimTabs ".to_string()),
+            window_title: Some("Editor".to_string()),
+            ..Default::default()
+        };
+        let config = Config::from_config_file(file);
+        assert_eq!(config.window_class, "NvimTabs");
+        assert_eq!(config.window_title, "Editor");
+
+        let file = ConfigFile {
+            window_class: Some("x".repeat(MAX_WINDOW_CLASS_LENGTH + 1)),
+            window_title: Some("  ".to_string()),
+            ..Default::default()
+        };
+        let config = Config::from_config_file(file);
+        assert_eq!(config.window_class, DEFAULT_WINDOW_CLASS);
+        assert_eq!(config.window_title, DEFAULT_WINDOW_TITLE);
+    }
+
+    #[test]
+    fn test_abbreviate_working_directory() {
+        let home = PathBuf::from("/home/user");
+        let abbreviate = |p: &str| abbreviate_working_directory(Path::new(p), Some(&home));
+        assert_eq!(abbreviate("/home/user"), "~");
+        assert_eq!(
+            abbreviate("/home/user/projects/neovide-tabs"),
+            "~/p/neovide-tabs"
+        );
+        assert_eq!(abbreviate("/home/user/.config/nvim"), "~/.c/nvim");
+        assert_eq!(abbreviate("/srv/www/site"), "/s/w/site");
+        assert_eq!(
+            abbreviate_working_directory(Path::new("D:/work/.config/nvim"), None),
+            "D:/w/.c/nvim"
+        );
+    }
+
+    #[test]
+    fn test_parse_mouse_bindings() {
+        let none = (false, false, false);
+        let config = Config::default();
+        assert_eq!(
+            config.mouse_action(MouseButton::Right, none, MouseArea::Tab),
+            Some(MouseAction::TabMenu)
+        );
+        assert_eq!(
+            config.mouse_action(MouseButton::Right, none, MouseArea::Caption),
+            None
+        );
+
+        let mut bindings = BTreeMap::new();
+        bindings.insert("X1".to_string(), "prev_tab".to_string());
+        bindings.insert("ctrl + Middle@Tab".to_string(), "close_tab".to_string());
+        bindings.insert("Right".to_string(), "profile_menu".to_string());
+        bindings.insert("Right@tab".to_string(), "none".to_string());
+        bindings.insert("Middle@titlebar".to_string(), "new_tab".to_string());
+        bindings.insert("X2".to_string(), "explode".to_string());
+        let config = Config {
+            mouse_bindings: parse_mouse_bindings(Some(bindings)),
+            ..Config::default()
+        };
+        assert_eq!(config.mouse_bindings.len(), 4);
+        assert_eq!(
+            config.mouse_action(MouseButton::X1, none, MouseArea::Caption),
+            Some(MouseAction::PrevTab)
+        );
+        assert_eq!(
+            config.mouse_action(MouseButton::Middle, (true, false, false), MouseArea::Tab),
+            Some(MouseAction::CloseTab)
+        );
+        assert_eq!(
+            config.mouse_action(MouseButton::Middle, none, MouseArea::Tab),
+            None
+        );
+        // The area's own binding (here unbinding the tab menu) wins
+        assert_eq!(
+            config.mouse_action(MouseButton::Right, none, MouseArea::Caption),
+            Some(MouseAction::ProfileMenu)
+        );
+        assert_eq!(
+            config.mouse_action(MouseButton::Right, none, MouseArea::Tab),
+            None
+        );
+        assert_eq!(
+            config.mouse_action(MouseButton::X2, none, MouseArea::Tab),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_tab_double_click() {
+        assert_eq!(parse_tab_double_click(None), TabDoubleClickAction::Rename);
+        assert_eq!(
+            parse_tab_double_click(Some("duplicate")),
+            TabDoubleClickAction::Duplicate
+        );
+        assert_eq!(
+            parse_tab_double_click(Some(" Pin ")),
+            TabDoubleClickAction::TogglePin
+        );
+        assert_eq!(
+            parse_tab_double_click(Some("close")),
+            TabDoubleClickAction::Close
+        );
+        assert_eq!(
+            parse_tab_double_click(Some("explode")),
+            TabDoubleClickAction::Rename
+        );
+    }
+
+    #[test]
+    fn test_config_from_file_tab_attention() {
+        let config = Config::default();
+        assert!(config.tab_attention);
+        assert!(!config.tab_attention_sound);
+
+        let file = ConfigFile {
+            tab_attention: Some(false),
+            tab_attention_sound: Some(true),
+            ..Default::default()
+        };
+        let config = Config::from_config_file(file);
+        assert!(!config.tab_attention);
+        assert!(config.tab_attention_sound);
+    }
+
+    #[test]
+    fn test_config_from_file_restore_foreground_on_exit() {
+        assert!(Config::default().restore_foreground_on_exit);
+
+        let file = ConfigFile {
+            restore_foreground_on_exit: Some(false),
+            ..Default::default()
+        };
+        assert!(!Config::from_config_file(file).restore_foreground_on_exit);
+    }
+
+    #[test]
+    fn test_config_from_file_terminal_command() {
+        assert_eq!(Config::default().terminal_command, DEFAULT_TERMINAL_COMMAND);
+
+        let file = ConfigFile {
+            terminal_command: Some("start pwsh".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            Config::from_config_file(file).terminal_command,
+            "start pwsh"
+        );
+
+        let file = ConfigFile {
+            terminal_command: Some(" ".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            Config::from_config_file(file).terminal_command,
+            DEFAULT_TERMINAL_COMMAND
+        );
+    }
+
+    #[test]
+    fn test_config_from_file_project_icons() {
+        assert!(Config::default().project_icons);
+
+        let file = ConfigFile {
+            project_icons: Some(false),
+            ..Default::default()
+        };
+        assert!(!Config::from_config_file(file).project_icons);
+    }
+
+    #[test]
+    fn test_config_from_file_git_status() {
+        assert!(!Config::default().git_status);
+
+        let file = ConfigFile {
+            git_status: Some(true),
+            ..Default::default()
+        };
+        assert!(Config::from_config_file(file).git_status);
+    }
+
+    #[test]
+    fn test_config_from_file_tab_previews() {
+        assert!(Config::default().tab_previews);
+
+        let file = ConfigFile {
+            tab_previews: Some(false),
+            ..Default::default()
+        };
+        assert!(!Config::from_config_file(file).tab_previews);
+    }
+
+    #[test]
+    fn test_config_from_file_check_updates() {
+        // Off unless the user opts in: it contacts GitHub
+        assert!(!Config::default().check_updates);
+        assert!(!Config::from_config_file(ConfigFile::default()).check_updates);
+
+        let file = ConfigFile {
+            check_updates: Some(true),
+            ..Default::default()
+        };
+        assert!(Config::from_config_file(file).check_updates);
+    }
+
+    #[test]
+    fn test_config_from_file_reduce_motion() {
+        assert_eq!(Config::default().reduce_motion, None);
+
+        let file = ConfigFile {
+            reduce_motion: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(Config::from_config_file(file).reduce_motion, Some(true));
+    }
+
+    #[test]
+    fn test_config_from_file_prompt_profile_on_start() {
+        assert!(!Config::default().prompt_profile_on_start);
+
+        let file = ConfigFile {
+            prompt_profile_on_start: Some(true),
+            ..Default::default()
+        };
+        assert!(Config::from_config_file(file).prompt_profile_on_start);
+    }
+
+    #[test]
+    fn test_config_from_file_title_fallback() {
+        assert_eq!(Config::default().title_fallback, vec!["%w_basename", "%p"]);
+
+        let file = ConfigFile {
+            title_fallback: Some(vec!["%p".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(Config::from_config_file(file).title_fallback, vec!["%p"]);
+    }
+
     #[test]
     fn test_expand_title_window_title() {
         let context = TitleContext {
@@ -1466,6 +4623,13 @@ mod tests {
             working_directory: None,
             hotkey: None,
             title: Some("%p: %w".to_string()),
+            elevated: None,
+            env: None,
+            open: None,
+            nvim_cmd: None,
+            discover: None,
+            max_instances: None,
+            monitor: None,
         }];
         let profiles = parse_profiles(Some(profile_files));
         assert_eq!(profiles[0].title, "%p: %w");
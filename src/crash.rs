@@ -0,0 +1,307 @@
+//! Crash reports.
+//!
+//! Release builds have no console, so a panic or an access violation used to make
+//! the wrapper disappear without a trace. `install` sets a panic hook and an
+//! unhandled exception filter that write a minidump and a text report (cause,
+//! backtrace and the last lines written to stderr) to `crashes` in the data
+//! directory, then offer to open that folder. stderr is routed through a pipe to
+//! keep those lines; output still reaches the original stderr if there is one.
+//!
+//! Only panics of the main (UI) thread end the process. A panic on a worker
+//! thread (update check, git status, output readers) just ends that thread, so it
+//! is logged and ends up in the recent log of a later report instead.
+
+#![cfg(target_os = "windows")]
+
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::mem::ManuallyDrop;
+use std::os::windows::io::{AsRawHandle, FromRawHandle, IntoRawHandle};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::thread::{self, ThreadId};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use windows::Win32::Foundation::{BOOL, HANDLE};
+use windows::Win32::System::Console::{GetStdHandle, STD_ERROR_HANDLE, SetStdHandle};
+use windows::Win32::System::Diagnostics::Debug::{
+    EXCEPTION_POINTERS, MINIDUMP_EXCEPTION_INFORMATION, MINIDUMP_TYPE,
+    MiniDumpWithIndirectlyReferencedMemory, MiniDumpWithThreadInfo, MiniDumpWithUnloadedModules,
+    MiniDumpWriteDump, SetUnhandledExceptionFilter,
+};
+use windows::Win32::System::Threading::{
+    GetCurrentProcess, GetCurrentProcessId, GetCurrentThreadId,
+};
+use windows::Win32::UI::Shell::ShellExecuteW;
+use windows::Win32::UI::WindowsAndMessaging::{
+    IDYES, MB_ICONERROR, MB_YESNO, MessageBoxW, SW_SHOWNORMAL,
+};
+use windows::core::{HSTRING, PCWSTR, w};
+
+use crate::config::data_dir_path;
+
+/// Number of stderr lines kept for the report
+const LOG_LINES: usize = 200;
+
+/// Let the exception continue to Windows Error Reporting after the report
+const EXCEPTION_CONTINUE_SEARCH: i32 = 0;
+
+/// Recent stderr lines, oldest first
+static RECENT_LOG: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Set while a crash is being reported, so a crash in the reporter doesn't recurse
+static REPORTING: AtomicBool = AtomicBool::new(false);
+
+/// The thread that called `install` (the main thread, which runs the UI)
+static MAIN_THREAD: OnceLock<ThreadId> = OnceLock::new();
+
+/// Report panics and unhandled exceptions from now on. Called on the main thread.
+pub fn install() {
+    let _ = MAIN_THREAD.set(thread::current().id());
+    capture_stderr();
+
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        let thread = thread::current();
+        let cause = format!(
+            "Panic in thread '{}': {}",
+            thread.name().unwrap_or("<unnamed>"),
+            info
+        );
+        let backtrace = std::backtrace::Backtrace::force_capture().to_string();
+        if MAIN_THREAD.get() == Some(&thread.id()) {
+            report_crash(&cause, &backtrace, None);
+        } else {
+            // The process keeps running: no crash dialog, and a later crash still
+            // gets reported
+            eprintln!("Crash: {} (not fatal)\n{}", cause, backtrace.trim_end());
+        }
+    }));
+
+    unsafe {
+        SetUnhandledExceptionFilter(Some(exception_filter));
+    }
+}
+
+unsafe extern "system" fn exception_filter(info: *const EXCEPTION_POINTERS) -> i32 {
+    unsafe {
+        let cause = match info.as_ref().and_then(|info| info.ExceptionRecord.as_ref()) {
+            Some(record) => format!(
+                "Unhandled exception 0x{:08X} at {:p}",
+                record.ExceptionCode.0 as u32, record.ExceptionAddress
+            ),
+            None => "Unhandled exception".to_string(),
+        };
+        report_crash(&cause, "", Some(info));
+    }
+    EXCEPTION_CONTINUE_SEARCH
+}
+
+/// Write the minidump and report, then offer to open the crash folder
+fn report_crash(cause: &str, details: &str, exception: Option<*const EXCEPTION_POINTERS>) {
+    if REPORTING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let Some(dir) = crash_dir() else {
+        return;
+    };
+    if let Err(e) = fs::create_dir_all(&dir) {
+        eprintln!("Crash: Failed to create {}: {}", dir.display(), e);
+        return;
+    }
+
+    let stem = report_file_stem(SystemTime::now(), std::process::id());
+    let dump_path = dir.join(format!("{}.dmp", stem));
+    let dump_written = match unsafe { write_minidump(&dump_path, exception) } {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("Crash: Failed to write minidump: {}", e);
+            false
+        }
+    };
+
+    // The crashing thread may hold the log lock; report without the log then
+    let log: Vec<String> = RECENT_LOG
+        .try_lock()
+        .map(|log| log.iter().cloned().collect())
+        .unwrap_or_default();
+    let report = crash_report(cause, details, &log, dump_written.then_some(&*dump_path));
+    let _ = fs::write(dir.join(format!("{}.txt", stem)), report);
+
+    offer_crash_folder(&dir);
+}
+
+/// Minidump of this process with the threads' stacks and the memory they point to
+unsafe fn write_minidump(
+    path: &Path,
+    exception: Option<*const EXCEPTION_POINTERS>,
+) -> windows::core::Result<()> {
+    unsafe {
+        let file = File::create(path)?;
+        let exception_info = exception.map(|pointers| MINIDUMP_EXCEPTION_INFORMATION {
+            ThreadId: GetCurrentThreadId(),
+            ExceptionPointers: pointers as *mut EXCEPTION_POINTERS,
+            ClientPointers: BOOL(0),
+        });
+        let dump_type: MINIDUMP_TYPE = MiniDumpWithThreadInfo
+            | MiniDumpWithUnloadedModules
+            | MiniDumpWithIndirectlyReferencedMemory;
+        MiniDumpWriteDump(
+            GetCurrentProcess(),
+            GetCurrentProcessId(),
+            HANDLE(file.as_raw_handle()),
+            dump_type,
+            exception_info
+                .as_ref()
+                .map(|info| info as *const MINIDUMP_EXCEPTION_INFORMATION),
+            None,
+            None,
+        )
+    }
+}
+
+/// Tell the user and offer to open the folder with the report
+fn offer_crash_folder(dir: &Path) {
+    unsafe {
+        let message = HSTRING::from(format!(
+            "neovide-tabs crashed. A crash report was saved to:\n\n{}\n\n\
+             Open the folder? Attaching its newest files to a bug report helps find the cause.",
+            dir.display()
+        ));
+        if MessageBoxW(None, &message, w!("neovide-tabs"), MB_YESNO | MB_ICONERROR) == IDYES {
+            let dir = HSTRING::from(dir.as_os_str());
+            ShellExecuteW(
+                None,
+                w!("open"),
+                &dir,
+                PCWSTR::null(),
+                PCWSTR::null(),
+                SW_SHOWNORMAL,
+            );
+        }
+    }
+}
+
+/// Route stderr through a pipe whose lines are kept in RECENT_LOG and passed on to
+/// the original stderr (if any)
+fn capture_stderr() {
+    let (reader, writer) = match std::io::pipe() {
+        Ok(pipe) => pipe,
+        Err(e) => {
+            eprintln!("Crash: Failed to capture stderr: {}", e);
+            return;
+        }
+    };
+    let original = unsafe { GetStdHandle(STD_ERROR_HANDLE) }
+        .ok()
+        .filter(|handle| !handle.is_invalid() && !handle.0.is_null())
+        .map(|handle| handle.0 as isize);
+
+    let writer = HANDLE(writer.into_raw_handle());
+    if let Err(e) = unsafe { SetStdHandle(STD_ERROR_HANDLE, writer) } {
+        eprintln!("Crash: Failed to capture stderr: {}", e);
+        return;
+    }
+
+    thread::spawn(move || {
+        // Not closed: the original stderr still belongs to whoever started us
+        let mut original = original.map(|handle| {
+            ManuallyDrop::new(unsafe { File::from_raw_handle(handle as *mut std::ffi::c_void) })
+        });
+        let mut reader = BufReader::new(reader);
+        let mut line = Vec::new();
+        while reader
+            .read_until(b'\n', &mut line)
+            .is_ok_and(|read| read > 0)
+        {
+            if let Some(original) = original.as_mut() {
+                let _ = original.write_all(&line);
+            }
+            let text = String::from_utf8_lossy(&line).trim_end().to_string();
+            let mut log = RECENT_LOG.lock().unwrap();
+            if log.len() == LOG_LINES {
+                log.pop_front();
+            }
+            log.push_back(text);
+            line.clear();
+        }
+    });
+}
+
+/// File name (without extension) of a crash's minidump and report
+fn report_file_stem(time: SystemTime, pid: u32) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+    format!("crash-{}-{}", seconds, pid)
+}
+
+/// Text of a crash report
+fn crash_report(cause: &str, details: &str, log: &[String], dump: Option<&Path>) -> String {
+    let mut report = format!(
+        "neovide-tabs {} crashed\n\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        cause
+    );
+    if !details.trim().is_empty() {
+        report.push_str(&format!("\n{}\n", details.trim_end()));
+    }
+    match dump {
+        Some(dump) => report.push_str(&format!(
+            "\nMinidump: {}\n",
+            dump.file_name()
+                .map(|name| name.to_string_lossy())
+                .unwrap_or_default()
+        )),
+        None => report.push_str("\nMinidump: not written\n"),
+    }
+    report.push_str("\nRecent log:\n");
+    for line in log {
+        report.push_str(line);
+        report.push('\n');
+    }
+    report
+}
+
+/// Folder of the crash reports
+fn crash_dir() -> Option<PathBuf> {
+    data_dir_path().map(|dir| dir.join("crashes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_report_file_stem() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_760_000_000);
+        assert_eq!(report_file_stem(time, 4242), "crash-1760000000-4242");
+    }
+
+    #[test]
+    fn test_crash_report() {
+        let log = vec!["Config: Loading from config.jsonc".to_string()];
+        let report = crash_report(
+            "Panic in thread 'main': boom",
+            "   0: neovide_tabs::main\n",
+            &log,
+            Some(Path::new("crashes/crash-1-2.dmp")),
+        );
+        assert!(report.starts_with(&format!(
+            "neovide-tabs {} crashed\n\nPanic in thread 'main': boom\n",
+            env!("CARGO_PKG_VERSION")
+        )));
+        assert!(report.contains("\n   0: neovide_tabs::main\n"));
+        assert!(report.contains("\nMinidump: crash-1-2.dmp\n"));
+        assert!(report.ends_with("Recent log:\nConfig: Loading from config.jsonc\n"));
+
+        let report = crash_report("Unhandled exception", "", &[], None);
+        assert!(report.contains("\nMinidump: not written\n"));
+        assert!(!report.contains("\n\n\n"));
+    }
+}
@@ -0,0 +1,79 @@
+//! Native shell dialogs used by neovide-tabs.
+//!
+//! Wraps the COM-based common item dialogs so the rest of the application
+//! can work with plain paths.
+
+#![cfg(target_os = "windows")]
+
+use std::path::{Path, PathBuf};
+
+use windows::Win32::Foundation::HWND;
+use windows::Win32::System::Com::{
+    CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED, COINIT_DISABLE_OLE1DDE, CoCreateInstance,
+    CoInitializeEx, CoTaskMemFree, CoUninitialize, IBindCtx,
+};
+use windows::Win32::UI::Shell::{
+    FOS_FORCEFILESYSTEM, FOS_PICKFOLDERS, FileOpenDialog, IFileOpenDialog, IShellItem,
+    SHCreateItemFromParsingName, SIGDN_FILESYSPATH,
+};
+use windows::core::{HSTRING, w};
+
+/// Show the native folder picker (IFileOpenDialog with FOS_PICKFOLDERS).
+/// The dialog starts in `initial_dir` when it exists.
+/// Returns None if the user cancelled or the dialog could not be shown.
+pub fn pick_folder(owner: HWND, initial_dir: Option<&Path>) -> Option<PathBuf> {
+    unsafe {
+        // S_FALSE means COM was already initialized on this thread, which is fine;
+        // every successful call must still be balanced with CoUninitialize
+        let com_initialized =
+            CoInitializeEx(None, COINIT_APARTMENTTHREADED | COINIT_DISABLE_OLE1DDE).is_ok();
+
+        let result = show_folder_dialog(owner, initial_dir);
+
+        if com_initialized {
+            CoUninitialize();
+        }
+
+        match result {
+            Ok(path) => Some(path),
+            Err(e) => {
+                // Cancelling the dialog is reported as an error HRESULT as well
+                eprintln!("Folder picker: no folder selected ({})", e);
+                None
+            }
+        }
+    }
+}
+
+/// Create, configure and show the folder dialog, returning the selected path
+unsafe fn show_folder_dialog(
+    owner: HWND,
+    initial_dir: Option<&Path>,
+) -> windows::core::Result<PathBuf> {
+    unsafe {
+        let dialog: IFileOpenDialog =
+            CoCreateInstance(&FileOpenDialog, None, CLSCTX_INPROC_SERVER)?;
+
+        let options = dialog.GetOptions()?;
+        dialog.SetOptions(options | FOS_PICKFOLDERS | FOS_FORCEFILESYSTEM)?;
+        dialog.SetTitle(w!("New tab in folder"))?;
+
+        if let Some(dir) = initial_dir.filter(|d| d.is_dir()) {
+            let dir_wide = HSTRING::from(dir.as_os_str());
+            if let Ok(item) =
+                SHCreateItemFromParsingName::<_, _, IShellItem>(&dir_wide, None::<&IBindCtx>)
+            {
+                let _ = dialog.SetFolder(&item);
+            }
+        }
+
+        dialog.Show(owner)?;
+
+        let item = dialog.GetResult()?;
+        let raw_path = item.GetDisplayName(SIGDN_FILESYSPATH)?;
+        let path = String::from_utf16_lossy(raw_path.as_wide());
+        CoTaskMemFree(Some(raw_path.0 as *const std::ffi::c_void));
+
+        Ok(PathBuf::from(path))
+    }
+}
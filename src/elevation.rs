@@ -0,0 +1,596 @@
+//! Elevated Neovide for profiles with `elevated: true`.
+//!
+//! Windows keeps a normal process from moving, showing or closing the windows of
+//! an elevated one (User Interface Privilege Isolation): the calls fail or the
+//! messages are silently dropped. An elevated tab is therefore started through an
+//! elevated copy of the wrapper (`--elevated-proxy`, one UAC prompt per tab) that
+//! launches Neovide and carries out the wrapper's window commands on it. The proxy
+//! lets those commands through UIPI with ChangeWindowMessageFilterEx, keeps Neovide
+//! in a job object so it dies with the proxy, and exits when Neovide does.
+//!
+//! The wrapper finds the Neovide window the usual way (by process ID, here of the
+//! proxy's child process) and tells the proxy which window it is; only changing
+//! the window goes through the proxy.
+
+#![cfg(target_os = "windows")]
+
+use anyhow::{Context, Result, bail};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::thread;
+use windows::Win32::Foundation::{
+    CloseHandle, ERROR_CANCELLED, HANDLE, HWND, LPARAM, LRESULT, WAIT_OBJECT_0, WPARAM,
+};
+use windows::Win32::System::JobObjects::{
+    AssignProcessToJobObject, CreateJobObjectW, JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    JOBOBJECT_EXTENDED_LIMIT_INFORMATION, JobObjectExtendedLimitInformation,
+    SetInformationJobObject,
+};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::System::Threading::{
+    GetProcessId, OpenProcess, PROCESS_SET_QUOTA, PROCESS_TERMINATE, TerminateProcess,
+    WaitForSingleObject,
+};
+use windows::Win32::UI::Shell::{
+    IsUserAnAdmin, SEE_MASK_NOASYNC, SEE_MASK_NOCLOSEPROCESS, SHELLEXECUTEINFOW, ShellExecuteExW,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    AllowSetForegroundWindow, ChangeWindowMessageFilterEx, CreateWindowExW, DefWindowProcW,
+    DestroyWindow, DispatchMessageW, FindWindowExW, GetMessageW, GetWindowThreadProcessId,
+    HWND_MESSAGE, MSG, MSGFLT_ALLOW, PostMessageW, PostQuitMessage, RegisterClassW, SW_HIDE,
+    TranslateMessage, WINDOW_EX_STYLE, WINDOW_STYLE, WM_APP, WM_CLOSE, WM_DESTROY, WNDCLASSW,
+};
+use windows::core::{HSTRING, PCWSTR, w};
+
+use crate::process::{apply_window_command, neovide_command};
+
+/// Command line flag that runs the wrapper executable as an elevated proxy
+pub const PROXY_FLAG: &str = "--elevated-proxy";
+
+/// Class of the proxy's message-only window, found by the wrapper per proxy process
+const PROXY_CLASS_NAME: PCWSTR = w!("NeovideTabsElevatedProxy");
+
+/// Sent by the wrapper to the proxy window: WPARAM is the command, LPARAM its argument
+const WM_PROXY_COMMAND: u32 = WM_APP + 17;
+
+/// Command codes in the WPARAM of WM_PROXY_COMMAND
+const COMMAND_MOVE: usize = 1;
+const COMMAND_SHOW: usize = 2;
+const COMMAND_HIDE: usize = 3;
+const COMMAND_FOREGROUND: usize = 4;
+const COMMAND_CLOSE: usize = 5;
+const COMMAND_ALT_TAB: usize = 6;
+const COMMAND_ATTACH: usize = 7;
+const COMMAND_TOPMOST: usize = 8;
+
+/// How long terminating a proxy may take before it is considered stuck
+const PROXY_EXIT_TIMEOUT_MS: u32 = 5000;
+
+/// Neovide window of this proxy process (0 until the wrapper attaches it)
+static NEOVIDE_HWND: AtomicUsize = AtomicUsize::new(0);
+/// Process ID of the Neovide started by this proxy
+static NEOVIDE_PID: AtomicU32 = AtomicU32::new(0);
+
+/// A change to a Neovide window, carried out directly or through the proxy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowCommand {
+    /// Move and resize the window (screen coordinates)
+    Move {
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    },
+    Show,
+    Hide,
+    /// Bring the window to the foreground
+    Foreground,
+    /// Ask Neovide to quit (WM_CLOSE)
+    Close,
+    /// Show (true) or hide the window in Alt-Tab and the taskbar
+    AltTabVisible(bool),
+    /// Tell the proxy which window (HWND value) is its Neovide's; sent first
+    Attach(usize),
+    /// Keep the window above non-topmost windows (true) or not
+    Topmost(bool),
+}
+
+impl WindowCommand {
+    /// WPARAM and LPARAM of the command's WM_PROXY_COMMAND. Move packs its four
+    /// values as 16-bit fields, which covers any screen coordinate.
+    fn encode(self) -> (usize, isize) {
+        match self {
+            WindowCommand::Move {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                let packed = [x, y, width, height]
+                    .iter()
+                    .fold(0u64, |acc, &v| (acc << 16) | (v as i16 as u16 as u64));
+                (COMMAND_MOVE, packed as isize)
+            }
+            WindowCommand::Show => (COMMAND_SHOW, 0),
+            WindowCommand::Hide => (COMMAND_HIDE, 0),
+            WindowCommand::Foreground => (COMMAND_FOREGROUND, 0),
+            WindowCommand::Close => (COMMAND_CLOSE, 0),
+            WindowCommand::AltTabVisible(visible) => (COMMAND_ALT_TAB, visible as isize),
+            WindowCommand::Attach(hwnd) => (COMMAND_ATTACH, hwnd as isize),
+            WindowCommand::Topmost(topmost) => (COMMAND_TOPMOST, topmost as isize),
+        }
+    }
+
+    /// The command sent as WM_PROXY_COMMAND with `wparam` and `lparam`
+    fn decode(wparam: usize, lparam: isize) -> Option<Self> {
+        let field = |shift: u32| ((lparam as u64 >> shift) as u16) as i16 as i32;
+        match wparam {
+            COMMAND_MOVE => Some(WindowCommand::Move {
+                x: field(48),
+                y: field(32),
+                width: field(16),
+                height: field(0),
+            }),
+            COMMAND_SHOW => Some(WindowCommand::Show),
+            COMMAND_HIDE => Some(WindowCommand::Hide),
+            COMMAND_FOREGROUND => Some(WindowCommand::Foreground),
+            COMMAND_CLOSE => Some(WindowCommand::Close),
+            COMMAND_ALT_TAB => Some(WindowCommand::AltTabVisible(lparam != 0)),
+            COMMAND_ATTACH => Some(WindowCommand::Attach(lparam as usize)),
+            COMMAND_TOPMOST => Some(WindowCommand::Topmost(lparam != 0)),
+            _ => None,
+        }
+    }
+}
+
+/// How the proxy starts Neovide (passed on its command line)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProxyArgs {
+    pub width: u32,
+    pub height: u32,
+    pub working_directory: Option<PathBuf>,
+    /// Address nvim listens on for RPC
+    pub listen_address: Option<String>,
+    /// Environment variables for Neovide (an elevated process doesn't inherit the
+    /// wrapper's environment, so they travel on the command line)
+    pub env: Vec<(String, String)>,
+    /// Files Neovide opens
+    pub files: Vec<PathBuf>,
+    /// Command nvim runs at startup
+    pub nvim_cmd: Option<String>,
+}
+
+/// Command line of the proxy (after the executable) for `args`
+fn proxy_command_line(args: &ProxyArgs) -> String {
+    let mut parts = vec![
+        PROXY_FLAG.to_string(),
+        "--size".to_string(),
+        format!("{}x{}", args.width, args.height),
+    ];
+    if let Some(dir) = &args.working_directory {
+        parts.push("--cwd".to_string());
+        parts.push(quote_argument(&dir.to_string_lossy()));
+    }
+    if let Some(address) = &args.listen_address {
+        parts.push("--listen".to_string());
+        parts.push(quote_argument(address));
+    }
+    for (name, value) in &args.env {
+        parts.push("--env".to_string());
+        parts.push(quote_argument(&format!("{}={}", name, value)));
+    }
+    for file in &args.files {
+        parts.push("--file".to_string());
+        parts.push(quote_argument(&file.to_string_lossy()));
+    }
+    if let Some(nvim_cmd) = &args.nvim_cmd {
+        parts.push("--nvim-cmd".to_string());
+        parts.push(quote_argument(nvim_cmd));
+    }
+    parts.join(" ")
+}
+
+/// Parse the proxy's arguments (those after `--elevated-proxy`)
+pub fn parse_proxy_args(args: &[String]) -> Result<ProxyArgs> {
+    let mut parsed = ProxyArgs::default();
+    let mut iter = args.iter();
+    while let Some(option) = iter.next() {
+        let Some(value) = iter.next() else {
+            bail!("{} requires a value", option);
+        };
+        match option.as_str() {
+            "--size" => {
+                let (width, height) = value
+                    .split_once('x')
+                    .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                    .with_context(|| format!("Invalid size '{}'", value))?;
+                parsed.width = width;
+                parsed.height = height;
+            }
+            "--cwd" => parsed.working_directory = Some(PathBuf::from(value)),
+            "--listen" => parsed.listen_address = Some(value.clone()),
+            "--env" => {
+                let (name, value) = value
+                    .split_once('=')
+                    .with_context(|| format!("Invalid environment variable '{}'", value))?;
+                parsed.env.push((name.to_string(), value.to_string()));
+            }
+            "--file" => parsed.files.push(PathBuf::from(value)),
+            "--nvim-cmd" => parsed.nvim_cmd = Some(value.clone()),
+            _ => bail!("Unknown elevated proxy option '{}'", option),
+        }
+    }
+    Ok(parsed)
+}
+
+/// Quote `arg` for a Windows command line so it is parsed back as one argument
+/// (backslashes only need doubling before a quote)
+fn quote_argument(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '"']) {
+        return arg.to_string();
+    }
+    let mut quoted = String::from('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        if c == '\\' {
+            backslashes += 1;
+            continue;
+        }
+        let escapes = if c == '"' {
+            backslashes * 2 + 1
+        } else {
+            backslashes
+        };
+        quoted.push_str(&"\\".repeat(escapes));
+        quoted.push(c);
+        backslashes = 0;
+    }
+    // Backslashes before the closing quote are doubled so it stays a quote
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+/// Check whether the wrapper itself already runs as administrator (then elevated
+/// profiles need no proxy)
+pub fn is_elevated() -> bool {
+    unsafe { IsUserAnAdmin().as_bool() }
+}
+
+/// An elevated proxy process started for a tab
+pub struct ProxyProcess {
+    /// Process handle from ShellExecuteEx (stored raw so the process is Send)
+    handle: isize,
+    pid: u32,
+}
+
+impl ProxyProcess {
+    /// Start an elevated proxy running Neovide as described by `args`. Shows the
+    /// UAC prompt; declining it is reported as an error.
+    pub fn launch(args: &ProxyArgs) -> Result<Self> {
+        let exe = std::env::current_exe().context("Failed to locate neovide-tabs executable")?;
+        let exe = HSTRING::from(exe.as_os_str());
+        let parameters = HSTRING::from(proxy_command_line(args));
+        eprintln!("Starting elevated proxy: {} {}", exe, parameters);
+
+        let mut info = SHELLEXECUTEINFOW {
+            cbSize: std::mem::size_of::<SHELLEXECUTEINFOW>() as u32,
+            fMask: SEE_MASK_NOCLOSEPROCESS | SEE_MASK_NOASYNC,
+            lpVerb: w!("runas"),
+            lpFile: PCWSTR(exe.as_ptr()),
+            lpParameters: PCWSTR(parameters.as_ptr()),
+            nShow: SW_HIDE.0,
+            ..Default::default()
+        };
+        unsafe {
+            if let Err(e) = ShellExecuteExW(&mut info) {
+                if e.code() == ERROR_CANCELLED.to_hresult() {
+                    bail!("Elevation was declined at the UAC prompt");
+                }
+                return Err(e).context("Failed to start the elevated proxy");
+            }
+            if info.hProcess.is_invalid() {
+                bail!("The elevated proxy started without a process handle");
+            }
+            Ok(Self {
+                handle: info.hProcess.0 as isize,
+                pid: GetProcessId(info.hProcess),
+            })
+        }
+    }
+
+    fn handle(&self) -> HANDLE {
+        HANDLE(self.handle as *mut _)
+    }
+
+    /// Process ID of the proxy
+    pub fn id(&self) -> u32 {
+        self.pid
+    }
+
+    /// Check whether the proxy (and with it Neovide) has exited
+    pub fn has_exited(&self) -> bool {
+        unsafe { WaitForSingleObject(self.handle(), 0) == WAIT_OBJECT_0 }
+    }
+
+    /// Terminate the proxy; closing its job object takes Neovide down with it
+    pub fn kill(&self) -> Result<()> {
+        unsafe {
+            TerminateProcess(self.handle(), 1).context("Failed to terminate elevated proxy")?;
+            if WaitForSingleObject(self.handle(), PROXY_EXIT_TIMEOUT_MS) != WAIT_OBJECT_0 {
+                bail!("Elevated proxy {} did not exit", self.pid);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ProxyProcess {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseHandle(self.handle());
+        }
+    }
+}
+
+/// Command window of the proxy process `proxy_pid`, once it has been created
+pub fn find_proxy_window(proxy_pid: u32) -> Option<HWND> {
+    let mut after = HWND::default();
+    unsafe {
+        while let Ok(hwnd) = FindWindowExW(HWND_MESSAGE, after, PROXY_CLASS_NAME, None) {
+            let mut pid = 0;
+            GetWindowThreadProcessId(hwnd, Some(&mut pid));
+            if pid == proxy_pid {
+                return Some(hwnd);
+            }
+            after = hwnd;
+        }
+    }
+    None
+}
+
+/// Send `command` to the proxy window `proxy_hwnd`. Returns false if it could not
+/// be posted (e.g. the proxy has exited).
+pub fn send_command(proxy_hwnd: HWND, proxy_pid: u32, command: WindowCommand) -> bool {
+    // Only the foreground process may hand the foreground to another one
+    if command == WindowCommand::Foreground {
+        unsafe {
+            let _ = AllowSetForegroundWindow(proxy_pid);
+        }
+    }
+    let (wparam, lparam) = command.encode();
+    unsafe { PostMessageW(proxy_hwnd, WM_PROXY_COMMAND, WPARAM(wparam), LPARAM(lparam)).is_ok() }
+}
+
+/// Run as the elevated proxy: start Neovide, carry out the wrapper's window
+/// commands and exit once Neovide has exited
+pub fn run_proxy(args: &[String]) -> Result<()> {
+    let args = parse_proxy_args(args)?;
+    let job = create_kill_on_close_job()?;
+
+    let mut child = neovide_command(
+        args.width,
+        args.height,
+        args.working_directory.as_deref(),
+        args.listen_address.as_deref(),
+        &args.env,
+        &args.files,
+        args.nvim_cmd.as_deref(),
+    )
+    .spawn()
+    .context("Failed to spawn Neovide process")?;
+    unsafe {
+        let assigned = OpenProcess(PROCESS_SET_QUOTA | PROCESS_TERMINATE, false, child.id())
+            .and_then(|process| {
+                let result = AssignProcessToJobObject(job, process);
+                let _ = CloseHandle(process);
+                result
+            });
+        if let Err(e) = assigned {
+            eprintln!("Failed to tie Neovide to the elevated proxy: {}", e);
+        }
+    }
+
+    let hwnd = create_proxy_window()?;
+    let hwnd_raw = hwnd.0 as usize;
+    NEOVIDE_PID.store(child.id(), Ordering::Relaxed);
+    thread::spawn(move || {
+        let _ = child.wait();
+        unsafe {
+            let _ = PostMessageW(HWND(hwnd_raw as *mut _), WM_CLOSE, None, None);
+        }
+    });
+
+    unsafe {
+        let mut msg = MSG::default();
+        while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+        let _ = CloseHandle(job);
+    }
+    Ok(())
+}
+
+/// Job object that terminates its processes when its last handle is closed
+fn create_kill_on_close_job() -> Result<HANDLE> {
+    unsafe {
+        let job = CreateJobObjectW(None, None).context("Failed to create job object")?;
+        let mut limits = JOBOBJECT_EXTENDED_LIMIT_INFORMATION::default();
+        limits.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+        SetInformationJobObject(
+            job,
+            JobObjectExtendedLimitInformation,
+            &limits as *const _ as *const _,
+            std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+        )
+        .context("Failed to configure job object")?;
+        Ok(job)
+    }
+}
+
+/// Create the proxy's message-only command window and let the wrapper's
+/// (lower-integrity) commands through UIPI
+fn create_proxy_window() -> Result<HWND> {
+    unsafe {
+        let hinstance = GetModuleHandleW(None).context("Failed to get module handle")?;
+        let wc = WNDCLASSW {
+            lpfnWndProc: Some(proxy_window_proc),
+            hInstance: hinstance.into(),
+            lpszClassName: PROXY_CLASS_NAME,
+            ..Default::default()
+        };
+        if RegisterClassW(&wc) == 0 {
+            bail!("Failed to register the elevated proxy window class");
+        }
+        let hwnd = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            PROXY_CLASS_NAME,
+            w!(""),
+            WINDOW_STYLE::default(),
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            None,
+            hinstance,
+            None,
+        )
+        .context("Failed to create the elevated proxy window")?;
+        ChangeWindowMessageFilterEx(hwnd, WM_PROXY_COMMAND, MSGFLT_ALLOW, None)
+            .context("Failed to allow wrapper commands through UIPI")?;
+        Ok(hwnd)
+    }
+}
+
+/// Accept `hwnd` as the Neovide window if it belongs to the proxy's Neovide, so
+/// the wrapper can't have the proxy act on other elevated windows
+fn attach_window(hwnd: HWND) {
+    let mut pid = 0;
+    unsafe {
+        GetWindowThreadProcessId(hwnd, Some(&mut pid));
+    }
+    if pid != 0 && pid == NEOVIDE_PID.load(Ordering::Relaxed) {
+        NEOVIDE_HWND.store(hwnd.0 as usize, Ordering::Relaxed);
+    } else {
+        eprintln!(
+            "Elevated proxy ignored window 0x{:X} of another process",
+            hwnd.0 as usize
+        );
+    }
+}
+
+unsafe extern "system" fn proxy_window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match msg {
+            WM_PROXY_COMMAND => {
+                let neovide_hwnd = NEOVIDE_HWND.load(Ordering::Relaxed);
+                match WindowCommand::decode(wparam.0, lparam.0) {
+                    Some(WindowCommand::Attach(window)) => attach_window(HWND(window as *mut _)),
+                    Some(command) if neovide_hwnd != 0 => {
+                        if let Err(e) = apply_window_command(HWND(neovide_hwnd as *mut _), command)
+                        {
+                            eprintln!("Elevated proxy failed to apply {:?}: {}", command, e);
+                        }
+                    }
+                    _ => {}
+                }
+                LRESULT(0)
+            }
+            WM_CLOSE => {
+                let _ = DestroyWindow(hwnd);
+                LRESULT(0)
+            }
+            WM_DESTROY => {
+                PostQuitMessage(0);
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_command_round_trip() {
+        let commands = [
+            WindowCommand::Move {
+                x: -1920,
+                y: 40,
+                width: 2560,
+                height: 1400,
+            },
+            WindowCommand::Show,
+            WindowCommand::Hide,
+            WindowCommand::Foreground,
+            WindowCommand::Close,
+            WindowCommand::AltTabVisible(false),
+            WindowCommand::AltTabVisible(true),
+            WindowCommand::Attach(0x1_2345_678a),
+            WindowCommand::Topmost(true),
+            WindowCommand::Topmost(false),
+        ];
+        for command in commands {
+            let (wparam, lparam) = command.encode();
+            assert_eq!(WindowCommand::decode(wparam, lparam), Some(command));
+        }
+        assert_eq!(WindowCommand::decode(0, 0), None);
+    }
+
+    #[test]
+    fn test_quote_argument() {
+        assert_eq!(quote_argument(r"C:\work"), r"C:\work");
+        assert_eq!(quote_argument(r"C:\My Projects"), r#""C:\My Projects""#);
+        assert_eq!(quote_argument(r"C:\My Projects\"), r#""C:\My Projects\\""#);
+        assert_eq!(quote_argument(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(quote_argument(""), r#""""#);
+    }
+
+    #[test]
+    fn test_proxy_args() {
+        let args = ProxyArgs {
+            width: 1200,
+            height: 800,
+            working_directory: Some(PathBuf::from(r"C:\Program Files\app")),
+            listen_address: Some(r"\\.\pipe\neovide-tabs-1-2".to_string()),
+            env: vec![("PATH".to_string(), r"C:\My Tools;C:\bin".to_string())],
+            files: vec![PathBuf::from(r"C:\Windows\System32\drivers\etc\hosts")],
+            nvim_cmd: Some("Telescope find_files".to_string()),
+        };
+        assert_eq!(
+            proxy_command_line(&args),
+            r#"--elevated-proxy --size 1200x800 --cwd "C:\Program Files\app" --listen \\.\pipe\neovide-tabs-1-2 --env "PATH=C:\My Tools;C:\bin" --file C:\Windows\System32\drivers\etc\hosts --nvim-cmd "Telescope find_files""#
+        );
+
+        let parsed = parse_proxy_args(&[
+            "--size".to_string(),
+            "1200x800".to_string(),
+            "--cwd".to_string(),
+            r"C:\Program Files\app".to_string(),
+            "--listen".to_string(),
+            r"\\.\pipe\neovide-tabs-1-2".to_string(),
+            "--env".to_string(),
+            r"PATH=C:\My Tools;C:\bin".to_string(),
+            "--file".to_string(),
+            r"C:\Windows\System32\drivers\etc\hosts".to_string(),
+            "--nvim-cmd".to_string(),
+            "Telescope find_files".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(parsed, args);
+
+        assert!(parse_proxy_args(&["--size".to_string(), "big".to_string()]).is_err());
+        assert!(parse_proxy_args(&["--size".to_string()]).is_err());
+        assert!(parse_proxy_args(&["--bogus".to_string(), "1".to_string()]).is_err());
+        assert!(parse_proxy_args(&["--env".to_string(), "PATH".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,624 @@
+//! Opening folders and files from Explorer and the command line.
+//!
+//! `neovide-tabs install-shell-extension` adds "Open in neovide-tabs" to the
+//! context menu of folders (and of a folder's background) for the current user.
+//! The menu entry starts `neovide-tabs --open-dir <folder>`; `neovide-tabs open
+//! <path>` opens a file or folder for editor associations. Either hands the request
+//! to the running instance with WM_COPYDATA and exits, or starts a new instance
+//! with a tab for it if none is running.
+//!
+//! `neovide-tabs install-file-association` registers the wrapper as an "Open with"
+//! choice for source files (a ProgID and an `Applications` entry, both running
+//! `neovide-tabs open "%1"`), so double-clicking a file lands in the right tab.
+//!
+//! `neovide-tabs install-autostart [--minimized]` starts the wrapper when the
+//! current user logs in, through a value in the `Run` key.
+
+#![cfg(target_os = "windows")]
+
+use anyhow::{Context, Result, bail};
+use std::path::{Path, PathBuf};
+use windows::Win32::Foundation::{ERROR_FILE_NOT_FOUND, LPARAM, WPARAM};
+use windows::Win32::System::DataExchange::COPYDATASTRUCT;
+use windows::Win32::System::Registry::{
+    HKEY, HKEY_CURRENT_USER, KEY_READ, KEY_WRITE, REG_OPTION_NON_VOLATILE, REG_SZ, RegCloseKey,
+    RegCreateKeyExW, RegDeleteKeyValueW, RegDeleteTreeW, RegEnumValueW, RegOpenKeyExW,
+    RegSetValueExW,
+};
+use windows::Win32::UI::Shell::{SHCNE_ASSOCCHANGED, SHCNF_IDLIST, SHChangeNotify};
+use windows::Win32::UI::WindowsAndMessaging::{
+    AllowSetForegroundWindow, FindWindowW, GetWindowThreadProcessId, SMTO_ABORTIFHUNG,
+    SendMessageTimeoutW, WM_COPYDATA,
+};
+use windows::core::{HSTRING, PCWSTR, PWSTR};
+
+use crate::projects::find_project_root;
+
+/// Command line option naming a folder to open a tab in
+pub const OPEN_DIR_FLAG: &str = "--open-dir";
+
+/// Identify the kinds of open requests among WM_COPYDATA messages
+const OPEN_DIR_COPYDATA: usize = 0x4E54_0001;
+const OPEN_PATH_COPYDATA: usize = 0x4E54_0002;
+
+/// How long to wait for the running instance to take the folder
+const SEND_TIMEOUT_MS: u32 = 5000;
+
+/// Context menu verbs for right-clicking a folder and a folder's background
+const MENU_KEYS: [&str; 2] = [
+    r"Software\Classes\Directory\shell\NeovideTabs",
+    r"Software\Classes\Directory\Background\shell\NeovideTabs",
+];
+
+/// Text of the context menu entry
+const MENU_TEXT: &str = "Open in neovide-tabs";
+
+/// ProgID files associated with neovide-tabs point to
+const PROG_ID: &str = "NeovideTabs.File";
+
+/// Key of the `neovide-tabs://` link scheme
+const PROTOCOL_KEY: &str = r"Software\Classes\neovide-tabs";
+
+/// Scheme of links opening files in neovide-tabs
+const URI_SCHEME: &str = "neovide-tabs";
+
+/// Key of the ProgID
+const PROG_ID_KEY: &str = r"Software\Classes\NeovideTabs.File";
+
+/// "Open with" entry of the executable
+const APPLICATION_KEY: &str = r"Software\Classes\Applications\neovide-tabs.exe";
+
+/// Key whose values Windows runs when the current user logs in
+const RUN_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Run";
+
+/// Name of the wrapper's value in the Run key
+const RUN_VALUE: &str = "neovide-tabs";
+
+/// Extensions associated when `install-file-association` is given none
+pub const DEFAULT_FILE_EXTENSIONS: [&str; 20] = [
+    "txt", "md", "rs", "toml", "json", "jsonc", "yaml", "yml", "lua", "vim", "py", "js", "ts",
+    "go", "c", "h", "cpp", "hpp", "sh", "ps1",
+];
+
+/// Add the context menu entries for the current user, starting `exe`
+pub fn install(exe: &Path) -> Result<()> {
+    let exe = exe.to_string_lossy();
+    for key in MENU_KEYS {
+        set_registry_values(
+            key,
+            &[(None, MENU_TEXT), (Some("Icon"), &format!("\"{}\",0", exe))],
+        )?;
+        set_registry_values(&format!(r"{}\command", key), &[(None, &menu_command(&exe))])?;
+    }
+    Ok(())
+}
+
+/// Remove the context menu entries of the current user
+pub fn uninstall() -> Result<()> {
+    for key in MENU_KEYS {
+        delete_registry_tree(key)?;
+    }
+    Ok(())
+}
+
+/// Register neovide-tabs, started from `exe`, as an "Open with" choice for files
+/// with `extensions` (with or without the leading dot). Returns the extensions
+/// registered.
+pub fn install_file_association(exe: &Path, extensions: &[String]) -> Result<Vec<String>> {
+    let exe = exe.to_string_lossy();
+    let extensions = normalize_extensions(extensions);
+    let icon = format!("\"{}\",0", exe);
+    let command = open_command(&exe);
+
+    set_registry_values(PROG_ID_KEY, &[(None, "Source file (neovide-tabs)")])?;
+    set_registry_values(&format!(r"{}\DefaultIcon", PROG_ID_KEY), &[(None, &icon)])?;
+    set_registry_values(
+        &format!(r"{}\shell\open\command", PROG_ID_KEY),
+        &[(None, &command)],
+    )?;
+
+    set_registry_values(
+        APPLICATION_KEY,
+        &[(Some("FriendlyAppName"), "neovide-tabs")],
+    )?;
+    set_registry_values(
+        &format!(r"{}\DefaultIcon", APPLICATION_KEY),
+        &[(None, &icon)],
+    )?;
+    set_registry_values(
+        &format!(r"{}\shell\open\command", APPLICATION_KEY),
+        &[(None, &command)],
+    )?;
+
+    for extension in &extensions {
+        set_registry_values(
+            &format!(r"{}\SupportedTypes", APPLICATION_KEY),
+            &[(Some(extension), "")],
+        )?;
+        set_registry_values(
+            &format!(r"Software\Classes\{}\OpenWithProgids", extension),
+            &[(Some(PROG_ID), "")],
+        )?;
+    }
+    notify_association_change();
+    Ok(extensions)
+}
+
+/// Remove the file association of the current user (for every extension it was
+/// registered for)
+pub fn uninstall_file_association() -> Result<()> {
+    for extension in registry_value_names(&format!(r"{}\SupportedTypes", APPLICATION_KEY)) {
+        let key = HSTRING::from(format!(r"Software\Classes\{}\OpenWithProgids", extension));
+        // Other programs' entries for the extension stay
+        let _ = unsafe { RegDeleteKeyValueW(HKEY_CURRENT_USER, &key, &HSTRING::from(PROG_ID)) };
+    }
+    for key in [PROG_ID_KEY, APPLICATION_KEY] {
+        delete_registry_tree(key)?;
+    }
+    notify_association_change();
+    Ok(())
+}
+
+/// Start `exe` when the current user logs in, minimized if `minimized`
+pub fn install_autostart(exe: &Path, minimized: bool) -> Result<()> {
+    set_registry_values(
+        RUN_KEY,
+        &[(
+            Some(RUN_VALUE),
+            &autostart_command(&exe.to_string_lossy(), minimized),
+        )],
+    )
+}
+
+/// Stop starting neovide-tabs at login
+pub fn uninstall_autostart() -> Result<()> {
+    let result = unsafe {
+        RegDeleteKeyValueW(
+            HKEY_CURRENT_USER,
+            &HSTRING::from(RUN_KEY),
+            &HSTRING::from(RUN_VALUE),
+        )
+    };
+    // Not installed is as good as removed
+    if result.is_err() && result != ERROR_FILE_NOT_FOUND {
+        result
+            .ok()
+            .with_context(|| format!("Failed to remove {} from HKCU\\{}", RUN_VALUE, RUN_KEY))?;
+    }
+    Ok(())
+}
+
+/// Command Windows runs at login
+fn autostart_command(exe: &str, minimized: bool) -> String {
+    if minimized {
+        format!("\"{}\" --minimized", exe)
+    } else {
+        format!("\"{}\"", exe)
+    }
+}
+
+/// Extensions as the registry names them: lowercase with a leading dot, no duplicates
+fn normalize_extensions(extensions: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for extension in extensions {
+        let extension = extension.trim().trim_start_matches('.').to_lowercase();
+        let extension = format!(".{}", extension);
+        if extension.len() > 1 && !normalized.contains(&extension) {
+            normalized.push(extension);
+        }
+    }
+    normalized
+}
+
+/// Command Explorer runs to open a file with neovide-tabs
+fn open_command(exe: &str) -> String {
+    format!("\"{}\" open \"%1\"", exe)
+}
+
+/// Tell Explorer that file associations changed
+fn notify_association_change() {
+    unsafe { SHChangeNotify(SHCNE_ASSOCCHANGED, SHCNF_IDLIST, None, None) };
+}
+
+/// Names of the values of HKCU\`key` (none if it doesn't exist)
+fn registry_value_names(key: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    unsafe {
+        let mut hkey = HKEY::default();
+        if RegOpenKeyExW(
+            HKEY_CURRENT_USER,
+            &HSTRING::from(key),
+            0,
+            KEY_READ,
+            &mut hkey,
+        )
+        .is_err()
+        {
+            return names;
+        }
+        let mut buffer = [0u16; 256];
+        for index in 0.. {
+            let mut len = buffer.len() as u32;
+            let result = RegEnumValueW(
+                hkey,
+                index,
+                PWSTR(buffer.as_mut_ptr()),
+                &mut len,
+                None,
+                None,
+                None,
+                None,
+            );
+            if result.is_err() {
+                break;
+            }
+            names.push(String::from_utf16_lossy(&buffer[..len as usize]));
+        }
+        let _ = RegCloseKey(hkey);
+    }
+    names
+}
+
+/// Delete HKCU\`key` with everything below it; a missing key is not an error
+fn delete_registry_tree(key: &str) -> Result<()> {
+    let result = unsafe { RegDeleteTreeW(HKEY_CURRENT_USER, &HSTRING::from(key)) };
+    // Not installed is as good as removed
+    if result.is_err() && result != ERROR_FILE_NOT_FOUND {
+        result
+            .ok()
+            .with_context(|| format!("Failed to remove HKCU\\{}", key))?;
+    }
+    Ok(())
+}
+
+/// Command the context menu runs (%V is the folder clicked or shown)
+fn menu_command(exe: &str) -> String {
+    format!("\"{}\" {} \"%V\"", exe, OPEN_DIR_FLAG)
+}
+
+/// Create HKCU\`key` and set its string values (None is the default value)
+fn set_registry_values(key: &str, values: &[(Option<&str>, &str)]) -> Result<()> {
+    unsafe {
+        let mut hkey = HKEY::default();
+        RegCreateKeyExW(
+            HKEY_CURRENT_USER,
+            &HSTRING::from(key),
+            0,
+            PCWSTR::null(),
+            REG_OPTION_NON_VOLATILE,
+            KEY_WRITE,
+            None,
+            &mut hkey,
+            None,
+        )
+        .ok()
+        .with_context(|| format!("Failed to create HKCU\\{}", key))?;
+
+        let mut result = Ok(());
+        for (name, value) in values {
+            let data: Vec<u8> = value
+                .encode_utf16()
+                .chain(std::iter::once(0))
+                .flat_map(u16::to_le_bytes)
+                .collect();
+            let name = name.map(HSTRING::from);
+            let name = name
+                .as_ref()
+                .map_or(PCWSTR::null(), |name| PCWSTR(name.as_ptr()));
+            result = RegSetValueExW(hkey, name, 0, REG_SZ, Some(&data))
+                .ok()
+                .with_context(|| format!("Failed to write HKCU\\{}", key));
+            if result.is_err() {
+                break;
+            }
+        }
+        let _ = RegCloseKey(hkey);
+        result
+    }
+}
+
+/// A folder or file to open, from another neovide-tabs process, the command line
+/// or a `neovide-tabs://` link
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OpenRequest {
+    /// Open a tab in the folder (`--open-dir`)
+    Folder(PathBuf),
+    /// Edit the file or folder in the tab whose working directory contains it, or
+    /// in a new tab in its project root (`open`). With `profile`, only that
+    /// profile's tabs are considered and a new tab uses it.
+    Path {
+        path: PathBuf,
+        profile: Option<String>,
+    },
+}
+
+impl OpenRequest {
+    /// Working directory and files to open of a new tab for the request
+    pub fn new_tab(&self) -> (PathBuf, Vec<PathBuf>) {
+        match self {
+            OpenRequest::Folder(dir) => (dir.clone(), Vec::new()),
+            OpenRequest::Path { path, .. } if path.is_dir() => {
+                (find_project_root(path), Vec::new())
+            }
+            OpenRequest::Path { path, .. } => (find_project_root(path), vec![path.clone()]),
+        }
+    }
+
+    /// Name of the profile to open the request with (the default profile if None)
+    pub fn profile(&self) -> Option<&str> {
+        match self {
+            OpenRequest::Folder(_) => None,
+            OpenRequest::Path { profile, .. } => profile.as_deref(),
+        }
+    }
+
+    fn copydata_kind(&self) -> usize {
+        match self {
+            OpenRequest::Folder(_) => OPEN_DIR_COPYDATA,
+            OpenRequest::Path { .. } => OPEN_PATH_COPYDATA,
+        }
+    }
+
+    fn path(&self) -> &Path {
+        match self {
+            OpenRequest::Folder(path) | OpenRequest::Path { path, .. } => path,
+        }
+    }
+}
+
+/// Whether a command line argument is a `neovide-tabs://` link
+pub fn is_uri(arg: &str) -> bool {
+    arg.get(..URI_SCHEME.len() + 1)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&format!("{}:", URI_SCHEME)))
+}
+
+/// Parse a `neovide-tabs://open?path=<path>[&profile=<name>]` link. The path must
+/// be absolute; values are percent-encoded.
+pub fn parse_uri(uri: &str) -> Result<OpenRequest> {
+    let rest = uri
+        .get(URI_SCHEME.len() + 1..)
+        .filter(|_| is_uri(uri))
+        .with_context(|| format!("Not a {}:// link: {}", URI_SCHEME, uri))?;
+    let rest = rest.trim_start_matches('/');
+    let (action, query) = rest.split_once('?').unwrap_or((rest, ""));
+    if !action.trim_end_matches('/').eq_ignore_ascii_case("open") {
+        bail!("Unknown {}:// action '{}'", URI_SCHEME, action);
+    }
+
+    let mut path = None;
+    let mut profile = None;
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = percent_decode(value)
+            .with_context(|| format!("Invalid encoding of '{}' in {}", key, uri))?;
+        match key {
+            "path" => path = Some(PathBuf::from(value)),
+            "profile" => profile = Some(value).filter(|name| !name.is_empty()),
+            // Unknown parameters are ignored so links can carry newer options
+            _ => {}
+        }
+    }
+    let path = path.with_context(|| format!("{} has no path", uri))?;
+    if !is_absolute_windows_path(&path.to_string_lossy()) {
+        bail!("The path of {} is not absolute", uri);
+    }
+    Ok(OpenRequest::Path { path, profile })
+}
+
+/// Whether `path` is a drive path with a root (`C:\` or `C:/`) or a UNC path. A
+/// link has no current directory a relative path could be relative to.
+fn is_absolute_windows_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    let drive = bytes.len() >= 3
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && matches!(bytes[2], b'\\' | b'/');
+    drive || path.starts_with(r"\\") || path.starts_with("//")
+}
+
+/// Decode %XX escapes (as UTF-8); other characters are kept as they are
+fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = value.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Register the `neovide-tabs://` link scheme for the current user, opening links
+/// with `exe`
+pub fn install_protocol(exe: &Path) -> Result<()> {
+    let exe = exe.to_string_lossy();
+    set_registry_values(
+        PROTOCOL_KEY,
+        &[
+            (None, "URL:neovide-tabs Protocol"),
+            (Some("URL Protocol"), ""),
+        ],
+    )?;
+    set_registry_values(
+        &format!(r"{}\DefaultIcon", PROTOCOL_KEY),
+        &[(None, &format!("\"{}\",0", exe))],
+    )?;
+    set_registry_values(
+        &format!(r"{}\shell\open\command", PROTOCOL_KEY),
+        &[(None, &format!("\"{}\" \"%1\"", exe))],
+    )
+}
+
+/// Remove the `neovide-tabs://` link scheme of the current user
+pub fn uninstall_protocol() -> Result<()> {
+    delete_registry_tree(PROTOCOL_KEY)
+}
+
+/// Hand `request` to the running instance, whose window has the class
+/// `window_class`. Returns false if no instance is running (or it did not take
+/// the request).
+pub fn send_open_request(request: &OpenRequest, window_class: &str) -> bool {
+    unsafe {
+        let Ok(hwnd) = FindWindowW(&HSTRING::from(window_class), None) else {
+            return false;
+        };
+        // The instance brings itself to the foreground, which needs our permission
+        let mut pid = 0;
+        GetWindowThreadProcessId(hwnd, Some(&mut pid));
+        let _ = AllowSetForegroundWindow(pid);
+
+        let data = encode_payload(request.path(), request.profile());
+        let copy = COPYDATASTRUCT {
+            dwData: request.copydata_kind(),
+            cbData: (data.len() * size_of::<u16>()) as u32,
+            lpData: data.as_ptr() as *mut _,
+        };
+        let mut handled = 0usize;
+        SendMessageTimeoutW(
+            hwnd,
+            WM_COPYDATA,
+            WPARAM(0),
+            LPARAM(&copy as *const _ as isize),
+            SMTO_ABORTIFHUNG,
+            SEND_TIMEOUT_MS,
+            Some(&mut handled),
+        );
+        handled != 0
+    }
+}
+
+/// The open request of a WM_COPYDATA message, or None for other messages.
+/// `lparam` must be the LPARAM of a WM_COPYDATA message.
+pub unsafe fn open_request_from_copydata(lparam: LPARAM) -> Option<OpenRequest> {
+    unsafe {
+        let copy = (lparam.0 as *const COPYDATASTRUCT).as_ref()?;
+        if !matches!(copy.dwData, OPEN_DIR_COPYDATA | OPEN_PATH_COPYDATA) || copy.lpData.is_null() {
+            return None;
+        }
+        let units = std::slice::from_raw_parts(
+            copy.lpData as *const u16,
+            copy.cbData as usize / size_of::<u16>(),
+        );
+        let (path, profile) = decode_payload(units)?;
+        Some(match copy.dwData {
+            OPEN_DIR_COPYDATA => OpenRequest::Folder(path),
+            _ => OpenRequest::Path { path, profile },
+        })
+    }
+}
+
+/// WM_COPYDATA payload of a request: the path, then the profile name after a NUL
+fn encode_payload(path: &Path, profile: Option<&str>) -> Vec<u16> {
+    let mut payload = path.to_string_lossy().into_owned();
+    if let Some(profile) = profile {
+        payload.push('\0');
+        payload.push_str(profile);
+    }
+    payload.encode_utf16().collect()
+}
+
+fn decode_payload(units: &[u16]) -> Option<(PathBuf, Option<String>)> {
+    let payload = String::from_utf16(units).ok()?;
+    let mut parts = payload.split('\0');
+    let path = parts.next().filter(|path| !path.is_empty())?;
+    let profile = parts
+        .next()
+        .filter(|profile| !profile.is_empty())
+        .map(str::to_string);
+    Some((PathBuf::from(path), profile))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_menu_command() {
+        assert_eq!(
+            menu_command(r"C:\Program Files\neovide-tabs\neovide-tabs.exe"),
+            r#""C:\Program Files\neovide-tabs\neovide-tabs.exe" --open-dir "%V""#
+        );
+    }
+
+    #[test]
+    fn test_autostart_command() {
+        assert_eq!(
+            autostart_command(r"C:\Tools\neovide-tabs.exe", false),
+            r#""C:\Tools\neovide-tabs.exe""#
+        );
+        assert_eq!(
+            autostart_command(r"C:\Tools\neovide-tabs.exe", true),
+            r#""C:\Tools\neovide-tabs.exe" --minimized"#
+        );
+    }
+
+    #[test]
+    fn test_file_association_commands() {
+        assert_eq!(
+            open_command(r"C:\Tools\neovide-tabs.exe"),
+            r#""C:\Tools\neovide-tabs.exe" open "%1""#
+        );
+        let extensions: Vec<String> = [".RS", "md", " toml ", "rs", ".", ""]
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(
+            normalize_extensions(&extensions),
+            vec![".rs", ".md", ".toml"]
+        );
+    }
+
+    #[test]
+    fn test_payload_round_trip() {
+        let dir = Path::new(r"C:\Users\me\プロジェクト");
+        assert_eq!(
+            decode_payload(&encode_payload(dir, None)),
+            Some((dir.to_path_buf(), None))
+        );
+        assert_eq!(
+            decode_payload(&encode_payload(dir, Some("Work"))),
+            Some((dir.to_path_buf(), Some("Work".to_string())))
+        );
+
+        let mut terminated = encode_payload(dir, None);
+        terminated.push(0);
+        assert_eq!(decode_payload(&terminated), Some((dir.to_path_buf(), None)));
+
+        assert_eq!(decode_payload(&[]), None);
+        assert_eq!(decode_payload(&[0xD800]), None);
+    }
+
+    #[test]
+    fn test_parse_uri() {
+        assert_eq!(
+            parse_uri("neovide-tabs://open?path=C%3A%5Csrc%5Cmy%20app%5Cmain.rs&profile=Work")
+                .unwrap(),
+            OpenRequest::Path {
+                path: PathBuf::from(r"C:\src\my app\main.rs"),
+                profile: Some("Work".to_string()),
+            }
+        );
+        // Browsers may add a slash after the action; unknown parameters are ignored
+        assert_eq!(
+            parse_uri("NEOVIDE-TABS://open/?line=3&path=C:/src/notes.md").unwrap(),
+            OpenRequest::Path {
+                path: PathBuf::from("C:/src/notes.md"),
+                profile: None,
+            }
+        );
+        assert!(is_uri("neovide-tabs://open?path=C:/x"));
+        assert!(!is_uri("neovide-tabs.exe"));
+
+        assert!(parse_uri("neovide-tabs://open").is_err());
+        assert!(parse_uri("neovide-tabs://open?path=relative%5Cfile.rs").is_err());
+        assert!(parse_uri("neovide-tabs://delete?path=C:/x").is_err());
+        assert!(parse_uri("neovide-tabs://open?path=C:/%ZZ").is_err());
+        assert!(parse_uri("https://example.com").is_err());
+    }
+}
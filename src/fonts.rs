@@ -0,0 +1,226 @@
+//! Fonts of the tab bar and popups.
+//!
+//! Fonts are created from `font_family`/`font_size` once and cached per size and
+//! weight instead of being rebuilt for every tab on every paint. Text with
+//! characters the configured font has no glyphs for (e.g. Japanese file names in
+//! Segoe UI) is drawn with the first fallback font that covers it.
+
+#![cfg(target_os = "windows")]
+#![allow(unsafe_op_in_unsafe_fn)]
+
+use std::cell::RefCell;
+use windows::Win32::Graphics::Gdi::{
+    CreateFontIndirectW, DeleteObject, GGI_MARK_NONEXISTING_GLYPHS, GetGlyphIndicesW, HDC, HFONT,
+    HGDIOBJ, LOGFONTW, SelectObject,
+};
+use windows::core::PCWSTR;
+
+use crate::config::{DEFAULT_FONT_FAMILY, DEFAULT_FONT_SIZE};
+
+/// Fonts tried in order for text the configured font can't display
+const FALLBACK_FONTS: [&str; 6] = [
+    "Yu Gothic UI",
+    "Microsoft YaHei UI",
+    "Microsoft JhengHei UI",
+    "Malgun Gothic",
+    "Nirmala UI",
+    "Segoe UI Symbol",
+];
+
+/// Glyph index GetGlyphIndicesW reports for characters missing from the font
+const MISSING_GLYPH: u16 = 0xFFFF;
+
+/// Text sizes of the UI, relative to the configured font size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextSize {
+    /// Working directories below two-line tab titles
+    Caption,
+    /// Tab labels and the overflow count
+    Label,
+    /// Popup entries and the session name
+    Body,
+    /// Startup progress text
+    Large,
+}
+
+impl TextSize {
+    /// Height in pixels for the configured `font_size`
+    fn pixels(self, font_size: i32) -> i32 {
+        match self {
+            TextSize::Caption => font_size - 1,
+            TextSize::Label => font_size,
+            TextSize::Body => font_size + 1,
+            TextSize::Large => font_size + 3,
+        }
+    }
+}
+
+/// Fonts created so far, keyed by family, pixel height and weight
+struct FontCache {
+    family: String,
+    size: i32,
+    fonts: Vec<((String, i32, i32), HFONT)>,
+}
+
+impl FontCache {
+    fn get(&mut self, family: &str, height: i32, weight: i32) -> HFONT {
+        if let Some((_, font)) = self
+            .fonts
+            .iter()
+            .find(|((f, h, w), _)| f == family && *h == height && *w == weight)
+        {
+            return *font;
+        }
+        let font = unsafe { create_font(family, height, weight) };
+        self.fonts
+            .push(((family.to_string(), height, weight), font));
+        font
+    }
+
+    fn clear(&mut self) {
+        for (_, font) in self.fonts.drain(..) {
+            unsafe {
+                let _ = DeleteObject(HGDIOBJ(font.0));
+            }
+        }
+    }
+}
+
+thread_local! {
+    static FONTS: RefCell<FontCache> = RefCell::new(FontCache {
+        family: DEFAULT_FONT_FAMILY.to_string(),
+        size: DEFAULT_FONT_SIZE,
+        fonts: Vec::new(),
+    });
+}
+
+/// Use `family` at `size` pixels from now on. Must not be called while a cached
+/// font is selected into a device context.
+pub fn set_ui_font(family: &str, size: i32) {
+    FONTS.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if cache.family != family || cache.size != size {
+            cache.clear();
+            cache.family = family.to_string();
+            cache.size = size;
+        }
+    });
+}
+
+/// The configured font at `size` and `weight`. The font belongs to the cache and
+/// must not be deleted.
+pub fn ui_font(size: TextSize, weight: i32) -> HFONT {
+    FONTS.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let family = cache.family.clone();
+        let height = size.pixels(cache.size);
+        cache.get(&family, height, weight)
+    })
+}
+
+/// A new font like `ui_font` owned by the caller, for controls that keep their
+/// font (the cached fonts are replaced when the config changes)
+pub fn create_ui_font(size: TextSize, weight: i32) -> HFONT {
+    FONTS.with(|cache| {
+        let cache = cache.borrow();
+        unsafe { create_font(&cache.family, size.pixels(cache.size), weight) }
+    })
+}
+
+/// Select the font for drawing `text` into `hdc`: the configured font, or the
+/// fallback font missing the fewest glyphs if the configured one lacks some.
+/// Returns the previously selected object so the caller can restore it.
+pub unsafe fn select_font_for_text(hdc: HDC, size: TextSize, weight: i32, text: &[u16]) -> HGDIOBJ {
+    let font = ui_font(size, weight);
+    let old_font = SelectObject(hdc, HGDIOBJ(font.0));
+
+    let mut best = (missing_glyphs(hdc, text), font);
+    if best.0 == 0 {
+        return old_font;
+    }
+    let height = FONTS.with(|cache| size.pixels(cache.borrow().size));
+    for family in FALLBACK_FONTS {
+        let fallback = FONTS.with(|cache| cache.borrow_mut().get(family, height, weight));
+        SelectObject(hdc, HGDIOBJ(fallback.0));
+        let missing = missing_glyphs(hdc, text);
+        if missing < best.0 {
+            best = (missing, fallback);
+            if missing == 0 {
+                break;
+            }
+        }
+    }
+    SelectObject(hdc, HGDIOBJ(best.1.0));
+    old_font
+}
+
+/// Number of characters of `text` the font selected into `hdc` has no glyph for
+unsafe fn missing_glyphs(hdc: HDC, text: &[u16]) -> usize {
+    // Surrogate pairs have no glyph index of their own, so only BMP characters count
+    let chars: Vec<u16> = text
+        .iter()
+        .copied()
+        .filter(|c| !(0xD800..=0xDFFF).contains(c))
+        .collect();
+    if chars.is_empty() {
+        return 0;
+    }
+    let mut glyphs = vec![0u16; chars.len()];
+    let result = GetGlyphIndicesW(
+        hdc,
+        PCWSTR(chars.as_ptr()),
+        chars.len() as i32,
+        glyphs.as_mut_ptr(),
+        GGI_MARK_NONEXISTING_GLYPHS,
+    );
+    if result == u32::MAX {
+        return 0;
+    }
+    glyphs.iter().filter(|&&g| g == MISSING_GLYPH).count()
+}
+
+unsafe fn create_font(family: &str, height: i32, weight: i32) -> HFONT {
+    let lf = LOGFONTW {
+        lfHeight: -height,
+        lfWeight: weight,
+        lfFaceName: face_name(family),
+        ..Default::default()
+    };
+    CreateFontIndirectW(&lf)
+}
+
+/// LOGFONT face name: UTF-16, truncated to 31 characters plus the terminator
+fn face_name(family: &str) -> [u16; 32] {
+    let mut name = [0u16; 32];
+    for (i, c) in family.encode_utf16().take(31).enumerate() {
+        name[i] = c;
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_size_pixels() {
+        assert_eq!(TextSize::Caption.pixels(11), 10);
+        assert_eq!(TextSize::Label.pixels(11), 11);
+        assert_eq!(TextSize::Body.pixels(11), 12);
+        assert_eq!(TextSize::Large.pixels(11), 14);
+    }
+
+    #[test]
+    fn test_face_name_is_terminated() {
+        let name = face_name("Segoe UI");
+        assert_eq!(
+            &name[..8],
+            &"Segoe UI".encode_utf16().collect::<Vec<_>>()[..]
+        );
+        assert_eq!(name[8], 0);
+
+        let long = face_name(&"x".repeat(40));
+        assert_eq!(long[30], 'x' as u16);
+        assert_eq!(long[31], 0);
+    }
+}
@@ -0,0 +1,153 @@
+//! Swipes that switch to the adjacent tab.
+//!
+//! Two-finger touchpad swipes reach the wrapper as horizontal wheel messages
+//! while the pointer is over the title bar; they are summed up until the
+//! gesture pauses. Touch and pen swipes are contacts that start on a tab and
+//! move sideways quickly. Like in browsers, moving the fingers to the left goes
+//! to the next tab and to the right to the previous one.
+
+use std::time::{Duration, Instant};
+
+use crate::config::SwipeSensitivity;
+
+/// A pause this long between wheel messages ends a touchpad swipe
+const WHEEL_GESTURE_GAP: Duration = Duration::from_millis(300);
+
+/// Which way a swipe switches tabs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwipeDirection {
+    Next,
+    Previous,
+}
+
+impl SwipeSensitivity {
+    /// Horizontal wheel distance of a touchpad swipe (WHEEL_DELTA is 120)
+    fn wheel_threshold(self) -> Option<i32> {
+        match self {
+            SwipeSensitivity::Off => None,
+            SwipeSensitivity::Low => Some(480),
+            SwipeSensitivity::Medium => Some(240),
+            SwipeSensitivity::High => Some(120),
+        }
+    }
+
+    /// Sideways distance (pixels) of a touch or pen swipe
+    pub fn touch_distance(self) -> Option<i32> {
+        match self {
+            SwipeSensitivity::Off => None,
+            SwipeSensitivity::Low => Some(160),
+            SwipeSensitivity::Medium => Some(96),
+            SwipeSensitivity::High => Some(48),
+        }
+    }
+}
+
+/// Sums up the horizontal wheel messages of a touchpad swipe
+#[derive(Debug, Default)]
+pub struct WheelSwipe {
+    accumulated: i32,
+    last: Option<Instant>,
+    /// The gesture already switched tabs; the rest of it is ignored
+    fired: bool,
+}
+
+impl WheelSwipe {
+    /// Add a horizontal wheel `delta` (positive: scrolling right) received at
+    /// `now`. Returns the direction to switch in once the gesture is long enough.
+    pub fn add(
+        &mut self,
+        delta: i32,
+        now: Instant,
+        sensitivity: SwipeSensitivity,
+    ) -> Option<SwipeDirection> {
+        let threshold = sensitivity.wheel_threshold()?;
+        if self
+            .last
+            .is_none_or(|last| now.duration_since(last) >= WHEEL_GESTURE_GAP)
+        {
+            *self = WheelSwipe::default();
+        }
+        self.last = Some(now);
+        if self.fired {
+            return None;
+        }
+        // A swipe that turns around starts over
+        if self.accumulated.signum() * delta.signum() < 0 {
+            self.accumulated = 0;
+        }
+        self.accumulated += delta;
+        if self.accumulated.abs() < threshold {
+            return None;
+        }
+        self.fired = true;
+        Some(if self.accumulated > 0 {
+            SwipeDirection::Next
+        } else {
+            SwipeDirection::Previous
+        })
+    }
+}
+
+/// Direction of a touch or pen contact that moved (dx, dy) pixels, if that is a
+/// swipe: far enough and mostly sideways
+pub fn touch_swipe(dx: i32, dy: i32, sensitivity: SwipeSensitivity) -> Option<SwipeDirection> {
+    let distance = sensitivity.touch_distance()?;
+    if dx.abs() < distance || dx.abs() < dy.abs() * 2 {
+        return None;
+    }
+    Some(if dx < 0 {
+        SwipeDirection::Next
+    } else {
+        SwipeDirection::Previous
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wheel_swipe() {
+        let mut swipe = WheelSwipe::default();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let medium = SwipeSensitivity::Medium;
+
+        assert_eq!(swipe.add(120, at(0), medium), None);
+        assert_eq!(swipe.add(120, at(20), medium), Some(SwipeDirection::Next));
+        // The rest of the gesture doesn't switch again
+        assert_eq!(swipe.add(240, at(40), medium), None);
+
+        // After a pause a new gesture starts
+        assert_eq!(swipe.add(-200, at(400), medium), None);
+        assert_eq!(
+            swipe.add(-40, at(410), medium),
+            Some(SwipeDirection::Previous)
+        );
+
+        // Turning around starts over
+        let mut swipe = WheelSwipe::default();
+        assert_eq!(swipe.add(200, at(0), medium), None);
+        assert_eq!(swipe.add(-100, at(10), medium), None);
+        assert_eq!(swipe.add(100, at(20), medium), None);
+
+        assert_eq!(
+            WheelSwipe::default().add(1000, at(0), SwipeSensitivity::Off),
+            None
+        );
+    }
+
+    #[test]
+    fn test_touch_swipe() {
+        let medium = SwipeSensitivity::Medium;
+        assert_eq!(touch_swipe(-100, 10, medium), Some(SwipeDirection::Next));
+        assert_eq!(
+            touch_swipe(100, -10, medium),
+            Some(SwipeDirection::Previous)
+        );
+        // Too short, or more down than sideways
+        assert_eq!(touch_swipe(-50, 0, medium), None);
+        assert_eq!(touch_swipe(-100, 60, medium), None);
+        assert_eq!(touch_swipe(-100, 0, SwipeSensitivity::Off), None);
+    }
+}
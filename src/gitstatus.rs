@@ -0,0 +1,277 @@
+//! Git status of the tabs' working directories (`git_status` config option).
+//!
+//! `git status --porcelain -b` runs on a background thread for each directory
+//! now and then, and the tab bar marks tabs with uncommitted changes or with
+//! commits to push or pull. Results are cached per directory, so tabs in the same
+//! repository share one check.
+
+use std::collections::HashMap;
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Interval between checks of a directory
+pub const GIT_STATUS_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Process creation flag that keeps git from opening a console window
+#[cfg(target_os = "windows")]
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+/// Summary of `git status` in a directory
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GitStatus {
+    /// Checked out branch; None for a detached HEAD
+    pub branch: Option<String>,
+    /// Changed and untracked files
+    pub changes: usize,
+    /// Commits not pushed to the upstream branch
+    pub ahead: u32,
+    /// Commits on the upstream branch not pulled yet
+    pub behind: u32,
+}
+
+/// What the dot on a tab says
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GitIndicator {
+    /// Uncommitted changes
+    Dirty,
+    /// Committed, but ahead of or behind the upstream branch
+    Unsynced,
+}
+
+impl GitStatus {
+    /// Parse the output of `git status --porcelain -b`
+    pub fn parse(output: &str) -> GitStatus {
+        let mut status = GitStatus::default();
+        for line in output.lines() {
+            let Some(header) = line.strip_prefix("## ") else {
+                if !line.trim().is_empty() {
+                    status.changes += 1;
+                }
+                continue;
+            };
+            // "main...origin/main [ahead 1, behind 2]", "main", or
+            // "No commits yet on main"
+            let (branch, tracking) = match header.split_once(" [") {
+                Some((branch, tracking)) => (branch, tracking.trim_end_matches(']')),
+                None => (header, ""),
+            };
+            let branch = branch.split("...").next().unwrap_or(branch);
+            let branch = branch.strip_prefix("No commits yet on ").unwrap_or(branch);
+            status.branch = (!branch.starts_with("HEAD (")).then(|| branch.to_string());
+            for part in tracking.split(", ") {
+                if let Some(count) = part.strip_prefix("ahead ") {
+                    status.ahead = count.parse().unwrap_or(0);
+                } else if let Some(count) = part.strip_prefix("behind ") {
+                    status.behind = count.parse().unwrap_or(0);
+                }
+            }
+        }
+        status
+    }
+
+    /// The dot shown on the tab; None for a clean, synced repository
+    pub fn indicator(&self) -> Option<GitIndicator> {
+        if self.changes > 0 {
+            Some(GitIndicator::Dirty)
+        } else if self.ahead > 0 || self.behind > 0 {
+            Some(GitIndicator::Unsynced)
+        } else {
+            None
+        }
+    }
+
+    /// One line for the tab's tooltip, e.g. "Git: main, 3 changed, 1 ahead"
+    pub fn summary(&self) -> String {
+        let mut parts = vec![
+            self.branch
+                .clone()
+                .unwrap_or_else(|| "detached HEAD".to_string()),
+        ];
+        if self.changes > 0 {
+            parts.push(format!("{} changed", self.changes));
+        }
+        if self.ahead > 0 {
+            parts.push(format!("{} ahead", self.ahead));
+        }
+        if self.behind > 0 {
+            parts.push(format!("{} behind", self.behind));
+        }
+        if self.indicator().is_none() {
+            parts.push("clean".to_string());
+        }
+        format!("Git: {}", parts.join(", "))
+    }
+}
+
+/// Runs the check of a directory; None if it isn't in a repository
+type Checker = fn(&Path) -> Option<GitStatus>;
+
+/// Results of checks: directory and its status
+type Finished = Arc<Mutex<Vec<(PathBuf, Option<GitStatus>)>>>;
+
+/// Git status by directory, checked on background threads
+pub struct GitStatusCache {
+    entries: HashMap<PathBuf, Entry>,
+    /// Results of checks that finished since the last `receive`
+    finished: Finished,
+    checker: Checker,
+}
+
+#[derive(Debug, Default)]
+struct Entry {
+    status: Option<GitStatus>,
+    checked_at: Option<Instant>,
+    running: bool,
+}
+
+impl Default for GitStatusCache {
+    fn default() -> Self {
+        Self::with_checker(run_git_status)
+    }
+}
+
+impl GitStatusCache {
+    fn with_checker(checker: Checker) -> Self {
+        GitStatusCache {
+            entries: HashMap::new(),
+            finished: Arc::default(),
+            checker,
+        }
+    }
+
+    /// Last known status of `dir`; None if it isn't in a repository or hasn't
+    /// been checked yet
+    pub fn get(&self, dir: &Path) -> Option<&GitStatus> {
+        self.entries.get(dir)?.status.as_ref()
+    }
+
+    /// Check `dir` in the background, unless a check is running or the last one
+    /// is younger than `max_age`
+    pub fn refresh(&mut self, dir: &Path, max_age: Duration) {
+        let entry = self.entries.entry(dir.to_path_buf()).or_default();
+        if entry.running || entry.checked_at.is_some_and(|at| at.elapsed() < max_age) {
+            return;
+        }
+        entry.running = true;
+        let dir = dir.to_path_buf();
+        let finished = Arc::clone(&self.finished);
+        let checker = self.checker;
+        thread::spawn(move || {
+            let status = checker(&dir);
+            finished.lock().unwrap().push((dir, status));
+        });
+    }
+
+    /// Take the results of the checks that finished. Returns true if a status
+    /// changed.
+    pub fn receive(&mut self) -> bool {
+        let finished = std::mem::take(&mut *self.finished.lock().unwrap());
+        let mut changed = false;
+        for (dir, status) in finished {
+            let entry = self.entries.entry(dir).or_default();
+            entry.running = false;
+            entry.checked_at = Some(Instant::now());
+            changed |= entry.status != status;
+            entry.status = status;
+        }
+        changed
+    }
+}
+
+/// Run `git status` in `dir`. Optional locks are off, so the check never holds
+/// up git commands run at the same time.
+fn run_git_status(dir: &Path) -> Option<GitStatus> {
+    let mut command = Command::new("git");
+    command
+        .args(["status", "--porcelain", "-b"])
+        .env("GIT_OPTIONAL_LOCKS", "0")
+        .current_dir(dir);
+    #[cfg(target_os = "windows")]
+    command.creation_flags(CREATE_NO_WINDOW);
+    let output = command.output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| GitStatus::parse(&String::from_utf8_lossy(&output.stdout)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let status = GitStatus::parse(
+            "## main...origin/main [ahead 1, behind 12]\n M src/main.rs\n?? notes.md\n",
+        );
+        assert_eq!(
+            status,
+            GitStatus {
+                branch: Some("main".to_string()),
+                changes: 2,
+                ahead: 1,
+                behind: 12,
+            }
+        );
+        assert_eq!(status.indicator(), Some(GitIndicator::Dirty));
+        assert_eq!(status.summary(), "Git: main, 2 changed, 1 ahead, 12 behind");
+
+        let status = GitStatus::parse("## feature/x...origin/feature/x [behind 3]\n");
+        assert_eq!(status.branch.as_deref(), Some("feature/x"));
+        assert_eq!((status.ahead, status.behind), (0, 3));
+        assert_eq!(status.indicator(), Some(GitIndicator::Unsynced));
+
+        let status = GitStatus::parse("## main\n");
+        assert_eq!(status.indicator(), None);
+        assert_eq!(status.summary(), "Git: main, clean");
+
+        assert_eq!(
+            GitStatus::parse("## No commits yet on trunk\n")
+                .branch
+                .as_deref(),
+            Some("trunk")
+        );
+        assert_eq!(
+            GitStatus::parse("## main...origin/main [gone]\n").indicator(),
+            None
+        );
+        let detached = GitStatus::parse("## HEAD (no branch)\n");
+        assert_eq!(detached.branch, None);
+        assert_eq!(detached.summary(), "Git: detached HEAD, clean");
+    }
+
+    #[test]
+    fn test_cache() {
+        let mut cache = GitStatusCache::with_checker(|dir| {
+            (dir == Path::new("repo")).then(|| GitStatus {
+                changes: 1,
+                ..Default::default()
+            })
+        });
+        cache.refresh(Path::new("repo"), GIT_STATUS_INTERVAL);
+        cache.refresh(Path::new("elsewhere"), GIT_STATUS_INTERVAL);
+        assert_eq!(cache.get(Path::new("repo")), None);
+
+        let started = Instant::now();
+        let mut changed = false;
+        while cache.entries.values().any(|entry| entry.running) {
+            changed |= cache.receive();
+            assert!(started.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(changed);
+        assert_eq!(cache.get(Path::new("repo")).map(|s| s.changes), Some(1));
+        assert_eq!(cache.get(Path::new("elsewhere")), None);
+
+        // Checked recently: nothing to do until forced
+        cache.refresh(Path::new("repo"), GIT_STATUS_INTERVAL);
+        assert!(!cache.entries[Path::new("repo")].running);
+        cache.refresh(Path::new("repo"), Duration::ZERO);
+        assert!(cache.entries[Path::new("repo")].running);
+    }
+}
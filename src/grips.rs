@@ -0,0 +1,381 @@
+//! Invisible resize grips for the wrapper window.
+//!
+//! Neovide runs as a separate top-level window floating over the wrapper's
+//! content area. When the content inset is smaller than the resize border,
+//! the Neovide window covers the wrapper's resize zones and swallows the mouse.
+//! The grips are thin, nearly transparent popups placed over those edges. They
+//! forward hit-tests to the wrapper's WM_NCHITTEST (so edges and the enlarged
+//! corner zones behave identically with and without grips) and hand clicks to
+//! the wrapper as non-client resize requests.
+
+#![cfg(target_os = "windows")]
+#![allow(unsafe_op_in_unsafe_fn)]
+
+use windows::Win32::Foundation::{COLORREF, HINSTANCE, HWND, LPARAM, LRESULT, POINT, RECT, WPARAM};
+use windows::Win32::Graphics::Gdi::ClientToScreen;
+use windows::Win32::UI::Input::KeyboardAndMouse::ReleaseCapture;
+use windows::Win32::UI::WindowsAndMessaging::*;
+use windows::core::{PCWSTR, w};
+
+use crate::layout::titlebar_height;
+
+/// Width of the resize zone along the window edges in pixels
+pub const RESIZE_BORDER_WIDTH: i32 = 8;
+
+/// Length of the diagonal resize zone at each corner, measured along both edges
+pub const RESIZE_CORNER_SIZE: i32 = 16;
+
+const GRIP_CLASS_NAME: PCWSTR = w!("NeovideTabsResizeGrip");
+
+/// Window edge covered by a grip
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GripEdge {
+    Left,
+    Right,
+    Bottom,
+}
+
+impl GripEdge {
+    const ALL: [GripEdge; 3] = [GripEdge::Left, GripEdge::Right, GripEdge::Bottom];
+}
+
+/// Set of grip windows owned by the wrapper window
+pub struct ResizeGrips {
+    owner: HWND,
+    grips: Vec<(GripEdge, HWND)>,
+    /// Grips are only needed when the content inset is smaller than the resize border
+    enabled: bool,
+    /// Whether the wrapper or one of its Neovide windows is in the foreground
+    foreground: bool,
+}
+
+/// Register the grip window class
+pub fn register_class(hinstance: HINSTANCE) -> bool {
+    unsafe {
+        let wc = WNDCLASSW {
+            lpfnWndProc: Some(grip_proc),
+            hInstance: hinstance,
+            hCursor: LoadCursorW(None, IDC_ARROW).ok().unwrap_or_default(),
+            lpszClassName: GRIP_CLASS_NAME,
+            ..Default::default()
+        };
+        RegisterClassW(&wc) != 0
+    }
+}
+
+impl ResizeGrips {
+    /// Create the (initially hidden) grip windows for `owner`
+    pub fn create(owner: HWND, content_inset: i32) -> Self {
+        let mut grips = Vec::new();
+        unsafe {
+            let hinstance = GetWindowLongPtrW(owner, GWLP_HINSTANCE);
+            for edge in GripEdge::ALL {
+                // Topmost so the grips stay above the Neovide window, which is a
+                // separate top-level window; alpha 1 keeps them invisible but hit-testable
+                let hwnd = CreateWindowExW(
+                    WS_EX_LAYERED | WS_EX_TOPMOST | WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE,
+                    GRIP_CLASS_NAME,
+                    w!(""),
+                    WS_POPUP,
+                    0,
+                    0,
+                    0,
+                    0,
+                    owner,
+                    None,
+                    HINSTANCE(hinstance as *mut _),
+                    None,
+                );
+                match hwnd {
+                    Ok(hwnd) => {
+                        let _ = SetLayeredWindowAttributes(hwnd, COLORREF(0), 1, LWA_ALPHA);
+                        grips.push((edge, hwnd));
+                    }
+                    Err(e) => eprintln!("Failed to create resize grip: {}", e),
+                }
+            }
+        }
+
+        let mut resize_grips = Self {
+            owner,
+            grips,
+            enabled: false,
+            foreground: true,
+        };
+        resize_grips.set_content_inset(content_inset);
+        resize_grips
+    }
+
+    /// Enable the grips when the content inset leaves no room for the resize border
+    pub fn set_content_inset(&mut self, content_inset: i32) {
+        self.enabled = content_inset < RESIZE_BORDER_WIDTH;
+        self.update();
+    }
+
+    /// Record whether the wrapper (or one of its Neovide windows) is in the foreground
+    pub fn set_foreground(&mut self, foreground: bool) {
+        if self.foreground != foreground {
+            self.foreground = foreground;
+            self.update();
+        }
+    }
+
+    /// Reposition the grips along the wrapper's edges, or hide them when not needed
+    pub fn update(&self) {
+        unsafe {
+            let visible = self.enabled
+                && self.foreground
+                && IsWindowVisible(self.owner).as_bool()
+                && !IsIconic(self.owner).as_bool()
+                && !IsZoomed(self.owner).as_bool();
+
+            let mut client_rect = RECT::default();
+            let mut origin = POINT::default();
+            if !visible
+                || GetClientRect(self.owner, &mut client_rect).is_err()
+                || !ClientToScreen(self.owner, &mut origin).as_bool()
+            {
+                for (_, hwnd) in &self.grips {
+                    let _ = ShowWindow(*hwnd, SW_HIDE);
+                }
+                return;
+            }
+
+            let window_rect = RECT {
+                left: origin.x,
+                top: origin.y,
+                right: origin.x + client_rect.right,
+                bottom: origin.y + client_rect.bottom,
+            };
+
+            for (edge, hwnd) in &self.grips {
+                let rect = grip_rect(*edge, &window_rect);
+                let _ = SetWindowPos(
+                    *hwnd,
+                    HWND_TOPMOST,
+                    rect.left,
+                    rect.top,
+                    rect.right - rect.left,
+                    rect.bottom - rect.top,
+                    SWP_NOACTIVATE | SWP_SHOWWINDOW,
+                );
+            }
+        }
+    }
+}
+
+impl Drop for ResizeGrips {
+    fn drop(&mut self) {
+        unsafe {
+            for (_, hwnd) in &self.grips {
+                let _ = DestroyWindow(*hwnd);
+            }
+        }
+    }
+}
+
+/// Screen rect of the grip for `edge`. The grips start below the title bar,
+/// which the Neovide window never covers.
+fn grip_rect(edge: GripEdge, window_rect: &RECT) -> RECT {
+    let top = window_rect.top + titlebar_height();
+    match edge {
+        GripEdge::Left => RECT {
+            left: window_rect.left,
+            top,
+            right: window_rect.left + RESIZE_BORDER_WIDTH,
+            bottom: window_rect.bottom,
+        },
+        GripEdge::Right => RECT {
+            left: window_rect.right - RESIZE_BORDER_WIDTH,
+            top,
+            right: window_rect.right,
+            bottom: window_rect.bottom,
+        },
+        GripEdge::Bottom => RECT {
+            left: window_rect.left,
+            top: window_rect.bottom - RESIZE_BORDER_WIDTH,
+            right: window_rect.right,
+            bottom: window_rect.bottom,
+        },
+    }
+}
+
+/// Resize hit-test for a point in the wrapper's client coordinates.
+/// `side_border` is the depth of the left, right and bottom resize zones below the
+/// title bar; the top edge and the title bar rows always use RESIZE_BORDER_WIDTH so
+/// the tabs and window buttons stay clickable. Points within RESIZE_CORNER_SIZE of
+/// a corner along either edge resize diagonally.
+pub fn resize_hit_test(x: i32, y: i32, width: i32, height: i32, side_border: i32) -> Option<u32> {
+    let side_border = if y < titlebar_height() {
+        RESIZE_BORDER_WIDTH
+    } else {
+        side_border.max(RESIZE_BORDER_WIDTH)
+    };
+
+    let left = x < side_border;
+    let right = x >= width - side_border;
+    let top = y < RESIZE_BORDER_WIDTH;
+    let bottom = y >= height - side_border;
+    if !(left || right || top || bottom) {
+        return None;
+    }
+
+    let corner_x = RESIZE_CORNER_SIZE.max(side_border);
+    let corner_y = RESIZE_CORNER_SIZE.max(RESIZE_BORDER_WIDTH);
+    let near_left = x < corner_x;
+    let near_right = x >= width - corner_x;
+    let near_top = y < corner_y;
+    let near_bottom = y >= height - corner_y;
+
+    let code = if (top && near_left) || (left && near_top) {
+        HTTOPLEFT
+    } else if (top && near_right) || (right && near_top) {
+        HTTOPRIGHT
+    } else if (bottom && near_left) || (left && near_bottom) {
+        HTBOTTOMLEFT
+    } else if (bottom && near_right) || (right && near_bottom) {
+        HTBOTTOMRIGHT
+    } else if top {
+        HTTOP
+    } else if bottom {
+        HTBOTTOM
+    } else if left {
+        HTLEFT
+    } else {
+        HTRIGHT
+    };
+    Some(code)
+}
+
+/// Resize cursor matching a hit-test code, or None for non-resize codes
+fn cursor_for_hit_code(code: u32) -> Option<PCWSTR> {
+    match code {
+        HTLEFT | HTRIGHT => Some(IDC_SIZEWE),
+        HTTOP | HTBOTTOM => Some(IDC_SIZENS),
+        HTTOPRIGHT | HTBOTTOMLEFT => Some(IDC_SIZENESW),
+        HTTOPLEFT | HTBOTTOMRIGHT => Some(IDC_SIZENWSE),
+        _ => None,
+    }
+}
+
+/// Pack a screen point into an LPARAM the way mouse messages do
+fn point_to_lparam(pt: POINT) -> LPARAM {
+    LPARAM(((pt.y as u16 as isize) << 16) | (pt.x as u16 as isize))
+}
+
+/// Ask the wrapper which resize zone the cursor is over. The grip forwards the
+/// hit-test so the wrapper's WM_NCHITTEST stays the single source of truth.
+/// Returns the wrapper HWND, the resize code and the cursor position.
+unsafe fn forward_hit_test(hwnd: HWND) -> Option<(HWND, u32, POINT)> {
+    let owner = GetWindow(hwnd, GW_OWNER).ok()?;
+    let mut pt = POINT::default();
+    GetCursorPos(&mut pt).ok()?;
+    let code = SendMessageW(owner, WM_NCHITTEST, WPARAM(0), point_to_lparam(pt)).0 as u32;
+    cursor_for_hit_code(code)?;
+    Some((owner, code, pt))
+}
+
+/// Window procedure for the grip windows
+unsafe extern "system" fn grip_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match msg {
+        WM_MOUSEACTIVATE => LRESULT(MA_NOACTIVATE as isize),
+
+        WM_SETCURSOR => {
+            if let Some((_, code, _)) = forward_hit_test(hwnd) {
+                if let Some(Ok(cursor)) = cursor_for_hit_code(code).map(|c| LoadCursorW(None, c)) {
+                    SetCursor(cursor);
+                }
+                return LRESULT(1);
+            }
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        }
+
+        WM_LBUTTONDOWN => {
+            // Hand the drag to the wrapper as if its own resize border was clicked
+            if let Some((owner, code, pt)) = forward_hit_test(hwnd) {
+                let _ = ReleaseCapture();
+                let _ = PostMessageW(
+                    owner,
+                    WM_NCLBUTTONDOWN,
+                    WPARAM(code as usize),
+                    point_to_lparam(pt),
+                );
+            }
+            LRESULT(0)
+        }
+
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grip_rect_edges() {
+        let window = RECT {
+            left: 100,
+            top: 50,
+            right: 900,
+            bottom: 650,
+        };
+        let top = 50 + titlebar_height();
+
+        let left = grip_rect(GripEdge::Left, &window);
+        assert_eq!(
+            (left.left, left.top, left.right, left.bottom),
+            (100, top, 108, 650)
+        );
+
+        let right = grip_rect(GripEdge::Right, &window);
+        assert_eq!(
+            (right.left, right.top, right.right, right.bottom),
+            (892, top, 900, 650)
+        );
+
+        let bottom = grip_rect(GripEdge::Bottom, &window);
+        assert_eq!(
+            (bottom.left, bottom.top, bottom.right, bottom.bottom),
+            (100, 642, 900, 650)
+        );
+    }
+
+    #[test]
+    fn test_resize_hit_test_edges() {
+        let (w, h) = (800, 600);
+        assert_eq!(resize_hit_test(2, 300, w, h, 8), Some(HTLEFT));
+        assert_eq!(resize_hit_test(795, 300, w, h, 8), Some(HTRIGHT));
+        assert_eq!(resize_hit_test(400, 595, w, h, 8), Some(HTBOTTOM));
+        assert_eq!(resize_hit_test(400, 3, w, h, 8), Some(HTTOP));
+        assert_eq!(resize_hit_test(400, 300, w, h, 8), None);
+    }
+
+    #[test]
+    fn test_resize_hit_test_extended_corners() {
+        let (w, h) = (800, 600);
+        // Corner zones extend RESIZE_CORNER_SIZE along each edge
+        assert_eq!(resize_hit_test(2, 590, w, h, 8), Some(HTBOTTOMLEFT));
+        assert_eq!(resize_hit_test(12, 596, w, h, 8), Some(HTBOTTOMLEFT));
+        assert_eq!(resize_hit_test(796, 588, w, h, 8), Some(HTBOTTOMRIGHT));
+        assert_eq!(resize_hit_test(3, 12, w, h, 8), Some(HTTOPLEFT));
+        assert_eq!(resize_hit_test(790, 2, w, h, 8), Some(HTTOPRIGHT));
+    }
+
+    #[test]
+    fn test_resize_hit_test_wide_inset() {
+        let (w, h) = (800, 600);
+        // The whole inset gap below the title bar resizes
+        assert_eq!(resize_hit_test(10, 300, w, h, 12), Some(HTLEFT));
+        assert_eq!(resize_hit_test(400, 589, w, h, 12), Some(HTBOTTOM));
+        assert_eq!(resize_hit_test(12, 300, w, h, 12), None);
+        // Title bar rows keep the narrow border so tabs and buttons stay clickable
+        assert_eq!(resize_hit_test(10, 20, w, h, 12), None);
+        assert_eq!(resize_hit_test(790, 20, w, h, 12), None);
+    }
+}
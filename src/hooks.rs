@@ -0,0 +1,173 @@
+//! Shell command hooks on tab lifecycle events.
+//!
+//! `hooks` in the config names commands that run when a tab's Neovide window
+//! appears, when a tab closes and when a tab is selected, e.g. to start and stop a
+//! project's dev server along with its tab. Commands run through `cmd /C` in the
+//! tab's working directory without a console window, with environment variables
+//! describing the tab. The wrapper doesn't wait for them.
+//!
+//! A tab's "Open in Terminal" runs `terminal_command` the same way.
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+use std::path::Path;
+use std::process::Command;
+use std::sync::{LazyLock, RwLock};
+
+use crate::config::TabHooks;
+
+/// Process creation flag that keeps `cmd` from opening a console window
+#[cfg(target_os = "windows")]
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+/// The configured hooks (`hooks` in the config)
+static HOOKS: LazyLock<RwLock<TabHooks>> = LazyLock::new(Default::default);
+
+/// Tab lifecycle events with a hook
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    /// The tab's Neovide window appeared
+    Open,
+    /// The tab was closed or its Neovide exited
+    Close,
+    /// The tab became the selected tab
+    Select,
+}
+
+impl HookEvent {
+    /// Value of `NEOVIDE_TABS_EVENT`
+    fn name(self) -> &'static str {
+        match self {
+            HookEvent::Open => "open",
+            HookEvent::Close => "close",
+            HookEvent::Select => "select",
+        }
+    }
+
+    fn command(self, hooks: &TabHooks) -> Option<&str> {
+        match self {
+            HookEvent::Open => hooks.on_tab_open.as_deref(),
+            HookEvent::Close => hooks.on_tab_close.as_deref(),
+            HookEvent::Select => hooks.on_tab_select.as_deref(),
+        }
+    }
+}
+
+/// The tab a hook runs for
+pub struct HookTab<'a> {
+    pub id: usize,
+    pub profile: &'a str,
+    pub working_directory: &'a Path,
+    /// Neovide's process ID, once its window has been found
+    pub pid: Option<u32>,
+    /// The tab's own environment variables (from the config and profile)
+    pub env: &'a [(String, String)],
+}
+
+/// Use `hooks` from now on
+pub fn set_hooks(hooks: TabHooks) {
+    *HOOKS.write().unwrap() = hooks;
+}
+
+/// Run the hook of `event` for `tab`, if one is configured
+pub fn run(event: HookEvent, tab: &HookTab) {
+    let Some(command) = event.command(&HOOKS.read().unwrap()).map(str::to_string) else {
+        return;
+    };
+
+    let mut cmd = shell_command(&command);
+    cmd.envs(tab.env.iter().map(|(name, value)| (name, value)))
+        .envs(hook_env(event, tab));
+    if tab.working_directory.is_dir() {
+        cmd.current_dir(tab.working_directory);
+    }
+    if let Err(e) = cmd.spawn() {
+        eprintln!(
+            "Failed to run {} hook for tab {} ({}): {}",
+            event.name(),
+            tab.id,
+            command,
+            e
+        );
+    }
+}
+
+/// Run `command` in `dir` without waiting for it (a tab's "Open in Terminal")
+pub fn spawn_in_directory(command: &str, dir: &Path) -> std::io::Result<()> {
+    shell_command(command).current_dir(dir).spawn().map(|_| ())
+}
+
+/// `cmd /C command` without a console window
+#[cfg(target_os = "windows")]
+fn shell_command(command: &str) -> Command {
+    let mut cmd = Command::new("cmd");
+    // /S keeps the quotes inside the command as written
+    cmd.raw_arg(format!("/S /C \"{}\"", command))
+        .creation_flags(CREATE_NO_WINDOW);
+    cmd
+}
+
+/// `sh -c command`, so the tab logic also runs (in tests) on other platforms
+#[cfg(not(target_os = "windows"))]
+fn shell_command(command: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command);
+    cmd
+}
+
+/// Environment variables describing `tab` to its hook
+fn hook_env(event: HookEvent, tab: &HookTab) -> [(&'static str, String); 5] {
+    [
+        ("NEOVIDE_TABS_EVENT", event.name().to_string()),
+        ("NEOVIDE_TABS_TAB_ID", tab.id.to_string()),
+        ("NEOVIDE_TABS_PROFILE", tab.profile.to_string()),
+        (
+            "NEOVIDE_TABS_CWD",
+            tab.working_directory.to_string_lossy().into_owned(),
+        ),
+        (
+            "NEOVIDE_TABS_PID",
+            tab.pid.map(|pid| pid.to_string()).unwrap_or_default(),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_env() {
+        let tab = HookTab {
+            id: 3,
+            profile: "Work",
+            working_directory: Path::new(r"C:\src\app"),
+            pid: Some(4242),
+            env: &[],
+        };
+        let env = hook_env(HookEvent::Close, &tab);
+        let value = |name: &str| env.iter().find(|(n, _)| *n == name).unwrap().1.clone();
+        assert_eq!(value("NEOVIDE_TABS_EVENT"), "close");
+        assert_eq!(value("NEOVIDE_TABS_TAB_ID"), "3");
+        assert_eq!(value("NEOVIDE_TABS_PROFILE"), "Work");
+        assert_eq!(value("NEOVIDE_TABS_CWD"), r"C:\src\app");
+        assert_eq!(value("NEOVIDE_TABS_PID"), "4242");
+
+        let tab = HookTab { pid: None, ..tab };
+        let env = hook_env(HookEvent::Open, &tab);
+        assert_eq!(env[0].1, "open");
+        assert_eq!(env[4].1, "");
+    }
+
+    #[test]
+    fn test_hook_command_per_event() {
+        let hooks = TabHooks {
+            on_tab_open: Some("start".to_string()),
+            on_tab_close: None,
+            on_tab_select: Some("select".to_string()),
+        };
+        assert_eq!(HookEvent::Open.command(&hooks), Some("start"));
+        assert_eq!(HookEvent::Close.command(&hooks), None);
+        assert_eq!(HookEvent::Select.command(&hooks), Some("select"));
+    }
+}
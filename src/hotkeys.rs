@@ -7,11 +7,12 @@
 use std::collections::HashMap;
 use windows::Win32::Foundation::HWND;
 use windows::Win32::UI::Input::KeyboardAndMouse::{
-    HOT_KEY_MODIFIERS, MOD_ALT, MOD_CONTROL, MOD_SHIFT, MOD_WIN, RegisterHotKey, UnregisterHotKey,
-    VK_0, VK_1, VK_2, VK_3, VK_4, VK_5, VK_6, VK_7, VK_8, VK_9, VK_A, VK_B, VK_C, VK_D, VK_E, VK_F,
-    VK_F1, VK_F2, VK_F3, VK_F4, VK_F5, VK_F6, VK_F7, VK_F8, VK_F9, VK_F10, VK_F11, VK_F12, VK_G,
-    VK_H, VK_I, VK_J, VK_K, VK_L, VK_M, VK_N, VK_O, VK_P, VK_Q, VK_R, VK_S, VK_T, VK_U, VK_V, VK_W,
-    VK_X, VK_Y, VK_Z,
+    GetAsyncKeyState, HOT_KEY_MODIFIERS, MOD_ALT, MOD_CONTROL, MOD_SHIFT, MOD_WIN, RegisterHotKey,
+    UnregisterHotKey, VIRTUAL_KEY, VK_0, VK_1, VK_2, VK_3, VK_4, VK_5, VK_6, VK_7, VK_8, VK_9,
+    VK_A, VK_B, VK_C, VK_CONTROL, VK_D, VK_DOWN, VK_E, VK_F, VK_F1, VK_F2, VK_F3, VK_F4, VK_F5,
+    VK_F6, VK_F7, VK_F8, VK_F9, VK_F10, VK_F11, VK_F12, VK_G, VK_H, VK_I, VK_J, VK_K, VK_L,
+    VK_LEFT, VK_LWIN, VK_M, VK_MENU, VK_N, VK_O, VK_P, VK_Q, VK_R, VK_RIGHT, VK_RWIN, VK_S,
+    VK_SHIFT, VK_T, VK_U, VK_UP, VK_V, VK_W, VK_X, VK_Y, VK_Z,
 };
 
 /// Base ID for tab hotkeys (1-10)
@@ -21,6 +22,30 @@ pub const TAB_HOTKEY_BASE: i32 = 1;
 /// Base ID for profile hotkeys (101+)
 pub const PROFILE_HOTKEY_BASE: i32 = 101;
 
+/// Hotkey ID for the "New tab in folder..." action (action hotkeys use 51-100)
+pub const NEW_TAB_IN_FOLDER_HOTKEY_ID: i32 = 51;
+
+/// Hotkey ID for toggling the task manager popup
+pub const TASK_MANAGER_HOTKEY_ID: i32 = 52;
+
+/// Hotkey IDs for closing the other tabs / the tabs to the right of the selected tab
+pub const CLOSE_OTHER_TABS_HOTKEY_ID: i32 = 53;
+pub const CLOSE_TABS_TO_RIGHT_HOTKEY_ID: i32 = 54;
+
+/// Hotkey IDs for walking the tab selection history back and forward
+pub const TAB_BACK_HOTKEY_ID: i32 = 55;
+pub const TAB_FORWARD_HOTKEY_ID: i32 = 56;
+
+/// Hotkey ID for moving the keyboard focus to the tab bar
+pub const FOCUS_TAB_BAR_HOTKEY_ID: i32 = 57;
+
+/// Hotkey ID for toggling the debug HUD
+pub const DEBUG_HUD_HOTKEY_ID: i32 = 58;
+
+/// Hotkey IDs for cycling to the next / previous tab
+pub const NEXT_TAB_HOTKEY_ID: i32 = 59;
+pub const PREV_TAB_HOTKEY_ID: i32 = 60;
+
 /// Parsed hotkey with modifiers and virtual key code
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ParsedHotkey {
@@ -94,6 +119,15 @@ fn parse_key_name(key: &str) -> Option<u32> {
         };
     }
 
+    // Arrow keys
+    match key_upper.as_str() {
+        "LEFT" => return Some(VK_LEFT.0 as u32),
+        "RIGHT" => return Some(VK_RIGHT.0 as u32),
+        "UP" => return Some(VK_UP.0 as u32),
+        "DOWN" => return Some(VK_DOWN.0 as u32),
+        _ => {}
+    }
+
     // Single character (number or letter)
     if key.len() == 1 {
         let c = key.chars().next()?;
@@ -212,6 +246,77 @@ pub fn register_profile_hotkeys(hwnd: HWND, profiles: &[crate::config::Profile])
     registered
 }
 
+/// Register a single application action hotkey (e.g. "New tab in folder...").
+/// Returns the hotkey ID if it was registered.
+pub fn register_action_hotkey(hwnd: HWND, id: i32, hotkey_str: Option<&str>) -> Option<i32> {
+    let hotkey_str = hotkey_str?;
+    match parse_hotkey_string(hotkey_str) {
+        Some(parsed) if register_hotkey(hwnd, id, &parsed) => Some(id),
+        Some(_) => None,
+        None => {
+            eprintln!("Warning: Invalid action hotkey format: '{}'", hotkey_str);
+            None
+        }
+    }
+}
+
+/// Key of each tab hotkey as drawn on its tab (e.g. "1", "0", "F1"), by tab index
+/// (0-9). A tab with several hotkeys shows the shortest key.
+pub fn tab_hotkey_labels(tab_hotkeys: &HashMap<String, u32>) -> Vec<Option<String>> {
+    let mut labels: Vec<Option<String>> = vec![None; 10];
+    for (hotkey_str, &tab_num) in tab_hotkeys {
+        let Some(slot) = (tab_num as usize)
+            .checked_sub(1)
+            .and_then(|index| labels.get_mut(index))
+        else {
+            continue;
+        };
+        if parse_hotkey_string(hotkey_str).is_none() {
+            continue;
+        }
+        let key = hotkey_str
+            .rsplit('+')
+            .next()
+            .unwrap_or("")
+            .trim()
+            .to_uppercase();
+        if slot
+            .as_ref()
+            .is_none_or(|current| (key.len(), &key) < (current.len(), current))
+        {
+            *slot = Some(key);
+        }
+    }
+    labels
+}
+
+/// Modifiers of the tab hotkeys (those of the lowest tab's if they differ)
+pub fn tab_hotkey_modifiers(tab_hotkeys: &HashMap<String, u32>) -> Option<HOT_KEY_MODIFIERS> {
+    tab_hotkeys
+        .iter()
+        .filter_map(|(hotkey_str, &tab_num)| {
+            Some((tab_num, parse_hotkey_string(hotkey_str)?.modifiers))
+        })
+        .min_by_key(|(tab_num, modifiers)| (*tab_num, modifiers.0))
+        .map(|(_, modifiers)| modifiers)
+}
+
+/// Check whether all of `modifiers` are held down right now
+pub fn modifiers_held(modifiers: HOT_KEY_MODIFIERS) -> bool {
+    // The high bit of the state (the sign) is set while the key is down
+    let down = |vk: VIRTUAL_KEY| unsafe { GetAsyncKeyState(vk.0 as i32) } < 0;
+    let held = [
+        (MOD_CONTROL, down(VK_CONTROL)),
+        (MOD_SHIFT, down(VK_SHIFT)),
+        (MOD_ALT, down(VK_MENU)),
+        (MOD_WIN, down(VK_LWIN) || down(VK_RWIN)),
+    ];
+    modifiers.0 != 0
+        && held
+            .iter()
+            .all(|(modifier, down)| modifiers.0 & modifier.0 == 0 || *down)
+}
+
 /// Check if a hotkey ID is a tab hotkey (1-10)
 pub fn is_tab_hotkey(id: i32) -> bool {
     (1..=10).contains(&id)
@@ -245,6 +350,30 @@ pub fn profile_index_from_hotkey_id(id: i32) -> Option<usize> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_tab_hotkey_labels() {
+        let mut tab_hotkeys = HashMap::new();
+        tab_hotkeys.insert("Ctrl+Shift+1".to_string(), 1);
+        tab_hotkeys.insert("Ctrl+Shift+0".to_string(), 10);
+        tab_hotkeys.insert("Alt+F2".to_string(), 2);
+        tab_hotkeys.insert("Alt+2".to_string(), 2);
+        tab_hotkeys.insert("Ctrl+Shift+Bogus".to_string(), 3);
+        tab_hotkeys.insert("Ctrl+Shift+4".to_string(), 11);
+
+        let labels = tab_hotkey_labels(&tab_hotkeys);
+        assert_eq!(labels.len(), 10);
+        assert_eq!(labels[0].as_deref(), Some("1"));
+        assert_eq!(labels[1].as_deref(), Some("2"));
+        assert_eq!(labels[2], None);
+        assert_eq!(labels[9].as_deref(), Some("0"));
+
+        assert_eq!(
+            tab_hotkey_modifiers(&tab_hotkeys),
+            Some(MOD_CONTROL | MOD_SHIFT)
+        );
+        assert_eq!(tab_hotkey_modifiers(&HashMap::new()), None);
+    }
+
     #[test]
     fn test_parse_hotkey_ctrl_shift_1() {
         let parsed = parse_hotkey_string("Ctrl+Shift+1").unwrap();
@@ -266,6 +395,17 @@ mod tests {
         assert_eq!(parsed.vk, VK_A.0 as u32);
     }
 
+    #[test]
+    fn test_parse_hotkey_arrow_keys() {
+        let parsed = parse_hotkey_string("Alt+Left").unwrap();
+        assert_eq!(parsed.modifiers, MOD_ALT);
+        assert_eq!(parsed.vk, VK_LEFT.0 as u32);
+        assert_eq!(
+            parse_hotkey_string("Ctrl+down").unwrap().vk,
+            VK_DOWN.0 as u32
+        );
+    }
+
     #[test]
     fn test_parse_hotkey_case_insensitive() {
         let parsed = parse_hotkey_string("ctrl+SHIFT+f1").unwrap();
@@ -359,6 +499,27 @@ mod tests {
         assert!(!is_profile_hotkey(100));
     }
 
+    #[test]
+    fn test_action_hotkey_ids_do_not_overlap() {
+        assert!(!is_tab_hotkey(NEW_TAB_IN_FOLDER_HOTKEY_ID));
+        assert!(!is_profile_hotkey(NEW_TAB_IN_FOLDER_HOTKEY_ID));
+        assert!(!is_tab_hotkey(TASK_MANAGER_HOTKEY_ID));
+        assert!(!is_profile_hotkey(TASK_MANAGER_HOTKEY_ID));
+        for id in [
+            CLOSE_OTHER_TABS_HOTKEY_ID,
+            CLOSE_TABS_TO_RIGHT_HOTKEY_ID,
+            TAB_BACK_HOTKEY_ID,
+            TAB_FORWARD_HOTKEY_ID,
+            FOCUS_TAB_BAR_HOTKEY_ID,
+            DEBUG_HUD_HOTKEY_ID,
+            NEXT_TAB_HOTKEY_ID,
+            PREV_TAB_HOTKEY_ID,
+        ] {
+            assert!(!is_tab_hotkey(id));
+            assert!(!is_profile_hotkey(id));
+        }
+    }
+
     #[test]
     fn test_tab_index_from_hotkey_id() {
         assert_eq!(tab_index_from_hotkey_id(1), Some(0));
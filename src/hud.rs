@@ -0,0 +1,160 @@
+//! Debug HUD: timing metrics drawn over the title bar.
+//!
+//! Toggled with the `debug_hud` hotkey or started with `--debug-hud`. It shows how
+//! long painting the title bar takes, how late the process poll timer fires (a
+//! stand-in for message loop latency: the timer can only fire when the loop is
+//! free), how long a poll takes, and what each tab's Neovide is doing. Meant for
+//! diagnosing repaint and focus timer complaints, not for everyday use.
+
+#![cfg(target_os = "windows")]
+
+use std::time::{Duration, Instant};
+
+use crate::tabs::TabState;
+
+/// Weight of a new sample in the running average
+const AVERAGE_WEIGHT: f64 = 0.1;
+
+/// Last, average and maximum of a measured duration, in milliseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Timing {
+    last: f64,
+    average: f64,
+    max: f64,
+    samples: u64,
+}
+
+impl Timing {
+    fn record(&mut self, duration: Duration) {
+        let ms = duration.as_secs_f64() * 1000.0;
+        self.last = ms;
+        self.max = self.max.max(ms);
+        self.average = if self.samples == 0 {
+            ms
+        } else {
+            self.average + (ms - self.average) * AVERAGE_WEIGHT
+        };
+        self.samples += 1;
+    }
+
+    /// e.g. "1.2 ms (avg 0.9, max 4.0)"
+    fn summary(&self) -> String {
+        if self.samples == 0 {
+            return "-".to_string();
+        }
+        format!(
+            "{:.1} ms (avg {:.1}, max {:.1})",
+            self.last, self.average, self.max
+        )
+    }
+}
+
+/// Metrics collected while the HUD is shown
+#[derive(Debug, Default)]
+pub struct DebugHud {
+    paint: Timing,
+    timer_lag: Timing,
+    poll: Timing,
+    last_poll: Option<Instant>,
+}
+
+impl DebugHud {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record how long painting the title bar took
+    pub fn record_paint(&mut self, duration: Duration) {
+        self.paint.record(duration);
+    }
+
+    /// Record a poll of the timer firing every `interval` that started at `started`
+    /// and just finished
+    pub fn record_poll(&mut self, started: Instant, interval: Duration) {
+        if let Some(last) = self.last_poll {
+            self.timer_lag
+                .record(started.duration_since(last).saturating_sub(interval));
+        }
+        self.last_poll = Some(started);
+        self.poll.record(started.elapsed());
+    }
+
+    /// The HUD's two lines of text; `tabs` are the states of the tabs in tab bar
+    /// order with the selected one marked
+    pub fn lines(&self, tabs: &[(TabState, bool)]) -> [String; 2] {
+        let timings = format!(
+            "paint {}  |  timer lag {}  |  poll {}",
+            self.paint.summary(),
+            self.timer_lag.summary(),
+            self.poll.summary()
+        );
+        let states: Vec<String> = tabs
+            .iter()
+            .enumerate()
+            .map(|(i, (state, selected))| {
+                format!(
+                    "#{}{} {}",
+                    i + 1,
+                    if *selected { "*" } else { "" },
+                    state.label()
+                )
+            })
+            .collect();
+        let count = format!(
+            "{} tab{}",
+            tabs.len(),
+            if tabs.len() == 1 { "" } else { "s" }
+        );
+        let tab_line = if states.is_empty() {
+            count
+        } else {
+            format!("{}: {}", count, states.join("  "))
+        };
+        [timings, tab_line]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timing_record() {
+        let mut timing = Timing::default();
+        assert_eq!(timing.summary(), "-");
+        timing.record(Duration::from_millis(2));
+        assert_eq!(timing.summary(), "2.0 ms (avg 2.0, max 2.0)");
+        timing.record(Duration::from_millis(12));
+        assert_eq!(timing.summary(), "12.0 ms (avg 3.0, max 12.0)");
+        timing.record(Duration::from_millis(1));
+        assert_eq!(timing.last, 1.0);
+        assert_eq!(timing.max, 12.0);
+    }
+
+    #[test]
+    fn test_record_poll_lag() {
+        let mut hud = DebugHud::new();
+        let interval = Duration::from_millis(250);
+        let start = Instant::now();
+        hud.record_poll(start, interval);
+        assert_eq!(hud.timer_lag.samples, 0);
+        hud.record_poll(start + Duration::from_millis(290), interval);
+        assert_eq!(hud.timer_lag.last, 40.0);
+        // Early ticks count as no lag
+        hud.record_poll(start + Duration::from_millis(500), interval);
+        assert_eq!(hud.timer_lag.last, 0.0);
+    }
+
+    #[test]
+    fn test_lines() {
+        let hud = DebugHud::new();
+        let [timings, tabs] = hud.lines(&[(TabState::Ready, false), (TabState::Starting, true)]);
+        assert_eq!(timings, "paint -  |  timer lag -  |  poll -");
+        assert_eq!(tabs, "2 tabs: #1 ready  #2* starting");
+        assert_eq!(hud.lines(&[])[1], "0 tabs");
+        assert_eq!(
+            hud.lines(&[(TabState::Failed, true)])[1],
+            "1 tab: #1* failed"
+        );
+    }
+}
@@ -1,35 +1,55 @@
 //! Icon loading and caching for profile icons.
 //!
-//! Two icons are bundled into the executable:
+//! Icons bundled into the executable:
 //! - `neovide.png` - the default tab icon (Neovide logo for profiles)
 //! - `neovide-tabs.png` - the application window icon (for taskbar/Alt-Tab)
+//! - `project-*.svg` - project type icons (see `projects`)
 //!
-//! Both are extracted to `~/.local/share/neovide-tabs/` at runtime.
+//! All are extracted to `~/.local/share/neovide-tabs/` at runtime.
 //! User-defined icons are loaded from full paths specified in the config.
 //!
 //! Note: This module uses thread-local storage since Win32 GDI handles
 //! (HBITMAP) are not thread-safe and should not cross thread boundaries.
+//! Profile icons are decoded ahead of time on a background thread (see
+//! `preload_icons`); only the decoded pixels cross over, and the UI thread turns
+//! them into bitmaps when WM_ICONS_DECODED arrives.
 
 #![cfg(target_os = "windows")]
 
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::thread;
 
-use windows::Win32::Foundation::HWND;
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
 use windows::Win32::Graphics::Gdi::{
     BI_RGB, BITMAPINFO, BITMAPINFOHEADER, CreateCompatibleBitmap, CreateCompatibleDC,
     CreateDIBSection, DIB_RGB_COLORS, DeleteDC, DeleteObject, GetDC, HBITMAP, HGDIOBJ, ReleaseDC,
     SetDIBits,
 };
-use windows::Win32::UI::WindowsAndMessaging::{CreateIconIndirect, HICON, ICONINFO};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateIconIndirect, HICON, ICONINFO, PostMessageW, WM_APP,
+};
 
 use crate::config::{APP_ICON, DEFAULT_ICON, data_dir_path};
+use crate::projects::ProjectType;
 
 /// Size of icons in the tab bar (16x16 pixels)
 pub const ICON_SIZE: i32 = 16;
 
+/// Pixel sizes icons are rendered at; the one matching the DPI is drawn into the
+/// ICON_SIZE slot so icons stay sharp instead of being upscaled
+pub const ICON_RENDER_SIZES: [i32; 4] = [16, 20, 24, 32];
+
+/// DPI at which ICON_SIZE pixels are drawn 1:1
+const BASE_DPI: u32 = 96;
+
+/// Size of the ICO file header and of each image directory entry
+const ICO_HEADER_SIZE: usize = 6;
+const ICO_ENTRY_SIZE: usize = 16;
+
 /// Size of window icons (32x32 pixels for better quality in taskbar/Alt-Tab)
 pub const WINDOW_ICON_SIZE: i32 = 32;
 
@@ -39,6 +59,43 @@ const BUNDLED_TAB_ICON_BYTES: &[u8] = include_bytes!("../neovide.png");
 /// The bundled application window icon (embedded at compile time)
 const BUNDLED_APP_ICON_BYTES: &[u8] = include_bytes!("../neovide-tabs.png");
 
+/// The bundled project type icons (embedded at compile time)
+const BUNDLED_PROJECT_ICONS: [(&str, &[u8]); 4] = [
+    (
+        ProjectType::Rust.icon(),
+        include_bytes!("../assets/project-rust.svg"),
+    ),
+    (
+        ProjectType::Node.icon(),
+        include_bytes!("../assets/project-node.svg"),
+    ),
+    (
+        ProjectType::Go.icon(),
+        include_bytes!("../assets/project-go.svg"),
+    ),
+    (
+        ProjectType::Git.icon(),
+        include_bytes!("../assets/project-git.svg"),
+    ),
+];
+
+/// Posted to the wrapper by the preload thread each time an icon has been decoded
+pub const WM_ICONS_DECODED: u32 = WM_APP + 16;
+
+/// Icons handed from the preload thread to the UI thread
+#[derive(Default)]
+struct Preload {
+    /// Icons queued for decoding that the UI thread hasn't received yet
+    pending: HashSet<IconKey>,
+    /// Decoded pixels (None if the icon failed to load) waiting to become bitmaps
+    decoded: Vec<(IconKey, Option<image::RgbaImage>)>,
+}
+
+/// Cache key: icon path or filename and the pixel size it is rendered at
+type IconKey = (String, i32);
+
+static PRELOAD: LazyLock<Mutex<Preload>> = LazyLock::new(Mutex::default);
+
 /// A cached icon bitmap
 pub struct CachedIcon {
     /// The Win32 bitmap handle
@@ -61,11 +118,13 @@ impl Drop for CachedIcon {
     }
 }
 
-/// Icon cache storing loaded bitmaps by path/filename (thread-local)
+/// Icon cache storing loaded bitmaps by path/filename and size (thread-local)
 struct IconCache {
-    cache: HashMap<String, Option<CachedIcon>>,
+    cache: HashMap<IconKey, Option<CachedIcon>>,
     data_dir: Option<PathBuf>,
-    fallback_icon: Option<CachedIcon>,
+    fallback_icons: HashMap<i32, Option<CachedIcon>>,
+    /// Pixel size requested for the current DPI
+    render_size: i32,
 }
 
 impl IconCache {
@@ -73,70 +132,168 @@ impl IconCache {
         Self {
             cache: HashMap::new(),
             data_dir: data_dir_path(),
-            fallback_icon: None,
+            fallback_icons: HashMap::new(),
+            render_size: ICON_SIZE,
         }
     }
 
     /// Get or load an icon by path or filename.
     /// For the default icon (neovide-tabs.png), loads from data directory.
     /// For user icons, treats the string as a full path.
-    fn get_or_load(&mut self, icon_path: &str) -> Option<HBITMAP> {
+    fn get_or_load(&mut self, key: &IconKey) -> Option<HBITMAP> {
         // Check if already cached
-        if !self.cache.contains_key(icon_path) {
+        if !self.cache.contains_key(key) {
             // Try to load the icon
-            let icon = self.load_icon(icon_path);
-            self.cache.insert(icon_path.to_string(), icon);
+            let (icon_path, size) = key;
+            let icon = decode_icon(icon_path, self.data_dir.as_deref(), *size)
+                .and_then(|rgba| create_bitmap_from_rgba(&rgba, *size, *size));
+            self.cache.insert(key.clone(), icon);
         }
 
         self.cache
-            .get(icon_path)
+            .get(key)
             .and_then(|opt| opt.as_ref())
             .map(|icon| icon.hbitmap)
     }
 
-    /// Load an icon from the appropriate location.
-    /// Default icon: loaded from data directory (~/.local/share/neovide-tabs/)
-    /// User icon: loaded from the full path specified
-    /// Supports both PNG and SVG formats (detected by file extension).
-    fn load_icon(&self, icon_path: &str) -> Option<CachedIcon> {
-        let path = if icon_path == DEFAULT_ICON {
-            // Default icon - load from data directory
-            let data_dir = self.data_dir.as_ref()?;
-            data_dir.join(icon_path)
-        } else {
-            // User-defined icon - treat as full path
-            PathBuf::from(icon_path)
-        };
+    /// Get the fallback icon at `size` (creates it if needed)
+    fn get_fallback(&mut self, size: i32) -> Option<HBITMAP> {
+        self.fallback_icons
+            .entry(size)
+            .or_insert_with(|| create_fallback_icon(size))
+            .as_ref()
+            .map(|icon| icon.hbitmap)
+    }
+}
 
-        if !path.exists() {
-            eprintln!("Icon file not found: {:?}", path);
-            return None;
-        }
+// Thread-local icon cache (Win32 GDI handles are not thread-safe)
+thread_local! {
+    static ICON_CACHE: RefCell<IconCache> = RefCell::new(IconCache::new());
+}
+
+/// Smallest render size covering ICON_SIZE at `dpi` (the largest one beyond that)
+pub fn icon_render_size(dpi: u32) -> i32 {
+    let wanted = (ICON_SIZE as u32 * dpi).div_ceil(BASE_DPI) as i32;
+    ICON_RENDER_SIZES
+        .into_iter()
+        .find(|&size| size >= wanted)
+        .unwrap_or(ICON_RENDER_SIZES[ICON_RENDER_SIZES.len() - 1])
+}
+
+/// Render icons for `dpi` from now on. Sizes rendered before stay cached, so
+/// moving between monitors doesn't decode them again.
+pub fn set_icon_dpi(dpi: u32) {
+    ICON_CACHE.with(|cache| cache.borrow_mut().render_size = icon_render_size(dpi));
+}
+
+/// Load an icon's pixels from the appropriate location, resized to `size` pixels.
+/// Default and project icons: loaded from data directory (~/.local/share/neovide-tabs/)
+/// User icon: loaded from the full path specified
+/// Supports PNG, SVG and ICO formats (detected by file extension); other raster
+/// formats the image crate reads (GIF, BMP, ...) load like PNG, using the first frame.
+fn decode_icon(icon_path: &str, data_dir: Option<&Path>, size: i32) -> Option<image::RgbaImage> {
+    let is_bundled = icon_path == DEFAULT_ICON
+        || BUNDLED_PROJECT_ICONS
+            .iter()
+            .any(|(name, _)| *name == icon_path);
+    let path = if is_bundled {
+        // Bundled icon - load from data directory
+        data_dir?.join(icon_path)
+    } else {
+        // User-defined icon - treat as full path
+        PathBuf::from(icon_path)
+    };
 
-        // Check file extension to determine loader
-        let extension = path
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .map(|ext| ext.to_lowercase());
+    if !path.exists() {
+        eprintln!("Icon file not found: {:?}", path);
+        return None;
+    }
+
+    // Check file extension to determine loader
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase());
 
-        match extension.as_deref() {
-            Some("svg") => load_svg_as_bitmap(&path),
-            _ => load_png_as_bitmap(&path),
+    match extension.as_deref() {
+        Some("svg") => decode_svg(&path, size),
+        Some("ico") => decode_ico(&path, size),
+        _ => decode_png(&path, size),
+    }
+}
+
+/// Decode `icon_paths` at the current render size on a background thread so the
+/// first paint showing an icon doesn't have to. Icons not cached yet are skipped
+/// by `get_icon_bitmap` until the wrapper receives WM_ICONS_DECODED and calls
+/// `receive_decoded_icons`.
+pub fn preload_icons(hwnd: HWND, icon_paths: Vec<String>) {
+    let (cached, size) = ICON_CACHE.with(|cache| {
+        let cache = cache.borrow();
+        let cached: HashSet<IconKey> = cache.cache.keys().cloned().collect();
+        (cached, cache.render_size)
+    });
+    let queued: Vec<IconKey> = {
+        let mut preload = PRELOAD.lock().unwrap();
+        let mut queued = Vec::new();
+        for path in icon_paths {
+            let key = (path, size);
+            if !cached.contains(&key) && preload.pending.insert(key.clone()) {
+                queued.push(key);
+            }
         }
+        queued
+    };
+    if queued.is_empty() {
+        return;
     }
 
-    /// Get the fallback icon (creates it if needed)
-    fn get_fallback(&mut self) -> Option<HBITMAP> {
-        if self.fallback_icon.is_none() {
-            self.fallback_icon = create_fallback_icon();
+    let hwnd_value = hwnd.0 as isize;
+    let data_dir = data_dir_path();
+    thread::spawn(move || {
+        for key in queued {
+            let rgba = decode_icon(&key.0, data_dir.as_deref(), key.1);
+            PRELOAD.lock().unwrap().decoded.push((key, rgba));
+            unsafe {
+                let hwnd = HWND(hwnd_value as *mut std::ffi::c_void);
+                let _ = PostMessageW(hwnd, WM_ICONS_DECODED, WPARAM(0), LPARAM(0));
+            }
+        }
+    });
+}
+
+/// Turn the icons decoded by the preload thread into cached bitmaps.
+/// Returns true if any icon was added (the tab bar should be repainted).
+pub fn receive_decoded_icons() -> bool {
+    let decoded = {
+        let mut preload = PRELOAD.lock().unwrap();
+        let decoded = std::mem::take(&mut preload.decoded);
+        for (key, _) in &decoded {
+            preload.pending.remove(key);
         }
-        self.fallback_icon.as_ref().map(|icon| icon.hbitmap)
+        decoded
+    };
+    if decoded.is_empty() {
+        return false;
     }
+
+    ICON_CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        for (key, rgba) in decoded {
+            let icon = rgba.and_then(|rgba| create_bitmap_from_rgba(&rgba, key.1, key.1));
+            cache.cache.entry(key).or_insert(icon);
+        }
+    });
+    true
 }
 
-// Thread-local icon cache (Win32 GDI handles are not thread-safe)
-thread_local! {
-    static ICON_CACHE: RefCell<IconCache> = RefCell::new(IconCache::new());
+/// Whether the preload thread is still decoding any icon
+pub fn icons_pending() -> bool {
+    !PRELOAD.lock().unwrap().pending.is_empty()
+}
+
+/// Whether the icon is still being decoded by the preload thread
+fn is_preloading(key: &IconKey) -> bool {
+    PRELOAD.lock().unwrap().pending.contains(key)
 }
 
 /// Clear the icon cache, forcing all icons to be reloaded on next access.
@@ -145,7 +302,7 @@ pub fn clear_icon_cache() {
     ICON_CACHE.with(|cache| {
         let mut cache = cache.borrow_mut();
         cache.cache.clear();
-        // Note: Keep fallback_icon and data_dir as they don't change
+        // Note: Keep fallback_icons and data_dir as they don't change
     });
 }
 
@@ -188,23 +345,46 @@ pub fn ensure_default_icon_extracted() {
             app_icon_path, e
         );
     }
+
+    // Extract the project type icons
+    for (name, bytes) in BUNDLED_PROJECT_ICONS {
+        let icon_path = data_dir.join(name);
+        if !icon_path.exists()
+            && let Err(e) = fs::write(&icon_path, bytes)
+        {
+            eprintln!(
+                "Warning: Failed to extract project icon to {:?}: {}",
+                icon_path, e
+            );
+        }
+    }
 }
 
-/// Get an icon bitmap handle for the given path or filename.
+/// Get an icon bitmap handle for the given path or filename, rendered at the size
+/// for the current DPI (draw it into an ICON_SIZE square).
 /// For default icon (neovide-tabs.png), loads from data directory.
 /// For user icons, loads from the full path.
-/// Returns a fallback icon if the specified icon cannot be loaded.
+/// Returns a fallback icon if the specified icon cannot be loaded, and None while
+/// the icon is still being decoded in the background.
 pub fn get_icon_bitmap(icon_path: &str) -> Option<HBITMAP> {
     ICON_CACHE.with(|cache| {
         let mut cache = cache.borrow_mut();
+        let size = cache.render_size;
+        let key = (icon_path.to_string(), size);
+
+        // Draw nothing rather than decode on the UI thread; the preload thread
+        // reports back shortly
+        if !cache.cache.contains_key(&key) && is_preloading(&key) {
+            return None;
+        }
 
         // Try to get the requested icon
-        if let Some(hbitmap) = cache.get_or_load(icon_path) {
+        if let Some(hbitmap) = cache.get_or_load(&key) {
             return Some(hbitmap);
         }
 
         // Fall back to default icon
-        cache.get_fallback()
+        cache.get_fallback(size)
     })
 }
 
@@ -337,8 +517,8 @@ fn create_hicon_from_rgba(rgba: &image::RgbaImage, size: i32) -> Option<HICON> {
 /// SVG is rendered at this multiple of the target size, then downsampled.
 const SVG_RENDER_SCALE: u32 = 4;
 
-/// Load an SVG file, rasterize it, and convert to a Win32 HBITMAP
-fn load_svg_as_bitmap(path: &Path) -> Option<CachedIcon> {
+/// Load an SVG file and rasterize it to `size` pixels
+fn decode_svg(path: &Path, size: i32) -> Option<image::RgbaImage> {
     // Read the SVG file
     let svg_data = fs::read(path).ok()?;
 
@@ -347,7 +527,7 @@ fn load_svg_as_bitmap(path: &Path) -> Option<CachedIcon> {
     let tree = resvg::usvg::Tree::from_data(&svg_data, &options).ok()?;
 
     // Render at higher resolution for quality, then downsample
-    let render_size = ICON_SIZE as u32 * SVG_RENDER_SCALE;
+    let render_size = size as u32 * SVG_RENDER_SCALE;
     let mut pixmap = resvg::tiny_skia::Pixmap::new(render_size, render_size)?;
 
     // Calculate the transform to fit the SVG into the render size
@@ -391,34 +571,86 @@ fn load_svg_as_bitmap(path: &Path) -> Option<CachedIcon> {
     // Downsample to target size using high-quality filter
     let img = image::DynamicImage::ImageRgba8(rgba);
     let resized = img.resize_exact(
-        ICON_SIZE as u32,
-        ICON_SIZE as u32,
+        size as u32,
+        size as u32,
         image::imageops::FilterType::Lanczos3,
     );
-    let rgba = resized.to_rgba8();
+    Some(resized.to_rgba8())
+}
 
-    create_bitmap_from_rgba(&rgba, ICON_SIZE, ICON_SIZE)
+/// Load an .ico file and resize the image best matching `size` to `size` pixels
+fn decode_ico(path: &Path, size: i32) -> Option<image::RgbaImage> {
+    let data = fs::read(path).ok()?;
+    let img = decode_ico_image(&data, size as u32)?;
+    let img = img.resize_exact(
+        size as u32,
+        size as u32,
+        image::imageops::FilterType::Lanczos3,
+    );
+    Some(img.to_rgba8())
 }
 
-/// Load a PNG file and convert it to a Win32 HBITMAP
-fn load_png_as_bitmap(path: &Path) -> Option<CachedIcon> {
+/// Decode the image of an ICO file best matching `size`: the smallest one at
+/// least that big (the most colors among equals), otherwise the largest one
+fn decode_ico_image(data: &[u8], size: u32) -> Option<image::DynamicImage> {
+    let u16_at = |bytes: &[u8], at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]);
+    let u32_at = |bytes: &[u8], at: usize| {
+        u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+    };
+
+    // ICONDIR: reserved (0), type (1 = icon), image count
+    if data.len() < ICO_HEADER_SIZE || u16_at(data, 0) != 0 || u16_at(data, 2) != 1 {
+        return None;
+    }
+    let count = u16_at(data, 4) as usize;
+    let entries: Vec<&[u8]> = (0..count)
+        .filter_map(|i| {
+            data.get(ICO_HEADER_SIZE + i * ICO_ENTRY_SIZE..)?
+                .get(..ICO_ENTRY_SIZE)
+        })
+        .collect();
+
+    // A width byte of 0 means 256 pixels
+    let dimension = |entry: &[u8]| if entry[0] == 0 { 256 } else { entry[0] as u32 };
+    let bit_count = |entry: &[u8]| u16_at(entry, 6);
+    let best = entries
+        .iter()
+        .filter(|entry| dimension(entry) >= size)
+        .min_by_key(|entry| (dimension(entry), std::cmp::Reverse(bit_count(entry))))
+        .or_else(|| {
+            entries
+                .iter()
+                .max_by_key(|entry| (dimension(entry), bit_count(entry)))
+        })?;
+
+    // Re-wrap the chosen image as a single-image ICO so the image crate's decoder
+    // (which always takes the largest image) returns it
+    let length = u32_at(best, 8) as usize;
+    let offset = u32_at(best, 12) as usize;
+    let image_data = data.get(offset..offset.checked_add(length)?)?;
+    let mut single = Vec::with_capacity(ICO_HEADER_SIZE + ICO_ENTRY_SIZE + length);
+    single.extend_from_slice(&[0, 0, 1, 0, 1, 0]);
+    single.extend_from_slice(&best[..8]);
+    single.extend_from_slice(&(length as u32).to_le_bytes());
+    single.extend_from_slice(&((ICO_HEADER_SIZE + ICO_ENTRY_SIZE) as u32).to_le_bytes());
+    single.extend_from_slice(image_data);
+    image::load_from_memory_with_format(&single, image::ImageFormat::Ico).ok()
+}
+
+/// Load a PNG (or other raster) file and resize it to `size` pixels
+fn decode_png(path: &Path, size: i32) -> Option<image::RgbaImage> {
     // Load the image using the image crate
     let img = image::open(path).ok()?;
 
-    // Resize to ICON_SIZE x ICON_SIZE
+    // Resize to size x size
     let img = img.resize_exact(
-        ICON_SIZE as u32,
-        ICON_SIZE as u32,
+        size as u32,
+        size as u32,
         image::imageops::FilterType::Lanczos3,
     );
 
     // Convert to RGBA8
-    let rgba = img.to_rgba8();
-    let width = rgba.width() as i32;
-    let height = rgba.height() as i32;
-
-    // Create the bitmap
-    create_bitmap_from_rgba(&rgba, width, height)
+    Some(img.to_rgba8())
 }
 
 /// Create a Win32 HBITMAP from RGBA pixel data.
@@ -497,9 +729,9 @@ fn create_bitmap_from_rgba(rgba: &image::RgbaImage, width: i32, height: i32) ->
 }
 
 /// Create a simple fallback icon (a colored square)
-fn create_fallback_icon() -> Option<CachedIcon> {
-    // Create a simple 16x16 green square as fallback
-    let mut rgba = image::RgbaImage::new(ICON_SIZE as u32, ICON_SIZE as u32);
+fn create_fallback_icon(size: i32) -> Option<CachedIcon> {
+    // Create a simple green square as fallback
+    let mut rgba = image::RgbaImage::new(size as u32, size as u32);
 
     // Fill with a dark green color (Neovim-ish)
     for pixel in rgba.pixels_mut() {
@@ -507,21 +739,22 @@ fn create_fallback_icon() -> Option<CachedIcon> {
     }
 
     // Add a simple border
-    for x in 0..ICON_SIZE as u32 {
+    for x in 0..size as u32 {
         rgba.put_pixel(x, 0, image::Rgba([60, 120, 50, 255]));
-        rgba.put_pixel(x, (ICON_SIZE - 1) as u32, image::Rgba([60, 120, 50, 255]));
+        rgba.put_pixel(x, (size - 1) as u32, image::Rgba([60, 120, 50, 255]));
     }
-    for y in 0..ICON_SIZE as u32 {
+    for y in 0..size as u32 {
         rgba.put_pixel(0, y, image::Rgba([60, 120, 50, 255]));
-        rgba.put_pixel((ICON_SIZE - 1) as u32, y, image::Rgba([60, 120, 50, 255]));
+        rgba.put_pixel((size - 1) as u32, y, image::Rgba([60, 120, 50, 255]));
     }
 
-    create_bitmap_from_rgba(&rgba, ICON_SIZE, ICON_SIZE)
+    create_bitmap_from_rgba(&rgba, size, size)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testdir::TempDir;
 
     #[test]
     fn test_bundled_tab_icon_is_valid_png() {
@@ -537,6 +770,89 @@ mod tests {
         assert!(img.is_ok(), "Bundled app icon should be a valid image");
     }
 
+    #[test]
+    fn test_decode_icon_resizes_to_icon_size() {
+        let tmp = TempDir::new("icons");
+        let dir = tmp.path();
+        fs::write(dir.join(DEFAULT_ICON), BUNDLED_TAB_ICON_BYTES).unwrap();
+
+        // The default icon comes from the data directory
+        let rgba = decode_icon(DEFAULT_ICON, Some(dir), ICON_SIZE).unwrap();
+        assert_eq!(rgba.dimensions(), (ICON_SIZE as u32, ICON_SIZE as u32));
+
+        // User icons are full paths
+        let full_path = dir.join(DEFAULT_ICON).to_string_lossy().to_string();
+        let rgba = decode_icon(&full_path, None, 24).unwrap();
+        assert_eq!(rgba.dimensions(), (24, 24));
+        assert!(decode_icon(DEFAULT_ICON, None, ICON_SIZE).is_none());
+        assert!(decode_icon(&dir.join("missing.png").to_string_lossy(), None, ICON_SIZE).is_none());
+    }
+
+    /// An ICO file with a solid square image of each of `sizes`
+    fn test_ico(sizes: &[u32]) -> Vec<u8> {
+        let buffers: Vec<Vec<u8>> = sizes
+            .iter()
+            .map(|&size| vec![200u8; (size * size * 4) as usize])
+            .collect();
+        let frames: Vec<image::codecs::ico::IcoFrame> = sizes
+            .iter()
+            .zip(&buffers)
+            .map(|(&size, buffer)| {
+                image::codecs::ico::IcoFrame::as_png(
+                    buffer,
+                    size,
+                    size,
+                    image::ExtendedColorType::Rgba8,
+                )
+                .unwrap()
+            })
+            .collect();
+        let mut data = Vec::new();
+        image::codecs::ico::IcoEncoder::new(&mut data)
+            .encode_images(&frames)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn test_decode_ico_picks_best_size() {
+        let data = test_ico(&[16, 32, 48, 256]);
+        let width = |size| decode_ico_image(&data, size).unwrap().width();
+        assert_eq!(width(16), 16);
+        assert_eq!(width(20), 32);
+        assert_eq!(width(32), 32);
+        assert_eq!(width(64), 256);
+
+        // Nothing big enough: the largest image
+        let small = test_ico(&[16, 24]);
+        assert_eq!(decode_ico_image(&small, 32).unwrap().width(), 24);
+
+        assert!(decode_ico_image(b"not an icon", 16).is_none());
+        assert!(decode_ico_image(&data[..20], 16).is_none());
+    }
+
+    #[test]
+    fn test_icon_render_size() {
+        assert_eq!(icon_render_size(96), 16);
+        assert_eq!(icon_render_size(120), 20);
+        assert_eq!(icon_render_size(144), 24);
+        assert_eq!(icon_render_size(168), 32);
+        assert_eq!(icon_render_size(192), 32);
+        assert_eq!(icon_render_size(288), 32);
+    }
+
+    #[test]
+    fn test_bundled_project_icons_are_valid_svg() {
+        let options = resvg::usvg::Options::default();
+        for (name, bytes) in BUNDLED_PROJECT_ICONS {
+            assert!(
+                resvg::usvg::Tree::from_data(bytes, &options).is_ok(),
+                "Bundled project icon {} should be a valid SVG",
+                name
+            );
+        }
+    }
+
     #[test]
     fn test_data_dir_path() {
         let path = data_dir_path();
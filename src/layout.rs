@@ -0,0 +1,1246 @@
+//! Tab bar geometry: where tabs and the tab bar buttons are, which of them is
+//! under the mouse, how many tabs fit before the overflow button and where a
+//! dragged tab swaps places. Plain integer math in client coordinates, so it is
+//! tested on any platform; the window converts `Rect` to Win32's RECT to paint.
+//!
+//! Like in browsers, tabs closed while the pointer is over the tab bar leave an
+//! empty slot, so the tabs to their right stay put (and a second click doesn't
+//! land on the next tab's close button) until the pointer leaves the tab bar.
+//!
+//! Widgets (clock, session name, ...) sit between the tab bar and the window
+//! buttons. Their space is reserved, so tabs go to the overflow before they'd
+//! run under them; the window says which widgets are shown with `set_widgets`.
+//!
+//! The window buttons (minimize, maximize, close) sit at the right end, or
+//! before the tabs with `window_controls: "left"`; `set_window_controls` says
+//! which.
+
+use std::cell::{Cell, RefCell};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+use crate::config::{TabStyle, WindowControls};
+
+/// Title bar height in pixels
+pub const TITLEBAR_HEIGHT: i32 = 32;
+/// Title bar height in pixels with `tab_style: "two_line"`
+pub const TWO_LINE_TITLEBAR_HEIGHT: i32 = 44;
+/// The title bar is hidden (`hide_titlebar`): Neovide gets the whole window
+static TITLEBAR_HIDDEN: AtomicBool = AtomicBool::new(false);
+/// Top of Neovide's area while the title bar is auto-hidden (`auto_hide_titlebar`),
+/// or -1 when it isn't
+static CONTENT_TOP: AtomicI32 = AtomicI32::new(-1);
+
+thread_local! {
+    /// Tab layout from the `tab_style` config option; the title bar height and
+    /// the tab width follow it
+    static TAB_STYLE: Cell<TabStyle> = const { Cell::new(TabStyle::Normal) };
+    /// Empty slots left by tabs closed while the pointer was over the tab bar
+    static FROZEN_SLOTS: RefCell<FrozenSlots> = RefCell::new(FrozenSlots::default());
+    /// Widgets shown at the right end of the tab bar, left to right
+    static WIDGETS: RefCell<Vec<Widget>> = const { RefCell::new(Vec::new()) };
+    /// Side of the window buttons, and whether they are traffic lights
+    static WINDOW_CONTROLS: Cell<(WindowControls, bool)> =
+        const { Cell::new((WindowControls::Right, false)) };
+    /// Which of the minimize, maximize and close buttons are shown
+    static WINDOW_BUTTONS: Cell<[bool; 3]> = const { Cell::new([true; 3]) };
+}
+/// Button width in pixels
+pub const BUTTON_WIDTH: i32 = 46;
+/// Width of each traffic light button (the dot is smaller, see the window)
+pub const TRAFFIC_LIGHT_WIDTH: i32 = 20;
+/// Space between the traffic lights and the window edge
+pub const TRAFFIC_LIGHTS_MARGIN: i32 = 6;
+
+/// A rectangle in client coordinates (or screen coordinates where noted) with
+/// exclusive right and bottom edges, like Win32's RECT
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Rect {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl Rect {
+    /// Whether (x, y) is inside the rectangle
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.left && x < self.right && y >= self.top && y < self.bottom
+    }
+
+    /// Whether the two rectangles share any area
+    pub fn overlaps(&self, other: &Rect) -> bool {
+        self.left < other.right
+            && self.right > other.left
+            && self.top < other.bottom
+            && self.bottom > other.top
+    }
+
+    /// The rectangle moved inside `area` (shrunk if it is larger) when it lies
+    /// entirely outside of it, e.g. on a display that was disconnected. A
+    /// rectangle that overlaps `area` is returned unchanged: a window may hang off
+    /// the screen edge on purpose.
+    pub fn pulled_into(self, area: &Rect) -> Rect {
+        if self.overlaps(area) {
+            return self;
+        }
+        let width = (self.right - self.left).min(area.right - area.left);
+        let height = (self.bottom - self.top).min(area.bottom - area.top);
+        let left = self.left.clamp(area.left, area.right - width);
+        let top = self.top.clamp(area.top, area.bottom - height);
+        Rect {
+            left,
+            top,
+            right: left + width,
+            bottom: top + height,
+        }
+    }
+
+    /// The rectangle shifted (not resized) to lie inside `area` as far as it fits;
+    /// when it is larger, its top-left corner stays inside
+    pub fn shifted_into(self, area: &Rect) -> Rect {
+        let width = self.right - self.left;
+        let height = self.bottom - self.top;
+        let left = self.left.min(area.right - width).max(area.left);
+        let top = self.top.min(area.bottom - height).max(area.top);
+        Rect {
+            left,
+            top,
+            right: left + width,
+            bottom: top + height,
+        }
+    }
+
+    /// Where a `width` x `height` popup opened from this button (screen
+    /// coordinates) goes within the monitor's `work_area`: below the button at its
+    /// left edge, above it when it doesn't fit below, and shifted left to stay
+    /// on screen
+    pub fn popup_below(&self, width: i32, height: i32, work_area: &Rect) -> Rect {
+        let fits_below = self.bottom + height <= work_area.bottom;
+        let fits_above = self.top - height >= work_area.top;
+        let top = if !fits_below && fits_above {
+            self.top - height
+        } else {
+            self.bottom
+        };
+        Rect {
+            left: self.left,
+            top,
+            right: self.left + width,
+            bottom: top + height,
+        }
+        .shifted_into(work_area)
+    }
+}
+
+/// Small indicators and buttons at the right end of the tab bar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Widget {
+    /// Name of the active session
+    Session,
+    /// High memory banner offering to hibernate background tabs
+    Memory,
+    /// "Update available" badge
+    Update,
+    /// Current time
+    Clock,
+    /// Settings (gear) button
+    Settings,
+}
+
+impl Widget {
+    /// Width reserved for the widget
+    pub fn width(self) -> i32 {
+        match self {
+            Widget::Session => SESSION_WIDGET_WIDTH,
+            Widget::Memory => MEMORY_WIDGET_WIDTH,
+            Widget::Update => UPDATE_WIDGET_WIDTH,
+            Widget::Clock => CLOCK_WIDGET_WIDTH,
+            Widget::Settings => SETTINGS_WIDGET_WIDTH,
+        }
+    }
+}
+
+/// Empty slots in the tab bar (see `freeze_closed_tab`)
+#[derive(Debug, Default)]
+struct FrozenSlots {
+    /// Number of tabs the slots were left among; the slots are dropped once it changes
+    tab_count: usize,
+    /// One entry per empty slot: the index of the tab it is in front of
+    gaps: Vec<usize>,
+}
+
+/// Keep the tabs right of the just closed tab at `index` where they are, leaving
+/// an empty slot, until `unfreeze_tabs`. `tab_count` is the number of tabs left.
+pub fn freeze_closed_tab(index: usize, tab_count: usize) {
+    FROZEN_SLOTS.with_borrow_mut(|frozen| {
+        if frozen.tab_count != tab_count + 1 {
+            frozen.gaps.clear();
+        }
+        // Slots in front of later tabs follow them to their new index
+        for gap in frozen.gaps.iter_mut().filter(|gap| **gap > index) {
+            *gap -= 1;
+        }
+        frozen.gaps.push(index);
+        // Slots after the last tab hold nothing in place
+        frozen.gaps.retain(|&gap| gap < tab_count);
+        frozen.tab_count = tab_count;
+    });
+}
+
+/// Whether closed tabs left empty slots in the tab bar
+pub fn tabs_frozen() -> bool {
+    FROZEN_SLOTS.with_borrow(|frozen| !frozen.gaps.is_empty())
+}
+
+/// Close the empty slots. Returns true if there were any (the tab bar needs a repaint).
+pub fn unfreeze_tabs() -> bool {
+    FROZEN_SLOTS.with_borrow_mut(|frozen| !std::mem::take(&mut frozen.gaps).is_empty())
+}
+
+/// Drop the empty slots if the tabs changed since, or if they'd push tabs into
+/// the overflow
+fn check_frozen_slots(tab_count: usize, client_width: i32) {
+    FROZEN_SLOTS.with_borrow_mut(|frozen| {
+        if frozen.gaps.is_empty() {
+            return;
+        }
+        let slots = (tab_count + frozen.gaps.len()) as i32;
+        let available = get_tab_bar_max_x(client_width)
+            - tab_bar_left()
+            - NEW_TAB_BUTTON_WIDTH
+            - DROPDOWN_BUTTON_WIDTH;
+        if frozen.tab_count != tab_count || slots * tab_width() > available {
+            frozen.gaps.clear();
+        }
+    });
+}
+
+/// Number of slots (tabs and empty ones) in front of the tab at `index`, or in
+/// front of everything after the first `index` tabs with `after_tabs`
+fn slot_index(index: usize, after_tabs: bool) -> i32 {
+    let gaps = FROZEN_SLOTS.with_borrow(|frozen| {
+        frozen
+            .gaps
+            .iter()
+            .filter(|&&gap| gap < index || (gap == index && !after_tabs))
+            .count()
+    });
+    (index + gaps) as i32
+}
+
+/// Use `style` for the tab layout from now on
+pub fn set_tab_style(style: TabStyle) {
+    TAB_STYLE.set(style);
+}
+
+/// Current tab layout
+pub fn tab_style() -> TabStyle {
+    TAB_STYLE.get()
+}
+
+/// Hide the title bar (or show it again) from now on
+pub fn set_titlebar_hidden(hidden: bool) {
+    TITLEBAR_HIDDEN.store(hidden, Ordering::Relaxed);
+}
+
+/// Whether the title bar is hidden
+pub fn titlebar_hidden() -> bool {
+    TITLEBAR_HIDDEN.load(Ordering::Relaxed)
+}
+
+/// Current title bar height in pixels, which depends on the tab layout; 0 while
+/// it is hidden
+pub fn titlebar_height() -> i32 {
+    if titlebar_hidden() {
+        return 0;
+    }
+    match tab_style() {
+        TabStyle::TwoLine => TWO_LINE_TITLEBAR_HEIGHT,
+        TabStyle::Normal | TabStyle::Compact => TITLEBAR_HEIGHT,
+    }
+}
+
+/// Put Neovide's area `top` pixels down from now on, over part of the title bar
+/// (auto-hide); None to put it below the title bar again
+pub fn set_content_top(top: Option<i32>) {
+    CONTENT_TOP.store(top.unwrap_or(-1), Ordering::Relaxed);
+}
+
+/// Top of Neovide's area: below the title bar, or below the part of it that shows
+/// while it is auto-hidden
+pub fn content_top() -> i32 {
+    match CONTENT_TOP.load(Ordering::Relaxed) {
+        -1 => titlebar_height(),
+        top => top.min(titlebar_height()),
+    }
+}
+
+/// Current width of each tab in pixels, which depends on the tab layout
+pub fn tab_width() -> i32 {
+    match tab_style() {
+        TabStyle::Compact => COMPACT_TAB_WIDTH,
+        TabStyle::Normal | TabStyle::TwoLine => TAB_WIDTH,
+    }
+}
+
+/// Put the window buttons on `side` from now on, drawn as traffic lights if
+/// `traffic_lights`
+pub fn set_window_controls(side: WindowControls, traffic_lights: bool) {
+    WINDOW_CONTROLS.set((side, traffic_lights));
+}
+
+/// Side of the title bar the window buttons are on
+pub fn window_controls() -> WindowControls {
+    WINDOW_CONTROLS.get().0
+}
+
+/// Whether the window buttons are drawn as traffic lights
+pub fn traffic_lights() -> bool {
+    WINDOW_CONTROLS.get().1
+}
+
+/// Show only the window buttons passed as true from now on; the others leave no gap
+pub fn set_window_buttons(minimize: bool, maximize: bool, close: bool) {
+    WINDOW_BUTTONS.set([minimize, maximize, close]);
+}
+
+/// Width and margin to the window edge of each window button
+fn window_button_size() -> (i32, i32) {
+    if traffic_lights() {
+        (TRAFFIC_LIGHT_WIDTH, TRAFFIC_LIGHTS_MARGIN)
+    } else {
+        (BUTTON_WIDTH, 0)
+    }
+}
+
+/// Width of the shown window buttons together, including the margin to the window
+/// edge; 0 without any
+pub fn window_controls_width() -> i32 {
+    let shown = WINDOW_BUTTONS.get().iter().filter(|&&shown| shown).count() as i32;
+    let (width, margin) = window_button_size();
+    if shown == 0 {
+        0
+    } else {
+        width * shown + margin
+    }
+}
+
+/// Rectangles of the minimize, maximize and close buttons, spanning the title bar
+/// height. On the right they are in Windows order, ending at the window edge; on
+/// the left in macOS order (close, minimize, maximize). Hidden buttons get an
+/// empty rectangle, which nothing hits.
+pub fn get_window_button_rects(client_width: i32) -> (Rect, Rect, Rect) {
+    let (width, margin) = window_button_size();
+    let (mut left, order) = match window_controls() {
+        WindowControls::Right => (client_width - window_controls_width(), [0, 1, 2]),
+        WindowControls::Left => (margin, [2, 0, 1]),
+    };
+    let shown = WINDOW_BUTTONS.get();
+    let mut rects = [Rect::default(); 3];
+    for button in order {
+        let right = if shown[button] { left + width } else { left };
+        rects[button] = Rect {
+            left,
+            top: 0,
+            right,
+            bottom: titlebar_height(),
+        };
+        left = right;
+    }
+    (rects[0], rects[1], rects[2])
+}
+
+/// X position of the first tab, after the window buttons when they are on the left
+pub fn tab_bar_left() -> i32 {
+    match window_controls() {
+        WindowControls::Right => TAB_BAR_LEFT_MARGIN,
+        WindowControls::Left => window_controls_width() + TAB_BAR_LEFT_MARGIN,
+    }
+}
+
+/// Right end of the tab bar and widgets' area: the window buttons, or the window edge
+/// when they are on the left
+fn content_right(client_width: i32) -> i32 {
+    match window_controls() {
+        WindowControls::Right => client_width - window_controls_width(),
+        WindowControls::Left => client_width,
+    }
+}
+
+// Tab bar layout constants
+/// Width of each tab in pixels
+pub const TAB_WIDTH: i32 = 200;
+/// Width of each tab with `tab_style: "compact"` (square icon-only tabs)
+pub const COMPACT_TAB_WIDTH: i32 = TITLEBAR_HEIGHT - TAB_VERTICAL_PADDING * 2;
+/// Size of the close button within a tab
+pub const TAB_CLOSE_SIZE: i32 = 16;
+/// Padding around the close button
+pub const TAB_CLOSE_PADDING: i32 = 8;
+/// Width of the new tab (+) button
+pub const NEW_TAB_BUTTON_WIDTH: i32 = 32;
+/// Width of the profile dropdown button (caret)
+pub const DROPDOWN_BUTTON_WIDTH: i32 = 20;
+/// Width of the overflow tabs button (accommodates icon + "+N" text when selected tab is in overflow)
+pub const OVERFLOW_BUTTON_WIDTH: i32 = 48;
+/// Left margin before the first tab
+pub const TAB_BAR_LEFT_MARGIN: i32 = 8;
+/// Vertical padding for tabs within the titlebar
+pub const TAB_VERTICAL_PADDING: i32 = 4;
+/// How far around a tab's close button a touch or pen tap still hits it
+pub const TOUCH_HIT_SLOP: i32 = 8;
+/// Width of the session name widget; longer names are cut off
+pub const SESSION_WIDGET_WIDTH: i32 = 120;
+/// Width of the high memory banner
+pub const MEMORY_WIDGET_WIDTH: i32 = 196;
+/// Width of the banner's dismiss button, at its right end
+pub const MEMORY_DISMISS_WIDTH: i32 = 22;
+/// Width of the "Update available" badge
+pub const UPDATE_WIDGET_WIDTH: i32 = 108;
+/// Width of the clock widget
+pub const CLOCK_WIDGET_WIDTH: i32 = 64;
+/// Width of the settings (gear) button
+pub const SETTINGS_WIDGET_WIDTH: i32 = 32;
+/// Space between widgets, and between the tab bar and the first widget
+pub const WIDGET_SPACING: i32 = 8;
+
+/// Result of hit testing in the tab bar area
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabHitResult {
+    /// Hit a tab body (index)
+    Tab(usize),
+    /// Hit a tab's close button (index)
+    TabClose(usize),
+    /// Hit the retry icon of a tab whose Neovide failed to launch (index)
+    TabRetry(usize),
+    /// Hit the new tab (+) button
+    NewTabButton,
+    /// Hit the profile dropdown button (caret)
+    ProfileDropdown,
+    /// Hit the overflow tabs dropdown button
+    OverflowButton,
+    /// Hit the caption/drag area
+    Caption,
+    /// Hit nothing in the tab bar
+    None,
+}
+
+/// Calculate the rectangle for a tab at a given index
+pub fn get_tab_rect(index: usize, client_width: i32) -> Rect {
+    let _ = client_width; // Reserved for future dynamic sizing
+    let left = tab_bar_left() + (slot_index(index, false) * tab_width());
+    Rect {
+        left,
+        top: TAB_VERTICAL_PADDING,
+        right: left + tab_width(),
+        bottom: titlebar_height() - TAB_VERTICAL_PADDING,
+    }
+}
+
+/// Calculate the rectangle for a tab's close button
+pub fn get_tab_close_rect(tab_rect: &Rect) -> Rect {
+    let close_left = tab_rect.right - TAB_CLOSE_PADDING - TAB_CLOSE_SIZE;
+    let close_top = (tab_rect.top + tab_rect.bottom - TAB_CLOSE_SIZE) / 2;
+    Rect {
+        left: close_left,
+        top: close_top,
+        right: close_left + TAB_CLOSE_SIZE,
+        bottom: close_top + TAB_CLOSE_SIZE,
+    }
+}
+
+/// Get the rectangle for the new tab (+) button
+/// When has_overflow is true, it's positioned after the overflow button
+pub fn get_new_tab_button_rect_ex(
+    visible_tab_count: usize,
+    has_overflow: bool,
+    client_width: i32,
+) -> Rect {
+    let _ = client_width; // Reserved for future dynamic sizing
+    let tabs_width = slot_index(visible_tab_count, true) * tab_width();
+    let left = if has_overflow {
+        tab_bar_left() + tabs_width + OVERFLOW_BUTTON_WIDTH
+    } else {
+        tab_bar_left() + tabs_width
+    };
+    Rect {
+        left,
+        top: TAB_VERTICAL_PADDING,
+        right: left + NEW_TAB_BUTTON_WIDTH,
+        bottom: titlebar_height() - TAB_VERTICAL_PADDING,
+    }
+}
+
+/// Get the rectangle for the new tab (+) button (legacy, assumes no overflow)
+#[allow(dead_code)]
+pub fn get_new_tab_button_rect(tab_count: usize, client_width: i32) -> Rect {
+    get_new_tab_button_rect_ex(tab_count, false, client_width)
+}
+
+/// Get the rectangle for the profile dropdown button (caret)
+pub fn get_dropdown_button_rect_ex(
+    visible_tab_count: usize,
+    has_overflow: bool,
+    client_width: i32,
+) -> Rect {
+    let new_tab_rect = get_new_tab_button_rect_ex(visible_tab_count, has_overflow, client_width);
+    Rect {
+        left: new_tab_rect.right,
+        top: TAB_VERTICAL_PADDING,
+        right: new_tab_rect.right + DROPDOWN_BUTTON_WIDTH,
+        bottom: titlebar_height() - TAB_VERTICAL_PADDING,
+    }
+}
+
+/// Get the maximum X position for the tab bar (before the widgets and window buttons)
+pub fn get_tab_bar_max_x(client_width: i32) -> i32 {
+    match get_widget_rects(client_width).first() {
+        Some((_, rect)) => rect.left - WIDGET_SPACING,
+        None => content_right(client_width) - 8, // Leave some padding before window buttons
+    }
+}
+
+/// Show `widgets` (left to right) from now on. Returns true if they changed
+/// (the tab bar needs a repaint).
+pub fn set_widgets(widgets: &[Widget]) -> bool {
+    WIDGETS.with_borrow_mut(|shown| {
+        if shown.as_slice() == widgets {
+            return false;
+        }
+        *shown = widgets.to_vec();
+        true
+    })
+}
+
+/// Rectangles of the widgets shown in a `client_width` wide window, left to
+/// right and ending next to the window buttons. Widgets that would leave no
+/// room for a tab and the tab bar buttons are left out, the leftmost first.
+pub fn get_widget_rects(client_width: i32) -> Vec<(Widget, Rect)> {
+    let min_tab_bar_width = tab_bar_left()
+        + tab_width()
+        + OVERFLOW_BUTTON_WIDTH
+        + NEW_TAB_BUTTON_WIDTH
+        + DROPDOWN_BUTTON_WIDTH;
+    let right = content_right(client_width) - 4;
+    WIDGETS.with_borrow(|widgets| {
+        let mut first = 0;
+        let mut width: i32 = widgets
+            .iter()
+            .map(|widget| widget.width() + WIDGET_SPACING)
+            .sum();
+        while first < widgets.len() && right - width < min_tab_bar_width {
+            width -= widgets[first].width() + WIDGET_SPACING;
+            first += 1;
+        }
+
+        let mut left = right - width + WIDGET_SPACING;
+        widgets[first..]
+            .iter()
+            .map(|&widget| {
+                let rect = Rect {
+                    left,
+                    top: TAB_VERTICAL_PADDING,
+                    right: left + widget.width(),
+                    bottom: titlebar_height() - TAB_VERTICAL_PADDING,
+                };
+                left = rect.right + WIDGET_SPACING;
+                (widget, rect)
+            })
+            .collect()
+    })
+}
+
+/// The widget at (x, y), if any
+pub fn hit_test_widgets(x: i32, y: i32, client_width: i32) -> Option<Widget> {
+    get_widget_rects(client_width)
+        .into_iter()
+        .find(|(_, rect)| rect.contains(x, y))
+        .map(|(widget, _)| widget)
+}
+
+/// Calculate how many tabs can be displayed before overflow
+/// Returns (visible_count, has_overflow)
+pub fn calculate_visible_tabs(tab_count: usize, client_width: i32) -> (usize, bool) {
+    check_frozen_slots(tab_count, client_width);
+    if tab_count == 0 {
+        return (0, false);
+    }
+
+    let max_x = get_tab_bar_max_x(client_width);
+    // Reserve space for new tab button, dropdown button, and potentially overflow button
+    let reserved_space = NEW_TAB_BUTTON_WIDTH + DROPDOWN_BUTTON_WIDTH + OVERFLOW_BUTTON_WIDTH;
+    let available_width = max_x - tab_bar_left() - reserved_space;
+
+    let max_visible = (available_width / tab_width()).max(0) as usize;
+
+    if max_visible >= tab_count {
+        // All tabs fit (no overflow button needed, so we can reclaim that space)
+        let available_without_overflow =
+            max_x - tab_bar_left() - NEW_TAB_BUTTON_WIDTH - DROPDOWN_BUTTON_WIDTH;
+        let max_visible_no_overflow = (available_without_overflow / tab_width()).max(0) as usize;
+        if max_visible_no_overflow >= tab_count {
+            return (tab_count, false);
+        }
+    }
+
+    // Need overflow
+    (max_visible.min(tab_count), max_visible < tab_count)
+}
+
+/// Get the rectangle for the overflow button
+pub fn get_overflow_button_rect(visible_tab_count: usize, client_width: i32) -> Rect {
+    let _ = client_width; // Reserved for future dynamic sizing
+    let left = tab_bar_left() + (visible_tab_count as i32 * tab_width());
+    Rect {
+        left,
+        top: TAB_VERTICAL_PADDING,
+        right: left + OVERFLOW_BUTTON_WIDTH,
+        bottom: titlebar_height() - TAB_VERTICAL_PADDING,
+    }
+}
+
+/// Hit test in the tab bar area
+pub fn hit_test_tab_bar(x: i32, y: i32, tab_count: usize, client_width: i32) -> TabHitResult {
+    // Must be in the titlebar height range
+    if !(TAB_VERTICAL_PADDING..titlebar_height() - TAB_VERTICAL_PADDING).contains(&y) {
+        // Could still be in the caption area if within titlebar
+        if (0..titlebar_height()).contains(&y) {
+            return TabHitResult::Caption;
+        }
+        return TabHitResult::None;
+    }
+
+    let max_x = get_tab_bar_max_x(client_width);
+    let (visible_count, has_overflow) = calculate_visible_tabs(tab_count, client_width);
+
+    // Check each visible tab
+    for i in 0..visible_count {
+        let tab_rect = get_tab_rect(i, client_width);
+        if tab_rect.left > max_x {
+            break; // Tab bar overflow
+        }
+        if x >= tab_rect.left && x < tab_rect.right {
+            // Compact tabs have no close button
+            if tab_style() == TabStyle::Compact {
+                return TabHitResult::Tab(i);
+            }
+            // Check if on the close button
+            if get_tab_close_rect(&tab_rect).contains(x, y) {
+                return TabHitResult::TabClose(i);
+            }
+            return TabHitResult::Tab(i);
+        }
+    }
+
+    // Check overflow button if there are overflow tabs
+    if has_overflow {
+        let overflow_rect = get_overflow_button_rect(visible_count, client_width);
+        if x >= overflow_rect.left && x < overflow_rect.right {
+            return TabHitResult::OverflowButton;
+        }
+    }
+
+    // Check new tab button
+    let new_tab_rect = get_new_tab_button_rect_ex(visible_count, has_overflow, client_width);
+    if new_tab_rect.right <= max_x && x >= new_tab_rect.left && x < new_tab_rect.right {
+        return TabHitResult::NewTabButton;
+    }
+
+    // Check dropdown button
+    let dropdown_rect = get_dropdown_button_rect_ex(visible_count, has_overflow, client_width);
+    if dropdown_rect.right <= max_x && x >= dropdown_rect.left && x < dropdown_rect.right {
+        return TabHitResult::ProfileDropdown;
+    }
+
+    TabHitResult::Caption
+}
+
+/// Point the tab bar hit test should use for a touch or pen tap at (x, y) in the
+/// title bar: taps above or below the tabs count for the tab row, and taps within
+/// TOUCH_HIT_SLOP of a close button land on it
+pub fn touch_hit_point(x: i32, y: i32, tab_count: usize, client_width: i32) -> (i32, i32) {
+    let row_y = y.clamp(
+        TAB_VERTICAL_PADDING,
+        titlebar_height() - TAB_VERTICAL_PADDING - 1,
+    );
+    if let TabHitResult::Tab(index) = hit_test_tab_bar(x, row_y, tab_count, client_width)
+        && tab_style() != TabStyle::Compact
+    {
+        let close = get_tab_close_rect(&get_tab_rect(index, client_width));
+        let target = Rect {
+            left: close.left - TOUCH_HIT_SLOP,
+            top: close.top - TOUCH_HIT_SLOP,
+            right: close.right + TOUCH_HIT_SLOP,
+            bottom: close.bottom + TOUCH_HIT_SLOP,
+        };
+        if target.contains(x, row_y) {
+            return (
+                (close.left + close.right) / 2,
+                (close.top + close.bottom) / 2,
+            );
+        }
+    }
+    (x, row_y)
+}
+
+/// Calculate the target index for dropping a tab at position x
+#[cfg(test)]
+pub fn calculate_drop_index(x: i32, tab_count: usize, client_width: i32) -> usize {
+    let _ = client_width; // Reserved for future dynamic sizing
+
+    // Calculate which slot the mouse is over
+    let relative_x = x - tab_bar_left();
+    if relative_x < 0 {
+        return 0;
+    }
+
+    let index = (relative_x / tab_width()) as usize;
+    if index >= tab_count {
+        tab_count.saturating_sub(1)
+    } else {
+        index
+    }
+}
+
+/// Calculate if a tab swap should occur during drag based on 50% threshold.
+/// Returns Some(target_index) if a swap should occur, None otherwise.
+///
+/// The swap logic:
+/// - When dragging right: swap when the dragged tab's center crosses past the center of the next tab
+/// - When dragging left: swap when the dragged tab's center crosses past the center of the previous tab
+pub fn calculate_swap_target(
+    drag_tab_index: usize,
+    drag_visual_x: i32,
+    tab_count: usize,
+    _client_width: i32,
+) -> Option<usize> {
+    if tab_count <= 1 {
+        return None;
+    }
+
+    // Calculate the center of the dragged tab at its visual position
+    let drag_center = drag_visual_x + tab_width() / 2;
+
+    // Check swap with the tab to the right
+    if drag_tab_index < tab_count - 1 {
+        let right_tab_index = drag_tab_index + 1;
+        let right_tab_rect = get_tab_rect(right_tab_index, 0);
+        let right_tab_center = (right_tab_rect.left + right_tab_rect.right) / 2;
+
+        // If dragged tab center is past the right tab's center, swap right
+        if drag_center > right_tab_center {
+            return Some(right_tab_index);
+        }
+    }
+
+    // Check swap with the tab to the left
+    if drag_tab_index > 0 {
+        let left_tab_index = drag_tab_index - 1;
+        let left_tab_rect = get_tab_rect(left_tab_index, 0);
+        let left_tab_center = (left_tab_rect.left + left_tab_rect.right) / 2;
+
+        // If dragged tab center is past the left tab's center (to the left), swap left
+        if drag_center < left_tab_center {
+            return Some(left_tab_index);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pulled_into() {
+        let work_area = Rect {
+            left: 0,
+            top: 0,
+            right: 1920,
+            bottom: 1040,
+        };
+        // Partly off screen: left alone
+        let hanging = Rect {
+            left: -200,
+            top: 100,
+            right: 600,
+            bottom: 700,
+        };
+        assert_eq!(hanging.pulled_into(&work_area), hanging);
+
+        // On a display to the right that is gone: moved to the nearest edge
+        let lost = Rect {
+            left: 2200,
+            top: 300,
+            right: 3000,
+            bottom: 900,
+        };
+        assert_eq!(
+            lost.pulled_into(&work_area),
+            Rect {
+                left: 1120,
+                top: 300,
+                right: 1920,
+                bottom: 900,
+            }
+        );
+
+        // Larger than the work area: shrunk to fit
+        let huge = Rect {
+            left: -3000,
+            top: -2000,
+            right: -100,
+            bottom: -100,
+        };
+        assert_eq!(huge.pulled_into(&work_area), work_area);
+    }
+
+    #[test]
+    fn test_popup_below() {
+        let work_area = Rect {
+            left: 0,
+            top: 0,
+            right: 1920,
+            bottom: 1040,
+        };
+        let button = |left, top| Rect {
+            left,
+            top,
+            right: left + 30,
+            bottom: top + 32,
+        };
+
+        // Room below: under the button
+        assert_eq!(
+            button(100, 100).popup_below(150, 300, &work_area),
+            Rect {
+                left: 100,
+                top: 132,
+                right: 250,
+                bottom: 432,
+            }
+        );
+
+        // Near the bottom right corner: above the button, shifted left
+        assert_eq!(
+            button(1850, 900).popup_below(150, 300, &work_area),
+            Rect {
+                left: 1770,
+                top: 600,
+                right: 1920,
+                bottom: 900,
+            }
+        );
+
+        // Fits neither below nor above: as low as it stays on screen
+        assert_eq!(
+            button(100, 500).popup_below(150, 800, &work_area),
+            Rect {
+                left: 100,
+                top: 240,
+                right: 250,
+                bottom: 1040,
+            }
+        );
+
+        // Taller than the work area: starts at its top
+        assert_eq!(button(100, 500).popup_below(150, 2000, &work_area).top, 0);
+    }
+
+    #[test]
+    fn test_get_tab_rect() {
+        let tab0 = get_tab_rect(0, 1024);
+        assert_eq!(tab0.left, TAB_BAR_LEFT_MARGIN);
+        assert_eq!(tab0.right, TAB_BAR_LEFT_MARGIN + TAB_WIDTH);
+        assert_eq!(tab0.top, TAB_VERTICAL_PADDING);
+        assert_eq!(tab0.bottom, TITLEBAR_HEIGHT - TAB_VERTICAL_PADDING);
+
+        let tab1 = get_tab_rect(1, 1024);
+        assert_eq!(tab1.left, TAB_BAR_LEFT_MARGIN + TAB_WIDTH);
+        assert_eq!(tab1.right, TAB_BAR_LEFT_MARGIN + TAB_WIDTH * 2);
+    }
+
+    #[test]
+    fn test_get_new_tab_button_rect() {
+        let btn = get_new_tab_button_rect(0, 1024);
+        assert_eq!(btn.left, TAB_BAR_LEFT_MARGIN);
+        assert_eq!(btn.right, TAB_BAR_LEFT_MARGIN + NEW_TAB_BUTTON_WIDTH);
+
+        let btn = get_new_tab_button_rect(2, 1024);
+        assert_eq!(btn.left, TAB_BAR_LEFT_MARGIN + TAB_WIDTH * 2);
+    }
+
+    #[test]
+    fn test_calculate_visible_tabs() {
+        let width = 1024;
+        assert_eq!(calculate_visible_tabs(0, width), (0, false));
+        assert_eq!(calculate_visible_tabs(3, width), (3, false));
+        // Room is kept for the overflow button once the tabs don't all fit
+        assert_eq!(calculate_visible_tabs(4, width), (3, true));
+        assert_eq!(calculate_visible_tabs(20, width), (3, true));
+        // Too narrow for any tab
+        assert_eq!(calculate_visible_tabs(1, 300), (0, true));
+
+        let overflow = get_overflow_button_rect(3, width);
+        assert_eq!(overflow.left, TAB_BAR_LEFT_MARGIN + TAB_WIDTH * 3);
+        let y = titlebar_height() / 2;
+        assert_eq!(
+            hit_test_tab_bar(overflow.left + 1, y, 5, width),
+            TabHitResult::OverflowButton
+        );
+        assert_eq!(
+            hit_test_tab_bar(overflow.right + 1, y, 5, width),
+            TabHitResult::NewTabButton
+        );
+    }
+
+    #[test]
+    fn test_frozen_tabs() {
+        let width = 1024;
+        let y = titlebar_height() / 2;
+        let slot_left = |slot: i32| TAB_BAR_LEFT_MARGIN + TAB_WIDTH * slot;
+
+        // Closing the second of four tabs keeps the two on its right in place
+        freeze_closed_tab(1, 3);
+        assert_eq!(calculate_visible_tabs(3, width), (3, false));
+        assert_eq!(get_tab_rect(0, width).left, slot_left(0));
+        assert_eq!(get_tab_rect(1, width).left, slot_left(2));
+        assert_eq!(get_tab_rect(2, width).left, slot_left(3));
+        assert_eq!(get_new_tab_button_rect(3, width).left, slot_left(4));
+        // The empty slot is caption
+        assert_eq!(
+            hit_test_tab_bar(slot_left(1) + 20, y, 3, width),
+            TabHitResult::Caption
+        );
+        assert_eq!(
+            hit_test_tab_bar(slot_left(2) + 20, y, 3, width),
+            TabHitResult::Tab(1)
+        );
+
+        // Closing the tab that moved under the pointer leaves another slot
+        freeze_closed_tab(1, 2);
+        assert_eq!(calculate_visible_tabs(2, width), (2, false));
+        assert_eq!(get_tab_rect(1, width).left, slot_left(3));
+
+        // Slots after the last tab are dropped
+        freeze_closed_tab(1, 1);
+        assert_eq!(get_new_tab_button_rect(1, width).left, slot_left(1));
+        assert!(!unfreeze_tabs());
+
+        // Leaving the tab bar closes the slots
+        freeze_closed_tab(0, 2);
+        assert_eq!(get_tab_rect(0, width).left, slot_left(1));
+        assert!(unfreeze_tabs());
+        assert_eq!(get_tab_rect(0, width).left, slot_left(0));
+
+        // So does a new tab, or not having room for the slots
+        freeze_closed_tab(0, 2);
+        calculate_visible_tabs(3, width);
+        assert_eq!(get_tab_rect(0, width).left, slot_left(0));
+        freeze_closed_tab(0, 2);
+        calculate_visible_tabs(2, 600);
+        assert_eq!(get_tab_rect(0, width).left, slot_left(0));
+    }
+
+    #[test]
+    fn test_widgets() {
+        let client_width = 1200;
+        let no_widgets_max_x = get_tab_bar_max_x(client_width);
+        assert!(get_widget_rects(client_width).is_empty());
+
+        assert!(set_widgets(&[
+            Widget::Session,
+            Widget::Clock,
+            Widget::Settings
+        ]));
+        assert!(!set_widgets(&[
+            Widget::Session,
+            Widget::Clock,
+            Widget::Settings
+        ]));
+        let rects = get_widget_rects(client_width);
+        let widgets: Vec<Widget> = rects.iter().map(|(widget, _)| *widget).collect();
+        assert_eq!(widgets, [Widget::Session, Widget::Clock, Widget::Settings]);
+        // Packed left to right, ending next to the window buttons
+        let right = client_width - BUTTON_WIDTH * 3 - 4;
+        assert_eq!(rects[2].1.right, right);
+        assert_eq!(rects[2].1.left, right - SETTINGS_WIDGET_WIDTH);
+        assert_eq!(rects[1].1.right, rects[2].1.left - WIDGET_SPACING);
+        assert_eq!(rects[0].1.right, rects[1].1.left - WIDGET_SPACING);
+        // The tab bar ends before them
+        let max_x = get_tab_bar_max_x(client_width);
+        assert_eq!(max_x, rects[0].1.left - WIDGET_SPACING);
+        assert!(max_x < no_widgets_max_x);
+
+        assert_eq!(
+            hit_test_widgets(rects[2].1.left, 10, client_width),
+            Some(Widget::Settings)
+        );
+        assert_eq!(
+            hit_test_widgets(rects[0].1.left - 1, 10, client_width),
+            None
+        );
+        assert_eq!(
+            hit_test_tab_bar(rects[1].1.left, 10, 1, client_width),
+            TabHitResult::Caption
+        );
+
+        // A narrow window drops the leftmost widgets to keep room for a tab
+        let rects = get_widget_rects(600);
+        let widgets: Vec<Widget> = rects.iter().map(|(widget, _)| *widget).collect();
+        assert_eq!(widgets, [Widget::Clock, Widget::Settings]);
+        assert_eq!(calculate_visible_tabs(1, 600), (1, false));
+
+        assert!(set_widgets(&[]));
+        assert_eq!(get_tab_bar_max_x(client_width), no_widgets_max_x);
+    }
+
+    #[test]
+    fn test_window_controls() {
+        let client_width = 1200;
+        let (min, max, close) = get_window_button_rects(client_width);
+        assert_eq!(
+            (min.left, max.left),
+            (1200 - BUTTON_WIDTH * 3, 1200 - BUTTON_WIDTH * 2)
+        );
+        assert_eq!(close.right, client_width);
+        assert_eq!(
+            get_tab_bar_max_x(client_width),
+            client_width - BUTTON_WIDTH * 3 - 8
+        );
+
+        // On the left in macOS order, with the tabs after them
+        set_window_controls(WindowControls::Left, false);
+        let (min, max, close) = get_window_button_rects(client_width);
+        assert_eq!(
+            (close.left, min.left, max.left),
+            (0, BUTTON_WIDTH, BUTTON_WIDTH * 2)
+        );
+        assert_eq!(
+            get_tab_rect(0, client_width).left,
+            BUTTON_WIDTH * 3 + TAB_BAR_LEFT_MARGIN
+        );
+        assert_eq!(get_tab_bar_max_x(client_width), client_width - 8);
+        assert_eq!(
+            hit_test_tab_bar(
+                BUTTON_WIDTH * 3 + TAB_BAR_LEFT_MARGIN + 20,
+                10,
+                1,
+                client_width
+            ),
+            TabHitResult::Tab(0)
+        );
+
+        // Traffic lights are narrower and keep a margin to the window edge
+        set_window_controls(WindowControls::Left, true);
+        let (_, max, close) = get_window_button_rects(client_width);
+        assert_eq!(close.left, TRAFFIC_LIGHTS_MARGIN);
+        assert_eq!(max.right, window_controls_width());
+        set_window_controls(WindowControls::Right, true);
+        let (min, _, close) = get_window_button_rects(client_width);
+        assert_eq!(close.right, client_width - TRAFFIC_LIGHTS_MARGIN);
+        assert_eq!(min.left, client_width - window_controls_width());
+        set_window_controls(WindowControls::Right, false);
+
+        // Hidden buttons leave no gap
+        set_window_buttons(true, false, true);
+        let (min, max, close) = get_window_button_rects(client_width);
+        assert_eq!(min.right, close.left);
+        assert!(!max.contains(max.left, 10));
+        assert_eq!(
+            get_tab_bar_max_x(client_width),
+            client_width - BUTTON_WIDTH * 2 - 8
+        );
+        set_window_buttons(false, false, false);
+        assert_eq!(window_controls_width(), 0);
+        assert_eq!(
+            hit_test_tab_bar(client_width - 1, 10, 1, client_width),
+            TabHitResult::Caption
+        );
+        set_window_controls(WindowControls::Left, false);
+        assert_eq!(tab_bar_left(), TAB_BAR_LEFT_MARGIN);
+
+        set_window_controls(WindowControls::Right, false);
+        set_window_buttons(true, true, true);
+    }
+
+    #[test]
+    fn test_hit_test_tab_bar() {
+        let width = 1024;
+        let tab_count = 2;
+        let y = (TAB_VERTICAL_PADDING + TITLEBAR_HEIGHT - TAB_VERTICAL_PADDING) / 2;
+
+        // First tab area
+        let x = TAB_BAR_LEFT_MARGIN + 20;
+        assert_eq!(
+            hit_test_tab_bar(x, y, tab_count, width),
+            TabHitResult::Tab(0)
+        );
+
+        // Second tab area
+        let x = TAB_BAR_LEFT_MARGIN + TAB_WIDTH + 20;
+        assert_eq!(
+            hit_test_tab_bar(x, y, tab_count, width),
+            TabHitResult::Tab(1)
+        );
+
+        // New tab button area
+        let x = TAB_BAR_LEFT_MARGIN + TAB_WIDTH * 2 + 10;
+        assert_eq!(
+            hit_test_tab_bar(x, y, tab_count, width),
+            TabHitResult::NewTabButton
+        );
+
+        // Caption area (between new tab button and window buttons)
+        let x = TAB_BAR_LEFT_MARGIN + TAB_WIDTH * 2 + NEW_TAB_BUTTON_WIDTH + 50;
+        assert_eq!(
+            hit_test_tab_bar(x, y, tab_count, width),
+            TabHitResult::Caption
+        );
+    }
+
+    #[test]
+    fn test_touch_hit_point() {
+        let width = 1024;
+        let tab_x = TAB_BAR_LEFT_MARGIN + 20;
+
+        // The very top of the title bar selects the tab below it
+        let (x, y) = touch_hit_point(tab_x, 0, 2, width);
+        assert_eq!(hit_test_tab_bar(x, y, 2, width), TabHitResult::Tab(0));
+
+        // Just left of the close button still closes the tab
+        let close = get_tab_close_rect(&get_tab_rect(1, width));
+        let (x, y) = touch_hit_point(close.left - 5, close.top, 2, width);
+        assert_eq!(hit_test_tab_bar(x, y, 2, width), TabHitResult::TabClose(1));
+
+        // Farther away it is the tab itself
+        let (x, y) = touch_hit_point(close.left - TOUCH_HIT_SLOP - 1, close.top, 2, width);
+        assert_eq!(hit_test_tab_bar(x, y, 2, width), TabHitResult::Tab(1));
+    }
+
+    #[test]
+    fn test_calculate_drop_index() {
+        let width = 1024;
+        let tab_count = 3;
+
+        // Position at first tab
+        assert_eq!(
+            calculate_drop_index(TAB_BAR_LEFT_MARGIN + 10, tab_count, width),
+            0
+        );
+
+        // Position at second tab
+        assert_eq!(
+            calculate_drop_index(TAB_BAR_LEFT_MARGIN + TAB_WIDTH + 10, tab_count, width),
+            1
+        );
+
+        // Position at third tab
+        assert_eq!(
+            calculate_drop_index(TAB_BAR_LEFT_MARGIN + TAB_WIDTH * 2 + 10, tab_count, width),
+            2
+        );
+
+        // Position beyond last tab
+        assert_eq!(
+            calculate_drop_index(TAB_BAR_LEFT_MARGIN + TAB_WIDTH * 10, tab_count, width),
+            2
+        );
+
+        // Position before first tab
+        assert_eq!(calculate_drop_index(0, tab_count, width), 0);
+    }
+
+    #[test]
+    fn test_compact_tabs() {
+        let width = 1024;
+        let normal_fit = calculate_visible_tabs(100, width).0;
+        set_tab_style(TabStyle::Compact);
+        let y = TITLEBAR_HEIGHT / 2;
+
+        // Square tabs, one after the other
+        let tab1 = get_tab_rect(1, width);
+        assert_eq!(tab1.left, TAB_BAR_LEFT_MARGIN + COMPACT_TAB_WIDTH);
+        assert_eq!(tab1.right - tab1.left, COMPACT_TAB_WIDTH);
+        assert_eq!(tab1.bottom - tab1.top, COMPACT_TAB_WIDTH);
+        assert_eq!(
+            get_new_tab_button_rect(2, width).left,
+            TAB_BAR_LEFT_MARGIN + COMPACT_TAB_WIDTH * 2
+        );
+
+        // No close button: its corner selects the tab, also for touch
+        let close = get_tab_close_rect(&tab1);
+        assert_eq!(
+            hit_test_tab_bar(close.left + 1, close.top + 1, 3, width),
+            TabHitResult::Tab(1)
+        );
+        let (x, y_touch) = touch_hit_point(close.left + 1, close.top + 1, 3, width);
+        assert_eq!(hit_test_tab_bar(x, y_touch, 3, width), TabHitResult::Tab(1));
+        assert_eq!(
+            hit_test_tab_bar(TAB_BAR_LEFT_MARGIN + COMPACT_TAB_WIDTH * 3 + 1, y, 3, width),
+            TabHitResult::NewTabButton
+        );
+
+        // Many more tabs fit before the overflow
+        assert!(calculate_visible_tabs(100, width).0 > normal_fit * 4);
+
+        // Drops and swaps use the compact width
+        assert_eq!(
+            calculate_drop_index(TAB_BAR_LEFT_MARGIN + COMPACT_TAB_WIDTH * 2 + 1, 5, width),
+            2
+        );
+        let tab2 = get_tab_rect(2, width);
+        let tab2_center = (tab2.left + tab2.right) / 2;
+        assert_eq!(
+            calculate_swap_target(1, tab2_center - COMPACT_TAB_WIDTH / 2 + 1, 5, width),
+            Some(2)
+        );
+        assert_eq!(calculate_swap_target(1, tab1.left, 5, width), None);
+
+        set_tab_style(TabStyle::Normal);
+    }
+
+    #[test]
+    fn test_calculate_swap_target() {
+        let width = 1024;
+        let tab_count = 3;
+
+        // Tab at index 0, visual position at its normal spot - no swap
+        let tab0_rect = get_tab_rect(0, width);
+        assert_eq!(
+            calculate_swap_target(0, tab0_rect.left, tab_count, width),
+            None
+        );
+
+        // Tab at index 0, dragged right past center of tab 1 - should swap to index 1
+        let tab1_rect = get_tab_rect(1, width);
+        let tab1_center = (tab1_rect.left + tab1_rect.right) / 2;
+        // Position where tab 0's center is past tab 1's center
+        let visual_x = tab1_center - TAB_WIDTH / 2 + 1;
+        assert_eq!(
+            calculate_swap_target(0, visual_x, tab_count, width),
+            Some(1)
+        );
+
+        // Tab at index 1, dragged left past center of tab 0 - should swap to index 0
+        let tab0_center = (tab0_rect.left + tab0_rect.right) / 2;
+        // Position where tab 1's center is past tab 0's center (to the left)
+        let visual_x = tab0_center - TAB_WIDTH / 2 - 1;
+        assert_eq!(
+            calculate_swap_target(1, visual_x, tab_count, width),
+            Some(0)
+        );
+
+        // Tab at index 0 (leftmost) - can't swap left
+        let visual_x = -50; // Far left
+        assert_eq!(calculate_swap_target(0, visual_x, tab_count, width), None);
+
+        // Tab at index 2 (rightmost with 3 tabs) - can't swap right
+        let tab2_rect = get_tab_rect(2, width);
+        let visual_x = tab2_rect.left + TAB_WIDTH * 2; // Far right
+        assert_eq!(calculate_swap_target(2, visual_x, tab_count, width), None);
+
+        // Single tab - no swaps possible
+        assert_eq!(calculate_swap_target(0, 0, 1, width), None);
+    }
+}
@@ -0,0 +1,192 @@
+//! "Show process output" view of a tab's Neovide output.
+//!
+//! A resizable tool window owned by the wrapper, filled by a read-only edit
+//! control so the output can be selected and copied. The wrapper keeps the
+//! `OutputView` and refreshes it from the poll timer while Neovide keeps writing.
+
+#![cfg(target_os = "windows")]
+#![allow(unsafe_op_in_unsafe_fn)]
+
+use windows::Win32::Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, RECT, WPARAM};
+use windows::Win32::Graphics::Gdi::{DeleteObject, HFONT, HGDIOBJ};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::Controls::{EM_LIMITTEXT, EM_SCROLLCARET, EM_SETSEL};
+use windows::Win32::UI::Input::KeyboardAndMouse::SetFocus;
+use windows::Win32::UI::WindowsAndMessaging::*;
+use windows::core::{HSTRING, PCWSTR, w};
+
+use crate::fonts::{self, TextSize};
+use crate::output::OutputLog;
+
+const LOG_VIEW_CLASS_NAME: PCWSTR = w!("NeovideTabsLogView");
+
+/// Initial size of the view
+const LOG_VIEW_WIDTH: i32 = 720;
+const LOG_VIEW_HEIGHT: i32 = 420;
+
+/// Control ID of the edit control
+const EDIT_ID: i32 = 1;
+
+/// Shown while Neovide hasn't written anything
+const EMPTY_TEXT: &str = "Neovide hasn't written any output.";
+
+/// An open output view of a tab
+pub struct OutputView {
+    hwnd: HWND,
+    log: OutputLog,
+    /// Version of the log shown
+    version: u64,
+}
+
+impl OutputView {
+    /// Open a view of `log` centered over `owner`, titled after the tab's `label`
+    pub fn open(owner: HWND, label: &str, log: OutputLog) -> Option<Self> {
+        unsafe {
+            let hinstance = GetModuleHandleW(None).ok()?;
+            let mut owner_rect = RECT::default();
+            GetWindowRect(owner, &mut owner_rect).ok()?;
+            let x = (owner_rect.left + owner_rect.right - LOG_VIEW_WIDTH) / 2;
+            let y = (owner_rect.top + owner_rect.bottom - LOG_VIEW_HEIGHT) / 2;
+
+            let hwnd = CreateWindowExW(
+                WS_EX_TOOLWINDOW,
+                LOG_VIEW_CLASS_NAME,
+                &HSTRING::from(format!("Output of {} - neovide-tabs", label)),
+                WS_OVERLAPPEDWINDOW & !WS_MINIMIZEBOX,
+                x,
+                y,
+                LOG_VIEW_WIDTH,
+                LOG_VIEW_HEIGHT,
+                owner,
+                None,
+                hinstance,
+                None,
+            )
+            .ok()?;
+            let mut view = OutputView {
+                hwnd,
+                log,
+                version: 0,
+            };
+            view.show_text();
+            let _ = ShowWindow(hwnd, SW_SHOW);
+            Some(view)
+        }
+    }
+
+    /// Check whether the user closed the view
+    pub fn is_closed(&self) -> bool {
+        unsafe { !IsWindow(self.hwnd).as_bool() }
+    }
+
+    /// Show the lines Neovide wrote since the last refresh
+    pub fn refresh(&mut self) {
+        if self.log.version() != self.version && !self.is_closed() {
+            self.show_text();
+        }
+    }
+
+    /// Replace the text with the whole log, scrolled to its end
+    fn show_text(&mut self) {
+        self.version = self.log.version();
+        let mut text = self.log.text();
+        if text.is_empty() {
+            text = EMPTY_TEXT.to_string();
+        }
+        unsafe {
+            let Ok(edit) = GetDlgItem(self.hwnd, EDIT_ID) else {
+                return;
+            };
+            let _ = SetWindowTextW(edit, &HSTRING::from(text));
+            SendMessageW(edit, EM_SETSEL, WPARAM(usize::MAX), LPARAM(-1));
+            SendMessageW(edit, EM_SCROLLCARET, WPARAM(0), LPARAM(0));
+        }
+    }
+}
+
+impl Drop for OutputView {
+    fn drop(&mut self) {
+        if !self.is_closed() {
+            unsafe {
+                let _ = DestroyWindow(self.hwnd);
+            }
+        }
+    }
+}
+
+/// Register the output view window class
+pub fn register_class(hinstance: HINSTANCE) -> bool {
+    unsafe {
+        let wc = WNDCLASSW {
+            lpfnWndProc: Some(log_view_proc),
+            hInstance: hinstance,
+            hCursor: LoadCursorW(None, IDC_ARROW).ok().unwrap_or_default(),
+            lpszClassName: LOG_VIEW_CLASS_NAME,
+            ..Default::default()
+        };
+        RegisterClassW(&wc) != 0
+    }
+}
+
+unsafe extern "system" fn log_view_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match msg {
+        WM_CREATE => {
+            let hinstance = GetWindowLongPtrW(hwnd, GWLP_HINSTANCE);
+            let style = ES_MULTILINE | ES_READONLY | ES_AUTOVSCROLL | ES_AUTOHSCROLL;
+            let Ok(edit) = CreateWindowExW(
+                WS_EX_CLIENTEDGE,
+                w!("EDIT"),
+                w!(""),
+                WS_CHILD | WS_VISIBLE | WS_VSCROLL | WS_HSCROLL | WINDOW_STYLE(style as u32),
+                0,
+                0,
+                0,
+                0,
+                hwnd,
+                HMENU(EDIT_ID as *mut _),
+                HINSTANCE(hinstance as *mut _),
+                None,
+            ) else {
+                return LRESULT(-1);
+            };
+            let font = fonts::create_ui_font(TextSize::Body, 400);
+            SendMessageW(edit, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(0));
+            SetWindowLongPtrW(hwnd, GWLP_USERDATA, font.0 as isize);
+            // No limit: a multiline edit control holds 32K characters by default
+            SendMessageW(edit, EM_LIMITTEXT, WPARAM(0), LPARAM(0));
+            LRESULT(0)
+        }
+
+        WM_SIZE => {
+            if let Ok(edit) = GetDlgItem(hwnd, EDIT_ID) {
+                let width = (lparam.0 & 0xFFFF) as i32;
+                let height = ((lparam.0 >> 16) & 0xFFFF) as i32;
+                let _ = MoveWindow(edit, 0, 0, width, height, true);
+            }
+            LRESULT(0)
+        }
+
+        WM_SETFOCUS => {
+            if let Ok(edit) = GetDlgItem(hwnd, EDIT_ID) {
+                let _ = SetFocus(edit);
+            }
+            LRESULT(0)
+        }
+
+        WM_DESTROY => {
+            let font = HFONT(GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut _);
+            if !font.is_invalid() {
+                let _ = DeleteObject(HGDIOBJ(font.0));
+                SetWindowLongPtrW(hwnd, GWLP_USERDATA, 0);
+            }
+            LRESULT(0)
+        }
+
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
@@ -1,21 +1,96 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
-#![cfg(target_os = "windows")]
+// Elsewhere only the platform-neutral modules (tab logic, tab bar layout, config)
+// are built, so their tests run on any CI
+#![cfg_attr(not(target_os = "windows"), allow(dead_code))]
 
+mod attention;
+mod autohide;
+mod clipboard;
+mod colors;
 mod config;
+mod crash;
+mod dialogs;
+mod elevation;
+mod explorer;
+mod fonts;
+mod gestures;
+mod gitstatus;
+mod grips;
+mod hooks;
 mod hotkeys;
+mod hud;
 mod icons;
+mod layout;
+mod logview;
+mod monitors;
+mod output;
+mod popup;
+mod preview;
 mod process;
+mod projects;
+mod rename;
+mod sessions;
 mod tabs;
+mod taskmgr;
+#[cfg(test)]
+mod testdir;
+mod tiling;
+mod uia;
+mod updates;
+mod usage;
 mod watcher;
 mod window;
+mod winevents;
 
+#[cfg(target_os = "windows")]
 use anyhow::Result;
-use config::Config;
+#[cfg(target_os = "windows")]
+use config::{Config, StartState, StorageDirs};
+#[cfg(target_os = "windows")]
+use explorer::OpenRequest;
+#[cfg(target_os = "windows")]
 use std::env;
+#[cfg(target_os = "windows")]
+use std::path::{Path, PathBuf};
 
+#[cfg(not(target_os = "windows"))]
+fn main() {
+    eprintln!("neovide-tabs only runs on Windows");
+    std::process::exit(1);
+}
+
+#[cfg(target_os = "windows")]
 fn main() -> Result<()> {
     let args: Vec<String> = env::args().collect();
 
+    // An elevated proxy only runs Neovide for a tab of the main instance
+    if args.get(1).map(String::as_str) == Some(elevation::PROXY_FLAG) {
+        return elevation::run_proxy(&args[2..]);
+    }
+
+    // Where config and data live; decided before anything reads a path. Portable
+    // mode (--portable or a `portable` file) keeps both next to the executable,
+    // --config/NEOVIDE_TABS_CONFIG and --data-dir override them.
+    let portable_flag = args.iter().any(|arg| arg == "--portable");
+    let mut storage = env::current_exe()
+        .ok()
+        .and_then(|exe| config::portable_dir(&exe, portable_flag))
+        .map(StorageDirs::portable)
+        .unwrap_or_default();
+    let config_file = match option_value(&args, "--config", "a file path")? {
+        Some(path) => Some(PathBuf::from(path)),
+        None => env::var_os(config::CONFIG_ENV_VAR)
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from),
+    };
+    if let Some(file) = config_file {
+        storage.config_file = Some(absolute_path(&file));
+    }
+    if let Some(dir) = option_value(&args, "--data-dir", "a directory")? {
+        storage.data_dir = Some(absolute_path(Path::new(dir)));
+    }
+    config::set_storage_dirs(storage);
+
     // Check for debug commands
     if args.len() >= 2 {
         match args[1].as_str() {
@@ -24,14 +99,123 @@ fn main() -> Result<()> {
                 process::debug_list_windows(search);
                 return Ok(());
             }
+            "session" => {
+                return sessions::run_cli(&args[2..]);
+            }
+            "install-shell-extension" => {
+                explorer::install(&env::current_exe()?)?;
+                println!("Added \"Open in neovide-tabs\" to the folder context menu");
+                return Ok(());
+            }
+            "install-file-association" => {
+                let extensions = match &args[2..] {
+                    [] => explorer::DEFAULT_FILE_EXTENSIONS
+                        .iter()
+                        .map(|e| e.to_string())
+                        .collect(),
+                    given => given.to_vec(),
+                };
+                let registered =
+                    explorer::install_file_association(&env::current_exe()?, &extensions)?;
+                println!(
+                    "Registered neovide-tabs to open {} files",
+                    registered.join(" ")
+                );
+                return Ok(());
+            }
+            "uninstall-file-association" => {
+                explorer::uninstall_file_association()?;
+                println!("Removed the neovide-tabs file association");
+                return Ok(());
+            }
+            "install-protocol" => {
+                explorer::install_protocol(&env::current_exe()?)?;
+                println!("Registered neovide-tabs:// links");
+                return Ok(());
+            }
+            "uninstall-protocol" => {
+                explorer::uninstall_protocol()?;
+                println!("Removed neovide-tabs:// links");
+                return Ok(());
+            }
+            "install-autostart" => {
+                let minimized = args[2..].iter().any(|arg| arg == "--minimized");
+                explorer::install_autostart(&env::current_exe()?, minimized)?;
+                println!(
+                    "neovide-tabs will start{} when you log in",
+                    if minimized { " minimized" } else { "" }
+                );
+                return Ok(());
+            }
+            "uninstall-autostart" => {
+                explorer::uninstall_autostart()?;
+                println!("neovide-tabs no longer starts when you log in");
+                return Ok(());
+            }
+            "uninstall-shell-extension" => {
+                explorer::uninstall()?;
+                println!("Removed \"Open in neovide-tabs\" from the folder context menu");
+                return Ok(());
+            }
             "help" | "--help" | "-h" => {
                 println!("neovide-tabs - A tabbed wrapper for Neovide");
                 println!();
                 println!("Usage:");
                 println!("  neovide-tabs                    Run the application");
+                println!(
+                    "  neovide-tabs --session <name>   Run with a named session (restored on start, saved on exit)"
+                );
                 println!(
                     "  neovide-tabs list-windows [name]  List windows matching name (default: neovide)"
                 );
+                println!(
+                    "  neovide-tabs session export <file> [--session <name>]  Export a saved session"
+                );
+                println!(
+                    "  neovide-tabs session import <file> [--session <name>] [--force]  Import a session file"
+                );
+                println!(
+                    "  neovide-tabs --open-dir <dir>   Open a tab in a folder (in the running instance if any)"
+                );
+                println!(
+                    "  neovide-tabs open <path> [--profile <name>]  Edit a file in the tab containing it, or in a new tab in its project"
+                );
+                println!(
+                    "  neovide-tabs install-shell-extension    Add \"Open in neovide-tabs\" to Explorer's folder menu"
+                );
+                println!("  neovide-tabs uninstall-shell-extension  Remove it again");
+                println!(
+                    "  neovide-tabs install-file-association [ext...]  Add neovide-tabs to \"Open with\" for source files"
+                );
+                println!(
+                    "  neovide-tabs uninstall-file-association        Remove the file association"
+                );
+                println!(
+                    "  neovide-tabs install-protocol   Open neovide-tabs://open?path=<path>&profile=<name> links"
+                );
+                println!("  neovide-tabs uninstall-protocol Stop opening neovide-tabs:// links");
+                println!(
+                    "  neovide-tabs install-autostart [--minimized]  Start neovide-tabs when you log in"
+                );
+                println!("  neovide-tabs uninstall-autostart  Don't start it at login anymore");
+                println!(
+                    "  neovide-tabs --portable         Keep config and data in config\\ next to the executable"
+                );
+                println!(
+                    "  neovide-tabs --config <file>    Use another config file (or set NEOVIDE_TABS_CONFIG)"
+                );
+                println!(
+                    "  neovide-tabs --data-dir <dir>   Keep icons and sessions in another directory"
+                );
+                println!(
+                    "  neovide-tabs --skip-update-check  Don't look for a newer release at startup"
+                );
+                println!(
+                    "  neovide-tabs --debug-hud        Show timings and tab states over the title bar"
+                );
+                println!(
+                    "  neovide-tabs --maximized        Start maximized (--minimized: minimized)"
+                );
                 println!("  neovide-tabs help               Show this help");
                 return Ok(());
             }
@@ -39,11 +223,56 @@ fn main() -> Result<()> {
         }
     }
 
+    // Named session to restore and save (--session <name>)
+    let session = match option_value(&args, "--session", "a session name")? {
+        Some(name) if sessions::is_valid_session_name(name) => Some(name.to_string()),
+        Some(name) => anyhow::bail!(
+            "Invalid session name '{}' (use letters, digits, '-' and '_')",
+            name
+        ),
+        None => None,
+    };
+
+    // A folder opened from Explorer, a file opened through an editor association or
+    // a neovide-tabs:// link goes to the running instance if there is one
+    let open_request = if let Some(uri) = args.get(1).filter(|arg| explorer::is_uri(arg)) {
+        Some(explorer::parse_uri(uri)?)
+    } else if args.get(1).map(String::as_str) == Some("open") {
+        let Some(path) = args.get(2) else {
+            anyhow::bail!("open requires a file or directory");
+        };
+        Some(OpenRequest::Path {
+            path: absolute_path(Path::new(path)),
+            profile: option_value(&args, "--profile", "a profile name")?.map(str::to_string),
+        })
+    } else {
+        option_value(&args, explorer::OPEN_DIR_FLAG, "a directory")?
+            .map(|dir| OpenRequest::Folder(absolute_path(Path::new(dir))))
+    };
+
+    // Load configuration (the running instance is found by its window class)
+    let mut config = Config::load();
+    if open_request
+        .as_ref()
+        .is_some_and(|request| explorer::send_open_request(request, &config.window_class))
+    {
+        return Ok(());
+    }
+
+    // Leave a minidump and report instead of vanishing if the wrapper crashes
+    crash::install();
+
     // Ensure the default icon is extracted to the data directory
     icons::ensure_default_icon_extracted();
 
-    // Load configuration
-    let config = Config::load();
+    if args.iter().any(|arg| arg == "--skip-update-check") {
+        config.check_updates = false;
+    }
+    if args.iter().any(|arg| arg == "--maximized") {
+        config.start_state = StartState::Maximized;
+    } else if args.iter().any(|arg| arg == "--minimized") {
+        config.start_state = StartState::Minimized;
+    }
 
     // Check if Neovide is available before creating the window
     if process::NeovideProcess::check_neovide_available().is_err() {
@@ -52,7 +281,8 @@ fn main() -> Result<()> {
     }
 
     // Register window class with configuration
-    window::register_window_class(config)?;
+    let debug_hud = args.iter().any(|arg| arg == "--debug-hud");
+    window::register_window_class(config, session, open_request, debug_hud)?;
 
     // Create main window
     let _hwnd = window::create_window()?;
@@ -62,3 +292,22 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// Value following `option` on the command line; an error naming the expected
+/// `value` if it is missing
+#[cfg(target_os = "windows")]
+fn option_value<'a>(args: &'a [String], option: &str, value: &str) -> Result<Option<&'a str>> {
+    match args.iter().position(|arg| arg == option) {
+        Some(i) => match args.get(i + 1) {
+            Some(given) => Ok(Some(given)),
+            None => anyhow::bail!("{} requires {}", option, value),
+        },
+        None => Ok(None),
+    }
+}
+
+/// `path` relative to the current directory, so the config watcher can match it
+#[cfg(target_os = "windows")]
+fn absolute_path(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
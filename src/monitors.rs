@@ -0,0 +1,514 @@
+//! Displays the wrapper can be placed on (`monitor` config and profile option,
+//! "Move to monitor" in the window menu), and the window's place remembered per
+//! monitor setup.
+//!
+//! Monitors are numbered left to right (top to bottom where they line up), which
+//! is what `monitor: 2` counts. A name matches the monitor's friendly name from
+//! its EDID ("DELL U2720Q") or the device name Windows gives it ("DISPLAY2").
+//!
+//! The window's geometry is kept in `window-layouts.json` in the data directory,
+//! once for every set of connected displays (`setup_key`), so docking a laptop
+//! puts the window back where it was on the external display, and undocking
+//! where it was on the laptop's screen.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+#[cfg(target_os = "windows")]
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+#[cfg(target_os = "windows")]
+use windows::Win32::Devices::Display::{
+    DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME, DISPLAYCONFIG_DEVICE_INFO_GET_TARGET_NAME,
+    DISPLAYCONFIG_MODE_INFO, DISPLAYCONFIG_PATH_INFO, DISPLAYCONFIG_SOURCE_DEVICE_NAME,
+    DISPLAYCONFIG_TARGET_DEVICE_NAME, DisplayConfigGetDeviceInfo, GetDisplayConfigBufferSizes,
+    QDC_ONLY_ACTIVE_PATHS, QueryDisplayConfig,
+};
+#[cfg(target_os = "windows")]
+use windows::Win32::Foundation::{BOOL, ERROR_SUCCESS, HWND, LPARAM, RECT};
+#[cfg(target_os = "windows")]
+use windows::Win32::Graphics::Gdi::{
+    EnumDisplayMonitors, GetMonitorInfoW, HDC, HMONITOR, MONITOR_DEFAULTTONEAREST, MONITORINFO,
+    MONITORINFOEXW, MonitorFromWindow,
+};
+
+use crate::config::{MonitorTarget, data_dir_path};
+use crate::layout::Rect;
+
+/// Prefix of the device names, as in `\\.\DISPLAY2`
+const DEVICE_PREFIX: &str = r"\\.\";
+
+/// File in the data directory with the window's geometry per monitor setup
+const LAYOUTS_FILE: &str = "window-layouts.json";
+
+/// Most monitor setups remembered; the one used longest ago is forgotten first
+const MAX_LAYOUTS: usize = 16;
+
+/// A connected display
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Monitor {
+    /// Device name, e.g. `\\.\DISPLAY2`
+    pub device: String,
+    /// Friendly name from the display's EDID, e.g. "DELL U2720Q"
+    pub name: Option<String>,
+    /// The whole display (screen coordinates)
+    pub bounds: Rect,
+    /// Area not taken by the taskbar (screen coordinates)
+    pub work_area: Rect,
+}
+
+impl Monitor {
+    /// The device name without the `\\.\` prefix
+    fn short_device(&self) -> &str {
+        self.device
+            .strip_prefix(DEVICE_PREFIX)
+            .unwrap_or(&self.device)
+    }
+
+    /// Whether `name` names this monitor (case-insensitive): its friendly name,
+    /// the start of it, or its device name with or without the prefix
+    fn is_named(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        let friendly = self.name.as_deref().unwrap_or_default().to_lowercase();
+        (!friendly.is_empty() && friendly.starts_with(&name))
+            || self.device.to_lowercase() == name
+            || self.short_device().to_lowercase() == name
+    }
+
+    /// Label of the window menu item for the monitor numbered `number`
+    pub fn label(&self, number: usize) -> String {
+        format!(
+            "&{}  {}",
+            number,
+            self.name.as_deref().unwrap_or(self.short_device())
+        )
+    }
+}
+
+/// Sort into the numbering of the `monitor` option: left to right, then top to
+/// bottom
+pub fn sort(monitors: &mut [Monitor]) {
+    monitors.sort_by_key(|monitor| (monitor.work_area.left, monitor.work_area.top));
+}
+
+/// Index into sorted `monitors` of the one `target` chooses. An exact friendly
+/// name wins over one only starting with the name.
+pub fn find(monitors: &[Monitor], target: &MonitorTarget) -> Option<usize> {
+    match target {
+        MonitorTarget::Number(number) => (1..=monitors.len()).contains(number).then(|| number - 1),
+        MonitorTarget::Name(name) => monitors
+            .iter()
+            .position(|monitor| {
+                monitor
+                    .name
+                    .as_deref()
+                    .is_some_and(|friendly| friendly.eq_ignore_ascii_case(name))
+            })
+            .or_else(|| monitors.iter().position(|monitor| monitor.is_named(name))),
+    }
+}
+
+/// Key of the set of connected `monitors` (sorted): their names, resolutions and
+/// positions. The taskbar doesn't count, so moving it keeps the setup.
+pub fn setup_key(monitors: &[Monitor]) -> String {
+    monitors
+        .iter()
+        .map(|monitor| {
+            let bounds = &monitor.bounds;
+            format!(
+                "{} {}x{}@{},{}",
+                monitor.name.as_deref().unwrap_or(monitor.short_device()),
+                bounds.right - bounds.left,
+                bounds.bottom - bounds.top,
+                bounds.left,
+                bounds.top
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Where the window is on a monitor setup: its restored rectangle, as Windows'
+/// window placement has it, and whether it is maximized
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowLayout {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+    #[serde(default)]
+    pub maximized: bool,
+}
+
+/// A monitor setup and the window's layout on it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SavedLayout {
+    setup: String,
+    window: WindowLayout,
+}
+
+/// The window's layout per monitor setup, most recently used last
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowLayouts {
+    layouts: Vec<SavedLayout>,
+}
+
+impl WindowLayouts {
+    /// Load the remembered layouts (none if the file is missing or unreadable)
+    pub fn load() -> Self {
+        data_dir_path()
+            .map(|dir| Self::load_from(&dir))
+            .unwrap_or_default()
+    }
+
+    /// Write the layouts to the data directory
+    pub fn save(&self) -> Result<()> {
+        let dir = data_dir_path().context("Could not determine the data directory")?;
+        self.save_to(&dir)
+    }
+
+    fn load_from(dir: &Path) -> Self {
+        fs::read_to_string(dir.join(LAYOUTS_FILE))
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    fn save_to(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+        let path = dir.join(LAYOUTS_FILE);
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(&path, contents).with_context(|| format!("Failed to write {:?}", path))
+    }
+
+    /// The window's layout on `setup`, if it has been on it
+    pub fn get(&self, setup: &str) -> Option<WindowLayout> {
+        self.layouts
+            .iter()
+            .find(|saved| saved.setup == setup)
+            .map(|saved| saved.window)
+    }
+
+    /// Remember the window's layout on `setup`. Returns true if it changed (and
+    /// should be saved).
+    pub fn set(&mut self, setup: &str, window: WindowLayout) -> bool {
+        let position = self.layouts.iter().position(|saved| saved.setup == setup);
+        if let Some(index) = position {
+            if self.layouts[index].window == window && index + 1 == self.layouts.len() {
+                return false;
+            }
+            self.layouts.remove(index);
+        }
+        self.layouts.push(SavedLayout {
+            setup: setup.to_string(),
+            window,
+        });
+        if self.layouts.len() > MAX_LAYOUTS {
+            self.layouts.remove(0);
+        }
+        true
+    }
+}
+
+/// Window rectangle `window` moved from the work area `from` to `to`: the same
+/// distance from the top-left corner, shrunk and shifted to fit if needed
+pub fn move_rect(window: Rect, from: &Rect, to: &Rect) -> Rect {
+    let width = (window.right - window.left).min(to.right - to.left);
+    let height = (window.bottom - window.top).min(to.bottom - to.top);
+    let left = to.left + window.left - from.left;
+    let top = to.top + window.top - from.top;
+    Rect {
+        left,
+        top,
+        right: left + width,
+        bottom: top + height,
+    }
+    .shifted_into(to)
+}
+
+/// The connected displays, sorted into the `monitor` numbering
+#[cfg(target_os = "windows")]
+pub fn enumerate() -> Vec<Monitor> {
+    let mut monitors: Vec<Monitor> = Vec::new();
+    unsafe {
+        let _ = EnumDisplayMonitors(
+            HDC::default(),
+            None,
+            Some(enum_monitors_callback),
+            LPARAM(&mut monitors as *mut Vec<Monitor> as isize),
+        );
+    }
+    let names = friendly_names();
+    for monitor in &mut monitors {
+        monitor.name = names.get(&monitor.device).cloned();
+    }
+    sort(&mut monitors);
+    monitors
+}
+
+/// The display most of `hwnd` is on (without its friendly name)
+#[cfg(target_os = "windows")]
+pub fn monitor_of_window(hwnd: HWND) -> Option<Monitor> {
+    unsafe {
+        let info = monitor_info(MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST))?;
+        Some(monitor_from_info(&info))
+    }
+}
+
+/// Callback for EnumDisplayMonitors, collecting into the Vec<Monitor> at `lparam`
+#[cfg(target_os = "windows")]
+unsafe extern "system" fn enum_monitors_callback(
+    monitor: HMONITOR,
+    _hdc: HDC,
+    _rect: *mut RECT,
+    lparam: LPARAM,
+) -> BOOL {
+    unsafe {
+        let monitors = &mut *(lparam.0 as *mut Vec<Monitor>);
+        if let Some(info) = monitor_info(monitor) {
+            monitors.push(monitor_from_info(&info));
+        }
+        BOOL(1)
+    }
+}
+
+/// Monitor (without its friendly name) from its GetMonitorInfoW info
+#[cfg(target_os = "windows")]
+fn monitor_from_info(info: &MONITORINFOEXW) -> Monitor {
+    let rect = |rect: &RECT| Rect {
+        left: rect.left,
+        top: rect.top,
+        right: rect.right,
+        bottom: rect.bottom,
+    };
+    Monitor {
+        device: from_wide(&info.szDevice),
+        name: None,
+        bounds: rect(&info.monitorInfo.rcMonitor),
+        work_area: rect(&info.monitorInfo.rcWork),
+    }
+}
+
+/// GetMonitorInfoW with the device name
+#[cfg(target_os = "windows")]
+unsafe fn monitor_info(monitor: HMONITOR) -> Option<MONITORINFOEXW> {
+    unsafe {
+        let mut info = MONITORINFOEXW {
+            monitorInfo: MONITORINFO {
+                cbSize: std::mem::size_of::<MONITORINFOEXW>() as u32,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        GetMonitorInfoW(
+            monitor,
+            &mut info as *mut MONITORINFOEXW as *mut MONITORINFO,
+        )
+        .as_bool()
+        .then_some(info)
+    }
+}
+
+/// Friendly names of the active displays by device name. A display mirrored to
+/// several monitors gets the name of the first.
+#[cfg(target_os = "windows")]
+fn friendly_names() -> HashMap<String, String> {
+    let mut names = HashMap::new();
+    unsafe {
+        let (mut path_count, mut mode_count) = (0, 0);
+        if GetDisplayConfigBufferSizes(QDC_ONLY_ACTIVE_PATHS, &mut path_count, &mut mode_count)
+            != ERROR_SUCCESS
+        {
+            return names;
+        }
+        let mut paths = vec![DISPLAYCONFIG_PATH_INFO::default(); path_count as usize];
+        let mut modes = vec![DISPLAYCONFIG_MODE_INFO::default(); mode_count as usize];
+        if QueryDisplayConfig(
+            QDC_ONLY_ACTIVE_PATHS,
+            &mut path_count,
+            paths.as_mut_ptr(),
+            &mut mode_count,
+            modes.as_mut_ptr(),
+            None,
+        ) != ERROR_SUCCESS
+        {
+            return names;
+        }
+        paths.truncate(path_count as usize);
+
+        for path in &paths {
+            let mut source = DISPLAYCONFIG_SOURCE_DEVICE_NAME::default();
+            source.header.r#type = DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME;
+            source.header.size = std::mem::size_of_val(&source) as u32;
+            source.header.adapterId = path.sourceInfo.adapterId;
+            source.header.id = path.sourceInfo.id;
+            let mut target = DISPLAYCONFIG_TARGET_DEVICE_NAME::default();
+            target.header.r#type = DISPLAYCONFIG_DEVICE_INFO_GET_TARGET_NAME;
+            target.header.size = std::mem::size_of_val(&target) as u32;
+            target.header.adapterId = path.targetInfo.adapterId;
+            target.header.id = path.targetInfo.id;
+            if DisplayConfigGetDeviceInfo(&mut source.header) != 0
+                || DisplayConfigGetDeviceInfo(&mut target.header) != 0
+            {
+                continue;
+            }
+            let name = from_wide(&target.monitorFriendlyDeviceName);
+            if !name.is_empty() {
+                names
+                    .entry(from_wide(&source.viewGdiDeviceName))
+                    .or_insert(name);
+            }
+        }
+    }
+    names
+}
+
+/// String from a null-terminated UTF-16 buffer
+#[cfg(target_os = "windows")]
+fn from_wide(buffer: &[u16]) -> String {
+    let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+    String::from_utf16_lossy(&buffer[..len])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdir::TempDir;
+
+    fn monitor(device: &str, name: Option<&str>, left: i32, top: i32) -> Monitor {
+        Monitor {
+            device: format!(r"\\.\{}", device),
+            name: name.map(str::to_string),
+            bounds: Rect {
+                left,
+                top,
+                right: left + 1920,
+                bottom: top + 1080,
+            },
+            work_area: Rect {
+                left,
+                top,
+                right: left + 1920,
+                bottom: top + 1040,
+            },
+        }
+    }
+
+    #[test]
+    fn test_find() {
+        let mut monitors = vec![
+            monitor("DISPLAY1", Some("DELL U2720Q"), 0, 0),
+            monitor("DISPLAY2", None, -1920, 0),
+            monitor("DISPLAY3", Some("DELL U2720QM"), 0, -1080),
+        ];
+        sort(&mut monitors);
+        let devices: Vec<&str> = monitors.iter().map(Monitor::short_device).collect();
+        assert_eq!(devices, ["DISPLAY2", "DISPLAY3", "DISPLAY1"]);
+
+        let find = |target| find(&monitors, &target);
+        assert_eq!(find(MonitorTarget::Number(1)), Some(0));
+        assert_eq!(find(MonitorTarget::Number(3)), Some(2));
+        assert_eq!(find(MonitorTarget::Number(4)), None);
+        // The exact name wins over a longer one starting with it
+        let name = |name: &str| MonitorTarget::Name(name.to_string());
+        assert_eq!(find(name("dell u2720q")), Some(2));
+        assert_eq!(find(name("DELL U2720QM")), Some(1));
+        assert_eq!(find(name("DELL")), Some(1));
+        assert_eq!(find(name("display2")), Some(0));
+        assert_eq!(find(name(r"\\.\DISPLAY1")), Some(2));
+        assert_eq!(find(name("LG")), None);
+
+        assert_eq!(monitors[0].label(1), "&1  DISPLAY2");
+        assert_eq!(monitors[1].label(2), "&2  DELL U2720QM");
+    }
+
+    #[test]
+    fn test_move_rect() {
+        let left = monitor("DISPLAY1", None, 0, 0).work_area;
+        let right = Rect {
+            left: 1920,
+            top: 200,
+            right: 3200,
+            bottom: 920,
+        };
+        let window = Rect {
+            left: 100,
+            top: 50,
+            right: 1124,
+            bottom: 818,
+        };
+        // Same distance from the corner, but shorter and shifted up to fit
+        assert_eq!(
+            move_rect(window, &left, &right),
+            Rect {
+                left: 2020,
+                top: 200,
+                right: 3044,
+                bottom: 920,
+            }
+        );
+
+        // Shrunk to a smaller display
+        let large = Rect {
+            left: 0,
+            top: 0,
+            right: 1900,
+            bottom: 1000,
+        };
+        assert_eq!(move_rect(large, &left, &right), right);
+    }
+
+    #[test]
+    fn test_setup_key() {
+        let laptop = vec![monitor("DISPLAY1", Some("Built-in"), 0, 0)];
+        let mut docked = vec![
+            monitor("DISPLAY1", Some("Built-in"), 0, 0),
+            monitor("DISPLAY4", None, 1920, -200),
+        ];
+        sort(&mut docked);
+        assert_eq!(setup_key(&laptop), "Built-in 1920x1080@0,0");
+        assert_eq!(
+            setup_key(&docked),
+            "Built-in 1920x1080@0,0; DISPLAY4 1920x1080@1920,-200"
+        );
+
+        // A moved taskbar is the same setup
+        let mut taskbar_left = laptop.clone();
+        taskbar_left[0].work_area.left = 48;
+        assert_eq!(setup_key(&taskbar_left), setup_key(&laptop));
+    }
+
+    #[test]
+    fn test_window_layouts() {
+        let tmp = TempDir::new("layouts");
+        let dir = tmp.path();
+        let layout = |left: i32, maximized| WindowLayout {
+            left,
+            top: 100,
+            right: left + 1024,
+            bottom: 868,
+            maximized,
+        };
+
+        let mut layouts = WindowLayouts::load_from(dir);
+        assert_eq!(layouts.get("laptop"), None);
+        assert!(layouts.set("laptop", layout(100, false)));
+        assert!(layouts.set("docked", layout(2100, true)));
+        assert!(!layouts.set("docked", layout(2100, true)));
+        assert!(layouts.set("laptop", layout(200, false)));
+        layouts.save_to(dir).unwrap();
+
+        let loaded = WindowLayouts::load_from(dir);
+        assert_eq!(loaded, layouts);
+        assert_eq!(loaded.get("laptop"), Some(layout(200, false)));
+        assert_eq!(loaded.get("docked"), Some(layout(2100, true)));
+
+        // The setup used longest ago goes first
+        for i in 0..MAX_LAYOUTS - 1 {
+            layouts.set(&format!("setup {}", i), layout(0, false));
+        }
+        assert_eq!(layouts.get("docked"), None);
+        assert_eq!(layouts.get("laptop"), Some(layout(200, false)));
+    }
+}
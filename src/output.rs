@@ -0,0 +1,182 @@
+//! Output Neovide writes to stdout and stderr.
+//!
+//! Neovide is a GUI program, so without a console its output would be lost. The
+//! wrapper pipes both streams and keeps the last lines of each tab in memory for
+//! the tab's "Show process output" view; handy when nvim fails to start because
+//! of an error in init.lua.
+
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Lines kept per tab; older lines are dropped
+const MAX_LINES: usize = 1000;
+/// Lines of the output quoted when Neovide fails to start
+const EXCERPT_LINES: usize = 4;
+/// Longest line quoted when Neovide fails to start (characters)
+const EXCERPT_LINE_LENGTH: usize = 160;
+
+/// The last lines a tab's Neovide wrote, shared with the threads reading its pipes
+#[derive(Debug, Clone, Default)]
+pub struct OutputLog {
+    inner: Arc<Mutex<Lines>>,
+}
+
+#[derive(Debug, Default)]
+struct Lines {
+    lines: VecDeque<String>,
+    /// Bumped whenever the lines change
+    version: u64,
+    /// Pipes still being read
+    open_streams: usize,
+}
+
+impl OutputLog {
+    /// Read `stream` into the log on a background thread until it closes
+    pub fn capture(&self, stream: impl Read + Send + 'static) {
+        self.inner.lock().unwrap().open_streams += 1;
+        let log = self.clone();
+        thread::spawn(move || {
+            log.read_lines(BufReader::new(stream));
+            log.inner.lock().unwrap().open_streams -= 1;
+        });
+    }
+
+    /// Wait up to `timeout` for the pipes to close after Neovide exited, so the
+    /// log holds everything it wrote. Returns false on timeout (e.g. a process
+    /// Neovide started keeps the pipes open).
+    pub fn wait_closed(&self, timeout: Duration) -> bool {
+        let started = Instant::now();
+        while self.inner.lock().unwrap().open_streams > 0 {
+            if started.elapsed() >= timeout {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        true
+    }
+
+    /// Drop all lines (Neovide is launched again)
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.lines.clear();
+        inner.version += 1;
+    }
+
+    /// Changes whenever lines are added or dropped
+    pub fn version(&self) -> u64 {
+        self.inner.lock().unwrap().version
+    }
+
+    /// All lines, ending in CRLF as edit controls expect
+    pub fn text(&self) -> String {
+        let inner = self.inner.lock().unwrap();
+        let mut text = String::new();
+        for line in &inner.lines {
+            text.push_str(line);
+            text.push_str("\r\n");
+        }
+        text
+    }
+
+    /// The last few non-empty lines, shortened to fit an error message; None
+    /// without output
+    pub fn excerpt(&self) -> Option<String> {
+        let inner = self.inner.lock().unwrap();
+        let mut lines: Vec<String> = inner
+            .lines
+            .iter()
+            .rev()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .take(EXCERPT_LINES)
+            .map(|line| match line.char_indices().nth(EXCERPT_LINE_LENGTH) {
+                Some((end, _)) => format!("{}\u{2026}", &line[..end]),
+                None => line.to_string(),
+            })
+            .collect();
+        lines.reverse();
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+
+    fn push_line(&self, line: String) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.lines.len() == MAX_LINES {
+            inner.lines.pop_front();
+        }
+        inner.lines.push_back(line);
+        inner.version += 1;
+    }
+
+    fn read_lines(&self, mut reader: impl BufRead) {
+        let mut buffer = Vec::new();
+        loop {
+            buffer.clear();
+            // Fails or reads nothing once Neovide exits and the pipe closes
+            match reader.read_until(b'\n', &mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    let line = String::from_utf8_lossy(&buffer);
+                    self.push_line(line.trim_end_matches(['\r', '\n']).to_string());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_read_lines() {
+        let log = OutputLog::default();
+        log.read_lines(Cursor::new(b"first\r\nsecond\n\xff broken\nlast".to_vec()));
+        assert_eq!(log.text(), "first\r\nsecond\r\n\u{fffd} broken\r\nlast\r\n");
+
+        let version = log.version();
+        log.clear();
+        assert_eq!(log.text(), "");
+        assert_ne!(log.version(), version);
+    }
+
+    #[test]
+    fn test_excerpt() {
+        let log = OutputLog::default();
+        assert_eq!(log.excerpt(), None);
+
+        for line in ["one", "two", "", "three", "  four  ", "five", ""] {
+            log.push_line(line.to_string());
+        }
+        assert_eq!(log.excerpt().as_deref(), Some("two\nthree\nfour\nfive"));
+
+        log.clear();
+        log.push_line("x".repeat(EXCERPT_LINE_LENGTH + 10));
+        let excerpt = log.excerpt().unwrap();
+        assert_eq!(excerpt.chars().count(), EXCERPT_LINE_LENGTH + 1);
+        assert!(excerpt.ends_with('\u{2026}'));
+    }
+
+    #[test]
+    fn test_wait_closed() {
+        let log = OutputLog::default();
+        log.capture(Cursor::new(b"done\n".to_vec()));
+        assert!(log.wait_closed(Duration::from_secs(5)));
+        assert_eq!(log.text(), "done\r\n");
+    }
+
+    #[test]
+    fn test_keeps_last_lines() {
+        let log = OutputLog::default();
+        for i in 0..MAX_LINES + 2 {
+            log.push_line(i.to_string());
+        }
+        let text = log.text();
+        assert_eq!(text.lines().count(), MAX_LINES);
+        assert!(text.starts_with("2\r\n"));
+        assert!(text.ends_with(&format!("{}\r\n", MAX_LINES + 1)));
+    }
+}
@@ -0,0 +1,751 @@
+//! Popup lists shown below the tab bar: the profile dropdown, the overflow tabs
+//! and the tab switcher.
+//!
+//! A `PopupList` is a column of items with an optional icon, a label and an
+//! optional close button. The popup captures the mouse while it is open; a click
+//! on an item (or its close button) posts the item's command to the owner and
+//! closes the popup, and a click anywhere else closes it with the `dismissed`
+//! message. The owner keeps the popup's window handle and reacts to the posted
+//! messages, so the popup itself never reaches into the wrapper's state.
+//!
+//! Long lists can be split into groups under header items and get a filter box
+//! at the top. Such a popup takes the keyboard focus: typing narrows the items
+//! down, the arrow keys move the highlight, Enter picks and Escape closes.
+
+#![cfg(target_os = "windows")]
+#![allow(unsafe_op_in_unsafe_fn)]
+
+use windows::Win32::Foundation::{COLORREF, HINSTANCE, HWND, LPARAM, LRESULT, RECT, SIZE, WPARAM};
+use windows::Win32::Graphics::Gdi::{
+    BeginPaint, CreatePen, CreateSolidBrush, DeleteObject, EndPaint, FillRect, FrameRect,
+    GetTextExtentPoint32W, GetTextMetricsW, HDC, HGDIOBJ, InvalidateRect, LineTo, MoveToEx,
+    PAINTSTRUCT, PS_SOLID, SelectObject, SetBkMode, SetTextColor, TEXTMETRICW, TRANSPARENT,
+};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    ReleaseCapture, SetCapture, VIRTUAL_KEY, VK_DOWN, VK_ESCAPE, VK_RETURN, VK_UP,
+};
+use windows::Win32::UI::WindowsAndMessaging::*;
+use windows::core::{PCWSTR, w};
+
+use crate::fonts::{self, TextSize};
+use crate::icons::{ICON_SIZE, get_icon_bitmap};
+use crate::layout::{Rect, TAB_CLOSE_PADDING, TAB_CLOSE_SIZE};
+use crate::window::{
+    TAB_CLOSE_HOVER_COLOR, TAB_HOVER_COLOR, TAB_OUTLINE_COLOR, TAB_UNSELECTED_COLOR, paint_icon,
+    replay_pointer_as_mouse, rgb_to_colorref, text_out_truncated, touch_contact,
+};
+
+const POPUP_CLASS_NAME: PCWSTR = w!("NeovideTabsPopup");
+
+/// Height of each item
+pub const ITEM_HEIGHT: i32 = 28;
+/// Padding around the items
+const PADDING: i32 = 4;
+/// Height taken by the filter box, above the items
+const FILTER_HEIGHT: i32 = ITEM_HEIGHT + PADDING;
+/// Color of headers, the filter placeholder and "No matches"
+const DIM_TEXT_COLOR: u32 = 0xA0A0A0;
+
+/// A message posted to the owner, with its wparam
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PopupCommand {
+    pub msg: u32,
+    pub wparam: usize,
+}
+
+/// One entry of a popup list
+#[derive(Debug, Clone)]
+pub struct PopupItem {
+    /// Icon filename drawn before the label (the space is kept without one, so
+    /// labels line up)
+    pub icon: Option<String>,
+    pub label: String,
+    /// Drawn bold on a highlighted background (e.g. the selected tab)
+    pub selected: bool,
+    /// Draw a separator line above the item
+    pub separator: bool,
+    /// A group header for the items up to the next header: not clickable, and
+    /// drawn dimmed with the number of its items shown
+    pub header: bool,
+    /// Posted to the owner when the item is clicked
+    pub command: PopupCommand,
+    /// Posted when the item's close button is clicked; None for no close button
+    pub close_command: Option<PopupCommand>,
+}
+
+/// Contents and behavior of a popup list
+pub struct PopupList {
+    /// Window the commands are posted to
+    pub owner: HWND,
+    pub items: Vec<PopupItem>,
+    pub background_color: u32,
+    /// Posted to the owner when the popup closes without a choice
+    pub dismissed: u32,
+    /// Only shows the items: no mouse capture or clicks, and the owner moves the
+    /// highlight (see `set_highlighted`)
+    pub passive: bool,
+    /// Show a filter box with this placeholder above the items; the popup takes
+    /// the keyboard focus
+    pub filter: Option<&'static str>,
+}
+
+/// State of an open popup
+struct PopupState {
+    list: PopupList,
+    /// What was typed into the filter box
+    filter_text: String,
+    /// First half of a surrogate pair typed into the filter box
+    high_surrogate: Option<u16>,
+    /// Items shown, top to bottom
+    rows: Vec<usize>,
+    hovered_item: Option<usize>,
+    /// Item whose close button is hovered
+    hovered_close: Option<usize>,
+}
+
+/// Register the popup window class
+pub fn register_class(hinstance: HINSTANCE) -> bool {
+    unsafe {
+        let wc = WNDCLASSW {
+            style: CS_HREDRAW | CS_VREDRAW | CS_DROPSHADOW,
+            lpfnWndProc: Some(popup_proc),
+            hInstance: hinstance,
+            hCursor: LoadCursorW(None, IDC_ARROW).ok().unwrap_or_default(),
+            lpszClassName: POPUP_CLASS_NAME,
+            ..Default::default()
+        };
+        RegisterClassW(&wc) != 0
+    }
+}
+
+/// Height of a popup with `item_count` items
+pub fn list_height(item_count: usize) -> i32 {
+    item_count as i32 * ITEM_HEIGHT + PADDING * 2
+}
+
+/// Height of a popup with `item_count` items below a filter box
+pub fn filtered_list_height(item_count: usize) -> i32 {
+    list_height(item_count) + FILTER_HEIGHT
+}
+
+/// Open a popup showing `list` at `rect` (screen coordinates)
+pub fn open(list: PopupList, rect: Rect) -> Option<HWND> {
+    unsafe {
+        let hinstance = GetModuleHandleW(None).ok()?;
+        let takes_focus = list.filter.is_some();
+        let state = Box::new(PopupState {
+            rows: visible_rows(&list.items, ""),
+            list,
+            filter_text: String::new(),
+            high_surrogate: None,
+            hovered_item: None,
+            hovered_close: None,
+        });
+        let ex_style = if takes_focus {
+            WS_EX_TOPMOST | WS_EX_TOOLWINDOW
+        } else {
+            WS_EX_TOPMOST | WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE
+        };
+        let hwnd = CreateWindowExW(
+            ex_style,
+            POPUP_CLASS_NAME,
+            w!(""),
+            WS_POPUP | WS_VISIBLE,
+            rect.left,
+            rect.top,
+            rect.right - rect.left,
+            rect.bottom - rect.top,
+            None, // No parent - independent window
+            None,
+            hinstance,
+            Some(Box::into_raw(state) as *const std::ffi::c_void),
+        )
+        .ok()?;
+        if takes_focus {
+            // The keyboard goes to the filter box; capture once we are in front
+            let _ = SetForegroundWindow(hwnd);
+            SetCapture(hwnd);
+        }
+        Some(hwnd)
+    }
+}
+
+/// Draw `item` hovered, as a passive popup's current choice
+pub fn set_highlighted(hwnd: HWND, item: Option<usize>) {
+    unsafe {
+        let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut PopupState;
+        if !state_ptr.is_null() && (*state_ptr).hovered_item != item {
+            (*state_ptr).hovered_item = item;
+            let _ = InvalidateRect(hwnd, None, false);
+        }
+    }
+}
+
+/// Rows shown for `filter`: the items whose label contains it (ignoring case),
+/// and the header of each group with an item shown
+fn visible_rows(items: &[PopupItem], filter: &str) -> Vec<usize> {
+    let filter = filter.to_lowercase();
+    let mut rows = Vec::new();
+    let mut header = None;
+    for (i, item) in items.iter().enumerate() {
+        if item.header {
+            header = Some(i);
+        } else if item.label.to_lowercase().contains(&filter) {
+            rows.extend(header.take());
+            rows.push(i);
+        }
+    }
+    rows
+}
+
+/// Character typed as the UTF-16 unit `unit` (a WM_CHAR). Characters outside
+/// the Basic Multilingual Plane (e.g. emoji) arrive as two surrogates; the first
+/// is kept in `high_surrogate` and the character comes with the second.
+fn typed_char(high_surrogate: &mut Option<u16>, unit: u16) -> Option<char> {
+    if (0xD800..0xDC00).contains(&unit) {
+        *high_surrogate = Some(unit);
+        return None;
+    }
+    let units = match high_surrogate.take() {
+        Some(high) => vec![high, unit],
+        None => vec![unit],
+    };
+    char::decode_utf16(units).next()?.ok()
+}
+
+/// Top of the first row
+fn rows_top(state: &PopupState) -> i32 {
+    if state.list.filter.is_some() {
+        PADDING + FILTER_HEIGHT
+    } else {
+        PADDING
+    }
+}
+
+/// Rectangle of row `row` in a popup `client_width` wide
+fn item_rect(state: &PopupState, row: usize, client_width: i32) -> RECT {
+    let top = rows_top(state) + row as i32 * ITEM_HEIGHT;
+    RECT {
+        left: PADDING,
+        top,
+        right: client_width - PADDING,
+        bottom: top + ITEM_HEIGHT,
+    }
+}
+
+/// Close button of the item at `item_rect`
+fn close_rect(item_rect: &RECT) -> RECT {
+    let left = item_rect.right - TAB_CLOSE_PADDING - TAB_CLOSE_SIZE;
+    let top = (item_rect.top + item_rect.bottom - TAB_CLOSE_SIZE) / 2;
+    RECT {
+        left,
+        top,
+        right: left + TAB_CLOSE_SIZE,
+        bottom: top + TAB_CLOSE_SIZE,
+    }
+}
+
+fn contains(rect: &RECT, x: i32, y: i32) -> bool {
+    x >= rect.left && x < rect.right && y >= rect.top && y < rect.bottom
+}
+
+/// Item at (x, y), and whether the point is on its close button. Headers are
+/// not hit.
+fn hit_test(state: &PopupState, x: i32, y: i32, client_width: i32) -> Option<(usize, bool)> {
+    let top = rows_top(state);
+    if y < top {
+        return None;
+    }
+    let row = ((y - top) / ITEM_HEIGHT) as usize;
+    let index = *state.rows.get(row)?;
+    let item = &state.list.items[index];
+    if item.header {
+        return None;
+    }
+    let on_close = item.close_command.is_some()
+        && contains(&close_rect(&item_rect(state, row, client_width)), x, y);
+    Some((index, on_close))
+}
+
+/// Show the rows matching the typed filter, highlight the first match (what
+/// Enter picks) and fit the popup's height to them
+#[allow(unused_must_use)]
+unsafe fn apply_filter(hwnd: HWND, state: &mut PopupState) {
+    state.rows = visible_rows(&state.list.items, &state.filter_text);
+    state.hovered_item = if state.filter_text.is_empty() {
+        None
+    } else {
+        selectable_rows(state).next()
+    };
+    state.hovered_close = None;
+
+    let mut rect = RECT::default();
+    GetWindowRect(hwnd, &mut rect).ok();
+    SetWindowPos(
+        hwnd,
+        None,
+        0,
+        0,
+        rect.right - rect.left,
+        filtered_list_height(state.rows.len().max(1)),
+        SWP_NOMOVE | SWP_NOZORDER | SWP_NOACTIVATE,
+    );
+    InvalidateRect(hwnd, None, false);
+}
+
+/// Items shown that can be picked (not headers)
+fn selectable_rows(state: &PopupState) -> impl Iterator<Item = usize> + '_ {
+    state
+        .rows
+        .iter()
+        .copied()
+        .filter(|&index| !state.list.items[index].header)
+}
+
+/// Move the keyboard highlight to the next (or previous) item shown, wrapping
+/// around at the ends
+fn move_highlight(state: &mut PopupState, forward: bool) {
+    let items: Vec<usize> = selectable_rows(state).collect();
+    let current = state
+        .hovered_item
+        .and_then(|item| items.iter().position(|&i| i == item));
+    let next = match (current, forward) {
+        (Some(pos), true) => items.get(pos + 1).or(items.first()),
+        (Some(pos), false) => pos
+            .checked_sub(1)
+            .and_then(|p| items.get(p))
+            .or(items.last()),
+        (None, true) => items.first(),
+        (None, false) => items.last(),
+    };
+    state.hovered_item = next.copied();
+    state.hovered_close = None;
+}
+
+/// Post `command` (if any) and close the popup
+#[allow(unused_must_use)]
+unsafe fn close_with(hwnd: HWND, state: &PopupState, command: Option<PopupCommand>) {
+    if let Some(command) = command {
+        post(state, command);
+    }
+    ReleaseCapture().ok();
+    DestroyWindow(hwnd).ok();
+}
+
+/// Post `command` to the popup's owner
+fn post(state: &PopupState, command: PopupCommand) {
+    unsafe {
+        let _ = PostMessageW(
+            state.list.owner,
+            command.msg,
+            WPARAM(command.wparam),
+            LPARAM(0),
+        );
+    }
+}
+
+/// Window procedure for popup lists
+#[allow(unused_must_use)]
+unsafe extern "system" fn popup_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut PopupState;
+    match msg {
+        WM_CREATE => {
+            let create_struct = lparam.0 as *const CREATESTRUCTW;
+            if !create_struct.is_null() {
+                let state_ptr = (*create_struct).lpCreateParams as *mut PopupState;
+                SetWindowLongPtrW(hwnd, GWLP_USERDATA, state_ptr as isize);
+                // Capture mouse to detect clicks outside the popup (a popup with
+                // a filter box captures once it has the focus, see `open`)
+                if !state_ptr.is_null()
+                    && !(*state_ptr).list.passive
+                    && (*state_ptr).list.filter.is_none()
+                {
+                    SetCapture(hwnd);
+                }
+            }
+            LRESULT(0)
+        }
+
+        WM_POINTERDOWN | WM_POINTERUPDATE | WM_POINTERUP => {
+            match touch_contact(hwnd, wparam, lparam) {
+                Some(pt) => {
+                    replay_pointer_as_mouse(hwnd, msg, wparam, pt, popup_proc);
+                    LRESULT(0)
+                }
+                None => DefWindowProcW(hwnd, msg, wparam, lparam),
+            }
+        }
+
+        WM_PAINT => {
+            let mut ps = PAINTSTRUCT::default();
+            let hdc = BeginPaint(hwnd, &mut ps);
+            if !state_ptr.is_null() {
+                paint(hwnd, hdc, &*state_ptr);
+            }
+            EndPaint(hwnd, &ps);
+            LRESULT(0)
+        }
+
+        WM_MOUSEMOVE if !state_ptr.is_null() && !(*state_ptr).list.passive => {
+            let state = &mut *state_ptr;
+            let x = (lparam.0 & 0xFFFF) as i16 as i32;
+            let y = ((lparam.0 >> 16) & 0xFFFF) as i16 as i32;
+            let mut rect = RECT::default();
+            GetClientRect(hwnd, &mut rect).ok();
+
+            let hit = hit_test(state, x, y, rect.right);
+            let hovered_item = hit.map(|(index, _)| index);
+            let hovered_close = hit
+                .filter(|&(_, on_close)| on_close)
+                .map(|(index, _)| index);
+            if state.hovered_item != hovered_item || state.hovered_close != hovered_close {
+                state.hovered_item = hovered_item;
+                state.hovered_close = hovered_close;
+                InvalidateRect(hwnd, None, false);
+            }
+            LRESULT(0)
+        }
+
+        WM_LBUTTONDOWN if !state_ptr.is_null() && !(*state_ptr).list.passive => {
+            let state = &*state_ptr;
+            let x = (lparam.0 & 0xFFFF) as i16 as i32;
+            let y = ((lparam.0 >> 16) & 0xFFFF) as i16 as i32;
+            let mut rect = RECT::default();
+            GetClientRect(hwnd, &mut rect).ok();
+
+            if contains(&rect, x, y) {
+                let hit = hit_test(state, x, y, rect.right);
+                if hit.is_none() && state.list.filter.is_some() {
+                    // The filter box or a header: stay open
+                    return LRESULT(0);
+                }
+                if let Some((index, on_close)) = hit {
+                    let item = &state.list.items[index];
+                    match item.close_command {
+                        Some(close_command) if on_close => post(state, close_command),
+                        _ => post(state, item.command),
+                    }
+                }
+            } else {
+                // Click outside - just notify the owner to close
+                post(
+                    state,
+                    PopupCommand {
+                        msg: state.list.dismissed,
+                        wparam: 0,
+                    },
+                );
+            }
+            // Release capture and close popup
+            ReleaseCapture().ok();
+            DestroyWindow(hwnd).ok();
+            LRESULT(0)
+        }
+
+        // A passive popup is driven by its owner alone
+        WM_MOUSEMOVE | WM_LBUTTONDOWN => LRESULT(0),
+
+        WM_CHAR if !state_ptr.is_null() && (*state_ptr).list.filter.is_some() => {
+            let state = &mut *state_ptr;
+            let changed = match typed_char(&mut state.high_surrogate, wparam.0 as u16) {
+                Some('\u{8}') => state.filter_text.pop().is_some(),
+                Some(c) if !c.is_control() => {
+                    state.filter_text.push(c);
+                    true
+                }
+                _ => false,
+            };
+            if changed {
+                apply_filter(hwnd, state);
+            }
+            LRESULT(0)
+        }
+
+        WM_KEYDOWN if !state_ptr.is_null() && (*state_ptr).list.filter.is_some() => {
+            let state = &mut *state_ptr;
+            match VIRTUAL_KEY(wparam.0 as u16) {
+                // Escape clears the filter first
+                VK_ESCAPE if !state.filter_text.is_empty() => {
+                    state.filter_text.clear();
+                    apply_filter(hwnd, state);
+                }
+                VK_ESCAPE => {
+                    let dismissed = PopupCommand {
+                        msg: state.list.dismissed,
+                        wparam: 0,
+                    };
+                    close_with(hwnd, state, Some(dismissed));
+                }
+                VK_RETURN => {
+                    if let Some(index) = state.hovered_item {
+                        close_with(hwnd, state, Some(state.list.items[index].command));
+                    }
+                }
+                VK_UP | VK_DOWN => {
+                    move_highlight(state, wparam.0 as u16 == VK_DOWN.0);
+                    InvalidateRect(hwnd, None, false);
+                }
+                _ => {}
+            }
+            LRESULT(0)
+        }
+
+        WM_CAPTURECHANGED => {
+            // We lost capture - close the popup
+            if !state_ptr.is_null() {
+                let state = &*state_ptr;
+                post(
+                    state,
+                    PopupCommand {
+                        msg: state.list.dismissed,
+                        wparam: 0,
+                    },
+                );
+            }
+            DestroyWindow(hwnd).ok();
+            LRESULT(0)
+        }
+
+        WM_DESTROY => {
+            if !state_ptr.is_null() {
+                // Free the state
+                let _ = Box::from_raw(state_ptr);
+                SetWindowLongPtrW(hwnd, GWLP_USERDATA, 0);
+            }
+            LRESULT(0)
+        }
+
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
+
+/// Paint the background, border and items
+#[allow(unused_must_use)]
+unsafe fn paint(hwnd: HWND, hdc: HDC, state: &PopupState) {
+    let mut rect = RECT::default();
+    GetClientRect(hwnd, &mut rect).ok();
+
+    // Fill background
+    let bg_brush = CreateSolidBrush(COLORREF(rgb_to_colorref(state.list.background_color)));
+    FillRect(hdc, &rect, bg_brush);
+    DeleteObject(HGDIOBJ(bg_brush.0));
+
+    // Draw border
+    let border_pen = CreatePen(PS_SOLID, 1, COLORREF(rgb_to_colorref(TAB_OUTLINE_COLOR)));
+    let old_pen = SelectObject(hdc, HGDIOBJ(border_pen.0));
+    MoveToEx(hdc, rect.left, rect.top, None);
+    LineTo(hdc, rect.right - 1, rect.top);
+    LineTo(hdc, rect.right - 1, rect.bottom - 1);
+    LineTo(hdc, rect.left, rect.bottom - 1);
+    LineTo(hdc, rect.left, rect.top);
+    SelectObject(hdc, old_pen);
+    DeleteObject(HGDIOBJ(border_pen.0));
+
+    if let Some(placeholder) = state.list.filter {
+        paint_filter(hdc, state, placeholder, rect.right);
+        if state.rows.is_empty() {
+            let row_rect = item_rect(state, 0, rect.right);
+            paint_text(hdc, &row_rect, "No matches", DIM_TEXT_COLOR, 400);
+        }
+    }
+
+    for (row, &i) in state.rows.iter().enumerate() {
+        let item = &state.list.items[i];
+        let item_rect = item_rect(state, row, rect.right);
+        let hovered = state.hovered_item == Some(i);
+
+        // Hover or selected background
+        if hovered || item.selected {
+            let color = if hovered {
+                TAB_HOVER_COLOR
+            } else {
+                TAB_UNSELECTED_COLOR
+            };
+            let item_brush = CreateSolidBrush(COLORREF(rgb_to_colorref(color)));
+            FillRect(hdc, &item_rect, item_brush);
+            DeleteObject(HGDIOBJ(item_brush.0));
+        }
+
+        if item.separator {
+            let separator_pen =
+                CreatePen(PS_SOLID, 1, COLORREF(rgb_to_colorref(TAB_OUTLINE_COLOR)));
+            let old_pen = SelectObject(hdc, HGDIOBJ(separator_pen.0));
+            MoveToEx(hdc, item_rect.left, item_rect.top, None);
+            LineTo(hdc, item_rect.right, item_rect.top);
+            SelectObject(hdc, old_pen);
+            DeleteObject(HGDIOBJ(separator_pen.0));
+        }
+
+        // Draw icon
+        if let Some(hbitmap) = item.icon.as_deref().and_then(get_icon_bitmap) {
+            let icon_x = item_rect.left + 4;
+            let icon_y = (item_rect.top + item_rect.bottom - ICON_SIZE) / 2;
+            paint_icon(hdc, hbitmap, icon_x, icon_y, ICON_SIZE, ICON_SIZE);
+        }
+
+        if item.header {
+            // "Name (shown items)"
+            let count = state.rows[row + 1..]
+                .iter()
+                .take_while(|&&index| !state.list.items[index].header)
+                .count();
+            let label = format!("{} ({})", item.label, count);
+            paint_text(hdc, &item_rect, &label, DIM_TEXT_COLOR, 700);
+            continue;
+        }
+
+        // Draw text after the icon, vertically centered
+        let weight = if item.selected { 700 } else { 400 };
+        let close_rect = close_rect(&item_rect);
+        let text_rect = RECT {
+            right: if item.close_command.is_some() {
+                close_rect.left
+            } else {
+                item_rect.right
+            },
+            ..item_rect
+        };
+        paint_text(hdc, &text_rect, &item.label, 0xFFFFFF, weight);
+
+        if item.close_command.is_none() {
+            continue;
+        }
+
+        // Close button background on hover
+        if state.hovered_close == Some(i) {
+            let close_hover_brush =
+                CreateSolidBrush(COLORREF(rgb_to_colorref(TAB_CLOSE_HOVER_COLOR)));
+            FillRect(hdc, &close_rect, close_hover_brush);
+            DeleteObject(HGDIOBJ(close_hover_brush.0));
+        }
+
+        // Draw X for close button
+        let close_pen = CreatePen(PS_SOLID, 1, COLORREF(0x00FFFFFF));
+        let old_pen = SelectObject(hdc, HGDIOBJ(close_pen.0));
+        let cx = (close_rect.left + close_rect.right) / 2;
+        let cy = (close_rect.top + close_rect.bottom) / 2;
+        let x_size = 4;
+        MoveToEx(hdc, cx - x_size, cy - x_size, None);
+        LineTo(hdc, cx + x_size + 1, cy + x_size + 1);
+        MoveToEx(hdc, cx + x_size, cy - x_size, None);
+        LineTo(hdc, cx - x_size - 1, cy + x_size + 1);
+        SelectObject(hdc, old_pen);
+        DeleteObject(HGDIOBJ(close_pen.0));
+    }
+}
+
+/// Draw `text` in a row at `rect`, after the icon space and vertically centered
+#[allow(unused_must_use)]
+unsafe fn paint_text(hdc: HDC, rect: &RECT, text: &str, color: u32, weight: i32) {
+    SetBkMode(hdc, TRANSPARENT);
+    SetTextColor(hdc, COLORREF(rgb_to_colorref(color)));
+    let text_wide: Vec<u16> = text.encode_utf16().collect();
+    let old_font = fonts::select_font_for_text(hdc, TextSize::Body, weight, &text_wide);
+    let mut tm = TEXTMETRICW::default();
+    GetTextMetricsW(hdc, &mut tm);
+    let text_x = rect.left + ICON_SIZE + 8;
+    let text_y = (rect.top + rect.bottom - tm.tmHeight) / 2;
+    text_out_truncated(hdc, text_x, text_y, text, rect.right - text_x - 4);
+    SelectObject(hdc, old_font);
+}
+
+/// Draw the filter box: the typed text and a caret, or the placeholder
+#[allow(unused_must_use)]
+unsafe fn paint_filter(hdc: HDC, state: &PopupState, placeholder: &str, client_width: i32) {
+    let box_rect = RECT {
+        left: PADDING,
+        top: PADDING,
+        right: client_width - PADDING,
+        bottom: PADDING + ITEM_HEIGHT,
+    };
+    let border_brush = CreateSolidBrush(COLORREF(rgb_to_colorref(TAB_OUTLINE_COLOR)));
+    FrameRect(hdc, &box_rect, border_brush);
+    DeleteObject(HGDIOBJ(border_brush.0));
+
+    let typed = !state.filter_text.is_empty();
+    let (text, color) = if typed {
+        (state.filter_text.as_str(), 0xFFFFFF)
+    } else {
+        (placeholder, DIM_TEXT_COLOR)
+    };
+    SetBkMode(hdc, TRANSPARENT);
+    SetTextColor(hdc, COLORREF(rgb_to_colorref(color)));
+    let text_wide: Vec<u16> = text.encode_utf16().collect();
+    let old_font = fonts::select_font_for_text(hdc, TextSize::Body, 400, &text_wide);
+    let mut tm = TEXTMETRICW::default();
+    GetTextMetricsW(hdc, &mut tm);
+    let text_x = box_rect.left + 8;
+    let text_y = (box_rect.top + box_rect.bottom - tm.tmHeight) / 2;
+    let max_width = box_rect.right - text_x - 8;
+    text_out_truncated(hdc, text_x, text_y, text, max_width);
+    let mut size = SIZE::default();
+    if typed {
+        GetTextExtentPoint32W(hdc, &text_wide, &mut size);
+    }
+    SelectObject(hdc, old_font);
+
+    // Caret after the typed text
+    let caret_x = text_x + size.cx.min(max_width);
+    let caret_pen = CreatePen(PS_SOLID, 1, COLORREF(0x00FFFFFF));
+    let old_pen = SelectObject(hdc, HGDIOBJ(caret_pen.0));
+    MoveToEx(hdc, caret_x, text_y, None);
+    LineTo(hdc, caret_x, text_y + tm.tmHeight);
+    SelectObject(hdc, old_pen);
+    DeleteObject(HGDIOBJ(caret_pen.0));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(label: &str, header: bool) -> PopupItem {
+        PopupItem {
+            icon: None,
+            label: label.to_string(),
+            selected: false,
+            separator: false,
+            header,
+            command: PopupCommand { msg: 0, wparam: 0 },
+            close_command: None,
+        }
+    }
+
+    #[test]
+    fn test_visible_rows() {
+        let items = vec![
+            item("Work", true),
+            item("api: main.rs", false),
+            item("Docs", false),
+            item("Personal", true),
+            item("blog", false),
+            item("Empty", true),
+        ];
+        // Without a filter every group with items shows
+        assert_eq!(visible_rows(&items, ""), vec![0, 1, 2, 3, 4]);
+        // Matching ignores case, and groups without a match are hidden
+        assert_eq!(visible_rows(&items, "DOC"), vec![0, 2]);
+        assert_eq!(visible_rows(&items, "Blog"), vec![3, 4]);
+        // Headers don't match themselves
+        assert_eq!(visible_rows(&items, "work"), Vec::<usize>::new());
+        assert_eq!(visible_rows(&items, "nothing"), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_typed_char() {
+        let mut high_surrogate = None;
+        assert_eq!(typed_char(&mut high_surrogate, 'a' as u16), Some('a'));
+        // U+1F600 arrives as 0xD83D 0xDE00
+        assert_eq!(typed_char(&mut high_surrogate, 0xD83D), None);
+        assert_eq!(typed_char(&mut high_surrogate, 0xDE00), Some('\u{1F600}'));
+        assert_eq!(high_surrogate, None);
+        // A stray low surrogate is dropped
+        assert_eq!(typed_char(&mut high_surrogate, 0xDE00), None);
+        assert_eq!(typed_char(&mut high_surrogate, 0x8), Some('\u{8}'));
+    }
+}
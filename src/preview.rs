@@ -0,0 +1,261 @@
+//! Thumbnail previews of background tabs, shown while hovering a tab.
+//!
+//! Windows draws neither hidden windows nor their DWM thumbnails, so a
+//! background tab's Neovide window can't be captured. Instead the selected tab's
+//! window is captured now and then while it is shown (see the poll timer in
+//! window.rs), and hovering a background tab shows its last capture: the tab as
+//! it was when it was last selected.
+
+#![cfg(target_os = "windows")]
+#![allow(unsafe_op_in_unsafe_fn)]
+
+use windows::Win32::Foundation::{
+    COLORREF, HANDLE, HINSTANCE, HWND, LPARAM, LRESULT, RECT, WPARAM,
+};
+use windows::Win32::Graphics::Gdi::{
+    BeginPaint, BitBlt, CreateCompatibleBitmap, CreateCompatibleDC, CreateSolidBrush, DeleteDC,
+    DeleteObject, EndPaint, FrameRect, GetDC, HALFTONE, HBITMAP, HGDIOBJ, PAINTSTRUCT, ReleaseDC,
+    SRCCOPY, SelectObject, SetBrushOrgEx, SetStretchBltMode, StretchBlt,
+};
+use windows::Win32::Storage::Xps::{PRINT_WINDOW_FLAGS, PW_CLIENTONLY, PrintWindow};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::WindowsAndMessaging::*;
+use windows::core::{PCWSTR, w};
+
+use crate::layout::Rect;
+use crate::window::{TAB_OUTLINE_COLOR, rgb_to_colorref};
+
+const PREVIEW_CLASS_NAME: PCWSTR = w!("NeovideTabsPreview");
+
+/// Largest size of a thumbnail; the window's aspect ratio is kept
+const MAX_WIDTH: i32 = 240;
+const MAX_HEIGHT: i32 = 180;
+/// Width of the border around the thumbnail in the preview popup
+const BORDER: i32 = 1;
+
+/// A scaled-down capture of a window
+pub struct Thumbnail {
+    bitmap: HBITMAP,
+    width: i32,
+    height: i32,
+}
+
+impl Thumbnail {
+    /// Size of the preview popup showing this thumbnail
+    pub fn popup_size(&self) -> (i32, i32) {
+        (self.width + BORDER * 2, self.height + BORDER * 2)
+    }
+}
+
+impl Drop for Thumbnail {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = DeleteObject(HGDIOBJ(self.bitmap.0));
+        }
+    }
+}
+
+/// Register the preview window class
+pub fn register_class(hinstance: HINSTANCE) -> bool {
+    unsafe {
+        let wc = WNDCLASSW {
+            style: CS_DROPSHADOW,
+            lpfnWndProc: Some(preview_proc),
+            hInstance: hinstance,
+            lpszClassName: PREVIEW_CLASS_NAME,
+            ..Default::default()
+        };
+        RegisterClassW(&wc) != 0
+    }
+}
+
+/// Capture the client area of the (shown) `window`, scaled down to thumbnail
+/// size. None if the window can't be captured, e.g. because it belongs to an
+/// elevated process.
+pub fn capture(window: HWND) -> Option<Thumbnail> {
+    unsafe {
+        let mut rect = RECT::default();
+        GetClientRect(window, &mut rect).ok()?;
+        let (width, height) = (rect.right, rect.bottom);
+        if width <= 0 || height <= 0 {
+            return None;
+        }
+        let (thumb_width, thumb_height) = thumbnail_size(width, height);
+
+        let screen_dc = GetDC(None);
+        let full_dc = CreateCompatibleDC(screen_dc);
+        let full_bitmap = CreateCompatibleBitmap(screen_dc, width, height);
+        let thumb_dc = CreateCompatibleDC(screen_dc);
+        let thumb_bitmap = CreateCompatibleBitmap(screen_dc, thumb_width, thumb_height);
+        let old_full = SelectObject(full_dc, HGDIOBJ(full_bitmap.0));
+        let old_thumb = SelectObject(thumb_dc, HGDIOBJ(thumb_bitmap.0));
+
+        // PW_RENDERFULLCONTENT also captures windows drawn with DirectX/OpenGL
+        let captured = PrintWindow(
+            window,
+            full_dc,
+            PRINT_WINDOW_FLAGS(PW_CLIENTONLY.0 | PW_RENDERFULLCONTENT),
+        )
+        .as_bool();
+        if captured {
+            SetStretchBltMode(thumb_dc, HALFTONE);
+            let _ = SetBrushOrgEx(thumb_dc, 0, 0, None);
+            let _ = StretchBlt(
+                thumb_dc,
+                0,
+                0,
+                thumb_width,
+                thumb_height,
+                full_dc,
+                0,
+                0,
+                width,
+                height,
+                SRCCOPY,
+            );
+        }
+
+        SelectObject(full_dc, old_full);
+        SelectObject(thumb_dc, old_thumb);
+        let _ = DeleteObject(HGDIOBJ(full_bitmap.0));
+        let _ = DeleteDC(full_dc);
+        let _ = DeleteDC(thumb_dc);
+        ReleaseDC(None, screen_dc);
+
+        let thumbnail = Thumbnail {
+            bitmap: thumb_bitmap,
+            width: thumb_width,
+            height: thumb_height,
+        };
+        captured.then_some(thumbnail)
+    }
+}
+
+/// Show `thumbnail` in a popup at `rect` (screen coordinates, see
+/// `Thumbnail::popup_size`). The popup keeps a copy, so the thumbnail may be
+/// replaced while it is shown.
+pub fn show(thumbnail: &Thumbnail, rect: Rect) -> Option<HWND> {
+    unsafe {
+        let hinstance = GetModuleHandleW(None).ok()?;
+        let copy = CopyImage(
+            HANDLE(thumbnail.bitmap.0),
+            IMAGE_BITMAP,
+            0,
+            0,
+            IMAGE_FLAGS(0),
+        )
+        .ok()?;
+        let hwnd = CreateWindowExW(
+            WS_EX_TOPMOST | WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE | WS_EX_TRANSPARENT,
+            PREVIEW_CLASS_NAME,
+            w!(""),
+            WS_POPUP,
+            rect.left,
+            rect.top,
+            rect.right - rect.left,
+            rect.bottom - rect.top,
+            None,
+            None,
+            hinstance,
+            Some(copy.0 as *const std::ffi::c_void),
+        );
+        match hwnd {
+            Ok(hwnd) => {
+                let _ = ShowWindow(hwnd, SW_SHOWNOACTIVATE);
+                Some(hwnd)
+            }
+            Err(_) => {
+                let _ = DeleteObject(HGDIOBJ(copy.0));
+                None
+            }
+        }
+    }
+}
+
+/// Size of the thumbnail of a `width` x `height` window
+fn thumbnail_size(width: i32, height: i32) -> (i32, i32) {
+    let scale = (MAX_WIDTH as f64 / width as f64).min(MAX_HEIGHT as f64 / height as f64);
+    (
+        ((width as f64 * scale).round() as i32).max(1),
+        ((height as f64 * scale).round() as i32).max(1),
+    )
+}
+
+unsafe extern "system" fn preview_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    let bitmap = HBITMAP(GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut _);
+    match msg {
+        WM_CREATE => {
+            let create_struct = lparam.0 as *const CREATESTRUCTW;
+            if !create_struct.is_null() {
+                SetWindowLongPtrW(
+                    hwnd,
+                    GWLP_USERDATA,
+                    (*create_struct).lpCreateParams as isize,
+                );
+            }
+            LRESULT(0)
+        }
+
+        WM_PAINT => {
+            let mut ps = PAINTSTRUCT::default();
+            let hdc = BeginPaint(hwnd, &mut ps);
+            let mut rect = RECT::default();
+            let _ = GetClientRect(hwnd, &mut rect);
+            if !bitmap.is_invalid() {
+                let bitmap_dc = CreateCompatibleDC(hdc);
+                let old = SelectObject(bitmap_dc, HGDIOBJ(bitmap.0));
+                let _ = BitBlt(
+                    hdc,
+                    BORDER,
+                    BORDER,
+                    rect.right - BORDER * 2,
+                    rect.bottom - BORDER * 2,
+                    bitmap_dc,
+                    0,
+                    0,
+                    SRCCOPY,
+                );
+                SelectObject(bitmap_dc, old);
+                let _ = DeleteDC(bitmap_dc);
+            }
+            let border = CreateSolidBrush(COLORREF(rgb_to_colorref(TAB_OUTLINE_COLOR)));
+            FrameRect(hdc, &rect, border);
+            let _ = DeleteObject(HGDIOBJ(border.0));
+            let _ = EndPaint(hwnd, &ps);
+            LRESULT(0)
+        }
+
+        // Clicks go through to whatever is below
+        WM_NCHITTEST => LRESULT(HTTRANSPARENT as isize),
+
+        WM_DESTROY => {
+            if !bitmap.is_invalid() {
+                let _ = DeleteObject(HGDIOBJ(bitmap.0));
+                SetWindowLongPtrW(hwnd, GWLP_USERDATA, 0);
+            }
+            LRESULT(0)
+        }
+
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thumbnail_size() {
+        // Wide windows are limited by the width, tall ones by the height
+        assert_eq!(thumbnail_size(1920, 1080), (240, 135));
+        assert_eq!(thumbnail_size(800, 1200), (120, 180));
+        // Small windows are scaled up to the same size
+        assert_eq!(thumbnail_size(480, 270), (240, 135));
+        assert_eq!(thumbnail_size(10000, 1), (240, 1));
+    }
+}
@@ -1,25 +1,163 @@
 #![cfg(target_os = "windows")]
 
 use anyhow::{Context, Result};
-use std::path::Path;
-use std::process::{Child, Command};
-use std::sync::{Arc, Mutex};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex, RwLock};
 use std::thread;
-use std::time::Duration;
-use windows::Win32::Foundation::{BOOL, HWND, LPARAM, RECT};
+use std::time::{Duration, Instant};
+use windows::Win32::Foundation::{
+    BOOL, CloseHandle, ERROR_ACCESS_DENIED, FILETIME, HANDLE, HWND, LPARAM, RECT, STILL_ACTIVE,
+    WAIT_OBJECT_0,
+};
+use windows::Win32::Graphics::Gdi::{
+    GetMonitorInfoW, MONITOR_DEFAULTTONEAREST, MONITORINFO, MonitorFromWindow,
+};
+use windows::Win32::System::Diagnostics::ToolHelp::{
+    CreateToolhelp32Snapshot, PROCESSENTRY32W, Process32FirstW, Process32NextW, TH32CS_SNAPPROCESS,
+    TH32CS_SNAPTHREAD, THREADENTRY32, Thread32First, Thread32Next,
+};
+use windows::Win32::System::ProcessStatus::{
+    EmptyWorkingSet, GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS,
+};
+use windows::Win32::System::Threading::{
+    GetExitCodeProcess, GetProcessTimes, OpenProcess, OpenThread,
+    PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_SET_QUOTA, PROCESS_SYNCHRONIZE, ResumeThread,
+    SuspendThread, THREAD_SUSPEND_RESUME, WaitForSingleObject,
+};
 use windows::Win32::UI::WindowsAndMessaging::{
-    EnumWindows, GetClassNameW, GetWindowRect, GetWindowTextLengthW, GetWindowTextW,
-    GetWindowThreadProcessId, HWND_TOP, IsWindow, IsWindowVisible, MB_ICONERROR, MB_OK,
-    MessageBoxW, PostMessageW, SW_HIDE, SW_SHOW, SWP_NOZORDER, SetWindowPos, ShowWindow, WM_CLOSE,
+    EnumWindows, GW_OWNER, GWL_EXSTYLE, GetClassNameW, GetWindow, GetWindowLongPtrW, GetWindowRect,
+    GetWindowTextLengthW, GetWindowTextW, GetWindowThreadProcessId, HWND_NOTOPMOST, HWND_TOP,
+    HWND_TOPMOST, IsHungAppWindow, IsWindow, IsWindowVisible, PostMessageW, SW_HIDE, SW_SHOW,
+    SW_SHOWNA, SWP_ASYNCWINDOWPOS, SWP_FRAMECHANGED, SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE,
+    SWP_NOZORDER, SetWindowLongPtrW, SetWindowPos, ShowWindow, WM_APP, WM_CLOSE, WS_EX_APPWINDOW,
+    WS_EX_TOOLWINDOW,
 };
-use windows::core::PCWSTR;
 
-use crate::window::CONTENT_INSET;
+use crate::attention;
+use crate::config::NeovideWindowMatch;
+use crate::elevation::{self, ProxyArgs, ProxyProcess, WindowCommand};
+use crate::layout::Rect;
+use crate::output::OutputLog;
+use crate::tabs::{CloseRequest, LaunchOptions, ProcessHandle};
+use crate::usage::{ProcessTable, UsageSample};
+use crate::window::content_inset;
+use crate::winevents::{mark_managed_window, unmark_managed_window};
+
+/// Whether newly found Neovide windows are hidden from Alt-Tab and the taskbar.
+/// Read by the spawn thread, so it is stored globally rather than passed down.
+static HIDE_FROM_ALT_TAB: AtomicBool = AtomicBool::new(false);
+
+/// Whether newly found Neovide windows are made topmost (wrapper kept on top)
+static TOPMOST: AtomicBool = AtomicBool::new(false);
+
+/// Posted to the wrapper by the spawn thread once a Neovide window has been found
+pub const WM_NEOVIDE_WINDOW_READY: u32 = WM_APP + 11;
+
+/// Posted to the wrapper by the spawn thread when a Neovide window never appeared
+/// (see `NeovideProcess::take_window_error`)
+pub const WM_NEOVIDE_WINDOW_FAILED: u32 = WM_APP + 18;
+
+/// How long the spawn thread looks for the Neovide window
+const WINDOW_SEARCH_TIMEOUT: Duration = Duration::from_secs(60);
+/// Delay between two searches for the Neovide window
+const WINDOW_SEARCH_INTERVAL: Duration = Duration::from_millis(100);
+/// How long a Neovide that exited before its window appeared gets to finish
+/// writing its output, which the launch error quotes
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How Neovide windows are recognized (`neovide_window` in the config); read by
+/// the spawn threads
+static WINDOW_MATCH: LazyLock<RwLock<NeovideWindowMatch>> = LazyLock::new(Default::default);
+
+/// Set whether Neovide windows found from now on are hidden from Alt-Tab
+pub fn set_hide_from_alt_tab(hide: bool) {
+    HIDE_FROM_ALT_TAB.store(hide, Ordering::Relaxed);
+}
+
+/// Set whether Neovide windows found from now on are topmost
+pub fn set_topmost(topmost: bool) {
+    TOPMOST.store(topmost, Ordering::Relaxed);
+}
+
+/// Set how Neovide windows are recognized from now on
+pub fn set_window_match(rules: NeovideWindowMatch) {
+    *WINDOW_MATCH.write().unwrap() = rules;
+}
+
+/// Another top-level window of a tab's Neovide process (besides the one filling
+/// the content area), kept hidden and moved along with the tab
+struct ExtraWindow {
+    hwnd: usize,
+    /// Hidden because the tab was hidden; shown again with the tab
+    hidden_with_tab: bool,
+}
+
+impl ExtraWindow {
+    fn hwnd(&self) -> HWND {
+        HWND(self.hwnd as *mut _)
+    }
+}
+
+/// The process started for a tab: Neovide itself, or the elevated proxy running it
+enum ChildProcess {
+    Neovide(Child),
+    Proxy(ProxyProcess),
+}
+
+impl ChildProcess {
+    fn id(&self) -> u32 {
+        match self {
+            ChildProcess::Neovide(child) => child.id(),
+            ChildProcess::Proxy(proxy) => proxy.id(),
+        }
+    }
+
+    /// Check whether the process has exited (reaping it if it has)
+    fn try_wait(&mut self) -> std::io::Result<bool> {
+        match self {
+            ChildProcess::Neovide(child) => Ok(child.try_wait()?.is_some()),
+            ChildProcess::Proxy(proxy) => Ok(proxy.has_exited()),
+        }
+    }
+
+    /// Terminate the process and wait for it to exit
+    fn kill(&mut self) -> Result<()> {
+        match self {
+            ChildProcess::Neovide(child) => {
+                child
+                    .kill()
+                    .context("Failed to terminate Neovide process")?;
+                child.wait().context("Failed to wait for Neovide process")?;
+                Ok(())
+            }
+            ChildProcess::Proxy(proxy) => proxy.kill(),
+        }
+    }
+}
 
 /// Manages the lifecycle of a Neovide process instance
 pub struct NeovideProcess {
-    child: Arc<Mutex<Option<Child>>>,
+    child: Arc<Mutex<Option<ChildProcess>>>,
     neovide_hwnd: Arc<Mutex<Option<usize>>>,
+    /// Command window of the elevated proxy, for elevated tabs
+    proxy_hwnd: Arc<Mutex<Option<usize>>>,
+    /// Address nvim listens on for RPC; None until launched
+    listen_address: Option<String>,
+    /// Other top-level windows of the process (see `track_extra_windows`)
+    extra_windows: Mutex<Vec<ExtraWindow>>,
+    /// Why the Neovide window never appeared, until the UI thread takes it
+    window_error: Arc<Mutex<Option<String>>>,
+    /// Whether the process threads are currently suspended (background tab)
+    suspended: AtomicBool,
+    /// Whether Neovide has been launched (false while waiting in the spawn queue)
+    launched: bool,
+    /// Set by the close watcher when nvim stayed open after being asked to close
+    close_cancelled: Arc<AtomicBool>,
+    /// What Neovide wrote to stdout and stderr (not captured for elevated tabs,
+    /// whose Neovide is started by the proxy)
+    output: OutputLog,
 }
 
 impl NeovideProcess {
@@ -39,64 +177,283 @@ impl NeovideProcess {
         Ok(())
     }
 
-    /// Spawn a new Neovide process with the specified dimensions, working directory, and position it
+    /// Spawn a new Neovide process with the specified dimensions and working directory
+    #[allow(dead_code)]
     pub fn spawn(
         width: u32,
         height: u32,
         parent_hwnd: HWND,
         working_directory: Option<&Path>,
     ) -> Result<Self> {
-        let mut cmd = Command::new("neovide");
-        cmd.arg("--frame")
-            .arg("none")
-            .arg("--size")
-            .arg(format!("{}x{}", width, height));
-
-        // Set working directory if specified
-        if let Some(dir) = working_directory {
-            if dir.is_dir() {
-                cmd.current_dir(dir);
-                eprintln!("Spawning Neovide in directory: {:?}", dir);
+        let mut process = Self::pending();
+        let options = LaunchOptions {
+            working_directory,
+            ..Default::default()
+        };
+        process.launch(width, height, parent_hwnd, &options)?;
+        Ok(process)
+    }
+
+    /// Ask nvim to quit over its RPC pipe (for windows that refuse WM_CLOSE)
+    fn request_quit_over_rpc(&self) -> CloseRequest {
+        // Only a process spawned without a tab (`spawn`) has no pipe
+        let Some(address) = self.listen_address.as_deref() else {
+            return CloseRequest::Blocked;
+        };
+        match attention::request_quit(address) {
+            Ok(()) => {
+                eprintln!("Asked nvim to quit over RPC instead");
+                CloseRequest::Sent
+            }
+            Err(e) => {
+                eprintln!("Failed to ask nvim to quit over RPC: {:#}", e);
+                CloseRequest::Blocked
+            }
+        }
+    }
+
+    /// Send WM_CLOSE to the Neovide window (see `request_close`)
+    fn send_close(&self) -> CloseRequest {
+        // A suspended Neovide could never process the close request
+        self.resume();
+        let Some(neovide_hwnd) = self.window_handle() else {
+            return CloseRequest::NoWindow;
+        };
+        unsafe {
+            // Check if the window is still valid
+            if !IsWindow(neovide_hwnd).as_bool() {
+                return CloseRequest::NoWindow;
+            }
+        }
+        // The proxy of an elevated tab passes the message on at Neovide's level
+        if self.proxy().is_some() {
+            return if self.send(neovide_hwnd, WindowCommand::Close) {
+                CloseRequest::Sent
             } else {
+                CloseRequest::NoWindow
+            };
+        }
+
+        match unsafe { PostMessageW(neovide_hwnd, WM_CLOSE, None, None) } {
+            Ok(()) => CloseRequest::Sent,
+            Err(e) if e.code() == ERROR_ACCESS_DENIED.to_hresult() => {
                 eprintln!(
-                    "Warning: Working directory {:?} does not exist, using default",
-                    dir
+                    "WM_CLOSE to Neovide window 0x{:X} was blocked: it runs at a higher integrity level than neovide-tabs",
+                    neovide_hwnd.0 as usize
                 );
+                self.request_quit_over_rpc()
+            }
+            Err(e) => {
+                eprintln!("Failed to send WM_CLOSE to Neovide window: {}", e);
+                CloseRequest::NoWindow
+            }
+        }
+    }
+
+    /// Show the extra windows that were hidden along with the tab
+    fn show_extra_windows(&self) {
+        for extra in self.extra_windows.lock().unwrap().iter_mut() {
+            if extra.hidden_with_tab {
+                unsafe {
+                    let _ = ShowWindow(extra.hwnd(), SW_SHOWNA);
+                }
+                extra.hidden_with_tab = false;
+            }
+        }
+    }
+
+    /// Hide the visible extra windows along with the tab
+    fn hide_extra_windows(&self) {
+        for extra in self.extra_windows.lock().unwrap().iter_mut() {
+            unsafe {
+                if IsWindowVisible(extra.hwnd()).as_bool() {
+                    let _ = ShowWindow(extra.hwnd(), SW_HIDE);
+                    extra.hidden_with_tab = true;
+                }
             }
         }
+    }
 
-        let child = cmd.spawn().context("Failed to spawn Neovide process")?;
+    /// Move the extra windows by the offset the tab's window was moved by
+    fn move_extra_windows(&self, dx: i32, dy: i32) {
+        for extra in self.extra_windows.lock().unwrap().iter() {
+            unsafe {
+                let mut rect = RECT::default();
+                if GetWindowRect(extra.hwnd(), &mut rect).is_ok() {
+                    let _ = SetWindowPos(
+                        extra.hwnd(),
+                        None,
+                        rect.left + dx,
+                        rect.top + dy,
+                        0,
+                        0,
+                        SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Command window and process ID of the elevated proxy, for elevated tabs
+    fn proxy(&self) -> Option<(HWND, u32)> {
+        let proxy_hwnd = (*self.proxy_hwnd.lock().unwrap())?;
+        let pid = self.child.lock().unwrap().as_ref().map(|c| c.id())?;
+        Some((HWND(proxy_hwnd as *mut _), pid))
+    }
+
+    /// Apply `command` to the Neovide window, through the proxy for elevated tabs
+    fn send(&self, neovide_hwnd: HWND, command: WindowCommand) -> bool {
+        run_window_command(neovide_hwnd, self.proxy(), command)
+    }
+}
+
+impl ProcessHandle for NeovideProcess {
+    type Window = HWND;
+
+    /// Create a process that has not been launched yet (waiting in the spawn queue).
+    /// It counts as running until it is launched or dropped.
+    fn pending() -> Self {
+        NeovideProcess {
+            child: Arc::new(Mutex::new(None)),
+            neovide_hwnd: Arc::new(Mutex::new(None)),
+            proxy_hwnd: Arc::new(Mutex::new(None)),
+            listen_address: None,
+            extra_windows: Mutex::new(Vec::new()),
+            window_error: Arc::new(Mutex::new(None)),
+            suspended: AtomicBool::new(false),
+            launched: false,
+            close_cancelled: Arc::new(AtomicBool::new(false)),
+            output: OutputLog::default(),
+        }
+    }
+
+    /// Check whether the Neovide process has been launched (left the spawn queue)
+    fn is_launched(&self) -> bool {
+        self.launched
+    }
+
+    /// Launch Neovide with the specified dimensions and `options`.
+    /// A background thread waits for its window, then posts WM_NEOVIDE_WINDOW_READY
+    /// to `parent_hwnd`; positioning and showing happen on the UI thread.
+    fn launch(
+        &mut self,
+        width: u32,
+        height: u32,
+        parent_hwnd: HWND,
+        options: &LaunchOptions,
+    ) -> Result<()> {
+        let LaunchOptions {
+            working_directory,
+            tab_id,
+            forward_attention,
+            elevated,
+            env,
+            files,
+            nvim_cmd,
+        } = *options;
+        // nvim listens on a pipe of its own, for closing it, the attention hook and
+        // the other requests over RPC
+        let listen_address = tab_id.map(attention::pipe_name);
+        let listen_address = listen_address.as_deref();
+        // A wrapper running as administrator starts elevated Neovides directly
+        let proxied = elevated && !elevation::is_elevated();
+        let child = if proxied {
+            let args = ProxyArgs {
+                width,
+                height,
+                working_directory: working_directory.map(Path::to_path_buf),
+                listen_address: listen_address.map(str::to_string),
+                env: env.to_vec(),
+                files: files.to_vec(),
+                nvim_cmd: nvim_cmd.map(str::to_string),
+            };
+            ChildProcess::Proxy(ProxyProcess::launch(&args)?)
+        } else {
+            let mut cmd = neovide_command(
+                width,
+                height,
+                working_directory,
+                listen_address,
+                env,
+                files,
+                nvim_cmd,
+            );
+            // Without a console Neovide's output would be lost
+            cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+            let mut child = cmd
+                .spawn()
+                .with_context(|| format!("Failed to spawn Neovide process ({:?})", cmd))?;
+            self.output.clear();
+            if let Some(stdout) = child.stdout.take() {
+                self.output.capture(stdout);
+            }
+            if let Some(stderr) = child.stderr.take() {
+                self.output.capture(stderr);
+            }
+            ChildProcess::Neovide(child)
+        };
+        self.launched = true;
+        self.listen_address = listen_address.map(str::to_string);
 
         // Get the process ID to find the correct window later
         let child_pid = child.id();
 
-        let child_arc = Arc::new(Mutex::new(Some(child)));
-        let child_clone = Arc::clone(&child_arc);
-        let neovide_hwnd = Arc::new(Mutex::new(None));
-        let neovide_hwnd_clone = Arc::clone(&neovide_hwnd);
+        *self.child.lock().unwrap() = Some(child);
+        let child_clone = Arc::clone(&self.child);
+        let neovide_hwnd_clone = Arc::clone(&self.neovide_hwnd);
+        let proxy_hwnd_clone = Arc::clone(&self.proxy_hwnd);
+        let window_error_clone = Arc::clone(&self.window_error);
+        let output = self.output.clone();
 
         // Convert HWND to raw pointer for thread safety
         let parent_hwnd_raw = parent_hwnd.0 as usize;
 
-        // Find and position the Neovide window
+        // Find the Neovide window and hand it over to the UI thread
         thread::spawn(move || {
             // Reconstruct HWND from raw pointer
             let parent_hwnd = HWND(parent_hwnd_raw as *mut _);
 
-            // Retry finding the window multiple times
-            let mut attempts = 0;
-            let max_attempts = 600; // Try for up to 60 seconds
-            let mut found = false;
+            // Waiting on the process between searches notices right away when
+            // Neovide exits without ever showing a window
+            let process = ProcessWait::open(child_pid);
+            let started = Instant::now();
 
-            while attempts < max_attempts {
-                thread::sleep(Duration::from_millis(100));
-
-                // Find the Neovide window by process ID
-                if let Some(info) = find_neovide_window_by_pid(child_pid) {
+            loop {
+                // Find the Neovide window by process ID (of the proxy's Neovide
+                // for elevated tabs, which also need the proxy's command window)
+                let window = if proxied {
+                    find_proxied_neovide_window(child_pid)
+                } else {
+                    find_neovide_window_by_pid(child_pid).map(|info| (info, None))
+                };
+                if let Some((info, proxy_hwnd)) = window {
+                    if let Some(proxy_hwnd) = proxy_hwnd {
+                        elevation::send_command(
+                            proxy_hwnd,
+                            child_pid,
+                            WindowCommand::Attach(info.hwnd.0 as usize),
+                        );
+                    }
+                    *proxy_hwnd_clone.lock().unwrap() = proxy_hwnd.map(|hwnd| hwnd.0 as usize);
                     *neovide_hwnd_clone.lock().unwrap() = Some(info.hwnd.0 as usize);
+                    mark_managed_window(info.hwnd);
+                    if HIDE_FROM_ALT_TAB.load(Ordering::Relaxed) {
+                        run_window_command(
+                            info.hwnd,
+                            proxy_hwnd.map(|hwnd| (hwnd, child_pid)),
+                            WindowCommand::AltTabVisible(false),
+                        );
+                    }
+                    if TOPMOST.load(Ordering::Relaxed) {
+                        run_window_command(
+                            info.hwnd,
+                            proxy_hwnd.map(|hwnd| (hwnd, child_pid)),
+                            WindowCommand::Topmost(true),
+                        );
+                    }
 
                     // Debug output - show window details
-                    eprintln!("Found Neovide window after {} attempts:", attempts + 1);
+                    eprintln!("Found Neovide window after {:?}:", started.elapsed());
                     eprintln!(
                         "  HWND: 0x{:X}, PID: {}",
                         info.hwnd.0 as usize, info.process_id
@@ -114,67 +471,96 @@ impl NeovideProcess {
                     );
                     eprintln!("  Visible: {}", info.visible);
 
-                    // Position the window (32 is the title bar height)
-                    match move_window_to_parent_content_area(info.hwnd, parent_hwnd, 32) {
-                        Ok(_) => {
-                            eprintln!("Successfully positioned Neovide window");
-                        }
-                        Err(e) => {
-                            eprintln!("Failed to position Neovide window: {}", e);
+                    // Windows of several tabs may become ready at the same time; the UI
+                    // thread places them in tab order so only the selected one is shown
+                    unsafe {
+                        if let Err(e) =
+                            PostMessageW(parent_hwnd, WM_NEOVIDE_WINDOW_READY, None, None)
+                        {
+                            eprintln!("Failed to notify wrapper of Neovide window: {}", e);
                         }
                     }
 
-                    found = true;
-                    break;
+                    return;
                 }
 
-                attempts += 1;
-            }
-
-            if !found {
-                eprintln!(
-                    "Failed to find Neovide window (PID: {}) after {} seconds",
-                    child_pid,
-                    max_attempts / 10
-                );
-                show_neovide_window_timeout_error();
-                std::process::exit(1);
+                let error = if started.elapsed() >= WINDOW_SEARCH_TIMEOUT {
+                    format!(
+                        "Neovide's window did not appear within {} seconds",
+                        WINDOW_SEARCH_TIMEOUT.as_secs()
+                    )
+                } else if process.wait_for_exit(WINDOW_SEARCH_INTERVAL)
+                    || child_exited(&child_clone)
+                {
+                    // Usually nvim failed to start (an error in init.lua, nvim
+                    // missing): say why, in Neovide's own words
+                    output.wait_closed(OUTPUT_DRAIN_TIMEOUT);
+                    startup_failure(process.exit_code(), started.elapsed(), output.excerpt())
+                } else {
+                    continue;
+                };
+                // The UI thread turns the tab into an error tab
+                eprintln!("{} (PID: {})", error, child_pid);
+                *window_error_clone.lock().unwrap() = Some(error);
+                unsafe {
+                    if let Err(e) = PostMessageW(parent_hwnd, WM_NEOVIDE_WINDOW_FAILED, None, None)
+                    {
+                        eprintln!("Failed to notify wrapper of missing Neovide window: {}", e);
+                    }
+                }
+                return;
             }
         });
 
+        if let Some(tab_id) = tab_id.filter(|_| forward_attention) {
+            attention::watch(tab_id, parent_hwnd);
+        }
+
         // Note: We no longer use a background thread to wait on the child process.
         // Instead, we poll the process status via is_running() and try_wait().
-        // The child_clone is no longer needed since we keep the child in the Arc<Mutex>.
-        drop(child_clone);
 
-        Ok(NeovideProcess {
-            child: child_arc,
-            neovide_hwnd,
-        })
+        Ok(())
+    }
+
+    /// Why the Neovide window never appeared (timeout, or Neovide exited first), once.
+    /// Set by the spawn thread before it posts WM_NEOVIDE_WINDOW_FAILED.
+    fn take_window_error(&self) -> Option<String> {
+        self.window_error.lock().unwrap().take()
+    }
+
+    /// Terminate a Neovide whose window never appeared and go back to the state
+    /// before the launch, so the tab can launch it again
+    fn abandon(&mut self) {
+        if let Err(e) = self.terminate() {
+            eprintln!("Failed to terminate Neovide without a window: {}", e);
+        }
+        self.launched = false;
     }
 
     /// Terminate the Neovide process forcefully using kill()
     /// If the process has already exited, this is a no-op.
-    pub fn terminate(&mut self) -> Result<()> {
+    fn terminate(&mut self) -> Result<()> {
+        // The tab is going away, so its windows are no longer managed
+        if let Some(hwnd) = self.window_handle() {
+            unmark_managed_window(hwnd);
+        }
+        for extra in self.extra_windows.lock().unwrap().iter() {
+            unmark_managed_window(extra.hwnd());
+        }
         if let Some(mut child) = self.child.lock().unwrap().take() {
             // First check if the process has already exited
             match child.try_wait() {
-                Ok(Some(_)) => {
+                Ok(true) => {
                     // Already exited, nothing to do
                     Ok(())
                 }
-                Ok(None) => {
+                Ok(false) => {
                     // Still running, kill it
-                    child
-                        .kill()
-                        .context("Failed to terminate Neovide process")?;
-                    child.wait().context("Failed to wait for Neovide process")?;
-                    Ok(())
+                    child.kill()
                 }
                 Err(_) => {
                     // Error checking status, try to kill anyway but ignore errors
                     let _ = child.kill();
-                    let _ = child.wait();
                     Ok(())
                 }
             }
@@ -184,44 +570,54 @@ impl NeovideProcess {
     }
 
     /// Request graceful close by sending WM_CLOSE to the Neovide window.
-    /// Returns true if the message was sent successfully, false if the window
-    /// handle is not yet available (caller should fall back to terminate()).
+    /// Returns NoWindow if the window handle is not yet available (caller should
+    /// fall back to terminate()). A window running at a higher integrity level
+    /// drops the message; nvim is then asked to quit over its RPC pipe instead
+    /// (every launched tab has one), and only if that fails too is it Blocked.
     /// Note: This does not immediately close the window - Neovide may prompt
     /// the user to save unsaved files. The process polling will detect when
     /// the process actually exits.
-    pub fn request_close(&self) -> bool {
-        if let Some(hwnd_raw) = *self.neovide_hwnd.lock().unwrap() {
-            let neovide_hwnd = HWND(hwnd_raw as *mut _);
-            unsafe {
-                // Check if the window is still valid
-                if !IsWindow(neovide_hwnd).as_bool() {
-                    return false;
-                }
-                // PostMessageW returns Ok(()) on success
-                PostMessageW(neovide_hwnd, WM_CLOSE, None, None).is_ok()
+    fn request_close(&self) -> CloseRequest {
+        let request = self.send_close();
+        // Notice when the user cancels nvim's save prompt
+        if request == CloseRequest::Sent {
+            self.close_cancelled.store(false, Ordering::Relaxed);
+            if let Some(address) = self.listen_address.as_deref() {
+                attention::watch_close(address, Arc::clone(&self.close_cancelled));
             }
-        } else {
-            false
         }
+        request
+    }
+
+    /// Check (once) whether nvim stayed open after the last close request
+    fn take_close_cancelled(&self) -> bool {
+        self.close_cancelled.swap(false, Ordering::Relaxed)
     }
 
     /// Check if the Neovide process is still running.
     /// Returns true if the process is still running, false if it has exited or was never started.
     /// When the process is detected as exited, this method reaps it (consumes the exit status)
     /// and clears the child handle to prevent issues with subsequent operations.
-    pub fn is_running(&self) -> bool {
+    fn is_running(&self) -> bool {
+        // A queued process has not started yet, so it cannot have exited either
+        if !self.launched {
+            return true;
+        }
         let mut child_guard = self.child.lock().unwrap();
         if let Some(child) = child_guard.as_mut() {
-            // try_wait() returns Ok(Some(status)) if exited, Ok(None) if still running
+            // try_wait() returns Ok(true) if exited, Ok(false) if still running
             match child.try_wait() {
-                Ok(Some(_status)) => {
+                // Exiting before the window appeared is a launch failure, which the
+                // spawn thread reports; until then the tab stays
+                Ok(true) if !self.is_ready() => true,
+                Ok(true) => {
                     // Process has exited - take ownership and drop to fully reap it
                     // This prevents issues with terminate() trying to wait on an
                     // already-reaped process
                     let _ = child_guard.take();
                     false
                 }
-                Ok(None) => true,
+                Ok(false) => true,
                 Err(_) => {
                     // Error checking status - take ownership and drop to clean up
                     let _ = child_guard.take();
@@ -236,11 +632,24 @@ impl NeovideProcess {
     /// Update the Neovide window position and size to match parent's content area
     /// (client area minus title bar)
     /// Returns true if the window was actually moved, false if already in position or not ready
-    pub fn update_position(&self, parent_hwnd: HWND, titlebar_height: i32) -> bool {
+    fn update_position(&self, parent_hwnd: HWND, titlebar_height: i32) -> bool {
+        // Moving a suspended window would block; it is repositioned on activation
+        if self.is_suspended() {
+            return false;
+        }
         if let Some(hwnd_raw) = *self.neovide_hwnd.lock().unwrap() {
             let neovide_hwnd = HWND(hwnd_raw as *mut _);
-            match move_window_to_parent_content_area(neovide_hwnd, parent_hwnd, titlebar_height) {
-                Ok(moved) => moved,
+            match move_window_to_parent_content_area(
+                neovide_hwnd,
+                parent_hwnd,
+                titlebar_height,
+                self.proxy(),
+            ) {
+                Ok(Some((dx, dy))) => {
+                    self.move_extra_windows(dx, dy);
+                    true
+                }
+                Ok(None) => false,
                 Err(e) => {
                     eprintln!("Failed to update Neovide position: {}", e);
                     false
@@ -251,68 +660,243 @@ impl NeovideProcess {
         }
     }
 
-    /// Update position only if needed, then show and bring to foreground
-    /// This is the proper sequence for activating a tab
-    pub fn activate(&self, parent_hwnd: HWND, titlebar_height: i32) {
+    /// Show the window and bring it to the foreground. The tab moves the window
+    /// into place first if the wrapper moved while it was hidden.
+    fn activate(&self) {
+        // Resume before touching the window: a suspended process cannot answer
+        // the messages sent by ShowWindow
+        self.resume();
         if let Some(hwnd_raw) = *self.neovide_hwnd.lock().unwrap() {
             let neovide_hwnd = HWND(hwnd_raw as *mut _);
+            self.send(neovide_hwnd, WindowCommand::Show);
+            self.show_extra_windows();
+            self.send(neovide_hwnd, WindowCommand::Foreground);
+        }
+    }
 
-            // First ensure position is correct (only moves if needed)
-            let _ = move_window_to_parent_content_area(neovide_hwnd, parent_hwnd, titlebar_height);
+    /// Neovide may destroy and create its window again, e.g. when its GPU device
+    /// was lost during sleep. Windows of elevated tabs are found by the proxy and
+    /// not looked up here.
+    fn revalidate_window(&self) -> bool {
+        let Some(hwnd) = self.window_handle() else {
+            return false;
+        };
+        if unsafe { IsWindow(hwnd).as_bool() } || self.proxy().is_some() {
+            return false;
+        }
+        let Some(pid) = self.child.lock().unwrap().as_ref().map(|c| c.id()) else {
+            return false;
+        };
+        let Some(info) = find_neovide_window_by_pid(pid) else {
+            return false;
+        };
+        eprintln!(
+            "Neovide window 0x{:X} was replaced by 0x{:X}",
+            hwnd.0 as usize, info.hwnd.0 as usize
+        );
+        *self.neovide_hwnd.lock().unwrap() = Some(info.hwnd.0 as usize);
+        unmark_managed_window(hwnd);
+        mark_managed_window(info.hwnd);
+        if HIDE_FROM_ALT_TAB.load(Ordering::Relaxed) {
+            self.set_alt_tab_visible(false);
+        }
+        if TOPMOST.load(Ordering::Relaxed) {
+            self.set_topmost(true);
+        }
+        true
+    }
 
-            unsafe {
-                // Show the window
-                let _ = ShowWindow(neovide_hwnd, SW_SHOW);
-                // Bring to foreground
-                let _ = windows::Win32::UI::WindowsAndMessaging::SetForegroundWindow(neovide_hwnd);
-                let _ = windows::Win32::UI::WindowsAndMessaging::BringWindowToTop(neovide_hwnd);
+    /// Look for other top-level windows of the Neovide process (e.g. a second
+    /// window it opened) and keep them consistent with the tab: hidden while the
+    /// tab's window is hidden and out of Alt-Tab like it. The windows of elevated
+    /// tabs are out of reach and not tracked.
+    fn track_extra_windows(&self) {
+        if self.is_suspended() || self.proxy().is_some() {
+            return;
+        }
+        let Some(main_hwnd) = self.window_handle() else {
+            return;
+        };
+        let Some(pid) = self.child.lock().unwrap().as_ref().map(|c| c.id()) else {
+            return;
+        };
+        let found: Vec<HWND> = find_neovide_windows_by_pid(pid)
+            .into_iter()
+            .map(|info| info.hwnd)
+            .filter(|&hwnd| hwnd != main_hwnd)
+            .collect();
+
+        let mut extras = self.extra_windows.lock().unwrap();
+        extras.retain(|extra| found.contains(&extra.hwnd()));
+
+        let tab_visible = unsafe { IsWindowVisible(main_hwnd).as_bool() };
+        for hwnd in found {
+            if extras.iter().any(|extra| extra.hwnd() == hwnd) {
+                continue;
             }
+            eprintln!(
+                "Found another window of Neovide process {}: 0x{:X}",
+                pid, hwnd.0 as usize
+            );
+            mark_managed_window(hwnd);
+            if HIDE_FROM_ALT_TAB.load(Ordering::Relaxed) {
+                set_alt_tab_visibility(hwnd, false);
+            }
+            let hidden_with_tab = !tab_visible && unsafe { IsWindowVisible(hwnd).as_bool() };
+            if hidden_with_tab {
+                unsafe {
+                    let _ = ShowWindow(hwnd, SW_HIDE);
+                }
+            }
+            extras.push(ExtraWindow {
+                hwnd: hwnd.0 as usize,
+                hidden_with_tab,
+            });
+        }
+    }
+
+    /// Get the Neovide window handle, if the window has been found
+    fn window_handle(&self) -> Option<HWND> {
+        self.neovide_hwnd
+            .lock()
+            .unwrap()
+            .map(|hwnd_raw| HWND(hwnd_raw as *mut _))
+    }
+
+    /// Process ID of Neovide itself (not of the elevated proxy), once its window
+    /// has been found
+    fn neovide_pid(&self) -> Option<u32> {
+        let hwnd = self.window_handle()?;
+        let mut process_id = 0u32;
+        unsafe { GetWindowThreadProcessId(hwnd, Some(&mut process_id)) };
+        (process_id != 0).then_some(process_id)
+    }
+
+    /// Show or hide the Neovide window in Alt-Tab and the taskbar
+    fn set_alt_tab_visible(&self, visible: bool) {
+        if self.is_suspended() {
+            return;
+        }
+        if let Some(hwnd) = self.window_handle() {
+            self.send(hwnd, WindowCommand::AltTabVisible(visible));
+        }
+        for extra in self.extra_windows.lock().unwrap().iter() {
+            set_alt_tab_visibility(extra.hwnd(), visible);
+        }
+    }
+
+    /// Keep the Neovide window above non-topmost windows (or stop doing so)
+    fn set_topmost(&self, topmost: bool) {
+        if let Some(hwnd) = self.window_handle() {
+            self.send(hwnd, WindowCommand::Topmost(topmost));
+        }
+    }
+
+    /// Suspend all threads of the Neovide process to stop it using CPU/GPU while
+    /// its tab is in the background. Returns true if the process was suspended.
+    /// Elevated tabs are never suspended (their threads can't be opened).
+    fn suspend(&self) -> bool {
+        if self.is_suspended() || !self.is_ready() || self.proxy().is_some() {
+            return false;
+        }
+        let Some(pid) = self.child.lock().unwrap().as_ref().map(|c| c.id()) else {
+            return false;
+        };
+        match set_process_threads_suspended(pid, true) {
+            Ok(()) => {
+                self.suspended.store(true, Ordering::Relaxed);
+                true
+            }
+            Err(e) => {
+                eprintln!("Failed to suspend Neovide process {}: {}", pid, e);
+                false
+            }
+        }
+    }
+
+    /// Resume a suspended Neovide process (no-op if it is not suspended)
+    fn resume(&self) {
+        if !self.suspended.swap(false, Ordering::Relaxed) {
+            return;
+        }
+        if let Some(pid) = self.child.lock().unwrap().as_ref().map(|c| c.id())
+            && let Err(e) = set_process_threads_suspended(pid, false)
+        {
+            eprintln!("Failed to resume Neovide process {}: {}", pid, e);
+        }
+    }
+
+    /// Empty the working sets of the Neovide process tree (hibernating tab)
+    fn trim_memory(&self) {
+        if let Some(pid) = self.child.lock().unwrap().as_ref().map(|c| c.id())
+            && let Err(e) = trim_process_tree_memory(pid)
+        {
+            eprintln!("Failed to trim memory of Neovide process {}: {}", pid, e);
+        }
+    }
+
+    /// Check if the Neovide process is currently suspended
+    fn is_suspended(&self) -> bool {
+        self.suspended.load(Ordering::Relaxed)
+    }
+
+    /// Read the memory and CPU time counters of the Neovide process and all of its
+    /// descendants (nvim and the processes it starts). Works while suspended.
+    fn usage_sample(&self, processes: &ProcessTable) -> Option<UsageSample> {
+        let pid = self.child.lock().unwrap().as_ref().map(|c| c.id())?;
+        Some(query_process_tree_usage(processes, pid))
+    }
+
+    /// Check whether Windows considers the Neovide window hung (it has not
+    /// processed messages for several seconds). Suspended tabs are never reported.
+    fn is_hung(&self) -> bool {
+        if self.is_suspended() {
+            return false;
         }
+        self.window_handle()
+            .is_some_and(|hwnd| unsafe { IsHungAppWindow(hwnd).as_bool() })
     }
 
     /// Check if the Neovide window has been found and positioned
-    pub fn is_ready(&self) -> bool {
+    fn is_ready(&self) -> bool {
         self.neovide_hwnd.lock().unwrap().is_some()
     }
 
     /// Bring the Neovide window to the foreground
-    #[allow(dead_code)]
-    pub fn bring_to_foreground(&self) {
-        if let Some(hwnd_raw) = *self.neovide_hwnd.lock().unwrap() {
-            let neovide_hwnd = HWND(hwnd_raw as *mut _);
-            unsafe {
-                // Use SetForegroundWindow to bring Neovide to front
-                let _ = windows::Win32::UI::WindowsAndMessaging::SetForegroundWindow(neovide_hwnd);
-                // Also use BringWindowToTop as a backup
-                let _ = windows::Win32::UI::WindowsAndMessaging::BringWindowToTop(neovide_hwnd);
-            }
+    fn bring_to_foreground(&self) {
+        if let Some(neovide_hwnd) = self.window_handle() {
+            self.send(neovide_hwnd, WindowCommand::Foreground);
         }
     }
 
     /// Show the Neovide window
-    #[allow(dead_code)]
-    pub fn show(&self) {
-        if let Some(hwnd_raw) = *self.neovide_hwnd.lock().unwrap() {
-            let neovide_hwnd = HWND(hwnd_raw as *mut _);
-            unsafe {
-                let _ = ShowWindow(neovide_hwnd, SW_SHOW);
-            }
+    fn show(&self) {
+        self.resume();
+        if let Some(neovide_hwnd) = self.window_handle() {
+            self.send(neovide_hwnd, WindowCommand::Show);
+            self.show_extra_windows();
         }
     }
 
     /// Hide the Neovide window
-    pub fn hide(&self) {
-        if let Some(hwnd_raw) = *self.neovide_hwnd.lock().unwrap() {
-            let neovide_hwnd = HWND(hwnd_raw as *mut _);
-            unsafe {
-                let _ = ShowWindow(neovide_hwnd, SW_HIDE);
-            }
+    fn hide(&self) {
+        // Suspended tabs are always hidden already
+        if self.is_suspended() {
+            return;
+        }
+        if let Some(neovide_hwnd) = self.window_handle() {
+            self.send(neovide_hwnd, WindowCommand::Hide);
+            self.hide_extra_windows();
         }
     }
 
     /// Get the current window title of the Neovide window.
     /// Returns an empty string if the window is not yet ready or title cannot be retrieved.
-    pub fn get_window_title(&self) -> String {
+    fn get_window_title(&self) -> String {
+        // GetWindowTextW sends WM_GETTEXT, which a suspended process never answers
+        if self.is_suspended() {
+            return String::new();
+        }
         if let Some(hwnd_raw) = *self.neovide_hwnd.lock().unwrap() {
             let neovide_hwnd = HWND(hwnd_raw as *mut _);
             unsafe {
@@ -340,6 +924,30 @@ impl NeovideProcess {
             String::new()
         }
     }
+
+    /// Check whether nvim was started with an RPC pipe (every launched tab is)
+    fn has_rpc(&self) -> bool {
+        self.listen_address.is_some()
+    }
+
+    /// Turn Neovide's animations off (or back on) over nvim's RPC pipe; None if
+    /// nvim was started without one
+    fn request_animations_paused(&self, paused: bool) -> Option<Result<()>> {
+        let address = self.listen_address.as_deref()?;
+        Some(attention::request_animations_paused(address, paused))
+    }
+
+    /// Have nvim edit `path` over its RPC pipe; None if nvim was started without one
+    fn request_edit(&self, path: &Path) -> Option<Result<()>> {
+        let address = self.listen_address.as_deref()?;
+        Some(attention::request_edit(address, path))
+    }
+
+    /// What Neovide wrote to stdout and stderr; None for elevated tabs
+    fn output(&self) -> Option<OutputLog> {
+        let proxied = matches!(*self.child.lock().unwrap(), Some(ChildProcess::Proxy(_)));
+        (!proxied).then(|| self.output.clone())
+    }
 }
 
 impl Drop for NeovideProcess {
@@ -361,8 +969,11 @@ pub struct WindowInfo {
 
 /// Context for EnumWindows callback - finds Neovide window by exact match
 struct NeovideSearchContext {
-    result: Option<WindowInfo>,
+    results: Vec<WindowInfo>,
     target_pid: Option<u32>,
+    rules: NeovideWindowMatch,
+    /// Collect every matching window instead of stopping at the first
+    find_all: bool,
 }
 
 /// Context for listing all matching windows
@@ -371,30 +982,13 @@ struct WindowListContext {
     windows: Vec<WindowInfo>,
 }
 
-/// Callback for EnumWindows to find Neovide window by class name and process ID
-/// Note: We don't check the title because Neovide's title changes based on the current file/directory
+/// Callback for EnumWindows to find Neovide windows by process ID, recognized by
+/// class name or title (see `NeovideWindowMatch`)
 unsafe extern "system" fn enum_windows_neovide_callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
     unsafe {
         let context = &mut *(lparam.0 as *mut NeovideSearchContext);
 
-        let visible = IsWindowVisible(hwnd).as_bool();
-
-        // Get class name first (this is the reliable identifier)
-        let class_name = {
-            let mut buffer: Vec<u16> = vec![0; 256];
-            let len = GetClassNameW(hwnd, &mut buffer);
-            if len == 0 {
-                return BOOL(1); // Continue
-            }
-            String::from_utf16_lossy(&buffer[..len as usize])
-        };
-
-        // Check for exact class match: "Window Class" (Neovide's window class)
-        if class_name != "Window Class" {
-            return BOOL(1); // Continue enumeration
-        }
-
-        // Get process ID
+        // Get process ID first (the reliable identifier)
         let mut process_id: u32 = 0;
         GetWindowThreadProcessId(hwnd, Some(&mut process_id));
 
@@ -406,7 +1000,24 @@ unsafe extern "system" fn enum_windows_neovide_callback(hwnd: HWND, lparam: LPAR
             return BOOL(1); // Continue enumeration - wrong process
         }
 
-        // Get window title (for logging purposes only)
+        // Owned windows (IME, tooltips, dialogs) belong to another window
+        if GetWindow(hwnd, GW_OWNER).is_ok_and(|owner| !owner.is_invalid()) {
+            return BOOL(1);
+        }
+
+        let visible = IsWindowVisible(hwnd).as_bool();
+
+        let class_name = {
+            let mut buffer: Vec<u16> = vec![0; 256];
+            let len = GetClassNameW(hwnd, &mut buffer);
+            if len == 0 {
+                return BOOL(1); // Continue
+            }
+            String::from_utf16_lossy(&buffer[..len as usize])
+        };
+
+        // The title changes with the current file/directory, so it only helps to
+        // recognize builds with another class name
         let title = {
             let len = GetWindowTextLengthW(hwnd);
             if len == 0 {
@@ -422,12 +1033,16 @@ unsafe extern "system" fn enum_windows_neovide_callback(hwnd: HWND, lparam: LPAR
             }
         };
 
+        if !context.rules.matches(&class_name, &title, visible) {
+            return BOOL(1); // Continue enumeration - not a Neovide window
+        }
+
         // Get window rect
         let mut rect = RECT::default();
         let _ = GetWindowRect(hwnd, &mut rect);
 
         // Found it!
-        context.result = Some(WindowInfo {
+        context.results.push(WindowInfo {
             hwnd,
             title,
             class_name,
@@ -436,31 +1051,33 @@ unsafe extern "system" fn enum_windows_neovide_callback(hwnd: HWND, lparam: LPAR
             visible,
         });
 
-        BOOL(0) // Stop enumeration - found it
+        // Stop enumeration unless all windows of the process are wanted
+        BOOL(context.find_all as i32)
     }
 }
 
 /// Find a Neovide window by class name "Window Class"
 #[allow(dead_code)]
 fn find_neovide_window() -> Option<WindowInfo> {
-    let mut context = NeovideSearchContext {
-        result: None,
-        target_pid: None,
-    };
+    search_neovide_windows(None, false).pop()
+}
 
-    unsafe {
-        let context_ptr = &mut context as *mut NeovideSearchContext as isize;
-        let _ = EnumWindows(Some(enum_windows_neovide_callback), LPARAM(context_ptr));
-    }
+/// Find a Neovide window by process ID
+pub(crate) fn find_neovide_window_by_pid(pid: u32) -> Option<WindowInfo> {
+    search_neovide_windows(Some(pid), false).pop()
+}
 
-    context.result
+/// Find every top-level Neovide window of a process, in Z order
+fn find_neovide_windows_by_pid(pid: u32) -> Vec<WindowInfo> {
+    search_neovide_windows(Some(pid), true)
 }
 
-/// Find a Neovide window by process ID
-fn find_neovide_window_by_pid(pid: u32) -> Option<WindowInfo> {
+fn search_neovide_windows(target_pid: Option<u32>, find_all: bool) -> Vec<WindowInfo> {
     let mut context = NeovideSearchContext {
-        result: None,
-        target_pid: Some(pid),
+        results: Vec::new(),
+        target_pid,
+        rules: WINDOW_MATCH.read().unwrap().clone(),
+        find_all,
     };
 
     unsafe {
@@ -468,7 +1085,7 @@ fn find_neovide_window_by_pid(pid: u32) -> Option<WindowInfo> {
         let _ = EnumWindows(Some(enum_windows_neovide_callback), LPARAM(context_ptr));
     }
 
-    context.result
+    context.results
 }
 
 /// Callback for listing all matching windows with details
@@ -582,7 +1199,78 @@ pub fn debug_list_windows(search: &str) {
     }
 }
 
-/// Calculate the target position and size for a Neovide window within the parent's content area
+/// The command that starts Neovide borderless at `width`x`height` in
+/// `working_directory` opening `files`, with nvim running `nvim_cmd` and listening
+/// for RPC on `listen_address` and the variables of `env` added to the wrapper's
+/// environment
+pub(crate) fn neovide_command(
+    width: u32,
+    height: u32,
+    working_directory: Option<&Path>,
+    listen_address: Option<&str>,
+    env: &[(String, String)],
+    files: &[PathBuf],
+    nvim_cmd: Option<&str>,
+) -> Command {
+    let mut cmd = Command::new("neovide");
+    cmd.arg("--frame")
+        .arg("none")
+        .arg("--size")
+        .arg(format!("{}x{}", width, height))
+        // A forked Neovide would leave the tracked process right away (and with
+        // it the tab), even when forking is enabled by NEOVIDE_FORK
+        .arg("--no-fork");
+    cmd.envs(env.iter().map(|(name, value)| (name, value)));
+    cmd.args(files);
+
+    // Arguments after "--" are passed on to nvim
+    if nvim_cmd.is_some() || listen_address.is_some() {
+        cmd.arg("--");
+    }
+    if let Some(nvim_cmd) = nvim_cmd {
+        cmd.arg(format!("+{}", nvim_cmd));
+    }
+    if let Some(address) = listen_address {
+        cmd.arg("--listen").arg(address);
+    }
+
+    // Set working directory if specified
+    if let Some(dir) = working_directory {
+        if dir.is_dir() {
+            cmd.current_dir(dir);
+            eprintln!("Spawning Neovide in directory: {:?}", dir);
+        } else {
+            eprintln!(
+                "Warning: Working directory {:?} does not exist, using default",
+                dir
+            );
+        }
+    }
+    cmd
+}
+
+/// Launch error of a Neovide that exited with `exit_code` `elapsed` after it was
+/// started, before its window appeared, quoting the end of its output
+fn startup_failure(exit_code: Option<u32>, elapsed: Duration, excerpt: Option<String>) -> String {
+    let mut error = match exit_code {
+        Some(code) => format!("Neovide exited with code {}", code),
+        None => "Neovide exited".to_string(),
+    };
+    error.push_str(&format!(
+        " {:.1} s after starting, before its window appeared",
+        elapsed.as_secs_f64()
+    ));
+    if let Some(excerpt) = excerpt {
+        error.push('\n');
+        error.push_str(&excerpt);
+        error.push_str("\nRight-click the tab and choose \"Show process output\" for all of it");
+    }
+    error
+}
+
+/// Calculate the target position and size for a Neovide window within the parent's content area.
+/// A target off every display (the parent's monitor was just disconnected) is
+/// pulled onto the work area of the monitor nearest to the parent.
 fn calculate_target_rect(parent_hwnd: HWND, titlebar_height: i32) -> Result<(i32, i32, i32, i32)> {
     unsafe {
         // Get parent window's client area
@@ -591,9 +1279,10 @@ fn calculate_target_rect(parent_hwnd: HWND, titlebar_height: i32) -> Result<(i32
             .context("Failed to get parent client rect")?;
 
         // Convert top-left of content area (below title bar, with inset) to screen coordinates
+        let inset = content_inset();
         let mut top_left = windows::Win32::Foundation::POINT {
-            x: client_rect.left + CONTENT_INSET,
-            y: client_rect.top + titlebar_height + CONTENT_INSET,
+            x: client_rect.left + inset,
+            y: client_rect.top + titlebar_height + inset,
         };
 
         let result = windows::Win32::Graphics::Gdi::ClientToScreen(parent_hwnd, &mut top_left);
@@ -602,21 +1291,48 @@ fn calculate_target_rect(parent_hwnd: HWND, titlebar_height: i32) -> Result<(i32
         }
 
         // Target size is the parent's client area size minus title bar height and insets
-        let target_width = client_rect.right - client_rect.left - (CONTENT_INSET * 2);
-        let target_height =
-            client_rect.bottom - client_rect.top - titlebar_height - (CONTENT_INSET * 2);
+        let target_width = client_rect.right - client_rect.left - (inset * 2);
+        let target_height = client_rect.bottom - client_rect.top - titlebar_height - (inset * 2);
+
+        let mut target = Rect {
+            left: top_left.x,
+            top: top_left.y,
+            right: top_left.x + target_width,
+            bottom: top_left.y + target_height,
+        };
+        let monitor = MonitorFromWindow(parent_hwnd, MONITOR_DEFAULTTONEAREST);
+        let mut info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+            ..Default::default()
+        };
+        if GetMonitorInfoW(monitor, &mut info).as_bool() {
+            let work = info.rcWork;
+            target = target.pulled_into(&Rect {
+                left: work.left,
+                top: work.top,
+                right: work.right,
+                bottom: work.bottom,
+            });
+        }
 
-        Ok((top_left.x, top_left.y, target_width, target_height))
+        Ok((
+            target.left,
+            target.top,
+            target.right - target.left,
+            target.bottom - target.top,
+        ))
     }
 }
 
-/// Move and resize a window to fill the parent's content area (below title bar, with inset)
-/// Returns true if the window was actually moved, false if it was already in position
+/// Move and resize a window to fill the parent's content area (below title bar, with inset),
+/// through `proxy` for elevated Neovide windows.
+/// Returns how far the window's top-left corner was moved, or None if it was already in position
 fn move_window_to_parent_content_area(
     neovide_hwnd: HWND,
     parent_hwnd: HWND,
     titlebar_height: i32,
-) -> Result<bool> {
+    proxy: Option<(HWND, u32)>,
+) -> Result<Option<(i32, i32)>> {
     unsafe {
         let (target_x, target_y, target_width, target_height) =
             calculate_target_rect(parent_hwnd, titlebar_height)?;
@@ -638,7 +1354,7 @@ fn move_window_to_parent_content_area(
             && current_height == target_height
         {
             // Already in position, no need to move
-            return Ok(false);
+            return Ok(None);
         }
 
         eprintln!(
@@ -653,42 +1369,326 @@ fn move_window_to_parent_content_area(
             target_height
         );
 
-        // SetWindowPos with SWP_NOZORDER to move AND resize
-        SetWindowPos(
-            neovide_hwnd,
-            HWND_TOP,
-            target_x,
-            target_y,
-            target_width,
-            target_height,
-            SWP_NOZORDER,
-        )
-        .context("SetWindowPos failed")?;
+        let command = WindowCommand::Move {
+            x: target_x,
+            y: target_y,
+            width: target_width,
+            height: target_height,
+        };
+        match proxy {
+            Some((proxy_hwnd, proxy_pid)) => {
+                if !elevation::send_command(proxy_hwnd, proxy_pid, command) {
+                    anyhow::bail!("Failed to send move to the elevated proxy");
+                }
+            }
+            None => apply_window_command(neovide_hwnd, command)?,
+        }
 
-        Ok(true)
+        Ok(Some((target_x - current_x, target_y - current_y)))
     }
 }
 
-/// Display an error message when Neovide window is not found after timeout
-fn show_neovide_window_timeout_error() {
-    let message = "Failed to find Neovide window after 60 seconds.\n\n\
-        The Neovide process was started but its window could not be detected.\n\n\
-        This may be caused by:\n\
-        - Neovide taking too long to initialize\n\
-        - A configuration issue with Neovide\n\
-        - Neovide crashing during startup\n\n\
-        Please check your Neovide installation and try again.";
-    let title = "Error: Neovide Window Not Found";
+/// Apply `command` to `neovide_hwnd` directly, or through the elevated proxy
+/// `proxy` (command window and process ID). Returns true if it was carried out
+/// or handed to the proxy.
+fn run_window_command(
+    neovide_hwnd: HWND,
+    proxy: Option<(HWND, u32)>,
+    command: WindowCommand,
+) -> bool {
+    match proxy {
+        Some((proxy_hwnd, proxy_pid)) => elevation::send_command(proxy_hwnd, proxy_pid, command),
+        None => match apply_window_command(neovide_hwnd, command) {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("Failed to apply {:?} to Neovide window: {}", command, e);
+                false
+            }
+        },
+    }
+}
 
+/// Carry out `command` on a window of the same (or lower) integrity level
+pub(crate) fn apply_window_command(hwnd: HWND, command: WindowCommand) -> Result<()> {
     unsafe {
-        let wide_message: Vec<u16> = message.encode_utf16().chain(std::iter::once(0)).collect();
-        let wide_title: Vec<u16> = title.encode_utf16().chain(std::iter::once(0)).collect();
+        match command {
+            WindowCommand::Move {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                // SetWindowPos with SWP_NOZORDER to move AND resize
+                SetWindowPos(hwnd, HWND_TOP, x, y, width, height, SWP_NOZORDER)
+                    .context("SetWindowPos failed")?;
+            }
+            WindowCommand::Show => {
+                let _ = ShowWindow(hwnd, SW_SHOW);
+            }
+            WindowCommand::Hide => {
+                let _ = ShowWindow(hwnd, SW_HIDE);
+            }
+            WindowCommand::Foreground => {
+                // Use SetForegroundWindow to bring Neovide to front,
+                // and BringWindowToTop as a backup
+                let _ = windows::Win32::UI::WindowsAndMessaging::SetForegroundWindow(hwnd);
+                let _ = windows::Win32::UI::WindowsAndMessaging::BringWindowToTop(hwnd);
+            }
+            WindowCommand::Close => {
+                PostMessageW(hwnd, WM_CLOSE, None, None).context("PostMessageW failed")?;
+            }
+            WindowCommand::AltTabVisible(visible) => set_alt_tab_visibility(hwnd, visible),
+            WindowCommand::Topmost(topmost) => {
+                // Asynchronous: the window's thread may be suspended
+                let insert_after = if topmost {
+                    HWND_TOPMOST
+                } else {
+                    HWND_NOTOPMOST
+                };
+                SetWindowPos(
+                    hwnd,
+                    insert_after,
+                    0,
+                    0,
+                    0,
+                    0,
+                    SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE | SWP_ASYNCWINDOWPOS,
+                )
+                .context("SetWindowPos failed")?;
+            }
+            // Only meaningful to the elevated proxy
+            WindowCommand::Attach(_) => {}
+        }
+    }
+    Ok(())
+}
 
-        MessageBoxW(
+/// Find the Neovide window started by the elevated proxy `proxy_pid`, together
+/// with the proxy's command window (both must exist before the tab is usable)
+fn find_proxied_neovide_window(proxy_pid: u32) -> Option<(WindowInfo, Option<HWND>)> {
+    let proxy_hwnd = elevation::find_proxy_window(proxy_pid)?;
+    let pids = process_tree(&process_table()?, proxy_pid);
+    pids.into_iter()
+        .skip(1)
+        .find_map(find_neovide_window_by_pid)
+        .map(|info| (info, Some(proxy_hwnd)))
+}
+
+/// Check whether the launched process has exited, as its `Child` (or the proxy)
+/// sees it; this works when no `ProcessWait` handle could be opened
+fn child_exited(child: &Mutex<Option<ChildProcess>>) -> bool {
+    child
+        .lock()
+        .unwrap()
+        .as_mut()
+        .is_some_and(|child| child.try_wait().unwrap_or(false))
+}
+
+/// Handle for waiting on a process by ID; without one (e.g. access denied) the
+/// wait just sleeps, and `child_exited` notices the exit
+struct ProcessWait(Option<HANDLE>);
+
+impl ProcessWait {
+    fn open(pid: u32) -> Self {
+        let access = PROCESS_SYNCHRONIZE | PROCESS_QUERY_LIMITED_INFORMATION;
+        Self(unsafe { OpenProcess(access, false, pid).ok() })
+    }
+
+    /// Exit code of the process, once it has exited
+    fn exit_code(&self) -> Option<u32> {
+        let mut code = 0;
+        unsafe { GetExitCodeProcess(self.0?, &mut code).ok()? };
+        (code != STILL_ACTIVE.0 as u32).then_some(code)
+    }
+
+    /// Wait up to `timeout` for the process to exit; returns true if it has
+    fn wait_for_exit(&self, timeout: Duration) -> bool {
+        match self.0 {
+            Some(handle) => unsafe {
+                WaitForSingleObject(handle, timeout.as_millis() as u32) == WAIT_OBJECT_0
+            },
+            None => {
+                thread::sleep(timeout);
+                false
+            }
+        }
+    }
+}
+
+impl Drop for ProcessWait {
+    fn drop(&mut self) {
+        if let Some(handle) = self.0 {
+            unsafe {
+                let _ = CloseHandle(handle);
+            }
+        }
+    }
+}
+
+/// Suspend or resume every thread of a process.
+/// Uses a Toolhelp thread snapshot with SuspendThread/ResumeThread, which nests the
+/// same way as the per-thread suspend count, so each suspend needs exactly one resume.
+fn set_process_threads_suspended(pid: u32, suspend: bool) -> Result<()> {
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0)
+            .context("Failed to create thread snapshot")?;
+
+        let mut entry = THREADENTRY32 {
+            dwSize: std::mem::size_of::<THREADENTRY32>() as u32,
+            ..Default::default()
+        };
+        let mut has_entry = Thread32First(snapshot, &mut entry).is_ok();
+        while has_entry {
+            if entry.th32OwnerProcessID == pid
+                && let Ok(thread) = OpenThread(THREAD_SUSPEND_RESUME, false, entry.th32ThreadID)
+            {
+                if suspend {
+                    SuspendThread(thread);
+                } else {
+                    ResumeThread(thread);
+                }
+                let _ = CloseHandle(thread);
+            }
+            has_entry = Thread32Next(snapshot, &mut entry).is_ok();
+        }
+
+        let _ = CloseHandle(snapshot);
+        Ok(())
+    }
+}
+
+/// Snapshot of the running processes and their parents; None (logged) if it
+/// can't be taken
+pub fn process_table() -> Option<ProcessTable> {
+    unsafe {
+        let snapshot = match CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                eprintln!("Failed to create process snapshot: {}", e);
+                return None;
+            }
+        };
+
+        let mut entries = Vec::new();
+        let mut entry = PROCESSENTRY32W {
+            dwSize: std::mem::size_of::<PROCESSENTRY32W>() as u32,
+            ..Default::default()
+        };
+        let mut has_entry = Process32FirstW(snapshot, &mut entry).is_ok();
+        while has_entry {
+            entries.push((entry.th32ProcessID, entry.th32ParentProcessID));
+            has_entry = Process32NextW(snapshot, &mut entry).is_ok();
+        }
+        let _ = CloseHandle(snapshot);
+        Some(ProcessTable::new(entries))
+    }
+}
+
+/// Creation time of a process (FILETIME ticks); None if it can't be opened
+fn process_creation_time(pid: u32) -> Option<u64> {
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let mut creation = FILETIME::default();
+        let mut exit = FILETIME::default();
+        let mut kernel = FILETIME::default();
+        let mut user = FILETIME::default();
+        let result = GetProcessTimes(process, &mut creation, &mut exit, &mut kernel, &mut user);
+        let _ = CloseHandle(process);
+        result.ok()?;
+        Some(((creation.dwHighDateTime as u64) << 32) | creation.dwLowDateTime as u64)
+    }
+}
+
+/// IDs of a process and all of its descendants, starting with `root_pid`
+fn process_tree(processes: &ProcessTable, root_pid: u32) -> Vec<u32> {
+    processes.tree(root_pid, process_creation_time)
+}
+
+/// Sum the working set and CPU time of a process and all of its descendants.
+/// Processes that cannot be opened (e.g. exited in the meantime) are skipped.
+fn query_process_tree_usage(processes: &ProcessTable, root_pid: u32) -> UsageSample {
+    let pids = process_tree(processes, root_pid);
+    let mut sample = UsageSample {
+        memory_bytes: 0,
+        cpu_time: Duration::ZERO,
+    };
+    for pid in pids {
+        unsafe {
+            let Ok(process) = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) else {
+                continue;
+            };
+
+            let mut counters = PROCESS_MEMORY_COUNTERS {
+                cb: std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32,
+                ..Default::default()
+            };
+            if GetProcessMemoryInfo(process, &mut counters, counters.cb).is_ok() {
+                sample.memory_bytes += counters.WorkingSetSize as u64;
+            }
+
+            let mut creation = FILETIME::default();
+            let mut exit = FILETIME::default();
+            let mut kernel = FILETIME::default();
+            let mut user = FILETIME::default();
+            if GetProcessTimes(process, &mut creation, &mut exit, &mut kernel, &mut user).is_ok() {
+                sample.cpu_time += filetime_duration(kernel) + filetime_duration(user);
+            }
+
+            let _ = CloseHandle(process);
+        }
+    }
+    sample
+}
+
+/// Empty the working sets of a process and all of its descendants, so Windows
+/// can hand their memory to other programs. Processes that cannot be opened are
+/// skipped.
+fn trim_process_tree_memory(root_pid: u32) -> Result<()> {
+    let processes = process_table().context("Failed to list processes")?;
+    for pid in process_tree(&processes, root_pid) {
+        unsafe {
+            let access = PROCESS_QUERY_LIMITED_INFORMATION | PROCESS_SET_QUOTA;
+            let Ok(process) = OpenProcess(access, false, pid) else {
+                continue;
+            };
+            let _ = EmptyWorkingSet(process);
+            let _ = CloseHandle(process);
+        }
+    }
+    Ok(())
+}
+
+/// Convert a FILETIME interval (100 ns units) to a Duration
+fn filetime_duration(time: FILETIME) -> Duration {
+    let ticks = ((time.dwHighDateTime as u64) << 32) | time.dwLowDateTime as u64;
+    Duration::from_nanos(ticks * 100)
+}
+
+/// Add or remove WS_EX_TOOLWINDOW so the window is excluded from (or included in)
+/// Alt-Tab and the taskbar. Tool windows can still be activated, so focus handling
+/// is unaffected.
+fn set_alt_tab_visibility(hwnd: HWND, visible: bool) {
+    unsafe {
+        let ex_style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE);
+        let new_style = if visible {
+            ex_style & !(WS_EX_TOOLWINDOW.0 as isize)
+        } else {
+            (ex_style | WS_EX_TOOLWINDOW.0 as isize) & !(WS_EX_APPWINDOW.0 as isize)
+        };
+        if new_style == ex_style {
+            return;
+        }
+
+        SetWindowLongPtrW(hwnd, GWL_EXSTYLE, new_style);
+        // Apply the style change without moving or activating the window
+        let _ = SetWindowPos(
+            hwnd,
             None,
-            PCWSTR(wide_message.as_ptr()),
-            PCWSTR(wide_title.as_ptr()),
-            MB_OK | MB_ICONERROR,
+            0,
+            0,
+            0,
+            0,
+            SWP_FRAMECHANGED | SWP_NOMOVE | SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE,
         );
     }
 }
@@ -0,0 +1,180 @@
+//! Project type detection for automatic tab icons.
+//!
+//! A tab whose profile has no icon of its own shows the icon of the kind of
+//! project its working directory holds, recognized by marker files such as
+//! `Cargo.toml` or `package.json`. Only the directory itself is checked, so a
+//! dotfiles repository in the home directory doesn't mark every tab as git.
+//!
+//! Profiles with `icon: "auto"` show the project's own logo instead, if the
+//! directory has one of the files in `PROJECT_LOGOS`.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Kinds of projects with a bundled icon
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectType {
+    Rust,
+    Node,
+    Go,
+    Git,
+}
+
+/// Marker files and directories in priority order (language markers before `.git`,
+/// which most projects also have)
+const PROJECT_MARKERS: [(&str, ProjectType); 4] = [
+    ("Cargo.toml", ProjectType::Rust),
+    ("package.json", ProjectType::Node),
+    ("go.mod", ProjectType::Go),
+    (".git", ProjectType::Git),
+];
+
+/// Project logos in priority order, relative to the working directory
+const PROJECT_LOGOS: [&str; 3] = [".neovide-icon.png", "logo.png", ".github/logo.svg"];
+
+impl ProjectType {
+    /// Every project type, for preloading their icons
+    pub const ALL: [ProjectType; 4] = [
+        ProjectType::Rust,
+        ProjectType::Node,
+        ProjectType::Go,
+        ProjectType::Git,
+    ];
+
+    /// Filename of the bundled icon (extracted to the data directory)
+    pub const fn icon(self) -> &'static str {
+        match self {
+            ProjectType::Rust => "project-rust.svg",
+            ProjectType::Node => "project-node.svg",
+            ProjectType::Go => "project-go.svg",
+            ProjectType::Git => "project-git.svg",
+        }
+    }
+}
+
+/// Detect the kind of project in `dir` from its marker files
+pub fn detect_project_type(dir: &Path) -> Option<ProjectType> {
+    PROJECT_MARKERS
+        .iter()
+        .find(|(marker, _)| dir.join(marker).exists())
+        .map(|(_, project_type)| *project_type)
+}
+
+/// The project logo in `dir`, if it has one
+pub fn find_project_logo(dir: &Path) -> Option<PathBuf> {
+    PROJECT_LOGOS
+        .iter()
+        .map(|logo| dir.join(logo))
+        .find(|path| path.is_file())
+}
+
+/// Project logos found so far by directory, so each directory is only searched
+/// once (until the config is reloaded)
+#[derive(Debug, Default)]
+pub struct LogoCache(HashMap<PathBuf, Option<PathBuf>>);
+
+impl LogoCache {
+    /// The project logo in `dir`, if it has one
+    pub fn logo(&mut self, dir: &Path) -> Option<&Path> {
+        self.0
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| find_project_logo(dir))
+            .as_deref()
+    }
+
+    /// Forget the logos found so far
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+/// Root of the project `path` (a file or directory) belongs to: the closest
+/// directory with a `.git`, or the directory of `path` if it is in no repository
+pub fn find_project_root(path: &Path) -> PathBuf {
+    let start = if path.is_dir() {
+        path
+    } else {
+        path.parent().unwrap_or(path)
+    };
+    start
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .unwrap_or(start)
+        .to_path_buf()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdir::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_detect_project_type() {
+        let tmp = TempDir::new("project");
+        let dir = tmp.path();
+        assert_eq!(detect_project_type(dir), None);
+
+        fs::create_dir(dir.join(".git")).unwrap();
+        assert_eq!(detect_project_type(dir), Some(ProjectType::Git));
+
+        // Language markers win over .git
+        fs::write(dir.join("go.mod"), "module example\n").unwrap();
+        assert_eq!(detect_project_type(dir), Some(ProjectType::Go));
+        fs::write(dir.join("Cargo.toml"), "[package]\n").unwrap();
+        assert_eq!(detect_project_type(dir), Some(ProjectType::Rust));
+
+        let _ = fs::remove_dir_all(dir);
+        assert_eq!(detect_project_type(dir), None);
+    }
+
+    #[test]
+    fn test_find_project_logo() {
+        let tmp = TempDir::new("logo");
+        let dir = tmp.path();
+        fs::create_dir_all(dir.join(".github")).unwrap();
+        let mut cache = LogoCache::default();
+        assert_eq!(cache.logo(dir), None);
+
+        fs::write(dir.join(".github").join("logo.svg"), "<svg/>").unwrap();
+        assert_eq!(find_project_logo(dir), Some(dir.join(".github/logo.svg")));
+        fs::write(dir.join("logo.png"), "").unwrap();
+        assert_eq!(find_project_logo(dir), Some(dir.join("logo.png")));
+        // The cache keeps what it found until it is cleared
+        assert_eq!(cache.logo(dir), None);
+        cache.clear();
+        assert_eq!(cache.logo(dir), Some(dir.join("logo.png").as_path()));
+
+        fs::write(dir.join(".neovide-icon.png"), "").unwrap();
+        assert_eq!(find_project_logo(dir), Some(dir.join(".neovide-icon.png")));
+    }
+
+    #[test]
+    fn test_find_project_root() {
+        let tmp = TempDir::new("root");
+        let dir = tmp.path();
+        let nested = dir.join("src").join("bin");
+        fs::create_dir_all(&nested).unwrap();
+        let file = nested.join("main.rs");
+        fs::write(&file, "fn main() {}\n").unwrap();
+
+        fs::create_dir(dir.join(".git")).unwrap();
+        assert_eq!(find_project_root(&file), dir);
+        assert_eq!(find_project_root(&nested), dir);
+        // A file that doesn't exist yet counts as a file
+        assert_eq!(find_project_root(&nested.join("new.rs")), dir);
+
+        // The closest repository wins (e.g. a submodule)
+        fs::create_dir(nested.join(".git")).unwrap();
+        assert_eq!(find_project_root(&file), nested);
+    }
+
+    #[test]
+    fn test_project_icons_are_distinct() {
+        for (i, a) in ProjectType::ALL.iter().enumerate() {
+            for b in &ProjectType::ALL[i + 1..] {
+                assert_ne!(a.icon(), b.icon());
+            }
+        }
+    }
+}
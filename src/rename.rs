@@ -0,0 +1,136 @@
+//! Inline editor for renaming a tab.
+//!
+//! A single-line edit control is placed over the tab in the tab bar. Enter
+//! commits the new name, Escape cancels, and clicking elsewhere commits. The
+//! edit control only reports back to the wrapper with WM_TAB_RENAME_DONE; the
+//! wrapper reads the text and destroys the editor.
+
+#![cfg(target_os = "windows")]
+#![allow(unsafe_op_in_unsafe_fn)]
+
+use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, RECT, WPARAM};
+use windows::Win32::Graphics::Gdi::{DeleteObject, HFONT, HGDIOBJ};
+use windows::Win32::UI::Controls::{EM_LIMITTEXT, EM_SETSEL};
+use windows::Win32::UI::Input::KeyboardAndMouse::{SetFocus, VK_ESCAPE, VK_RETURN};
+use windows::Win32::UI::Shell::{DefSubclassProc, RemoveWindowSubclass, SetWindowSubclass};
+use windows::Win32::UI::WindowsAndMessaging::*;
+use windows::core::{HSTRING, w};
+
+use crate::fonts::{self, TextSize};
+
+/// Posted to the wrapper when renaming ends; WPARAM is 1 to apply the text, 0 to cancel
+pub const WM_TAB_RENAME_DONE: u32 = WM_APP + 14;
+
+/// Longest tab name that can be entered
+const MAX_TAB_NAME_LENGTH: usize = 128;
+
+/// Subclass ID of the edit control
+const RENAME_SUBCLASS_ID: usize = 1;
+
+/// Edit control shown over a tab while it is being renamed
+pub struct TabRenameEditor {
+    hwnd: HWND,
+    font: HFONT,
+    /// Text the editor started with
+    original: String,
+    /// ID of the tab being renamed
+    pub tab_id: usize,
+}
+
+impl TabRenameEditor {
+    /// Show the editor over `rect` (wrapper client coordinates) with `text` selected
+    pub fn show(owner: HWND, rect: &RECT, text: &str, tab_id: usize) -> Option<Self> {
+        unsafe {
+            let hinstance = GetWindowLongPtrW(owner, GWLP_HINSTANCE);
+            let hwnd = CreateWindowExW(
+                WINDOW_EX_STYLE(0),
+                w!("EDIT"),
+                &HSTRING::from(text),
+                WS_CHILD | WS_VISIBLE | WS_BORDER | WINDOW_STYLE(ES_AUTOHSCROLL as u32),
+                rect.left,
+                rect.top,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+                owner,
+                None,
+                windows::Win32::Foundation::HINSTANCE(hinstance as *mut _),
+                None,
+            )
+            .ok()?;
+
+            // Same font as the tab labels
+            let font = fonts::create_ui_font(TextSize::Label, 400);
+            SendMessageW(hwnd, WM_SETFONT, WPARAM(font.0 as usize), LPARAM(1));
+            SendMessageW(hwnd, EM_LIMITTEXT, WPARAM(MAX_TAB_NAME_LENGTH), LPARAM(0));
+            SendMessageW(hwnd, EM_SETSEL, WPARAM(0), LPARAM(-1));
+
+            let _ = SetWindowSubclass(
+                hwnd,
+                Some(rename_subclass_proc),
+                RENAME_SUBCLASS_ID,
+                owner.0 as usize,
+            );
+            let _ = SetFocus(hwnd);
+
+            Some(TabRenameEditor {
+                hwnd,
+                font,
+                original: text.to_string(),
+                tab_id,
+            })
+        }
+    }
+
+    /// Text entered, or None if it wasn't changed (so an untouched title keeps
+    /// following the profile's title format)
+    pub fn changed_text(&self) -> Option<String> {
+        let text = unsafe {
+            let len = GetWindowTextLengthW(self.hwnd);
+            let mut buffer = vec![0u16; len as usize + 1];
+            let copied = GetWindowTextW(self.hwnd, &mut buffer);
+            String::from_utf16_lossy(&buffer[..copied as usize])
+        };
+        (text != self.original).then_some(text)
+    }
+}
+
+impl Drop for TabRenameEditor {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = RemoveWindowSubclass(self.hwnd, Some(rename_subclass_proc), RENAME_SUBCLASS_ID);
+            let _ = DestroyWindow(self.hwnd);
+            let _ = DeleteObject(HGDIOBJ(self.font.0));
+        }
+    }
+}
+
+/// Turns Enter, Escape and focus loss into WM_TAB_RENAME_DONE for the wrapper
+unsafe extern "system" fn rename_subclass_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    _id: usize,
+    owner: usize,
+) -> LRESULT {
+    let owner = HWND(owner as *mut _);
+    match msg {
+        // Receive Enter and Escape as key messages
+        WM_GETDLGCODE => LRESULT(DLGC_WANTALLKEYS as isize),
+        WM_KEYDOWN if wparam.0 == VK_RETURN.0 as usize => {
+            let _ = PostMessageW(owner, WM_TAB_RENAME_DONE, WPARAM(1), LPARAM(0));
+            LRESULT(0)
+        }
+        WM_KEYDOWN if wparam.0 == VK_ESCAPE.0 as usize => {
+            let _ = PostMessageW(owner, WM_TAB_RENAME_DONE, WPARAM(0), LPARAM(0));
+            LRESULT(0)
+        }
+        // Swallow the characters so the edit control doesn't beep
+        WM_CHAR if wparam.0 == '\r' as usize || wparam.0 == 0x1b => LRESULT(0),
+        WM_KILLFOCUS => {
+            let _ = PostMessageW(owner, WM_TAB_RENAME_DONE, WPARAM(1), LPARAM(0));
+            DefSubclassProc(hwnd, msg, wparam, lparam)
+        }
+        _ => DefSubclassProc(hwnd, msg, wparam, lparam),
+    }
+}
@@ -0,0 +1,457 @@
+//! Named sessions: saved sets of tabs that can be restored and switched between.
+//!
+//! Each session is stored as JSON in `~/.local/share/neovide-tabs/sessions/<name>.json`
+//! and records the profile and working directory of every tab plus the selected tab.
+//! Profiles are stored by name so a session survives reordering of the config.
+//! Tabs keep their IDs across a restore, and the selection is stored by ID, so a
+//! tab that can't be restored doesn't shift the selection to its neighbor.
+//! Besides saving on exit, the active session is snapshotted periodically while its
+//! tabs change (see `Autosave`), so a crash does not lose the layout.
+//!
+//! `neovide-tabs session export|import <file>` copies sessions to and from other
+//! files so a tab layout can be shared between machines or kept in a dotfiles repo.
+//!
+//! The profile last picked in the startup chooser (`prompt_profile_on_start`) is
+//! kept next to the sessions, in `startup-profile.txt`.
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::config::{Config, data_dir_path};
+
+/// File in the data directory remembering the last profile chosen at startup
+const STARTUP_PROFILE_FILE: &str = "startup-profile.txt";
+
+/// Maximum length of a session name
+pub const MAX_SESSION_NAME_LEN: usize = 64;
+
+/// One tab of a saved session
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionTab {
+    /// Profile name, matched against the configured profiles on restore
+    pub profile: String,
+    /// Directory the tab's Neovide was started in
+    pub working_directory: PathBuf,
+    /// Name the tab was renamed to, replacing its title format
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Whether the tab is pinned
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    /// The tab's ID, which the restored tab gets back unless another tab has it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<usize>,
+}
+
+/// A saved set of tabs
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    pub tabs: Vec<SessionTab>,
+    /// Index of the selected tab
+    #[serde(default)]
+    pub selected: usize,
+    /// ID of the selected tab; preferred over `selected` when restoring
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selected_id: Option<usize>,
+}
+
+/// Check whether a session name is usable as a file name: letters, digits, `-` and `_`
+pub fn is_valid_session_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_SESSION_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Get the directory holding the session files
+pub fn sessions_dir() -> Option<PathBuf> {
+    Some(data_dir_path()?.join("sessions"))
+}
+
+/// Names of all saved sessions, sorted alphabetically
+pub fn list_sessions() -> Vec<String> {
+    sessions_dir()
+        .map(|dir| list_sessions_in(&dir))
+        .unwrap_or_default()
+}
+
+/// Load a saved session. Returns Ok(None) if no session with that name exists.
+pub fn load_session(name: &str) -> Result<Option<Session>> {
+    let dir = sessions_dir().context("Could not determine the data directory")?;
+    load_session_from(&dir, name)
+}
+
+/// Save a session, replacing any previous session with the same name
+pub fn save_session(name: &str, session: &Session) -> Result<()> {
+    let dir = sessions_dir().context("Could not determine the data directory")?;
+    save_session_to(&dir, name, session)
+}
+
+/// Name of the profile last chosen in the startup chooser, if any
+pub fn load_startup_profile() -> Option<String> {
+    load_startup_profile_from(&data_dir_path()?)
+}
+
+/// Remember the profile chosen in the startup chooser
+pub fn save_startup_profile(name: &str) -> Result<()> {
+    let dir = data_dir_path().context("Could not determine the data directory")?;
+    save_startup_profile_to(&dir, name)
+}
+
+/// Debounces automatic session snapshots: a snapshot is written when the tab set
+/// changed since the last save and at least the autosave interval has passed
+#[derive(Debug)]
+pub struct Autosave {
+    /// Tab manager generation captured by the last save
+    saved_generation: u64,
+    /// When the session was last saved
+    last_save: Instant,
+}
+
+impl Autosave {
+    /// Start tracking from a freshly restored (or saved) tab set
+    pub fn new(generation: u64, now: Instant) -> Self {
+        Self {
+            saved_generation: generation,
+            last_save: now,
+        }
+    }
+
+    /// Whether a snapshot of the tab set at `generation` should be written at `now`
+    pub fn is_due(&self, generation: u64, now: Instant, interval: Duration) -> bool {
+        generation != self.saved_generation && now.duration_since(self.last_save) >= interval
+    }
+
+    /// Record that the tab set at `generation` was saved at `now`
+    pub fn mark_saved(&mut self, generation: u64, now: Instant) {
+        self.saved_generation = generation;
+        self.last_save = now;
+    }
+}
+
+/// Run a `session` subcommand: `export <file>` or `import <file>`, optionally
+/// followed by `--session <name>` (defaults to the file name without extension)
+/// and, for import, `--force` to replace an existing session
+pub fn run_cli(args: &[String]) -> Result<()> {
+    let (Some(command), Some(file)) = (args.first(), args.get(1)) else {
+        bail!("Usage: neovide-tabs session <export|import> <file> [--session <name>] [--force]");
+    };
+    let file = Path::new(file);
+    let name = match args.iter().position(|arg| arg == "--session") {
+        Some(i) => args
+            .get(i + 1)
+            .cloned()
+            .context("--session requires a session name")?,
+        None => file
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or_default()
+            .to_string(),
+    };
+    if !is_valid_session_name(&name) {
+        bail!(
+            "Invalid session name '{}' (use letters, digits, '-' and '_', or pass --session <name>)",
+            name
+        );
+    }
+    let force = args.iter().any(|arg| arg == "--force");
+
+    match command.as_str() {
+        "export" => {
+            let session = load_session(&name)?
+                .with_context(|| format!("No saved session named '{}'", name))?;
+            write_session_file(file, &session)?;
+            println!(
+                "Exported session '{}' ({} tabs) to {}",
+                name,
+                session.tabs.len(),
+                file.display()
+            );
+        }
+        "import" => {
+            if !force && load_session(&name)?.is_some() {
+                bail!(
+                    "A session named '{}' already exists (use --force to replace it)",
+                    name
+                );
+            }
+            let mut session = read_session_file(file)?;
+            let config = Config::load();
+            let profile_names: Vec<&str> =
+                config.profiles.iter().map(|p| p.name.as_str()).collect();
+            for warning in
+                map_missing_profiles(&mut session, &profile_names, &config.default_profile().name)
+            {
+                eprintln!("Warning: {}", warning);
+            }
+            save_session(&name, &session)?;
+            println!(
+                "Imported session '{}' ({} tabs) from {}",
+                name,
+                session.tabs.len(),
+                file.display()
+            );
+        }
+        other => bail!(
+            "Unknown session command '{}' (expected export or import)",
+            other
+        ),
+    }
+    Ok(())
+}
+
+/// Read a session from an exported file
+pub fn read_session_file(path: &Path) -> Result<Session> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+    let session: Session = serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse session file {:?}", path))?;
+    Ok(session)
+}
+
+/// Write a session to a file for export
+pub fn write_session_file(path: &Path, session: &Session) -> Result<()> {
+    let contents = serde_json::to_string_pretty(session)?;
+    fs::write(path, contents).with_context(|| format!("Failed to write {:?}", path))
+}
+
+/// Point tabs whose profile is not configured at the default profile.
+/// Returns a warning for every tab that was changed.
+pub fn map_missing_profiles(
+    session: &mut Session,
+    profile_names: &[&str],
+    default_profile: &str,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for tab in &mut session.tabs {
+        if !profile_names.contains(&tab.profile.as_str()) {
+            warnings.push(format!(
+                "profile '{}' is not configured, the tab in {:?} uses '{}' instead",
+                tab.profile, tab.working_directory, default_profile
+            ));
+            tab.profile = default_profile.to_string();
+        }
+    }
+    warnings
+}
+
+fn session_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.json", name))
+}
+
+fn list_sessions_in(dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .filter(|name| is_valid_session_name(name))
+        .collect();
+    names.sort();
+    names
+}
+
+fn load_session_from(dir: &Path, name: &str) -> Result<Option<Session>> {
+    if !is_valid_session_name(name) {
+        bail!("Invalid session name '{}'", name);
+    }
+    let path = session_path(dir, name);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {:?}", path))?;
+    let session = serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse session file {:?}", path))?;
+    Ok(Some(session))
+}
+
+fn save_session_to(dir: &Path, name: &str, session: &Session) -> Result<()> {
+    if !is_valid_session_name(name) {
+        bail!("Invalid session name '{}'", name);
+    }
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+
+    // Write to a temporary file first so an interrupted save never leaves a
+    // truncated session behind
+    let path = session_path(dir, name);
+    let tmp_path = path.with_extension("json.tmp");
+    let contents = serde_json::to_string_pretty(session)?;
+    fs::write(&tmp_path, contents).with_context(|| format!("Failed to write {:?}", tmp_path))?;
+    fs::rename(&tmp_path, &path).with_context(|| format!("Failed to replace {:?}", path))?;
+    Ok(())
+}
+
+fn load_startup_profile_from(dir: &Path) -> Option<String> {
+    let name = fs::read_to_string(dir.join(STARTUP_PROFILE_FILE)).ok()?;
+    let name = name.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+fn save_startup_profile_to(dir: &Path, name: &str) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+    let path = dir.join(STARTUP_PROFILE_FILE);
+    fs::write(&path, name).with_context(|| format!("Failed to write {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdir::TempDir;
+
+    #[test]
+    fn test_session_name_validation() {
+        assert!(is_valid_session_name("work"));
+        assert!(is_valid_session_name("dot-files_2"));
+        assert!(!is_valid_session_name(""));
+        assert!(!is_valid_session_name("../work"));
+        assert!(!is_valid_session_name("my work"));
+        assert!(!is_valid_session_name(
+            &"a".repeat(MAX_SESSION_NAME_LEN + 1)
+        ));
+    }
+
+    #[test]
+    fn test_session_save_load_roundtrip() {
+        let tmp = TempDir::new("sessions-roundtrip");
+        let dir = tmp.path();
+        let session = Session {
+            tabs: vec![
+                SessionTab {
+                    profile: "Default".to_string(),
+                    working_directory: PathBuf::from("C:\\Users\\me"),
+                    ..Default::default()
+                },
+                SessionTab {
+                    profile: "Blog".to_string(),
+                    working_directory: PathBuf::from("C:\\src\\blog"),
+                    ..Default::default()
+                },
+            ],
+            selected: 1,
+            ..Default::default()
+        };
+
+        assert_eq!(load_session_from(dir, "work").unwrap(), None);
+        save_session_to(dir, "work", &session).unwrap();
+        save_session_to(dir, "blog", &Session::default()).unwrap();
+
+        assert_eq!(load_session_from(dir, "work").unwrap(), Some(session));
+        assert_eq!(list_sessions_in(dir), vec!["blog", "work"]);
+        assert!(save_session_to(dir, "../escape", &Session::default()).is_err());
+    }
+
+    #[test]
+    fn test_session_tab_optional_fields() {
+        // Sessions saved before tabs could be renamed or pinned still load
+        let session: Session = serde_json::from_str(
+            r#"{"tabs": [{"profile": "Default", "working_directory": "C:\\src"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(session.tabs[0].title, None);
+        assert!(!session.tabs[0].pinned);
+        assert_eq!(session.tabs[0].id, None);
+        assert_eq!(session.selected_id, None);
+
+        let tab = SessionTab {
+            profile: "Default".to_string(),
+            title: Some("Notes".to_string()),
+            pinned: true,
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&tab).unwrap();
+        assert_eq!(serde_json::from_str::<SessionTab>(&json).unwrap(), tab);
+        let plain = serde_json::to_string(&SessionTab::default()).unwrap();
+        assert!(!plain.contains("title") && !plain.contains("pinned") && !plain.contains("id"));
+    }
+
+    #[test]
+    fn test_map_missing_profiles() {
+        let mut session = Session {
+            tabs: vec![
+                SessionTab {
+                    profile: "Work".to_string(),
+                    working_directory: PathBuf::from("C:\\work"),
+                    ..Default::default()
+                },
+                SessionTab {
+                    profile: "Gone".to_string(),
+                    working_directory: PathBuf::from("C:\\old"),
+                    ..Default::default()
+                },
+            ],
+            selected: 0,
+            ..Default::default()
+        };
+        let warnings = map_missing_profiles(&mut session, &["Default", "Work"], "Default");
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("'Gone'"));
+        assert_eq!(session.tabs[0].profile, "Work");
+        assert_eq!(session.tabs[1].profile, "Default");
+    }
+
+    #[test]
+    fn test_session_file_export_import() {
+        let tmp = TempDir::new("sessions-export");
+        let dir = tmp.path();
+        let file = dir.join("shared.json");
+        let session = Session {
+            tabs: vec![SessionTab {
+                profile: "Default".to_string(),
+                working_directory: PathBuf::from("C:\\src"),
+                ..Default::default()
+            }],
+            selected: 0,
+            ..Default::default()
+        };
+        write_session_file(&file, &session).unwrap();
+        assert_eq!(read_session_file(&file).unwrap(), session);
+        assert!(read_session_file(&dir.join("missing.json")).is_err());
+    }
+
+    #[test]
+    fn test_autosave_debounce() {
+        let start = Instant::now();
+        let interval = Duration::from_secs(300);
+        let mut autosave = Autosave::new(3, start);
+
+        // Nothing changed yet
+        assert!(!autosave.is_due(3, start + interval, interval));
+        // Changed, but the interval has not passed
+        assert!(!autosave.is_due(4, start + Duration::from_secs(10), interval));
+        assert!(autosave.is_due(4, start + interval, interval));
+
+        autosave.mark_saved(4, start + interval);
+        assert!(!autosave.is_due(4, start + interval * 3, interval));
+        assert!(!autosave.is_due(5, start + interval + Duration::from_secs(1), interval));
+        assert!(autosave.is_due(5, start + interval * 2, interval));
+    }
+
+    #[test]
+    fn test_session_selected_defaults_to_first_tab() {
+        let json = r#"{ "tabs": [{ "profile": "Default", "working_directory": "C:\\" }] }"#;
+        let session: Session = serde_json::from_str(json).unwrap();
+        assert_eq!(session.selected, 0);
+        assert_eq!(session.tabs.len(), 1);
+    }
+
+    #[test]
+    fn test_startup_profile_roundtrip() {
+        let tmp = TempDir::new("sessions-startup-profile");
+        let dir = tmp.path();
+        assert_eq!(load_startup_profile_from(dir), None);
+
+        save_startup_profile_to(dir, "Work").unwrap();
+        assert_eq!(load_startup_profile_from(dir), Some("Work".to_string()));
+
+        fs::write(dir.join(STARTUP_PROFILE_FILE), "  \n").unwrap();
+        assert_eq!(load_startup_profile_from(dir), None);
+    }
+}
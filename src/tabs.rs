@@ -1,33 +1,321 @@
-#![cfg(target_os = "windows")]
+//! Tabs and their processes, independent of the windowing system.
+//!
+//! `TabManager` drives a `ProcessHandle` per tab; the Win32 implementation is
+//! `NeovideProcess`, and the tests use a fake so selection, spawn queueing and
+//! close sequencing can be checked on any platform.
 
 use anyhow::Result;
-use std::time::Instant;
-use windows::Win32::Foundation::HWND;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::config::{
+    DEFAULT_ICON, DEFAULT_MAX_CONCURRENT_SPAWNS, DEFAULT_TITLE_FALLBACK, Profile, TitleContext,
+    expand_title_with_fallback,
+};
+use crate::gitstatus::{GIT_STATUS_INTERVAL, GitStatus, GitStatusCache};
+use crate::hooks::{self, HookEvent, HookTab};
+use crate::output::OutputLog;
+use crate::projects::{LogoCache, detect_project_type};
+use crate::sessions::{Session, SessionTab};
+use crate::usage::{self, ProcessTable, ResourceUsage, UsageSample, UsageTracker};
+
+/// Length of one attention highlight pulse
+const PULSE_PERIOD: Duration = Duration::from_millis(600);
+/// Number of pulses shown when a tab asks for attention
+const PULSE_COUNT: u32 = 3;
+
+/// Outcome of asking Neovide to close
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseRequest {
+    /// The request was delivered; Neovide may still prompt to save files
+    Sent,
+    /// There is no window to ask yet (or anymore); the caller terminates the process
+    NoWindow,
+    /// The window refused WM_CLOSE because it runs at a higher integrity level than
+    /// the wrapper (UIPI), and nvim could not be asked to quit over RPC either
+    Blocked,
+}
+
+/// How a tab's Neovide is started
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LaunchOptions<'a> {
+    /// Directory Neovide starts in (the wrapper's if None or missing)
+    pub working_directory: Option<&'a Path>,
+    /// ID of the tab; nvim listens for RPC on a pipe named after it (see
+    /// `attention`). None starts nvim without an RPC address
+    pub tab_id: Option<usize>,
+    /// Install the hook that forwards nvim's attention requests (`tab_attention`)
+    pub forward_attention: bool,
+    /// Run Neovide as administrator through an elevated proxy (see `elevation`)
+    pub elevated: bool,
+    /// Environment variables added to the wrapper's environment
+    pub env: &'a [(String, String)],
+    /// Files Neovide opens
+    pub files: &'a [PathBuf],
+    /// Command nvim runs at startup (without the leading '+')
+    pub nvim_cmd: Option<&'a str>,
+}
 
-use crate::config::{Profile, TitleContext, expand_title};
-use crate::process::NeovideProcess;
+/// The Neovide process behind a tab, as far as `TabManager` needs it.
+/// Implemented by `NeovideProcess` on Windows.
+pub trait ProcessHandle {
+    /// Handle of the windows the process is placed in and reports to
+    type Window: Copy + PartialEq;
+
+    /// A process that has not been launched yet (waiting in the spawn queue).
+    /// It counts as running until it is launched or dropped.
+    fn pending() -> Self;
+    /// Check whether the process has been launched (left the spawn queue)
+    fn is_launched(&self) -> bool;
+    /// Launch Neovide with the specified dimensions and `options`; `parent` is
+    /// told once its window has been found
+    fn launch(
+        &mut self,
+        width: u32,
+        height: u32,
+        parent: Self::Window,
+        options: &LaunchOptions,
+    ) -> Result<()>;
+    /// Check if the Neovide window has been found and positioned
+    fn is_ready(&self) -> bool;
+    /// Check if the process is still running (true while queued)
+    fn is_running(&self) -> bool;
+    /// Why the Neovide window never appeared, once
+    fn take_window_error(&self) -> Option<String>;
+    /// Terminate a Neovide whose window never appeared so it can be launched again
+    fn abandon(&mut self);
+    /// Terminate the process forcefully (no-op if it has exited)
+    fn terminate(&mut self) -> Result<()>;
+    /// Ask Neovide to close; it may prompt to save files first
+    fn request_close(&self) -> CloseRequest;
+    /// Check (once) whether Neovide stayed open after the last close request
+    /// because the user cancelled the save prompt
+    fn take_close_cancelled(&self) -> bool;
+    /// The Neovide window, once it has been found
+    fn window_handle(&self) -> Option<Self::Window>;
+    /// Show the window and bring it to the foreground
+    fn activate(&self);
+    /// Move the window to the parent's content area; true if it was moved
+    fn update_position(&self, parent: Self::Window, titlebar_height: i32) -> bool;
+    /// Show the Neovide window
+    fn show(&self);
+    /// Hide the Neovide window
+    fn hide(&self);
+    /// Bring the Neovide window to the foreground
+    fn bring_to_foreground(&self);
+    /// Show or hide the Neovide window in Alt-Tab and the taskbar
+    fn set_alt_tab_visible(&self, visible: bool);
+    /// Keep the Neovide window above non-topmost windows (or stop doing so)
+    fn set_topmost(&self, topmost: bool);
+    /// Keep other windows of the process consistent with the tab's window
+    fn track_extra_windows(&self);
+    /// Look the Neovide window up again if its handle is no longer valid
+    /// (Neovide recreated it, e.g. after sleep); true if it was replaced
+    fn revalidate_window(&self) -> bool;
+    /// Current title of the Neovide window (empty if unavailable)
+    fn get_window_title(&self) -> String;
+    /// Process ID of Neovide itself, once its window has been found
+    fn neovide_pid(&self) -> Option<u32>;
+    /// Check if the process is currently suspended
+    fn is_suspended(&self) -> bool;
+    /// Suspend the process while its tab is in the background; true if it was
+    fn suspend(&self) -> bool;
+    /// Resume a suspended process (no-op if it is not suspended)
+    fn resume(&self);
+    /// Hand the physical memory of the process tree back to Windows (of a
+    /// suspended process; pages are read back in as it runs again)
+    fn trim_memory(&self);
+    /// Check whether the Neovide window stopped processing messages
+    fn is_hung(&self) -> bool;
+    /// Memory and CPU time counters of the process tree, found in `processes`
+    fn usage_sample(&self, processes: &ProcessTable) -> Option<UsageSample>;
+    /// Check whether nvim can be reached over RPC
+    fn has_rpc(&self) -> bool;
+    /// Turn Neovide's animations off (or back on) over RPC; None if nvim has no
+    /// RPC address
+    fn request_animations_paused(&self, paused: bool) -> Option<Result<()>>;
+    /// Have nvim edit `path` over RPC; None if nvim has no RPC address
+    fn request_edit(&self, path: &Path) -> Option<Result<()>>;
+    /// What Neovide wrote to stdout and stderr; None if it isn't captured
+    fn output(&self) -> Option<OutputLog>;
+}
 
 /// Represents a single tab with its associated Neovide process
-pub struct Tab {
+pub struct Tab<P> {
     /// Unique identifier for this tab
-    #[allow(dead_code)]
     pub id: usize,
     /// The Neovide process associated with this tab
-    pub process: NeovideProcess,
+    pub process: P,
     /// Profile name used to create this tab
     pub profile_name: String,
     /// Profile icon filename
     pub profile_icon: String,
+    /// Icon shown instead of the profile icon (detected from the project type of
+    /// the working directory when the profile has no icon of its own)
+    pub icon_override: Option<String>,
     /// Profile working directory (for tooltip display)
     pub working_directory: std::path::PathBuf,
     /// Profile index in the config (for reference)
     pub profile_index: usize,
-    /// Timestamp when graceful close was requested (for timeout tracking)
-    pub close_requested_at: Option<Instant>,
+    /// Where the tab is in being closed (see `CloseState`)
+    pub close_state: CloseState,
     /// Tab title format string (from profile, e.g., "%t", "%p: %w")
     pub title_format: String,
     /// Cached expanded tab title (computed from title_format)
     pub cached_title: String,
+    /// When the Neovide process was spawned (for the startup indicator)
+    pub created_at: Instant,
+    /// When this tab was last the selected tab (for suspending idle background tabs)
+    pub last_active: Instant,
+    /// Memory and CPU usage of the tab's process tree
+    pub usage: UsageTracker,
+    /// Whether the Neovide window stopped responding (checked on the poll timer)
+    pub not_responding: bool,
+    /// Whether the Neovide window has been positioned and shown or hidden after
+    /// it was found (done on the UI thread, in tab order)
+    pub placed: bool,
+    /// Whether the Neovide window may be out of place: hidden windows are not
+    /// moved along with the wrapper, only right before they are shown
+    position_dirty: Cell<bool>,
+    /// When the tab last asked for attention while in the background (drives the pulse)
+    pub attention_at: Option<Instant>,
+    /// Name given by the user; replaces the expanded title format when set
+    pub custom_title: Option<String>,
+    /// Pinned tabs stay at the left of the tab bar and have no close button
+    pub pinned: bool,
+    /// Turn Neovide's animations off while the tab is in the background
+    pub pause_animations: bool,
+    /// Whether Neovide was last told to turn its animations off
+    animations_paused: bool,
+    /// Why Neovide failed to launch; the tab stays in the bar as an error tab
+    /// with a retry icon until it is retried or closed
+    pub spawn_error: Option<String>,
+    /// Whether Neovide runs as administrator (from the profile)
+    pub elevated: bool,
+    /// Environment variables for Neovide (from the config and profile)
+    pub env: Vec<(String, String)>,
+    /// Neovide's process ID, recorded when its window is placed (for hooks)
+    pub neovide_pid: Option<u32>,
+    /// Files Neovide opens when it starts (`neovide-tabs open`, or the profile's `open`)
+    pub files: Vec<PathBuf>,
+    /// Command nvim runs when it starts (the profile's `nvim_cmd`)
+    pub nvim_cmd: Option<String>,
+}
+
+impl<P> Tab<P> {
+    /// Run the configured hook of `event` for this tab
+    fn run_hook(&self, event: HookEvent) {
+        hooks::run(
+            event,
+            &HookTab {
+                id: self.id,
+                profile: &self.profile_name,
+                working_directory: &self.working_directory,
+                pid: self.neovide_pid,
+                env: &self.env,
+            },
+        );
+    }
+
+    /// Icon filename to show for the tab
+    pub fn icon(&self) -> &str {
+        self.icon_override.as_deref().unwrap_or(&self.profile_icon)
+    }
+}
+
+impl<P: ProcessHandle> Tab<P> {
+    /// Move the window into place if it may have moved while hidden, then show
+    /// and focus it
+    fn activate(&self, parent: P::Window, titlebar_height: i32) {
+        // A suspended process cannot answer the messages sent by SetWindowPos
+        self.process.resume();
+        if self.position_dirty.replace(false) {
+            self.process.update_position(parent, titlebar_height);
+        }
+        self.process.activate();
+    }
+}
+
+/// Where a tab is in being closed.
+///
+/// Transitions:
+/// - `Open` -> `Queued`: a close sequence (all tabs, others, to the right) takes
+///   the tab, or the tab is opened while all tabs are closing
+/// - `Open`/`Queued` -> `Requested`: Neovide was asked to close (the tab is closed
+///   on its own, or it is its turn in the sequence)
+/// - `Open`/`Queued` -> `Blocked`: Neovide refused to be asked
+/// - `Blocked` -> `Open`: the user keeps the tab (`cancel_close`)
+/// - `Requested` -> `Open`: the user cancelled Neovim's save prompt; this aborts
+///   the close sequence (`handle_cancelled_closes`)
+/// - `Queued` -> `Open`: the sequence is aborted or replaced
+/// - any -> removed: the process exits, or the tab is closed forcefully (no
+///   window to ask, or the user forces a blocked close)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseState {
+    /// Not being closed
+    Open,
+    /// Waiting for its turn in a close sequence
+    Queued,
+    /// Asked to close; removed once the process exits
+    Requested,
+    /// Neovide refused to close (it runs elevated); waits for the user to force
+    /// the close or keep the tab
+    Blocked,
+}
+
+/// Close sequence run by the tab manager. Tabs are closed one at a time and
+/// shown first, since hidden windows don't process WM_CLOSE reliably; the poll
+/// timer moves the sequence on as tabs exit (`continue_close_sequence`).
+///
+/// Transitions:
+/// - `Idle` -> `All`: `request_close_all`; replaces a bulk close
+/// - `Idle`/`Bulk` -> `Bulk`: close others / to the right (ignored during `All`)
+/// - `Bulk` -> `Idle`: no queued tab is left; the kept tab is selected again
+/// - `All` -> `Idle`: the last tab is gone, or the user keeps a blocked tab
+/// - `All`/`Bulk` -> `Idle`: the user cancelled a save prompt
+/// - any -> `Idle`: `abort_close_sequence`, or every tab is gone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CloseSequence {
+    Idle,
+    /// Closing every tab, including tabs opened meanwhile
+    All,
+    /// Closing the queued tabs left to right, then selecting tab `keep` again
+    Bulk {
+        keep: usize,
+    },
+}
+
+/// What a tab's Neovide is doing (shown by the debug HUD)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabState {
+    /// Waiting in the spawn queue
+    Queued,
+    /// Launched, window not found yet
+    Starting,
+    Ready,
+    Suspended,
+    NotResponding,
+    /// Asked to close, not exited yet
+    Closing,
+    /// Neovide failed to launch (error tab)
+    Failed,
+}
+
+impl TabState {
+    pub fn label(self) -> &'static str {
+        match self {
+            TabState::Queued => "queued",
+            TabState::Starting => "starting",
+            TabState::Ready => "ready",
+            TabState::Suspended => "suspended",
+            TabState::NotResponding => "not responding",
+            TabState::Closing => "closing",
+            TabState::Failed => "failed",
+        }
+    }
 }
 
 /// State for tab drag-and-drop reordering
@@ -56,18 +344,53 @@ impl DragState {
 }
 
 /// Manages multiple tabs and their associated Neovide processes
-pub struct TabManager {
+pub struct TabManager<P> {
     /// All tabs in display order
-    tabs: Vec<Tab>,
+    tabs: Vec<Tab<P>>,
     /// Index of the currently selected tab
     selected_index: usize,
     /// Counter for generating unique tab IDs
     next_id: usize,
     /// Current drag state (if dragging)
     pub drag_state: Option<DragState>,
+    /// IDs of tabs whose Neovide process waits to be launched, in launch order
+    spawn_queue: VecDeque<usize>,
+    /// Maximum number of Neovide processes starting (launched, no window yet) at once
+    max_concurrent_spawns: usize,
+    /// Number of tabs opened since the last time all tabs were ready; drives the
+    /// startup progress shown while several tabs start together
+    spawn_batch_size: usize,
+    /// Incremented whenever the tab set, order or selection changes (drives session autosave)
+    session_generation: u64,
+    /// Title formats tried when a tab's title format expands to nothing
+    title_fallback: Vec<String>,
+    /// Close sequence in progress, if any (see `CloseSequence`)
+    close_sequence: CloseSequence,
+    /// Tab the close sequence asked to close and which has not exited yet
+    close_in_flight: Option<usize>,
+    /// Tabs whose Neovide refused to close; the user decides (see `take_blocked_closes`)
+    blocked_closes: Vec<usize>,
+    /// Tabs selected so far, for back/forward navigation
+    history: TabHistory,
+    /// Whether new tabs forward attention requests from nvim (see `attention`)
+    attention_forwarding: bool,
+    /// Whether tabs of profiles without an icon show their project type's icon
+    project_icons: bool,
+    /// Logos of the working directories of tabs with `icon: "auto"`
+    logos: LogoCache,
+    /// Git status of the tabs' working directories, while `git_status` is on
+    git_statuses: Option<GitStatusCache>,
+    /// ID of the tab selected at the last `update_git_statuses` (a newly
+    /// selected tab is checked right away)
+    git_selected: Option<usize>,
+    /// Whether animations are turned off (attention is shown without pulsing)
+    reduce_motion: bool,
+    /// When `select_tab` last changed the selection (the new tab's title is
+    /// refreshed more often for a while)
+    switched_at: Option<Instant>,
 }
 
-impl TabManager {
+impl<P: ProcessHandle> TabManager<P> {
     /// Create a new TabManager with no tabs
     pub fn new() -> Self {
         TabManager {
@@ -75,6 +398,25 @@ impl TabManager {
             selected_index: 0,
             next_id: 1,
             drag_state: None,
+            spawn_queue: VecDeque::new(),
+            max_concurrent_spawns: DEFAULT_MAX_CONCURRENT_SPAWNS,
+            spawn_batch_size: 0,
+            session_generation: 0,
+            title_fallback: DEFAULT_TITLE_FALLBACK
+                .iter()
+                .map(|f| f.to_string())
+                .collect(),
+            close_sequence: CloseSequence::Idle,
+            close_in_flight: None,
+            blocked_closes: Vec::new(),
+            history: TabHistory::default(),
+            attention_forwarding: true,
+            project_icons: true,
+            logos: LogoCache::default(),
+            git_statuses: None,
+            git_selected: None,
+            reduce_motion: false,
+            switched_at: None,
         }
     }
 
@@ -95,65 +437,322 @@ impl TabManager {
 
     /// Get a reference to a tab by index
     #[allow(dead_code)]
-    pub fn get(&self, index: usize) -> Option<&Tab> {
+    pub fn get(&self, index: usize) -> Option<&Tab<P>> {
         self.tabs.get(index)
     }
 
     /// Get a mutable reference to a tab by index
     #[allow(dead_code)]
-    pub fn get_mut(&mut self, index: usize) -> Option<&mut Tab> {
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Tab<P>> {
         self.tabs.get_mut(index)
     }
 
     /// Get the currently selected tab
-    pub fn selected_tab(&self) -> Option<&Tab> {
+    pub fn selected_tab(&self) -> Option<&Tab<P>> {
         self.tabs.get(self.selected_index)
     }
 
     /// Get a mutable reference to the currently selected tab
     #[allow(dead_code)]
-    pub fn selected_tab_mut(&mut self) -> Option<&mut Tab> {
+    pub fn selected_tab_mut(&mut self) -> Option<&mut Tab<P>> {
         self.tabs.get_mut(self.selected_index)
     }
 
-    /// Create a new tab with a spawned Neovide process using a profile
-    /// Returns the index of the new tab, or an error if spawning failed
+    /// Create a new tab for a profile and queue its Neovide process for launch.
+    /// The process starts right away when fewer than `max_concurrent_spawns` others
+    /// are starting; otherwise it launches from the queue as earlier ones get ready.
+    /// Returns the index of the new tab, or an error if launching it failed
     pub fn create_tab(
         &mut self,
         width: u32,
         height: u32,
-        parent_hwnd: HWND,
+        parent: P::Window,
         profile: &Profile,
         profile_index: usize,
     ) -> Result<usize> {
-        let process = NeovideProcess::spawn(
-            width,
-            height,
-            parent_hwnd,
-            Some(profile.working_directory.as_path()),
-        )?;
+        self.add_tab(width, height, parent, profile, profile_index, Vec::new())
+    }
+
+    /// `create_tab` with `files` for Neovide to open
+    fn add_tab(
+        &mut self,
+        width: u32,
+        height: u32,
+        parent: P::Window,
+        profile: &Profile,
+        profile_index: usize,
+        files: Vec<PathBuf>,
+    ) -> Result<usize> {
+        let process = P::pending();
+        let profile = &profile.with_directory_overrides(&profile.working_directory);
+
+        // The profile's startup files and command are for plain new tabs, not
+        // tabs opened for particular files
+        let (files, nvim_cmd) = if files.is_empty() {
+            (profile.open.clone(), profile.nvim_cmd.clone())
+        } else {
+            (files, None)
+        };
 
-        // Initialize with profile name as cached title (will be updated when Neovide window is ready)
-        let initial_title = profile.name.clone();
+        // Initialize with the fallback title (updated once the Neovide window is ready)
+        let initial_title = expand_title_with_fallback(
+            &profile.title,
+            &self.title_fallback,
+            &TitleContext {
+                profile_name: &profile.name,
+                working_directory: &profile.working_directory,
+                window_title: "",
+            },
+        );
 
         let tab = Tab {
             id: self.next_id,
             process,
             profile_name: profile.name.clone(),
             profile_icon: profile.icon.clone(),
+            icon_override: project_icon(
+                self.project_icons,
+                profile,
+                &profile.working_directory,
+                &mut self.logos,
+            ),
             working_directory: profile.working_directory.clone(),
             profile_index,
-            close_requested_at: None,
+            // Tabs opened while every tab is closing are closed too
+            close_state: if self.close_sequence == CloseSequence::All {
+                CloseState::Queued
+            } else {
+                CloseState::Open
+            },
             title_format: profile.title.clone(),
             cached_title: initial_title,
+            created_at: Instant::now(),
+            last_active: Instant::now(),
+            usage: UsageTracker::default(),
+            not_responding: false,
+            placed: false,
+            position_dirty: Cell::new(true),
+            attention_at: None,
+            custom_title: None,
+            pinned: false,
+            pause_animations: false,
+            animations_paused: false,
+            spawn_error: None,
+            elevated: profile.elevated,
+            env: profile.env.clone(),
+            neovide_pid: None,
+            files,
+            nvim_cmd,
         };
+        let tab_id = tab.id;
         self.next_id += 1;
 
+        // A new batch starts when no other tab is still starting
+        if self.pending_count() == 0 {
+            self.spawn_batch_size = 0;
+        }
+        self.spawn_batch_size += 1;
+
         self.tabs.push(tab);
-        let new_index = self.tabs.len() - 1;
-        self.selected_index = new_index;
+        self.selected_index = self.tabs.len() - 1;
+        self.history.visit(tab_id);
+        self.spawn_queue.push_back(tab_id);
+        self.session_generation += 1;
+
+        // A failed launch leaves an error tab in the bar rather than failing here
+        self.launch_queued(width, height, parent);
+        self.find_tab_by_id(tab_id)
+            .ok_or_else(|| anyhow::anyhow!("Failed to spawn Neovide process"))
+    }
 
-        Ok(new_index)
+    /// Set the title formats tried when a tab's title format expands to nothing
+    pub fn set_title_fallback(&mut self, fallback: Vec<String>) {
+        self.title_fallback = fallback;
+    }
+
+    /// Set how many Neovide processes may be starting at the same time (at least 1)
+    pub fn set_max_concurrent_spawns(&mut self, limit: usize) {
+        self.max_concurrent_spawns = limit.max(1);
+    }
+
+    /// Number of launched Neovide processes whose window has not been found yet
+    fn starting_count(&self) -> usize {
+        self.tabs
+            .iter()
+            .filter(|tab| tab.process.is_launched() && !tab.process.is_ready())
+            .count()
+    }
+
+    /// Check whether any tab is still waiting in the spawn queue
+    pub fn has_queued_spawns(&self) -> bool {
+        !self.spawn_queue.is_empty()
+    }
+
+    /// Number of tabs whose Neovide window is not ready yet (queued or starting)
+    fn pending_count(&self) -> usize {
+        self.tabs
+            .iter()
+            .filter(|tab| !tab.process.is_ready() && tab.spawn_error.is_none())
+            .count()
+    }
+
+    /// Check whether a tab is a placeholder whose Neovide window is not ready yet
+    pub fn is_tab_pending(&self, index: usize) -> bool {
+        self.tabs
+            .get(index)
+            .is_some_and(|tab| !tab.process.is_ready() && tab.spawn_error.is_none())
+    }
+
+    /// Why a tab's Neovide failed to launch, if it did
+    pub fn get_tab_spawn_error(&self, index: usize) -> Option<&str> {
+        self.tabs.get(index)?.spawn_error.as_deref()
+    }
+
+    /// Queue an error tab's Neovide to be launched again and launch it if a spawn
+    /// slot is free. Returns false if the tab isn't an error tab.
+    pub fn retry_tab(&mut self, index: usize, width: u32, height: u32, parent: P::Window) -> bool {
+        if self.get_tab_spawn_error(index).is_none() {
+            return false;
+        }
+        // Like a new tab, the retry starts a new batch when no other tab is starting
+        if self.pending_count() == 0 {
+            self.spawn_batch_size = 0;
+        }
+        self.spawn_batch_size += 1;
+
+        let tab = &mut self.tabs[index];
+        tab.spawn_error = None;
+        tab.created_at = Instant::now();
+        self.spawn_queue.push_back(tab.id);
+        self.launch_queued(width, height, parent);
+        true
+    }
+
+    /// Progress of the current startup batch as (ready, total), or None when no
+    /// more than one tab is starting
+    pub fn startup_progress(&self) -> Option<(usize, usize)> {
+        batch_progress(self.spawn_batch_size, self.pending_count())
+    }
+
+    /// Drop all tabs still waiting in the spawn queue (already launched tabs keep
+    /// starting). Returns the number of tabs removed.
+    pub fn cancel_queued_spawns(&mut self) -> usize {
+        let mut removed = 0;
+        while let Some(tab_id) = self.spawn_queue.pop_front() {
+            if let Some(index) = self.find_tab_by_id(tab_id) {
+                self.remove_exited_tab(index);
+                removed += 1;
+            }
+        }
+        self.spawn_batch_size = self.spawn_batch_size.saturating_sub(removed);
+        removed
+    }
+
+    /// Turn tabs whose Neovide window never appeared into error tabs (see
+    /// `ProcessHandle::take_window_error`). Returns true if any tab changed.
+    pub fn mark_window_failures(&mut self) -> bool {
+        let mut changed = false;
+        for tab in &mut self.tabs {
+            if let Some(error) = tab.process.take_window_error() {
+                tab.process.abandon();
+                tab.spawn_error = Some(error);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Launch queued Neovide processes while spawn slots are free.
+    /// Tabs that fail to launch become error tabs. Returns true if any failed.
+    pub fn launch_queued(&mut self, width: u32, height: u32, parent: P::Window) -> bool {
+        let mut failed = false;
+        while self.starting_count() < self.max_concurrent_spawns {
+            let Some(tab_id) = self.spawn_queue.pop_front() else {
+                break;
+            };
+            // The tab may have been closed while it was queued
+            let Some(index) = self.find_tab_by_id(tab_id) else {
+                continue;
+            };
+            let forward_attention = self.attention_forwarding;
+            let tab = &mut self.tabs[index];
+            let working_directory = tab.working_directory.clone();
+            let options = LaunchOptions {
+                working_directory: Some(working_directory.as_path()),
+                tab_id: Some(tab_id),
+                forward_attention,
+                elevated: tab.elevated,
+                env: &tab.env,
+                files: &tab.files,
+                nvim_cmd: tab.nvim_cmd.as_deref(),
+            };
+            if let Err(e) = tab.process.launch(width, height, parent, &options) {
+                // The error carries the full command line
+                eprintln!("Failed to launch Neovide for tab {}: {:#}", tab_id, e);
+                tab.spawn_error = Some(format!("{:#}", e));
+                failed = true;
+            }
+        }
+        failed
+    }
+
+    /// Position newly found Neovide windows, in tab order: the selected tab's window
+    /// is shown and brought to the foreground (unless the wrapper is not `shown`,
+    /// e.g. minimized), all others are hidden.
+    /// Returns true if any window was placed (caller should repaint).
+    pub fn place_ready_windows(
+        &mut self,
+        parent: P::Window,
+        titlebar_height: i32,
+        shown: bool,
+    ) -> bool {
+        let mut placed_any = false;
+        for (i, tab) in self.tabs.iter_mut().enumerate() {
+            if tab.placed || !tab.process.is_ready() {
+                continue;
+            }
+            tab.placed = true;
+            placed_any = true;
+            if i == self.selected_index && shown {
+                tab.activate(parent, titlebar_height);
+            } else {
+                // Moved into place once it is shown
+                tab.process.hide();
+            }
+            tab.neovide_pid = tab.process.neovide_pid();
+            tab.run_hook(HookEvent::Open);
+        }
+        if placed_any {
+            // The newly found windows may carry a title already
+            self.update_selected_tab_title();
+        }
+        placed_any
+    }
+
+    /// Create a new tab from a profile but starting in a different working directory,
+    /// with Neovide opening `files`. The override applies to this tab only; the
+    /// profile itself is left unchanged.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_tab_in_directory(
+        &mut self,
+        width: u32,
+        height: u32,
+        parent: P::Window,
+        profile: &Profile,
+        profile_index: usize,
+        working_directory: &Path,
+        files: &[PathBuf],
+    ) -> Result<usize> {
+        let mut profile = profile.clone();
+        profile.working_directory = working_directory.to_path_buf();
+        self.add_tab(
+            width,
+            height,
+            parent,
+            &profile,
+            profile_index,
+            files.to_vec(),
+        )
     }
 
     /// Create a new tab with a spawned Neovide process (legacy, uses no working directory)
@@ -163,20 +762,39 @@ impl TabManager {
         &mut self,
         width: u32,
         height: u32,
-        parent_hwnd: HWND,
+        parent: P::Window,
     ) -> Result<usize> {
-        let process = NeovideProcess::spawn(width, height, parent_hwnd, None)?;
+        let mut process = P::pending();
+        process.launch(width, height, parent, &LaunchOptions::default())?;
 
         let tab = Tab {
             id: self.next_id,
             process,
             profile_name: "Default".to_string(),
             profile_icon: crate::config::DEFAULT_ICON.to_string(),
+            icon_override: None,
             working_directory: dirs::home_dir().unwrap_or_default(),
             profile_index: 0,
-            close_requested_at: None,
+            close_state: CloseState::Open,
             title_format: crate::config::DEFAULT_TITLE_FORMAT.to_string(),
             cached_title: "Default".to_string(),
+            created_at: Instant::now(),
+            last_active: Instant::now(),
+            usage: UsageTracker::default(),
+            not_responding: false,
+            placed: false,
+            position_dirty: Cell::new(true),
+            attention_at: None,
+            custom_title: None,
+            pinned: false,
+            pause_animations: false,
+            animations_paused: false,
+            spawn_error: None,
+            elevated: false,
+            env: Vec::new(),
+            neovide_pid: None,
+            files: Vec::new(),
+            nvim_cmd: None,
         };
         self.next_id += 1;
 
@@ -192,9 +810,22 @@ impl TabManager {
     /// Also updates the tab's title when selected
     pub fn select_tab(&mut self, index: usize) -> bool {
         if index < self.tabs.len() && index != self.selected_index {
+            let now = Instant::now();
+            if let Some(previous) = self.tabs.get_mut(self.selected_index) {
+                previous.last_active = now;
+            }
             self.selected_index = index;
+            self.tabs[index].last_active = now;
+            self.switched_at = Some(now);
+            self.session_generation += 1;
+            self.history.visit(self.tabs[index].id);
+            self.tabs[index].attention_at = None;
+            // Resume a suspended tab before anything touches its window
+            self.tabs[index].process.resume();
+            self.update_animation_pauses();
             // Update the tab title when selected
             self.update_tab_title(index);
+            self.tabs[index].run_hook(HookEvent::Select);
             true
         } else {
             false
@@ -211,8 +842,14 @@ impl TabManager {
         // Remove and drop the tab (which terminates the process via Drop)
         let mut tab = self.tabs.remove(index);
         let _ = tab.process.terminate();
+        // Only tabs that ran the open hook run the close hook
+        if tab.placed {
+            tab.run_hook(HookEvent::Close);
+        }
+        self.session_generation += 1;
 
         if self.tabs.is_empty() {
+            self.end_close_sequence();
             return true; // Last tab closed
         }
 
@@ -222,10 +859,145 @@ impl TabManager {
         } else if self.selected_index > index {
             self.selected_index -= 1;
         }
+        self.update_animation_pauses();
 
         false
     }
 
+    /// Index of the tab after (or before) `index` in the tab bar, wrapping around
+    /// at the ends. None without tabs.
+    pub fn cycled_index(&self, index: usize, forward: bool) -> Option<usize> {
+        let count = self.tabs.len();
+        if count == 0 {
+            return None;
+        }
+        let index = index.min(count - 1);
+        Some(if forward {
+            (index + 1) % count
+        } else {
+            (index + count - 1) % count
+        })
+    }
+
+    /// Select the tab that was selected before the current one, skipping closed tabs.
+    /// Returns true if the selection changed.
+    pub fn history_back(&mut self) -> bool {
+        self.navigate_history(false)
+    }
+
+    /// Undo a `history_back`, skipping closed tabs. Returns true if the selection changed.
+    pub fn history_forward(&mut self) -> bool {
+        self.navigate_history(true)
+    }
+
+    fn navigate_history(&mut self, forward: bool) -> bool {
+        let Some(current) = self.get_tab_id(self.selected_index) else {
+            return false;
+        };
+        // The selection may have moved without select_tab (e.g. a tab closed)
+        self.history.visit(current);
+        let tabs = &self.tabs;
+        let exists = |id: usize| tabs.iter().any(|tab| tab.id == id);
+        let target = if forward {
+            self.history.forward(current, exists)
+        } else {
+            self.history.back(current, exists)
+        };
+        match target.and_then(|id| self.find_tab_by_id(id)) {
+            Some(index) => self.select_tab(index),
+            None => false,
+        }
+    }
+
+    /// Set whether tabs launched from now on forward attention requests from nvim
+    pub fn set_attention_forwarding(&mut self, enabled: bool) {
+        self.attention_forwarding = enabled;
+    }
+
+    /// Set whether animations are turned off (`reduce_motion` config option or
+    /// Windows' animation setting)
+    pub fn set_reduce_motion(&mut self, reduce: bool) {
+        self.reduce_motion = reduce;
+    }
+
+    /// Whether animations are turned off
+    pub fn reduce_motion(&self) -> bool {
+        self.reduce_motion
+    }
+
+    /// Set whether tabs of profiles without an icon show their project type's icon.
+    /// Existing tabs follow on the next `refresh_profiles`.
+    pub fn set_project_icons(&mut self, enabled: bool) {
+        self.project_icons = enabled;
+    }
+
+    /// Set whether the tabs' working directories are checked with `git status`
+    pub fn set_git_status(&mut self, enabled: bool) {
+        if enabled != self.git_statuses.is_some() {
+            self.git_statuses = enabled.then(GitStatusCache::default);
+            self.git_selected = None;
+        }
+    }
+
+    /// Take the results of finished git status checks and start new ones: every
+    /// `GIT_STATUS_INTERVAL` for each tab, and right away for a newly selected
+    /// tab. Returns true if a tab's status changed (the tab bar needs a repaint).
+    pub fn update_git_statuses(&mut self) -> bool {
+        let Some(statuses) = self.git_statuses.as_mut() else {
+            return false;
+        };
+        let changed = statuses.receive();
+        let selected = self.tabs.get(self.selected_index).map(|tab| tab.id);
+        let newly_selected = selected != self.git_selected;
+        self.git_selected = selected;
+        for (index, tab) in self.tabs.iter().enumerate() {
+            let max_age = if newly_selected && index == self.selected_index {
+                Duration::ZERO
+            } else {
+                GIT_STATUS_INTERVAL
+            };
+            statuses.refresh(&tab.working_directory, max_age);
+        }
+        changed
+    }
+
+    /// Git status of a tab's working directory; None while `git_status` is off,
+    /// outside a repository, or before the first check finished
+    pub fn get_tab_git_status(&self, index: usize) -> Option<&GitStatus> {
+        self.git_statuses
+            .as_ref()?
+            .get(&self.tabs.get(index)?.working_directory)
+    }
+
+    /// Start the attention pulse of a background tab.
+    /// Returns false if the tab is gone or is the selected tab.
+    pub fn request_attention(&mut self, tab_id: usize) -> bool {
+        match self.find_tab_by_id(tab_id) {
+            Some(index) if index != self.selected_index => {
+                self.tabs[index].attention_at = Some(Instant::now());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Strength (0.0-1.0) of a tab's attention highlight; 0 when it isn't pulsing
+    pub fn attention_intensity(&self, index: usize) -> f32 {
+        self.tabs
+            .get(index)
+            .and_then(|tab| tab.attention_at)
+            .and_then(|at| pulse_intensity(at.elapsed(), self.reduce_motion))
+            .unwrap_or(0.0)
+    }
+
+    /// Whether any tab's attention pulse is still running
+    pub fn has_attention_pulse(&self) -> bool {
+        self.tabs.iter().any(|tab| {
+            tab.attention_at
+                .is_some_and(|at| pulse_intensity(at.elapsed(), self.reduce_motion).is_some())
+        })
+    }
+
     /// Move a tab from one position to another
     pub fn move_tab(&mut self, from_index: usize, to_index: usize) {
         if from_index >= self.tabs.len() || to_index >= self.tabs.len() || from_index == to_index {
@@ -234,6 +1006,7 @@ impl TabManager {
 
         let tab = self.tabs.remove(from_index);
         self.tabs.insert(to_index, tab);
+        self.session_generation += 1;
 
         // Update selected index to follow the moved tab if it was selected
         if self.selected_index == from_index {
@@ -245,20 +1018,37 @@ impl TabManager {
         }
     }
 
-    /// Update the position of all Neovide windows (only moves if needed)
-    pub fn update_all_positions(&self, parent_hwnd: HWND, titlebar_height: i32) {
+    /// Mark every tab's window as out of place, e.g. after the display setup
+    /// changed; each is moved into place when it is next shown
+    pub fn mark_positions_dirty(&self) {
         for tab in &self.tabs {
-            tab.process.update_position(parent_hwnd, titlebar_height);
+            tab.position_dirty.set(true);
+        }
+    }
+
+    /// Move the selected tab's Neovide window to the content area (only moves if
+    /// needed). Hidden windows are not touched but marked as out of place:
+    /// `activate_selected` moves a tab's window into place right before showing it.
+    /// Returns true if the window was moved.
+    pub fn update_selected_position(&self, parent: P::Window, titlebar_height: i32) -> bool {
+        let mut moved = false;
+        for (i, tab) in self.tabs.iter().enumerate() {
+            if i == self.selected_index {
+                tab.position_dirty.set(false);
+                moved = tab.process.update_position(parent, titlebar_height);
+            } else {
+                tab.position_dirty.set(true);
+            }
         }
+        moved
     }
 
     /// Activate the selected tab: ensure position, show it, hide others, bring to foreground
     /// This is the main method for switching tabs
-    pub fn activate_selected(&self, parent_hwnd: HWND, titlebar_height: i32) {
+    pub fn activate_selected(&self, parent: P::Window, titlebar_height: i32) {
         for (i, tab) in self.tabs.iter().enumerate() {
             if i == self.selected_index {
-                // Use the combined activate method which handles position check + show + foreground
-                tab.process.activate(parent_hwnd, titlebar_height);
+                tab.activate(parent, titlebar_height);
             } else {
                 tab.process.hide();
             }
@@ -266,7 +1056,7 @@ impl TabManager {
     }
 
     /// Show the selected tab's Neovide window and hide all others
-    /// Note: Prefer activate_selected() when parent_hwnd is available
+    /// Note: Prefer activate_selected() when the parent window is available
     #[allow(dead_code)]
     pub fn show_selected_hide_others(&self) {
         for (i, tab) in self.tabs.iter().enumerate() {
@@ -288,9 +1078,9 @@ impl TabManager {
     }
 
     /// Activate the selected tab with position check, then bring to foreground
-    pub fn activate_and_foreground_selected(&self, parent_hwnd: HWND, titlebar_height: i32) {
+    pub fn activate_and_foreground_selected(&self, parent: P::Window, titlebar_height: i32) {
         if let Some(tab) = self.selected_tab() {
-            tab.process.activate(parent_hwnd, titlebar_height);
+            tab.activate(parent, titlebar_height);
         }
     }
 
@@ -300,128 +1090,683 @@ impl TabManager {
             .is_some_and(|tab| tab.process.is_ready())
     }
 
-    /// Terminate all tabs' processes forcefully
-    #[allow(dead_code)]
-    pub fn terminate_all(&mut self) {
-        for tab in &mut self.tabs {
-            let _ = tab.process.terminate();
-        }
-        self.tabs.clear();
+    /// Time since the selected tab's Neovide was spawned, while its window is not ready yet.
+    /// Returns None once the window is ready (or if there is no selected tab).
+    pub fn selected_startup_elapsed(&self) -> Option<Duration> {
+        self.selected_tab()
+            .filter(|tab| !tab.process.is_ready() && tab.spawn_error.is_none())
+            .map(|tab| tab.created_at.elapsed())
     }
 
-    /// Request graceful close for a tab by sending WM_CLOSE to its Neovide window.
-    /// If the window is not ready, falls back to forceful termination via close_tab().
-    /// Returns true if graceful close was requested (tab remains until process exits),
-    /// false if forceful close was used (tab already removed).
-    pub fn request_close_tab(&mut self, index: usize) -> bool {
-        if index >= self.tabs.len() {
-            return false;
+    /// The Neovide window of the tab at the given index, once it has one
+    pub fn get_tab_window(&self, index: usize) -> Option<P::Window> {
+        self.tabs.get(index)?.process.window_handle()
+    }
+
+    /// Check whether the tab's animations are turned off while it is in the background
+    pub fn is_tab_pausing_animations(&self, index: usize) -> bool {
+        self.tabs.get(index).is_some_and(|tab| tab.pause_animations)
+    }
+
+    /// Check whether the tab's animations can be paused: that takes nvim's RPC
+    /// pipe, which it has once launched
+    pub fn can_pause_animations(&self, index: usize) -> bool {
+        self.tabs
+            .get(index)
+            .is_some_and(|tab| tab.process.has_rpc())
+    }
+
+    /// Toggle turning off the tab's animations while it is in the background
+    pub fn toggle_pause_animations(&mut self, index: usize) {
+        if let Some(tab) = self.tabs.get_mut(index) {
+            tab.pause_animations = !tab.pause_animations;
+            self.update_animation_pauses();
         }
+    }
 
-        // Try to send WM_CLOSE to the Neovide window
-        if self.tabs[index].process.request_close() {
-            // Message sent successfully - record timestamp and wait for process to exit
-            if self.tabs[index].close_requested_at.is_none() {
-                self.tabs[index].close_requested_at = Some(Instant::now());
+    /// Turn the animations of background tabs with `pause_animations` off, and
+    /// those of the selected tab back on. Neovide is only told about changes, once
+    /// its window is ready.
+    pub fn update_animation_pauses(&mut self) {
+        let selected_index = self.selected_index;
+        for (index, tab) in self.tabs.iter_mut().enumerate() {
+            let paused = tab.pause_animations && index != selected_index;
+            if paused == tab.animations_paused || !tab.process.is_ready() {
+                continue;
+            }
+            tab.animations_paused = paused;
+            if let Some(Err(e)) = tab.process.request_animations_paused(paused) {
+                eprintln!("Failed to pause animations of tab {}: {:#}", tab.id, e);
             }
-            true
-        } else {
-            // Window not ready - fall back to forceful close
-            self.close_tab(index);
-            false
         }
     }
 
-    /// Request graceful close for all tabs by sending WM_CLOSE to each Neovide window.
-    /// For tabs where window is not ready, forcefully terminates them.
-    /// Does not remove tabs - process polling will handle removal as processes exit.
-    ///
-    /// Important: We only send WM_CLOSE to the currently selected (visible) tab.
-    /// Hidden windows may not process WM_CLOSE properly. The polling mechanism
-    /// will handle closing subsequent tabs one at a time as each one exits.
-    pub fn request_close_all(&mut self) {
-        if self.tabs.is_empty() {
-            return;
-        }
+    /// Check whether `hwnd` is the Neovide window of one of the tabs
+    pub fn owns_window(&self, hwnd: P::Window) -> bool {
+        self.tabs
+            .iter()
+            .any(|tab| tab.process.window_handle() == Some(hwnd))
+    }
 
-        let now = Instant::now();
+    /// Check whether a tab's Neovide process is suspended
+    pub fn is_tab_suspended(&self, index: usize) -> bool {
+        self.tabs
+            .get(index)
+            .is_some_and(|tab| tab.process.is_suspended())
+    }
 
-        // Only request close for the currently selected (visible) tab
-        // Hidden tabs don't process WM_CLOSE reliably
-        let selected = self.selected_index;
-        if selected < self.tabs.len() {
-            let tab = &mut self.tabs[selected];
-            if tab.process.request_close() {
-                if tab.close_requested_at.is_none() {
-                    tab.close_requested_at = Some(now);
-                }
-            } else {
-                // Window not ready - forcefully close it
-                self.close_tab(selected);
+    /// What the Neovide of the tab at the given index is doing
+    pub fn tab_state(&self, index: usize) -> Option<TabState> {
+        let tab = self.tabs.get(index)?;
+        Some(if tab.spawn_error.is_some() {
+            TabState::Failed
+        } else if tab.close_state != CloseState::Open {
+            TabState::Closing
+        } else if !tab.process.is_launched() {
+            TabState::Queued
+        } else if !tab.process.is_ready() {
+            TabState::Starting
+        } else if tab.process.is_suspended() {
+            TabState::Suspended
+        } else if tab.not_responding {
+            TabState::NotResponding
+        } else {
+            TabState::Ready
+        })
+    }
+
+    /// Check if the Neovide of the tab at the given index runs as administrator
+    pub fn is_tab_elevated(&self, index: usize) -> bool {
+        self.tabs.get(index).is_some_and(|tab| tab.elevated)
+    }
+
+    /// Suspend background tabs that have not been selected for at least `idle`.
+    /// Tabs that are starting up or closing are left alone.
+    /// Returns true if any tab was suspended (caller should repaint).
+    pub fn suspend_idle_background_tabs(&mut self, idle: Duration) -> bool {
+        let now = Instant::now();
+        let mut changed = false;
+        for (i, tab) in self.tabs.iter_mut().enumerate() {
+            if i == self.selected_index {
+                tab.last_active = now;
+                continue;
+            }
+            if tab.close_state == CloseState::Open
+                && !tab.process.is_suspended()
+                && now.duration_since(tab.last_active) >= idle
+                && tab.process.suspend()
+            {
+                changed = true;
             }
         }
+        changed
+    }
 
-        // Mark all other tabs as pending close (but don't send WM_CLOSE yet)
-        // They will be closed one at a time by the polling mechanism
-        for (i, tab) in self.tabs.iter_mut().enumerate() {
-            if i != self.selected_index && tab.close_requested_at.is_none() {
-                tab.close_requested_at = Some(now);
+    /// Hibernate background tabs, least recently used first, until the memory
+    /// they use adds up to `to_free`: suspend each and trim its memory. The
+    /// selected tab and tabs that are starting, closing or already suspended are
+    /// left alone. Returns the number of tabs hibernated.
+    pub fn hibernate_least_recently_used(&mut self, to_free: u64) -> usize {
+        let mut candidates: Vec<usize> = (0..self.tabs.len())
+            .filter(|&i| i != self.selected_index)
+            .filter(|&i| {
+                matches!(
+                    self.tab_state(i),
+                    Some(TabState::Ready | TabState::NotResponding)
+                )
+            })
+            .collect();
+        candidates.sort_by_key(|&i| self.tabs[i].last_active);
+
+        let mut freed = 0;
+        let mut count = 0;
+        for i in candidates {
+            if freed >= to_free {
+                break;
+            }
+            let tab = &self.tabs[i];
+            if tab.process.suspend() {
+                tab.process.trim_memory();
+                freed += tab.usage.current().map_or(0, |usage| usage.memory_bytes);
+                count += 1;
             }
         }
+        count
     }
 
-    /// Check if any tabs are pending close (close was requested but not yet sent WM_CLOSE)
-    pub fn has_pending_close(&self) -> bool {
-        self.tabs.iter().any(|tab| tab.close_requested_at.is_some())
+    /// Resume all suspended tabs (e.g. when suspension is disabled or on close).
+    /// Returns true if any tab was resumed.
+    pub fn resume_all(&self) -> bool {
+        let mut changed = false;
+        for tab in &self.tabs {
+            if tab.process.is_suspended() {
+                tab.process.resume();
+                changed = true;
+            }
+        }
+        changed
     }
 
-    /// Request close for the currently selected tab if it has a pending close.
-    /// Called after a tab is removed to continue the close sequence.
-    /// Returns true if a close was requested.
-    pub fn continue_close_sequence(&mut self) -> bool {
-        if self.tabs.is_empty() {
+    /// Sample the memory and CPU usage of every tab whose sample is due. The
+    /// process table comes from `take_processes`, called once if any tab is due.
+    /// Returns true if any tab's usage changed (tooltip/task manager should refresh).
+    pub fn update_resource_usage(
+        &mut self,
+        take_processes: impl FnOnce() -> Option<ProcessTable>,
+    ) -> bool {
+        let now = Instant::now();
+        if !self.tabs.iter().any(|tab| tab.usage.is_due(now)) {
+            return false;
+        }
+        let Some(processes) = take_processes() else {
             return false;
+        };
+        let cpu_count = usage::cpu_count();
+        let mut changed = false;
+        for tab in &mut self.tabs {
+            if !tab.usage.is_due(now) {
+                continue;
+            }
+            if let Some(sample) = tab.process.usage_sample(&processes) {
+                let previous = tab.usage.current();
+                tab.usage.record(sample, now, cpu_count);
+                changed |= tab.usage.current() != previous;
+            }
         }
+        changed
+    }
 
-        let selected = self.selected_index;
-        if selected < self.tabs.len() {
-            let tab = &mut self.tabs[selected];
-            if tab.close_requested_at.is_some() {
-                // Show the window first so it can process WM_CLOSE
-                tab.process.show();
-                return tab.process.request_close();
+    /// Memory the tabs use in total, by their latest samples
+    pub fn total_memory(&self) -> u64 {
+        self.tabs
+            .iter()
+            .filter_map(|tab| tab.usage.current())
+            .map(|usage| usage.memory_bytes)
+            .sum()
+    }
+
+    /// Get the latest memory and CPU usage of a tab
+    pub fn get_tab_usage(&self, index: usize) -> Option<ResourceUsage> {
+        self.tabs.get(index).and_then(|tab| tab.usage.current())
+    }
+
+    /// What the Neovide of the tab at the given index wrote to stdout and stderr
+    pub fn get_tab_output(&self, index: usize) -> Option<OutputLog> {
+        self.tabs.get(index)?.process.output()
+    }
+
+    /// Re-check which tabs' Neovide windows are not responding.
+    /// Returns true if any tab changed state (caller should repaint).
+    pub fn update_responsiveness(&mut self) -> bool {
+        let mut changed = false;
+        for tab in &mut self.tabs {
+            let hung = tab.process.is_hung();
+            if hung != tab.not_responding {
+                tab.not_responding = hung;
+                changed = true;
             }
         }
-        false
+        changed
     }
 
-    /// Get the label for a tab (cached expanded title)
-    pub fn get_tab_label(&self, index: usize) -> String {
-        if let Some(tab) = self.tabs.get(index) {
-            tab.cached_title.clone()
-        } else {
-            String::new()
+    /// Keep other windows of each tab's Neovide process in line with the tab
+    /// (see `ProcessHandle::track_extra_windows`)
+    pub fn track_extra_windows(&self) {
+        for tab in &self.tabs {
+            tab.process.track_extra_windows();
         }
     }
 
-    /// Update the cached title for a tab by expanding its title format.
-    /// Returns true if the title changed.
+    /// After the machine resumed from sleep: pick up Neovide windows that were
+    /// recreated, and move every window into place again before it is shown.
+    /// Returns true if a window was replaced.
+    pub fn revalidate_windows(&self) -> bool {
+        let mut replaced = false;
+        for tab in &self.tabs {
+            replaced |= tab.process.revalidate_window();
+        }
+        self.mark_positions_dirty();
+        replaced
+    }
+
+    /// Check whether a tab's Neovide window is not responding
+    pub fn is_tab_not_responding(&self, index: usize) -> bool {
+        self.tabs.get(index).is_some_and(|tab| tab.not_responding)
+    }
+
+    /// Get the unique ID of a tab by index
+    pub fn get_tab_id(&self, index: usize) -> Option<usize> {
+        self.tabs.get(index).map(|tab| tab.id)
+    }
+
+    /// Find the current index of a tab by its unique ID
+    pub fn find_tab_by_id(&self, id: usize) -> Option<usize> {
+        self.tabs.iter().position(|tab| tab.id == id)
+    }
+
+    /// Show or hide all tabs' Neovide windows in Alt-Tab and the taskbar
+    pub fn set_alt_tab_visible(&self, visible: bool) {
+        for tab in &self.tabs {
+            tab.process.set_alt_tab_visible(visible);
+        }
+    }
+
+    /// Make every tab's Neovide window topmost (or not), following the wrapper
+    pub fn set_topmost(&self, topmost: bool) {
+        for tab in &self.tabs {
+            tab.process.set_topmost(topmost);
+        }
+    }
+
+    /// Terminate all tabs' processes forcefully
+    #[allow(dead_code)]
+    pub fn terminate_all(&mut self) {
+        for tab in &mut self.tabs {
+            let _ = tab.process.terminate();
+        }
+        self.tabs.clear();
+    }
+
+    /// Request graceful close for a tab by sending WM_CLOSE to its Neovide window.
+    /// If the window is not ready, falls back to forceful termination via close_tab().
+    /// Returns true if graceful close was requested (tab remains until process exits),
+    /// false if forceful close was used (tab already removed).
+    pub fn request_close_tab(&mut self, index: usize) -> bool {
+        if index >= self.tabs.len() {
+            return false;
+        }
+
+        if self.request_tab_close(index) {
+            true
+        } else {
+            // Window not ready - fall back to forceful close
+            self.close_tab(index);
+            false
+        }
+    }
+
+    /// Close every tab, one at a time (app exit, session switch).
+    /// Does not remove tabs - process polling will handle removal as processes exit.
+    ///
+    /// Only the selected (visible) tab is asked first: hidden windows may not
+    /// process WM_CLOSE properly. The others are queued and asked in turn by
+    /// `continue_close_sequence` as each one exits.
+    pub fn request_close_all(&mut self) {
+        if self.tabs.is_empty() {
+            return;
+        }
+
+        // Closing everything supersedes a bulk close in progress
+        self.close_sequence = CloseSequence::All;
+
+        // Suspended tabs must be running again to handle their WM_CLOSE later
+        self.resume_all();
+
+        for tab in &mut self.tabs {
+            if tab.close_state == CloseState::Open {
+                tab.close_state = CloseState::Queued;
+            }
+        }
+        self.advance_close_sequence();
+    }
+
+    /// Gracefully close every tab except the one at `index`, one at a time.
+    /// The tab at `index` is selected again once the others are gone.
+    pub fn request_close_others(&mut self, index: usize) {
+        if let Some(keep_id) = self.get_tab_id(index) {
+            let targets = close_others_targets(&self.tab_ids(), index);
+            self.request_close_many(targets, keep_id);
+        }
+    }
+
+    /// Gracefully close every tab to the right of `index`, one at a time.
+    /// The tab at `index` is selected again once they are gone.
+    pub fn request_close_to_right(&mut self, index: usize) {
+        if let Some(keep_id) = self.get_tab_id(index) {
+            let targets = close_to_right_targets(&self.tab_ids(), index);
+            self.request_close_many(targets, keep_id);
+        }
+    }
+
+    fn tab_ids(&self) -> Vec<usize> {
+        self.tabs.iter().map(|tab| tab.id).collect()
+    }
+
+    /// Start a bulk close of the tabs with the given IDs. Like `request_close_all`,
+    /// the tabs are closed one at a time and shown first, since hidden windows don't
+    /// process WM_CLOSE reliably. A new bulk close replaces one in progress; while
+    /// all tabs are closing, it is ignored. Pinned tabs are left open.
+    fn request_close_many(&mut self, mut targets: Vec<usize>, keep_id: usize) {
+        if self.close_sequence == CloseSequence::All {
+            return;
+        }
+        targets.retain(|id| self.tabs.iter().any(|tab| tab.id == *id && !tab.pinned));
+        if targets.is_empty() {
+            return;
+        }
+        for tab in &mut self.tabs {
+            if tab.close_state == CloseState::Queued {
+                tab.close_state = CloseState::Open;
+            }
+            if targets.contains(&tab.id) && tab.close_state == CloseState::Open {
+                tab.close_state = CloseState::Queued;
+            }
+        }
+        self.close_sequence = CloseSequence::Bulk { keep: keep_id };
+        self.advance_close_sequence();
+    }
+
+    /// Ask the next queued tab of the close sequence to close once the previous
+    /// one has exited (or was kept open). Tabs without a window are closed
+    /// forcefully on the way. When a bulk close runs out of tabs, the kept tab is
+    /// selected again and the sequence ends; closing all tabs ends when the last
+    /// tab is gone. Returns true if a close was requested.
+    fn advance_close_sequence(&mut self) -> bool {
+        loop {
+            if let Some(id) = self.close_in_flight {
+                let waiting = self.find_tab_by_id(id).is_some_and(|index| {
+                    matches!(
+                        self.tabs[index].close_state,
+                        CloseState::Requested | CloseState::Blocked
+                    )
+                });
+                if waiting {
+                    return false;
+                }
+                self.close_in_flight = None;
+            }
+
+            let queued = |tab: &Tab<P>| tab.close_state == CloseState::Queued;
+            let next = match self.close_sequence {
+                CloseSequence::Idle => return false,
+                // Prefer the selected tab: it is the one the user sees
+                CloseSequence::All => self
+                    .tabs
+                    .get(self.selected_index)
+                    .filter(|tab| queued(tab))
+                    .map(|_| self.selected_index)
+                    .or_else(|| self.tabs.iter().position(queued)),
+                CloseSequence::Bulk { .. } => self.tabs.iter().position(queued),
+            };
+
+            let Some(index) = next else {
+                if let CloseSequence::Bulk { keep } = self.close_sequence {
+                    self.close_sequence = CloseSequence::Idle;
+                    if let Some(index) = self.find_tab_by_id(keep) {
+                        self.select_tab(index);
+                    }
+                }
+                return false;
+            };
+
+            let id = self.tabs[index].id;
+            self.select_tab(index);
+            // Show the window first so it can process WM_CLOSE
+            self.tabs[index].process.show();
+            if self.request_tab_close(index) {
+                self.close_in_flight = Some(id);
+                return true;
+            }
+            // Window not ready - forcefully close it
+            self.close_tab(index);
+        }
+    }
+
+    /// Check if a close sequence is running or any tab is closing
+    pub fn has_pending_close(&self) -> bool {
+        self.close_sequence != CloseSequence::Idle
+            || self
+                .tabs
+                .iter()
+                .any(|tab| tab.close_state != CloseState::Open)
+    }
+
+    /// Move the close sequence on after a tab was removed (or kept open).
+    /// Returns true if a close was requested.
+    pub fn continue_close_sequence(&mut self) -> bool {
+        if self.tabs.is_empty() {
+            return false;
+        }
+        self.advance_close_sequence()
+    }
+
+    /// Stop the close sequence: queued tabs stay open. Tabs already asked to close
+    /// are left to Neovide (they are removed if it exits).
+    pub fn abort_close_sequence(&mut self) {
+        self.close_sequence = CloseSequence::Idle;
+        self.close_in_flight = None;
+        for tab in &mut self.tabs {
+            if tab.close_state == CloseState::Queued {
+                tab.close_state = CloseState::Open;
+            }
+        }
+    }
+
+    /// Reopen tabs whose Neovide stayed open after being asked to close (the user
+    /// cancelled the save prompt) and abort the close sequence, so the remaining
+    /// tabs are not closed later unexpectedly.
+    /// Returns true if any close was cancelled (caller should repaint).
+    pub fn handle_cancelled_closes(&mut self) -> bool {
+        let mut cancelled = false;
+        for tab in &mut self.tabs {
+            if tab.close_state == CloseState::Requested && tab.process.take_close_cancelled() {
+                tab.close_state = CloseState::Open;
+                cancelled = true;
+            }
+        }
+        if cancelled {
+            self.abort_close_sequence();
+        }
+        cancelled
+    }
+
+    /// Ask the Neovide of the tab at `index` to close and record the outcome in its
+    /// close state. Returns false if it has no window to ask (the caller closes the
+    /// tab forcefully). A window that refuses counts as asked: the tab waits in
+    /// `blocked_closes` for the user to decide.
+    fn request_tab_close(&mut self, index: usize) -> bool {
+        let tab = &mut self.tabs[index];
+        match tab.process.request_close() {
+            CloseRequest::Sent => {
+                tab.close_state = CloseState::Requested;
+                true
+            }
+            CloseRequest::NoWindow => false,
+            CloseRequest::Blocked => {
+                tab.close_state = CloseState::Blocked;
+                if !self.blocked_closes.contains(&tab.id) {
+                    self.blocked_closes.push(tab.id);
+                }
+                true
+            }
+        }
+    }
+
+    /// IDs of the tabs whose Neovide refused to close since the last call
+    pub fn take_blocked_closes(&mut self) -> Vec<usize> {
+        std::mem::take(&mut self.blocked_closes)
+    }
+
+    /// Keep the tab `tab_id` open after its close was blocked. A bulk close moves
+    /// on to its next tab; closing all tabs stops (the app stays open).
+    pub fn cancel_close(&mut self, tab_id: usize) {
+        if let Some(index) = self.find_tab_by_id(tab_id) {
+            self.tabs[index].close_state = CloseState::Open;
+        }
+        match self.close_sequence {
+            CloseSequence::All => self.abort_close_sequence(),
+            CloseSequence::Bulk { .. } => {
+                self.advance_close_sequence();
+            }
+            CloseSequence::Idle => {}
+        }
+    }
+
+    /// Forget the close sequence once every tab is gone, so tabs opened afterwards
+    /// (e.g. the next session's) start open
+    fn end_close_sequence(&mut self) {
+        self.close_sequence = CloseSequence::Idle;
+        self.close_in_flight = None;
+    }
+
+    /// Counter that changes whenever the tab set, order or selection changes
+    pub fn session_generation(&self) -> u64 {
+        self.session_generation
+    }
+
+    /// Capture the open tabs (profile, working directory, ID, order and selection) as a session
+    pub fn session_snapshot(&self) -> Session {
+        Session {
+            tabs: self
+                .tabs
+                .iter()
+                .map(|tab| SessionTab {
+                    profile: tab.profile_name.clone(),
+                    working_directory: tab.working_directory.clone(),
+                    title: tab.custom_title.clone(),
+                    pinned: tab.pinned,
+                    id: Some(tab.id),
+                })
+                .collect(),
+            selected: self.selected_index,
+            selected_id: self.tabs.get(self.selected_index).map(|tab| tab.id),
+        }
+    }
+
+    /// Get the label for a tab (its custom name, or the cached expanded title)
+    pub fn get_tab_label(&self, index: usize) -> String {
+        if let Some(tab) = self.tabs.get(index) {
+            tab.custom_title
+                .clone()
+                .unwrap_or_else(|| tab.cached_title.clone())
+        } else {
+            String::new()
+        }
+    }
+
+    /// Give a tab a custom name; an empty name goes back to the profile's title format.
+    /// Returns true if the name changed.
+    pub fn rename_tab(&mut self, index: usize, name: &str) -> bool {
+        let Some(tab) = self.tabs.get_mut(index) else {
+            return false;
+        };
+        let name = name.trim();
+        let custom_title = (!name.is_empty()).then(|| name.to_string());
+        if tab.custom_title == custom_title {
+            return false;
+        }
+        tab.custom_title = custom_title;
+        self.session_generation += 1;
+        true
+    }
+
+    /// Restore the ID, custom name and pin state saved with a session for the tab
+    /// at `index`. The saved ID is only taken back if no other tab has it.
+    pub fn restore_tab_state(&mut self, index: usize, saved: &SessionTab) {
+        let Some(old_id) = self.tabs.get(index).map(|tab| tab.id) else {
+            return;
+        };
+        if let Some(id) = saved.id
+            && !self.tabs.iter().any(|tab| tab.id == id)
+        {
+            self.tabs[index].id = id;
+            for queued in self
+                .spawn_queue
+                .iter_mut()
+                .filter(|queued| **queued == old_id)
+            {
+                *queued = id;
+            }
+            self.history.replace(old_id, id);
+            self.next_id = self.next_id.max(id + 1);
+        }
+        let tab = &mut self.tabs[index];
+        tab.custom_title = saved.title.clone();
+        tab.pinned = saved.pinned;
+    }
+
+    /// Finish restoring `session` once its tabs were created: move the pinned tabs
+    /// first (keeping the saved order otherwise) and select the saved selection.
+    /// `restored` holds the index of the tab created for each saved tab, None for
+    /// those that failed; the selection then falls back to the closest earlier tab.
+    pub fn finish_session_restore(&mut self, session: &Session, restored: &[Option<usize>]) {
+        let restored_ids: Vec<Option<usize>> = restored
+            .iter()
+            .map(|index| index.and_then(|index| self.get_tab_id(index)))
+            .collect();
+        let selected_id = session
+            .selected_id
+            .filter(|&id| restored_ids.contains(&Some(id)))
+            .or_else(|| {
+                let saved = session.selected.min(restored_ids.len().saturating_sub(1));
+                restored_ids
+                    .iter()
+                    .take(saved + 1)
+                    .rev()
+                    .chain(restored_ids.iter().skip(saved + 1))
+                    .find_map(|&id| id)
+            });
+
+        // Sorting is stable, so the tabs otherwise keep their order
+        let current_id = self.get_tab_id(self.selected_index);
+        self.tabs.sort_by_key(|tab| !tab.pinned);
+        if let Some(index) = current_id.and_then(|id| self.find_tab_by_id(id)) {
+            self.selected_index = index;
+        }
+
+        if let Some(index) = selected_id.and_then(|id| self.find_tab_by_id(id)) {
+            self.select_tab(index);
+        }
+    }
+
+    /// Check if a tab is pinned
+    pub fn is_tab_pinned(&self, index: usize) -> bool {
+        self.tabs.get(index).is_some_and(|tab| tab.pinned)
+    }
+
+    /// Number of pinned tabs (they always come first)
+    pub fn pinned_count(&self) -> usize {
+        self.tabs.iter().filter(|tab| tab.pinned).count()
+    }
+
+    /// Pin or unpin a tab, moving it to the end of the pinned tabs or to the first
+    /// unpinned position. Returns the tab's new index.
+    pub fn toggle_pin(&mut self, index: usize) -> Option<usize> {
+        let pinned_count = self.pinned_count();
+        let tab = self.tabs.get_mut(index)?;
+        tab.pinned = !tab.pinned;
+        let target = pin_target(pinned_count, tab.pinned);
+        self.session_generation += 1;
+        self.move_tab(index, target);
+        Some(target)
+    }
+
+    /// Whether dragging the tab at `from_index` to `to_index` keeps pinned tabs first
+    pub fn can_move_tab(&self, from_index: usize, to_index: usize) -> bool {
+        move_keeps_pins(
+            self.pinned_count(),
+            self.is_tab_pinned(from_index),
+            to_index,
+        )
+    }
+
+    /// Update the cached title for a tab by expanding its title format.
+    /// Returns true if the title changed.
     pub fn update_tab_title(&mut self, index: usize) -> bool {
         if let Some(tab) = self.tabs.get_mut(index) {
+            // A suspended window cannot report its title; keep the cached one
+            if tab.process.is_suspended() {
+                return false;
+            }
             let window_title = tab.process.get_window_title();
             let context = TitleContext {
                 profile_name: &tab.profile_name,
                 working_directory: &tab.working_directory,
                 window_title: &window_title,
             };
-            let new_title = expand_title(&tab.title_format, &context);
-
-            // If title is empty after expansion (e.g., window not ready), fall back to profile name
-            let final_title = if new_title.is_empty() {
-                tab.profile_name.clone()
-            } else {
-                new_title
-            };
+            // While the window title is unavailable, fall back along the configured chain
+            let final_title =
+                expand_title_with_fallback(&tab.title_format, &self.title_fallback, &context);
 
             if final_title != tab.cached_title {
                 tab.cached_title = final_title;
@@ -437,12 +1782,32 @@ impl TabManager {
         self.update_tab_title(self.selected_index)
     }
 
+    /// Whether the selection changed less than `window` before `now`
+    pub fn switched_within(&self, window: Duration, now: Instant) -> bool {
+        self.switched_at
+            .is_some_and(|at| now.saturating_duration_since(at) < window)
+    }
+
     /// Get the profile index for a tab
-    #[allow(dead_code)]
     pub fn get_tab_profile_index(&self, index: usize) -> Option<usize> {
         self.tabs.get(index).map(|tab| tab.profile_index)
     }
 
+    /// The tabs in `range` grouped by profile name, for the overflow popup's
+    /// sections. Groups come in the order of their first tab, and the tabs of a
+    /// group keep their order.
+    pub fn group_by_profile(&self, range: Range<usize>) -> Vec<(String, Vec<usize>)> {
+        let mut groups: Vec<(String, Vec<usize>)> = Vec::new();
+        for index in range.filter(|&i| i < self.tabs.len()) {
+            let name = &self.tabs[index].profile_name;
+            match groups.iter_mut().find(|(group, _)| group == name) {
+                Some((_, tabs)) => tabs.push(index),
+                None => groups.push((name.clone(), vec![index])),
+            }
+        }
+        groups
+    }
+
     /// Find the first tab with the given profile index.
     /// Returns the tab index if found, None otherwise.
     pub fn find_tab_by_profile_index(&self, profile_index: usize) -> Option<usize> {
@@ -451,13 +1816,65 @@ impl TabManager {
             .position(|tab| tab.profile_index == profile_index)
     }
 
+    /// Find a tab that uses a profile in the given working directory
+    pub fn find_tab_by_directory(&self, profile_index: usize, directory: &Path) -> Option<usize> {
+        self.tabs.iter().position(|tab| {
+            tab.profile_index == profile_index && same_directory(&tab.working_directory, directory)
+        })
+    }
+
+    /// Whether opening another tab of `profile_index` would exceed `max_tabs` tabs
+    /// in all or `max_instances` tabs of the profile (None: no limit)
+    pub fn tab_limit_reached(
+        &self,
+        profile_index: usize,
+        max_tabs: Option<usize>,
+        max_instances: Option<usize>,
+    ) -> bool {
+        let instances = || {
+            self.tabs
+                .iter()
+                .filter(|tab| tab.profile_index == profile_index)
+                .count()
+        };
+        max_tabs.is_some_and(|max| self.tabs.len() >= max)
+            || max_instances.is_some_and(|max| instances() >= max)
+    }
+
+    /// Find the tab whose working directory contains `path` (the deepest one if
+    /// several do), among the tabs of `profile_index` if given
+    pub fn find_tab_containing(&self, path: &Path, profile_index: Option<usize>) -> Option<usize> {
+        self.tabs
+            .iter()
+            .enumerate()
+            .filter(|(_, tab)| profile_index.is_none_or(|index| tab.profile_index == index))
+            .filter(|(_, tab)| directory_contains(&tab.working_directory, path))
+            .max_by_key(|(_, tab)| tab.working_directory.components().count())
+            .map(|(index, _)| index)
+    }
+
+    /// Have the tab's nvim edit `path` over RPC. Returns false if the tab has no
+    /// RPC address (not launched yet) or nvim could not be reached.
+    pub fn edit_in_tab(&self, index: usize, path: &Path) -> bool {
+        let Some(tab) = self.tabs.get(index) else {
+            return false;
+        };
+        match tab.process.request_edit(path) {
+            None => false,
+            Some(Ok(())) => true,
+            Some(Err(e)) => {
+                eprintln!("Failed to open {} in tab {}: {}", path.display(), index, e);
+                false
+            }
+        }
+    }
+
     /// Get the icon filename for a tab
     pub fn get_tab_icon(&self, index: usize) -> Option<&str> {
-        self.tabs.get(index).map(|tab| tab.profile_icon.as_str())
+        self.tabs.get(index).map(|tab| tab.icon())
     }
 
     /// Get the working directory for a tab (for tooltip display)
-    #[allow(dead_code)]
     pub fn get_tab_working_directory(&self, index: usize) -> Option<&std::path::Path> {
         self.tabs
             .get(index)
@@ -465,7 +1882,7 @@ impl TabManager {
     }
 
     /// Iterate over all tabs with their indices
-    pub fn iter(&self) -> impl Iterator<Item = (usize, &Tab)> {
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Tab<P>)> {
         self.tabs.iter().enumerate()
     }
 
@@ -491,9 +1908,14 @@ impl TabManager {
         }
 
         // Just remove the tab - don't call terminate() since process already exited
-        self.tabs.remove(index);
+        let tab = self.tabs.remove(index);
+        if tab.placed {
+            tab.run_hook(HookEvent::Close);
+        }
+        self.session_generation += 1;
 
         if self.tabs.is_empty() {
+            self.end_close_sequence();
             return true;
         }
 
@@ -503,6 +1925,7 @@ impl TabManager {
         } else if self.selected_index > index {
             self.selected_index -= 1;
         }
+        self.update_animation_pauses();
 
         false
     }
@@ -511,35 +1934,197 @@ impl TabManager {
     /// This is called after a config reload to update tab titles, icons, etc.
     /// Tabs whose profile_index no longer exists in the config keep their current values.
     pub fn refresh_profiles(&mut self, profiles: &[Profile]) {
+        // Logos may have been added or removed in the meantime
+        self.logos.clear();
         for tab in &mut self.tabs {
             if let Some(profile) = profiles.get(tab.profile_index) {
+                let profile = &profile.with_directory_overrides(&tab.working_directory);
                 // Update profile-derived fields
                 tab.profile_name = profile.name.clone();
                 tab.profile_icon = profile.icon.clone();
+                tab.icon_override = project_icon(
+                    self.project_icons,
+                    profile,
+                    &tab.working_directory,
+                    &mut self.logos,
+                );
                 tab.title_format = profile.title.clone();
                 // Note: working_directory is intentionally NOT updated since it was
                 // the directory used when the tab was created (affects %w token)
 
-                // Re-expand the title with the new format
+                // Re-expand the title with the new format (suspended tabs keep their
+                // cached title until they are resumed and polled again)
+                if tab.process.is_suspended() {
+                    continue;
+                }
                 let window_title = tab.process.get_window_title();
                 let context = TitleContext {
                     profile_name: &tab.profile_name,
                     working_directory: &tab.working_directory,
                     window_title: &window_title,
                 };
-                let new_title = expand_title(&tab.title_format, &context);
-                tab.cached_title = if new_title.is_empty() {
-                    tab.profile_name.clone()
-                } else {
-                    new_title
-                };
+                tab.cached_title =
+                    expand_title_with_fallback(&tab.title_format, &self.title_fallback, &context);
             }
             // If profile_index is out of bounds, keep current values (profile was removed)
         }
     }
 }
 
-impl Default for TabManager {
+/// A path the way Windows compares them: lowercase, backslashes only and without
+/// trailing separators
+fn normalize_path(path: &Path) -> String {
+    path.to_string_lossy()
+        .replace('/', "\\")
+        .trim_end_matches('\\')
+        .to_lowercase()
+}
+
+/// Compare two directories the way Windows does: case-insensitive, with either
+/// slash style and ignoring trailing separators
+fn same_directory(a: &Path, b: &Path) -> bool {
+    normalize_path(a) == normalize_path(b)
+}
+
+/// Whether `path` is `dir` or lies below it (compared like `same_directory`)
+fn directory_contains(dir: &Path, path: &Path) -> bool {
+    let (dir, path) = (normalize_path(dir), normalize_path(path));
+    path.strip_prefix(&dir)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('\\'))
+}
+
+/// Startup progress of a batch of `batch_size` tabs with `pending` still starting
+fn batch_progress(batch_size: usize, pending: usize) -> Option<(usize, usize)> {
+    if pending == 0 || batch_size < 2 {
+        return None;
+    }
+    let total = batch_size.max(pending);
+    Some((total - pending, total))
+}
+
+/// Maximum number of entries kept in the tab navigation history
+const MAX_TAB_HISTORY: usize = 100;
+
+/// Browser-style navigation history of selected tab IDs
+#[derive(Debug, Default)]
+struct TabHistory {
+    entries: Vec<usize>,
+    /// Index of the current entry
+    position: usize,
+}
+
+impl TabHistory {
+    /// Record that the tab `id` was selected. Going somewhere new drops the forward entries.
+    fn visit(&mut self, id: usize) {
+        if self.entries.get(self.position) == Some(&id) {
+            return;
+        }
+        if !self.entries.is_empty() {
+            self.entries.truncate(self.position + 1);
+        }
+        self.entries.push(id);
+        if self.entries.len() > MAX_TAB_HISTORY {
+            self.entries.remove(0);
+        }
+        self.position = self.entries.len() - 1;
+    }
+
+    /// Follow a tab whose ID changed from `old` to `new`
+    fn replace(&mut self, old: usize, new: usize) {
+        for entry in self.entries.iter_mut().filter(|entry| **entry == old) {
+            *entry = new;
+        }
+    }
+
+    /// Step back to the closest earlier entry that still exists and isn't `current`
+    fn back(&mut self, current: usize, exists: impl Fn(usize) -> bool) -> Option<usize> {
+        let found = (0..self.position)
+            .rev()
+            .find(|&i| self.entries[i] != current && exists(self.entries[i]))?;
+        self.position = found;
+        Some(self.entries[found])
+    }
+
+    /// Step forward to the closest later entry that still exists and isn't `current`
+    fn forward(&mut self, current: usize, exists: impl Fn(usize) -> bool) -> Option<usize> {
+        let found = (self.position + 1..self.entries.len())
+            .find(|&i| self.entries[i] != current && exists(self.entries[i]))?;
+        self.position = found;
+        Some(self.entries[found])
+    }
+}
+
+/// Index a tab moves to when it is pinned (`pinning`) or unpinned, given the number
+/// of pinned tabs before the change
+fn pin_target(pinned_count: usize, pinning: bool) -> usize {
+    if pinning {
+        pinned_count
+    } else {
+        pinned_count.saturating_sub(1)
+    }
+}
+
+/// Whether moving a (pinned or unpinned) tab to `to_index` keeps the pinned tabs first
+fn move_keeps_pins(pinned_count: usize, pinned: bool, to_index: usize) -> bool {
+    if pinned {
+        to_index < pinned_count
+    } else {
+        to_index >= pinned_count
+    }
+}
+
+/// IDs of the tabs closed by "Close other tabs" on the tab at `index`, in tab order
+fn close_others_targets(ids: &[usize], index: usize) -> Vec<usize> {
+    if index >= ids.len() {
+        return Vec::new();
+    }
+    ids.iter()
+        .enumerate()
+        .filter(|(i, _)| *i != index)
+        .map(|(_, id)| *id)
+        .collect()
+}
+
+/// IDs of the tabs closed by "Close tabs to the right" on the tab at `index`
+fn close_to_right_targets(ids: &[usize], index: usize) -> Vec<usize> {
+    ids.iter().skip(index + 1).copied().collect()
+}
+
+/// Icon of the project in `working_directory`: its own logo for profiles with
+/// `icon: "auto"`, else the bundled icon of its type when project icons are
+/// enabled and the profile kept the default icon
+fn project_icon(
+    enabled: bool,
+    profile: &Profile,
+    working_directory: &Path,
+    logos: &mut LogoCache,
+) -> Option<String> {
+    if profile.auto_icon
+        && let Some(logo) = logos.logo(working_directory)
+    {
+        return Some(logo.to_string_lossy().to_string());
+    }
+    if !enabled || profile.icon != DEFAULT_ICON {
+        return None;
+    }
+    detect_project_type(working_directory).map(|project| project.icon().to_string())
+}
+
+/// Strength (0.0-1.0) of the attention highlight `elapsed` after a tab asked for
+/// attention; None once the pulses are over. With `reduce_motion` the highlight
+/// stays at full strength for as long as the pulses would last.
+fn pulse_intensity(elapsed: Duration, reduce_motion: bool) -> Option<f32> {
+    if elapsed >= PULSE_PERIOD * PULSE_COUNT {
+        return None;
+    }
+    if reduce_motion {
+        return Some(1.0);
+    }
+    let t = elapsed.as_secs_f32() / PULSE_PERIOD.as_secs_f32();
+    Some((1.0 - (t * std::f32::consts::TAU).cos()) / 2.0)
+}
+
+impl<P: ProcessHandle> Default for TabManager<P> {
     fn default() -> Self {
         Self::new()
     }
@@ -548,18 +2133,900 @@ impl Default for TabManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::cell::Cell;
+
+    /// In-memory process: becomes ready or exits when a test says so, and
+    /// records what the manager asked of it
+    #[derive(Default)]
+    struct FakeProcess {
+        launched: bool,
+        ready: bool,
+        exited: bool,
+        /// Answer WM_CLOSE like a window at a higher integrity level
+        refuses_close: bool,
+        visible: Cell<bool>,
+        close_requests: Cell<usize>,
+        /// Stay open after the next close request, like a cancelled save prompt
+        close_cancelled: Cell<bool>,
+        position_updates: Cell<usize>,
+        /// Last animation pause requested over RPC
+        animations_paused: Cell<Option<bool>>,
+        suspended: Cell<bool>,
+        memory_trimmed: Cell<bool>,
+        /// Report a recreated window on the next revalidation
+        window_recreated: Cell<bool>,
+    }
 
-    // Note: Full tests requiring NeovideProcess cannot be run without Windows,
-    // but we can test the basic TabManager logic with mock data
+    impl ProcessHandle for FakeProcess {
+        type Window = ();
+
+        fn pending() -> Self {
+            Self::default()
+        }
+        fn is_launched(&self) -> bool {
+            self.launched
+        }
+        fn launch(&mut self, _: u32, _: u32, _: (), _: &LaunchOptions) -> Result<()> {
+            self.launched = true;
+            Ok(())
+        }
+        fn is_ready(&self) -> bool {
+            self.ready
+        }
+        fn is_running(&self) -> bool {
+            !self.exited
+        }
+        fn take_window_error(&self) -> Option<String> {
+            None
+        }
+        fn abandon(&mut self) {
+            self.launched = false;
+        }
+        fn terminate(&mut self) -> Result<()> {
+            self.exited = true;
+            Ok(())
+        }
+        fn request_close(&self) -> CloseRequest {
+            if !self.ready {
+                return CloseRequest::NoWindow;
+            }
+            self.close_requests.set(self.close_requests.get() + 1);
+            if self.refuses_close {
+                CloseRequest::Blocked
+            } else {
+                CloseRequest::Sent
+            }
+        }
+        fn take_close_cancelled(&self) -> bool {
+            self.close_cancelled.replace(false)
+        }
+        fn window_handle(&self) -> Option<()> {
+            self.ready.then_some(())
+        }
+        fn activate(&self) {
+            self.visible.set(true);
+        }
+        fn update_position(&self, _: (), _: i32) -> bool {
+            self.position_updates.set(self.position_updates.get() + 1);
+            true
+        }
+        fn show(&self) {
+            self.visible.set(true);
+        }
+        fn hide(&self) {
+            self.visible.set(false);
+        }
+        fn bring_to_foreground(&self) {}
+        fn set_alt_tab_visible(&self, _: bool) {}
+        fn set_topmost(&self, _: bool) {}
+        fn track_extra_windows(&self) {}
+        fn revalidate_window(&self) -> bool {
+            self.window_recreated.replace(false)
+        }
+        fn get_window_title(&self) -> String {
+            String::new()
+        }
+        fn neovide_pid(&self) -> Option<u32> {
+            None
+        }
+        fn is_suspended(&self) -> bool {
+            self.suspended.get()
+        }
+        fn suspend(&self) -> bool {
+            self.ready && !self.suspended.replace(true)
+        }
+        fn resume(&self) {
+            self.suspended.set(false);
+        }
+        fn trim_memory(&self) {
+            self.memory_trimmed.set(true);
+        }
+        fn is_hung(&self) -> bool {
+            false
+        }
+        fn usage_sample(&self, _processes: &ProcessTable) -> Option<UsageSample> {
+            None
+        }
+        fn has_rpc(&self) -> bool {
+            true
+        }
+        fn request_animations_paused(&self, paused: bool) -> Option<Result<()>> {
+            self.animations_paused.set(Some(paused));
+            Some(Ok(()))
+        }
+        fn request_edit(&self, _: &Path) -> Option<Result<()>> {
+            None
+        }
+        fn output(&self) -> Option<OutputLog> {
+            None
+        }
+    }
+
+    /// A manager with `count` tabs whose windows have appeared
+    fn ready_tabs(count: usize) -> TabManager<FakeProcess> {
+        let mut manager = TabManager::<FakeProcess>::new();
+        let profile = Profile::default_profile();
+        for _ in 0..count {
+            manager.create_tab(800, 600, (), &profile, 0).unwrap();
+        }
+        for tab in &mut manager.tabs {
+            tab.process.ready = true;
+        }
+        manager.place_ready_windows((), 0, true);
+        manager
+    }
+
+    /// Let the tab at `index` exit and handle it like the poll timer does
+    fn exit_tab(manager: &mut TabManager<FakeProcess>, index: usize) {
+        manager.tabs[index].process.exited = true;
+        for index in manager.find_exited_tabs() {
+            manager.remove_exited_tab(index);
+        }
+        manager.continue_close_sequence();
+    }
+
+    fn close_requests(manager: &TabManager<FakeProcess>) -> Vec<usize> {
+        manager
+            .tabs
+            .iter()
+            .map(|tab| tab.process.close_requests.get())
+            .collect()
+    }
 
     #[test]
     fn test_tab_manager_new() {
-        let manager = TabManager::new();
+        let manager = TabManager::<FakeProcess>::new();
         assert!(manager.is_empty());
         assert_eq!(manager.count(), 0);
         assert_eq!(manager.selected_index(), 0);
     }
 
+    #[test]
+    fn test_max_concurrent_spawns_is_at_least_one() {
+        let mut manager = TabManager::<FakeProcess>::new();
+        assert_eq!(manager.max_concurrent_spawns, DEFAULT_MAX_CONCURRENT_SPAWNS);
+        manager.set_max_concurrent_spawns(0);
+        assert_eq!(manager.max_concurrent_spawns, 1);
+        assert!(!manager.has_queued_spawns());
+    }
+
+    #[test]
+    fn test_select_tab() {
+        let mut manager = ready_tabs(3);
+        // New tabs are selected, and only the selected window is shown
+        assert_eq!(manager.selected_index(), 2);
+        let visible: Vec<bool> = manager
+            .tabs
+            .iter()
+            .map(|t| t.process.visible.get())
+            .collect();
+        assert_eq!(visible, [false, false, true]);
+
+        let second = Duration::from_secs(1);
+        assert!(!manager.switched_within(second, Instant::now()));
+        assert!(manager.select_tab(0));
+        assert!(!manager.select_tab(0));
+        assert!(!manager.select_tab(3));
+        assert_eq!(manager.selected_index(), 0);
+        assert!(manager.switched_within(second, Instant::now()));
+        assert!(!manager.switched_within(second, Instant::now() + second));
+
+        assert!(manager.history_back());
+        assert_eq!(manager.selected_index(), 2);
+        assert!(manager.history_forward());
+        assert_eq!(manager.selected_index(), 0);
+    }
+
+    #[test]
+    fn test_cycled_index() {
+        assert_eq!(TabManager::<FakeProcess>::new().cycled_index(0, true), None);
+        let manager = ready_tabs(3);
+        assert_eq!(manager.cycled_index(0, true), Some(1));
+        assert_eq!(manager.cycled_index(2, true), Some(0));
+        assert_eq!(manager.cycled_index(0, false), Some(2));
+        assert_eq!(manager.cycled_index(1, false), Some(0));
+        // A stale index (tabs closed meanwhile) counts as the last tab
+        assert_eq!(manager.cycled_index(7, true), Some(0));
+    }
+
+    #[test]
+    fn test_update_selected_position() {
+        let mut manager = ready_tabs(3);
+        manager.select_tab(1);
+        for tab in &manager.tabs {
+            tab.process.position_updates.set(0);
+        }
+        assert!(manager.update_selected_position((), 0));
+        // Hidden windows are left where they are
+        let updates: Vec<usize> = manager
+            .tabs
+            .iter()
+            .map(|t| t.process.position_updates.get())
+            .collect();
+        assert_eq!(updates, [0, 1, 0]);
+    }
+
+    #[test]
+    fn test_hidden_tabs_move_when_shown() {
+        let mut manager = ready_tabs(3);
+        let updates = |m: &TabManager<FakeProcess>| -> Vec<usize> {
+            m.tabs
+                .iter()
+                .map(|t| t.process.position_updates.get())
+                .collect()
+        };
+        // Only the selected window is moved when the windows appear
+        assert_eq!(updates(&manager), [0, 0, 1]);
+
+        manager.select_tab(0);
+        manager.activate_selected((), 0);
+        manager.activate_selected((), 0);
+        assert_eq!(updates(&manager), [1, 0, 1]);
+
+        // The wrapper moved: tab 2 is moved once it is selected again
+        manager.update_selected_position((), 0);
+        assert_eq!(updates(&manager), [2, 0, 1]);
+        manager.select_tab(2);
+        manager.activate_selected((), 0);
+        assert_eq!(updates(&manager), [2, 0, 2]);
+    }
+
+    #[test]
+    fn test_revalidate_windows() {
+        let mut manager = ready_tabs(3);
+        manager.select_tab(0);
+        manager.activate_selected((), 0);
+        assert!(!manager.revalidate_windows());
+
+        // Every window is moved into place again when shown after sleep
+        manager.tabs[1].process.window_recreated.set(true);
+        manager.tabs[1].process.position_updates.set(0);
+        assert!(manager.revalidate_windows());
+        assert!(manager.tabs.iter().all(|tab| tab.position_dirty.get()));
+        manager.select_tab(1);
+        manager.activate_selected((), 0);
+        assert_eq!(manager.tabs[1].process.position_updates.get(), 1);
+    }
+
+    #[test]
+    fn test_windows_stay_hidden_while_minimized() {
+        let mut manager = TabManager::<FakeProcess>::new();
+        let profile = Profile::default_profile();
+        manager.create_tab(800, 600, (), &profile, 0).unwrap();
+        manager.tabs[0].process.ready = true;
+
+        // Placed while the wrapper is minimized: hidden, moved once shown
+        assert!(manager.place_ready_windows((), 0, false));
+        assert!(!manager.tabs[0].process.visible.get());
+        assert_eq!(manager.tabs[0].process.position_updates.get(), 0);
+
+        manager.activate_selected((), 0);
+        assert!(manager.tabs[0].process.visible.get());
+        assert_eq!(manager.tabs[0].process.position_updates.get(), 1);
+    }
+
+    #[test]
+    fn test_profile_startup_files() {
+        let mut manager = TabManager::<FakeProcess>::new();
+        let mut profile = Profile::default_profile();
+        profile.open = vec![PathBuf::from("TODO.md")];
+        profile.nvim_cmd = Some("Telescope find_files".to_string());
+
+        manager.create_tab(800, 600, (), &profile, 0).unwrap();
+        assert_eq!(manager.tabs[0].files, [PathBuf::from("TODO.md")]);
+        assert_eq!(
+            manager.tabs[0].nvim_cmd.as_deref(),
+            Some("Telescope find_files")
+        );
+
+        // A tab opened for a file opens just that file
+        let file = PathBuf::from(r"C:\src\main.rs");
+        manager
+            .create_tab_in_directory(
+                800,
+                600,
+                (),
+                &profile,
+                0,
+                Path::new(r"C:\src"),
+                std::slice::from_ref(&file),
+            )
+            .unwrap();
+        assert_eq!(manager.tabs[1].files, [file]);
+        assert_eq!(manager.tabs[1].nvim_cmd, None);
+    }
+
+    #[test]
+    fn test_spawn_queue() {
+        let mut manager = TabManager::<FakeProcess>::new();
+        manager.set_max_concurrent_spawns(1);
+        let profile = Profile::default_profile();
+        for _ in 0..3 {
+            manager.create_tab(800, 600, (), &profile, 0).unwrap();
+        }
+        let states = |m: &TabManager<FakeProcess>| -> Vec<TabState> {
+            (0..m.count()).filter_map(|i| m.tab_state(i)).collect()
+        };
+        assert_eq!(
+            states(&manager),
+            [TabState::Starting, TabState::Queued, TabState::Queued]
+        );
+        assert_eq!(manager.startup_progress(), Some((0, 3)));
+
+        manager.tabs[0].process.ready = true;
+        manager.launch_queued(800, 600, ());
+        assert_eq!(
+            states(&manager),
+            [TabState::Ready, TabState::Starting, TabState::Queued]
+        );
+        assert_eq!(manager.startup_progress(), Some((1, 3)));
+
+        // A tab closed while queued is skipped
+        manager.close_tab(2);
+        manager.tabs[1].process.ready = true;
+        manager.launch_queued(800, 600, ());
+        assert!(!manager.has_queued_spawns());
+        assert_eq!(states(&manager), [TabState::Ready, TabState::Ready]);
+    }
+
+    #[test]
+    fn test_close_all_one_at_a_time() {
+        let mut manager = ready_tabs(3);
+        manager.select_tab(1);
+        manager.request_close_all();
+        // Only the visible tab is asked; the others wait their turn
+        assert_eq!(close_requests(&manager), [0, 1, 0]);
+        assert!(manager.has_pending_close());
+
+        exit_tab(&mut manager, 1);
+        assert_eq!(manager.selected_index(), 1);
+        assert_eq!(close_requests(&manager), [0, 1]);
+        assert!(manager.tabs[1].process.visible.get());
+
+        exit_tab(&mut manager, 1);
+        assert_eq!(close_requests(&manager), [1]);
+        exit_tab(&mut manager, 0);
+        assert!(manager.is_empty());
+    }
+
+    #[test]
+    fn test_close_without_window_terminates() {
+        let mut manager = ready_tabs(2);
+        manager.tabs[1].process.ready = false;
+        // Nothing to ask: the tab is closed right away
+        assert!(!manager.request_close_tab(1));
+        assert_eq!(manager.count(), 1);
+        assert!(manager.request_close_tab(0));
+        assert_eq!(manager.count(), 1);
+    }
+
+    #[test]
+    fn test_close_others() {
+        let mut manager = ready_tabs(4);
+        manager.request_close_others(1);
+        // Closed left to right, each shown and selected in turn
+        assert_eq!(close_requests(&manager), [1, 0, 0, 0]);
+        assert_eq!(manager.selected_index(), 0);
+
+        exit_tab(&mut manager, 0);
+        assert_eq!(close_requests(&manager), [0, 1, 0]);
+        exit_tab(&mut manager, 1);
+        assert_eq!(close_requests(&manager), [0, 1]);
+        exit_tab(&mut manager, 1);
+        // The kept tab is selected again
+        assert_eq!(manager.count(), 1);
+        assert_eq!(manager.selected_index(), 0);
+        assert!(!manager.has_pending_close());
+    }
+
+    #[test]
+    fn test_blocked_close() {
+        let mut manager = ready_tabs(2);
+        manager.tabs[1].process.refuses_close = true;
+        let blocked_id = manager.tabs[1].id;
+        manager.request_close_all();
+        assert_eq!(manager.take_blocked_closes(), [blocked_id]);
+        assert!(manager.take_blocked_closes().is_empty());
+
+        // Keeping the tab open stops closing all tabs
+        manager.cancel_close(blocked_id);
+        assert!(!manager.has_pending_close());
+        assert_eq!(manager.count(), 2);
+    }
+
+    #[test]
+    fn test_close_states() {
+        let mut manager = ready_tabs(3);
+        let states = |m: &TabManager<FakeProcess>| -> Vec<CloseState> {
+            m.tabs.iter().map(|tab| tab.close_state).collect()
+        };
+        manager.request_close_tab(0);
+        manager.request_close_all();
+        assert_eq!(
+            states(&manager),
+            [
+                CloseState::Requested,
+                CloseState::Queued,
+                CloseState::Requested
+            ]
+        );
+        // A tab that was already asked is not asked again
+        assert_eq!(close_requests(&manager), [1, 0, 1]);
+        manager.continue_close_sequence();
+        assert_eq!(close_requests(&manager), [1, 0, 1]);
+
+        // The queued tab's turn comes once the tab the sequence asked has exited
+        exit_tab(&mut manager, 0);
+        assert_eq!(close_requests(&manager), [0, 1]);
+        exit_tab(&mut manager, 1);
+        assert_eq!(close_requests(&manager), [1]);
+    }
+
+    #[test]
+    fn test_hibernate_least_recently_used() {
+        let mut manager = ready_tabs(4);
+        manager.select_tab(0);
+        let start = Instant::now();
+        for (tab, (memory_mb, idle_secs)) in
+            manager
+                .tabs
+                .iter_mut()
+                .zip([(100, 0), (200, 30), (300, 10), (400, 20)])
+        {
+            let sample = |memory_mb: u64| UsageSample {
+                memory_bytes: memory_mb * 1024 * 1024,
+                cpu_time: Duration::ZERO,
+            };
+            tab.usage.record(sample(memory_mb), start, 1);
+            tab.usage.record(sample(memory_mb), start, 1);
+            tab.last_active = start - Duration::from_secs(idle_secs);
+            // Past the spawn queue
+            tab.process.launched = true;
+        }
+        assert_eq!(manager.total_memory(), 1000 * 1024 * 1024);
+
+        // Tab 1 was used longest ago, then tab 3
+        assert_eq!(manager.hibernate_least_recently_used(300 * 1024 * 1024), 2);
+        let hibernated: Vec<bool> = manager
+            .tabs
+            .iter()
+            .map(|tab| tab.process.is_suspended() && tab.process.memory_trimmed.get())
+            .collect();
+        assert_eq!(hibernated, [false, true, false, true]);
+
+        // The selected tab is never hibernated
+        assert_eq!(manager.hibernate_least_recently_used(u64::MAX), 1);
+        assert!(!manager.is_tab_suspended(0));
+        assert_eq!(manager.hibernate_least_recently_used(u64::MAX), 0);
+    }
+
+    #[test]
+    fn test_tab_limit_reached() {
+        let mut manager = TabManager::<FakeProcess>::new();
+        let profile = Profile::default_profile();
+        manager.create_tab(800, 600, (), &profile, 0).unwrap();
+        manager.create_tab(800, 600, (), &profile, 1).unwrap();
+        manager.create_tab(800, 600, (), &profile, 1).unwrap();
+
+        assert!(!manager.tab_limit_reached(0, None, None));
+        assert!(manager.tab_limit_reached(0, Some(3), None));
+        assert!(!manager.tab_limit_reached(0, Some(4), None));
+        assert!(manager.tab_limit_reached(1, None, Some(2)));
+        assert!(!manager.tab_limit_reached(0, None, Some(2)));
+        assert!(!manager.tab_limit_reached(2, Some(4), Some(1)));
+    }
+
+    #[test]
+    fn test_group_by_profile() {
+        let mut manager = TabManager::<FakeProcess>::new();
+        let default = Profile::default_profile();
+        let work = Profile {
+            name: "Work".to_string(),
+            ..Profile::default_profile()
+        };
+        for profile in [&default, &work, &default, &work, &work] {
+            manager.create_tab(800, 600, (), profile, 0).unwrap();
+        }
+
+        assert_eq!(
+            manager.group_by_profile(1..5),
+            [
+                ("Work".to_string(), vec![1, 3, 4]),
+                ("Default".to_string(), vec![2]),
+            ]
+        );
+        assert_eq!(
+            manager.group_by_profile(4..9),
+            [("Work".to_string(), vec![4])]
+        );
+        assert!(manager.group_by_profile(5..5).is_empty());
+    }
+
+    #[test]
+    fn test_tab_opened_during_close_all() {
+        let mut manager = ready_tabs(1);
+        manager.request_close_all();
+        manager
+            .create_tab(800, 600, (), &Profile::default_profile(), 0)
+            .unwrap();
+        assert_eq!(manager.tabs[1].close_state, CloseState::Queued);
+        manager.tabs[1].process.ready = true;
+
+        exit_tab(&mut manager, 0);
+        assert_eq!(close_requests(&manager), [1]);
+        exit_tab(&mut manager, 0);
+        assert!(manager.is_empty());
+
+        // Tabs opened once everything is closed (the next session) stay open
+        manager
+            .create_tab(800, 600, (), &Profile::default_profile(), 0)
+            .unwrap();
+        assert_eq!(manager.tabs[0].close_state, CloseState::Open);
+        assert!(!manager.has_pending_close());
+    }
+
+    #[test]
+    fn test_abort_close_sequence() {
+        let mut manager = ready_tabs(3);
+        manager.request_close_all();
+        manager.abort_close_sequence();
+        // The asked tab is left to Neovide; the queued ones stay open
+        assert_eq!(manager.tab_state(2), Some(TabState::Closing));
+        assert_eq!(manager.tab_state(0), Some(TabState::Ready));
+        exit_tab(&mut manager, 2);
+        assert_eq!(close_requests(&manager), [0, 0]);
+        assert!(!manager.has_pending_close());
+    }
+
+    #[test]
+    fn test_cancelled_save_prompt_aborts_close_all() {
+        let mut manager = ready_tabs(3);
+        manager.request_close_all();
+        manager.tabs[2].process.close_cancelled.set(true);
+        assert!(manager.handle_cancelled_closes());
+        assert!(!manager.handle_cancelled_closes());
+        // No tab is left closing, and none is asked later
+        assert!(!manager.has_pending_close());
+        assert_eq!(manager.tab_state(0), Some(TabState::Ready));
+        manager.continue_close_sequence();
+        assert_eq!(close_requests(&manager), [0, 0, 1]);
+    }
+
+    #[test]
+    fn test_close_all_replaces_bulk_close() {
+        let mut manager = ready_tabs(3);
+        manager.request_close_to_right(0);
+        manager.request_close_all();
+        // Closing others is ignored while everything closes
+        manager.request_close_others(0);
+        assert_eq!(manager.close_sequence, CloseSequence::All);
+        assert!(
+            manager
+                .tabs
+                .iter()
+                .all(|tab| tab.close_state != CloseState::Open)
+        );
+    }
+
+    #[test]
+    fn test_move_tab_keeps_selection() {
+        let mut manager = ready_tabs(4);
+        let ids = |m: &TabManager<FakeProcess>| -> Vec<usize> { m.tab_ids() };
+        let before = ids(&manager);
+        manager.select_tab(1);
+
+        manager.move_tab(1, 3);
+        assert_eq!(ids(&manager), [before[0], before[2], before[3], before[1]]);
+        assert_eq!(manager.selected_index(), 3);
+
+        // Moving another tab across the selected one shifts it
+        manager.move_tab(0, 3);
+        assert_eq!(manager.selected_index(), 2);
+        manager.move_tab(3, 0);
+        assert_eq!(manager.selected_index(), 3);
+    }
+
+    #[test]
+    fn test_pause_animations_in_background() {
+        let mut manager = ready_tabs(3);
+        let paused = |manager: &TabManager<FakeProcess>| -> Vec<Option<bool>> {
+            manager
+                .tabs
+                .iter()
+                .map(|tab| tab.process.animations_paused.get())
+                .collect()
+        };
+
+        // The selected tab keeps its animations until it goes to the background
+        manager.toggle_pause_animations(2);
+        assert!(manager.is_tab_pausing_animations(2));
+        assert_eq!(paused(&manager), vec![None, None, None]);
+        manager.toggle_pause_animations(0);
+        assert_eq!(paused(&manager), vec![Some(true), None, None]);
+
+        // Selecting a tab turns its animations back on
+        manager.select_tab(0);
+        assert_eq!(paused(&manager), vec![Some(false), None, Some(true)]);
+
+        // Closing the selected tab selects one that may be paused
+        manager.select_tab(1);
+        manager.close_tab(1);
+        assert_eq!(manager.selected_index(), 1);
+        assert_eq!(paused(&manager), vec![Some(true), Some(false)]);
+
+        // Turning the toggle off restores a background tab
+        manager.toggle_pause_animations(0);
+        assert_eq!(paused(&manager), vec![Some(false), Some(false)]);
+    }
+
+    #[test]
+    fn test_pulse_intensity() {
+        assert_eq!(pulse_intensity(Duration::ZERO, false), Some(0.0));
+        let peak = pulse_intensity(PULSE_PERIOD / 2, false).unwrap();
+        assert!((peak - 1.0).abs() < 0.001);
+        assert_eq!(pulse_intensity(PULSE_PERIOD * PULSE_COUNT, false), None);
+        // Without motion the highlight is steady, and ends as the pulses would
+        assert_eq!(pulse_intensity(Duration::ZERO, true), Some(1.0));
+        assert_eq!(pulse_intensity(PULSE_PERIOD, true), Some(1.0));
+        assert_eq!(pulse_intensity(PULSE_PERIOD * PULSE_COUNT, true), None);
+    }
+
+    #[test]
+    fn test_same_directory() {
+        assert!(same_directory(
+            Path::new("C:\\Users\\me\\src"),
+            Path::new("c:/users/me/src/")
+        ));
+        assert!(!same_directory(
+            Path::new("C:\\Users\\me\\src"),
+            Path::new("C:\\Users\\me\\src2")
+        ));
+    }
+
+    #[test]
+    fn test_directory_contains() {
+        let dir = Path::new("C:\\Users\\me\\src");
+        assert!(directory_contains(
+            dir,
+            Path::new("c:/users/me/src/app/main.rs")
+        ));
+        assert!(directory_contains(dir, Path::new("C:\\Users\\me\\src\\")));
+        assert!(!directory_contains(
+            dir,
+            Path::new("C:\\Users\\me\\src2\\main.rs")
+        ));
+        assert!(!directory_contains(dir, Path::new("C:\\Users\\me")));
+    }
+
+    /// Restore `session` into `manager` like the wrapper does, except that the saved
+    /// tabs at the indices in `failing` can't be opened
+    fn restore(manager: &mut TabManager<FakeProcess>, session: &Session, failing: &[usize]) {
+        let profile = Profile::default_profile();
+        let mut restored = Vec::new();
+        for (i, saved) in session.tabs.iter().enumerate() {
+            if failing.contains(&i) {
+                restored.push(None);
+                continue;
+            }
+            let index = manager
+                .create_tab_in_directory(800, 600, (), &profile, 0, &saved.working_directory, &[])
+                .unwrap();
+            manager.restore_tab_state(index, saved);
+            restored.push(Some(index));
+        }
+        manager.finish_session_restore(session, &restored);
+    }
+
+    fn saved_tab(id: usize, pinned: bool) -> SessionTab {
+        SessionTab {
+            profile: "Default".to_string(),
+            working_directory: PathBuf::from(format!("C:\\src\\{}", id)),
+            pinned,
+            id: Some(id),
+            ..Default::default()
+        }
+    }
+
+    fn tab_ids(manager: &TabManager<FakeProcess>) -> Vec<usize> {
+        manager.tabs.iter().map(|tab| tab.id).collect()
+    }
+
+    #[test]
+    fn test_session_restore_keeps_ids() {
+        let session = Session {
+            tabs: vec![
+                saved_tab(3, true),
+                saved_tab(7, false),
+                saved_tab(12, false),
+            ],
+            selected: 2,
+            selected_id: Some(12),
+        };
+        let mut manager = TabManager::<FakeProcess>::new();
+        restore(&mut manager, &session, &[]);
+        assert_eq!(tab_ids(&manager), vec![3, 7, 12]);
+        assert_eq!(manager.selected_index(), 2);
+        assert!(manager.is_tab_pinned(0));
+        // The queued launches follow the new IDs, and new tabs don't reuse them
+        assert!(
+            manager
+                .spawn_queue
+                .iter()
+                .all(|&id| manager.find_tab_by_id(id).is_some())
+        );
+        let profile = Profile::default_profile();
+        let index = manager.create_tab(800, 600, (), &profile, 0).unwrap();
+        assert_eq!(manager.get_tab_id(index), Some(13));
+
+        // Saving again gives the same session back
+        manager.select_tab(2);
+        let snapshot = manager.session_snapshot();
+        assert_eq!(snapshot.selected_id, Some(12));
+        assert_eq!(
+            snapshot.tabs[..3]
+                .iter()
+                .map(|tab| (tab.id, tab.pinned))
+                .collect::<Vec<_>>(),
+            vec![(Some(3), true), (Some(7), false), (Some(12), false)]
+        );
+    }
+
+    #[test]
+    fn test_session_restore_adjusts_indices() {
+        // A pinned tab saved after unpinned ones (e.g. an edited file) moves first;
+        // the rest keep their order
+        let session = Session {
+            tabs: vec![saved_tab(5, false), saved_tab(6, false), saved_tab(2, true)],
+            selected: 1,
+            selected_id: Some(6),
+        };
+        let mut manager = TabManager::<FakeProcess>::new();
+        restore(&mut manager, &session, &[]);
+        assert_eq!(tab_ids(&manager), vec![2, 5, 6]);
+        assert_eq!(manager.selected_index(), 2);
+
+        // The selected tab failed to open: the closest earlier tab is selected
+        let session = Session {
+            tabs: vec![saved_tab(1, true), saved_tab(4, false), saved_tab(9, false)],
+            selected: 2,
+            selected_id: Some(9),
+        };
+        let mut manager = TabManager::<FakeProcess>::new();
+        restore(&mut manager, &session, &[2]);
+        assert_eq!(tab_ids(&manager), vec![1, 4]);
+        assert_eq!(manager.selected_index(), 1);
+
+        // Sessions saved without IDs select by index, shifted past failed tabs
+        let mut session = Session {
+            tabs: vec![
+                saved_tab(1, false),
+                saved_tab(2, false),
+                saved_tab(3, false),
+            ],
+            selected: 2,
+            selected_id: None,
+        };
+        for tab in &mut session.tabs {
+            tab.id = None;
+        }
+        let mut manager = TabManager::<FakeProcess>::new();
+        restore(&mut manager, &session, &[0]);
+        assert_eq!(manager.count(), 2);
+        assert_eq!(manager.selected_index(), 1);
+        assert_eq!(
+            manager.tabs[1].working_directory,
+            PathBuf::from("C:\\src\\3")
+        );
+    }
+
+    #[test]
+    fn test_session_restore_id_in_use() {
+        // A saved ID another tab already has is not taken over
+        let mut manager = ready_tabs(1);
+        let session = Session {
+            tabs: vec![saved_tab(1, false)],
+            selected: 0,
+            selected_id: Some(1),
+        };
+        restore(&mut manager, &session, &[]);
+        assert_eq!(tab_ids(&manager), vec![1, 2]);
+        // The restored tab is selected, not the one that kept the ID
+        assert_eq!(manager.selected_index(), 1);
+    }
+
+    #[test]
+    fn test_batch_progress() {
+        // A single starting tab shows no batch progress
+        assert_eq!(batch_progress(1, 1), None);
+        assert_eq!(batch_progress(5, 0), None);
+        assert_eq!(batch_progress(5, 5), Some((0, 5)));
+        assert_eq!(batch_progress(5, 2), Some((3, 5)));
+        // Never report more pending tabs than the batch holds
+        assert_eq!(batch_progress(2, 3), Some((0, 3)));
+    }
+
+    #[test]
+    fn test_close_targets() {
+        let ids = [4, 7, 9, 12];
+        assert_eq!(close_others_targets(&ids, 1), vec![4, 9, 12]);
+        assert_eq!(close_to_right_targets(&ids, 1), vec![9, 12]);
+        // Nothing to the right of the last tab
+        assert!(close_to_right_targets(&ids, 3).is_empty());
+        assert!(close_others_targets(&ids, 4).is_empty());
+        assert!(close_others_targets(&[4], 0).is_empty());
+    }
+
+    #[test]
+    fn test_pin_target() {
+        // Pinning appends to the pinned tabs, unpinning puts the tab right after them
+        assert_eq!(pin_target(0, true), 0);
+        assert_eq!(pin_target(2, true), 2);
+        assert_eq!(pin_target(2, false), 1);
+        assert_eq!(pin_target(1, false), 0);
+    }
+
+    #[test]
+    fn test_move_keeps_pins() {
+        assert!(move_keeps_pins(2, true, 1));
+        assert!(!move_keeps_pins(2, true, 2));
+        assert!(move_keeps_pins(2, false, 2));
+        assert!(!move_keeps_pins(2, false, 1));
+        assert!(move_keeps_pins(0, false, 0));
+    }
+
+    #[test]
+    fn test_tab_history() {
+        let mut history = TabHistory::default();
+        let all = |_| true;
+        assert_eq!(history.back(1, all), None);
+
+        history.visit(1);
+        history.visit(2);
+        history.visit(3);
+        assert_eq!(history.back(3, all), Some(2));
+        assert_eq!(history.back(2, all), Some(1));
+        assert_eq!(history.back(1, all), None);
+        assert_eq!(history.forward(1, all), Some(2));
+
+        // Selecting another tab drops the forward entries
+        history.visit(4);
+        assert_eq!(history.forward(4, all), None);
+        assert_eq!(history.back(4, all), Some(2));
+    }
+
+    #[test]
+    fn test_tab_history_skips_closed_tabs() {
+        let mut history = TabHistory::default();
+        for id in [1, 2, 1, 3] {
+            history.visit(id);
+        }
+        // Tab 2 was closed; going back from 3 lands on 1, then skips 2 and the duplicate 1
+        let open = |id| id != 2;
+        assert_eq!(history.back(3, open), Some(1));
+        assert_eq!(history.back(1, open), None);
+        assert_eq!(history.forward(1, open), Some(3));
+    }
+
     #[test]
     fn test_drag_state_threshold() {
         let drag = DragState {
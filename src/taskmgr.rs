@@ -0,0 +1,489 @@
+//! "Task manager" popup listing every tab with its memory and CPU usage.
+//!
+//! The popup is a small tool window owned by the wrapper. It only displays a
+//! snapshot of rows pushed by the wrapper (refreshed whenever usage is
+//! sampled); killing a tab is posted back to the wrapper, which owns the tabs.
+
+#![cfg(target_os = "windows")]
+#![allow(unsafe_op_in_unsafe_fn)]
+#![allow(clippy::field_reassign_with_default)]
+
+use windows::Win32::Foundation::{COLORREF, HINSTANCE, HWND, LPARAM, LRESULT, RECT, WPARAM};
+use windows::Win32::Graphics::Gdi::{
+    BeginPaint, CreatePen, CreateSolidBrush, DRAW_TEXT_FORMAT, DT_CENTER, DT_END_ELLIPSIS, DT_LEFT,
+    DT_NOPREFIX, DT_RIGHT, DT_SINGLELINE, DT_VCENTER, DeleteObject, DrawTextW, EndPaint, FillRect,
+    HDC, HGDIOBJ, InvalidateRect, LineTo, MoveToEx, PAINTSTRUCT, PS_SOLID, SelectObject, SetBkMode,
+    SetTextColor, TRANSPARENT,
+};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::Controls::WM_MOUSELEAVE;
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    TME_LEAVE, TRACKMOUSEEVENT, TrackMouseEvent, VK_ESCAPE,
+};
+use windows::Win32::UI::WindowsAndMessaging::*;
+use windows::core::{PCWSTR, w};
+
+use crate::fonts::{self, TextSize};
+use crate::icons::{ICON_SIZE, get_icon_bitmap};
+use crate::usage::{ResourceUsage, format_cpu, format_memory};
+use crate::window::{
+    TAB_CLOSE_HOVER_COLOR, TAB_HOVER_COLOR, TAB_OUTLINE_COLOR, paint_icon, rgb_to_colorref,
+};
+
+/// Posted to the wrapper when a row's Kill button is clicked (wparam = tab ID)
+pub const WM_TASK_MANAGER_KILL: u32 = WM_APP + 8;
+
+/// Posted to the wrapper when the task manager popup was closed
+pub const WM_TASK_MANAGER_CLOSED: u32 = WM_APP + 9;
+
+const TASK_MANAGER_CLASS_NAME: PCWSTR = w!("NeovideTabsTaskManager");
+const TASK_MANAGER_TITLE: PCWSTR = w!("Task Manager - neovide-tabs");
+
+/// Width of the popup's client area
+const TASK_MANAGER_WIDTH: i32 = 440;
+/// Padding around the list
+const PADDING: i32 = 4;
+/// Height of the column header row
+const HEADER_HEIGHT: i32 = 24;
+/// Height of each tab row
+const ROW_HEIGHT: i32 = 28;
+/// Width of the memory column
+const MEMORY_COLUMN_WIDTH: i32 = 80;
+/// Width of the CPU column
+const CPU_COLUMN_WIDTH: i32 = 56;
+/// Size of the Kill button
+const KILL_BUTTON_WIDTH: i32 = 44;
+const KILL_BUTTON_HEIGHT: i32 = 20;
+/// Gap between columns
+const COLUMN_GAP: i32 = 8;
+/// Text color of the column headers
+const HEADER_TEXT_COLOR: u32 = 0x9a9a9a;
+
+/// One tab as shown in the task manager
+#[derive(Debug, Clone)]
+pub struct TaskManagerRow {
+    /// Unique tab ID (indices shift while the popup is open)
+    pub tab_id: usize,
+    pub label: String,
+    /// Icon filename
+    pub icon: String,
+    pub usage: Option<ResourceUsage>,
+    pub is_selected: bool,
+    pub is_suspended: bool,
+    pub is_not_responding: bool,
+}
+
+/// State for the task manager popup window
+struct TaskManagerState {
+    owner: HWND,
+    rows: Vec<TaskManagerRow>,
+    /// Row whose Kill button is hovered
+    hovered_kill: Option<usize>,
+    tracking_mouse: bool,
+    background_color: u32,
+}
+
+/// Register the task manager window class
+pub fn register_class(hinstance: HINSTANCE) -> bool {
+    unsafe {
+        let wc = WNDCLASSW {
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(task_manager_proc),
+            hInstance: hinstance,
+            hCursor: LoadCursorW(None, IDC_ARROW).ok().unwrap_or_default(),
+            lpszClassName: TASK_MANAGER_CLASS_NAME,
+            ..Default::default()
+        };
+        RegisterClassW(&wc) != 0
+    }
+}
+
+/// Open the task manager centered over `owner`
+pub fn open(owner: HWND, rows: Vec<TaskManagerRow>, background_color: u32) -> Option<HWND> {
+    unsafe {
+        let hinstance = GetModuleHandleW(None).ok()?;
+        let (width, height) = window_size(rows.len());
+
+        let mut owner_rect = RECT::default();
+        GetWindowRect(owner, &mut owner_rect).ok()?;
+        let x = (owner_rect.left + owner_rect.right - width) / 2;
+        let y = (owner_rect.top + owner_rect.bottom - height) / 2;
+
+        let state = Box::new(TaskManagerState {
+            owner,
+            rows,
+            hovered_kill: None,
+            tracking_mouse: false,
+            background_color,
+        });
+
+        CreateWindowExW(
+            WS_EX_TOOLWINDOW | WS_EX_TOPMOST,
+            TASK_MANAGER_CLASS_NAME,
+            TASK_MANAGER_TITLE,
+            WS_POPUP | WS_CAPTION | WS_SYSMENU | WS_VISIBLE,
+            x,
+            y,
+            width,
+            height,
+            owner,
+            None,
+            hinstance,
+            Some(Box::into_raw(state) as *const std::ffi::c_void),
+        )
+        .ok()
+    }
+}
+
+/// Replace the rows shown by an open task manager, resizing it to fit
+pub fn set_rows(hwnd: HWND, rows: Vec<TaskManagerRow>) {
+    unsafe {
+        let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut TaskManagerState;
+        if state_ptr.is_null() {
+            return;
+        }
+        let state = &mut *state_ptr;
+        let row_count_changed = state.rows.len() != rows.len();
+        state.rows = rows;
+        if state.hovered_kill.is_some_and(|i| i >= state.rows.len()) {
+            state.hovered_kill = None;
+        }
+
+        if row_count_changed {
+            let (width, height) = window_size(state.rows.len());
+            let _ = SetWindowPos(
+                hwnd,
+                None,
+                0,
+                0,
+                width,
+                height,
+                SWP_NOMOVE | SWP_NOZORDER | SWP_NOACTIVATE,
+            );
+        }
+        let _ = InvalidateRect(hwnd, None, false);
+    }
+}
+
+/// Height of the client area for `row_count` rows
+fn client_height(row_count: usize) -> i32 {
+    PADDING * 2 + HEADER_HEIGHT + row_count as i32 * ROW_HEIGHT
+}
+
+/// Outer window size (including caption and borders) for `row_count` rows
+fn window_size(row_count: usize) -> (i32, i32) {
+    let mut rect = RECT {
+        left: 0,
+        top: 0,
+        right: TASK_MANAGER_WIDTH,
+        bottom: client_height(row_count),
+    };
+    unsafe {
+        let _ = AdjustWindowRectEx(
+            &mut rect,
+            WS_POPUP | WS_CAPTION | WS_SYSMENU,
+            false,
+            WS_EX_TOOLWINDOW | WS_EX_TOPMOST,
+        );
+    }
+    (rect.right - rect.left, rect.bottom - rect.top)
+}
+
+/// Rectangle of a row in client coordinates
+fn row_rect(index: usize, client_width: i32) -> RECT {
+    let top = PADDING + HEADER_HEIGHT + index as i32 * ROW_HEIGHT;
+    RECT {
+        left: PADDING,
+        top,
+        right: client_width - PADDING,
+        bottom: top + ROW_HEIGHT,
+    }
+}
+
+/// Column rectangles within a row (or the header): (label, memory, CPU, Kill button)
+fn column_rects(row: &RECT) -> (RECT, RECT, RECT, RECT) {
+    let kill_left = row.right - PADDING - KILL_BUTTON_WIDTH;
+    let kill_top = (row.top + row.bottom - KILL_BUTTON_HEIGHT) / 2;
+    let kill = RECT {
+        left: kill_left,
+        top: kill_top,
+        right: kill_left + KILL_BUTTON_WIDTH,
+        bottom: kill_top + KILL_BUTTON_HEIGHT,
+    };
+    let cpu = RECT {
+        left: kill_left - COLUMN_GAP - CPU_COLUMN_WIDTH,
+        top: row.top,
+        right: kill_left - COLUMN_GAP,
+        bottom: row.bottom,
+    };
+    let memory = RECT {
+        left: cpu.left - COLUMN_GAP - MEMORY_COLUMN_WIDTH,
+        top: row.top,
+        right: cpu.left - COLUMN_GAP,
+        bottom: row.bottom,
+    };
+    let label = RECT {
+        left: row.left + ICON_SIZE + 8,
+        top: row.top,
+        right: memory.left - COLUMN_GAP,
+        bottom: row.bottom,
+    };
+    (label, memory, cpu, kill)
+}
+
+/// Find the row whose Kill button contains the point
+fn hit_test_kill_button(x: i32, y: i32, row_count: usize, client_width: i32) -> Option<usize> {
+    (0..row_count).find(|&i| {
+        let (_, _, _, kill) = column_rects(&row_rect(i, client_width));
+        x >= kill.left && x < kill.right && y >= kill.top && y < kill.bottom
+    })
+}
+
+/// Draw single-line text into a rectangle
+unsafe fn draw_text(hdc: HDC, text: &str, rect: &RECT, align: DRAW_TEXT_FORMAT) {
+    let mut wide: Vec<u16> = text.encode_utf16().collect();
+    let mut rect = *rect;
+    DrawTextW(
+        hdc,
+        &mut wide,
+        &mut rect,
+        DT_SINGLELINE | DT_VCENTER | DT_NOPREFIX | DT_END_ELLIPSIS | align,
+    );
+}
+
+/// Paint the header and all rows
+#[allow(unused_must_use)]
+unsafe fn paint(hdc: HDC, client: &RECT, state: &TaskManagerState) {
+    let bg_brush = CreateSolidBrush(COLORREF(rgb_to_colorref(state.background_color)));
+    FillRect(hdc, client, bg_brush);
+    DeleteObject(HGDIOBJ(bg_brush.0));
+
+    SetBkMode(hdc, TRANSPARENT);
+    let regular_font = fonts::ui_font(TextSize::Body, 400);
+    let old_font = SelectObject(hdc, HGDIOBJ(regular_font.0));
+
+    // Column headers with a separator line below
+    let header = RECT {
+        left: PADDING,
+        top: PADDING,
+        right: client.right - PADDING,
+        bottom: PADDING + HEADER_HEIGHT,
+    };
+    let (label, memory, cpu, _) = column_rects(&header);
+    SetTextColor(hdc, COLORREF(rgb_to_colorref(HEADER_TEXT_COLOR)));
+    draw_text(hdc, "Tab", &label, DT_LEFT);
+    draw_text(hdc, "Memory", &memory, DT_RIGHT);
+    draw_text(hdc, "CPU", &cpu, DT_RIGHT);
+
+    let line_pen = CreatePen(PS_SOLID, 1, COLORREF(rgb_to_colorref(TAB_OUTLINE_COLOR)));
+    let old_pen = SelectObject(hdc, HGDIOBJ(line_pen.0));
+    MoveToEx(hdc, header.left, header.bottom - 1, None);
+    LineTo(hdc, header.right, header.bottom - 1);
+    SelectObject(hdc, old_pen);
+    DeleteObject(HGDIOBJ(line_pen.0));
+
+    for (i, row) in state.rows.iter().enumerate() {
+        let rect = row_rect(i, client.right);
+        let (label, memory, cpu, kill) = column_rects(&rect);
+
+        // Icon
+        let icon_x = rect.left + 4;
+        let icon_y = (rect.top + rect.bottom - ICON_SIZE) / 2;
+        if let Some(hbitmap) = get_icon_bitmap(&row.icon) {
+            paint_icon(hdc, hbitmap, icon_x, icon_y, ICON_SIZE, ICON_SIZE);
+        }
+
+        // Label (selected tab in bold), memory and CPU
+        SetTextColor(hdc, COLORREF(0x00FFFFFF));
+        let label_text = if row.is_not_responding {
+            format!("{} (not responding)", row.label)
+        } else if row.is_suspended {
+            format!("{} (suspended)", row.label)
+        } else {
+            row.label.clone()
+        };
+        let weight = if row.is_selected { 700 } else { 400 };
+        let label_wide: Vec<u16> = label_text.encode_utf16().collect();
+        fonts::select_font_for_text(hdc, TextSize::Body, weight, &label_wide);
+        draw_text(hdc, &label_text, &label, DT_LEFT);
+        SelectObject(hdc, HGDIOBJ(regular_font.0));
+
+        let (memory_text, cpu_text) = match row.usage {
+            Some(usage) => (
+                format_memory(usage.memory_bytes),
+                format_cpu(usage.cpu_percent),
+            ),
+            None => ("\u{2013}".to_string(), "\u{2013}".to_string()),
+        };
+        draw_text(hdc, &memory_text, &memory, DT_RIGHT);
+        draw_text(hdc, &cpu_text, &cpu, DT_RIGHT);
+
+        // Kill button
+        let kill_color = if state.hovered_kill == Some(i) {
+            TAB_CLOSE_HOVER_COLOR
+        } else {
+            TAB_HOVER_COLOR
+        };
+        let kill_brush = CreateSolidBrush(COLORREF(rgb_to_colorref(kill_color)));
+        FillRect(hdc, &kill, kill_brush);
+        DeleteObject(HGDIOBJ(kill_brush.0));
+        draw_text(hdc, "Kill", &kill, DT_CENTER);
+    }
+
+    SelectObject(hdc, old_font);
+}
+
+/// Window procedure for the task manager popup
+#[allow(unused_must_use)]
+unsafe extern "system" fn task_manager_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match msg {
+        WM_CREATE => {
+            let create_struct = lparam.0 as *const CREATESTRUCTW;
+            if !create_struct.is_null() {
+                let state_ptr = (*create_struct).lpCreateParams as *mut TaskManagerState;
+                SetWindowLongPtrW(hwnd, GWLP_USERDATA, state_ptr as isize);
+            }
+            LRESULT(0)
+        }
+
+        WM_PAINT => {
+            let mut ps = PAINTSTRUCT::default();
+            let hdc = BeginPaint(hwnd, &mut ps);
+            let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut TaskManagerState;
+            if !state_ptr.is_null() {
+                let mut client = RECT::default();
+                GetClientRect(hwnd, &mut client).ok();
+                paint(hdc, &client, &*state_ptr);
+            }
+            EndPaint(hwnd, &ps);
+            LRESULT(0)
+        }
+
+        WM_ERASEBKGND => LRESULT(1),
+
+        WM_MOUSEMOVE => {
+            let x = (lparam.0 & 0xFFFF) as i16 as i32;
+            let y = ((lparam.0 >> 16) & 0xFFFF) as i16 as i32;
+            let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut TaskManagerState;
+            if !state_ptr.is_null() {
+                let state = &mut *state_ptr;
+                let mut client = RECT::default();
+                GetClientRect(hwnd, &mut client).ok();
+
+                let hovered = hit_test_kill_button(x, y, state.rows.len(), client.right);
+                if hovered != state.hovered_kill {
+                    state.hovered_kill = hovered;
+                    InvalidateRect(hwnd, None, false);
+                }
+
+                if !state.tracking_mouse {
+                    let mut tme = TRACKMOUSEEVENT {
+                        cbSize: std::mem::size_of::<TRACKMOUSEEVENT>() as u32,
+                        dwFlags: TME_LEAVE,
+                        hwndTrack: hwnd,
+                        dwHoverTime: 0,
+                    };
+                    TrackMouseEvent(&mut tme);
+                    state.tracking_mouse = true;
+                }
+            }
+            LRESULT(0)
+        }
+
+        WM_MOUSELEAVE => {
+            let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut TaskManagerState;
+            if !state_ptr.is_null() {
+                let state = &mut *state_ptr;
+                state.tracking_mouse = false;
+                if state.hovered_kill.take().is_some() {
+                    InvalidateRect(hwnd, None, false);
+                }
+            }
+            LRESULT(0)
+        }
+
+        WM_LBUTTONDOWN => {
+            let x = (lparam.0 & 0xFFFF) as i16 as i32;
+            let y = ((lparam.0 >> 16) & 0xFFFF) as i16 as i32;
+            let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut TaskManagerState;
+            if !state_ptr.is_null() {
+                let state = &*state_ptr;
+                let mut client = RECT::default();
+                GetClientRect(hwnd, &mut client).ok();
+
+                if let Some(i) = hit_test_kill_button(x, y, state.rows.len(), client.right) {
+                    PostMessageW(
+                        state.owner,
+                        WM_TASK_MANAGER_KILL,
+                        WPARAM(state.rows[i].tab_id),
+                        LPARAM(0),
+                    );
+                }
+            }
+            LRESULT(0)
+        }
+
+        WM_KEYDOWN if wparam.0 == VK_ESCAPE.0 as usize => {
+            DestroyWindow(hwnd);
+            LRESULT(0)
+        }
+
+        WM_DESTROY => {
+            let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut TaskManagerState;
+            if !state_ptr.is_null() {
+                let state = Box::from_raw(state_ptr);
+                SetWindowLongPtrW(hwnd, GWLP_USERDATA, 0);
+                PostMessageW(state.owner, WM_TASK_MANAGER_CLOSED, WPARAM(0), LPARAM(0));
+            }
+            LRESULT(0)
+        }
+
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_height() {
+        assert_eq!(client_height(0), PADDING * 2 + HEADER_HEIGHT);
+        assert_eq!(
+            client_height(3),
+            PADDING * 2 + HEADER_HEIGHT + 3 * ROW_HEIGHT
+        );
+    }
+
+    #[test]
+    fn test_column_rects_do_not_overlap() {
+        let row = row_rect(0, TASK_MANAGER_WIDTH);
+        let (label, memory, cpu, kill) = column_rects(&row);
+        assert!(label.left < label.right);
+        assert!(label.right <= memory.left);
+        assert!(memory.right <= cpu.left);
+        assert!(cpu.right <= kill.left);
+        assert!(kill.right <= row.right);
+        assert!(kill.top >= row.top && kill.bottom <= row.bottom);
+    }
+
+    #[test]
+    fn test_hit_test_kill_button() {
+        let (_, _, _, kill) = column_rects(&row_rect(1, TASK_MANAGER_WIDTH));
+        let cx = (kill.left + kill.right) / 2;
+        let cy = (kill.top + kill.bottom) / 2;
+        assert_eq!(hit_test_kill_button(cx, cy, 3, TASK_MANAGER_WIDTH), Some(1));
+        // Same row, but over the label
+        assert_eq!(
+            hit_test_kill_button(PADDING + 30, cy, 3, TASK_MANAGER_WIDTH),
+            None
+        );
+        // Row that does not exist
+        assert_eq!(hit_test_kill_button(cx, cy, 1, TASK_MANAGER_WIDTH), None);
+    }
+}
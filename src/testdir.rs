@@ -0,0 +1,32 @@
+//! Scratch directories for tests.
+//!
+//! Each test gets its own directory under the system temp dir, removed again when
+//! the guard is dropped, so a failing test doesn't leave it behind.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A fresh, empty directory that is deleted on drop
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Create `neovide-tabs-<name>-<pid>` under the system temp dir, emptying
+    /// whatever an earlier run left there
+    pub fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("neovide-tabs-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,86 @@
+//! Cooperation with tiling window managers (`tiling_wm` config option).
+//!
+//! Tiling window managers lay out every top-level window they see, including the
+//! Neovide windows neovide-tabs keeps inside its wrapper, and then both sides
+//! keep moving them. The Neovide windows have to be ignored by the window
+//! manager; this module words the rules that do so for each window manager and
+//! applies them to a running Komorebi.
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+use std::process::Command;
+
+use crate::config::TilingWm;
+
+/// Executable of the Neovide processes neovide-tabs launches
+const NEOVIDE_EXE: &str = "neovide.exe";
+
+/// Process creation flag that keeps komorebic from opening a console window
+#[cfg(target_os = "windows")]
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+/// Rules that make `wm` leave the Neovide windows alone, in the format of its
+/// config file (for the settings menu's "Copy tiling WM rules"); `window_class`
+/// is the wrapper's class, which the window manager should keep managing. None
+/// while `tiling_wm` is off.
+pub fn ignore_rules(wm: TilingWm, window_class: &str) -> Option<String> {
+    let rules = match wm {
+        TilingWm::Off => return None,
+        TilingWm::Komorebi => format!(
+            "// komorebi.json: neovide-tabs positions its Neovide windows itself\n\
+             \"ignore_rules\": [\n    \
+             {{ \"kind\": \"Exe\", \"id\": \"{NEOVIDE_EXE}\", \"matching_strategy\": \"Equals\" }}\n\
+             ]"
+        ),
+        TilingWm::GlazeWm => format!(
+            "# config.yaml: neovide-tabs positions its Neovide windows itself\n\
+             window_rules:\n  \
+             - commands: ['ignore']\n    \
+             match:\n      \
+             - window_process: {{ equals: '{}' }}",
+            NEOVIDE_EXE.trim_end_matches(".exe")
+        ),
+        TilingWm::Other => format!(
+            "Ignore (don't tile) the windows of the process {NEOVIDE_EXE}; they carry \
+             the window property NEOVIDE_TABS_MANAGED.\n\
+             Tile the neovide-tabs window (window class {window_class}) as usual."
+        ),
+    };
+    Some(rules)
+}
+
+/// Add the ignore rule for the Neovide windows to a running Komorebi with
+/// `komorebic`; does nothing for other window managers. The rule lasts until
+/// Komorebi restarts, so this runs whenever neovide-tabs starts.
+pub fn apply_ignore_rules(wm: TilingWm) {
+    if wm != TilingWm::Komorebi {
+        return;
+    }
+    let mut command = Command::new("komorebic");
+    command.args(["ignore-rule", "exe", NEOVIDE_EXE]);
+    #[cfg(target_os = "windows")]
+    command.creation_flags(CREATE_NO_WINDOW);
+    if let Err(e) = command.spawn() {
+        eprintln!("Tiling: Failed to run komorebic: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignore_rules() {
+        assert_eq!(ignore_rules(TilingWm::Off, "NeovideTabsWindow"), None);
+
+        let komorebi = ignore_rules(TilingWm::Komorebi, "NeovideTabsWindow").unwrap();
+        assert!(komorebi.contains(r#"{ "kind": "Exe", "id": "neovide.exe", "#));
+
+        let glazewm = ignore_rules(TilingWm::GlazeWm, "NeovideTabsWindow").unwrap();
+        assert!(glazewm.contains("- window_process: { equals: 'neovide' }"));
+
+        let other = ignore_rules(TilingWm::Other, "NvimTabs").unwrap();
+        assert!(other.contains("neovide.exe"));
+        assert!(other.contains("window class NvimTabs"));
+    }
+}
@@ -0,0 +1,719 @@
+//! UI Automation provider for the custom-drawn titlebar.
+//!
+//! The tab bar is painted by the wrapper, so screen readers and UIA-based tools
+//! see nothing in it unless the window provides the elements itself. WM_GETOBJECT
+//! returns a fragment root for the window whose children are the tab list (one
+//! item per tab, each with a close button unless the tab is pinned) and the
+//! titlebar buttons.
+//!
+//! Elements only remember what they refer to and read the current layout from the
+//! window on every call, so they never go stale. UI Automation calls them on the
+//! window's thread. Invoking an element posts WM_UIA_INVOKE and the window performs
+//! the action like a click would.
+
+#![cfg(target_os = "windows")]
+// Matching on the UI Automation IDs, which keep their SDK names
+#![allow(non_upper_case_globals)]
+
+use windows::Win32::Foundation::{BOOL, HWND, LPARAM, POINT, RECT, WPARAM};
+use windows::Win32::Graphics::Gdi::{ClientToScreen, ScreenToClient};
+use windows::Win32::System::Com::SAFEARRAY;
+use windows::Win32::System::Ole::{SafeArrayCreateVector, SafeArrayPutElement};
+use windows::Win32::System::Variant::VT_I4;
+use windows::Win32::UI::Accessibility::{
+    IInvokeProvider, IInvokeProvider_Impl, IRawElementProviderFragment,
+    IRawElementProviderFragment_Impl, IRawElementProviderFragmentRoot,
+    IRawElementProviderFragmentRoot_Impl, IRawElementProviderSimple,
+    IRawElementProviderSimple_Impl, ISelectionItemProvider, ISelectionItemProvider_Impl,
+    NavigateDirection, NavigateDirection_FirstChild, NavigateDirection_LastChild,
+    NavigateDirection_NextSibling, NavigateDirection_Parent, NavigateDirection_PreviousSibling,
+    ProviderOptions, ProviderOptions_ServerSideProvider, UIA_AutomationIdPropertyId,
+    UIA_ButtonControlTypeId, UIA_CONTROLTYPE_ID, UIA_ControlTypePropertyId,
+    UIA_E_ELEMENTNOTAVAILABLE, UIA_InvokePatternId, UIA_IsEnabledPropertyId,
+    UIA_IsKeyboardFocusablePropertyId, UIA_IsOffscreenPropertyId, UIA_NamePropertyId,
+    UIA_PATTERN_ID, UIA_PROPERTY_ID, UIA_SelectionItemPatternId, UIA_TabControlTypeId,
+    UIA_TabItemControlTypeId, UiaAppendRuntimeId, UiaHostProviderFromHwnd, UiaRect,
+};
+use windows::Win32::UI::WindowsAndMessaging::{PostMessageW, WM_APP};
+use windows::core::{
+    Error, HRESULT, IUnknown, IUnknownImpl, Interface, Result, VARIANT, implement,
+};
+
+/// Posted to the wrapper when an element is invoked; see `UiaAction::to_params`
+pub const WM_UIA_INVOKE: u32 = WM_APP + 15;
+
+/// Buttons of the titlebar, in the order they appear
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TitlebarButton {
+    /// Opens the list of tabs that don't fit in the tab bar
+    Overflow,
+    /// Opens a tab with the default profile
+    NewTab,
+    /// Opens the profile menu
+    Profiles,
+    Minimize,
+    Maximize,
+    Close,
+}
+
+/// All titlebar buttons, indexed by `TitlebarButton as usize`
+const TITLEBAR_BUTTONS: [TitlebarButton; 6] = [
+    TitlebarButton::Overflow,
+    TitlebarButton::NewTab,
+    TitlebarButton::Profiles,
+    TitlebarButton::Minimize,
+    TitlebarButton::Maximize,
+    TitlebarButton::Close,
+];
+
+impl TitlebarButton {
+    /// Name read by screen readers
+    fn name(self, maximized: bool) -> &'static str {
+        match self {
+            TitlebarButton::Overflow => "More tabs",
+            TitlebarButton::NewTab => "New tab",
+            TitlebarButton::Profiles => "Profiles",
+            TitlebarButton::Minimize => "Minimize",
+            TitlebarButton::Maximize if maximized => "Restore",
+            TitlebarButton::Maximize => "Maximize",
+            TitlebarButton::Close => "Close",
+        }
+    }
+
+    /// Stable identifier for automation scripts
+    fn automation_id(self) -> &'static str {
+        match self {
+            TitlebarButton::Overflow => "OverflowButton",
+            TitlebarButton::NewTab => "NewTabButton",
+            TitlebarButton::Profiles => "ProfilesButton",
+            TitlebarButton::Minimize => "MinimizeButton",
+            TitlebarButton::Maximize => "MaximizeButton",
+            TitlebarButton::Close => "CloseButton",
+        }
+    }
+}
+
+/// A tab as exposed to UI Automation
+#[derive(Debug, Clone)]
+pub struct TabItem {
+    /// Tab ID (stable while the tab is open)
+    pub id: usize,
+    /// Label shown on the tab
+    pub name: String,
+    pub selected: bool,
+    /// Whether the tab has a close button (pinned tabs don't)
+    pub closable: bool,
+    /// Tab rectangle in client coordinates; None if the tab is in the overflow list
+    pub rect: Option<RECT>,
+    /// Close button rectangle in client coordinates, if shown
+    pub close_rect: Option<RECT>,
+}
+
+/// Current layout of the titlebar, provided by the window
+#[derive(Debug, Clone, Default)]
+pub struct TabBarSnapshot {
+    pub tabs: Vec<TabItem>,
+    /// Area of the visible tabs in client coordinates
+    pub tab_list: RECT,
+    /// Shown buttons with their rectangles in client coordinates
+    pub buttons: Vec<(TitlebarButton, RECT)>,
+    /// Whether the window is maximized (names the maximize button)
+    pub maximized: bool,
+}
+
+impl TabBarSnapshot {
+    fn tab(&self, id: usize) -> Option<&TabItem> {
+        self.tabs.iter().find(|tab| tab.id == id)
+    }
+}
+
+/// Element of the titlebar's automation tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Element {
+    /// The window itself
+    Root,
+    /// Container of the tabs
+    TabList,
+    /// A tab (by ID)
+    Tab(usize),
+    /// The close button of a tab (by ID)
+    TabClose(usize),
+    Button(TitlebarButton),
+}
+
+/// Action requested through UI Automation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UiaAction {
+    /// Select the tab with this ID
+    SelectTab(usize),
+    /// Close the tab with this ID
+    CloseTab(usize),
+    /// Press a titlebar button
+    Press(TitlebarButton),
+}
+
+impl UiaAction {
+    /// WPARAM is 0 to select a tab, 1 to close it, or 2 + the button index; LPARAM is
+    /// the tab ID
+    fn to_params(self) -> (WPARAM, LPARAM) {
+        match self {
+            UiaAction::SelectTab(id) => (WPARAM(0), LPARAM(id as isize)),
+            UiaAction::CloseTab(id) => (WPARAM(1), LPARAM(id as isize)),
+            UiaAction::Press(button) => (WPARAM(2 + button as usize), LPARAM(0)),
+        }
+    }
+
+    /// Decode the parameters of WM_UIA_INVOKE
+    pub fn from_params(wparam: WPARAM, lparam: LPARAM) -> Option<Self> {
+        match wparam.0 {
+            0 => Some(UiaAction::SelectTab(lparam.0 as usize)),
+            1 => Some(UiaAction::CloseTab(lparam.0 as usize)),
+            n => TITLEBAR_BUTTONS.get(n - 2).map(|b| UiaAction::Press(*b)),
+        }
+    }
+}
+
+/// Child elements in tree order
+fn children(snapshot: &TabBarSnapshot, element: Element) -> Vec<Element> {
+    match element {
+        Element::Root => std::iter::once(Element::TabList)
+            .chain(snapshot.buttons.iter().map(|(b, _)| Element::Button(*b)))
+            .collect(),
+        Element::TabList => snapshot.tabs.iter().map(|t| Element::Tab(t.id)).collect(),
+        Element::Tab(id) if snapshot.tab(id).is_some_and(|t| t.closable) => {
+            vec![Element::TabClose(id)]
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn parent(element: Element) -> Option<Element> {
+    match element {
+        Element::Root => None,
+        Element::TabList | Element::Button(_) => Some(Element::Root),
+        Element::Tab(_) => Some(Element::TabList),
+        Element::TabClose(id) => Some(Element::Tab(id)),
+    }
+}
+
+/// Whether the element is still part of the titlebar (tabs come and go)
+fn exists(snapshot: &TabBarSnapshot, element: Element) -> bool {
+    match parent(element) {
+        None => true,
+        Some(parent) => exists(snapshot, parent) && children(snapshot, parent).contains(&element),
+    }
+}
+
+/// Element reached by moving in `direction` from `element`
+fn navigate(
+    snapshot: &TabBarSnapshot,
+    element: Element,
+    direction: NavigateDirection,
+) -> Option<Element> {
+    if !exists(snapshot, element) {
+        return None;
+    }
+    match direction {
+        NavigateDirection_Parent => parent(element),
+        NavigateDirection_FirstChild => children(snapshot, element).first().copied(),
+        NavigateDirection_LastChild => children(snapshot, element).last().copied(),
+        NavigateDirection_NextSibling | NavigateDirection_PreviousSibling => {
+            let siblings = children(snapshot, parent(element)?);
+            let position = siblings.iter().position(|e| *e == element)?;
+            let sibling = if direction == NavigateDirection_NextSibling {
+                position.checked_add(1)
+            } else {
+                position.checked_sub(1)
+            };
+            sibling.and_then(|i| siblings.get(i)).copied()
+        }
+        _ => None,
+    }
+}
+
+fn rect_contains(rect: &RECT, point: POINT) -> bool {
+    point.x >= rect.left && point.x < rect.right && point.y >= rect.top && point.y < rect.bottom
+}
+
+/// Innermost element at `point` (client coordinates)
+fn element_at(snapshot: &TabBarSnapshot, point: POINT) -> Option<Element> {
+    for tab in &snapshot.tabs {
+        if tab.close_rect.is_some_and(|r| rect_contains(&r, point)) {
+            return Some(Element::TabClose(tab.id));
+        }
+        if tab.rect.is_some_and(|r| rect_contains(&r, point)) {
+            return Some(Element::Tab(tab.id));
+        }
+    }
+    snapshot
+        .buttons
+        .iter()
+        .find(|(_, rect)| rect_contains(rect, point))
+        .map(|(button, _)| Element::Button(*button))
+}
+
+/// Rectangle of the element in client coordinates; None if it isn't shown
+fn bounds(snapshot: &TabBarSnapshot, element: Element) -> Option<RECT> {
+    match element {
+        Element::Root => None,
+        Element::TabList => Some(snapshot.tab_list),
+        Element::Tab(id) => snapshot.tab(id)?.rect,
+        Element::TabClose(id) => snapshot.tab(id)?.close_rect,
+        Element::Button(button) => snapshot
+            .buttons
+            .iter()
+            .find(|(b, _)| *b == button)
+            .map(|(_, rect)| *rect),
+    }
+}
+
+fn name(snapshot: &TabBarSnapshot, element: Element) -> String {
+    match element {
+        Element::Root => String::new(),
+        Element::TabList => "Tabs".to_string(),
+        Element::Tab(id) => snapshot.tab(id).map(|t| t.name.clone()).unwrap_or_default(),
+        Element::TabClose(id) => match snapshot.tab(id) {
+            Some(tab) => format!("Close {}", tab.name),
+            None => "Close tab".to_string(),
+        },
+        Element::Button(button) => button.name(snapshot.maximized).to_string(),
+    }
+}
+
+fn automation_id(element: Element) -> String {
+    match element {
+        Element::Root => String::new(),
+        Element::TabList => "TabList".to_string(),
+        Element::Tab(id) => format!("Tab{}", id),
+        Element::TabClose(id) => format!("CloseTab{}", id),
+        Element::Button(button) => button.automation_id().to_string(),
+    }
+}
+
+fn control_type(element: Element) -> UIA_CONTROLTYPE_ID {
+    match element {
+        Element::TabList => UIA_TabControlTypeId,
+        Element::Tab(_) => UIA_TabItemControlTypeId,
+        _ => UIA_ButtonControlTypeId,
+    }
+}
+
+/// Runtime ID of an element, unique within the window (the root gets its ID from
+/// the window handle)
+fn runtime_id(element: Element) -> Option<[i32; 3]> {
+    let (kind, id) = match element {
+        Element::Root => return None,
+        Element::TabList => (1, 0),
+        Element::Tab(id) => (2, id),
+        Element::TabClose(id) => (3, id),
+        Element::Button(button) => (4, button as usize),
+    };
+    Some([UiaAppendRuntimeId as i32, kind, id as i32])
+}
+
+/// Provider of the window's fragment root, returned for WM_GETOBJECT
+pub fn root_provider(hwnd: HWND) -> IRawElementProviderSimple {
+    RootProvider { hwnd }.into()
+}
+
+fn fragment(hwnd: HWND, element: Element) -> IRawElementProviderFragment {
+    match element {
+        Element::Root => RootProvider { hwnd }.into(),
+        _ => ItemProvider { hwnd, element }.into(),
+    }
+}
+
+/// Current titlebar layout, or an error once the window is gone
+fn snapshot(hwnd: HWND) -> Result<TabBarSnapshot> {
+    crate::window::tab_bar_snapshot(hwnd).ok_or_else(element_not_available)
+}
+
+fn element_not_available() -> Error {
+    HRESULT(UIA_E_ELEMENTNOTAVAILABLE as i32).into()
+}
+
+/// Returns a null element or value to UI Automation
+fn none<T>() -> Result<T> {
+    Err(Error::empty())
+}
+
+fn to_screen(hwnd: HWND, rect: &RECT) -> UiaRect {
+    let mut origin = POINT::default();
+    unsafe {
+        let _ = ClientToScreen(hwnd, &mut origin);
+    }
+    UiaRect {
+        left: (rect.left + origin.x) as f64,
+        top: (rect.top + origin.y) as f64,
+        width: (rect.right - rect.left) as f64,
+        height: (rect.bottom - rect.top) as f64,
+    }
+}
+
+/// Root of the titlebar's tree; the window provides its name, bounds and runtime ID
+#[implement(
+    IRawElementProviderSimple,
+    IRawElementProviderFragment,
+    IRawElementProviderFragmentRoot
+)]
+struct RootProvider {
+    hwnd: HWND,
+}
+
+impl IRawElementProviderSimple_Impl for RootProvider_Impl {
+    fn ProviderOptions(&self) -> Result<ProviderOptions> {
+        Ok(ProviderOptions_ServerSideProvider)
+    }
+
+    fn GetPatternProvider(&self, _patternid: UIA_PATTERN_ID) -> Result<IUnknown> {
+        none()
+    }
+
+    fn GetPropertyValue(&self, _propertyid: UIA_PROPERTY_ID) -> Result<VARIANT> {
+        Ok(VARIANT::default())
+    }
+
+    fn HostRawElementProvider(&self) -> Result<IRawElementProviderSimple> {
+        unsafe { UiaHostProviderFromHwnd(self.hwnd) }
+    }
+}
+
+impl IRawElementProviderFragment_Impl for RootProvider_Impl {
+    fn Navigate(&self, direction: NavigateDirection) -> Result<IRawElementProviderFragment> {
+        let snapshot = snapshot(self.hwnd)?;
+        match navigate(&snapshot, Element::Root, direction) {
+            Some(element) => Ok(fragment(self.hwnd, element)),
+            None => none(),
+        }
+    }
+
+    fn GetRuntimeId(&self) -> Result<*mut SAFEARRAY> {
+        Ok(std::ptr::null_mut())
+    }
+
+    fn BoundingRectangle(&self) -> Result<UiaRect> {
+        Ok(UiaRect::default())
+    }
+
+    fn GetEmbeddedFragmentRoots(&self) -> Result<*mut SAFEARRAY> {
+        Ok(std::ptr::null_mut())
+    }
+
+    fn SetFocus(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn FragmentRoot(&self) -> Result<IRawElementProviderFragmentRoot> {
+        Ok(self.to_interface())
+    }
+}
+
+impl IRawElementProviderFragmentRoot_Impl for RootProvider_Impl {
+    fn ElementProviderFromPoint(&self, x: f64, y: f64) -> Result<IRawElementProviderFragment> {
+        let snapshot = snapshot(self.hwnd)?;
+        let mut point = POINT {
+            x: x as i32,
+            y: y as i32,
+        };
+        unsafe {
+            let _ = ScreenToClient(self.hwnd, &mut point);
+        }
+        match element_at(&snapshot, point) {
+            Some(element) => Ok(fragment(self.hwnd, element)),
+            None => none(),
+        }
+    }
+
+    fn GetFocus(&self) -> Result<IRawElementProviderFragment> {
+        // The tab bar has no keyboard focus of its own
+        none()
+    }
+}
+
+/// A tab, a tab's close button, the tab list or a titlebar button
+#[implement(
+    IRawElementProviderSimple,
+    IRawElementProviderFragment,
+    IInvokeProvider,
+    ISelectionItemProvider
+)]
+struct ItemProvider {
+    hwnd: HWND,
+    element: Element,
+}
+
+impl ItemProvider {
+    /// Action performed when the element is invoked
+    fn action(&self) -> Option<UiaAction> {
+        match self.element {
+            Element::Tab(id) => Some(UiaAction::SelectTab(id)),
+            Element::TabClose(id) => Some(UiaAction::CloseTab(id)),
+            Element::Button(button) => Some(UiaAction::Press(button)),
+            Element::Root | Element::TabList => None,
+        }
+    }
+
+    /// Post the element's action to the window if the element still exists
+    fn perform(&self) -> Result<()> {
+        let snapshot = snapshot(self.hwnd)?;
+        let action = self.action().filter(|_| exists(&snapshot, self.element));
+        let Some(action) = action else {
+            return Err(element_not_available());
+        };
+        let (wparam, lparam) = action.to_params();
+        unsafe { PostMessageW(self.hwnd, WM_UIA_INVOKE, wparam, lparam) }
+    }
+}
+
+impl IRawElementProviderSimple_Impl for ItemProvider_Impl {
+    fn ProviderOptions(&self) -> Result<ProviderOptions> {
+        Ok(ProviderOptions_ServerSideProvider)
+    }
+
+    fn GetPatternProvider(&self, patternid: UIA_PATTERN_ID) -> Result<IUnknown> {
+        let is_tab = matches!(self.element, Element::Tab(_));
+        if patternid == UIA_InvokePatternId && self.action().is_some() {
+            Ok(self.to_interface::<IInvokeProvider>().into())
+        } else if patternid == UIA_SelectionItemPatternId && is_tab {
+            Ok(self.to_interface::<ISelectionItemProvider>().into())
+        } else {
+            none()
+        }
+    }
+
+    fn GetPropertyValue(&self, propertyid: UIA_PROPERTY_ID) -> Result<VARIANT> {
+        let snapshot = snapshot(self.hwnd)?;
+        let value = match propertyid {
+            UIA_ControlTypePropertyId => VARIANT::from(control_type(self.element).0),
+            UIA_NamePropertyId => VARIANT::from(name(&snapshot, self.element).as_str()),
+            UIA_AutomationIdPropertyId => VARIANT::from(automation_id(self.element).as_str()),
+            UIA_IsEnabledPropertyId => VARIANT::from(true),
+            UIA_IsKeyboardFocusablePropertyId => VARIANT::from(false),
+            UIA_IsOffscreenPropertyId => VARIANT::from(bounds(&snapshot, self.element).is_none()),
+            _ => VARIANT::default(),
+        };
+        Ok(value)
+    }
+
+    fn HostRawElementProvider(&self) -> Result<IRawElementProviderSimple> {
+        none()
+    }
+}
+
+impl IRawElementProviderFragment_Impl for ItemProvider_Impl {
+    fn Navigate(&self, direction: NavigateDirection) -> Result<IRawElementProviderFragment> {
+        let snapshot = snapshot(self.hwnd)?;
+        match navigate(&snapshot, self.element, direction) {
+            Some(element) => Ok(fragment(self.hwnd, element)),
+            None => none(),
+        }
+    }
+
+    fn GetRuntimeId(&self) -> Result<*mut SAFEARRAY> {
+        let Some(id) = runtime_id(self.element) else {
+            return Ok(std::ptr::null_mut());
+        };
+        unsafe {
+            let array = SafeArrayCreateVector(VT_I4, 0, id.len() as u32);
+            for (i, value) in id.iter().enumerate() {
+                SafeArrayPutElement(array, &(i as i32), value as *const i32 as *const _)?;
+            }
+            Ok(array)
+        }
+    }
+
+    fn BoundingRectangle(&self) -> Result<UiaRect> {
+        let snapshot = snapshot(self.hwnd)?;
+        Ok(bounds(&snapshot, self.element)
+            .map(|rect| to_screen(self.hwnd, &rect))
+            .unwrap_or_default())
+    }
+
+    fn GetEmbeddedFragmentRoots(&self) -> Result<*mut SAFEARRAY> {
+        Ok(std::ptr::null_mut())
+    }
+
+    fn SetFocus(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn FragmentRoot(&self) -> Result<IRawElementProviderFragmentRoot> {
+        fragment(self.hwnd, Element::Root).cast()
+    }
+}
+
+impl IInvokeProvider_Impl for ItemProvider_Impl {
+    fn Invoke(&self) -> Result<()> {
+        self.perform()
+    }
+}
+
+impl ISelectionItemProvider_Impl for ItemProvider_Impl {
+    fn Select(&self) -> Result<()> {
+        self.perform()
+    }
+
+    fn AddToSelection(&self) -> Result<()> {
+        // Only one tab can be selected
+        self.perform()
+    }
+
+    fn RemoveFromSelection(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn IsSelected(&self) -> Result<BOOL> {
+        let snapshot = snapshot(self.hwnd)?;
+        let selected = match self.element {
+            Element::Tab(id) => snapshot.tab(id).is_some_and(|t| t.selected),
+            _ => false,
+        };
+        Ok(selected.into())
+    }
+
+    fn SelectionContainer(&self) -> Result<IRawElementProviderSimple> {
+        fragment(self.hwnd, Element::TabList).cast()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(left: i32, right: i32) -> RECT {
+        RECT {
+            left,
+            top: 0,
+            right,
+            bottom: 30,
+        }
+    }
+
+    fn tab(id: usize, rect: Option<RECT>, closable: bool) -> TabItem {
+        TabItem {
+            id,
+            name: format!("tab {}", id),
+            selected: id == 1,
+            closable,
+            rect,
+            close_rect: rect.filter(|_| closable).map(|r| RECT {
+                left: r.right - 10,
+                ..r
+            }),
+        }
+    }
+
+    fn sample() -> TabBarSnapshot {
+        TabBarSnapshot {
+            tabs: vec![
+                tab(1, Some(rect(0, 100)), false),
+                tab(4, Some(rect(100, 200)), true),
+                tab(7, None, true),
+            ],
+            tab_list: rect(0, 200),
+            buttons: vec![
+                (TitlebarButton::NewTab, rect(200, 230)),
+                (TitlebarButton::Close, rect(400, 450)),
+            ],
+            maximized: false,
+        }
+    }
+
+    #[test]
+    fn test_navigate() {
+        let s = sample();
+        let first = NavigateDirection_FirstChild;
+        assert_eq!(navigate(&s, Element::Root, first), Some(Element::TabList));
+        assert_eq!(
+            navigate(&s, Element::Root, NavigateDirection_LastChild),
+            Some(Element::Button(TitlebarButton::Close))
+        );
+        assert_eq!(navigate(&s, Element::TabList, first), Some(Element::Tab(1)));
+        assert_eq!(
+            navigate(&s, Element::Tab(1), NavigateDirection_NextSibling),
+            Some(Element::Tab(4))
+        );
+        assert_eq!(
+            navigate(&s, Element::Tab(1), NavigateDirection_PreviousSibling),
+            None
+        );
+        assert_eq!(
+            navigate(&s, Element::TabList, NavigateDirection_NextSibling),
+            Some(Element::Button(TitlebarButton::NewTab))
+        );
+
+        // Pinned tabs have no close button
+        assert_eq!(navigate(&s, Element::Tab(1), first), None);
+        assert_eq!(
+            navigate(&s, Element::Tab(4), first),
+            Some(Element::TabClose(4))
+        );
+        assert_eq!(
+            navigate(&s, Element::TabClose(4), NavigateDirection_Parent),
+            Some(Element::Tab(4))
+        );
+
+        // Closed tabs can't be navigated from
+        assert_eq!(
+            navigate(&s, Element::Tab(9), NavigateDirection_Parent),
+            None
+        );
+        assert_eq!(
+            navigate(&s, Element::TabClose(1), NavigateDirection_Parent),
+            None
+        );
+    }
+
+    #[test]
+    fn test_element_at() {
+        let s = sample();
+        assert_eq!(
+            element_at(&s, POINT { x: 50, y: 10 }),
+            Some(Element::Tab(1))
+        );
+        assert_eq!(
+            element_at(&s, POINT { x: 195, y: 10 }),
+            Some(Element::TabClose(4))
+        );
+        assert_eq!(
+            element_at(&s, POINT { x: 420, y: 10 }),
+            Some(Element::Button(TitlebarButton::Close))
+        );
+        assert_eq!(element_at(&s, POINT { x: 300, y: 10 }), None);
+    }
+
+    #[test]
+    fn test_names_and_bounds() {
+        let mut s = sample();
+        assert_eq!(name(&s, Element::Tab(4)), "tab 4");
+        assert_eq!(name(&s, Element::TabClose(4)), "Close tab 4");
+        assert_eq!(
+            name(&s, Element::Button(TitlebarButton::Maximize)),
+            "Maximize"
+        );
+        s.maximized = true;
+        assert_eq!(
+            name(&s, Element::Button(TitlebarButton::Maximize)),
+            "Restore"
+        );
+
+        // Tabs in the overflow list are offscreen
+        assert_eq!(bounds(&s, Element::Tab(7)), None);
+        assert_eq!(bounds(&s, Element::Tab(4)), Some(rect(100, 200)));
+    }
+
+    #[test]
+    fn test_action_params_round_trip() {
+        let actions = [
+            UiaAction::SelectTab(3),
+            UiaAction::CloseTab(12),
+            UiaAction::Press(TitlebarButton::Overflow),
+            UiaAction::Press(TitlebarButton::Close),
+        ];
+        for action in actions {
+            let (wparam, lparam) = action.to_params();
+            assert_eq!(UiaAction::from_params(wparam, lparam), Some(action));
+        }
+        assert_eq!(UiaAction::from_params(WPARAM(99), LPARAM(0)), None);
+    }
+}
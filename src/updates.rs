@@ -0,0 +1,285 @@
+//! Startup check for a newer release.
+//!
+//! With `check_updates`, a background thread asks the GitHub releases API for the
+//! latest release once at startup. If it is newer than the running version, the
+//! wrapper gets WM_UPDATE_AVAILABLE and shows an "Update available" badge in the
+//! title bar that opens the release page. Nothing is downloaded; failures (offline,
+//! rate limited) are only logged.
+
+#![cfg(target_os = "windows")]
+
+use std::ffi::c_void;
+use std::sync::Mutex;
+use std::thread;
+
+use anyhow::{Result, bail};
+use serde::Deserialize;
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::Networking::WinHttp::{
+    INTERNET_DEFAULT_HTTPS_PORT, WINHTTP_ACCESS_TYPE_AUTOMATIC_PROXY, WINHTTP_FLAG_SECURE,
+    WINHTTP_QUERY_FLAG_NUMBER, WINHTTP_QUERY_STATUS_CODE, WinHttpCloseHandle, WinHttpConnect,
+    WinHttpOpen, WinHttpOpenRequest, WinHttpQueryHeaders, WinHttpReadData, WinHttpReceiveResponse,
+    WinHttpSendRequest, WinHttpSetTimeouts,
+};
+use windows::Win32::UI::Shell::ShellExecuteW;
+use windows::Win32::UI::WindowsAndMessaging::{PostMessageW, SW_SHOWNORMAL, WM_APP};
+use windows::core::{HSTRING, PCWSTR, w};
+
+/// Posted to the wrapper when the check found a newer release
+pub const WM_UPDATE_AVAILABLE: u32 = WM_APP + 19;
+
+/// Version of this build
+const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const API_HOST: PCWSTR = w!("api.github.com");
+const LATEST_RELEASE_PATH: PCWSTR = w!("/repos/keathmilligan/neovide-tabs/releases/latest");
+
+/// Release pages the badge may open (the URL comes from the network)
+const RELEASE_URL_PREFIX: &str = "https://github.com/keathmilligan/neovide-tabs/releases/";
+
+/// Resolve, connect, send and receive timeout of the request
+const REQUEST_TIMEOUT_MS: i32 = 10_000;
+
+/// Larger responses are not a release description
+const MAX_RESPONSE_BYTES: usize = 1 << 20;
+
+/// A release newer than the running version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Release {
+    /// Version without the tag's "v" prefix
+    pub version: String,
+    /// Release page on GitHub
+    pub url: String,
+}
+
+/// The fields of the releases API response the check uses
+#[derive(Deserialize)]
+struct ReleaseResponse {
+    tag_name: String,
+    html_url: String,
+}
+
+/// Newer release found by the check, if any
+static AVAILABLE: Mutex<Option<Release>> = Mutex::new(None);
+
+/// Look for a newer release on a background thread; `hwnd` gets
+/// WM_UPDATE_AVAILABLE if there is one
+pub fn check_in_background(hwnd: HWND) {
+    let hwnd_value = hwnd.0 as isize;
+    thread::spawn(move || match fetch_latest_release() {
+        Ok(Some(release)) if is_newer(&release.version, CURRENT_VERSION) => {
+            eprintln!("Update: {} is available ({})", release.version, release.url);
+            *AVAILABLE.lock().unwrap() = Some(release);
+            unsafe {
+                let hwnd = HWND(hwnd_value as *mut c_void);
+                let _ = PostMessageW(hwnd, WM_UPDATE_AVAILABLE, WPARAM(0), LPARAM(0));
+            }
+        }
+        Ok(_) => eprintln!("Update: {} is the latest release", CURRENT_VERSION),
+        Err(e) => eprintln!("Update: Check failed: {}", e),
+    });
+}
+
+/// The newer release found by the check, if any
+pub fn available_update() -> Option<Release> {
+    AVAILABLE.lock().unwrap().clone()
+}
+
+/// Open the release page in the default browser
+pub fn open_release_page(release: &Release) {
+    unsafe {
+        let url = HSTRING::from(release.url.as_str());
+        let result = ShellExecuteW(
+            None,
+            w!("open"),
+            &url,
+            PCWSTR::null(),
+            PCWSTR::null(),
+            SW_SHOWNORMAL,
+        );
+        // ShellExecute reports errors as values up to 32
+        if result.0 as usize <= 32 {
+            eprintln!("Update: Failed to open {}", release.url);
+        }
+    }
+}
+
+/// The latest release (None if the repository has no release yet)
+fn fetch_latest_release() -> Result<Option<Release>> {
+    match unsafe { https_get(API_HOST, LATEST_RELEASE_PATH)? } {
+        Some(body) => parse_release(&body).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// WinHTTP handle closed when dropped
+struct InternetHandle(*mut c_void);
+
+impl InternetHandle {
+    fn new(handle: *mut c_void) -> Result<Self> {
+        if handle.is_null() {
+            return Err(windows::core::Error::from_win32().into());
+        }
+        Ok(Self(handle))
+    }
+}
+
+impl Drop for InternetHandle {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = WinHttpCloseHandle(self.0);
+        }
+    }
+}
+
+/// Body of an HTTPS GET request (None for 404 Not Found)
+unsafe fn https_get(host: PCWSTR, path: PCWSTR) -> Result<Option<Vec<u8>>> {
+    unsafe {
+        let agent = HSTRING::from(format!("neovide-tabs/{}", CURRENT_VERSION));
+        let session = InternetHandle::new(WinHttpOpen(
+            &agent,
+            WINHTTP_ACCESS_TYPE_AUTOMATIC_PROXY,
+            PCWSTR::null(),
+            PCWSTR::null(),
+            0,
+        ))?;
+        WinHttpSetTimeouts(
+            session.0,
+            REQUEST_TIMEOUT_MS,
+            REQUEST_TIMEOUT_MS,
+            REQUEST_TIMEOUT_MS,
+            REQUEST_TIMEOUT_MS,
+        )?;
+        let connection = InternetHandle::new(WinHttpConnect(
+            session.0,
+            host,
+            INTERNET_DEFAULT_HTTPS_PORT,
+            0,
+        ))?;
+        let request = InternetHandle::new(WinHttpOpenRequest(
+            connection.0,
+            w!("GET"),
+            path,
+            PCWSTR::null(),
+            PCWSTR::null(),
+            std::ptr::null(),
+            WINHTTP_FLAG_SECURE,
+        ))?;
+
+        let headers: Vec<u16> = "Accept: application/vnd.github+json"
+            .encode_utf16()
+            .collect();
+        WinHttpSendRequest(request.0, Some(&headers), None, 0, 0, 0)?;
+        WinHttpReceiveResponse(request.0, std::ptr::null_mut())?;
+
+        let mut status = 0u32;
+        let mut size = size_of::<u32>() as u32;
+        WinHttpQueryHeaders(
+            request.0,
+            WINHTTP_QUERY_STATUS_CODE | WINHTTP_QUERY_FLAG_NUMBER,
+            PCWSTR::null(),
+            Some(&mut status as *mut u32 as *mut c_void),
+            &mut size,
+            std::ptr::null_mut(),
+        )?;
+        match status {
+            200 => {}
+            404 => return Ok(None),
+            _ => bail!("HTTP status {}", status),
+        }
+
+        let mut body = Vec::new();
+        let mut chunk = [0u8; 8192];
+        loop {
+            let mut read = 0u32;
+            WinHttpReadData(
+                request.0,
+                chunk.as_mut_ptr() as *mut c_void,
+                chunk.len() as u32,
+                &mut read,
+            )?;
+            if read == 0 {
+                return Ok(Some(body));
+            }
+            body.extend_from_slice(&chunk[..read as usize]);
+            if body.len() > MAX_RESPONSE_BYTES {
+                bail!("Response larger than {} bytes", MAX_RESPONSE_BYTES);
+            }
+        }
+    }
+}
+
+/// The release described by a releases API response
+fn parse_release(body: &[u8]) -> Result<Release> {
+    let response: ReleaseResponse = serde_json::from_slice(body)?;
+    if !response.html_url.starts_with(RELEASE_URL_PREFIX) {
+        bail!("Unexpected release page {}", response.html_url);
+    }
+    Ok(Release {
+        version: response
+            .tag_name
+            .trim()
+            .trim_start_matches(['v', 'V'])
+            .to_string(),
+        url: response.html_url,
+    })
+}
+
+/// Numeric components of a version like "1.2.3" (pre-release and build suffixes
+/// are ignored); None if it isn't one
+fn parse_version(version: &str) -> Option<Vec<u64>> {
+    let version = version.trim().trim_start_matches(['v', 'V']);
+    let core = version.split(['-', '+']).next()?;
+    core.split('.').map(|part| part.parse().ok()).collect()
+}
+
+/// Whether version `latest` is newer than `current` (missing components count as 0)
+fn is_newer(latest: &str, current: &str) -> bool {
+    let (Some(mut latest), Some(mut current)) = (parse_version(latest), parse_version(current))
+    else {
+        return false;
+    };
+    let len = latest.len().max(current.len());
+    latest.resize(len, 0);
+    current.resize(len, 0);
+    latest > current
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("0.2.0", "0.1.0"));
+        assert!(is_newer("v0.1.1", "0.1.0"));
+        assert!(is_newer("1.0", "0.9.9"));
+        assert!(is_newer("0.10.0", "0.9.0"));
+        assert!(!is_newer("0.1.0", "0.1.0"));
+        assert!(!is_newer("0.1", "0.1.0"));
+        assert!(!is_newer("0.0.9", "0.1.0"));
+        assert!(!is_newer("0.1.0-beta.1", "0.1.0"));
+        assert!(!is_newer("nightly", "0.1.0"));
+    }
+
+    #[test]
+    fn test_parse_release() {
+        let body = br#"{
+            "tag_name": "v0.2.0",
+            "html_url": "https://github.com/keathmilligan/neovide-tabs/releases/tag/v0.2.0",
+            "assets": []
+        }"#;
+        assert_eq!(
+            parse_release(body).unwrap(),
+            Release {
+                version: "0.2.0".to_string(),
+                url: "https://github.com/keathmilligan/neovide-tabs/releases/tag/v0.2.0"
+                    .to_string(),
+            }
+        );
+
+        let elsewhere = br#"{"tag_name": "v9.0.0", "html_url": "https://example.com/setup.exe"}"#;
+        assert!(parse_release(elsewhere).is_err());
+        assert!(parse_release(b"{}").is_err());
+    }
+}
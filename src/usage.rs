@@ -0,0 +1,335 @@
+//! Per-tab resource usage (memory and CPU).
+//!
+//! Each tab's Neovide process tree (Neovide, nvim and anything nvim starts) is
+//! sampled from the process poll timer, all from one snapshot of the system's
+//! processes per poll. CPU usage is derived from the change in
+//! cumulative CPU time between two samples, like Task Manager does.
+
+use std::time::{Duration, Instant};
+
+/// Minimum time between two usage samples of the same process
+pub const USAGE_SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Raw usage counters read from a process tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UsageSample {
+    /// Sum of the working sets of all processes in the tree
+    pub memory_bytes: u64,
+    /// Cumulative kernel + user CPU time of all processes in the tree
+    pub cpu_time: Duration,
+}
+
+/// The system's processes with their parents, as of one snapshot
+#[derive(Debug, Default)]
+pub struct ProcessTable {
+    /// (process ID, parent process ID)
+    entries: Vec<(u32, u32)>,
+}
+
+impl ProcessTable {
+    pub fn new(entries: Vec<(u32, u32)>) -> Self {
+        ProcessTable { entries }
+    }
+
+    /// IDs of `root` and all of its descendants, starting with `root`. Process
+    /// IDs are reused, so a process older than the parent its parent ID points to
+    /// was started by an earlier owner of that ID and is skipped. `created` gives
+    /// a process's creation time (None if unknown, e.g. access denied).
+    pub fn tree(&self, root: u32, mut created: impl FnMut(u32) -> Option<u64>) -> Vec<u32> {
+        let mut pids = vec![root];
+        // Walk the tree breadth-first; `pids` grows while it is being scanned
+        let mut i = 0;
+        while i < pids.len() {
+            let parent = pids[i];
+            let parent_created = created(parent);
+            for &(pid, parent_pid) in &self.entries {
+                if parent_pid != parent || pid == parent || pids.contains(&pid) {
+                    continue;
+                }
+                if let (Some(parent_created), Some(child_created)) = (parent_created, created(pid))
+                    && child_created < parent_created
+                {
+                    continue;
+                }
+                pids.push(pid);
+            }
+            i += 1;
+        }
+        pids
+    }
+}
+
+/// Memory and CPU usage of a tab, as shown in the tooltip and task manager
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResourceUsage {
+    pub memory_bytes: u64,
+    /// Share of the total CPU capacity of the machine (0-100)
+    pub cpu_percent: f32,
+}
+
+/// Turns successive samples of one process tree into a `ResourceUsage`
+#[derive(Debug, Default)]
+pub struct UsageTracker {
+    /// Previous sample and when it was taken
+    last_sample: Option<(UsageSample, Instant)>,
+    /// Latest computed usage (None until two samples were taken)
+    current: Option<ResourceUsage>,
+}
+
+impl UsageTracker {
+    /// Latest known usage, if enough samples have been taken
+    pub fn current(&self) -> Option<ResourceUsage> {
+        self.current
+    }
+
+    /// Whether a new sample is due at `now`
+    pub fn is_due(&self, now: Instant) -> bool {
+        self.last_sample
+            .is_none_or(|(_, taken)| now.duration_since(taken) >= USAGE_SAMPLE_INTERVAL)
+    }
+
+    /// Record a new sample and update the current usage
+    pub fn record(&mut self, sample: UsageSample, now: Instant, cpu_count: usize) {
+        if let Some((last, taken)) = self.last_sample {
+            self.current = Some(ResourceUsage {
+                memory_bytes: sample.memory_bytes,
+                cpu_percent: cpu_percent(
+                    sample.cpu_time.saturating_sub(last.cpu_time),
+                    now.duration_since(taken),
+                    cpu_count,
+                ),
+            });
+        }
+        self.last_sample = Some((sample, now));
+    }
+}
+
+/// Number of logical processors, used to scale CPU time to a percentage
+pub fn cpu_count() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// CPU usage in percent of the whole machine for `cpu_delta` CPU time spent
+/// over `wall_delta` of wall-clock time
+pub fn cpu_percent(cpu_delta: Duration, wall_delta: Duration, cpu_count: usize) -> f32 {
+    let capacity = wall_delta.as_secs_f64() * cpu_count.max(1) as f64;
+    if capacity <= 0.0 {
+        return 0.0;
+    }
+    ((cpu_delta.as_secs_f64() / capacity) * 100.0).clamp(0.0, 100.0) as f32
+}
+
+/// Format a byte count for display (e.g. "512 KB", "143.2 MB", "1.5 GB")
+pub fn format_memory(bytes: u64) -> String {
+    const KB: f64 = 1024.0;
+    const MB: f64 = KB * 1024.0;
+    const GB: f64 = MB * 1024.0;
+    let bytes = bytes as f64;
+    if bytes >= GB {
+        format!("{:.1} GB", bytes / GB)
+    } else if bytes >= MB {
+        format!("{:.1} MB", bytes / MB)
+    } else {
+        format!("{:.0} KB", bytes / KB)
+    }
+}
+
+/// Format a CPU percentage for display (e.g. "2.5%")
+pub fn format_cpu(percent: f32) -> String {
+    format!("{:.1}%", percent)
+}
+
+/// One-line usage summary for the tab tooltip
+pub fn usage_summary(usage: Option<ResourceUsage>) -> String {
+    match usage {
+        Some(usage) => format!(
+            "Memory: {}  \u{00b7}  CPU: {}",
+            format_memory(usage.memory_bytes),
+            format_cpu(usage.cpu_percent)
+        ),
+        None => "Memory: \u{2013}  \u{00b7}  CPU: \u{2013}".to_string(),
+    }
+}
+
+/// When to show the high memory banner: while the tabs together use at least the
+/// threshold, unless dismissed. A dismissed banner comes back once usage dropped
+/// below the threshold and reached it again.
+#[derive(Debug, Default)]
+pub struct MemoryWarning {
+    /// Bytes the tabs may use before the banner shows; None: never
+    threshold: Option<u64>,
+    /// Whether the last total reached the threshold
+    over: bool,
+    dismissed: bool,
+}
+
+impl MemoryWarning {
+    pub fn new(threshold: Option<u64>) -> Self {
+        MemoryWarning {
+            threshold,
+            ..Default::default()
+        }
+    }
+
+    /// Change the threshold (config reload); takes effect with the next `update`
+    pub fn set_threshold(&mut self, threshold: Option<u64>) {
+        self.threshold = threshold;
+    }
+
+    /// Record the memory the tabs use in total. Returns true if the banner
+    /// appeared or went away.
+    pub fn update(&mut self, total: u64) -> bool {
+        let was_visible = self.is_visible();
+        self.over = self.threshold.is_some_and(|threshold| total >= threshold);
+        if !self.over {
+            self.dismissed = false;
+        }
+        self.is_visible() != was_visible
+    }
+
+    /// Hide the banner until usage drops below the threshold. Returns true if it
+    /// was visible.
+    pub fn dismiss(&mut self) -> bool {
+        let was_visible = self.is_visible();
+        self.dismissed = true;
+        was_visible
+    }
+
+    /// Whether the banner is shown
+    pub fn is_visible(&self) -> bool {
+        self.over && !self.dismissed
+    }
+
+    /// Memory to free to get from `total` back below the threshold
+    pub fn excess(&self, total: u64) -> u64 {
+        self.threshold
+            .map_or(0, |threshold| (total + 1).saturating_sub(threshold))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cpu_percent() {
+        // One full core out of four for the whole interval
+        let percent = cpu_percent(Duration::from_secs(2), Duration::from_secs(2), 4);
+        assert!((percent - 25.0).abs() < 0.01);
+        // No elapsed time or no CPU time
+        assert_eq!(cpu_percent(Duration::from_secs(1), Duration::ZERO, 4), 0.0);
+        assert_eq!(cpu_percent(Duration::ZERO, Duration::from_secs(2), 4), 0.0);
+        // Never more than the whole machine
+        assert_eq!(
+            cpu_percent(Duration::from_secs(10), Duration::from_secs(1), 2),
+            100.0
+        );
+    }
+
+    #[test]
+    fn test_process_table_tree() {
+        // (pid, parent, created)
+        let processes = [
+            (100, 4, 10),
+            (200, 100, 20),
+            (300, 200, 30),
+            (400, 100, 40),
+            // Started by an earlier process 100 whose ID Neovide got
+            (500, 100, 5),
+            (600, 1, 50),
+            // Its own parent (System Idle Process)
+            (0, 0, 0),
+        ];
+        let table = ProcessTable::new(
+            processes
+                .iter()
+                .map(|&(pid, parent, _)| (pid, parent))
+                .collect(),
+        );
+        let created = |pid: u32| {
+            processes
+                .iter()
+                .find(|&&(p, _, _)| p == pid)
+                .map(|&(_, _, created)| created)
+        };
+        assert_eq!(table.tree(100, created), vec![100, 200, 400, 300]);
+        assert_eq!(table.tree(300, created), vec![300]);
+        assert_eq!(table.tree(0, created), vec![0]);
+        // Without creation times every child counts
+        assert_eq!(table.tree(100, |_| None), vec![100, 200, 400, 500, 300]);
+    }
+
+    #[test]
+    fn test_usage_tracker() {
+        let start = Instant::now();
+        let mut tracker = UsageTracker::default();
+        assert!(tracker.is_due(start));
+
+        tracker.record(
+            UsageSample {
+                memory_bytes: 100,
+                cpu_time: Duration::from_millis(500),
+            },
+            start,
+            1,
+        );
+        // The first sample only establishes a baseline
+        assert_eq!(tracker.current(), None);
+        assert!(!tracker.is_due(start + Duration::from_secs(1)));
+        assert!(tracker.is_due(start + USAGE_SAMPLE_INTERVAL));
+
+        tracker.record(
+            UsageSample {
+                memory_bytes: 200,
+                cpu_time: Duration::from_millis(1500),
+            },
+            start + Duration::from_secs(2),
+            1,
+        );
+        let usage = tracker.current().unwrap();
+        assert_eq!(usage.memory_bytes, 200);
+        assert!((usage.cpu_percent - 50.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_format_memory() {
+        assert_eq!(format_memory(512 * 1024), "512 KB");
+        assert_eq!(format_memory(150 * 1024 * 1024), "150.0 MB");
+        assert_eq!(format_memory(3 * 1024 * 1024 * 1024 / 2), "1.5 GB");
+    }
+
+    #[test]
+    fn test_usage_summary() {
+        let usage = ResourceUsage {
+            memory_bytes: 64 * 1024 * 1024,
+            cpu_percent: 2.54,
+        };
+        assert_eq!(
+            usage_summary(Some(usage)),
+            "Memory: 64.0 MB  \u{00b7}  CPU: 2.5%"
+        );
+        assert!(usage_summary(None).starts_with("Memory: \u{2013}"));
+    }
+
+    #[test]
+    fn test_memory_warning() {
+        let mut warning = MemoryWarning::new(Some(1000));
+        assert!(!warning.update(999));
+        assert!(warning.update(1000));
+        assert!(warning.is_visible());
+        assert_eq!(warning.excess(1500), 501);
+
+        // Dismissed: stays hidden while usage stays high
+        assert!(warning.dismiss());
+        assert!(!warning.update(2000));
+        assert!(!warning.is_visible());
+        // Back after dropping below the threshold and reaching it again
+        assert!(!warning.update(500));
+        assert!(warning.update(1200));
+
+        warning.set_threshold(None);
+        assert!(warning.update(5000));
+        assert!(!warning.is_visible());
+        assert_eq!(warning.excess(5000), 0);
+    }
+}
@@ -15,7 +15,7 @@ use notify_debouncer_mini::new_debouncer;
 use windows::Win32::Foundation::HWND;
 use windows::Win32::UI::WindowsAndMessaging::{PostMessageW, WM_APP};
 
-use crate::config::config_dir_path;
+use crate::config::{config_dir_path, config_file_candidates};
 
 /// Custom message ID for config reload events (WM_APP + 10)
 pub const WM_CONFIG_RELOAD: u32 = WM_APP + 10;
@@ -41,6 +41,7 @@ impl ConfigWatcher {
     /// - The watcher fails to initialize
     pub fn start(hwnd: HWND) -> Option<Self> {
         let config_dir = config_dir_path()?;
+        let config_files = config_file_candidates();
 
         if !config_dir.exists() {
             eprintln!(
@@ -58,7 +59,7 @@ impl ConfigWatcher {
 
         // Start the watcher thread
         let thread_handle = thread::spawn(move || {
-            run_watcher(config_dir, hwnd_value, stop_rx);
+            run_watcher(config_dir, config_files, hwnd_value, stop_rx);
         });
 
         Some(ConfigWatcher {
@@ -69,7 +70,12 @@ impl ConfigWatcher {
 }
 
 /// Run the file watcher (called from the background thread)
-fn run_watcher(config_dir: PathBuf, hwnd_value: isize, stop_rx: mpsc::Receiver<()>) {
+fn run_watcher(
+    config_dir: PathBuf,
+    config_files: Vec<PathBuf>,
+    hwnd_value: isize,
+    stop_rx: mpsc::Receiver<()>,
+) {
     eprintln!("ConfigWatcher: Starting to watch {:?}", config_dir);
 
     // Create a channel for debounced events
@@ -109,7 +115,7 @@ fn run_watcher(config_dir: PathBuf, hwnd_value: isize, stop_rx: mpsc::Receiver<(
                 match result {
                     Ok(events) => {
                         // Got debounced events - check if any are config files
-                        if should_reload(&events, &config_dir) {
+                        if should_reload(&events, &config_files) {
                             eprintln!(
                                 "ConfigWatcher: Config file changed, posting reload message"
                             );
@@ -134,19 +140,14 @@ fn run_watcher(config_dir: PathBuf, hwnd_value: isize, stop_rx: mpsc::Receiver<(
     eprintln!("ConfigWatcher: Stopped");
 }
 
-/// Check if the debounced events include a config file change
-fn should_reload(events: &[notify_debouncer_mini::DebouncedEvent], config_dir: &std::path::Path) -> bool {
-    let jsonc_path = config_dir.join("config.jsonc");
-    let json_path = config_dir.join("config.json");
-
-    for event in events {
-        let path = &event.path;
-        if path == &jsonc_path || path == &json_path {
-            return true;
-        }
-    }
-
-    false
+/// Check if the debounced events include a change of one of the config files
+fn should_reload(
+    events: &[notify_debouncer_mini::DebouncedEvent],
+    config_files: &[PathBuf],
+) -> bool {
+    events
+        .iter()
+        .any(|event| config_files.contains(&event.path))
 }
 
 /// Post the config reload message to the window
@@ -168,6 +169,14 @@ fn post_reload_message(hwnd_value: isize) {
 mod tests {
     use super::*;
 
+    /// The default config files in `config_dir`
+    fn default_config_files(config_dir: &std::path::Path) -> Vec<PathBuf> {
+        vec![
+            config_dir.join("config.jsonc"),
+            config_dir.join("config.json"),
+        ]
+    }
+
     #[test]
     fn test_should_reload_jsonc() {
         let config_dir = PathBuf::from("/test/config");
@@ -176,7 +185,7 @@ mod tests {
             kind: notify_debouncer_mini::DebouncedEventKind::Any,
         }];
 
-        assert!(should_reload(&events, &config_dir));
+        assert!(should_reload(&events, &default_config_files(&config_dir)));
     }
 
     #[test]
@@ -187,7 +196,7 @@ mod tests {
             kind: notify_debouncer_mini::DebouncedEventKind::Any,
         }];
 
-        assert!(should_reload(&events, &config_dir));
+        assert!(should_reload(&events, &default_config_files(&config_dir)));
     }
 
     #[test]
@@ -198,7 +207,7 @@ mod tests {
             kind: notify_debouncer_mini::DebouncedEventKind::Any,
         }];
 
-        assert!(!should_reload(&events, &config_dir));
+        assert!(!should_reload(&events, &default_config_files(&config_dir)));
     }
 
     #[test]
@@ -206,6 +215,23 @@ mod tests {
         let config_dir = PathBuf::from("/test/config");
         let events: Vec<notify_debouncer_mini::DebouncedEvent> = vec![];
 
-        assert!(!should_reload(&events, &config_dir));
+        assert!(!should_reload(&events, &default_config_files(&config_dir)));
+    }
+
+    #[test]
+    fn test_should_reload_custom_config_file() {
+        let config_files = vec![PathBuf::from("/setups/work/neovide-tabs.jsonc")];
+        let events = vec![notify_debouncer_mini::DebouncedEvent {
+            path: PathBuf::from("/setups/work/neovide-tabs.jsonc"),
+            kind: notify_debouncer_mini::DebouncedEventKind::Any,
+        }];
+        assert!(should_reload(&events, &config_files));
+
+        // The default config names next to it are not watched
+        let events = vec![notify_debouncer_mini::DebouncedEvent {
+            path: PathBuf::from("/setups/work/config.jsonc"),
+            kind: notify_debouncer_mini::DebouncedEventKind::Any,
+        }];
+        assert!(!should_reload(&events, &config_files));
     }
 }
@@ -4,91 +4,289 @@
 #![allow(clippy::collapsible_if)]
 
 use anyhow::{Context, Result};
-use std::cell::Cell;
-use windows::Win32::Foundation::{COLORREF, HWND, LPARAM, LRESULT, POINT, RECT, SIZE, WPARAM};
+use std::cell::{Cell, RefCell, RefMut};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::time::{Duration, Instant};
+use windows::Win32::Foundation::{
+    BOOL, COLORREF, HWND, LPARAM, LRESULT, POINT, RECT, SIZE, WPARAM,
+};
+use windows::Win32::Globalization::{GetTimeFormatEx, TIME_NOSECONDS};
 use windows::Win32::Graphics::Dwm::{
     DWMWA_WINDOW_CORNER_PREFERENCE, DWMWCP_ROUND, DwmSetWindowAttribute,
 };
 use windows::Win32::Graphics::Gdi::{
-    AC_SRC_ALPHA, AC_SRC_OVER, BITMAP, BLENDFUNCTION, BeginPaint, BitBlt, ClientToScreen,
-    CreateCompatibleBitmap, CreateCompatibleDC, CreateFontIndirectW, CreatePen, CreateSolidBrush,
-    DeleteDC, DeleteObject, EndPaint, FillRect, GdiAlphaBlend, GetObjectW, GetTextExtentPoint32W,
-    GetTextMetricsW, HBITMAP, HBRUSH, HGDIOBJ, InvalidateRect, LOGFONTW, LineTo, MoveToEx,
-    PAINTSTRUCT, PS_SOLID, SRCCOPY, ScreenToClient, SelectObject, SetBkMode, SetTextColor,
-    TEXTMETRICW, TRANSPARENT, TextOutW,
+    AC_SRC_ALPHA, AC_SRC_OVER, Arc, BITMAP, BLENDFUNCTION, BeginPaint, BitBlt, ClientToScreen,
+    CreateCompatibleBitmap, CreateCompatibleDC, CreatePen, CreateSolidBrush, DeleteDC,
+    DeleteObject, Ellipse, EndPaint, FillRect, FrameRect, GdiAlphaBlend, GetMonitorInfoW,
+    GetObjectW, GetStockObject, GetTextExtentPoint32W, GetTextMetricsW, HBITMAP, HBRUSH, HGDIOBJ,
+    InvalidateRect, LineTo, MONITOR_DEFAULTTONEAREST, MONITORINFO, MonitorFromWindow, MoveToEx,
+    NULL_BRUSH, PAINTSTRUCT, PS_SOLID, RoundRect, SRCCOPY, ScreenToClient, SelectObject, SetBkMode,
+    SetTextColor, TEXTMETRICW, TRANSPARENT, TextOutW,
 };
 use windows::Win32::System::LibraryLoader::GetModuleHandleW;
-use windows::Win32::UI::Controls::WM_MOUSELEAVE;
+use windows::Win32::System::RemoteDesktop::{
+    NOTIFY_FOR_THIS_SESSION, WTSRegisterSessionNotification, WTSUnRegisterSessionNotification,
+};
+use windows::Win32::System::Shutdown::{ShutdownBlockReasonCreate, ShutdownBlockReasonDestroy};
+use windows::Win32::UI::Accessibility::{UiaReturnRawElementProvider, UiaRootObjectId};
+use windows::Win32::UI::Controls::{
+    ICC_BAR_CLASSES, INITCOMMONCONTROLSEX, InitCommonControlsEx, NMHDR, NMTTDISPINFOW,
+    TOOLTIPS_CLASSW, TTF_SUBCLASS, TTM_ADDTOOLW, TTM_NEWTOOLRECTW, TTM_POP, TTM_SETMAXTIPWIDTH,
+    TTM_UPDATE, TTN_GETDISPINFOW, TTS_ALWAYSTIP, TTS_NOPREFIX, TTTOOLINFOW, WM_MOUSELEAVE,
+};
+use windows::Win32::UI::HiDpi::GetDpiForWindow;
 use windows::Win32::UI::Input::KeyboardAndMouse::{
-    ReleaseCapture, SetCapture, TME_LEAVE, TRACKMOUSEEVENT, TrackMouseEvent,
+    GetKeyState, HOT_KEY_MODIFIERS, ReleaseCapture, SetCapture, SetFocus, TME_LEAVE,
+    TRACKMOUSEEVENT, TrackMouseEvent, VIRTUAL_KEY, VK_CONTROL, VK_DELETE, VK_END, VK_ESCAPE,
+    VK_HOME, VK_LEFT, VK_MENU, VK_RETURN, VK_RIGHT, VK_SHIFT, VK_SPACE, VK_TAB,
+};
+use windows::Win32::UI::Input::Pointer::GetPointerType;
+use windows::Win32::UI::Shell::{
+    ABE_BOTTOM, ABE_LEFT, ABE_RIGHT, ABE_TOP, ABM_GETAUTOHIDEBAREX, APPBARDATA, SHAppBarMessage,
+    ShellExecuteW,
 };
 use windows::Win32::UI::WindowsAndMessaging::*;
-use windows::core::{PCWSTR, w};
-
-use crate::config::{Config, Profile};
+use windows::core::{PCWSTR, PWSTR, w};
+
+use crate::attention::WM_TAB_ATTENTION;
+use crate::autohide::AutoHide;
+use crate::clipboard;
+use crate::config::{
+    self, Config, DEFAULT_CONTENT_INSET, MonitorTarget, MouseAction, MouseArea, MouseButton,
+    Profile, StartState, TabDoubleClickAction, TabNumbers, TabStyle, abbreviate_working_directory,
+};
+use crate::dialogs;
+use crate::explorer::{self, OpenRequest};
+use crate::fonts::{self, TextSize};
+use crate::gestures::{self, SwipeDirection, WheelSwipe};
+use crate::gitstatus::{GitIndicator, GitStatus};
+use crate::grips::{self, ResizeGrips};
+use crate::hooks;
 use crate::hotkeys;
-use crate::icons::{ICON_SIZE, clear_icon_cache, create_window_icons, get_icon_bitmap};
-use crate::tabs::{DragState, TabManager};
+use crate::hud::DebugHud;
+use crate::icons::{
+    ICON_SIZE, WM_ICONS_DECODED, clear_icon_cache, create_window_icons, get_icon_bitmap,
+    icons_pending, preload_icons, receive_decoded_icons, set_icon_dpi,
+};
+use crate::layout::{
+    self, MEMORY_DISMISS_WIDTH, Rect, TAB_VERTICAL_PADDING, TAB_WIDTH, TabHitResult, Widget,
+    calculate_swap_target, calculate_visible_tabs, content_top, get_dropdown_button_rect_ex,
+    get_new_tab_button_rect_ex, get_overflow_button_rect, get_tab_bar_max_x, get_tab_close_rect,
+    get_tab_rect, get_widget_rects, hit_test_tab_bar, hit_test_widgets, tab_bar_left, tab_style,
+    tab_width, titlebar_height, touch_hit_point,
+};
+use crate::logview::{self, OutputView};
+use crate::monitors::{self, Monitor, WindowLayout, WindowLayouts};
+use crate::popup::{self, PopupCommand, PopupItem, PopupList};
+use crate::preview::{self, Thumbnail};
+use crate::process::{self, NeovideProcess, WM_NEOVIDE_WINDOW_FAILED, WM_NEOVIDE_WINDOW_READY};
+use crate::projects::ProjectType;
+use crate::rename::{TabRenameEditor, WM_TAB_RENAME_DONE};
+use crate::sessions::{self, Autosave, Session};
+use crate::tabs::{DragState, ProcessHandle, TabManager, TabState};
+use crate::taskmgr::{self, TaskManagerRow, WM_TASK_MANAGER_CLOSED, WM_TASK_MANAGER_KILL};
+use crate::tiling;
+use crate::uia::{self, TitlebarButton, UiaAction, WM_UIA_INVOKE};
+use crate::updates::{self, WM_UPDATE_AVAILABLE};
+use crate::usage::{MemoryWarning, format_memory, usage_summary};
 use crate::watcher::{ConfigWatcher, WM_CONFIG_RELOAD};
+use crate::winevents::{
+    WM_FOREGROUND_CHANGED, WM_WINDOW_LOCATION_CHANGED, WinEventHooks, is_managed_window,
+};
+
+/// Inset for content area (neovim window) from all edges, from the `content_inset` config
+/// option. Stored globally because Neovide windows are positioned from the spawn thread.
+static CONTENT_INSET: AtomicI32 = AtomicI32::new(DEFAULT_CONTENT_INSET);
 
-const WINDOW_CLASS_NAME: PCWSTR = w!("NeovideTabsWindow");
-const DROPDOWN_CLASS_NAME: PCWSTR = w!("NeovideTabsDropdown");
-const OVERFLOW_CLASS_NAME: PCWSTR = w!("NeovideTabsOverflow");
-const WINDOW_TITLE: PCWSTR = w!("neovide-tabs");
+/// Current inset for the content area (neovim window) from all edges
+pub fn content_inset() -> i32 {
+    CONTENT_INSET.load(Ordering::Relaxed)
+}
 
-/// Title bar height in pixels
-const TITLEBAR_HEIGHT: i32 = 32;
-/// Button width in pixels
-const BUTTON_WIDTH: i32 = 46;
-/// Inset for content area (neovim window) from all edges
-pub const CONTENT_INSET: i32 = 12;
+/// Strip left uncovered by the maximized window on the edges with an auto-hide
+/// taskbar: the taskbar only slides in when the mouse reaches it
+const AUTOHIDE_TASKBAR_EDGE: i32 = 2;
 
-/// Timer ID for delayed foreground activation
+/// Timer ID for delayed foreground activation (`advanced.foreground_delay_ms`)
 const FOREGROUND_TIMER_ID: usize = 2;
-/// Delay before bringing Neovide to foreground (ms)
-const FOREGROUND_DELAY_MS: u32 = 50;
 
-/// Timer ID for deferred position update (for external tools like FancyZones)
+/// Timer ID for deferred position update (for external tools like FancyZones;
+/// `advanced.position_update_delay_ms`)
 const POSITION_UPDATE_TIMER_ID: usize = 3;
-/// Delay before updating position after external move/resize (ms)
-const POSITION_UPDATE_DELAY_MS: u32 = 100;
 
-/// Timer ID for polling Neovide process status
+/// Timer ID for polling Neovide process status (`advanced.process_poll_interval_ms`)
 const PROCESS_POLL_TIMER_ID: usize = 4;
-/// Interval for polling Neovide process status (ms) - spec requires detection within 500ms
-const PROCESS_POLL_INTERVAL_MS: u32 = 250;
+
+/// Timer ID for animating the startup indicator while Neovide is starting
+const LOADING_TIMER_ID: usize = 5;
+/// Interval between startup spinner frames (ms)
+const LOADING_FRAME_MS: u32 = 125;
+/// Timer ID for animating the attention pulse of background tabs
+const ATTENTION_TIMER_ID: usize = 6;
+/// Interval between attention pulse frames (ms)
+const ATTENTION_FRAME_MS: u32 = 33;
+/// Timer ID for polling the tab hotkey modifiers (`tab_numbers: "hold"`)
+const TAB_NUMBERS_TIMER_ID: usize = 7;
+/// Interval for polling the tab hotkey modifiers (ms)
+const TAB_NUMBERS_POLL_MS: u32 = 100;
+/// Timer ID for watching the release of the next/prev tab hotkey modifiers
+const TAB_SWITCHER_TIMER_ID: usize = 8;
+/// Interval for polling the next/prev tab hotkey modifiers (ms)
+const TAB_SWITCHER_POLL_MS: u32 = 30;
+/// Timer ID for refreshing the selected tab's title quickly after a tab switch
+const TITLE_BOOST_TIMER_ID: usize = 9;
+/// Interval of the title refresh after a tab switch (ms)
+const TITLE_BOOST_INTERVAL_MS: u32 = 50;
+/// How long after a tab switch the title is refreshed quickly
+const TITLE_BOOST_DURATION: Duration = Duration::from_secs(2);
+/// Timer ID for showing the preview of a hovered background tab
+const PREVIEW_TIMER_ID: usize = 10;
+/// How long a background tab is hovered before its preview shows (ms)
+const PREVIEW_HOVER_DELAY_MS: u32 = 1000;
+/// How often the selected tab is captured for the previews
+const PREVIEW_CAPTURE_INTERVAL: Duration = Duration::from_secs(2);
+/// Timer ID for revealing and collapsing the auto-hidden title bar
+const AUTO_HIDE_TIMER_ID: usize = 11;
+/// Interval for polling the pointer while the title bar isn't sliding (ms)
+const AUTO_HIDE_POLL_MS: u32 = 100;
+/// Interval between the frames of the title bar sliding in or out (ms)
+const AUTO_HIDE_FRAME_MS: u32 = 15;
+/// Timer ID for putting the window back in place after the displays changed
+const MONITOR_SETUP_TIMER_ID: usize = 12;
+/// Time Windows gets to rearrange the windows after the displays changed (ms)
+const MONITOR_SETUP_DELAY_MS: u32 = 1000;
+
+/// How long a touch on a tab is held before it drags the tab (when swipes are on)
+const TOUCH_HOLD_MS: u64 = 400;
+
+/// Number of dots in the startup spinner
+const LOADING_SPINNER_DOTS: u32 = 8;
+/// Radius of the startup spinner circle
+const LOADING_SPINNER_RADIUS: i32 = 14;
+/// Diameter of each spinner dot
+const LOADING_SPINNER_DOT_SIZE: i32 = 6;
 
 // Tab bar layout constants
-/// Width of each tab in pixels
-const TAB_WIDTH: i32 = 200;
-/// Size of the close button within a tab
-const TAB_CLOSE_SIZE: i32 = 16;
-/// Padding around the close button
-const TAB_CLOSE_PADDING: i32 = 8;
-/// Width of the new tab (+) button
-const NEW_TAB_BUTTON_WIDTH: i32 = 32;
-/// Width of the profile dropdown button (caret)
-const DROPDOWN_BUTTON_WIDTH: i32 = 20;
-/// Width of the overflow tabs button (accommodates icon + "+N" text when selected tab is in overflow)
-const OVERFLOW_BUTTON_WIDTH: i32 = 48;
-/// Left margin before the first tab
-const TAB_BAR_LEFT_MARGIN: i32 = 8;
-/// Vertical padding for tabs within the titlebar
-const TAB_VERTICAL_PADDING: i32 = 4;
-/// Height of each item in the dropdown menu
-const DROPDOWN_ITEM_HEIGHT: i32 = 28;
-/// Padding around dropdown menu
-const DROPDOWN_PADDING: i32 = 4;
+/// Label of the dropdown entry that opens the folder picker (listed after the profiles)
+const NEW_TAB_IN_FOLDER_LABEL: &str = "New tab in folder\u{2026}";
+/// Label of the dropdown entry that opens the session menu
+const SWITCH_SESSION_LABEL: &str = "Switch session\u{2026}";
+/// More overflowing tabs than this are grouped by profile, with a filter box
+const OVERFLOW_GROUP_THRESHOLD: usize = 15;
+/// Placeholder of the overflow popup's filter box
+const OVERFLOW_FILTER_PLACEHOLDER: &str = "Filter tabs\u{2026}";
+/// Posted by the dropdown when "Switch session..." is chosen
+const WM_SESSION_MENU: u32 = WM_APP + 12;
+/// Posted at startup to show the profile chooser (`prompt_profile_on_start`)
+const WM_PROFILE_PROMPT: u32 = WM_APP + 20;
+/// Posted by the dropdown to show the profiles of a discovered group (wparam =
+/// index into `Config::profile_groups`)
+const WM_PROFILE_GROUP_MENU: u32 = WM_APP + 21;
+/// ID of the tab tooltip's only tool; its rect follows the hovered tab
+const TAB_TOOLTIP_ID: usize = 1;
+/// Width at which the tab tooltip wraps (also enables multi-line text)
+const TAB_TOOLTIP_MAX_WIDTH: isize = 480;
 
 // Tab bar colors
 /// Background color for unselected tabs (slightly darker than titlebar)
-const TAB_UNSELECTED_COLOR: u32 = 0x16161e;
+pub(crate) const TAB_UNSELECTED_COLOR: u32 = 0x16161e;
 /// Outline color for tabs and content area
-const TAB_OUTLINE_COLOR: u32 = 0x3d3d3d;
+pub(crate) const TAB_OUTLINE_COLOR: u32 = 0x3d3d3d;
 /// Hover color for tabs (same as button hover)
-const TAB_HOVER_COLOR: u32 = 0x3d3d3d;
+pub(crate) const TAB_HOVER_COLOR: u32 = 0x3d3d3d;
 /// Close button hover color (red)
-const TAB_CLOSE_HOVER_COLOR: u32 = 0xe81123;
+pub(crate) const TAB_CLOSE_HOVER_COLOR: u32 = 0xe81123;
+/// Opacity (0-255) of the icon and label of a suspended background tab
+const SUSPENDED_TAB_OPACITY: u8 = 110;
+/// Color of the warning badge on tabs whose Neovide window is not responding
+const NOT_RESPONDING_BADGE_COLOR: u32 = 0xe0af68;
+/// Diameter of the not-responding badge
+const NOT_RESPONDING_BADGE_SIZE: i32 = 10;
+/// Color a background tab pulses towards when it asks for attention
+const ATTENTION_COLOR: u32 = 0x7aa2f7;
+/// Color of the git status dot of a tab with uncommitted changes
+const GIT_DIRTY_COLOR: u32 = 0xe0af68;
+/// Color of the git status dot of a tab with commits to push or pull
+const GIT_UNSYNCED_COLOR: u32 = 0x7aa2f7;
+/// Diameter of the git status dot
+const GIT_DOT_SIZE: i32 = 7;
+/// Colors of the traffic light window buttons (`traffic_lights`)
+const TRAFFIC_LIGHT_CLOSE_COLOR: u32 = 0xff5f57;
+const TRAFFIC_LIGHT_MINIMIZE_COLOR: u32 = 0xfebc2e;
+const TRAFFIC_LIGHT_MAXIMIZE_COLOR: u32 = 0x28c840;
+/// Color of the symbols shown on the traffic lights while hovered
+const TRAFFIC_LIGHT_SYMBOL_COLOR: u32 = 0x4d0000;
+/// Diameter of a traffic light
+const TRAFFIC_LIGHT_SIZE: i32 = 12;
+/// Strongest blend (0-255) of the attention color into the tab background
+const ATTENTION_MAX_BLEND: f32 = 140.0;
+/// Label and retry icon color of tabs whose Neovide failed to launch
+const SPAWN_ERROR_COLOR: u32 = 0xf7768e;
+/// Blend (0-255) of the label color into the tab background for the working
+/// directory line of two-line tabs
+const SUBTITLE_TEXT_BLEND: u32 = 150;
+/// Vertical gap in pixels between the two lines of a two-line tab
+const SUBTITLE_GAP: i32 = 1;
+/// Color of the "Update available" badge
+const UPDATE_BADGE_COLOR: u32 = 0x9ece6a;
+/// Label of the update badge
+const UPDATE_BADGE_LABEL: &str = "Update available";
+/// Height of the update badge (it is as wide as its widget)
+const UPDATE_BADGE_HEIGHT: i32 = 20;
+/// Color of the high memory banner
+const MEMORY_BANNER_COLOR: u32 = 0xe0af68;
+/// Color of the session name and clock widgets
+const WIDGET_TEXT_COLOR: u32 = 0xA0A0A0;
+/// Colors and padding of the debug HUD box
+const DEBUG_HUD_BACKGROUND: u32 = 0x000000;
+const DEBUG_HUD_TEXT_COLOR: u32 = 0x9ece6a;
+const DEBUG_HUD_PADDING: i32 = 6;
+/// Color of the focus ring drawn while the titlebar has the keyboard focus
+const FOCUS_RING_COLOR: u32 = 0xc0caf5;
+/// Color of the startup progress bar along the bottom of the tab bar
+const STARTUP_PROGRESS_COLOR: u32 = 0x7aa2f7;
+/// Height of the startup progress bar
+const STARTUP_PROGRESS_HEIGHT: i32 = 2;
+/// Size of the "Cancel" button below the startup indicator
+const STARTUP_CANCEL_WIDTH: i32 = 96;
+const STARTUP_CANCEL_HEIGHT: i32 = 26;
+
+/// Command IDs of the tab context menu
+const TAB_MENU_CLOSE: usize = 1;
+const TAB_MENU_FORCE_CLOSE: usize = 2;
+const TAB_MENU_CANCEL_PENDING: usize = 3;
+const TAB_MENU_CLOSE_OTHERS: usize = 4;
+const TAB_MENU_CLOSE_TO_RIGHT: usize = 5;
+const TAB_MENU_RENAME: usize = 6;
+const TAB_MENU_DUPLICATE: usize = 7;
+const TAB_MENU_TOGGLE_PIN: usize = 8;
+const TAB_MENU_PAUSE_ANIMATIONS: usize = 9;
+const TAB_MENU_SHOW_OUTPUT: usize = 10;
+const TAB_MENU_COPY_PATH: usize = 11;
+const TAB_MENU_COPY_TITLE: usize = 12;
+const TAB_MENU_REVEAL: usize = 13;
+const TAB_MENU_OPEN_TERMINAL: usize = 14;
+
+/// Command IDs of the settings (gear) menu
+const SETTINGS_MENU_EDIT_CONFIG: usize = 1;
+const SETTINGS_MENU_RELOAD_CONFIG: usize = 2;
+const SETTINGS_MENU_OPEN_DATA_FOLDER: usize = 3;
+const SETTINGS_MENU_ABOUT: usize = 4;
+const SETTINGS_MENU_COPY_TILING_RULES: usize = 5;
+
+/// Command IDs added to the window (system) menu. Windows uses the low four bits
+/// of WM_SYSCOMMAND's wparam and IDs from 0xF000 up, so these are multiples of 16
+/// below that.
+const SYSMENU_NEW_TAB: usize = 0x0010;
+const SYSMENU_CLOSE_TAB: usize = 0x0020;
+const SYSMENU_ALWAYS_ON_TOP: usize = 0x0030;
+const SYSMENU_SETTINGS: usize = 0x0040;
+/// First "Move to monitor" command; the monitor index is (ID - base) / 16
+const SYSMENU_MONITOR_BASE: usize = 0x0100;
+/// First "New tab from profile" command; the profile index is (ID - base) / 16
+const SYSMENU_PROFILE_BASE: usize = 0x1000;
+const SYSMENU_ID_STEP: usize = 0x10;
 
 /// Which title bar button is being hovered
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -99,27 +297,6 @@ enum HoveredButton {
     Close,
 }
 
-/// Result of hit testing in the tab bar area
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum TabHitResult {
-    /// Hit a tab body (index)
-    Tab(usize),
-    /// Hit a tab's close button (index)
-    TabClose(usize),
-    /// Hit the new tab (+) button
-    NewTabButton,
-    /// Hit the profile dropdown button (caret)
-    ProfileDropdown,
-    /// Hit a profile in the dropdown menu (index)
-    DropdownItem(usize),
-    /// Hit the overflow tabs dropdown button
-    OverflowButton,
-    /// Hit the caption/drag area
-    Caption,
-    /// Hit nothing in the tab bar
-    None,
-}
-
 /// Which tab bar element is being hovered
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum HoveredTab {
@@ -129,15 +306,30 @@ enum HoveredTab {
     Tab(usize),
     /// Hovering over tab close button (index)
     TabClose(usize),
+    /// Hovering over the retry icon of an error tab (index)
+    TabRetry(usize),
     /// Hovering over new tab button
     NewTabButton,
     /// Hovering over profile dropdown button
     ProfileDropdown,
-    /// Hovering over dropdown menu item (index) - unused, popup handles hover
-    #[allow(dead_code)]
-    DropdownItem(usize),
     /// Hovering over overflow tabs button
     OverflowButton,
+    /// Hovering over the high memory banner (hibernates tabs)
+    MemoryBanner,
+    /// Hovering over the dismiss button of the high memory banner
+    MemoryDismiss,
+    /// Hovering over the "Update available" badge
+    UpdateBadge,
+    /// Hovering over the settings (gear) button
+    SettingsButton,
+}
+
+/// Titlebar element with the keyboard focus ring
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TitlebarFocus {
+    /// A tab shown in the tab bar (by ID)
+    Tab(usize),
+    Button(TitlebarButton),
 }
 
 /// State of the profile dropdown menu
@@ -149,11 +341,64 @@ enum DropdownState {
     Open,
 }
 
-/// Application state stored in window user data
+/// Hotkey numbers drawn on the tabs (see `Config::tab_numbers`)
+struct TabNumberBadges {
+    mode: TabNumbers,
+    /// Key of each tab hotkey, by tab index
+    labels: Vec<Option<String>>,
+    /// Modifiers of the tab hotkeys, held down to show the numbers in Hold mode
+    modifiers: Option<HOT_KEY_MODIFIERS>,
+    /// Whether the modifiers were down at the last poll
+    held: bool,
+}
+
+impl TabNumberBadges {
+    fn new(config: &Config) -> Self {
+        Self {
+            mode: config.tab_numbers,
+            labels: hotkeys::tab_hotkey_labels(&config.hotkeys.tab),
+            modifiers: hotkeys::tab_hotkey_modifiers(&config.hotkeys.tab),
+            held: false,
+        }
+    }
+
+    /// Labels to draw now, if the numbers are shown
+    fn shown(&self) -> Option<&[Option<String>]> {
+        let shown = match self.mode {
+            TabNumbers::Off => false,
+            TabNumbers::Hold => self.held,
+            TabNumbers::Always => true,
+        };
+        shown.then_some(&self.labels[..])
+    }
+
+    /// Run the modifier polling timer only in Hold mode
+    fn update_timer(&self, hwnd: HWND) {
+        unsafe {
+            if self.mode == TabNumbers::Hold && self.modifiers.is_some() {
+                SetTimer(hwnd, TAB_NUMBERS_TIMER_ID, TAB_NUMBERS_POLL_MS, None);
+            } else {
+                let _ = KillTimer(hwnd, TAB_NUMBERS_TIMER_ID);
+            }
+        }
+    }
+
+    /// Check the modifiers; returns whether the numbers appeared or disappeared
+    fn poll(&mut self) -> bool {
+        let held = self.modifiers.is_some_and(hotkeys::modifiers_held);
+        let changed = held != self.held;
+        self.held = held;
+        changed
+    }
+}
+
+/// Application state, borrowed through the `WindowCell` in the window user data
 struct WindowState {
-    tab_manager: TabManager,
+    tab_manager: TabManager<NeovideProcess>,
     config: Config,
     in_size_move: bool,
+    /// Whether POSITION_UPDATE_TIMER_ID is running (see `schedule_position_update`)
+    position_update_pending: bool,
     background_color: u32,
     hovered_button: HoveredButton,
     hovered_tab: HoveredTab,
@@ -167,58 +412,280 @@ struct WindowState {
     /// Handle to the config file watcher (for hot-reload)
     #[allow(dead_code)]
     config_watcher: Option<ConfigWatcher>,
+    /// Whether the startup indicator animation timer is running
+    loading_timer_active: bool,
+    /// Invisible resize grips over the window edges covered by Neovide
+    resize_grips: ResizeGrips,
+    /// WinEvent hooks for foreground and (in FancyZones mode) location changes
+    win_event_hooks: WinEventHooks,
+    /// Tooltip control showing details and resource usage of the hovered tab
+    tab_tooltip: HWND,
+    /// Tab the tooltip currently describes (its tool rect covers this tab)
+    tooltip_tab: Option<usize>,
+    /// Last capture of each tab's Neovide window by tab ID (`tab_previews`)
+    tab_previews: HashMap<usize, Thumbnail>,
+    /// When the selected tab was last captured
+    last_preview_capture: Option<Instant>,
+    /// Hovered background tab whose preview is shown (or about to be)
+    preview_tab: Option<usize>,
+    /// Handle to the tab preview popup (if shown)
+    preview_hwnd: Option<HWND>,
+    /// Handle to the task manager popup window (if open)
+    task_manager_hwnd: Option<HWND>,
+    /// Output view of a tab ("Show process output"), if open
+    output_view: Option<OutputView>,
+    /// Name of the active session (saved on exit), if any
+    session_name: Option<String>,
+    /// Session to open once the current session's tabs have closed (switch in progress)
+    pending_session: Option<String>,
+    /// Windows is shutting down or logging off and waits for the tabs to close
+    /// (a shutdown block reason is set)
+    ending_session: bool,
+    /// The startup profile chooser waits for the profile icons to be decoded
+    profile_prompt_pending: bool,
+    /// Debounces periodic snapshots of the active session
+    session_autosave: Autosave,
+    /// Inline editor of the tab being renamed (if any)
+    tab_rename: Option<TabRenameEditor>,
+    /// Titlebar element with the keyboard focus; None while Neovide has the keyboard
+    titlebar_focus: Option<TitlebarFocus>,
+    /// Last other application that had the focus; reactivated when the wrapper exits
+    previous_foreground: Option<HWND>,
+    /// Newer release found by the startup update check (shows the update badge)
+    update: Option<updates::Release>,
+    /// Time shown by the clock widget
+    clock_text: String,
+    /// Whether the high memory banner is shown
+    memory_warning: MemoryWarning,
+    /// Memory in use shown by the high memory banner
+    memory_text: String,
+    /// Metrics of the debug HUD while it is shown
+    debug_hud: Option<DebugHud>,
+    /// Hotkey numbers drawn on the tabs
+    tab_numbers: TabNumberBadges,
+    /// Switcher popup while cycling through the tabs with the modifiers held
+    tab_switcher: Option<TabSwitcher>,
+    /// Whether the wrapper and its Neovide windows stay above other windows
+    always_on_top: bool,
+    /// Touch or pen contact that started on a tab bar element
+    touch: Option<TouchContact>,
+    /// Horizontal wheel messages of a touchpad swipe over the title bar
+    wheel_swipe: WheelSwipe,
+    /// Whether TITLE_BOOST_TIMER_ID is running
+    title_boost: bool,
+    /// The process polling is paused while the workstation is locked
+    polling_paused: bool,
+    /// The auto-hiding title bar (`auto_hide_titlebar`)
+    auto_hide: Option<AutoHide>,
+    /// The window's remembered place per monitor setup
+    window_layouts: WindowLayouts,
+    /// Key of the connected monitor setup; None while the displays change,
+    /// until the window is back in place
+    monitor_setup: Option<String>,
 }
 
-/// State for the dropdown popup window
-struct DropdownPopupState {
-    parent_hwnd: HWND,
-    profiles: Vec<Profile>,
-    hovered_item: Option<usize>,
-    background_color: u32,
+/// What GWLP_USERDATA points to: the state and the work waiting for it.
+///
+/// Handlers borrow the state one at a time. Messages can arrive while a handler
+/// has it: a menu or dialog pumps messages in its modal loop, SetWindowPos sends
+/// WM_WINDOWPOSCHANGED, a tooltip asks for its text. Those are queued and handled
+/// once the outermost handler returns (see `window_proc`), or, for messages
+/// that can't wait, handled as if there were no state.
+struct WindowCell {
+    state: RefCell<WindowState>,
+    deferred: RefCell<VecDeque<Deferred>>,
+    /// Text handed to the tooltip (must outlive the TTN_GETDISPINFO notification).
+    /// Kept out of the state: the tooltip asks for it while a handler has that.
+    tooltip_text: RefCell<Vec<u16>>,
+    /// The active session as of the last process poll (name, tab generation and
+    /// tabs), saved when Windows ends the session while a handler has the state,
+    /// e.g. a dialog in its modal loop (see `update_shutdown_session`)
+    shutdown_session: RefCell<Option<(String, u64, Session)>>,
 }
 
-/// Info about an overflow tab for the popup
-struct OverflowTabInfo {
-    /// Original index of this tab in the tab manager
-    index: usize,
-    /// Tab label
-    label: String,
-    /// Icon filename
-    icon: String,
-    /// Whether this tab is selected
-    is_selected: bool,
+/// Work that came up while a handler had the window's state
+enum Deferred {
+    /// A message to handle again
+    Message(u32, WPARAM, LPARAM),
+    /// The part of a message that needs the state, for messages that have to be
+    /// answered right away
+    Run(StateWork),
 }
 
-/// State for the overflow tabs popup window
-struct OverflowPopupState {
-    parent_hwnd: HWND,
-    tabs: Vec<OverflowTabInfo>,
-    hovered_item: Option<usize>,
-    /// Which item's close button is hovered (index)
-    hovered_close: Option<usize>,
-    background_color: u32,
+/// Code to run with the window's state
+type StateWork = Box<dyn FnOnce(HWND, &mut WindowState)>;
+
+/// The window's state and its queue, from WM_CREATE until WM_NCDESTROY
+unsafe fn window_cell<'a>(hwnd: HWND) -> Option<&'a WindowCell> {
+    (GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *const WindowCell).as_ref()
+}
+
+/// Borrow the window's state; None before WM_CREATE, after WM_NCDESTROY and while
+/// a handler up the stack has it
+unsafe fn window_state<'a>(hwnd: HWND) -> Option<RefMut<'a, WindowState>> {
+    window_cell(hwnd)?.state.try_borrow_mut().ok()
+}
+
+/// Run `f` with the window's state now, or once the handler that has it returns
+unsafe fn with_state_or_defer(hwnd: HWND, f: impl FnOnce(HWND, &mut WindowState) + 'static) {
+    let Some(cell) = window_cell(hwnd) else {
+        return;
+    };
+    match cell.state.try_borrow_mut() {
+        Ok(mut state) => f(hwnd, &mut state),
+        Err(_) => cell
+            .deferred
+            .borrow_mut()
+            .push_back(Deferred::Run(Box::new(f))),
+    }
+}
+
+/// Messages that wait for the state when a handler has it. They only change the
+/// state or start work, so handling them late is fine; others (input, hit
+/// testing, painting) would be stale by then.
+fn is_deferrable(msg: u32, wparam: WPARAM) -> bool {
+    matches!(
+        msg,
+        WM_TIMER
+            | WM_HOTKEY
+            | WM_CLOSE
+            | WM_MOUSELEAVE
+            | WM_ENTERSIZEMOVE
+            | WM_EXITSIZEMOVE
+            | WM_SETTINGCHANGE
+            | WM_DISPLAYCHANGE
+            | WM_DPICHANGED
+            | WM_POWERBROADCAST
+            | WM_WTSSESSION_CHANGE
+    ) || (WM_APP..0xC000).contains(&msg)
+        // The wrapper's own window menu commands
+        || (msg == WM_SYSCOMMAND && (wparam.0 & 0xFFF0) < SC_SIZE as usize)
+}
+
+/// Queue a message that arrived while a handler had the state and answer it
+unsafe fn defer_message(
+    hwnd: HWND,
+    cell: &WindowCell,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    // These point into the sender's memory, and the handlers don't read them
+    let queued_lparam = if matches!(msg, WM_SETTINGCHANGE | WM_DPICHANGED) {
+        LPARAM(0)
+    } else {
+        lparam
+    };
+    let mut deferred = cell.deferred.borrow_mut();
+    // A modal loop can run for long; repeated timer ticks only need one run
+    let queued = deferred.iter().any(|work| {
+        matches!(work, Deferred::Message(m, w, l) if (*m, *w, *l) == (msg, wparam, queued_lparam))
+    });
+    if !queued {
+        deferred.push_back(Deferred::Message(msg, wparam, queued_lparam));
+    }
+    if msg >= WM_APP || matches!(msg, WM_CLOSE | WM_SYSCOMMAND | WM_DPICHANGED) {
+        LRESULT(0)
+    } else {
+        DefWindowProcW(hwnd, msg, wparam, lparam)
+    }
+}
+
+/// Handle the work queued while a handler had the state, once none has it
+unsafe fn run_deferred(hwnd: HWND) {
+    loop {
+        // Looked up again each time: a message may destroy the window
+        let Some(cell) = window_cell(hwnd) else {
+            return;
+        };
+        let Ok(mut state) = cell.state.try_borrow_mut() else {
+            return;
+        };
+        let Some(work) = cell.deferred.borrow_mut().pop_front() else {
+            return;
+        };
+        match work {
+            Deferred::Message(msg, wparam, lparam) => {
+                drop(state);
+                handle_message(hwnd, msg, wparam, lparam);
+            }
+            Deferred::Run(f) => f(hwnd, &mut state),
+        }
+    }
+}
+
+/// Tab switcher popup shown while the next/prev tab hotkey modifiers are held
+struct TabSwitcher {
+    popup: HWND,
+    /// Tab selected when the modifiers are released
+    candidate: usize,
+    modifiers: HOT_KEY_MODIFIERS,
+}
+
+/// A touch or pen contact on the tab bar. Contacts on a tab wait to see whether
+/// they are a swipe, a tap or a press and hold (which starts dragging the tab)
+/// before going through the mouse handlers.
+struct TouchContact {
+    pointer: u32,
+    /// Client position where the contact started (snapped onto the tab bar element)
+    start: POINT,
+    started: Instant,
+    /// Replayed as mouse input from the start, or since being held
+    replaying: bool,
+    /// Already switched tabs; the rest of the contact is ignored
+    swiped: bool,
 }
 
 // Thread-local storage for config during window creation
 thread_local! {
     static INITIAL_BG_COLOR: Cell<u32> = const { Cell::new(0x1a1b26) };
     static INITIAL_CONFIG: std::cell::RefCell<Option<Config>> = const { std::cell::RefCell::new(None) };
+    static INITIAL_SESSION: std::cell::RefCell<Option<String>> = const { std::cell::RefCell::new(None) };
+    static INITIAL_OPEN_REQUEST: std::cell::RefCell<Option<OpenRequest>> = const { std::cell::RefCell::new(None) };
+    static INITIAL_DEBUG_HUD: Cell<bool> = const { Cell::new(false) };
+    /// Window class registered for the wrapper (`window_class`)
+    static WINDOW_CLASS: std::cell::RefCell<windows::core::HSTRING> = std::cell::RefCell::default();
 }
 
 /// Convert RGB color (0x00RRGGBB) to Win32 COLORREF (0x00BBGGRR)
-fn rgb_to_colorref(rgb: u32) -> u32 {
+pub(crate) fn rgb_to_colorref(rgb: u32) -> u32 {
     let r = (rgb >> 16) & 0xFF;
     let g = (rgb >> 8) & 0xFF;
     let b = rgb & 0xFF;
     (b << 16) | (g << 8) | r
 }
 
-/// Register the window class with Win32
-pub fn register_window_class(config: Config) -> Result<()> {
+/// Register the window class with Win32. `session` names the session to restore
+/// at startup and save on exit (if any); `open_request` is a folder or file to open
+/// a tab for (`--open-dir` or `open`); `debug_hud` starts with the debug HUD shown.
+pub fn register_window_class(
+    config: Config,
+    session: Option<String>,
+    open_request: Option<OpenRequest>,
+    debug_hud: bool,
+) -> Result<()> {
     // Store config for use in WM_CREATE
     let background_color = config.background_color;
+    CONTENT_INSET.store(config.content_inset, Ordering::Relaxed);
+    layout::set_tab_style(config.tab_style);
+    layout::set_window_controls(config.window_controls, config.traffic_lights);
+    layout::set_window_buttons(
+        config.show_minimize,
+        config.show_maximize,
+        config.show_close,
+    );
+    layout::set_titlebar_hidden(config.hide_titlebar);
+    process::set_hide_from_alt_tab(config.hide_neovide_from_alt_tab);
+    process::set_window_match(config.neovide_window.clone());
+    hooks::set_hooks(config.hooks.clone());
+    fonts::set_ui_font(&config.font_family, config.font_size);
     INITIAL_BG_COLOR.with(|c| c.set(background_color));
+    let class_name = windows::core::HSTRING::from(config.window_class.as_str());
+    WINDOW_CLASS.with(|c| *c.borrow_mut() = class_name.clone());
     INITIAL_CONFIG.with(|c| *c.borrow_mut() = Some(config));
+    INITIAL_SESSION.with(|c| *c.borrow_mut() = session);
+    INITIAL_OPEN_REQUEST.with(|c| *c.borrow_mut() = open_request);
+    INITIAL_DEBUG_HUD.with(|c| c.set(debug_hud));
 
     unsafe {
         let hinstance = GetModuleHandleW(None).context("Failed to get module handle")?;
@@ -234,7 +701,8 @@ pub fn register_window_class(config: Config) -> Result<()> {
 
         // Register main window class
         let wc = WNDCLASSW {
-            style: CS_HREDRAW | CS_VREDRAW,
+            // CS_DBLCLKS for the configurable tab double-click action
+            style: CS_HREDRAW | CS_VREDRAW | CS_DBLCLKS,
             lpfnWndProc: Some(window_proc),
             cbClsExtra: 0,
             cbWndExtra: 0,
@@ -243,7 +711,7 @@ pub fn register_window_class(config: Config) -> Result<()> {
             hCursor: LoadCursorW(None, IDC_ARROW).ok().unwrap_or_default(),
             hbrBackground: HBRUSH(brush.0),
             lpszMenuName: PCWSTR::null(),
-            lpszClassName: WINDOW_CLASS_NAME,
+            lpszClassName: PCWSTR(class_name.as_ptr()),
         };
 
         let atom = RegisterClassW(&wc);
@@ -251,44 +719,29 @@ pub fn register_window_class(config: Config) -> Result<()> {
             anyhow::bail!("Failed to register window class");
         }
 
-        // Register dropdown popup window class
-        let dropdown_brush = CreateSolidBrush(COLORREF(colorref));
-        let dropdown_wc = WNDCLASSW {
-            style: CS_HREDRAW | CS_VREDRAW | CS_DROPSHADOW,
-            lpfnWndProc: Some(dropdown_proc),
-            cbClsExtra: 0,
-            cbWndExtra: 0,
-            hInstance: hinstance.into(),
-            hIcon: Default::default(),
-            hCursor: LoadCursorW(None, IDC_ARROW).ok().unwrap_or_default(),
-            hbrBackground: HBRUSH(dropdown_brush.0),
-            lpszMenuName: PCWSTR::null(),
-            lpszClassName: DROPDOWN_CLASS_NAME,
-        };
+        // Register the popup list window class (dropdown, overflow and tab switcher)
+        if !popup::register_class(hinstance.into()) {
+            anyhow::bail!("Failed to register popup window class");
+        }
 
-        let dropdown_atom = RegisterClassW(&dropdown_wc);
-        if dropdown_atom == 0 {
-            anyhow::bail!("Failed to register dropdown window class");
+        // Register the tab preview window class
+        if !preview::register_class(hinstance.into()) {
+            anyhow::bail!("Failed to register tab preview window class");
         }
 
-        // Register overflow tabs popup window class
-        let overflow_brush = CreateSolidBrush(COLORREF(colorref));
-        let overflow_wc = WNDCLASSW {
-            style: CS_HREDRAW | CS_VREDRAW | CS_DROPSHADOW,
-            lpfnWndProc: Some(overflow_proc),
-            cbClsExtra: 0,
-            cbWndExtra: 0,
-            hInstance: hinstance.into(),
-            hIcon: Default::default(),
-            hCursor: LoadCursorW(None, IDC_ARROW).ok().unwrap_or_default(),
-            hbrBackground: HBRUSH(overflow_brush.0),
-            lpszMenuName: PCWSTR::null(),
-            lpszClassName: OVERFLOW_CLASS_NAME,
-        };
+        // Register the process output view window class
+        if !logview::register_class(hinstance.into()) {
+            anyhow::bail!("Failed to register output view window class");
+        }
 
-        let overflow_atom = RegisterClassW(&overflow_wc);
-        if overflow_atom == 0 {
-            anyhow::bail!("Failed to register overflow window class");
+        // Register resize grip window class (used when the content inset is small)
+        if !grips::register_class(hinstance.into()) {
+            anyhow::bail!("Failed to register resize grip window class");
+        }
+
+        // Register task manager popup window class
+        if !taskmgr::register_class(hinstance.into()) {
+            anyhow::bail!("Failed to register task manager window class");
         }
     }
 
@@ -302,13 +755,26 @@ pub fn create_window() -> Result<HWND> {
 
         // Use WS_POPUP with thick frame for resize borders, but no caption
         // WS_SYSMENU ensures the window appears in taskbar and has system menu
-        let style =
-            WS_POPUP | WS_THICKFRAME | WS_MINIMIZEBOX | WS_MAXIMIZEBOX | WS_SYSMENU | WS_VISIBLE;
-
+        // WS_CLIPCHILDREN keeps tab bar painting off the inline tab rename editor
+        let style = WS_POPUP
+            | WS_THICKFRAME
+            | WS_MINIMIZEBOX
+            | WS_MAXIMIZEBOX
+            | WS_SYSMENU
+            | WS_VISIBLE
+            | WS_CLIPCHILDREN;
+
+        // WM_CREATE adds the session name
+        let class_name = WINDOW_CLASS.with(|c| c.borrow().clone());
+        let title = INITIAL_CONFIG.with(|c| {
+            c.borrow()
+                .as_ref()
+                .map_or_else(Default::default, |config| config.window_title.clone())
+        });
         let hwnd = CreateWindowExW(
             WINDOW_EX_STYLE(0),
-            WINDOW_CLASS_NAME,
-            WINDOW_TITLE,
+            &class_name,
+            &windows::core::HSTRING::from(title),
             style,
             CW_USEDEFAULT,
             CW_USEDEFAULT,
@@ -347,10 +813,11 @@ fn get_content_rect(hwnd: HWND) -> Result<RECT> {
         let mut rect = RECT::default();
         GetClientRect(hwnd, &mut rect).context("Failed to get client rect")?;
         // Content area starts below title bar with inset from all edges
-        rect.left = CONTENT_INSET;
-        rect.top = TITLEBAR_HEIGHT + CONTENT_INSET;
-        rect.right -= CONTENT_INSET;
-        rect.bottom -= CONTENT_INSET;
+        let inset = content_inset();
+        rect.left = inset;
+        rect.top = content_top() + inset;
+        rect.right -= inset;
+        rect.bottom -= inset;
         Ok(rect)
     }
 }
@@ -378,35 +845,42 @@ fn get_full_client_rect(hwnd: HWND) -> Result<RECT> {
     }
 }
 
-/// Calculate button rectangles for the title bar
-fn get_button_rects(client_width: i32) -> (RECT, RECT, RECT) {
-    let close_rect = RECT {
-        left: client_width - BUTTON_WIDTH,
-        top: 0,
-        right: client_width,
-        bottom: TITLEBAR_HEIGHT,
-    };
-
-    let maximize_rect = RECT {
-        left: client_width - BUTTON_WIDTH * 2,
-        top: 0,
-        right: client_width - BUTTON_WIDTH,
-        bottom: TITLEBAR_HEIGHT,
-    };
+impl From<RECT> for Rect {
+    fn from(rect: RECT) -> Self {
+        Rect {
+            left: rect.left,
+            top: rect.top,
+            right: rect.right,
+            bottom: rect.bottom,
+        }
+    }
+}
 
-    let minimize_rect = RECT {
-        left: client_width - BUTTON_WIDTH * 3,
-        top: 0,
-        right: client_width - BUTTON_WIDTH * 2,
-        bottom: TITLEBAR_HEIGHT,
-    };
+impl From<Rect> for RECT {
+    fn from(rect: Rect) -> Self {
+        RECT {
+            left: rect.left,
+            top: rect.top,
+            right: rect.right,
+            bottom: rect.bottom,
+        }
+    }
+}
 
-    (minimize_rect, maximize_rect, close_rect)
+/// Calculate button rectangles for the title bar (minimize, maximize, close; see
+/// `layout::get_window_button_rects`)
+fn get_button_rects(client_width: i32) -> (RECT, RECT, RECT) {
+    let (minimize_rect, maximize_rect, close_rect) = layout::get_window_button_rects(client_width);
+    (
+        minimize_rect.into(),
+        maximize_rect.into(),
+        close_rect.into(),
+    )
 }
 
 /// Check which button (if any) contains the given point
 fn hit_test_buttons(x: i32, y: i32, client_width: i32) -> HoveredButton {
-    if !(0..TITLEBAR_HEIGHT).contains(&y) {
+    if !(0..titlebar_height()).contains(&y) {
         return HoveredButton::None;
     }
 
@@ -423,305 +897,132 @@ fn hit_test_buttons(x: i32, y: i32, client_width: i32) -> HoveredButton {
     }
 }
 
-/// Calculate the rectangle for a tab at a given index
-fn get_tab_rect(index: usize, client_width: i32) -> RECT {
-    let _ = client_width; // Reserved for future dynamic sizing
-    let left = TAB_BAR_LEFT_MARGIN + (index as i32 * TAB_WIDTH);
-    RECT {
-        left,
-        top: TAB_VERTICAL_PADDING,
-        right: left + TAB_WIDTH,
-        bottom: TITLEBAR_HEIGHT - TAB_VERTICAL_PADDING,
-    }
-}
-
-/// Calculate the rectangle for a tab's close button
-fn get_tab_close_rect(tab_rect: &RECT) -> RECT {
-    let close_left = tab_rect.right - TAB_CLOSE_PADDING - TAB_CLOSE_SIZE;
-    let close_top = (tab_rect.top + tab_rect.bottom - TAB_CLOSE_SIZE) / 2;
-    RECT {
-        left: close_left,
-        top: close_top,
-        right: close_left + TAB_CLOSE_SIZE,
-        bottom: close_top + TAB_CLOSE_SIZE,
-    }
-}
-
-/// Get the rectangle for the new tab (+) button
-/// When has_overflow is true, it's positioned after the overflow button
-fn get_new_tab_button_rect_ex(
-    visible_tab_count: usize,
-    has_overflow: bool,
-    client_width: i32,
-) -> RECT {
-    let _ = client_width; // Reserved for future dynamic sizing
-    let left = if has_overflow {
-        TAB_BAR_LEFT_MARGIN + (visible_tab_count as i32 * TAB_WIDTH) + OVERFLOW_BUTTON_WIDTH
+/// Calculate the rectangle for a tab's icon (centered in compact tabs)
+fn get_tab_icon_rect(tab_rect: &RECT) -> RECT {
+    let left = if tab_style() == TabStyle::Compact {
+        (tab_rect.left + tab_rect.right - ICON_SIZE) / 2
     } else {
-        TAB_BAR_LEFT_MARGIN + (visible_tab_count as i32 * TAB_WIDTH)
+        tab_rect.left + 6
     };
+    let top = (tab_rect.top + tab_rect.bottom - ICON_SIZE) / 2;
     RECT {
         left,
-        top: TAB_VERTICAL_PADDING,
-        right: left + NEW_TAB_BUTTON_WIDTH,
-        bottom: TITLEBAR_HEIGHT - TAB_VERTICAL_PADDING,
+        top,
+        right: left + ICON_SIZE,
+        bottom: top + ICON_SIZE,
     }
 }
 
-/// Get the rectangle for the new tab (+) button (legacy, assumes no overflow)
-#[allow(dead_code)]
-fn get_new_tab_button_rect(tab_count: usize, client_width: i32) -> RECT {
-    get_new_tab_button_rect_ex(tab_count, false, client_width)
-}
-
-/// Get the rectangle for the profile dropdown button (caret)
-fn get_dropdown_button_rect_ex(
-    visible_tab_count: usize,
-    has_overflow: bool,
-    client_width: i32,
-) -> RECT {
-    let new_tab_rect = get_new_tab_button_rect_ex(visible_tab_count, has_overflow, client_width);
+/// Calculate the clickable rectangle of an error tab's retry icon (the icon slot
+/// plus a small margin)
+fn get_tab_retry_rect(tab_rect: &RECT) -> RECT {
+    let icon_rect = get_tab_icon_rect(tab_rect);
     RECT {
-        left: new_tab_rect.right,
-        top: TAB_VERTICAL_PADDING,
-        right: new_tab_rect.right + DROPDOWN_BUTTON_WIDTH,
-        bottom: TITLEBAR_HEIGHT - TAB_VERTICAL_PADDING,
+        left: icon_rect.left - 2,
+        top: icon_rect.top - 2,
+        right: icon_rect.right + 2,
+        bottom: icon_rect.bottom + 2,
     }
 }
 
-/// Get the rectangle for the profile dropdown button (legacy, assumes no overflow)
-fn get_dropdown_button_rect(tab_count: usize, client_width: i32) -> RECT {
-    get_dropdown_button_rect_ex(tab_count, false, client_width)
-}
-
-/// Get the rectangle for the dropdown menu (unused - popup handles its own layout)
-#[allow(dead_code)]
-fn get_dropdown_menu_rect(tab_count: usize, profile_count: usize, client_width: i32) -> RECT {
-    let dropdown_btn = get_dropdown_button_rect(tab_count, client_width);
-    let menu_width = 180; // Fixed width for dropdown menu
-    let menu_height = (profile_count as i32 * DROPDOWN_ITEM_HEIGHT) + (DROPDOWN_PADDING * 2);
-
-    // Position below the dropdown button, aligned to its left edge
+/// Get the rectangle of the "Update available" badge, vertically centered in its
+/// widget's `slot`
+fn get_update_badge_rect(slot: &RECT) -> RECT {
+    let top = (slot.top + slot.bottom - UPDATE_BADGE_HEIGHT) / 2;
     RECT {
-        left: dropdown_btn.left,
-        top: TITLEBAR_HEIGHT,
-        right: dropdown_btn.left + menu_width,
-        bottom: TITLEBAR_HEIGHT + menu_height,
+        left: slot.left,
+        top,
+        right: slot.right,
+        bottom: top + UPDATE_BADGE_HEIGHT,
     }
 }
 
-/// Get the rectangle for a dropdown menu item (unused - popup handles its own layout)
-#[allow(dead_code)]
-fn get_dropdown_item_rect(
-    item_index: usize,
-    tab_count: usize,
-    profile_count: usize,
-    client_width: i32,
-) -> RECT {
-    let menu_rect = get_dropdown_menu_rect(tab_count, profile_count, client_width);
-    let top = menu_rect.top + DROPDOWN_PADDING + (item_index as i32 * DROPDOWN_ITEM_HEIGHT);
+/// Get the rectangle of the high memory banner's dismiss button, at the right
+/// end of the banner in its widget's `slot`
+fn get_memory_dismiss_rect(slot: &RECT) -> RECT {
+    let banner = get_update_badge_rect(slot);
     RECT {
-        left: menu_rect.left + DROPDOWN_PADDING,
-        top,
-        right: menu_rect.right - DROPDOWN_PADDING,
-        bottom: top + DROPDOWN_ITEM_HEIGHT,
+        left: banner.right - MEMORY_DISMISS_WIDTH,
+        ..banner
     }
 }
 
-/// Get the maximum X position for the tab bar (before window buttons)
-fn get_tab_bar_max_x(client_width: i32) -> i32 {
-    client_width - (BUTTON_WIDTH * 3) - 8 // Leave some padding before window buttons
-}
-
-/// Calculate how many tabs can be displayed before overflow
-/// Returns (visible_count, has_overflow)
-fn calculate_visible_tabs(tab_count: usize, client_width: i32) -> (usize, bool) {
-    if tab_count == 0 {
-        return (0, false);
-    }
-
-    let max_x = get_tab_bar_max_x(client_width);
-    // Reserve space for new tab button, dropdown button, and potentially overflow button
-    let reserved_space = NEW_TAB_BUTTON_WIDTH + DROPDOWN_BUTTON_WIDTH + OVERFLOW_BUTTON_WIDTH;
-    let available_width = max_x - TAB_BAR_LEFT_MARGIN - reserved_space;
-
-    let max_visible = (available_width / TAB_WIDTH).max(0) as usize;
-
-    if max_visible >= tab_count {
-        // All tabs fit (no overflow button needed, so we can reclaim that space)
-        let available_without_overflow =
-            max_x - TAB_BAR_LEFT_MARGIN - NEW_TAB_BUTTON_WIDTH - DROPDOWN_BUTTON_WIDTH;
-        let max_visible_no_overflow = (available_without_overflow / TAB_WIDTH).max(0) as usize;
-        if max_visible_no_overflow >= tab_count {
-            return (tab_count, false);
-        }
+/// Show the widgets enabled in the config that have something to show: the
+/// session name while a session is active, the update badge while there is an
+/// update. The high memory banner is shown whenever it is due.
+unsafe fn update_widgets(hwnd: HWND, state: &mut WindowState) {
+    let enabled = &state.config.widgets;
+    let mut widgets = Vec::new();
+    if enabled.session && state.session_name.is_some() {
+        widgets.push(Widget::Session);
     }
-
-    // Need overflow
-    (max_visible.min(tab_count), max_visible < tab_count)
-}
-
-/// Get the rectangle for the overflow button
-fn get_overflow_button_rect(visible_tab_count: usize, client_width: i32) -> RECT {
-    let _ = client_width; // Reserved for future dynamic sizing
-    let left = TAB_BAR_LEFT_MARGIN + (visible_tab_count as i32 * TAB_WIDTH);
-    RECT {
-        left,
-        top: TAB_VERTICAL_PADDING,
-        right: left + OVERFLOW_BUTTON_WIDTH,
-        bottom: TITLEBAR_HEIGHT - TAB_VERTICAL_PADDING,
+    if state.memory_warning.is_visible() {
+        widgets.push(Widget::Memory);
     }
-}
-
-/// Hit test in the tab bar area
-fn hit_test_tab_bar(x: i32, y: i32, tab_count: usize, client_width: i32) -> TabHitResult {
-    // Must be in the titlebar height range
-    if !(TAB_VERTICAL_PADDING..TITLEBAR_HEIGHT - TAB_VERTICAL_PADDING).contains(&y) {
-        // Could still be in the caption area if within titlebar
-        if (0..TITLEBAR_HEIGHT).contains(&y) {
-            return TabHitResult::Caption;
-        }
-        return TabHitResult::None;
+    if enabled.update && state.update.is_some() {
+        widgets.push(Widget::Update);
     }
-
-    let max_x = get_tab_bar_max_x(client_width);
-    let (visible_count, has_overflow) = calculate_visible_tabs(tab_count, client_width);
-
-    // Check each visible tab
-    for i in 0..visible_count {
-        let tab_rect = get_tab_rect(i, client_width);
-        if tab_rect.left > max_x {
-            break; // Tab bar overflow
-        }
-        if x >= tab_rect.left && x < tab_rect.right {
-            // Check if on the close button
-            let close_rect = get_tab_close_rect(&tab_rect);
-            if x >= close_rect.left
-                && x < close_rect.right
-                && y >= close_rect.top
-                && y < close_rect.bottom
-            {
-                return TabHitResult::TabClose(i);
-            }
-            return TabHitResult::Tab(i);
-        }
+    if enabled.clock {
+        widgets.push(Widget::Clock);
+        state.clock_text = clock_text();
     }
-
-    // Check overflow button if there are overflow tabs
-    if has_overflow {
-        let overflow_rect = get_overflow_button_rect(visible_count, client_width);
-        if x >= overflow_rect.left && x < overflow_rect.right {
-            return TabHitResult::OverflowButton;
-        }
+    if enabled.settings {
+        widgets.push(Widget::Settings);
     }
-
-    // Check new tab button
-    let new_tab_rect = get_new_tab_button_rect_ex(visible_count, has_overflow, client_width);
-    if new_tab_rect.right <= max_x {
-        if x >= new_tab_rect.left && x < new_tab_rect.right {
-            return TabHitResult::NewTabButton;
-        }
+    if layout::set_widgets(&widgets) {
+        let _ = InvalidateRect(hwnd, None, false);
     }
+}
 
-    // Check dropdown button
-    let dropdown_rect = get_dropdown_button_rect_ex(visible_count, has_overflow, client_width);
-    if dropdown_rect.right <= max_x {
-        if x >= dropdown_rect.left && x < dropdown_rect.right {
-            return TabHitResult::ProfileDropdown;
-        }
+/// Current time in the user's short time format, for the clock widget
+fn clock_text() -> String {
+    unsafe {
+        let mut buffer = [0u16; 64];
+        let len = GetTimeFormatEx(
+            PCWSTR::null(),
+            TIME_NOSECONDS,
+            None,
+            PCWSTR::null(),
+            Some(&mut buffer),
+        );
+        // The length includes the terminating null; 0 on failure
+        String::from_utf16_lossy(&buffer[..(len.max(1) - 1) as usize])
     }
-
-    TabHitResult::Caption
 }
 
-/// Hit test in the dropdown menu area (unused - popup handles its own hit testing)
-#[allow(dead_code)]
-fn hit_test_dropdown_menu(
+/// Hit test the tab bar like `hit_test_tab_bar`; pinned tabs have no close button
+/// and error tabs have a retry icon in place of their icon
+fn hit_test_tabs(
     x: i32,
     y: i32,
-    tab_count: usize,
-    profile_count: usize,
+    tab_manager: &TabManager<NeovideProcess>,
     client_width: i32,
 ) -> TabHitResult {
-    let menu_rect = get_dropdown_menu_rect(tab_count, profile_count, client_width);
-
-    // Check if in the menu bounds
-    if x >= menu_rect.left && x < menu_rect.right && y >= menu_rect.top && y < menu_rect.bottom {
-        // Check which item
-        for i in 0..profile_count {
-            let item_rect = get_dropdown_item_rect(i, tab_count, profile_count, client_width);
-            if y >= item_rect.top && y < item_rect.bottom {
-                return TabHitResult::DropdownItem(i);
+    match hit_test_tab_bar(x, y, tab_manager.count(), client_width) {
+        TabHitResult::TabClose(i) if tab_manager.is_tab_pinned(i) => TabHitResult::Tab(i),
+        TabHitResult::Tab(i) if tab_manager.get_tab_spawn_error(i).is_some() => {
+            let retry_rect = get_tab_retry_rect(&get_tab_rect(i, client_width).into());
+            if x >= retry_rect.left
+                && x < retry_rect.right
+                && y >= retry_rect.top
+                && y < retry_rect.bottom
+            {
+                TabHitResult::TabRetry(i)
+            } else {
+                TabHitResult::Tab(i)
             }
         }
-    }
-
-    TabHitResult::None
-}
-
-/// Calculate the target index for dropping a tab at position x
-#[cfg(test)]
-fn calculate_drop_index(x: i32, tab_count: usize, client_width: i32) -> usize {
-    let _ = client_width; // Reserved for future dynamic sizing
-
-    // Calculate which slot the mouse is over
-    let relative_x = x - TAB_BAR_LEFT_MARGIN;
-    if relative_x < 0 {
-        return 0;
-    }
-
-    let index = (relative_x / TAB_WIDTH) as usize;
-    if index >= tab_count {
-        tab_count.saturating_sub(1)
-    } else {
-        index
+        hit => hit,
     }
 }
 
-/// Calculate if a tab swap should occur during drag based on 50% threshold.
-/// Returns Some(target_index) if a swap should occur, None otherwise.
-///
-/// The swap logic:
-/// - When dragging right: swap when the dragged tab's center crosses past the center of the next tab
-/// - When dragging left: swap when the dragged tab's center crosses past the center of the previous tab
-fn calculate_swap_target(
-    drag_tab_index: usize,
-    drag_visual_x: i32,
-    tab_count: usize,
-    _client_width: i32,
-) -> Option<usize> {
-    if tab_count <= 1 {
-        return None;
-    }
-
-    // Calculate the center of the dragged tab at its visual position
-    let drag_center = drag_visual_x + TAB_WIDTH / 2;
-
-    // Check swap with the tab to the right
-    if drag_tab_index < tab_count - 1 {
-        let right_tab_index = drag_tab_index + 1;
-        let right_tab_rect = get_tab_rect(right_tab_index, 0);
-        let right_tab_center = (right_tab_rect.left + right_tab_rect.right) / 2;
-
-        // If dragged tab center is past the right tab's center, swap right
-        if drag_center > right_tab_center {
-            return Some(right_tab_index);
-        }
-    }
-
-    // Check swap with the tab to the left
-    if drag_tab_index > 0 {
-        let left_tab_index = drag_tab_index - 1;
-        let left_tab_rect = get_tab_rect(left_tab_index, 0);
-        let left_tab_center = (left_tab_rect.left + left_tab_rect.right) / 2;
-
-        // If dragged tab center is past the left tab's center (to the left), swap left
-        if drag_center < left_tab_center {
-            return Some(left_tab_index);
-        }
+/// Whether the mouse pointer is over the wrapper's title bar
+unsafe fn pointer_over_title_bar(hwnd: HWND) -> bool {
+    let mut pt = POINT::default();
+    if GetCursorPos(&mut pt).is_err() || WindowFromPoint(pt) != hwnd {
+        return false;
     }
-
-    None
+    ScreenToClient(hwnd, &mut pt).as_bool() && (0..titlebar_height()).contains(&pt.y)
 }
 
 /// Paint a single tab
@@ -734,6 +1035,13 @@ fn paint_tab(
     is_selected: bool,
     is_hovered: bool,
     close_hovered: bool,
+    is_dimmed: bool,
+    is_not_responding: bool,
+    is_pinned: bool,
+    spawn_failed: bool,
+    retry_hovered: bool,
+    attention: f32,
+    subtitle: Option<&str>,
     background_color: u32,
 ) {
     unsafe {
@@ -745,20 +1053,26 @@ fn paint_tab(
         } else {
             TAB_UNSELECTED_COLOR
         };
+        // Background tabs asking for attention pulse towards the attention color
+        let tab_bg = blend_rgb(
+            tab_bg,
+            ATTENTION_COLOR,
+            (attention * ATTENTION_MAX_BLEND) as u32,
+        );
 
         let tab_brush = CreateSolidBrush(COLORREF(rgb_to_colorref(tab_bg)));
         FillRect(hdc, tab_rect, tab_brush);
         DeleteObject(HGDIOBJ(tab_brush.0));
 
         // Draw outline around tab (top, left, right)
-        // For selected tabs, extend sides down to the bottom line (TITLEBAR_HEIGHT - 1)
+        // For selected tabs, extend sides down to the bottom line (titlebar height - 1)
         // For unselected tabs, stop at the tab rect bottom
         let outline_pen = CreatePen(PS_SOLID, 1, COLORREF(rgb_to_colorref(TAB_OUTLINE_COLOR)));
         let old_pen = SelectObject(hdc, HGDIOBJ(outline_pen.0));
 
         // Selected tabs extend down to connect with the tab bar bottom line
         let side_bottom = if is_selected {
-            TITLEBAR_HEIGHT - 1
+            titlebar_height() - 1
         } else {
             tab_rect.bottom
         };
@@ -771,14 +1085,30 @@ fn paint_tab(
         SelectObject(hdc, old_pen);
         DeleteObject(HGDIOBJ(outline_pen.0));
 
-        // Calculate icon position (centered vertically, with padding from left)
-        let icon_x = tab_rect.left + 6;
-        let icon_y = (tab_rect.top + tab_rect.bottom - ICON_SIZE) / 2;
+        // Calculate icon position (centered vertically, with padding from left, or
+        // centered in compact tabs)
+        let compact = tab_style() == TabStyle::Compact;
+        let icon_rect = get_tab_icon_rect(tab_rect);
+        let (icon_x, icon_y) = (icon_rect.left, icon_rect.top);
 
-        // Draw icon if available
-        let label_offset = if let Some(filename) = icon_filename {
+        // Error tabs show a retry icon instead of the profile icon
+        let label_offset = if spawn_failed {
+            if retry_hovered {
+                let hover_brush = CreateSolidBrush(COLORREF(rgb_to_colorref(TAB_HOVER_COLOR)));
+                FillRect(hdc, &get_tab_retry_rect(tab_rect), hover_brush);
+                DeleteObject(HGDIOBJ(hover_brush.0));
+            }
+            paint_retry_glyph(hdc, &icon_rect, SPAWN_ERROR_COLOR);
+            ICON_SIZE + 4
+        } else if let Some(filename) = icon_filename {
             if let Some(hbitmap) = get_icon_bitmap(filename) {
-                paint_icon(hdc, hbitmap, icon_x, icon_y, ICON_SIZE, ICON_SIZE);
+                // Suspended tabs and placeholders of starting tabs are drawn dimmed
+                let opacity = if is_dimmed {
+                    SUSPENDED_TAB_OPACITY
+                } else {
+                    255
+                };
+                paint_icon_with_alpha(hdc, hbitmap, icon_x, icon_y, ICON_SIZE, ICON_SIZE, opacity);
                 ICON_SIZE + 4 // Icon width + padding
             } else {
                 0
@@ -787,76 +1117,119 @@ fn paint_tab(
             0
         };
 
-        // Draw tab label
-        SetBkMode(hdc, TRANSPARENT);
-        SetTextColor(hdc, COLORREF(0x00FFFFFF)); // White text
+        // Badge the icon corner when Neovide stopped responding
+        if is_not_responding {
+            let badge_left = icon_x + ICON_SIZE - NOT_RESPONDING_BADGE_SIZE + 2;
+            let badge_top = icon_y + ICON_SIZE - NOT_RESPONDING_BADGE_SIZE + 2;
+            let badge_brush =
+                CreateSolidBrush(COLORREF(rgb_to_colorref(NOT_RESPONDING_BADGE_COLOR)));
+            let badge_pen = CreatePen(PS_SOLID, 1, COLORREF(rgb_to_colorref(tab_bg)));
+            let old_brush = SelectObject(hdc, HGDIOBJ(badge_brush.0));
+            let old_pen = SelectObject(hdc, HGDIOBJ(badge_pen.0));
+            Ellipse(
+                hdc,
+                badge_left,
+                badge_top,
+                badge_left + NOT_RESPONDING_BADGE_SIZE,
+                badge_top + NOT_RESPONDING_BADGE_SIZE,
+            );
+
+            // Exclamation mark in the tab background color
+            let mark_x = badge_left + NOT_RESPONDING_BADGE_SIZE / 2;
+            MoveToEx(hdc, mark_x, badge_top + 2, None);
+            LineTo(hdc, mark_x, badge_top + 6);
+            MoveToEx(hdc, mark_x, badge_top + 7, None);
+            LineTo(hdc, mark_x, badge_top + 8);
+
+            SelectObject(hdc, old_pen);
+            SelectObject(hdc, old_brush);
+            DeleteObject(HGDIOBJ(badge_pen.0));
+            DeleteObject(HGDIOBJ(badge_brush.0));
+        }
 
-        let mut lf = LOGFONTW::default();
-        lf.lfHeight = -11;
-        lf.lfWeight = 400;
-        let font_name = "Segoe UI";
-        for (i, c) in font_name.encode_utf16().enumerate() {
-            if i < 32 {
-                lf.lfFaceName[i] = c;
+        // Draw tab label (white, the error color for error tabs, or dimmed towards
+        // the tab background)
+        SetBkMode(hdc, TRANSPARENT);
+        let text_color = if spawn_failed {
+            SPAWN_ERROR_COLOR
+        } else if is_dimmed {
+            blend_rgb(tab_bg, 0xFFFFFF, SUSPENDED_TAB_OPACITY as u32)
+        } else {
+            0xFFFFFF
+        };
+        SetTextColor(hdc, COLORREF(rgb_to_colorref(text_color)));
+
+        // Compact tabs show only the icon (the title is in the tooltip), or the
+        // title's first letter while there's no icon to show
+        if compact {
+            if label_offset == 0 {
+                let initial: Vec<u16> = label
+                    .chars()
+                    .next()
+                    .map(|c| c.to_uppercase().collect::<String>())
+                    .unwrap_or_default()
+                    .encode_utf16()
+                    .collect();
+                let old_font = fonts::select_font_for_text(hdc, TextSize::Label, 600, &initial);
+                let mut initial_size = SIZE::default();
+                GetTextExtentPoint32W(hdc, &initial, &mut initial_size);
+                TextOutW(
+                    hdc,
+                    (tab_rect.left + tab_rect.right - initial_size.cx) / 2,
+                    (tab_rect.top + tab_rect.bottom - initial_size.cy) / 2,
+                    &initial,
+                );
+                SelectObject(hdc, old_font);
             }
+            return;
         }
-        let font = CreateFontIndirectW(&lf);
-        let old_font = SelectObject(hdc, HGDIOBJ(font.0));
+
+        // Two-line tabs show the working directory below the title in the smaller
+        // caption font; measure it first so both lines can be centered together
+        let subtitle_wide: Option<Vec<u16>> = subtitle.map(|s| s.encode_utf16().collect());
+        let subtitle_height = match &subtitle_wide {
+            Some(wide) => {
+                let old_font = fonts::select_font_for_text(hdc, TextSize::Caption, 400, wide);
+                let height = current_text_height(hdc);
+                SelectObject(hdc, old_font);
+                height + SUBTITLE_GAP
+            }
+            None => 0,
+        };
+
+        // Falls back to another font for titles the configured one can't display
+        let label_wide: Vec<u16> = label.encode_utf16().collect();
+        let old_font = fonts::select_font_for_text(hdc, TextSize::Label, 400, &label_wide);
 
         // Get actual text metrics for proper vertical centering
-        let mut tm = TEXTMETRICW::default();
-        GetTextMetricsW(hdc, &mut tm);
-        let text_height = tm.tmHeight;
+        let text_height = current_text_height(hdc);
 
         // Label position (after icon, leaving room for close button)
         // Center text vertically using actual text height
         let label_x = tab_rect.left + 6 + label_offset;
-        let label_y = (tab_rect.top + tab_rect.bottom - text_height) / 2;
+        let label_y = (tab_rect.top + tab_rect.bottom - text_height - subtitle_height) / 2;
 
         // Calculate available width for text (between icon and close button)
-        let close_rect = get_tab_close_rect(tab_rect);
+        let close_rect: RECT = get_tab_close_rect(&Rect::from(*tab_rect)).into();
         let max_text_width = close_rect.left - label_x - 4; // 4px padding before close button
 
-        // Measure text width and truncate with ellipsis if needed
-        let label_wide: Vec<u16> = label.encode_utf16().collect();
-        let mut text_size = SIZE::default();
-        GetTextExtentPoint32W(hdc, &label_wide, &mut text_size);
+        text_out_truncated(hdc, label_x, label_y, label, max_text_width);
+        SelectObject(hdc, old_font);
 
-        if text_size.cx <= max_text_width {
-            // Text fits - draw normally
-            TextOutW(hdc, label_x, label_y, &label_wide);
-        } else {
-            // Text too wide - truncate with ellipsis
-            let ellipsis = "...";
-            let ellipsis_wide: Vec<u16> = ellipsis.encode_utf16().collect();
-            let mut ellipsis_size = SIZE::default();
-            GetTextExtentPoint32W(hdc, &ellipsis_wide, &mut ellipsis_size);
-
-            let available_for_text = max_text_width - ellipsis_size.cx;
-            if available_for_text > 0 {
-                // Find how many characters fit
-                let mut truncated = String::new();
-                for ch in label.chars() {
-                    let test = format!("{}{}", truncated, ch);
-                    let test_wide: Vec<u16> = test.encode_utf16().collect();
-                    let mut test_size = SIZE::default();
-                    GetTextExtentPoint32W(hdc, &test_wide, &mut test_size);
-                    if test_size.cx > available_for_text {
-                        break;
-                    }
-                    truncated.push(ch);
-                }
-                truncated.push_str(ellipsis);
-                let truncated_wide: Vec<u16> = truncated.encode_utf16().collect();
-                TextOutW(hdc, label_x, label_y, &truncated_wide);
-            } else {
-                // Not even ellipsis fits - just draw ellipsis
-                TextOutW(hdc, label_x, label_y, &ellipsis_wide);
-            }
+        if let (Some(subtitle), Some(wide)) = (subtitle, &subtitle_wide) {
+            let subtitle_color = blend_rgb(tab_bg, text_color, SUBTITLE_TEXT_BLEND);
+            SetTextColor(hdc, COLORREF(rgb_to_colorref(subtitle_color)));
+            let old_font = fonts::select_font_for_text(hdc, TextSize::Caption, 400, wide);
+            let subtitle_y = label_y + text_height + SUBTITLE_GAP;
+            text_out_truncated(hdc, label_x, subtitle_y, subtitle, max_text_width);
+            SelectObject(hdc, old_font);
         }
 
-        SelectObject(hdc, old_font);
-        DeleteObject(HGDIOBJ(font.0));
+        // Pinned tabs show a pin where the close button would be
+        if is_pinned {
+            paint_pin_glyph(hdc, &close_rect);
+            return;
+        }
 
         // Draw close button (close_rect already calculated above for text truncation)
         // Close button background on hover
@@ -885,27 +1258,169 @@ fn paint_tab(
     }
 }
 
-/// Paint an icon bitmap to the device context with alpha blending
+/// Height of the font selected into `hdc`
+fn current_text_height(hdc: windows::Win32::Graphics::Gdi::HDC) -> i32 {
+    let mut tm = TEXTMETRICW::default();
+    unsafe {
+        let _ = GetTextMetricsW(hdc, &mut tm);
+    }
+    tm.tmHeight
+}
+
+/// Draw `text` at (x, y), truncated with an ellipsis if it is wider than `max_width`
 #[allow(unused_must_use)]
-fn paint_icon(
+pub(crate) fn text_out_truncated(
     hdc: windows::Win32::Graphics::Gdi::HDC,
-    hbitmap: HBITMAP,
     x: i32,
     y: i32,
-    dest_width: i32,
-    dest_height: i32,
+    text: &str,
+    max_width: i32,
 ) {
     unsafe {
-        // Get bitmap dimensions
-        let mut bm = BITMAP::default();
-        let bm_size = std::mem::size_of::<BITMAP>() as i32;
-        if GetObjectW(
-            HGDIOBJ(hbitmap.0),
-            bm_size,
-            Some(&mut bm as *mut _ as *mut std::ffi::c_void),
-        ) == 0
-        {
-            return;
+        let text_wide: Vec<u16> = text.encode_utf16().collect();
+
+        // Measure text width and truncate with ellipsis if needed
+        let mut text_size = SIZE::default();
+        GetTextExtentPoint32W(hdc, &text_wide, &mut text_size);
+
+        if text_size.cx <= max_width {
+            // Text fits - draw normally
+            TextOutW(hdc, x, y, &text_wide);
+            return;
+        }
+
+        // Text too wide - truncate with ellipsis
+        let ellipsis = "...";
+        let ellipsis_wide: Vec<u16> = ellipsis.encode_utf16().collect();
+        let mut ellipsis_size = SIZE::default();
+        GetTextExtentPoint32W(hdc, &ellipsis_wide, &mut ellipsis_size);
+
+        let available_for_text = max_width - ellipsis_size.cx;
+        if available_for_text > 0 {
+            // Find how many characters fit
+            let mut truncated = String::new();
+            for ch in text.chars() {
+                let test = format!("{}{}", truncated, ch);
+                let test_wide: Vec<u16> = test.encode_utf16().collect();
+                let mut test_size = SIZE::default();
+                GetTextExtentPoint32W(hdc, &test_wide, &mut test_size);
+                if test_size.cx > available_for_text {
+                    break;
+                }
+                truncated.push(ch);
+            }
+            truncated.push_str(ellipsis);
+            let truncated_wide: Vec<u16> = truncated.encode_utf16().collect();
+            TextOutW(hdc, x, y, &truncated_wide);
+        } else {
+            // Not even ellipsis fits - just draw ellipsis
+            TextOutW(hdc, x, y, &ellipsis_wide);
+        }
+    }
+}
+
+/// Abbreviated working directory shown below the title of two-line tabs
+fn tab_subtitle(tab_manager: &TabManager<NeovideProcess>, index: usize) -> Option<String> {
+    if tab_style() != TabStyle::TwoLine {
+        return None;
+    }
+    let dir = tab_manager.get_tab_working_directory(index)?;
+    Some(abbreviate_working_directory(
+        dir,
+        dirs::home_dir().as_deref(),
+    ))
+}
+
+/// Paint the retry marker of an error tab (a circular arrow) in `rect`
+#[allow(unused_must_use)]
+fn paint_retry_glyph(hdc: windows::Win32::Graphics::Gdi::HDC, rect: &RECT, color: u32) {
+    unsafe {
+        let cx = (rect.left + rect.right) / 2;
+        let cy = (rect.top + rect.bottom) / 2;
+        let radius = 5;
+        let pen = CreatePen(PS_SOLID, 2, COLORREF(rgb_to_colorref(color)));
+        let old_pen = SelectObject(hdc, HGDIOBJ(pen.0));
+
+        // Three quarters of a circle, open at the top right, counterclockwise from
+        // the top right round to the right
+        Arc(
+            hdc,
+            cx - radius,
+            cy - radius,
+            cx + radius + 1,
+            cy + radius + 1,
+            cx + radius,
+            cy - radius,
+            cx + radius,
+            cy,
+        );
+        // Arrowhead at the open end, pointing back along the circle
+        MoveToEx(hdc, cx + 1, cy - radius - 1, None);
+        LineTo(hdc, cx + 4, cy - radius + 2);
+        LineTo(hdc, cx, cy - radius + 4);
+
+        SelectObject(hdc, old_pen);
+        DeleteObject(HGDIOBJ(pen.0));
+    }
+}
+
+/// Paint the pin marker of a pinned tab (a pin head over a short needle) in `rect`
+#[allow(unused_must_use)]
+fn paint_pin_glyph(hdc: windows::Win32::Graphics::Gdi::HDC, rect: &RECT) {
+    unsafe {
+        let cx = (rect.left + rect.right) / 2;
+        let cy = (rect.top + rect.bottom) / 2;
+        let pen = CreatePen(PS_SOLID, 1, COLORREF(0x00FFFFFF));
+        let brush = CreateSolidBrush(COLORREF(0x00FFFFFF));
+        let old_pen = SelectObject(hdc, HGDIOBJ(pen.0));
+        let old_brush = SelectObject(hdc, HGDIOBJ(brush.0));
+
+        Ellipse(hdc, cx - 3, cy - 5, cx + 4, cy + 2);
+        MoveToEx(hdc, cx, cy + 2, None);
+        LineTo(hdc, cx, cy + 6);
+
+        SelectObject(hdc, old_pen);
+        SelectObject(hdc, old_brush);
+        DeleteObject(HGDIOBJ(pen.0));
+        DeleteObject(HGDIOBJ(brush.0));
+    }
+}
+
+/// Paint an icon bitmap to the device context with alpha blending
+#[allow(unused_must_use)]
+pub(crate) fn paint_icon(
+    hdc: windows::Win32::Graphics::Gdi::HDC,
+    hbitmap: HBITMAP,
+    x: i32,
+    y: i32,
+    dest_width: i32,
+    dest_height: i32,
+) {
+    paint_icon_with_alpha(hdc, hbitmap, x, y, dest_width, dest_height, 255);
+}
+
+/// Paint an icon bitmap with an additional constant opacity (255 = fully opaque)
+#[allow(unused_must_use)]
+fn paint_icon_with_alpha(
+    hdc: windows::Win32::Graphics::Gdi::HDC,
+    hbitmap: HBITMAP,
+    x: i32,
+    y: i32,
+    dest_width: i32,
+    dest_height: i32,
+    opacity: u8,
+) {
+    unsafe {
+        // Get bitmap dimensions
+        let mut bm = BITMAP::default();
+        let bm_size = std::mem::size_of::<BITMAP>() as i32;
+        if GetObjectW(
+            HGDIOBJ(hbitmap.0),
+            bm_size,
+            Some(&mut bm as *mut _ as *mut std::ffi::c_void),
+        ) == 0
+        {
+            return;
         }
 
         // Create compatible DC for the bitmap
@@ -920,7 +1435,7 @@ fn paint_icon(
         let blend_func = BLENDFUNCTION {
             BlendOp: AC_SRC_OVER as u8,
             BlendFlags: 0,
-            SourceConstantAlpha: 255, // Use per-pixel alpha
+            SourceConstantAlpha: opacity, // Per-pixel alpha scaled by the opacity
             AlphaFormat: AC_SRC_ALPHA as u8,
         };
 
@@ -1005,116 +1520,45 @@ fn paint_dropdown_button(hdc: windows::Win32::Graphics::Gdi::HDC, rect: &RECT, i
     }
 }
 
-/// Paint the dropdown menu (unused - popup renders itself)
-#[allow(unused_must_use, dead_code)]
-fn paint_dropdown_menu(
-    hdc: windows::Win32::Graphics::Gdi::HDC,
-    profiles: &[Profile],
-    tab_count: usize,
-    hovered_item: Option<usize>,
-    client_width: i32,
-    background_color: u32,
-) {
+/// Work area of the monitor showing most of `hwnd`, in screen coordinates
+fn monitor_work_area(hwnd: HWND) -> Option<Rect> {
     unsafe {
-        let profile_count = profiles.len();
-        let menu_rect = get_dropdown_menu_rect(tab_count, profile_count, client_width);
-
-        // Draw menu background
-        let bg_brush = CreateSolidBrush(COLORREF(rgb_to_colorref(background_color)));
-        FillRect(hdc, &menu_rect, bg_brush);
-        DeleteObject(HGDIOBJ(bg_brush.0));
-
-        // Draw menu border
-        let border_pen = CreatePen(PS_SOLID, 1, COLORREF(rgb_to_colorref(TAB_OUTLINE_COLOR)));
-        let old_pen = SelectObject(hdc, HGDIOBJ(border_pen.0));
-
-        MoveToEx(hdc, menu_rect.left, menu_rect.top, None);
-        LineTo(hdc, menu_rect.right - 1, menu_rect.top);
-        LineTo(hdc, menu_rect.right - 1, menu_rect.bottom - 1);
-        LineTo(hdc, menu_rect.left, menu_rect.bottom - 1);
-        LineTo(hdc, menu_rect.left, menu_rect.top);
-
-        SelectObject(hdc, old_pen);
-        DeleteObject(HGDIOBJ(border_pen.0));
-
-        // Draw each menu item
-        for (i, profile) in profiles.iter().enumerate() {
-            let item_rect = get_dropdown_item_rect(i, tab_count, profile_count, client_width);
-            let is_hovered = hovered_item == Some(i);
-
-            // Item background on hover
-            if is_hovered {
-                let hover_brush = CreateSolidBrush(COLORREF(rgb_to_colorref(TAB_HOVER_COLOR)));
-                FillRect(hdc, &item_rect, hover_brush);
-                DeleteObject(HGDIOBJ(hover_brush.0));
-            }
-
-            // Draw profile name
-            SetBkMode(hdc, TRANSPARENT);
-            SetTextColor(hdc, COLORREF(0x00FFFFFF)); // White text
-
-            let mut lf = LOGFONTW::default();
-            lf.lfHeight = -12;
-            lf.lfWeight = 400;
-            let font_name = "Segoe UI";
-            for (j, c) in font_name.encode_utf16().enumerate() {
-                if j < 32 {
-                    lf.lfFaceName[j] = c;
-                }
-            }
-            let font = CreateFontIndirectW(&lf);
-            let old_font = SelectObject(hdc, HGDIOBJ(font.0));
-
-            // Text position (with left padding for icon space)
-            let text_x = item_rect.left + 24; // Leave space for icon
-            let text_y = (item_rect.top + item_rect.bottom - 12) / 2;
-            let name_wide: Vec<u16> = profile.name.encode_utf16().collect();
-            TextOutW(hdc, text_x, text_y, &name_wide);
-
-            SelectObject(hdc, old_font);
-            DeleteObject(HGDIOBJ(font.0));
-        }
+        let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
+        let mut info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+            ..Default::default()
+        };
+        GetMonitorInfoW(monitor, &mut info)
+            .as_bool()
+            .then(|| info.rcWork.into())
     }
 }
 
-/// Create the dropdown popup window
-fn create_dropdown_popup(
-    parent_hwnd: HWND,
-    profiles: Vec<Profile>,
-    background_color: u32,
-    x: i32,
-    y: i32,
-    width: i32,
-    height: i32,
-) -> Option<HWND> {
+/// Screen rectangle of a `width` x `height` popup opened from `button` (client
+/// coordinates of `parent_hwnd`): below the button, or above it and shifted left
+/// where the monitor's work area ends
+fn popup_rect(parent_hwnd: HWND, button: Rect, width: i32, height: i32) -> Rect {
+    let mut top_left = POINT {
+        x: button.left,
+        y: button.top,
+    };
     unsafe {
-        let hinstance = GetModuleHandleW(None).ok()?;
-
-        // Create popup state
-        let popup_state = Box::new(DropdownPopupState {
-            parent_hwnd,
-            profiles,
-            hovered_item: None,
-            background_color,
-        });
-
-        let hwnd = CreateWindowExW(
-            WS_EX_TOPMOST | WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE,
-            DROPDOWN_CLASS_NAME,
-            w!(""),
-            WS_POPUP | WS_VISIBLE,
-            x,
-            y,
-            width,
-            height,
-            None, // No parent - independent window
-            None,
-            hinstance,
-            Some(Box::into_raw(popup_state) as *const std::ffi::c_void),
-        )
-        .ok()?;
-
-        Some(hwnd)
+        let _ = ClientToScreen(parent_hwnd, &mut top_left);
+    }
+    let button = Rect {
+        left: top_left.x,
+        top: top_left.y,
+        right: top_left.x + (button.right - button.left),
+        bottom: top_left.y + (button.bottom - button.top),
+    };
+    match monitor_work_area(parent_hwnd) {
+        Some(work_area) => button.popup_below(width, height, &work_area),
+        None => Rect {
+            left: button.left,
+            top: button.bottom,
+            right: button.left + width,
+            bottom: button.bottom + height,
+        },
     }
 }
 
@@ -1139,31 +1583,80 @@ fn show_dropdown_popup(parent_hwnd: HWND, state: &mut WindowState) {
         let dropdown_btn =
             get_dropdown_button_rect_ex(visible_count, has_overflow, client_rect.right);
 
-        // Convert button position to screen coordinates
-        let mut screen_pt = POINT {
-            x: dropdown_btn.left,
-            y: dropdown_btn.bottom,
-        };
-        ClientToScreen(parent_hwnd, &mut screen_pt);
-
-        let profile_count = state.config.profiles.len();
+        // One item per profile and per group of discovered profiles, then the
+        // action entries below a separator
+        let mut items: Vec<PopupItem> = state
+            .config
+            .profiles
+            .iter()
+            .enumerate()
+            .filter(|(_, profile)| profile.group.is_none())
+            .map(|(i, profile)| PopupItem {
+                icon: Some(profile.icon.clone()),
+                label: profile.name.clone(),
+                selected: false,
+                separator: false,
+                header: false,
+                command: PopupCommand {
+                    msg: WM_APP,
+                    wparam: i,
+                },
+                close_command: None,
+            })
+            .collect();
+        for (i, group) in state.config.profile_groups().into_iter().enumerate() {
+            let icon = state
+                .config
+                .profiles
+                .iter()
+                .find(|profile| profile.group.as_deref() == Some(group))
+                .map(|profile| profile.icon.clone());
+            items.push(PopupItem {
+                icon,
+                label: format!("{} \u{25B8}", group),
+                selected: false,
+                separator: false,
+                header: false,
+                command: PopupCommand {
+                    msg: WM_PROFILE_GROUP_MENU,
+                    wparam: i,
+                },
+                close_command: None,
+            });
+        }
+        let actions = [
+            (NEW_TAB_IN_FOLDER_LABEL, WM_APP + 5),
+            (SWITCH_SESSION_LABEL, WM_SESSION_MENU),
+        ];
+        for (i, (label, msg)) in actions.into_iter().enumerate() {
+            items.push(PopupItem {
+                icon: None,
+                label: label.to_string(),
+                selected: false,
+                separator: i == 0,
+                header: false,
+                command: PopupCommand { msg, wparam: 0 },
+                close_command: None,
+            });
+        }
         let menu_width = 150;
-        let menu_height = (profile_count as i32 * DROPDOWN_ITEM_HEIGHT) + (DROPDOWN_PADDING * 2);
+        let menu_height = popup::list_height(items.len());
+        let rect = popup_rect(parent_hwnd, dropdown_btn, menu_width, menu_height);
 
         // IMPORTANT: Clicking on our title bar brought our window to the foreground,
         // which covers the Neovide window. We need to bring Neovide back to the
         // foreground BEFORE showing the popup (which is topmost and will appear above it).
         state.tab_manager.bring_selected_to_foreground();
 
-        if let Some(popup_hwnd) = create_dropdown_popup(
-            parent_hwnd,
-            state.config.profiles.clone(),
-            state.background_color,
-            screen_pt.x,
-            screen_pt.y,
-            menu_width,
-            menu_height,
-        ) {
+        let list = PopupList {
+            owner: parent_hwnd,
+            items,
+            background_color: state.background_color,
+            dismissed: WM_APP + 1,
+            passive: false,
+            filter: None,
+        };
+        if let Some(popup_hwnd) = popup::open(list, rect) {
             state.dropdown_hwnd = Some(popup_hwnd);
             state.dropdown_state = DropdownState::Open;
         }
@@ -1181,246 +1674,6 @@ fn hide_dropdown_popup(_parent_hwnd: HWND, state: &mut WindowState) {
     state.dropdown_state = DropdownState::Closed;
 }
 
-/// Window procedure for the dropdown popup
-#[allow(unused_must_use)]
-unsafe extern "system" fn dropdown_proc(
-    hwnd: HWND,
-    msg: u32,
-    wparam: WPARAM,
-    lparam: LPARAM,
-) -> LRESULT {
-    unsafe {
-        match msg {
-            WM_CREATE => {
-                let create_struct = lparam.0 as *const CREATESTRUCTW;
-                if !create_struct.is_null() {
-                    let state_ptr = (*create_struct).lpCreateParams as *mut DropdownPopupState;
-                    SetWindowLongPtrW(hwnd, GWLP_USERDATA, state_ptr as isize);
-                }
-                // Capture mouse to detect clicks outside the popup
-                SetCapture(hwnd);
-                LRESULT(0)
-            }
-
-            WM_PAINT => {
-                let mut ps = PAINTSTRUCT::default();
-                let hdc = BeginPaint(hwnd, &mut ps);
-
-                let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut DropdownPopupState;
-                if !state_ptr.is_null() {
-                    let state = &*state_ptr;
-
-                    let mut rect = RECT::default();
-                    GetClientRect(hwnd, &mut rect).ok();
-
-                    // Fill background
-                    let bg_brush =
-                        CreateSolidBrush(COLORREF(rgb_to_colorref(state.background_color)));
-                    FillRect(hdc, &rect, bg_brush);
-                    DeleteObject(HGDIOBJ(bg_brush.0));
-
-                    // Draw border
-                    let border_pen =
-                        CreatePen(PS_SOLID, 1, COLORREF(rgb_to_colorref(TAB_OUTLINE_COLOR)));
-                    let old_pen = SelectObject(hdc, HGDIOBJ(border_pen.0));
-                    MoveToEx(hdc, rect.left, rect.top, None);
-                    LineTo(hdc, rect.right - 1, rect.top);
-                    LineTo(hdc, rect.right - 1, rect.bottom - 1);
-                    LineTo(hdc, rect.left, rect.bottom - 1);
-                    LineTo(hdc, rect.left, rect.top);
-                    SelectObject(hdc, old_pen);
-                    DeleteObject(HGDIOBJ(border_pen.0));
-
-                    // Draw each profile item
-                    for (i, profile) in state.profiles.iter().enumerate() {
-                        let item_top = DROPDOWN_PADDING + (i as i32 * DROPDOWN_ITEM_HEIGHT);
-                        let item_rect = RECT {
-                            left: DROPDOWN_PADDING,
-                            top: item_top,
-                            right: rect.right - DROPDOWN_PADDING,
-                            bottom: item_top + DROPDOWN_ITEM_HEIGHT,
-                        };
-
-                        // Hover background
-                        if state.hovered_item == Some(i) {
-                            let hover_brush =
-                                CreateSolidBrush(COLORREF(rgb_to_colorref(TAB_HOVER_COLOR)));
-                            FillRect(hdc, &item_rect, hover_brush);
-                            DeleteObject(HGDIOBJ(hover_brush.0));
-                        }
-
-                        // Draw icon
-                        let icon_x = item_rect.left + 4;
-                        let icon_y = (item_rect.top + item_rect.bottom - ICON_SIZE) / 2;
-                        if let Some(hbitmap) = get_icon_bitmap(&profile.icon) {
-                            paint_icon(hdc, hbitmap, icon_x, icon_y, ICON_SIZE, ICON_SIZE);
-                        }
-
-                        // Draw text
-                        SetBkMode(hdc, TRANSPARENT);
-                        SetTextColor(hdc, COLORREF(0x00FFFFFF));
-
-                        let mut lf = LOGFONTW::default();
-                        lf.lfHeight = -12;
-                        lf.lfWeight = 400;
-                        let font_name = "Segoe UI";
-                        for (j, c) in font_name.encode_utf16().enumerate() {
-                            if j < 32 {
-                                lf.lfFaceName[j] = c;
-                            }
-                        }
-                        let font = CreateFontIndirectW(&lf);
-                        let old_font = SelectObject(hdc, HGDIOBJ(font.0));
-
-                        // Get actual text metrics for proper vertical centering
-                        let mut tm = TEXTMETRICW::default();
-                        GetTextMetricsW(hdc, &mut tm);
-                        let text_height = tm.tmHeight;
-
-                        // Text position after icon, vertically centered
-                        let text_x = item_rect.left + ICON_SIZE + 8;
-                        let text_y = (item_rect.top + item_rect.bottom - text_height) / 2;
-                        let name_wide: Vec<u16> = profile.name.encode_utf16().collect();
-                        TextOutW(hdc, text_x, text_y, &name_wide);
-
-                        SelectObject(hdc, old_font);
-                        DeleteObject(HGDIOBJ(font.0));
-                    }
-                }
-
-                EndPaint(hwnd, &ps);
-                LRESULT(0)
-            }
-
-            WM_MOUSEMOVE => {
-                let y = ((lparam.0 >> 16) & 0xFFFF) as i16 as i32;
-
-                let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut DropdownPopupState;
-                if !state_ptr.is_null() {
-                    let state = &mut *state_ptr;
-
-                    // Calculate which item is hovered
-                    let item_index = (y - DROPDOWN_PADDING) / DROPDOWN_ITEM_HEIGHT;
-                    let new_hovered =
-                        if item_index >= 0 && (item_index as usize) < state.profiles.len() {
-                            Some(item_index as usize)
-                        } else {
-                            None
-                        };
-
-                    if state.hovered_item != new_hovered {
-                        state.hovered_item = new_hovered;
-                        InvalidateRect(hwnd, None, false);
-                    }
-                }
-                LRESULT(0)
-            }
-
-            WM_LBUTTONDOWN => {
-                let x = (lparam.0 & 0xFFFF) as i16 as i32;
-                let y = ((lparam.0 >> 16) & 0xFFFF) as i16 as i32;
-
-                let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut DropdownPopupState;
-                if !state_ptr.is_null() {
-                    let state = &*state_ptr;
-
-                    // Check if click is inside the popup
-                    let mut rect = RECT::default();
-                    GetClientRect(hwnd, &mut rect).ok();
-
-                    if x >= rect.left && x < rect.right && y >= rect.top && y < rect.bottom {
-                        // Click inside - check which item
-                        let item_index = (y - DROPDOWN_PADDING) / DROPDOWN_ITEM_HEIGHT;
-                        if item_index >= 0 && (item_index as usize) < state.profiles.len() {
-                            // Send custom message to parent with profile index
-                            let profile_index = item_index as usize;
-                            PostMessageW(
-                                state.parent_hwnd,
-                                WM_APP,
-                                WPARAM(profile_index),
-                                LPARAM(0),
-                            )
-                            .ok();
-                        }
-                    } else {
-                        // Click outside - just notify parent to close
-                        PostMessageW(state.parent_hwnd, WM_APP + 1, WPARAM(0), LPARAM(0)).ok();
-                    }
-                    // Release capture and close popup
-                    ReleaseCapture().ok();
-                    DestroyWindow(hwnd).ok();
-                }
-                LRESULT(0)
-            }
-
-            WM_CAPTURECHANGED => {
-                // We lost capture - close the popup
-                let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut DropdownPopupState;
-                if !state_ptr.is_null() {
-                    let state = &*state_ptr;
-                    PostMessageW(state.parent_hwnd, WM_APP + 1, WPARAM(0), LPARAM(0)).ok();
-                }
-                DestroyWindow(hwnd).ok();
-                LRESULT(0)
-            }
-
-            WM_DESTROY => {
-                let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut DropdownPopupState;
-                if !state_ptr.is_null() {
-                    // Free the state
-                    let _ = Box::from_raw(state_ptr);
-                    SetWindowLongPtrW(hwnd, GWLP_USERDATA, 0);
-                }
-                LRESULT(0)
-            }
-
-            _ => DefWindowProcW(hwnd, msg, wparam, lparam),
-        }
-    }
-}
-
-/// Create the overflow tabs popup window
-fn create_overflow_popup(
-    parent_hwnd: HWND,
-    tabs: Vec<OverflowTabInfo>,
-    background_color: u32,
-    x: i32,
-    y: i32,
-    width: i32,
-    height: i32,
-) -> Option<HWND> {
-    unsafe {
-        let hinstance = GetModuleHandleW(None).ok()?;
-
-        // Create popup state
-        let popup_state = Box::new(OverflowPopupState {
-            parent_hwnd,
-            tabs,
-            hovered_item: None,
-            hovered_close: None,
-            background_color,
-        });
-
-        let hwnd = CreateWindowExW(
-            WS_EX_TOPMOST | WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE,
-            OVERFLOW_CLASS_NAME,
-            w!(""),
-            WS_POPUP | WS_VISIBLE,
-            x,
-            y,
-            width,
-            height,
-            None, // No parent - independent window
-            None,
-            hinstance,
-            Some(Box::into_raw(popup_state) as *const std::ffi::c_void),
-        )
-        .ok()?;
-
-        Some(hwnd)
-    }
-}
-
 /// Show the overflow tabs popup at the appropriate position
 #[allow(unused_must_use)]
 fn show_overflow_popup(parent_hwnd: HWND, state: &mut WindowState, client_width: i32) {
@@ -1438,54 +1691,76 @@ fn show_overflow_popup(parent_hwnd: HWND, state: &mut WindowState, client_width:
         return;
     }
 
-    // Collect overflow tabs info
-    let mut overflow_tabs = Vec::new();
-    let selected_index = state.tab_manager.selected_index();
-    for i in visible_count..state.tab_manager.count() {
-        overflow_tabs.push(OverflowTabInfo {
-            index: i,
-            label: state.tab_manager.get_tab_label(i),
-            icon: state
-                .tab_manager
-                .get_tab_icon(i)
-                .unwrap_or_default()
-                .to_string(),
-            is_selected: i == selected_index,
+    // The tabs that didn't fit, selected with WM_APP + 2 and closed with WM_APP + 4
+    let overflow_tab = |i: usize| {
+        let mut item = tab_popup_item(state, i);
+        item.command.msg = WM_APP + 2;
+        item.close_command = Some(PopupCommand {
+            msg: WM_APP + 4,
+            wparam: i,
         });
-    }
-
-    if overflow_tabs.is_empty() {
+        item
+    };
+    let overflow_range = visible_count..state.tab_manager.count();
+    if overflow_range.is_empty() {
         return;
     }
 
-    unsafe {
-        let overflow_btn = get_overflow_button_rect(visible_count, client_width);
+    // Many of them are grouped by profile below a filter box
+    let grouped = overflow_range.len() > OVERFLOW_GROUP_THRESHOLD;
+    let overflow_tabs: Vec<PopupItem> = if grouped {
+        let mut items = Vec::new();
+        for (profile, tabs) in state.tab_manager.group_by_profile(overflow_range) {
+            items.push(PopupItem {
+                icon: state
+                    .config
+                    .find_profile_by_name(&profile)
+                    .map(|(_, profile)| profile.icon.clone()),
+                label: profile,
+                selected: false,
+                separator: !items.is_empty(),
+                header: true,
+                // Never posted: headers can't be picked
+                command: PopupCommand {
+                    msg: WM_APP + 3,
+                    wparam: 0,
+                },
+                close_command: None,
+            });
+            items.extend(tabs.into_iter().map(overflow_tab));
+        }
+        items
+    } else {
+        overflow_range.map(overflow_tab).collect()
+    };
 
-        // Convert button position to screen coordinates
-        let mut screen_pt = POINT {
-            x: overflow_btn.left,
-            y: overflow_btn.bottom,
-        };
-        ClientToScreen(parent_hwnd, &mut screen_pt);
+    let overflow_btn = get_overflow_button_rect(visible_count, client_width);
 
-        let tab_count = overflow_tabs.len();
-        let menu_width = TAB_WIDTH; // Same width as tabs
-        let menu_height = (tab_count as i32 * DROPDOWN_ITEM_HEIGHT) + (DROPDOWN_PADDING * 2);
+    let row_count = overflow_tabs.len();
+    let menu_width = TAB_WIDTH; // Same width as tabs
+    let menu_height = if grouped {
+        popup::filtered_list_height(row_count)
+    } else {
+        popup::list_height(row_count)
+    };
+    let rect = popup_rect(parent_hwnd, overflow_btn, menu_width, menu_height);
 
-        // Bring Neovide back to foreground before showing popup
+    // Bring Neovide back to foreground before showing popup, unless the popup
+    // takes the keyboard for its filter box
+    if !grouped {
         state.tab_manager.bring_selected_to_foreground();
+    }
 
-        if let Some(popup_hwnd) = create_overflow_popup(
-            parent_hwnd,
-            overflow_tabs,
-            state.background_color,
-            screen_pt.x,
-            screen_pt.y,
-            menu_width,
-            menu_height,
-        ) {
-            state.overflow_hwnd = Some(popup_hwnd);
-        }
+    let list = PopupList {
+        owner: parent_hwnd,
+        items: overflow_tabs,
+        background_color: state.background_color,
+        dismissed: WM_APP + 3,
+        passive: false,
+        filter: grouped.then_some(OVERFLOW_FILTER_PLACEHOLDER),
+    };
+    if let Some(popup_hwnd) = popup::open(list, rect) {
+        state.overflow_hwnd = Some(popup_hwnd);
     }
 }
 
@@ -1499,780 +1774,4001 @@ fn hide_overflow_popup(_parent_hwnd: HWND, state: &mut WindowState) {
     }
 }
 
-/// Window procedure for the overflow tabs popup
-#[allow(unused_must_use)]
-unsafe extern "system" fn overflow_proc(
-    hwnd: HWND,
-    msg: u32,
-    wparam: WPARAM,
-    lparam: LPARAM,
-) -> LRESULT {
-    unsafe {
-        match msg {
-            WM_CREATE => {
-                let create_struct = lparam.0 as *const CREATESTRUCTW;
-                if !create_struct.is_null() {
-                    let state_ptr = (*create_struct).lpCreateParams as *mut OverflowPopupState;
-                    SetWindowLongPtrW(hwnd, GWLP_USERDATA, state_ptr as isize);
-                }
-                // Capture mouse to detect clicks outside the popup
-                SetCapture(hwnd);
-                LRESULT(0)
-            }
-
-            WM_PAINT => {
-                let mut ps = PAINTSTRUCT::default();
-                let hdc = BeginPaint(hwnd, &mut ps);
-
-                let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut OverflowPopupState;
-                if !state_ptr.is_null() {
-                    let state = &*state_ptr;
-
-                    let mut rect = RECT::default();
-                    GetClientRect(hwnd, &mut rect).ok();
-
-                    // Fill background
-                    let bg_brush =
-                        CreateSolidBrush(COLORREF(rgb_to_colorref(state.background_color)));
-                    FillRect(hdc, &rect, bg_brush);
-                    DeleteObject(HGDIOBJ(bg_brush.0));
-
-                    // Draw border
-                    let border_pen =
-                        CreatePen(PS_SOLID, 1, COLORREF(rgb_to_colorref(TAB_OUTLINE_COLOR)));
-                    let old_pen = SelectObject(hdc, HGDIOBJ(border_pen.0));
-                    MoveToEx(hdc, rect.left, rect.top, None);
-                    LineTo(hdc, rect.right - 1, rect.top);
-                    LineTo(hdc, rect.right - 1, rect.bottom - 1);
-                    LineTo(hdc, rect.left, rect.bottom - 1);
-                    LineTo(hdc, rect.left, rect.top);
-                    SelectObject(hdc, old_pen);
-                    DeleteObject(HGDIOBJ(border_pen.0));
-
-                    // Draw each overflow tab item
-                    for (i, tab_info) in state.tabs.iter().enumerate() {
-                        let item_top = DROPDOWN_PADDING + (i as i32 * DROPDOWN_ITEM_HEIGHT);
-                        let item_rect = RECT {
-                            left: DROPDOWN_PADDING,
-                            top: item_top,
-                            right: rect.right - DROPDOWN_PADDING,
-                            bottom: item_top + DROPDOWN_ITEM_HEIGHT,
-                        };
+/// Popup list entry for tab `index`: its label and icon, bold when selected
+fn tab_popup_item(state: &WindowState, index: usize) -> PopupItem {
+    PopupItem {
+        icon: state.tab_manager.get_tab_icon(index).map(str::to_string),
+        label: state.tab_manager.get_tab_label(index),
+        selected: index == state.tab_manager.selected_index(),
+        separator: false,
+        header: false,
+        command: PopupCommand {
+            msg: WM_APP + 2,
+            wparam: index,
+        },
+        close_command: None,
+    }
+}
 
-                        // Hover or selected background
-                        if state.hovered_item == Some(i) || tab_info.is_selected {
-                            let bg_color = if state.hovered_item == Some(i) {
-                                TAB_HOVER_COLOR
-                            } else {
-                                TAB_UNSELECTED_COLOR
-                            };
-                            let item_brush = CreateSolidBrush(COLORREF(rgb_to_colorref(bg_color)));
-                            FillRect(hdc, &item_rect, item_brush);
-                            DeleteObject(HGDIOBJ(item_brush.0));
-                        }
+/// Select the tab after (or before) the selected one. Pressed while the hotkey's
+/// modifiers are held, the tab switcher opens (or moves to the next candidate)
+/// and the choice is made once they are released.
+#[allow(unused_must_use)]
+fn cycle_tabs(hwnd: HWND, state: &mut WindowState, forward: bool) {
+    if let Some(switcher) = state.tab_switcher.as_mut() {
+        if let Some(next) = state.tab_manager.cycled_index(switcher.candidate, forward) {
+            switcher.candidate = next;
+            popup::set_highlighted(switcher.popup, Some(next));
+        }
+        return;
+    }
 
-                        // Draw icon
-                        let icon_x = item_rect.left + 4;
-                        let icon_y = (item_rect.top + item_rect.bottom - ICON_SIZE) / 2;
-                        if let Some(hbitmap) = get_icon_bitmap(&tab_info.icon) {
-                            paint_icon(hdc, hbitmap, icon_x, icon_y, ICON_SIZE, ICON_SIZE);
-                        }
+    let selected = state.tab_manager.selected_index();
+    let Some(target) = state.tab_manager.cycled_index(selected, forward) else {
+        return;
+    };
+    let hotkey = if forward {
+        &state.config.hotkeys.next_tab
+    } else {
+        &state.config.hotkeys.prev_tab
+    };
+    let modifiers = hotkey
+        .as_deref()
+        .and_then(hotkeys::parse_hotkey_string)
+        .map(|parsed| parsed.modifiers)
+        .filter(|&modifiers| hotkeys::modifiers_held(modifiers));
+    let Some(modifiers) = modifiers else {
+        // Released already (or no modifiers): switch right away
+        select_cycled_tab(hwnd, state, target);
+        return;
+    };
 
-                        // Draw text
-                        SetBkMode(hdc, TRANSPARENT);
-                        SetTextColor(hdc, COLORREF(0x00FFFFFF));
-
-                        let mut lf = LOGFONTW::default();
-                        lf.lfHeight = -12;
-                        lf.lfWeight = if tab_info.is_selected { 700 } else { 400 };
-                        let font_name = "Segoe UI";
-                        for (j, c) in font_name.encode_utf16().enumerate() {
-                            if j < 32 {
-                                lf.lfFaceName[j] = c;
-                            }
-                        }
-                        let font = CreateFontIndirectW(&lf);
-                        let old_font = SelectObject(hdc, HGDIOBJ(font.0));
-
-                        // Get actual text metrics for proper vertical centering
-                        let mut tm = TEXTMETRICW::default();
-                        GetTextMetricsW(hdc, &mut tm);
-                        let text_height = tm.tmHeight;
-
-                        // Text position after icon, vertically centered
-                        let text_x = item_rect.left + ICON_SIZE + 8;
-                        let text_y = (item_rect.top + item_rect.bottom - text_height) / 2;
-
-                        // Calculate close button rect (on the far right)
-                        let close_size = TAB_CLOSE_SIZE;
-                        let close_left = item_rect.right - TAB_CLOSE_PADDING - close_size;
-                        let close_top = (item_rect.top + item_rect.bottom - close_size) / 2;
-                        let close_rect = RECT {
-                            left: close_left,
-                            top: close_top,
-                            right: close_left + close_size,
-                            bottom: close_top + close_size,
-                        };
+    let tabs: Vec<PopupItem> = (0..state.tab_manager.count())
+        .map(|i| tab_popup_item(state, i))
+        .collect();
+    unsafe {
+        // Centered over the window
+        let mut client_rect = RECT::default();
+        GetClientRect(hwnd, &mut client_rect);
+        let mut center = POINT {
+            x: client_rect.right / 2,
+            y: client_rect.bottom / 2,
+        };
+        ClientToScreen(hwnd, &mut center);
+        let width = TAB_WIDTH * 2;
+        let height = popup::list_height(tabs.len());
+        let mut rect = Rect {
+            left: center.x - width / 2,
+            top: center.y - height / 2,
+            right: center.x - width / 2 + width,
+            bottom: center.y - height / 2 + height,
+        };
+        if let Some(work_area) = monitor_work_area(hwnd) {
+            rect = rect.shifted_into(&work_area);
+        }
+        // Driven by the hotkeys alone; closes once the modifiers are released
+        let list = PopupList {
+            owner: hwnd,
+            items: tabs,
+            background_color: state.background_color,
+            dismissed: WM_APP + 3,
+            passive: true,
+            filter: None,
+        };
+        match popup::open(list, rect) {
+            Some(popup) => {
+                popup::set_highlighted(popup, Some(target));
+                SetTimer(hwnd, TAB_SWITCHER_TIMER_ID, TAB_SWITCHER_POLL_MS, None);
+                state.tab_switcher = Some(TabSwitcher {
+                    popup,
+                    candidate: target,
+                    modifiers,
+                });
+            }
+            None => select_cycled_tab(hwnd, state, target),
+        }
+    }
+}
+
+/// Close the tab switcher (if open) and select its candidate
+#[allow(unused_must_use)]
+fn commit_tab_switcher(hwnd: HWND, state: &mut WindowState) {
+    unsafe {
+        KillTimer(hwnd, TAB_SWITCHER_TIMER_ID);
+        if let Some(switcher) = state.tab_switcher.take() {
+            DestroyWindow(switcher.popup);
+            select_cycled_tab(hwnd, state, switcher.candidate);
+        }
+    }
+}
+
+/// Select the tab chosen by the next/prev tab hotkeys
+#[allow(unused_must_use)]
+fn select_cycled_tab(hwnd: HWND, state: &mut WindowState, index: usize) {
+    if state.tab_manager.select_tab(index) {
+        state.tab_manager.activate_selected(hwnd, content_top());
+        unsafe {
+            InvalidateRect(hwnd, None, false);
+        }
+    } else {
+        state
+            .tab_manager
+            .activate_and_foreground_selected(hwnd, content_top());
+    }
+}
+
+/// Switch to the tab after (or before) the selected one right away, for swipes
+/// and mouse buttons
+fn select_adjacent_tab(hwnd: HWND, state: &mut WindowState, forward: bool) {
+    let selected = state.tab_manager.selected_index();
+    if let Some(target) = state.tab_manager.cycled_index(selected, forward) {
+        select_cycled_tab(hwnd, state, target);
+    }
+}
+
+/// Run the `mouse_bindings` action for `button` released at client point `pt`.
+/// Returns false when nothing is bound there, so the button does what it
+/// otherwise would.
+#[allow(unused_must_use)]
+unsafe fn run_mouse_binding(
+    hwnd: HWND,
+    state: &mut WindowState,
+    button: MouseButton,
+    pt: POINT,
+) -> bool {
+    unsafe {
+        let mut client_rect = RECT::default();
+        if !(0..titlebar_height()).contains(&pt.y) || GetClientRect(hwnd, &mut client_rect).is_err()
+        {
+            return false;
+        }
+        let (area, tab) = match hit_test_tabs(pt.x, pt.y, &state.tab_manager, client_rect.right) {
+            TabHitResult::Tab(i) | TabHitResult::TabClose(i) | TabHitResult::TabRetry(i) => {
+                (MouseArea::Tab, i)
+            }
+            TabHitResult::Caption | TabHitResult::None
+                if hit_test_buttons(pt.x, pt.y, client_rect.right) == HoveredButton::None =>
+            {
+                (MouseArea::Caption, state.tab_manager.selected_index())
+            }
+            _ => return false,
+        };
+        let modifiers = (
+            GetKeyState(VK_CONTROL.0 as i32) < 0,
+            GetKeyState(VK_SHIFT.0 as i32) < 0,
+            GetKeyState(VK_MENU.0 as i32) < 0,
+        );
+        let Some(action) = state.config.mouse_action(button, modifiers, area) else {
+            return false;
+        };
+
+        hide_dropdown_popup(hwnd, state);
+        hide_overflow_popup(hwnd, state);
+        let mut screen_pt = pt;
+        ClientToScreen(hwnd, &mut screen_pt);
+        match action {
+            MouseAction::NextTab => select_adjacent_tab(hwnd, state, true),
+            MouseAction::PrevTab => select_adjacent_tab(hwnd, state, false),
+            MouseAction::NewTab => {
+                let default_profile = state.config.default_profile().clone();
+                open_profile_tab(hwnd, state, &default_profile, 0, None);
+            }
+            MouseAction::CloseTab => close_tab_gracefully(hwnd, state, tab),
+            MouseAction::TabMenu => show_tab_context_menu(hwnd, state, tab, screen_pt),
+            MouseAction::ProfileMenu => {
+                show_dropdown_popup(hwnd, state);
+                InvalidateRect(hwnd, None, false);
+            }
+            MouseAction::WindowMenu => {
+                let menu = GetSystemMenu(hwnd, false);
+                SetForegroundWindow(hwnd);
+                let command = TrackPopupMenu(
+                    menu,
+                    TPM_RETURNCMD | TPM_RIGHTBUTTON,
+                    screen_pt.x,
+                    screen_pt.y,
+                    0,
+                    hwnd,
+                    None,
+                );
+                if command.0 != 0 {
+                    PostMessageW(hwnd, WM_SYSCOMMAND, WPARAM(command.0 as usize), LPARAM(0));
+                }
+            }
+            MouseAction::Minimize => {
+                ShowWindow(hwnd, SW_MINIMIZE);
+            }
+            MouseAction::Maximize if IsZoomed(hwnd).as_bool() => {
+                ShowWindow(hwnd, SW_RESTORE);
+            }
+            MouseAction::Maximize => {
+                ShowWindow(hwnd, SW_MAXIMIZE);
+            }
+            MouseAction::None => return false,
+        }
+        true
+    }
+}
+
+/// Button released by a WM_[NC]{M,R,X}BUTTONUP message
+fn bound_mouse_button(msg: u32, wparam: WPARAM) -> Option<MouseButton> {
+    match msg {
+        WM_MBUTTONUP | WM_NCMBUTTONUP => Some(MouseButton::Middle),
+        WM_RBUTTONUP | WM_NCRBUTTONUP => Some(MouseButton::Right),
+        // GET_XBUTTON_WPARAM
+        WM_XBUTTONUP | WM_NCXBUTTONUP => match (wparam.0 >> 16) as u16 {
+            XBUTTON1 => Some(MouseButton::X1),
+            XBUTTON2 => Some(MouseButton::X2),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Pointer ID of a WM_POINTER* message (GET_POINTERID_WPARAM)
+fn pointer_id(wparam: WPARAM) -> u32 {
+    (wparam.0 & 0xFFFF) as u32
+}
+
+/// Client position of a WM_POINTER* message from touch or pen; None for the
+/// mouse, which Windows turns into mouse messages itself
+pub(crate) unsafe fn touch_contact(hwnd: HWND, wparam: WPARAM, lparam: LPARAM) -> Option<POINT> {
+    unsafe {
+        let mut pointer_type = POINTER_INPUT_TYPE::default();
+        GetPointerType(pointer_id(wparam), &mut pointer_type).ok()?;
+        if pointer_type != PT_TOUCH && pointer_type != PT_PEN {
+            return None;
+        }
+        let mut pt = POINT {
+            x: (lparam.0 & 0xFFFF) as i16 as i32,
+            y: ((lparam.0 >> 16) & 0xFFFF) as i16 as i32,
+        };
+        ScreenToClient(hwnd, &mut pt).as_bool().then_some(pt)
+    }
+}
+
+/// Feed a touch or pen WM_POINTER* message at client point `pt` to `proc` as the
+/// mouse messages its handlers expect: a move, then the button press or release
+pub(crate) unsafe fn replay_pointer_as_mouse(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    pt: POINT,
+    proc: unsafe extern "system" fn(HWND, u32, WPARAM, LPARAM) -> LRESULT,
+) {
+    unsafe {
+        let lparam = LPARAM((((pt.y as u16 as u32) << 16) | pt.x as u16 as u32) as isize);
+        // POINTER_MESSAGE_FLAG_INCONTACT stands in for MK_LBUTTON
+        let in_contact = (wparam.0 >> 16) & 0x0004 != 0;
+        let buttons = WPARAM((in_contact && msg != WM_POINTERUP) as usize);
+        proc(hwnd, WM_MOUSEMOVE, buttons, lparam);
+        match msg {
+            WM_POINTERDOWN => {
+                proc(hwnd, WM_LBUTTONDOWN, WPARAM(1), lparam);
+            }
+            WM_POINTERUP => {
+                proc(hwnd, WM_LBUTTONUP, WPARAM(0), lparam);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Tool info for the tab tooltip's single tool covering `rect`
+fn tab_tool_info(hwnd: HWND, rect: RECT) -> TTTOOLINFOW {
+    TTTOOLINFOW {
+        // Size without lpReserved, accepted by every common controls version
+        cbSize: (std::mem::size_of::<TTTOOLINFOW>() - std::mem::size_of::<usize>()) as u32,
+        uFlags: TTF_SUBCLASS,
+        hwnd,
+        uId: TAB_TOOLTIP_ID,
+        rect,
+        ..Default::default()
+    }
+}
+
+/// Create the tooltip control for the tab bar. The text is supplied on demand
+/// through TTN_GETDISPINFO so it always shows the latest title and usage.
+fn create_tab_tooltip(hwnd: HWND) -> HWND {
+    unsafe {
+        let icc = INITCOMMONCONTROLSEX {
+            dwSize: std::mem::size_of::<INITCOMMONCONTROLSEX>() as u32,
+            dwICC: ICC_BAR_CLASSES,
+        };
+        let _ = InitCommonControlsEx(&icc);
+
+        let hinstance = GetModuleHandleW(None).unwrap_or_default();
+        let tooltip = match CreateWindowExW(
+            WS_EX_TOPMOST,
+            TOOLTIPS_CLASSW,
+            PCWSTR::null(),
+            WS_POPUP | WINDOW_STYLE(TTS_NOPREFIX | TTS_ALWAYSTIP),
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            hwnd,
+            None,
+            hinstance,
+            None,
+        ) {
+            Ok(tooltip) => tooltip,
+            Err(e) => {
+                eprintln!("Failed to create tab tooltip: {}", e);
+                return HWND::default();
+            }
+        };
+
+        // LPSTR_TEXTCALLBACKW: ask the owner for the text each time the tip is shown
+        let mut tool = tab_tool_info(hwnd, RECT::default());
+        tool.lpszText = PWSTR(std::ptr::without_provenance_mut(usize::MAX));
+        SendMessageW(
+            tooltip,
+            TTM_ADDTOOLW,
+            WPARAM(0),
+            LPARAM(&tool as *const _ as isize),
+        );
+        SendMessageW(
+            tooltip,
+            TTM_SETMAXTIPWIDTH,
+            WPARAM(0),
+            LPARAM(TAB_TOOLTIP_MAX_WIDTH),
+        );
+        tooltip
+    }
+}
+
+/// Point the tab tooltip at another tab (None = no tooltip)
+fn set_tooltip_tab(hwnd: HWND, state: &mut WindowState, tab: Option<usize>, client_width: i32) {
+    if state.tooltip_tab == tab || state.tab_tooltip.is_invalid() {
+        return;
+    }
+    state.tooltip_tab = tab;
+    let rect = tab.map_or(RECT::default(), |i| get_tab_rect(i, client_width).into());
+    let tool = tab_tool_info(hwnd, rect);
+    unsafe {
+        SendMessageW(
+            state.tab_tooltip,
+            TTM_NEWTOOLRECTW,
+            WPARAM(0),
+            LPARAM(&tool as *const _ as isize),
+        );
+    }
+}
+
+/// Wait to preview the hovered background tab (None: the pointer left the
+/// background tabs), hiding the preview of the previous one
+fn set_preview_tab(hwnd: HWND, state: &mut WindowState, tab: Option<usize>) {
+    if state.preview_tab == tab {
+        return;
+    }
+    hide_tab_preview(hwnd, state);
+    state.preview_tab = tab;
+    if tab.is_some() && state.config.tab_previews {
+        unsafe {
+            SetTimer(hwnd, PREVIEW_TIMER_ID, PREVIEW_HOVER_DELAY_MS, None);
+        }
+    }
+}
+
+/// Close the tab preview popup, or stop waiting to show it
+fn hide_tab_preview(hwnd: HWND, state: &mut WindowState) {
+    unsafe {
+        let _ = KillTimer(hwnd, PREVIEW_TIMER_ID);
+        if let Some(popup_hwnd) = state.preview_hwnd.take() {
+            let _ = DestroyWindow(popup_hwnd);
+        }
+    }
+}
+
+/// Show the last capture of the hovered background tab below it. Tabs that were
+/// never shown since they started have no preview.
+fn show_tab_preview(hwnd: HWND, state: &mut WindowState) {
+    let Some(index) = state.preview_tab else {
+        return;
+    };
+    if index == state.tab_manager.selected_index() || state.preview_hwnd.is_some() {
+        return;
+    }
+    let Some(thumbnail) = state
+        .tab_manager
+        .get_tab_id(index)
+        .and_then(|id| state.tab_previews.get(&id))
+    else {
+        return;
+    };
+    let mut client_rect = RECT::default();
+    unsafe {
+        if GetClientRect(hwnd, &mut client_rect).is_err() {
+            return;
+        }
+    }
+    let (width, height) = thumbnail.popup_size();
+    let rect = popup_rect(hwnd, get_tab_rect(index, client_rect.right), width, height);
+    state.preview_hwnd = preview::show(thumbnail, rect);
+    // The preview takes the tooltip's place below the tab
+    if state.preview_hwnd.is_some() && !state.tab_tooltip.is_invalid() {
+        unsafe {
+            SendMessageW(state.tab_tooltip, TTM_POP, WPARAM(0), LPARAM(0));
+        }
+    }
+}
+
+/// Capture the selected tab now and then for the previews (hidden windows can't
+/// be captured), and forget the captures of closed tabs
+fn update_tab_previews(hwnd: HWND, state: &mut WindowState) {
+    if !state.config.tab_previews {
+        return;
+    }
+    let tab_manager = &state.tab_manager;
+    state
+        .tab_previews
+        .retain(|&id, _| tab_manager.find_tab_by_id(id).is_some());
+
+    if state
+        .last_preview_capture
+        .is_some_and(|captured| captured.elapsed() < PREVIEW_CAPTURE_INTERVAL)
+        || unsafe { IsIconic(hwnd).as_bool() }
+    {
+        return;
+    }
+    // A suspended or hung Neovide would not draw (or answer) the capture
+    let index = tab_manager.selected_index();
+    if tab_manager.tab_state(index) != Some(TabState::Ready) {
+        return;
+    }
+    let (Some(id), Some(window)) = (
+        tab_manager.get_tab_id(index),
+        tab_manager.get_tab_window(index),
+    ) else {
+        return;
+    };
+    state.last_preview_capture = Some(Instant::now());
+    if let Some(thumbnail) = preview::capture(window) {
+        state.tab_previews.insert(id, thumbnail);
+    }
+}
+
+/// Tooltip text for a tab: label, working directory and resource usage
+fn tab_tooltip_text(tab_manager: &TabManager<NeovideProcess>, index: usize) -> String {
+    if index >= tab_manager.count() {
+        return String::new();
+    }
+    let mut lines = vec![tab_manager.get_tab_label(index)];
+    if let Some(dir) = tab_manager.get_tab_working_directory(index) {
+        lines.push(dir.display().to_string());
+    }
+    if let Some(status) = tab_manager.get_tab_git_status(index) {
+        lines.push(status.summary());
+    }
+    if let Some(error) = tab_manager.get_tab_spawn_error(index) {
+        lines.extend(error.lines().map(str::to_string));
+        lines.push("Click the retry icon to launch Neovide again".to_string());
+        return lines.join("\r\n");
+    }
+    let mut usage = usage_summary(tab_manager.get_tab_usage(index));
+    if tab_manager.is_tab_suspended(index) {
+        usage.push_str("  \u{00b7}  Suspended");
+    }
+    if tab_manager.is_tab_elevated(index) {
+        usage.push_str("  \u{00b7}  Administrator");
+    }
+    lines.push(usage);
+    if tab_manager.is_tab_not_responding(index) {
+        lines.push("Not responding - right-click the tab to force close it".to_string());
+    }
+    lines.join("\r\n")
+}
+
+/// Snapshot of all tabs for the task manager popup
+fn task_manager_rows(tab_manager: &TabManager<NeovideProcess>) -> Vec<TaskManagerRow> {
+    tab_manager
+        .iter()
+        .map(|(i, tab)| TaskManagerRow {
+            tab_id: tab.id,
+            label: tab_manager.get_tab_label(i),
+            icon: tab.icon().to_string(),
+            usage: tab.usage.current(),
+            is_selected: i == tab_manager.selected_index(),
+            is_suspended: tab.process.is_suspended(),
+            is_not_responding: tab.not_responding,
+        })
+        .collect()
+}
+
+/// Open the task manager popup, or close it if it is already open
+fn toggle_task_manager(hwnd: HWND, state: &mut WindowState) {
+    if let Some(popup_hwnd) = state.task_manager_hwnd.take() {
+        unsafe {
+            DestroyWindow(popup_hwnd).ok();
+        }
+        return;
+    }
+    state.task_manager_hwnd = taskmgr::open(
+        hwnd,
+        task_manager_rows(&state.tab_manager),
+        state.background_color,
+    );
+}
+
+/// Store the text for the tooltip's next TTN_GETDISPINFO
+fn store_tooltip_text(hwnd: HWND, state: &WindowState) {
+    // An empty text keeps the tooltip hidden
+    let text = state
+        .tooltip_tab
+        .map(|index| tab_tooltip_text(&state.tab_manager, index))
+        .unwrap_or_default();
+    if let Some(cell) = unsafe { window_cell(hwnd) } {
+        *cell.tooltip_text.borrow_mut() = text.encode_utf16().chain(std::iter::once(0)).collect();
+    }
+}
+
+/// Refresh the views showing resource usage: the visible tab tooltip and the
+/// task manager popup
+fn refresh_usage_views(hwnd: HWND, state: &WindowState) {
+    unsafe {
+        if state.tooltip_tab.is_some() && !state.tab_tooltip.is_invalid() {
+            // Re-requests the text through TTN_GETDISPINFO if the tip is showing
+            store_tooltip_text(hwnd, state);
+            SendMessageW(state.tab_tooltip, TTM_UPDATE, WPARAM(0), LPARAM(0));
+        }
+    }
+    if let Some(popup_hwnd) = state.task_manager_hwnd {
+        taskmgr::set_rows(popup_hwnd, task_manager_rows(&state.tab_manager));
+    }
+}
+
+/// Ask before force closing a tab: unlike closing, it skips Neovim's save prompts
+fn confirm_force_close(owner: HWND, label: &str) -> bool {
+    let message = format!(
+        "Force close \"{}\"?\n\nThe Neovide process is terminated immediately; unsaved changes in this tab will be lost.",
+        label
+    );
+    let wide_message: Vec<u16> = message.encode_utf16().chain(std::iter::once(0)).collect();
+    unsafe {
+        MessageBoxW(
+            owner,
+            PCWSTR(wide_message.as_ptr()),
+            w!("Force close tab"),
+            MB_YESNO | MB_ICONWARNING | MB_DEFBUTTON2,
+        ) == IDYES
+    }
+}
+
+/// Ask whether to terminate a tab whose Neovide refused to close
+fn confirm_force_close_blocked(owner: HWND, label: &str) -> bool {
+    let message = format!(
+        "\"{}\" can't be closed normally: its Neovide runs with higher privileges than neovide-tabs, which Windows doesn't allow to ask it to close.\n\nForce close it? The Neovide process is terminated immediately; unsaved changes in this tab will be lost.",
+        label
+    );
+    let wide_message: Vec<u16> = message.encode_utf16().chain(std::iter::once(0)).collect();
+    unsafe {
+        MessageBoxW(
+            owner,
+            PCWSTR(wide_message.as_ptr()),
+            w!("Close tab"),
+            MB_YESNO | MB_ICONWARNING | MB_DEFBUTTON2,
+        ) == IDYES
+    }
+}
+
+/// Whether the wrapper's animations are off: the `reduce_motion` option, or
+/// Windows' "Animation effects" setting when it is not set
+fn reduce_motion(config: &Config) -> bool {
+    config.reduce_motion.unwrap_or_else(|| {
+        let mut enabled = BOOL(1);
+        let queried = unsafe {
+            SystemParametersInfoW(
+                SPI_GETCLIENTAREAANIMATION,
+                0,
+                Some(&mut enabled as *mut BOOL as *mut _),
+                SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+            )
+        };
+        queried.is_ok() && !enabled.as_bool()
+    })
+}
+
+/// Whether the selected tab's title can be seen changing: the wrapper isn't
+/// minimized and it, one of its popups or a Neovide window is in the foreground
+fn title_in_view(hwnd: HWND, state: &WindowState) -> bool {
+    unsafe {
+        if IsIconic(hwnd).as_bool() {
+            return false;
+        }
+        let foreground = GetForegroundWindow();
+        foreground == hwnd
+            || GetAncestor(foreground, GA_ROOTOWNER) == hwnd
+            || state.tab_manager.owns_window(foreground)
+    }
+}
+
+/// Run the quick title refresh for TITLE_BOOST_DURATION after a tab switch
+fn update_title_boost(hwnd: HWND, state: &mut WindowState) {
+    let boost = state
+        .tab_manager
+        .switched_within(TITLE_BOOST_DURATION, Instant::now());
+    if boost == state.title_boost {
+        return;
+    }
+    state.title_boost = boost;
+    unsafe {
+        if boost {
+            SetTimer(hwnd, TITLE_BOOST_TIMER_ID, TITLE_BOOST_INTERVAL_MS, None);
+        } else {
+            let _ = KillTimer(hwnd, TITLE_BOOST_TIMER_ID);
+        }
+    }
+}
+
+/// Reposition the selected Neovide window shortly after an external move or
+/// resize. The update runs `position_update_delay_ms` after the first move of a
+/// burst rather than after the last one, so a stream of moves (a snapping tool
+/// animating the window) is coalesced into one update per interval instead of
+/// being pushed back until it ends. A tiling window manager places windows
+/// without animating them, so its moves are followed right away.
+fn schedule_position_update(hwnd: HWND, state: &mut WindowState) {
+    if state.config.tiling_wm.is_enabled() {
+        unsafe {
+            if !IsIconic(hwnd).as_bool() {
+                state
+                    .tab_manager
+                    .update_selected_position(hwnd, content_top());
+            }
+        }
+    } else if !state.position_update_pending {
+        state.position_update_pending = true;
+        unsafe {
+            SetTimer(
+                hwnd,
+                POSITION_UPDATE_TIMER_ID,
+                state.config.advanced.position_update_delay_ms,
+                None,
+            );
+        }
+    }
+}
+
+/// Let the user decide about tabs whose Neovide refused to close: force close
+/// them, or keep them open (which also stops closing the app)
+#[allow(unused_must_use)]
+fn resolve_blocked_closes(hwnd: HWND, state: &mut WindowState) {
+    for tab_id in state.tab_manager.take_blocked_closes() {
+        let Some(label) = state
+            .tab_manager
+            .find_tab_by_id(tab_id)
+            .map(|index| state.tab_manager.get_tab_label(index))
+        else {
+            continue;
+        };
+        let confirmed = confirm_force_close_blocked(hwnd, &label);
+        // The confirmation runs a modal loop in which tabs may move or exit
+        let Some(index) = state.tab_manager.find_tab_by_id(tab_id) else {
+            continue;
+        };
+        if !confirmed {
+            state.tab_manager.cancel_close(tab_id);
+            continue;
+        }
+        unsafe {
+            if state.tab_manager.close_tab(index) {
+                // That was the last tab
+                PostMessageW(hwnd, WM_CLOSE, WPARAM(0), LPARAM(0));
+                return;
+            }
+            state.tab_manager.activate_selected(hwnd, content_top());
+            if state.tab_manager.has_pending_close() {
+                state.tab_manager.continue_close_sequence();
+            }
+            refresh_usage_views(hwnd, state);
+            InvalidateRect(hwnd, None, false);
+        }
+    }
+}
+
+/// Terminate a tab's Neovide process after confirmation (owner = parent of the
+/// confirmation box). The tab is identified by ID because the confirmation runs
+/// a modal loop in which tabs may move or exit.
+#[allow(unused_must_use)]
+fn force_close_tab(hwnd: HWND, state: &mut WindowState, tab_id: usize, owner: HWND) {
+    let Some(label) = state
+        .tab_manager
+        .find_tab_by_id(tab_id)
+        .map(|index| state.tab_manager.get_tab_label(index))
+    else {
+        return;
+    };
+    if !confirm_force_close(owner, &label) {
+        return;
+    }
+    let Some(index) = state.tab_manager.find_tab_by_id(tab_id) else {
+        return;
+    };
+
+    unsafe {
+        if state.tab_manager.close_tab(index) {
+            // That was the last tab
+            PostMessageW(hwnd, WM_CLOSE, WPARAM(0), LPARAM(0));
+        } else {
+            state.tab_manager.activate_selected(hwnd, content_top());
+            refresh_usage_views(hwnd, state);
+        }
+        InvalidateRect(hwnd, None, false);
+    }
+}
+
+/// Gracefully close all tabs but the one at `index` (or only those to its right),
+/// one at a time; the tab at `index` is selected again afterwards
+fn close_other_tabs(hwnd: HWND, state: &mut WindowState, index: usize, to_right: bool) {
+    if to_right {
+        state.tab_manager.request_close_to_right(index);
+    } else {
+        state.tab_manager.request_close_others(index);
+    }
+    // Tabs whose window wasn't ready yet are gone already
+    state.tab_manager.activate_selected(hwnd, content_top());
+    refresh_usage_views(hwnd, state);
+    unsafe {
+        let _ = InvalidateRect(hwnd, None, false);
+    }
+}
+
+/// Show the context menu of a tab at a screen position and run the chosen command
+#[allow(unused_must_use)]
+fn show_tab_context_menu(hwnd: HWND, state: &mut WindowState, index: usize, screen_pt: POINT) {
+    let Some(tab_id) = state.tab_manager.get_tab_id(index) else {
+        return;
+    };
+    unsafe {
+        let Ok(menu) = CreatePopupMenu() else {
+            return;
+        };
+        AppendMenuW(menu, MF_STRING, TAB_MENU_RENAME, w!("Rename tab\u{2026}"));
+        AppendMenuW(menu, MF_STRING, TAB_MENU_DUPLICATE, w!("Duplicate tab"));
+        let pin_label = if state.tab_manager.is_tab_pinned(index) {
+            w!("Unpin tab")
+        } else {
+            w!("Pin tab")
+        };
+        AppendMenuW(menu, MF_STRING, TAB_MENU_TOGGLE_PIN, pin_label);
+        // Needs nvim's RPC pipe, which tabs have once launched
+        let mut pause_flags = MF_STRING;
+        if state.tab_manager.is_tab_pausing_animations(index) {
+            pause_flags |= MF_CHECKED;
+        }
+        if !state.tab_manager.can_pause_animations(index) {
+            pause_flags |= MF_GRAYED;
+        }
+        AppendMenuW(
+            menu,
+            pause_flags,
+            TAB_MENU_PAUSE_ANIMATIONS,
+            w!("Pause animations in background"),
+        );
+        AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null());
+        let path_flags = if state.tab_manager.get_tab_working_directory(index).is_some() {
+            MF_STRING
+        } else {
+            MF_STRING | MF_GRAYED
+        };
+        AppendMenuW(menu, path_flags, TAB_MENU_COPY_PATH, w!("Copy path"));
+        AppendMenuW(menu, MF_STRING, TAB_MENU_COPY_TITLE, w!("Copy title"));
+        let folder_flags = if state
+            .tab_manager
+            .get_tab_working_directory(index)
+            .is_some_and(|dir| dir.is_dir())
+        {
+            MF_STRING
+        } else {
+            MF_STRING | MF_GRAYED
+        };
+        AppendMenuW(
+            menu,
+            folder_flags,
+            TAB_MENU_REVEAL,
+            w!("Reveal in Explorer"),
+        );
+        AppendMenuW(
+            menu,
+            folder_flags,
+            TAB_MENU_OPEN_TERMINAL,
+            w!("Open in Terminal"),
+        );
+        AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null());
+        AppendMenuW(menu, MF_STRING, TAB_MENU_CLOSE, w!("Close tab"));
+        let others_flags = if state.tab_manager.count() > 1 {
+            MF_STRING
+        } else {
+            MF_STRING | MF_GRAYED
+        };
+        AppendMenuW(
+            menu,
+            others_flags,
+            TAB_MENU_CLOSE_OTHERS,
+            w!("Close other tabs"),
+        );
+        let right_flags = if index + 1 < state.tab_manager.count() {
+            MF_STRING
+        } else {
+            MF_STRING | MF_GRAYED
+        };
+        AppendMenuW(
+            menu,
+            right_flags,
+            TAB_MENU_CLOSE_TO_RIGHT,
+            w!("Close tabs to the right"),
+        );
+        AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null());
+        AppendMenuW(
+            menu,
+            MF_STRING,
+            TAB_MENU_FORCE_CLOSE,
+            w!("Force close\u{2026}"),
+        );
+        if state.tab_manager.is_tab_not_responding(index) {
+            // Highlight the action that actually helps with a hung window
+            SetMenuDefaultItem(menu, TAB_MENU_FORCE_CLOSE as u32, 0);
+        }
+        // Elevated tabs' Neovide is started by the proxy, which keeps its output
+        let output_flags = if state.tab_manager.get_tab_output(index).is_some() {
+            MF_STRING
+        } else {
+            MF_STRING | MF_GRAYED
+        };
+        AppendMenuW(
+            menu,
+            output_flags,
+            TAB_MENU_SHOW_OUTPUT,
+            w!("Show process output"),
+        );
+        if state.tab_manager.has_queued_spawns() {
+            AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null());
+            AppendMenuW(
+                menu,
+                MF_STRING,
+                TAB_MENU_CANCEL_PENDING,
+                w!("Cancel pending tabs"),
+            );
+        }
+
+        // The menu only dismisses properly when its owner is the foreground window
+        SetForegroundWindow(hwnd);
+        let command = TrackPopupMenu(
+            menu,
+            TPM_RETURNCMD | TPM_RIGHTBUTTON | TPM_NONOTIFY,
+            screen_pt.x,
+            screen_pt.y,
+            0,
+            hwnd,
+            None,
+        );
+        DestroyMenu(menu);
+
+        match command.0 as usize {
+            TAB_MENU_CLOSE => {
+                if let Some(index) = state.tab_manager.find_tab_by_id(tab_id) {
+                    close_tab_gracefully(hwnd, state, index);
+                }
+            }
+            TAB_MENU_RENAME => {
+                if let Some(index) = state.tab_manager.find_tab_by_id(tab_id) {
+                    start_tab_rename(hwnd, state, index);
+                }
+            }
+            TAB_MENU_DUPLICATE => {
+                if let Some(index) = state.tab_manager.find_tab_by_id(tab_id) {
+                    duplicate_tab(hwnd, state, index);
+                }
+            }
+            TAB_MENU_TOGGLE_PIN => {
+                if let Some(index) = state.tab_manager.find_tab_by_id(tab_id) {
+                    toggle_tab_pin(hwnd, state, index);
+                }
+            }
+            TAB_MENU_PAUSE_ANIMATIONS => {
+                if let Some(index) = state.tab_manager.find_tab_by_id(tab_id) {
+                    state.tab_manager.toggle_pause_animations(index);
+                }
+            }
+            TAB_MENU_CLOSE_OTHERS => {
+                if let Some(index) = state.tab_manager.find_tab_by_id(tab_id) {
+                    close_other_tabs(hwnd, state, index, false);
+                }
+            }
+            TAB_MENU_CLOSE_TO_RIGHT => {
+                if let Some(index) = state.tab_manager.find_tab_by_id(tab_id) {
+                    close_other_tabs(hwnd, state, index, true);
+                }
+            }
+            TAB_MENU_FORCE_CLOSE => force_close_tab(hwnd, state, tab_id, hwnd),
+            TAB_MENU_SHOW_OUTPUT => {
+                if let Some(index) = state.tab_manager.find_tab_by_id(tab_id) {
+                    show_tab_output(hwnd, state, index);
+                }
+            }
+            TAB_MENU_COPY_PATH => {
+                if let Some(dir) = state
+                    .tab_manager
+                    .find_tab_by_id(tab_id)
+                    .and_then(|index| state.tab_manager.get_tab_working_directory(index))
+                {
+                    let text = dir.display().to_string();
+                    copy_to_clipboard(hwnd, state, &text);
+                }
+            }
+            TAB_MENU_REVEAL => {
+                if let Some(dir) = state
+                    .tab_manager
+                    .find_tab_by_id(tab_id)
+                    .and_then(|index| state.tab_manager.get_tab_working_directory(index))
+                {
+                    open_folder(dir);
+                }
+            }
+            TAB_MENU_OPEN_TERMINAL => {
+                if let Some(dir) = state
+                    .tab_manager
+                    .find_tab_by_id(tab_id)
+                    .and_then(|index| state.tab_manager.get_tab_working_directory(index))
+                {
+                    let command = &state.config.terminal_command;
+                    if let Err(e) = hooks::spawn_in_directory(command, dir) {
+                        show_error(
+                            &format!("Failed to run \"{}\": {}", command, e),
+                            "Open in Terminal",
+                        );
+                    }
+                }
+            }
+            TAB_MENU_COPY_TITLE => {
+                if let Some(index) = state.tab_manager.find_tab_by_id(tab_id) {
+                    let text = state.tab_manager.get_tab_label(index);
+                    copy_to_clipboard(hwnd, state, &text);
+                }
+            }
+            TAB_MENU_CANCEL_PENDING => cancel_pending_tabs(hwnd, state),
+            _ => {
+                // Menu dismissed - give the keyboard back to Neovide
+                state
+                    .tab_manager
+                    .activate_and_foreground_selected(hwnd, content_top());
+            }
+        }
+    }
+}
+
+/// Put `text` on the clipboard and give the keyboard back to Neovide
+fn copy_to_clipboard(hwnd: HWND, state: &mut WindowState, text: &str) {
+    if !clipboard::set_text(hwnd, text) {
+        eprintln!("Failed to copy to the clipboard: it is in use by another application");
+    }
+    state
+        .tab_manager
+        .activate_and_foreground_selected(hwnd, content_top());
+}
+
+/// Open the view of what a tab's Neovide wrote to stdout and stderr, replacing
+/// the view of another tab
+fn show_tab_output(hwnd: HWND, state: &mut WindowState, index: usize) {
+    let Some(log) = state.tab_manager.get_tab_output(index) else {
+        return;
+    };
+    state.output_view = None;
+    let label = state.tab_manager.get_tab_label(index);
+    state.output_view = OutputView::open(hwnd, &label, log);
+}
+
+/// Edges (ABE_*) of the window's monitor that have an auto-hide taskbar
+fn autohide_taskbar_edges(hwnd: HWND) -> Vec<u32> {
+    unsafe {
+        let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
+        let mut info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+            ..Default::default()
+        };
+        if !GetMonitorInfoW(monitor, &mut info).as_bool() {
+            return Vec::new();
+        }
+        [ABE_LEFT, ABE_TOP, ABE_RIGHT, ABE_BOTTOM]
+            .into_iter()
+            .filter(|&edge| {
+                let mut data = APPBARDATA {
+                    cbSize: std::mem::size_of::<APPBARDATA>() as u32,
+                    uEdge: edge,
+                    rc: info.rcMonitor,
+                    ..Default::default()
+                };
+                // Returns the taskbar's window if it auto-hides on that edge
+                SHAppBarMessage(ABM_GETAUTOHIDEBAREX, &mut data) != 0
+            })
+            .collect()
+    }
+}
+
+/// Shrink a maximized window's client rect away from auto-hide taskbar `edges`
+fn leave_autohide_taskbar_edges(rect: &mut RECT, edges: &[u32]) {
+    for &edge in edges {
+        match edge {
+            ABE_LEFT => rect.left += AUTOHIDE_TASKBAR_EDGE,
+            ABE_TOP => rect.top += AUTOHIDE_TASKBAR_EDGE,
+            ABE_RIGHT => rect.right -= AUTOHIDE_TASKBAR_EDGE,
+            ABE_BOTTOM => rect.bottom -= AUTOHIDE_TASKBAR_EDGE,
+            _ => {}
+        }
+    }
+}
+
+/// Add the wrapper's actions to the window menu (right-click on the title bar,
+/// Alt+Space). Starts over from the default menu, so it also refreshes the
+/// profile list after a config reload.
+#[allow(unused_must_use)]
+fn update_system_menu(hwnd: HWND, state: &WindowState) {
+    unsafe {
+        GetSystemMenu(hwnd, true);
+        let menu = GetSystemMenu(hwnd, false);
+        if menu.is_invalid() {
+            return;
+        }
+        AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null());
+        AppendMenuW(menu, MF_STRING, SYSMENU_NEW_TAB, w!("New tab"));
+        if let Ok(profiles_menu) = CreatePopupMenu() {
+            append_profile_items(
+                profiles_menu,
+                &state.config,
+                |i| SYSMENU_PROFILE_BASE + i * SYSMENU_ID_STEP,
+                false,
+            );
+            AppendMenuW(
+                menu,
+                MF_POPUP,
+                profiles_menu.0 as usize,
+                w!("New tab from profile"),
+            );
+        }
+        AppendMenuW(menu, MF_STRING, SYSMENU_CLOSE_TAB, w!("Close tab"));
+        AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null());
+        let displays = monitors::enumerate();
+        if displays.len() > 1
+            && let Ok(monitors_menu) = CreatePopupMenu()
+        {
+            for (i, monitor) in displays.iter().enumerate() {
+                let label = windows::core::HSTRING::from(monitor.label(i + 1));
+                let id = SYSMENU_MONITOR_BASE + i * SYSMENU_ID_STEP;
+                AppendMenuW(monitors_menu, MF_STRING, id, &label);
+            }
+            AppendMenuW(
+                menu,
+                MF_POPUP,
+                monitors_menu.0 as usize,
+                w!("Move to monitor"),
+            );
+        }
+        let on_top_flags = if state.always_on_top {
+            MF_STRING | MF_CHECKED
+        } else {
+            MF_STRING
+        };
+        AppendMenuW(
+            menu,
+            on_top_flags,
+            SYSMENU_ALWAYS_ON_TOP,
+            w!("Always on top"),
+        );
+        AppendMenuW(menu, MF_STRING, SYSMENU_SETTINGS, w!("Settings\u{2026}"));
+    }
+}
+
+/// Append the profiles of `config` to `menu`, the discovered ones in a submenu
+/// per group. `command_id` gives the command ID of a profile index.
+#[allow(unused_must_use)]
+unsafe fn append_profile_items(
+    menu: HMENU,
+    config: &Config,
+    command_id: impl Fn(usize) -> usize,
+    with_icons: bool,
+) {
+    for (i, profile) in config.profiles.iter().enumerate() {
+        if profile.group.is_none() {
+            append_profile_item(menu, command_id(i), profile, with_icons);
+        }
+    }
+    for group in config.profile_groups() {
+        let Ok(group_menu) = CreatePopupMenu() else {
+            continue;
+        };
+        for (i, profile) in config.profiles.iter().enumerate() {
+            if profile.group.as_deref() == Some(group) {
+                append_profile_item(group_menu, command_id(i), profile, with_icons);
+            }
+        }
+        let label = windows::core::HSTRING::from(group);
+        AppendMenuW(menu, MF_POPUP, group_menu.0 as usize, &label);
+    }
+}
+
+/// Append a menu item for `profile`, with its icon if `with_icon`
+#[allow(unused_must_use)]
+unsafe fn append_profile_item(menu: HMENU, id: usize, profile: &Profile, with_icon: bool) {
+    let label = windows::core::HSTRING::from(profile.name.as_str());
+    AppendMenuW(menu, MF_STRING, id, &label);
+    // The bitmap stays owned by the icon cache
+    if with_icon && let Some(bitmap) = get_icon_bitmap(&profile.icon) {
+        let info = MENUITEMINFOW {
+            cbSize: std::mem::size_of::<MENUITEMINFOW>() as u32,
+            fMask: MIIM_BITMAP,
+            hbmpItem: bitmap,
+            ..Default::default()
+        };
+        SetMenuItemInfoW(menu, id as u32, false, &info);
+    }
+}
+
+/// Show the profiles of a group of discovered profiles (chosen in the dropdown)
+/// at the cursor and open a tab with the chosen one
+#[allow(unused_must_use)]
+unsafe fn show_profile_group_menu(hwnd: HWND, group_index: usize) {
+    let Some(profiles) = window_state(hwnd).map(|mut state| {
+        state.dropdown_hwnd = None; // Popup already destroyed itself
+        state.dropdown_state = DropdownState::Closed;
+        let group = state
+            .config
+            .profile_groups()
+            .get(group_index)
+            .map(|group| group.to_string());
+        state
+            .config
+            .profiles
+            .iter()
+            .enumerate()
+            .filter(|(_, profile)| group.is_some() && profile.group == group)
+            .map(|(i, profile)| (i, profile.clone()))
+            .collect::<Vec<_>>()
+    }) else {
+        return;
+    };
+    InvalidateRect(hwnd, None, false);
+
+    // The menu runs a modal loop, so don't hold on to the state while it is open
+    let mut command = 0;
+    if let Ok(menu) = CreatePopupMenu() {
+        for (i, profile) in &profiles {
+            // Command IDs start at 1; 0 means the menu was dismissed
+            append_profile_item(menu, i + 1, profile, true);
+        }
+        let mut cursor = POINT::default();
+        GetCursorPos(&mut cursor);
+        SetForegroundWindow(hwnd);
+        command = TrackPopupMenu(
+            menu,
+            TPM_RETURNCMD | TPM_NONOTIFY,
+            cursor.x,
+            cursor.y,
+            0,
+            hwnd,
+            None,
+        )
+        .0 as usize;
+        DestroyMenu(menu);
+    }
+
+    let Some(mut guard) = window_state(hwnd) else {
+        return;
+    };
+    let state = &mut *guard;
+    match profiles.iter().find(|(i, _)| i + 1 == command) {
+        Some((index, profile)) => open_profile_tab(hwnd, state, profile, *index, None),
+        None => {
+            // Menu dismissed - give the keyboard back to Neovide
+            state
+                .tab_manager
+                .activate_and_foreground_selected(hwnd, content_top());
+        }
+    }
+    InvalidateRect(hwnd, None, false);
+}
+
+/// Run a command added to the window menu; false for the system's own commands
+unsafe fn run_system_menu_command(hwnd: HWND, state: &mut WindowState, command: usize) -> bool {
+    unsafe {
+        match command {
+            SYSMENU_NEW_TAB => {
+                let default_profile = state.config.default_profile().clone();
+                open_profile_tab(hwnd, state, &default_profile, 0, None);
+            }
+            SYSMENU_CLOSE_TAB => {
+                let selected = state.tab_manager.selected_index();
+                if selected < state.tab_manager.count() {
+                    close_tab_gracefully(hwnd, state, selected);
+                }
+            }
+            SYSMENU_ALWAYS_ON_TOP => set_always_on_top(hwnd, state, !state.always_on_top),
+            SYSMENU_SETTINGS => open_config_file(),
+            _ if (SYSMENU_MONITOR_BASE..SYSMENU_PROFILE_BASE).contains(&command) => {
+                let index = (command - SYSMENU_MONITOR_BASE) / SYSMENU_ID_STEP;
+                if let Some(monitor) = monitors::enumerate().get(index) {
+                    move_to_monitor(hwnd, state, monitor);
+                }
+            }
+            _ if (SYSMENU_PROFILE_BASE..SC_SIZE as usize).contains(&command) => {
+                let profile_index = (command - SYSMENU_PROFILE_BASE) / SYSMENU_ID_STEP;
+                if let Some(profile) = state.config.get_profile(profile_index) {
+                    let profile = profile.clone();
+                    open_profile_tab(hwnd, state, &profile, profile_index, None);
+                }
+            }
+            _ => return false,
+        }
+        let _ = InvalidateRect(hwnd, None, false);
+        true
+    }
+}
+
+/// The connected display `target` chooses, if it is connected
+fn find_monitor(target: &MonitorTarget) -> Option<Monitor> {
+    let mut displays = monitors::enumerate();
+    let index = monitors::find(&displays, target)?;
+    Some(displays.swap_remove(index))
+}
+
+/// Move the wrapper onto `monitor`, at the same place on the display. A maximized
+/// or minimized wrapper is restored for the move, and maximized again there.
+/// Returns false if it already was on the monitor.
+#[allow(unused_must_use)]
+unsafe fn move_wrapper_to_monitor(hwnd: HWND, monitor: &Monitor) -> bool {
+    let maximized = IsZoomed(hwnd).as_bool();
+    if maximized || IsIconic(hwnd).as_bool() {
+        ShowWindow(hwnd, SW_RESTORE);
+    }
+    let Some(current) = monitors::monitor_of_window(hwnd) else {
+        return false;
+    };
+    if current.device == monitor.device {
+        if maximized {
+            ShowWindow(hwnd, SW_MAXIMIZE);
+        }
+        return false;
+    }
+    let mut rect = RECT::default();
+    if GetWindowRect(hwnd, &mut rect).is_err() {
+        return false;
+    }
+    let window = Rect {
+        left: rect.left,
+        top: rect.top,
+        right: rect.right,
+        bottom: rect.bottom,
+    };
+    let moved = monitors::move_rect(window, &current.work_area, &monitor.work_area);
+    SetWindowPos(
+        hwnd,
+        None,
+        moved.left,
+        moved.top,
+        moved.right - moved.left,
+        moved.bottom - moved.top,
+        SWP_NOZORDER | SWP_NOACTIVATE,
+    );
+    if maximized {
+        ShowWindow(hwnd, SW_MAXIMIZE);
+    }
+    true
+}
+
+/// Move the wrapper and the Neovide windows onto `monitor` (window menu's "Move
+/// to monitor", a changed `monitor` option)
+unsafe fn move_to_monitor(hwnd: HWND, state: &mut WindowState, monitor: &Monitor) {
+    if !move_wrapper_to_monitor(hwnd, monitor) {
+        return;
+    }
+    follow_wrapper_move(hwnd, state);
+    remember_window_layout(hwnd, state);
+}
+
+/// Move the Neovide windows after the wrapper was moved by code
+unsafe fn follow_wrapper_move(hwnd: HWND, state: &mut WindowState) {
+    // The hidden Neovide windows are moved when shown
+    state.tab_manager.mark_positions_dirty();
+    state.resize_grips.update();
+    state
+        .tab_manager
+        .update_selected_position(hwnd, content_top());
+}
+
+/// Put the window where `layout` has it, without activating it unless it gets
+/// maximized. A minimized window stays minimized and is restored there; a window
+/// not shown yet (at startup) is shown by CreateWindowExW as usual.
+#[allow(unused_must_use)]
+unsafe fn apply_window_layout(hwnd: HWND, layout: WindowLayout) {
+    let mut placement = WINDOWPLACEMENT {
+        length: std::mem::size_of::<WINDOWPLACEMENT>() as u32,
+        ..Default::default()
+    };
+    if GetWindowPlacement(hwnd, &mut placement).is_err() {
+        return;
+    }
+    // Window placements are in workspace coordinates; so is the remembered one
+    placement.rcNormalPosition = RECT {
+        left: layout.left,
+        top: layout.top,
+        right: layout.right,
+        bottom: layout.bottom,
+    };
+    let show = if IsIconic(hwnd).as_bool() {
+        if layout.maximized {
+            placement.flags |= WPF_RESTORETOMAXIMIZED;
+        } else {
+            placement.flags &= !WPF_RESTORETOMAXIMIZED;
+        }
+        SW_SHOWMINNOACTIVE
+    } else if layout.maximized {
+        SW_SHOWMAXIMIZED
+    } else if !IsWindowVisible(hwnd).as_bool() {
+        SW_HIDE
+    } else {
+        SW_SHOWNOACTIVATE
+    };
+    placement.showCmd = show.0 as u32;
+    SetWindowPlacement(hwnd, &placement);
+}
+
+/// Remember where the window is on the connected displays (`remember_window_layout`);
+/// not while it is minimized or the displays change
+unsafe fn remember_window_layout(hwnd: HWND, state: &mut WindowState) {
+    if !state.config.remember_window_layout || IsIconic(hwnd).as_bool() {
+        return;
+    }
+    let Some(setup) = state.monitor_setup.as_deref() else {
+        return;
+    };
+    let mut placement = WINDOWPLACEMENT {
+        length: std::mem::size_of::<WINDOWPLACEMENT>() as u32,
+        ..Default::default()
+    };
+    if GetWindowPlacement(hwnd, &mut placement).is_err() {
+        return;
+    }
+    let normal = placement.rcNormalPosition;
+    let layout = WindowLayout {
+        left: normal.left,
+        top: normal.top,
+        right: normal.right,
+        bottom: normal.bottom,
+        maximized: IsZoomed(hwnd).as_bool(),
+    };
+    if state.window_layouts.set(setup, layout)
+        && let Err(e) = state.window_layouts.save()
+    {
+        eprintln!("Failed to save the window layout: {}", e);
+    }
+}
+
+/// Once Windows is done rearranging after the displays changed: put the window
+/// where it was the last time these displays were connected, and remember its
+/// place on them from now on
+unsafe fn enter_monitor_setup(hwnd: HWND, state: &mut WindowState) {
+    let setup = monitors::setup_key(&monitors::enumerate());
+    if state.config.remember_window_layout
+        && let Some(layout) = state.window_layouts.get(&setup)
+    {
+        apply_window_layout(hwnd, layout);
+        follow_wrapper_move(hwnd, state);
+    }
+    state.monitor_setup = Some(setup);
+}
+
+/// Keep the wrapper and its Neovide windows above other windows (or stop doing so)
+#[allow(unused_must_use)]
+fn set_always_on_top(hwnd: HWND, state: &mut WindowState, on_top: bool) {
+    state.always_on_top = on_top;
+    process::set_topmost(on_top);
+    unsafe {
+        let insert_after = if on_top { HWND_TOPMOST } else { HWND_NOTOPMOST };
+        SetWindowPos(
+            hwnd,
+            insert_after,
+            0,
+            0,
+            0,
+            0,
+            SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+        );
+        state.tab_manager.set_topmost(on_top);
+        let check = if on_top { MF_CHECKED } else { MF_UNCHECKED };
+        CheckMenuItem(
+            GetSystemMenu(hwnd, false),
+            SYSMENU_ALWAYS_ON_TOP as u32,
+            (MF_BYCOMMAND | check).0,
+        );
+    }
+    // The Neovide window goes above the wrapper again
+    state
+        .tab_manager
+        .activate_and_foreground_selected(hwnd, content_top());
+}
+
+/// Show the settings menu below the gear button at `button` (client coordinates)
+#[allow(unused_must_use)]
+unsafe fn show_settings_menu(hwnd: HWND, state: &mut WindowState, button: &RECT) {
+    let Ok(menu) = CreatePopupMenu() else {
+        return;
+    };
+    AppendMenuW(
+        menu,
+        MF_STRING,
+        SETTINGS_MENU_EDIT_CONFIG,
+        w!("Edit config"),
+    );
+    SetMenuDefaultItem(menu, SETTINGS_MENU_EDIT_CONFIG as u32, 0);
+    AppendMenuW(
+        menu,
+        MF_STRING,
+        SETTINGS_MENU_RELOAD_CONFIG,
+        w!("Reload config"),
+    );
+    AppendMenuW(
+        menu,
+        MF_STRING,
+        SETTINGS_MENU_OPEN_DATA_FOLDER,
+        w!("Open data folder"),
+    );
+    if state.config.tiling_wm.is_enabled() {
+        AppendMenuW(
+            menu,
+            MF_STRING,
+            SETTINGS_MENU_COPY_TILING_RULES,
+            w!("Copy tiling WM rules"),
+        );
+    }
+    AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null());
+    AppendMenuW(
+        menu,
+        MF_STRING,
+        SETTINGS_MENU_ABOUT,
+        w!("About neovide-tabs"),
+    );
+
+    // Right-aligned below the button, like the menus of browsers
+    let mut anchor = POINT {
+        x: button.right,
+        y: button.bottom,
+    };
+    ClientToScreen(hwnd, &mut anchor);
+    SetForegroundWindow(hwnd);
+    let command = TrackPopupMenu(
+        menu,
+        TPM_RETURNCMD | TPM_NONOTIFY | TPM_RIGHTALIGN | TPM_TOPALIGN,
+        anchor.x,
+        anchor.y,
+        0,
+        hwnd,
+        None,
+    );
+    DestroyMenu(menu);
+
+    match command.0 as usize {
+        SETTINGS_MENU_EDIT_CONFIG => {
+            edit_config_file(hwnd, state);
+            return;
+        }
+        SETTINGS_MENU_RELOAD_CONFIG => {
+            PostMessageW(hwnd, WM_CONFIG_RELOAD, WPARAM(0), LPARAM(0));
+        }
+        SETTINGS_MENU_OPEN_DATA_FOLDER => open_data_folder(),
+        SETTINGS_MENU_COPY_TILING_RULES => {
+            if let Some(rules) =
+                tiling::ignore_rules(state.config.tiling_wm, &state.config.window_class)
+            {
+                copy_to_clipboard(hwnd, state, &rules);
+                return;
+            }
+        }
+        SETTINGS_MENU_ABOUT => show_about(hwnd),
+        _ => {}
+    }
+    // Give the keyboard back to Neovide
+    state
+        .tab_manager
+        .activate_and_foreground_selected(hwnd, content_top());
+}
+
+/// Edit the config file in the selected tab's nvim if it can be reached over RPC,
+/// or else in the file's associated editor
+unsafe fn edit_config_file(hwnd: HWND, state: &mut WindowState) {
+    if let Some(path) = config::active_config_file()
+        && state
+            .tab_manager
+            .edit_in_tab(state.tab_manager.selected_index(), &path)
+    {
+        state
+            .tab_manager
+            .activate_and_foreground_selected(hwnd, content_top());
+        return;
+    }
+    open_config_file();
+}
+
+/// Open the data directory (icons and sessions) in Explorer
+fn open_data_folder() {
+    let Some(dir) = config::data_dir_path() else {
+        show_error("The data folder could not be found.", "Settings");
+        return;
+    };
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        show_error(
+            &format!("Failed to create {}: {}", dir.display(), e),
+            "Settings",
+        );
+        return;
+    }
+    open_folder(&dir);
+}
+
+/// Open `dir` in Explorer
+fn open_folder(dir: &std::path::Path) {
+    unsafe {
+        let folder = windows::core::HSTRING::from(dir.as_os_str());
+        ShellExecuteW(
+            None,
+            w!("open"),
+            &folder,
+            PCWSTR::null(),
+            PCWSTR::null(),
+            SW_SHOWNORMAL,
+        );
+    }
+}
+
+/// Show the version and where the config and data are kept
+fn show_about(hwnd: HWND) {
+    let location = |path: Option<std::path::PathBuf>| {
+        path.map_or_else(|| "-".to_string(), |path| path.display().to_string())
+    };
+    let text = format!(
+        "neovide-tabs {}\n\
+        Tabs for Neovide windows\n\n\
+        Config file: {}\n\
+        Data folder: {}\n\n\
+        https://github.com/keathmilligan/neovide-tabs",
+        env!("CARGO_PKG_VERSION"),
+        location(config::active_config_file()),
+        location(config::data_dir_path()),
+    );
+    unsafe {
+        MessageBoxW(
+            hwnd,
+            &windows::core::HSTRING::from(text),
+            w!("About neovide-tabs"),
+            MB_OK | MB_ICONINFORMATION,
+        );
+    }
+}
+
+/// Open the config file in its associated editor, or Notepad if .jsonc has none
+fn open_config_file() {
+    let Some(path) = config::active_config_file() else {
+        show_error("No config file could be created.", "Settings");
+        return;
+    };
+    unsafe {
+        let file = windows::core::HSTRING::from(path.as_os_str());
+        let result = ShellExecuteW(
+            None,
+            w!("open"),
+            &file,
+            PCWSTR::null(),
+            PCWSTR::null(),
+            SW_SHOWNORMAL,
+        );
+        // ShellExecute reports errors (e.g. no associated program) as values up to 32
+        if result.0 as usize <= 32 {
+            let quoted = windows::core::HSTRING::from(format!("\"{}\"", path.display()));
+            ShellExecuteW(
+                None,
+                w!("open"),
+                w!("notepad.exe"),
+                &quoted,
+                PCWSTR::null(),
+                SW_SHOWNORMAL,
+            );
+        }
+    }
+}
+
+/// Paint the overflow button (shows "+N" count indicator) styled like a tab
+/// When has_selected_overflow is true, also displays the selected tab's icon
+#[allow(unused_must_use)]
+fn paint_overflow_button(
+    hdc: windows::Win32::Graphics::Gdi::HDC,
+    rect: &RECT,
+    overflow_count: usize,
+    is_hovered: bool,
+    has_selected_overflow: bool,
+    selected_icon: Option<&str>,
+) {
+    unsafe {
+        // Determine background color - acts like a "selected" tab if it contains the selected tab
+        let bg_color = if is_hovered {
+            TAB_HOVER_COLOR
+        } else if has_selected_overflow {
+            // When selected tab is in overflow, use unselected color (like other non-active tabs)
+            TAB_UNSELECTED_COLOR
+        } else {
+            TAB_UNSELECTED_COLOR
+        };
+
+        let bg_brush = CreateSolidBrush(COLORREF(rgb_to_colorref(bg_color)));
+        FillRect(hdc, rect, bg_brush);
+        DeleteObject(HGDIOBJ(bg_brush.0));
+
+        // Draw outline around overflow button (top, left, right - like a tab)
+        let outline_pen = CreatePen(PS_SOLID, 1, COLORREF(rgb_to_colorref(TAB_OUTLINE_COLOR)));
+        let old_pen = SelectObject(hdc, HGDIOBJ(outline_pen.0));
+
+        // If selected tab is in overflow, extend sides down to connect with bottom line
+        let side_bottom = if has_selected_overflow {
+            titlebar_height() - 1
+        } else {
+            rect.bottom
+        };
+
+        MoveToEx(hdc, rect.left, side_bottom, None);
+        LineTo(hdc, rect.left, rect.top);
+        LineTo(hdc, rect.right - 1, rect.top);
+        LineTo(hdc, rect.right - 1, side_bottom);
+
+        SelectObject(hdc, old_pen);
+        DeleteObject(HGDIOBJ(outline_pen.0));
+
+        // Draw selected tab's icon if selected is in overflow
+        let text_offset = if has_selected_overflow {
+            if let Some(icon_filename) = selected_icon {
+                if let Some(hbitmap) = get_icon_bitmap(icon_filename) {
+                    let icon_x = rect.left + 4;
+                    let icon_y = (rect.top + rect.bottom - ICON_SIZE) / 2;
+                    paint_icon(hdc, hbitmap, icon_x, icon_y, ICON_SIZE, ICON_SIZE);
+                    ICON_SIZE + 2 // Icon width + small padding
+                } else {
+                    0
+                }
+            } else {
+                0
+            }
+        } else {
+            0
+        };
+
+        // Draw the count text
+        SetBkMode(hdc, TRANSPARENT);
+        SetTextColor(hdc, COLORREF(0x00FFFFFF));
+
+        let font = fonts::ui_font(TextSize::Label, 400);
+        let old_font = SelectObject(hdc, HGDIOBJ(font.0));
+
+        // Show count like "+3" for overflow tabs
+        let text = format!("+{}", overflow_count);
+        let text_wide: Vec<u16> = text.encode_utf16().collect();
+
+        let mut tm = TEXTMETRICW::default();
+        GetTextMetricsW(hdc, &mut tm);
+        let text_height = tm.tmHeight;
+
+        let mut text_size = SIZE::default();
+        GetTextExtentPoint32W(hdc, &text_wide, &mut text_size);
+
+        // Center text in remaining space (after icon if present)
+        let text_area_left = rect.left + text_offset;
+        let text_x = (text_area_left + rect.right - text_size.cx) / 2;
+        let text_y = (rect.top + rect.bottom - text_height) / 2;
+        TextOutW(hdc, text_x, text_y, &text_wide);
+
+        SelectObject(hdc, old_font);
+    }
+}
+
+/// Paint the tab bar (all tabs and new tab button)
+#[allow(unused_must_use)]
+fn paint_tab_bar(
+    hdc: windows::Win32::Graphics::Gdi::HDC,
+    tab_manager: &TabManager<NeovideProcess>,
+    hovered_tab: HoveredTab,
+    client_width: i32,
+    background_color: u32,
+    tab_numbers: Option<&[Option<String>]>,
+) {
+    let max_x = get_tab_bar_max_x(client_width);
+    let selected_index = tab_manager.selected_index();
+    let drag_state = &tab_manager.drag_state;
+    let (visible_count, has_overflow) = calculate_visible_tabs(tab_manager.count(), client_width);
+
+    // First pass: paint all visible non-dragged tabs
+    for (i, _tab) in tab_manager.iter() {
+        // Skip overflow tabs
+        if i >= visible_count {
+            break;
+        }
+
+        // Skip the dragged tab - we'll paint it last so it appears on top
+        if let Some(drag) = drag_state {
+            if drag.is_active() && i == drag.tab_index {
+                continue;
+            }
+        }
+
+        let tab_rect: RECT = get_tab_rect(i, client_width).into();
+        if tab_rect.left > max_x {
+            break;
+        }
+
+        let is_selected = i == selected_index;
+        let is_hovered = matches!(hovered_tab, HoveredTab::Tab(idx) if idx == i);
+        let close_hovered = matches!(hovered_tab, HoveredTab::TabClose(idx) if idx == i);
+        let retry_hovered = matches!(hovered_tab, HoveredTab::TabRetry(idx) if idx == i);
+        let label = tab_manager.get_tab_label(i);
+        let icon = tab_manager.get_tab_icon(i);
+
+        paint_tab(
+            hdc,
+            &tab_rect,
+            &label,
+            icon,
+            is_selected,
+            is_hovered,
+            close_hovered,
+            tab_manager.is_tab_suspended(i) || tab_manager.is_tab_pending(i),
+            tab_manager.is_tab_not_responding(i),
+            tab_manager.is_tab_pinned(i),
+            tab_manager.get_tab_spawn_error(i).is_some(),
+            retry_hovered,
+            tab_manager.attention_intensity(i),
+            tab_subtitle(tab_manager, i).as_deref(),
+            background_color,
+        );
+        if let Some(label) = tab_numbers.and_then(|labels| labels.get(i)?.as_deref()) {
+            paint_tab_number(hdc, &tab_rect, label);
+        }
+        if let Some(status) = tab_manager.get_tab_git_status(i)
+            && tab_manager.get_tab_spawn_error(i).is_none()
+        {
+            paint_git_dot(hdc, &tab_rect, status);
+        }
+    }
+
+    // Paint overflow button if there are overflow tabs
+    if has_overflow {
+        let overflow_rect: RECT = get_overflow_button_rect(visible_count, client_width).into();
+        let overflow_count = tab_manager.count() - visible_count;
+        let is_hovered = matches!(hovered_tab, HoveredTab::OverflowButton);
+        let has_selected_overflow = selected_index >= visible_count;
+        let selected_icon = if has_selected_overflow {
+            tab_manager.get_tab_icon(selected_index)
+        } else {
+            None
+        };
+        paint_overflow_button(
+            hdc,
+            &overflow_rect,
+            overflow_count,
+            is_hovered,
+            has_selected_overflow,
+            selected_icon,
+        );
+    }
+
+    // Paint new tab button
+    let new_tab_rect: RECT =
+        get_new_tab_button_rect_ex(visible_count, has_overflow, client_width).into();
+    if new_tab_rect.right <= max_x {
+        let is_hovered = matches!(hovered_tab, HoveredTab::NewTabButton);
+        paint_new_tab_button(hdc, &new_tab_rect, is_hovered);
+    }
+
+    // Paint dropdown button
+    let dropdown_rect: RECT =
+        get_dropdown_button_rect_ex(visible_count, has_overflow, client_width).into();
+    if dropdown_rect.right <= max_x {
+        let is_hovered = matches!(hovered_tab, HoveredTab::ProfileDropdown);
+        paint_dropdown_button(hdc, &dropdown_rect, is_hovered);
+    }
+
+    // Draw line at the bottom of the tab bar with a gap for the selected tab
+    // This creates the illusion of physical tabbed pages
+    paint_tab_bar_bottom_line(hdc, tab_manager, client_width);
+
+    // Second pass: paint the dragged tab at its visual position (on top of everything)
+    if let Some(drag) = drag_state {
+        if drag.is_active() {
+            let drag_index = drag.tab_index;
+            let visual_x = drag.get_visual_x();
+
+            // Clamp the visual position to stay within the visible tab bar bounds
+            let min_x = tab_bar_left();
+            let max_tab_x =
+                tab_bar_left() + ((visible_count.saturating_sub(1)) as i32 * tab_width());
+            let clamped_x = visual_x.clamp(min_x, max_tab_x.max(min_x));
+
+            let drag_rect = RECT {
+                left: clamped_x,
+                top: TAB_VERTICAL_PADDING,
+                right: clamped_x + tab_width(),
+                bottom: titlebar_height() - TAB_VERTICAL_PADDING,
+            };
+
+            let is_selected = drag_index == selected_index;
+            let label = tab_manager.get_tab_label(drag_index);
+            let icon = tab_manager.get_tab_icon(drag_index);
+
+            // Dragged tab is never hovered (we're dragging it)
+            paint_tab(
+                hdc,
+                &drag_rect,
+                &label,
+                icon,
+                is_selected,
+                false,
+                false,
+                tab_manager.is_tab_suspended(drag_index) || tab_manager.is_tab_pending(drag_index),
+                tab_manager.is_tab_not_responding(drag_index),
+                tab_manager.is_tab_pinned(drag_index),
+                tab_manager.get_tab_spawn_error(drag_index).is_some(),
+                false,
+                tab_manager.attention_intensity(drag_index),
+                tab_subtitle(tab_manager, drag_index).as_deref(),
+                background_color,
+            );
+            if let Some(label) = tab_numbers.and_then(|labels| labels.get(drag_index)?.as_deref()) {
+                paint_tab_number(hdc, &drag_rect, label);
+            }
+            if let Some(status) = tab_manager.get_tab_git_status(drag_index)
+                && tab_manager.get_tab_spawn_error(drag_index).is_none()
+            {
+                paint_git_dot(hdc, &drag_rect, status);
+            }
+        }
+    }
+}
+
+/// Paint a tab's git status as a dot over the top-right of its icon: amber for
+/// uncommitted changes, blue for commits to push or pull, nothing when clean
+#[allow(unused_must_use)]
+fn paint_git_dot(hdc: windows::Win32::Graphics::Gdi::HDC, tab_rect: &RECT, status: &GitStatus) {
+    let color = match status.indicator() {
+        Some(GitIndicator::Dirty) => GIT_DIRTY_COLOR,
+        Some(GitIndicator::Unsynced) => GIT_UNSYNCED_COLOR,
+        None => return,
+    };
+    unsafe {
+        let icon_rect = get_tab_icon_rect(tab_rect);
+        let left = icon_rect.right - GIT_DOT_SIZE + 2;
+        let top = icon_rect.top - 2;
+
+        let color = COLORREF(rgb_to_colorref(color));
+        let pen = CreatePen(PS_SOLID, 1, color);
+        let old_pen = SelectObject(hdc, HGDIOBJ(pen.0));
+        let brush = CreateSolidBrush(color);
+        let old_brush = SelectObject(hdc, HGDIOBJ(brush.0));
+        Ellipse(hdc, left, top, left + GIT_DOT_SIZE, top + GIT_DOT_SIZE);
+        SelectObject(hdc, old_brush);
+        DeleteObject(HGDIOBJ(brush.0));
+        SelectObject(hdc, old_pen);
+        DeleteObject(HGDIOBJ(pen.0));
+    }
+}
+
+/// Paint a tab's hotkey number in a small badge over the top-left of its icon
+#[allow(unused_must_use)]
+fn paint_tab_number(hdc: windows::Win32::Graphics::Gdi::HDC, tab_rect: &RECT, label: &str) {
+    unsafe {
+        let text_wide: Vec<u16> = label.encode_utf16().collect();
+        let old_font = fonts::select_font_for_text(hdc, TextSize::Caption, 600, &text_wide);
+        let mut text_size = SIZE::default();
+        GetTextExtentPoint32W(hdc, &text_wide, &mut text_size);
+
+        let icon_rect = get_tab_icon_rect(tab_rect);
+        let height = text_size.cy;
+        let width = (text_size.cx + 6).max(height);
+        let rect = RECT {
+            left: icon_rect.left - 3,
+            top: icon_rect.top - 3,
+            right: icon_rect.left - 3 + width,
+            bottom: icon_rect.top - 3 + height,
+        };
+
+        let color = COLORREF(rgb_to_colorref(TAB_OUTLINE_COLOR));
+        let pen = CreatePen(PS_SOLID, 1, color);
+        let old_pen = SelectObject(hdc, HGDIOBJ(pen.0));
+        let brush = CreateSolidBrush(color);
+        let old_brush = SelectObject(hdc, HGDIOBJ(brush.0));
+        RoundRect(
+            hdc,
+            rect.left,
+            rect.top,
+            rect.right,
+            rect.bottom,
+            height,
+            height,
+        );
+        SelectObject(hdc, old_brush);
+        DeleteObject(HGDIOBJ(brush.0));
+        SelectObject(hdc, old_pen);
+        DeleteObject(HGDIOBJ(pen.0));
+
+        SetBkMode(hdc, TRANSPARENT);
+        SetTextColor(hdc, COLORREF(rgb_to_colorref(0xffffff)));
+        TextOutW(
+            hdc,
+            rect.left + (width - text_size.cx) / 2,
+            rect.top,
+            &text_wide,
+        );
+        SelectObject(hdc, old_font);
+    }
+}
+
+/// Paint the bottom line of the tab bar with a gap for the selected tab (or overflow button)
+#[allow(unused_must_use)]
+fn paint_tab_bar_bottom_line(
+    hdc: windows::Win32::Graphics::Gdi::HDC,
+    tab_manager: &TabManager<NeovideProcess>,
+    client_width: i32,
+) {
+    unsafe {
+        let outline_pen = CreatePen(PS_SOLID, 1, COLORREF(rgb_to_colorref(TAB_OUTLINE_COLOR)));
+        let old_pen = SelectObject(hdc, HGDIOBJ(outline_pen.0));
+
+        // The bottom line is at the titlebar height - 1 (bottom of tab area)
+        let line_y = titlebar_height() - 1;
+        let line_start_x = 0;
+        let line_end_x = client_width;
+
+        // Determine where the gap should be
+        let selected_index = tab_manager.selected_index();
+        let (visible_count, has_overflow) =
+            calculate_visible_tabs(tab_manager.count(), client_width);
+
+        // If selected tab is in overflow, gap is at the overflow button
+        // Otherwise, gap is at the selected tab
+        let gap_rect = if has_overflow && selected_index >= visible_count {
+            // Selected tab is in overflow - gap at overflow button
+            get_overflow_button_rect(visible_count, client_width)
+        } else {
+            // Selected tab is visible - gap at the selected tab
+            get_tab_rect(selected_index, client_width)
+        };
+
+        // Draw line from left edge to start of gap (connects with left side)
+        if gap_rect.left > line_start_x {
+            MoveToEx(hdc, line_start_x, line_y, None);
+            LineTo(hdc, gap_rect.left + 1, line_y);
+        }
+
+        // Draw line from end of gap to right edge (connects with right side)
+        if gap_rect.right < line_end_x {
+            MoveToEx(hdc, gap_rect.right - 1, line_y, None);
+            LineTo(hdc, line_end_x, line_y);
+        }
+
+        SelectObject(hdc, old_pen);
+        DeleteObject(HGDIOBJ(outline_pen.0));
+    }
+}
+
+/// Blend two RGB colors (0x00RRGGBB); `amount` 0 returns `from`, 255 returns `to`
+fn blend_rgb(from: u32, to: u32, amount: u32) -> u32 {
+    let amount = amount.min(255);
+    let channel = |shift: u32| {
+        let a = (from >> shift) & 0xFF;
+        let b = (to >> shift) & 0xFF;
+        ((a * (255 - amount) + b * amount) / 255) << shift
+    };
+    channel(16) | channel(8) | channel(0)
+}
+
+/// Text shown below the startup spinner, e.g. "Starting Neovide… 3s", or
+/// "Starting Neovide… 2 of 5 tabs ready" while several tabs start together
+fn loading_indicator_text(elapsed: Duration, progress: Option<(usize, usize)>) -> String {
+    match progress {
+        Some((ready, total)) => {
+            format!("Starting Neovide\u{2026} {} of {} tabs ready", ready, total)
+        }
+        None => format!("Starting Neovide\u{2026} {}s", elapsed.as_secs()),
+    }
+}
+
+/// Rectangle of the "Cancel" button below the startup indicator
+fn get_startup_cancel_rect(client_rect: &RECT) -> RECT {
+    let center_x = (client_rect.left + client_rect.right) / 2;
+    let center_y = (client_rect.top + titlebar_height() + client_rect.bottom) / 2;
+    let top = center_y + LOADING_SPINNER_RADIUS + 32;
+    RECT {
+        left: center_x - STARTUP_CANCEL_WIDTH / 2,
+        top,
+        right: center_x + STARTUP_CANCEL_WIDTH / 2,
+        bottom: top + STARTUP_CANCEL_HEIGHT,
+    }
+}
+
+/// Paint the startup progress bar along the bottom of the tab bar
+#[allow(unused_must_use)]
+fn paint_startup_progress(
+    hdc: windows::Win32::Graphics::Gdi::HDC,
+    client_width: i32,
+    ready: usize,
+    total: usize,
+) {
+    if total == 0 {
+        return;
+    }
+    unsafe {
+        let filled = (client_width as i64 * ready as i64 / total as i64) as i32;
+        let bar_rect = RECT {
+            left: 0,
+            top: titlebar_height() - STARTUP_PROGRESS_HEIGHT,
+            right: filled,
+            bottom: titlebar_height(),
+        };
+        let brush = CreateSolidBrush(COLORREF(rgb_to_colorref(STARTUP_PROGRESS_COLOR)));
+        FillRect(hdc, &bar_rect, brush);
+        DeleteObject(HGDIOBJ(brush.0));
+    }
+}
+
+/// Paint the launch error of the selected error tab, centered in the content area
+#[allow(unused_must_use)]
+fn paint_spawn_error(hdc: windows::Win32::Graphics::Gdi::HDC, client_rect: &RECT, error: &str) {
+    unsafe {
+        let center_x = (client_rect.left + client_rect.right) / 2;
+        let center_y = (client_rect.top + titlebar_height() + client_rect.bottom) / 2;
+        let max_width = client_rect.right - client_rect.left - 32;
+        SetBkMode(hdc, TRANSPARENT);
+
+        let heading = "Neovide failed to start";
+        let heading_wide: Vec<u16> = heading.encode_utf16().collect();
+        let old_font = fonts::select_font_for_text(hdc, TextSize::Large, 600, &heading_wide);
+        let heading_height = current_text_height(hdc);
+        let mut heading_size = SIZE::default();
+        GetTextExtentPoint32W(hdc, &heading_wide, &mut heading_size);
+        SetTextColor(hdc, COLORREF(rgb_to_colorref(SPAWN_ERROR_COLOR)));
+        TextOutW(
+            hdc,
+            center_x - heading_size.cx / 2,
+            center_y - heading_height,
+            &heading_wide,
+        );
+        SelectObject(hdc, old_font);
+
+        // The error (with the command line or Neovide's last words), then how to retry
+        SetTextColor(hdc, COLORREF(rgb_to_colorref(0xC0C0C0)));
+        let mut y = center_y + 4;
+        for line in error
+            .lines()
+            .chain(["Click the retry icon on the tab to try again"])
+        {
+            let line_wide: Vec<u16> = line.encode_utf16().collect();
+            let old_font = fonts::select_font_for_text(hdc, TextSize::Body, 400, &line_wide);
+            let mut line_size = SIZE::default();
+            GetTextExtentPoint32W(hdc, &line_wide, &mut line_size);
+            let width = line_size.cx.min(max_width);
+            text_out_truncated(hdc, center_x - width / 2, y, line, max_width);
+            y += current_text_height(hdc) + 4;
+            SelectObject(hdc, old_font);
+        }
+    }
+}
+
+/// Paint the startup indicator (spinner, "Starting Neovide…" and elapsed time or
+/// batch progress) centered in the content area, with a "Cancel" button while
+/// tabs are still waiting to be launched
+#[allow(unused_must_use)]
+fn paint_loading_indicator(
+    hdc: windows::Win32::Graphics::Gdi::HDC,
+    client_rect: &RECT,
+    background_color: u32,
+    elapsed: Duration,
+    progress: Option<(usize, usize)>,
+    can_cancel: bool,
+    animate: bool,
+) {
+    unsafe {
+        let center_x = (client_rect.left + client_rect.right) / 2;
+        let center_y = (client_rect.top + titlebar_height() + client_rect.bottom) / 2;
+
+        // Spinner: a ring of dots with a bright "head" and a fading tail (standing
+        // still when animations are off)
+        let head = if animate {
+            (elapsed.as_millis() / LOADING_FRAME_MS as u128) as u32 % LOADING_SPINNER_DOTS
+        } else {
+            0
+        };
+        let spinner_y = center_y - LOADING_SPINNER_RADIUS;
+        for i in 0..LOADING_SPINNER_DOTS {
+            let angle = (i as f64 / LOADING_SPINNER_DOTS as f64) * std::f64::consts::TAU;
+            let dot_x = center_x + (angle.sin() * LOADING_SPINNER_RADIUS as f64).round() as i32;
+            let dot_y = spinner_y - (angle.cos() * LOADING_SPINNER_RADIUS as f64).round() as i32;
+
+            let distance = (head + LOADING_SPINNER_DOTS - i) % LOADING_SPINNER_DOTS;
+            let intensity = 255 - (distance * 255 / LOADING_SPINNER_DOTS);
+            let color = blend_rgb(background_color, 0xFFFFFF, intensity.max(48));
+
+            let brush = CreateSolidBrush(COLORREF(rgb_to_colorref(color)));
+            let pen = CreatePen(PS_SOLID, 1, COLORREF(rgb_to_colorref(color)));
+            let old_brush = SelectObject(hdc, HGDIOBJ(brush.0));
+            let old_pen = SelectObject(hdc, HGDIOBJ(pen.0));
+            let half = LOADING_SPINNER_DOT_SIZE / 2;
+            Ellipse(hdc, dot_x - half, dot_y - half, dot_x + half, dot_y + half);
+            SelectObject(hdc, old_pen);
+            SelectObject(hdc, old_brush);
+            DeleteObject(HGDIOBJ(pen.0));
+            DeleteObject(HGDIOBJ(brush.0));
+        }
+
+        // Label with elapsed time, centered below the spinner
+        SetBkMode(hdc, TRANSPARENT);
+        SetTextColor(hdc, COLORREF(rgb_to_colorref(0xC0C0C0)));
+
+        let font = fonts::ui_font(TextSize::Large, 400);
+        let old_font = SelectObject(hdc, HGDIOBJ(font.0));
+
+        let text_wide: Vec<u16> = loading_indicator_text(elapsed, progress)
+            .encode_utf16()
+            .collect();
+        let mut text_size = SIZE::default();
+        GetTextExtentPoint32W(hdc, &text_wide, &mut text_size);
+        TextOutW(
+            hdc,
+            center_x - text_size.cx / 2,
+            center_y + LOADING_SPINNER_RADIUS,
+            &text_wide,
+        );
+
+        // Outlined "Cancel" button that drops the tabs still waiting to be launched
+        if can_cancel {
+            let cancel_rect = get_startup_cancel_rect(client_rect);
+            let outline_pen = CreatePen(PS_SOLID, 1, COLORREF(rgb_to_colorref(TAB_OUTLINE_COLOR)));
+            let old_pen = SelectObject(hdc, HGDIOBJ(outline_pen.0));
+            MoveToEx(hdc, cancel_rect.left, cancel_rect.top, None);
+            LineTo(hdc, cancel_rect.right - 1, cancel_rect.top);
+            LineTo(hdc, cancel_rect.right - 1, cancel_rect.bottom - 1);
+            LineTo(hdc, cancel_rect.left, cancel_rect.bottom - 1);
+            LineTo(hdc, cancel_rect.left, cancel_rect.top);
+            SelectObject(hdc, old_pen);
+            DeleteObject(HGDIOBJ(outline_pen.0));
+
+            let cancel_wide: Vec<u16> = "Cancel".encode_utf16().collect();
+            let mut cancel_size = SIZE::default();
+            GetTextExtentPoint32W(hdc, &cancel_wide, &mut cancel_size);
+            TextOutW(
+                hdc,
+                (cancel_rect.left + cancel_rect.right - cancel_size.cx) / 2,
+                (cancel_rect.top + cancel_rect.bottom - cancel_size.cy) / 2,
+                &cancel_wide,
+            );
+        }
+
+        SelectObject(hdc, old_font);
+    }
+}
+
+/// Paint the active session's name right-aligned in its widget's `rect`, cut off
+/// with an ellipsis if it is too long
+#[allow(unused_must_use)]
+fn paint_session_name(hdc: windows::Win32::Graphics::Gdi::HDC, rect: &RECT, name: &str) {
+    unsafe {
+        let text_wide: Vec<u16> = name.encode_utf16().collect();
+        let old_font = fonts::select_font_for_text(hdc, TextSize::Body, 400, &text_wide);
+        let mut text_size = SIZE::default();
+        GetTextExtentPoint32W(hdc, &text_wide, &mut text_size);
+        let width = rect.right - rect.left;
+        let y = (rect.top + rect.bottom - text_size.cy) / 2;
+        SetBkMode(hdc, TRANSPARENT);
+        SetTextColor(hdc, COLORREF(rgb_to_colorref(WIDGET_TEXT_COLOR)));
+        if text_size.cx <= width {
+            TextOutW(hdc, rect.right - text_size.cx, y, &text_wide);
+        } else {
+            text_out_truncated(hdc, rect.left, y, name, width);
+        }
+
+        SelectObject(hdc, old_font);
+    }
+}
+
+/// Paint the current time centered in the clock widget's `rect`
+#[allow(unused_must_use)]
+fn paint_clock(hdc: windows::Win32::Graphics::Gdi::HDC, rect: &RECT, time: &str) {
+    unsafe {
+        let text_wide: Vec<u16> = time.encode_utf16().collect();
+        let old_font = fonts::select_font_for_text(hdc, TextSize::Body, 400, &text_wide);
+        let mut text_size = SIZE::default();
+        GetTextExtentPoint32W(hdc, &text_wide, &mut text_size);
+        SetBkMode(hdc, TRANSPARENT);
+        SetTextColor(hdc, COLORREF(rgb_to_colorref(WIDGET_TEXT_COLOR)));
+        text_out_truncated(
+            hdc,
+            rect.left + ((rect.right - rect.left - text_size.cx) / 2).max(0),
+            (rect.top + rect.bottom - text_size.cy) / 2,
+            time,
+            rect.right - rect.left,
+        );
+        SelectObject(hdc, old_font);
+    }
+}
+
+/// Paint the settings button (a gear) like the new tab button
+#[allow(unused_must_use)]
+fn paint_settings_button(hdc: windows::Win32::Graphics::Gdi::HDC, rect: &RECT, is_hovered: bool) {
+    unsafe {
+        // Background on hover
+        if is_hovered {
+            let hover_brush = CreateSolidBrush(COLORREF(rgb_to_colorref(TAB_HOVER_COLOR)));
+            FillRect(hdc, rect, hover_brush);
+            DeleteObject(HGDIOBJ(hover_brush.0));
+        }
+
+        let pen = CreatePen(PS_SOLID, 2, COLORREF(0x00FFFFFF));
+        let old_pen = SelectObject(hdc, HGDIOBJ(pen.0));
+        let old_brush = SelectObject(hdc, GetStockObject(NULL_BRUSH));
+
+        let cx = (rect.left + rect.right) / 2;
+        let cy = (rect.top + rect.bottom) / 2;
+
+        // Eight teeth around a ring
+        for tooth in 0..8 {
+            let angle = tooth as f64 * std::f64::consts::FRAC_PI_4;
+            let (sin, cos) = angle.sin_cos();
+            MoveToEx(
+                hdc,
+                cx + (cos * 5.0).round() as i32,
+                cy + (sin * 5.0).round() as i32,
+                None,
+            );
+            LineTo(
+                hdc,
+                cx + (cos * 7.5).round() as i32,
+                cy + (sin * 7.5).round() as i32,
+            );
+        }
+        Ellipse(hdc, cx - 5, cy - 5, cx + 6, cy + 6);
+
+        SelectObject(hdc, old_brush);
+        SelectObject(hdc, old_pen);
+        DeleteObject(HGDIOBJ(pen.0));
+    }
+}
+
+/// Paint the high memory banner in its widget's `slot`: an outlined pill like the
+/// update badge with the memory in use and an offer to hibernate tabs, which
+/// fills on hover, and a dismiss button at its right end
+#[allow(unused_must_use)]
+fn paint_memory_banner(
+    hdc: windows::Win32::Graphics::Gdi::HDC,
+    slot: &RECT,
+    memory_text: &str,
+    hovered_tab: HoveredTab,
+) {
+    unsafe {
+        let rect = get_update_badge_rect(slot);
+        let dismiss_rect = get_memory_dismiss_rect(slot);
+        let color = COLORREF(rgb_to_colorref(MEMORY_BANNER_COLOR));
+        let hover_color = blend_rgb(TAB_UNSELECTED_COLOR, MEMORY_BANNER_COLOR, 60);
+        let pen = CreatePen(PS_SOLID, 1, color);
+        let old_pen = SelectObject(hdc, HGDIOBJ(pen.0));
+        let brush = if hovered_tab == HoveredTab::MemoryBanner {
+            CreateSolidBrush(COLORREF(rgb_to_colorref(hover_color)))
+        } else {
+            CreateSolidBrush(COLORREF(rgb_to_colorref(TAB_UNSELECTED_COLOR)))
+        };
+        let old_brush = SelectObject(hdc, HGDIOBJ(brush.0));
+        let height = rect.bottom - rect.top;
+        RoundRect(
+            hdc,
+            rect.left,
+            rect.top,
+            rect.right,
+            rect.bottom,
+            height,
+            height,
+        );
+        SelectObject(hdc, old_brush);
+        DeleteObject(HGDIOBJ(brush.0));
+
+        // Dismiss button: a circle behind the cross on hover
+        if hovered_tab == HoveredTab::MemoryDismiss {
+            let hover_brush = CreateSolidBrush(COLORREF(rgb_to_colorref(hover_color)));
+            let old_brush = SelectObject(hdc, HGDIOBJ(hover_brush.0));
+            Ellipse(
+                hdc,
+                dismiss_rect.left + 2,
+                dismiss_rect.top + 2,
+                dismiss_rect.right - 2,
+                dismiss_rect.bottom - 2,
+            );
+            SelectObject(hdc, old_brush);
+            DeleteObject(HGDIOBJ(hover_brush.0));
+        }
+        let cx = (dismiss_rect.left + dismiss_rect.right) / 2 - 1;
+        let cy = (dismiss_rect.top + dismiss_rect.bottom) / 2;
+        MoveToEx(hdc, cx - 3, cy - 3, None);
+        LineTo(hdc, cx + 4, cy + 4);
+        MoveToEx(hdc, cx + 3, cy - 3, None);
+        LineTo(hdc, cx - 4, cy + 4);
+        SelectObject(hdc, old_pen);
+        DeleteObject(HGDIOBJ(pen.0));
+
+        let label = format!("{} in use \u{00b7} Hibernate", memory_text);
+        let text_wide: Vec<u16> = label.encode_utf16().collect();
+        let old_font = fonts::select_font_for_text(hdc, TextSize::Caption, 600, &text_wide);
+        let mut text_size = SIZE::default();
+        GetTextExtentPoint32W(hdc, &text_wide, &mut text_size);
+        let text_width = dismiss_rect.left - rect.left;
+        SetBkMode(hdc, TRANSPARENT);
+        SetTextColor(hdc, color);
+        text_out_truncated(
+            hdc,
+            rect.left + ((text_width - text_size.cx) / 2).max(height / 2),
+            rect.top + (height - text_size.cy) / 2,
+            &label,
+            text_width - height / 2,
+        );
+        SelectObject(hdc, old_font);
+    }
+}
 
-                        // Calculate available width for text (leave room for close button)
-                        let max_text_width = close_rect.left - text_x - 4;
-                        let label_wide: Vec<u16> = tab_info.label.encode_utf16().collect();
-                        let mut text_size = SIZE::default();
-                        GetTextExtentPoint32W(hdc, &label_wide, &mut text_size);
+/// Hibernate the least recently used background tabs until the tabs use less
+/// memory than the high memory banner allows, and hide the banner. Beeps when
+/// there is no tab to hibernate.
+#[allow(unused_must_use)]
+unsafe fn hibernate_tabs(hwnd: HWND, state: &mut WindowState) {
+    let to_free = state
+        .memory_warning
+        .excess(state.tab_manager.total_memory());
+    if state
+        .tab_manager
+        .hibernate_least_recently_used(to_free.max(1))
+        == 0
+    {
+        windows::Win32::System::Diagnostics::Debug::MessageBeep(MB_ICONWARNING).ok();
+        return;
+    }
+    state.memory_warning.dismiss();
+    update_widgets(hwnd, state);
+    InvalidateRect(hwnd, None, false);
+}
 
-                        if text_size.cx <= max_text_width {
-                            TextOutW(hdc, text_x, text_y, &label_wide);
-                        } else {
-                            // Truncate with ellipsis
-                            let ellipsis = "...";
-                            let ellipsis_wide: Vec<u16> = ellipsis.encode_utf16().collect();
-                            let mut ellipsis_size = SIZE::default();
-                            GetTextExtentPoint32W(hdc, &ellipsis_wide, &mut ellipsis_size);
-
-                            let available_for_text = max_text_width - ellipsis_size.cx;
-                            if available_for_text > 0 {
-                                let mut truncated = String::new();
-                                for ch in tab_info.label.chars() {
-                                    let test = format!("{}{}", truncated, ch);
-                                    let test_wide: Vec<u16> = test.encode_utf16().collect();
-                                    let mut test_size = SIZE::default();
-                                    GetTextExtentPoint32W(hdc, &test_wide, &mut test_size);
-                                    if test_size.cx > available_for_text {
-                                        break;
-                                    }
-                                    truncated.push(ch);
-                                }
-                                truncated.push_str(ellipsis);
-                                let truncated_wide: Vec<u16> = truncated.encode_utf16().collect();
-                                TextOutW(hdc, text_x, text_y, &truncated_wide);
-                            } else {
-                                TextOutW(hdc, text_x, text_y, &ellipsis_wide);
-                            }
-                        }
+/// Paint the "Update available" badge: an outlined pill that fills on hover
+#[allow(unused_must_use)]
+fn paint_update_badge(hdc: windows::Win32::Graphics::Gdi::HDC, rect: &RECT, is_hovered: bool) {
+    unsafe {
+        let color = COLORREF(rgb_to_colorref(UPDATE_BADGE_COLOR));
+        let pen = CreatePen(PS_SOLID, 1, color);
+        let old_pen = SelectObject(hdc, HGDIOBJ(pen.0));
+        let brush = if is_hovered {
+            CreateSolidBrush(COLORREF(rgb_to_colorref(blend_rgb(
+                TAB_UNSELECTED_COLOR,
+                UPDATE_BADGE_COLOR,
+                60,
+            ))))
+        } else {
+            CreateSolidBrush(COLORREF(rgb_to_colorref(TAB_UNSELECTED_COLOR)))
+        };
+        let old_brush = SelectObject(hdc, HGDIOBJ(brush.0));
+        let height = rect.bottom - rect.top;
+        RoundRect(
+            hdc,
+            rect.left,
+            rect.top,
+            rect.right,
+            rect.bottom,
+            height,
+            height,
+        );
+        SelectObject(hdc, old_brush);
+        DeleteObject(HGDIOBJ(brush.0));
+        SelectObject(hdc, old_pen);
+        DeleteObject(HGDIOBJ(pen.0));
 
-                        SelectObject(hdc, old_font);
-                        DeleteObject(HGDIOBJ(font.0));
+        let text_wide: Vec<u16> = UPDATE_BADGE_LABEL.encode_utf16().collect();
+        let old_font = fonts::select_font_for_text(hdc, TextSize::Caption, 600, &text_wide);
+        let mut text_size = SIZE::default();
+        GetTextExtentPoint32W(hdc, &text_wide, &mut text_size);
+        SetBkMode(hdc, TRANSPARENT);
+        SetTextColor(hdc, color);
+        TextOutW(
+            hdc,
+            rect.left + (rect.right - rect.left - text_size.cx) / 2,
+            rect.top + (height - text_size.cy) / 2,
+            &text_wide,
+        );
+        SelectObject(hdc, old_font);
+    }
+}
 
-                        // Draw close button
-                        // Close button background on hover
-                        if state.hovered_close == Some(i) {
-                            let close_hover_brush =
-                                CreateSolidBrush(COLORREF(rgb_to_colorref(TAB_CLOSE_HOVER_COLOR)));
-                            FillRect(hdc, &close_rect, close_hover_brush);
-                            DeleteObject(HGDIOBJ(close_hover_brush.0));
-                        }
+/// Paint the debug HUD's lines in a box right-aligned before the window buttons
+#[allow(unused_must_use)]
+fn paint_debug_hud(hdc: windows::Win32::Graphics::Gdi::HDC, client_width: i32, lines: &[String]) {
+    unsafe {
+        let texts: Vec<Vec<u16>> = lines
+            .iter()
+            .map(|line| line.encode_utf16().collect())
+            .collect();
+        let old_font = SelectObject(hdc, HGDIOBJ(fonts::ui_font(TextSize::Caption, 400).0));
+        let line_height = current_text_height(hdc);
+        let text_width = texts
+            .iter()
+            .map(|text| {
+                let mut size = SIZE::default();
+                GetTextExtentPoint32W(hdc, text, &mut size);
+                size.cx
+            })
+            .max()
+            .unwrap_or(0);
+
+        let right = get_tab_bar_max_x(client_width);
+        let rect = RECT {
+            left: (right - text_width - DEBUG_HUD_PADDING * 2).max(0),
+            top: 0,
+            right,
+            bottom: titlebar_height(),
+        };
+        let background = CreateSolidBrush(COLORREF(rgb_to_colorref(DEBUG_HUD_BACKGROUND)));
+        FillRect(hdc, &rect, background);
+        DeleteObject(HGDIOBJ(background.0));
+
+        SetBkMode(hdc, TRANSPARENT);
+        SetTextColor(hdc, COLORREF(rgb_to_colorref(DEBUG_HUD_TEXT_COLOR)));
+        let top = (titlebar_height() - line_height * texts.len() as i32) / 2;
+        for (i, text) in texts.iter().enumerate() {
+            TextOutW(
+                hdc,
+                rect.left + DEBUG_HUD_PADDING,
+                top + line_height * i as i32,
+                text,
+            );
+        }
+        SelectObject(hdc, old_font);
+    }
+}
 
-                        // Draw X for close button
-                        let close_pen = CreatePen(PS_SOLID, 1, COLORREF(0x00FFFFFF));
-                        let old_pen = SelectObject(hdc, HGDIOBJ(close_pen.0));
+/// Paint the title bar content to a device context
+#[allow(unused_must_use, clippy::too_many_arguments)]
+fn paint_titlebar_content(
+    hwnd: HWND,
+    hdc: windows::Win32::Graphics::Gdi::HDC,
+    client_rect: &RECT,
+    background_color: u32,
+    hovered_button: HoveredButton,
+    hovered_tab: HoveredTab,
+    tab_manager: &TabManager<NeovideProcess>,
+    dropdown_state: DropdownState,
+    profiles: &[Profile],
+    session_name: Option<&str>,
+    clock_text: &str,
+    memory_text: &str,
+    debug_hud: Option<&[String]>,
+    focus_rect: Option<RECT>,
+    tab_numbers: Option<&[Option<String>]>,
+) {
+    unsafe {
+        let client_width = client_rect.right;
 
-                        let cx = (close_rect.left + close_rect.right) / 2;
-                        let cy = (close_rect.top + close_rect.bottom) / 2;
-                        let x_size = 4;
+        // Fill entire client area with background color
+        let bg_colorref = COLORREF(rgb_to_colorref(background_color));
+        let bg_brush = CreateSolidBrush(bg_colorref);
+        FillRect(hdc, client_rect, bg_brush);
+        DeleteObject(HGDIOBJ(bg_brush.0));
 
-                        MoveToEx(hdc, cx - x_size, cy - x_size, None);
-                        LineTo(hdc, cx + x_size + 1, cy + x_size + 1);
-                        MoveToEx(hdc, cx + x_size, cy - x_size, None);
-                        LineTo(hdc, cx - x_size - 1, cy + x_size + 1);
+        // Paint tab bar
+        paint_tab_bar(
+            hdc,
+            tab_manager,
+            hovered_tab,
+            client_width,
+            background_color,
+            tab_numbers,
+        );
 
-                        SelectObject(hdc, old_pen);
-                        DeleteObject(HGDIOBJ(close_pen.0));
+        // Paint the widgets between the tab bar and the window buttons
+        for (widget, rect) in get_widget_rects(client_width) {
+            let rect: RECT = rect.into();
+            match widget {
+                Widget::Session => {
+                    if let Some(name) = session_name {
+                        paint_session_name(hdc, &rect, name);
                     }
                 }
-
-                EndPaint(hwnd, &ps);
-                LRESULT(0)
+                Widget::Memory => paint_memory_banner(hdc, &rect, memory_text, hovered_tab),
+                Widget::Update => paint_update_badge(
+                    hdc,
+                    &get_update_badge_rect(&rect),
+                    hovered_tab == HoveredTab::UpdateBadge,
+                ),
+                Widget::Clock => paint_clock(hdc, &rect, clock_text),
+                Widget::Settings => {
+                    paint_settings_button(hdc, &rect, hovered_tab == HoveredTab::SettingsButton)
+                }
             }
+        }
 
-            WM_MOUSEMOVE => {
-                let x = (lparam.0 & 0xFFFF) as i16 as i32;
-                let y = ((lparam.0 >> 16) & 0xFFFF) as i16 as i32;
+        // Show batch startup progress while several tabs start together
+        let startup_progress = tab_manager.startup_progress();
+        if let Some((ready, total)) = startup_progress {
+            paint_startup_progress(hdc, client_width, ready, total);
+        }
 
-                let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut OverflowPopupState;
-                if !state_ptr.is_null() {
-                    let state = &mut *state_ptr;
+        // Show the startup indicator until the selected tab's Neovide window is ready
+        if let Some(elapsed) = tab_manager.selected_startup_elapsed() {
+            paint_loading_indicator(
+                hdc,
+                client_rect,
+                background_color,
+                elapsed,
+                startup_progress,
+                tab_manager.has_queued_spawns(),
+                !tab_manager.reduce_motion(),
+            );
+        }
 
-                    // Get popup rect for calculating close button positions
-                    let mut rect = RECT::default();
-                    GetClientRect(hwnd, &mut rect).ok();
+        // Explain why the selected error tab has no Neovide window
+        if let Some(error) = tab_manager.get_tab_spawn_error(tab_manager.selected_index()) {
+            paint_spawn_error(hdc, client_rect, error);
+        }
 
-                    // Calculate which item is hovered
-                    let item_index = (y - DROPDOWN_PADDING) / DROPDOWN_ITEM_HEIGHT;
-                    let new_hovered = if item_index >= 0 && (item_index as usize) < state.tabs.len()
-                    {
-                        Some(item_index as usize)
-                    } else {
-                        None
-                    };
+        // Minimize, maximize and close buttons
+        if layout::traffic_lights() {
+            paint_traffic_lights(hdc, client_width, hovered_button);
+        } else {
+            paint_window_buttons(hdc, hwnd, client_width, hovered_button);
+        }
 
-                    // Check if close button is hovered
-                    let new_hovered_close = if let Some(idx) = new_hovered {
-                        let item_top = DROPDOWN_PADDING + (idx as i32 * DROPDOWN_ITEM_HEIGHT);
-                        let item_right = rect.right - DROPDOWN_PADDING;
-                        let close_size = TAB_CLOSE_SIZE;
-                        let close_left = item_right - TAB_CLOSE_PADDING - close_size;
-                        let close_top = (item_top + item_top + DROPDOWN_ITEM_HEIGHT - close_size) / 2;
-                        let close_right = close_left + close_size;
-                        let close_bottom = close_top + close_size;
-
-                        if x >= close_left && x < close_right && y >= close_top && y < close_bottom {
-                            Some(idx)
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    };
+        // Debug HUD on top of the tab bar
+        if let Some(lines) = debug_hud {
+            paint_debug_hud(hdc, client_width, lines);
+        }
+
+        // Keyboard focus ring (two pixels wide)
+        if let Some(rect) = focus_rect {
+            let ring_brush = CreateSolidBrush(COLORREF(rgb_to_colorref(FOCUS_RING_COLOR)));
+            let inner = RECT {
+                left: rect.left + 1,
+                top: rect.top + 1,
+                right: rect.right - 1,
+                bottom: rect.bottom - 1,
+            };
+            FrameRect(hdc, &rect, ring_brush);
+            FrameRect(hdc, &inner, ring_brush);
+            let _ = DeleteObject(HGDIOBJ(ring_brush.0));
+        }
+
+        // Note: Dropdown menu is now rendered as a separate popup window,
+        // so we don't paint it here anymore.
+        let _ = dropdown_state; // Silence unused warning
+        let _ = profiles; // Silence unused warning
+    }
+}
 
-                    let needs_repaint = state.hovered_item != new_hovered
-                        || state.hovered_close != new_hovered_close;
+/// Paint the minimize, maximize and close buttons in the Windows style: plain
+/// glyphs, with the hovered button highlighted (red for close)
+#[allow(unused_must_use)]
+unsafe fn paint_window_buttons(
+    hdc: windows::Win32::Graphics::Gdi::HDC,
+    hwnd: HWND,
+    client_width: i32,
+    hovered_button: HoveredButton,
+) {
+    // Get button rectangles
+    let (minimize_rect, maximize_rect, close_rect) = get_button_rects(client_width);
 
-                    state.hovered_item = new_hovered;
-                    state.hovered_close = new_hovered_close;
+    // Draw button backgrounds for hover states
+    let hover_brush = CreateSolidBrush(COLORREF(rgb_to_colorref(0x3d3d3d)));
+    let close_hover_brush = CreateSolidBrush(COLORREF(rgb_to_colorref(0xe81123))); // Red for close
 
-                    if needs_repaint {
-                        InvalidateRect(hwnd, None, false);
-                    }
+    match hovered_button {
+        HoveredButton::Minimize => {
+            FillRect(hdc, &minimize_rect, hover_brush);
+        }
+        HoveredButton::Maximize => {
+            FillRect(hdc, &maximize_rect, hover_brush);
+        }
+        HoveredButton::Close => {
+            FillRect(hdc, &close_rect, close_hover_brush);
+        }
+        HoveredButton::None => {}
+    }
+
+    DeleteObject(HGDIOBJ(hover_brush.0));
+    DeleteObject(HGDIOBJ(close_hover_brush.0));
+
+    // Draw button icons using simple lines (white color), leaving out hidden buttons
+    let pen = CreatePen(PS_SOLID, 1, COLORREF(0x00FFFFFF));
+    let old_pen = SelectObject(hdc, HGDIOBJ(pen.0));
+
+    // Minimize button: horizontal line
+    if minimize_rect.right > minimize_rect.left {
+        let min_cx = (minimize_rect.left + minimize_rect.right) / 2;
+        let min_cy = (minimize_rect.top + minimize_rect.bottom) / 2;
+        MoveToEx(hdc, min_cx - 5, min_cy, None);
+        LineTo(hdc, min_cx + 6, min_cy);
+    }
+
+    // Maximize/Restore button
+    if maximize_rect.right > maximize_rect.left {
+        let max_cx = (maximize_rect.left + maximize_rect.right) / 2;
+        let max_cy = (maximize_rect.top + maximize_rect.bottom) / 2;
+
+        let is_maximized = IsZoomed(hwnd).as_bool();
+        if is_maximized {
+            // Draw restore icon (two overlapping rectangles)
+            // Back rectangle (smaller, offset up-right)
+            MoveToEx(hdc, max_cx - 3, max_cy - 5, None);
+            LineTo(hdc, max_cx + 5, max_cy - 5);
+            MoveToEx(hdc, max_cx + 5, max_cy - 5, None);
+            LineTo(hdc, max_cx + 5, max_cy - 2);
+            // Front rectangle
+            MoveToEx(hdc, max_cx - 5, max_cy - 2, None);
+            LineTo(hdc, max_cx + 3, max_cy - 2);
+            LineTo(hdc, max_cx + 3, max_cy + 6);
+            LineTo(hdc, max_cx - 5, max_cy + 6);
+            LineTo(hdc, max_cx - 5, max_cy - 2);
+        } else {
+            // Draw maximize icon (single rectangle)
+            MoveToEx(hdc, max_cx - 5, max_cy - 5, None);
+            LineTo(hdc, max_cx + 5, max_cy - 5);
+            LineTo(hdc, max_cx + 5, max_cy + 5);
+            LineTo(hdc, max_cx - 5, max_cy + 5);
+            LineTo(hdc, max_cx - 5, max_cy - 5);
+        }
+    }
+
+    // Close button: X
+    if close_rect.right > close_rect.left {
+        let close_cx = (close_rect.left + close_rect.right) / 2;
+        let close_cy = (close_rect.top + close_rect.bottom) / 2;
+        let _ = MoveToEx(hdc, close_cx - 5, close_cy - 5, None);
+        let _ = LineTo(hdc, close_cx + 6, close_cy + 6);
+        let _ = MoveToEx(hdc, close_cx + 5, close_cy - 5, None);
+        let _ = LineTo(hdc, close_cx - 6, close_cy + 6);
+    }
+
+    let _ = SelectObject(hdc, old_pen);
+    let _ = DeleteObject(HGDIOBJ(pen.0));
+}
+
+/// Paint the window buttons as macOS-style traffic lights: colored dots that show
+/// their symbols while the pointer is over any of them
+#[allow(unused_must_use)]
+unsafe fn paint_traffic_lights(
+    hdc: windows::Win32::Graphics::Gdi::HDC,
+    client_width: i32,
+    hovered_button: HoveredButton,
+) {
+    let (minimize_rect, maximize_rect, close_rect) = get_button_rects(client_width);
+    let show_symbols = hovered_button != HoveredButton::None;
+    let symbol_pen = CreatePen(
+        PS_SOLID,
+        1,
+        COLORREF(rgb_to_colorref(TRAFFIC_LIGHT_SYMBOL_COLOR)),
+    );
+    let lights = [
+        (close_rect, TRAFFIC_LIGHT_CLOSE_COLOR, HoveredButton::Close),
+        (
+            minimize_rect,
+            TRAFFIC_LIGHT_MINIMIZE_COLOR,
+            HoveredButton::Minimize,
+        ),
+        (
+            maximize_rect,
+            TRAFFIC_LIGHT_MAXIMIZE_COLOR,
+            HoveredButton::Maximize,
+        ),
+    ];
+    for (rect, color, button) in lights {
+        if rect.right == rect.left {
+            continue;
+        }
+        let cx = (rect.left + rect.right) / 2;
+        let cy = (rect.top + rect.bottom) / 2;
+        let half = TRAFFIC_LIGHT_SIZE / 2;
+
+        let color = COLORREF(rgb_to_colorref(color));
+        let pen = CreatePen(PS_SOLID, 1, color);
+        let old_pen = SelectObject(hdc, HGDIOBJ(pen.0));
+        let brush = CreateSolidBrush(color);
+        let old_brush = SelectObject(hdc, HGDIOBJ(brush.0));
+        Ellipse(hdc, cx - half, cy - half, cx + half, cy + half);
+        SelectObject(hdc, old_brush);
+        DeleteObject(HGDIOBJ(brush.0));
+
+        if show_symbols {
+            SelectObject(hdc, HGDIOBJ(symbol_pen.0));
+            match button {
+                HoveredButton::Close => {
+                    MoveToEx(hdc, cx - 3, cy - 3, None);
+                    LineTo(hdc, cx + 4, cy + 4);
+                    MoveToEx(hdc, cx + 3, cy - 3, None);
+                    LineTo(hdc, cx - 4, cy + 4);
+                }
+                HoveredButton::Minimize => {
+                    MoveToEx(hdc, cx - 3, cy, None);
+                    LineTo(hdc, cx + 4, cy);
+                }
+                _ => {
+                    MoveToEx(hdc, cx - 3, cy, None);
+                    LineTo(hdc, cx + 4, cy);
+                    MoveToEx(hdc, cx, cy - 3, None);
+                    LineTo(hdc, cx, cy + 4);
                 }
-                LRESULT(0)
             }
+        }
+        SelectObject(hdc, old_pen);
+        DeleteObject(HGDIOBJ(pen.0));
+    }
+    DeleteObject(HGDIOBJ(symbol_pen.0));
+}
+
+/// Paint the title bar using double-buffering to prevent flicker
+#[allow(unused_must_use, clippy::too_many_arguments)]
+fn paint_titlebar(
+    hwnd: HWND,
+    ps: &PAINTSTRUCT,
+    background_color: u32,
+    hovered_button: HoveredButton,
+    hovered_tab: HoveredTab,
+    tab_manager: &TabManager<NeovideProcess>,
+    dropdown_state: DropdownState,
+    profiles: &[Profile],
+    session_name: Option<&str>,
+    clock_text: &str,
+    memory_text: &str,
+    debug_hud: Option<&[String]>,
+    focus_rect: Option<RECT>,
+    tab_numbers: Option<&[Option<String>]>,
+) {
+    unsafe {
+        let hdc = ps.hdc;
 
-            WM_LBUTTONDOWN => {
-                let x = (lparam.0 & 0xFFFF) as i16 as i32;
-                let y = ((lparam.0 >> 16) & 0xFFFF) as i16 as i32;
+        // Get client rect
+        let mut client_rect = RECT::default();
+        if GetClientRect(hwnd, &mut client_rect).is_err() {
+            return;
+        }
 
-                let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut OverflowPopupState;
-                if !state_ptr.is_null() {
-                    let state = &*state_ptr;
+        let width = client_rect.right - client_rect.left;
+        let height = client_rect.bottom - client_rect.top;
 
-                    // Check if click is inside the popup
-                    let mut rect = RECT::default();
-                    GetClientRect(hwnd, &mut rect).ok();
+        // Create off-screen buffer for double-buffering
+        let mem_dc = CreateCompatibleDC(hdc);
+        let mem_bitmap = CreateCompatibleBitmap(hdc, width, height);
+        let old_bitmap = SelectObject(mem_dc, HGDIOBJ(mem_bitmap.0));
 
-                    if x >= rect.left && x < rect.right && y >= rect.top && y < rect.bottom {
-                        // Click inside - check which item
-                        let item_index = (y - DROPDOWN_PADDING) / DROPDOWN_ITEM_HEIGHT;
-                        if item_index >= 0 && (item_index as usize) < state.tabs.len() {
-                            let idx = item_index as usize;
-                            let tab_index = state.tabs[idx].index;
+        // Paint everything to the off-screen buffer
+        paint_titlebar_content(
+            hwnd,
+            mem_dc,
+            &client_rect,
+            background_color,
+            hovered_button,
+            hovered_tab,
+            tab_manager,
+            dropdown_state,
+            profiles,
+            session_name,
+            clock_text,
+            memory_text,
+            debug_hud,
+            focus_rect,
+            tab_numbers,
+        );
 
-                            // Check if close button was clicked
-                            let item_top = DROPDOWN_PADDING + (idx as i32 * DROPDOWN_ITEM_HEIGHT);
-                            let item_right = rect.right - DROPDOWN_PADDING;
-                            let close_size = TAB_CLOSE_SIZE;
-                            let close_left = item_right - TAB_CLOSE_PADDING - close_size;
-                            let close_top =
-                                (item_top + item_top + DROPDOWN_ITEM_HEIGHT - close_size) / 2;
-                            let close_right = close_left + close_size;
-                            let close_bottom = close_top + close_size;
+        // Copy the off-screen buffer to the screen in one operation
+        BitBlt(hdc, 0, 0, width, height, mem_dc, 0, 0, SRCCOPY);
 
-                            if x >= close_left
-                                && x < close_right
-                                && y >= close_top
-                                && y < close_bottom
-                            {
-                                // Close button clicked - send close message
-                                PostMessageW(
-                                    state.parent_hwnd,
-                                    WM_APP + 4, // Message for overflow tab close
-                                    WPARAM(tab_index),
-                                    LPARAM(0),
-                                )
-                                .ok();
-                            } else {
-                                // Tab item clicked - send selection message
-                                PostMessageW(
-                                    state.parent_hwnd,
-                                    WM_APP + 2, // Message for overflow tab selection
-                                    WPARAM(tab_index),
-                                    LPARAM(0),
-                                )
-                                .ok();
-                            }
-                        }
-                    } else {
-                        // Click outside - just notify parent to close
-                        PostMessageW(state.parent_hwnd, WM_APP + 3, WPARAM(0), LPARAM(0)).ok();
-                    }
-                    // Release capture and close popup
-                    ReleaseCapture().ok();
-                    DestroyWindow(hwnd).ok();
-                }
-                LRESULT(0)
+        // Clean up
+        SelectObject(mem_dc, old_bitmap);
+        DeleteObject(HGDIOBJ(mem_bitmap.0));
+        DeleteDC(mem_dc);
+    }
+}
+
+/// Open a new tab for a profile, optionally in another working directory. With
+/// `reuse_existing_tab`, a tab with the same profile and directory is selected instead.
+/// At `max_tabs` or the profile's `max_instances`, a tab of the profile is selected
+/// (the selected tab if it is one); without one, the wrapper only beeps.
+#[allow(unused_must_use)]
+unsafe fn open_profile_tab(
+    hwnd: HWND,
+    state: &mut WindowState,
+    profile: &Profile,
+    profile_index: usize,
+    directory: Option<&std::path::Path>,
+) {
+    let directory = directory.unwrap_or(&profile.working_directory);
+    if state.config.reuse_existing_tab {
+        if let Some(existing_tab) = state
+            .tab_manager
+            .find_tab_by_directory(profile_index, directory)
+        {
+            if state.tab_manager.select_tab(existing_tab) {
+                state.tab_manager.activate_selected(hwnd, content_top());
+                InvalidateRect(hwnd, None, false);
+            } else {
+                state
+                    .tab_manager
+                    .activate_and_foreground_selected(hwnd, content_top());
+            }
+            return;
+        }
+    }
+
+    if state.tab_manager.tab_limit_reached(
+        profile_index,
+        state.config.max_tabs,
+        profile.max_instances,
+    ) {
+        let selected = state.tab_manager.selected_index();
+        let existing_tab =
+            if state.tab_manager.get_tab_profile_index(selected) == Some(profile_index) {
+                Some(selected)
+            } else {
+                state.tab_manager.find_tab_by_profile_index(profile_index)
+            };
+        match existing_tab {
+            Some(index) => select_cycled_tab(hwnd, state, index),
+            None => {
+                windows::Win32::System::Diagnostics::Debug::MessageBeep(MB_ICONWARNING).ok();
+            }
+        }
+        return;
+    }
+
+    create_profile_tab(hwnd, state, profile, profile_index, directory, &[]);
+}
+
+/// Open a folder or file handed over by another neovide-tabs process. A folder
+/// gets a tab like a profile does; a file (or folder) is edited in the tab whose
+/// working directory contains it if its nvim can be reached, or else in a new tab
+/// in its project root.
+#[allow(unused_must_use)]
+unsafe fn handle_open_request(hwnd: HWND, state: &mut WindowState, request: &OpenRequest) {
+    let (profile_index, profile) = request_profile(&state.config, request);
+    let profile = profile.clone();
+    let path = match request {
+        OpenRequest::Folder(dir) => {
+            open_profile_tab(hwnd, state, &profile, profile_index, Some(dir));
+            return;
+        }
+        OpenRequest::Path { path, .. } => path,
+    };
+
+    let edited = state
+        .tab_manager
+        .find_tab_containing(path, request.profile().map(|_| profile_index))
+        .filter(|&index| state.tab_manager.edit_in_tab(index, path));
+    match edited {
+        Some(index) => {
+            if state.tab_manager.select_tab(index) {
+                state.tab_manager.activate_selected(hwnd, content_top());
+                InvalidateRect(hwnd, None, false);
+            } else {
+                state
+                    .tab_manager
+                    .activate_and_foreground_selected(hwnd, content_top());
             }
+        }
+        None => {
+            let (directory, files) = request.new_tab();
+            create_profile_tab(hwnd, state, &profile, profile_index, &directory, &files);
+        }
+    }
+}
 
-            WM_CAPTURECHANGED => {
-                // We lost capture - close the popup
-                let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut OverflowPopupState;
-                if !state_ptr.is_null() {
-                    let state = &*state_ptr;
-                    PostMessageW(state.parent_hwnd, WM_APP + 3, WPARAM(0), LPARAM(0)).ok();
-                }
-                DestroyWindow(hwnd).ok();
-                LRESULT(0)
+/// Profile an open request names, or the default profile (also if the named one
+/// doesn't exist)
+fn request_profile<'a>(config: &'a Config, request: &OpenRequest) -> (usize, &'a Profile) {
+    request
+        .profile()
+        .and_then(|name| {
+            let found = config.find_profile_by_name(name);
+            if found.is_none() {
+                eprintln!(
+                    "Open: profile '{}' not found, using the default profile",
+                    name
+                );
             }
+            found
+        })
+        .unwrap_or((0, config.default_profile()))
+}
 
-            WM_DESTROY => {
-                let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut OverflowPopupState;
-                if !state_ptr.is_null() {
-                    // Free the state
-                    let _ = Box::from_raw(state_ptr);
-                    SetWindowLongPtrW(hwnd, GWLP_USERDATA, 0);
+/// Create a tab for a profile in `directory`, opening `files`, and select it.
+/// Returns the new tab's index, or None if it could not be created (error shown).
+unsafe fn create_profile_tab(
+    hwnd: HWND,
+    state: &mut WindowState,
+    profile: &Profile,
+    profile_index: usize,
+    directory: &std::path::Path,
+    files: &[std::path::PathBuf],
+) -> Option<usize> {
+    let rect = get_content_rect(hwnd).ok()?;
+    let width = (rect.right - rect.left) as u32;
+    let height = (rect.bottom - rect.top) as u32;
+
+    let created = match state.tab_manager.create_tab_in_directory(
+        width,
+        height,
+        hwnd,
+        profile,
+        profile_index,
+        directory,
+        files,
+    ) {
+        Ok(index) => {
+            // Hide other tabs immediately; the new tab is shown once its window is ready
+            for (i, tab) in state.tab_manager.iter() {
+                if i != state.tab_manager.selected_index() {
+                    tab.process.hide();
                 }
-                LRESULT(0)
             }
+            Some(index)
+        }
+        Err(e) => {
+            let error_msg = format!("Failed to create new tab: {}", e);
+            show_error(&error_msg, "Error: Failed to Create Tab");
+            None
+        }
+    };
+    let _ = InvalidateRect(hwnd, None, false);
+    created
+}
 
-            _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+/// Open another tab with the same profile and working directory right after the tab
+/// at `index` (but never among the pinned tabs)
+unsafe fn duplicate_tab(hwnd: HWND, state: &mut WindowState, index: usize) {
+    let (Some(profile_index), Some(directory)) = (
+        state.tab_manager.get_tab_profile_index(index),
+        state
+            .tab_manager
+            .get_tab_working_directory(index)
+            .map(|dir| dir.to_path_buf()),
+    ) else {
+        return;
+    };
+    // The profile may have been removed from the config since the tab was opened
+    let (profile_index, profile) = match state.config.profiles.get(profile_index) {
+        Some(profile) => (profile_index, profile.clone()),
+        None => (0, state.config.default_profile().clone()),
+    };
+    if let Some(new_index) =
+        create_profile_tab(hwnd, state, &profile, profile_index, &directory, &[])
+    {
+        let target = (index + 1).max(state.tab_manager.pinned_count());
+        state.tab_manager.move_tab(new_index, target);
+    }
+}
+
+/// Pin or unpin the tab at `index`
+fn toggle_tab_pin(hwnd: HWND, state: &mut WindowState, index: usize) {
+    if state.tab_manager.toggle_pin(index).is_some() {
+        unsafe {
+            let _ = InvalidateRect(hwnd, None, false);
         }
     }
 }
 
-/// Paint the overflow button (shows "+N" count indicator) styled like a tab
-/// When has_selected_overflow is true, also displays the selected tab's icon
-#[allow(unused_must_use)]
-fn paint_overflow_button(
-    hdc: windows::Win32::Graphics::Gdi::HDC,
-    rect: &RECT,
-    overflow_count: usize,
-    is_hovered: bool,
-    has_selected_overflow: bool,
-    selected_icon: Option<&str>,
-) {
+/// Current layout of the titlebar for UI Automation (client coordinates)
+pub(crate) fn tab_bar_snapshot(hwnd: HWND) -> Option<uia::TabBarSnapshot> {
     unsafe {
-        // Determine background color - acts like a "selected" tab if it contains the selected tab
-        let bg_color = if is_hovered {
-            TAB_HOVER_COLOR
-        } else if has_selected_overflow {
-            // When selected tab is in overflow, use unselected color (like other non-active tabs)
-            TAB_UNSELECTED_COLOR
-        } else {
-            TAB_UNSELECTED_COLOR
-        };
-
-        let bg_brush = CreateSolidBrush(COLORREF(rgb_to_colorref(bg_color)));
-        FillRect(hdc, rect, bg_brush);
-        DeleteObject(HGDIOBJ(bg_brush.0));
-
-        // Draw outline around overflow button (top, left, right - like a tab)
-        let outline_pen = CreatePen(PS_SOLID, 1, COLORREF(rgb_to_colorref(TAB_OUTLINE_COLOR)));
-        let old_pen = SelectObject(hdc, HGDIOBJ(outline_pen.0));
-
-        // If selected tab is in overflow, extend sides down to connect with bottom line
-        let side_bottom = if has_selected_overflow {
-            TITLEBAR_HEIGHT - 1
-        } else {
-            rect.bottom
-        };
+        let state = window_state(hwnd)?;
+        let tab_manager = &state.tab_manager;
+        let mut client_rect = RECT::default();
+        GetClientRect(hwnd, &mut client_rect).ok()?;
+        let client_width = client_rect.right;
+        let (visible_count, has_overflow) =
+            calculate_visible_tabs(tab_manager.count(), client_width);
 
-        MoveToEx(hdc, rect.left, side_bottom, None);
-        LineTo(hdc, rect.left, rect.top);
-        LineTo(hdc, rect.right - 1, rect.top);
-        LineTo(hdc, rect.right - 1, side_bottom);
+        let tabs = (0..tab_manager.count())
+            .filter_map(|index| {
+                let closable = !tab_manager.is_tab_pinned(index);
+                let rect = (index < visible_count).then(|| get_tab_rect(index, client_width));
+                Some(uia::TabItem {
+                    id: tab_manager.get_tab_id(index)?,
+                    name: tab_manager.get_tab_label(index),
+                    selected: index == tab_manager.selected_index(),
+                    closable,
+                    rect: rect.map(RECT::from),
+                    close_rect: rect
+                        .filter(|_| closable)
+                        .map(|r| get_tab_close_rect(&r).into()),
+                })
+            })
+            .collect();
+
+        // Same buttons as painted: the tab bar ones only while they fit
+        let max_x = get_tab_bar_max_x(client_width);
+        let mut buttons = Vec::new();
+        if has_overflow {
+            let rect = get_overflow_button_rect(visible_count, client_width);
+            buttons.push((TitlebarButton::Overflow, rect.into()));
+        }
+        let new_tab_rect = get_new_tab_button_rect_ex(visible_count, has_overflow, client_width);
+        if new_tab_rect.right <= max_x {
+            buttons.push((TitlebarButton::NewTab, new_tab_rect.into()));
+        }
+        let dropdown_rect = get_dropdown_button_rect_ex(visible_count, has_overflow, client_width);
+        if dropdown_rect.right <= max_x {
+            buttons.push((TitlebarButton::Profiles, dropdown_rect.into()));
+        }
+        let (minimize_rect, maximize_rect, close_rect) = get_button_rects(client_width);
+        buttons.extend(
+            [
+                (TitlebarButton::Minimize, minimize_rect),
+                (TitlebarButton::Maximize, maximize_rect),
+                (TitlebarButton::Close, close_rect),
+            ]
+            .into_iter()
+            // Hidden window buttons have empty rectangles
+            .filter(|(_, rect)| rect.right > rect.left),
+        );
 
-        SelectObject(hdc, old_pen);
-        DeleteObject(HGDIOBJ(outline_pen.0));
+        Some(uia::TabBarSnapshot {
+            tabs,
+            tab_list: RECT {
+                left: tab_bar_left(),
+                top: TAB_VERTICAL_PADDING,
+                right: tab_bar_left() + visible_count as i32 * tab_width(),
+                bottom: titlebar_height() - TAB_VERTICAL_PADDING,
+            },
+            buttons,
+            maximized: IsZoomed(hwnd).as_bool(),
+        })
+    }
+}
 
-        // Draw selected tab's icon if selected is in overflow
-        let text_offset = if has_selected_overflow {
-            if let Some(icon_filename) = selected_icon {
-                if let Some(hbitmap) = get_icon_bitmap(icon_filename) {
-                    let icon_x = rect.left + 4;
-                    let icon_y = (rect.top + rect.bottom - ICON_SIZE) / 2;
-                    paint_icon(hdc, hbitmap, icon_x, icon_y, ICON_SIZE, ICON_SIZE);
-                    ICON_SIZE + 2 // Icon width + small padding
-                } else {
-                    0
+/// Perform a titlebar action requested through UI Automation or the keyboard like the
+/// matching click
+#[allow(unused_must_use)]
+unsafe fn run_titlebar_action(hwnd: HWND, state: &mut WindowState, action: UiaAction) {
+    match action {
+        UiaAction::SelectTab(id) => {
+            let Some(index) = state.tab_manager.find_tab_by_id(id) else {
+                return;
+            };
+            hide_dropdown_popup(hwnd, state);
+            hide_overflow_popup(hwnd, state);
+            if state.tab_manager.select_tab(index) {
+                state.tab_manager.activate_selected(hwnd, content_top());
+                InvalidateRect(hwnd, None, false);
+            } else {
+                state
+                    .tab_manager
+                    .activate_and_foreground_selected(hwnd, content_top());
+            }
+        }
+        UiaAction::CloseTab(id) => {
+            let Some(index) = state.tab_manager.find_tab_by_id(id) else {
+                return;
+            };
+            hide_dropdown_popup(hwnd, state);
+            hide_overflow_popup(hwnd, state);
+            close_tab_gracefully(hwnd, state, index);
+        }
+        UiaAction::Press(TitlebarButton::Overflow) => {
+            hide_dropdown_popup(hwnd, state);
+            if state.overflow_hwnd.is_some() {
+                hide_overflow_popup(hwnd, state);
+            } else {
+                let mut client_rect = RECT::default();
+                if GetClientRect(hwnd, &mut client_rect).is_ok() {
+                    show_overflow_popup(hwnd, state, client_rect.right);
                 }
+            }
+            InvalidateRect(hwnd, None, false);
+        }
+        UiaAction::Press(TitlebarButton::NewTab) => {
+            hide_dropdown_popup(hwnd, state);
+            hide_overflow_popup(hwnd, state);
+            let default_profile = state.config.default_profile().clone();
+            open_profile_tab(hwnd, state, &default_profile, 0, None);
+        }
+        UiaAction::Press(TitlebarButton::Profiles) => {
+            hide_overflow_popup(hwnd, state);
+            if state.dropdown_state == DropdownState::Open {
+                hide_dropdown_popup(hwnd, state);
             } else {
-                0
+                show_dropdown_popup(hwnd, state);
             }
-        } else {
-            0
-        };
-
-        // Draw the count text
-        SetBkMode(hdc, TRANSPARENT);
-        SetTextColor(hdc, COLORREF(0x00FFFFFF));
-
-        let mut lf = LOGFONTW::default();
-        lf.lfHeight = -11;
-        lf.lfWeight = 400;
-        let font_name = "Segoe UI";
-        for (i, c) in font_name.encode_utf16().enumerate() {
-            if i < 32 {
-                lf.lfFaceName[i] = c;
+            InvalidateRect(hwnd, None, false);
+        }
+        UiaAction::Press(button) => {
+            hide_dropdown_popup(hwnd, state);
+            hide_overflow_popup(hwnd, state);
+            match button {
+                TitlebarButton::Minimize => {
+                    ShowWindow(hwnd, SW_MINIMIZE);
+                }
+                TitlebarButton::Maximize if IsZoomed(hwnd).as_bool() => {
+                    ShowWindow(hwnd, SW_RESTORE);
+                }
+                TitlebarButton::Maximize => {
+                    ShowWindow(hwnd, SW_MAXIMIZE);
+                }
+                _ => {
+                    PostMessageW(hwnd, WM_CLOSE, WPARAM(0), LPARAM(0));
+                }
             }
         }
-        let font = CreateFontIndirectW(&lf);
-        let old_font = SelectObject(hdc, HGDIOBJ(font.0));
+    }
+}
 
-        // Show count like "+3" for overflow tabs
-        let text = format!("+{}", overflow_count);
-        let text_wide: Vec<u16> = text.encode_utf16().collect();
+/// Titlebar elements in keyboard focus order: the tabs shown in the tab bar, then the
+/// buttons
+fn titlebar_focus_items(snapshot: &uia::TabBarSnapshot) -> Vec<TitlebarFocus> {
+    let tabs = snapshot
+        .tabs
+        .iter()
+        .filter(|tab| tab.rect.is_some())
+        .map(|tab| TitlebarFocus::Tab(tab.id));
+    let buttons = snapshot
+        .buttons
+        .iter()
+        .map(|(button, _)| TitlebarFocus::Button(*button));
+    tabs.chain(buttons).collect()
+}
 
-        let mut tm = TEXTMETRICW::default();
-        GetTextMetricsW(hdc, &mut tm);
-        let text_height = tm.tmHeight;
+/// Element next to `current` in focus order, wrapping around at the ends; the first
+/// element if `current` is gone
+fn step_titlebar_focus(
+    items: &[TitlebarFocus],
+    current: TitlebarFocus,
+    forward: bool,
+) -> Option<TitlebarFocus> {
+    let Some(position) = items.iter().position(|item| *item == current) else {
+        return items.first().copied();
+    };
+    let next = if forward {
+        (position + 1) % items.len()
+    } else {
+        (position + items.len() - 1) % items.len()
+    };
+    items.get(next).copied()
+}
 
-        let mut text_size = SIZE::default();
-        GetTextExtentPoint32W(hdc, &text_wide, &mut text_size);
+/// Rectangle of the focused titlebar element (client coordinates), if it is shown
+fn titlebar_focus_rect(hwnd: HWND, focus: TitlebarFocus) -> Option<RECT> {
+    let snapshot = tab_bar_snapshot(hwnd)?;
+    match focus {
+        TitlebarFocus::Tab(id) => snapshot.tabs.iter().find(|tab| tab.id == id)?.rect,
+        TitlebarFocus::Button(button) => snapshot
+            .buttons
+            .iter()
+            .find(|(b, _)| *b == button)
+            .map(|(_, rect)| *rect),
+    }
+}
 
-        // Center text in remaining space (after icon if present)
-        let text_area_left = rect.left + text_offset;
-        let text_x = (text_area_left + rect.right - text_size.cx) / 2;
-        let text_y = (rect.top + rect.bottom - text_height) / 2;
-        TextOutW(hdc, text_x, text_y, &text_wide);
+/// Give the titlebar the keyboard focus, starting on the selected tab
+unsafe fn focus_tab_bar(hwnd: HWND, state: &mut WindowState) {
+    finish_tab_rename(hwnd, state, true);
+    let Some(snapshot) = tab_bar_snapshot(hwnd) else {
+        return;
+    };
+    let items = titlebar_focus_items(&snapshot);
+    let selected = snapshot
+        .tabs
+        .iter()
+        .find(|tab| tab.selected)
+        .map(|tab| TitlebarFocus::Tab(tab.id));
+    state.titlebar_focus = selected
+        .filter(|focus| items.contains(focus))
+        .or(items.first().copied());
+
+    // Keep the keyboard instead of handing it to the selected tab's Neovide
+    let _ = KillTimer(hwnd, FOREGROUND_TIMER_ID);
+    let _ = SetFocus(hwnd);
+    let _ = InvalidateRect(hwnd, None, false);
+}
 
-        SelectObject(hdc, old_font);
-        DeleteObject(HGDIOBJ(font.0));
+/// Remove the focus ring (the keyboard goes back to Neovide)
+fn leave_titlebar_focus(hwnd: HWND, state: &mut WindowState) {
+    if state.titlebar_focus.take().is_some() {
+        unsafe {
+            let _ = InvalidateRect(hwnd, None, false);
+        }
     }
 }
 
-/// Paint the tab bar (all tabs and new tab button)
-#[allow(unused_must_use)]
-fn paint_tab_bar(
-    hdc: windows::Win32::Graphics::Gdi::HDC,
-    tab_manager: &TabManager,
-    hovered_tab: HoveredTab,
-    client_width: i32,
-    background_color: u32,
+/// Handle a key press while the titlebar has the keyboard focus
+unsafe fn handle_titlebar_key(
+    hwnd: HWND,
+    state: &mut WindowState,
+    focus: TitlebarFocus,
+    key: VIRTUAL_KEY,
 ) {
-    let max_x = get_tab_bar_max_x(client_width);
-    let selected_index = tab_manager.selected_index();
-    let drag_state = &tab_manager.drag_state;
-    let (visible_count, has_overflow) = calculate_visible_tabs(tab_manager.count(), client_width);
-
-    // First pass: paint all visible non-dragged tabs
-    for (i, _tab) in tab_manager.iter() {
-        // Skip overflow tabs
-        if i >= visible_count {
-            break;
+    let Some(snapshot) = tab_bar_snapshot(hwnd) else {
+        return;
+    };
+    let items = titlebar_focus_items(&snapshot);
+    let shift = GetKeyState(VK_SHIFT.0 as i32) < 0;
+
+    match key {
+        VK_LEFT | VK_RIGHT | VK_TAB | VK_HOME | VK_END => {
+            let target = match key {
+                VK_HOME => items.first().copied(),
+                VK_END => items.last().copied(),
+                _ => {
+                    let forward = key == VK_RIGHT || (key == VK_TAB && !shift);
+                    step_titlebar_focus(&items, focus, forward)
+                }
+            };
+            if target.is_some() {
+                state.titlebar_focus = target;
+                let _ = InvalidateRect(hwnd, None, false);
+            }
         }
-
-        // Skip the dragged tab - we'll paint it last so it appears on top
-        if let Some(drag) = drag_state {
-            if drag.is_active() && i == drag.tab_index {
-                continue;
+        VK_RETURN | VK_SPACE => {
+            leave_titlebar_focus(hwnd, state);
+            match focus {
+                TitlebarFocus::Tab(id) => {
+                    run_titlebar_action(hwnd, state, UiaAction::SelectTab(id));
+                }
+                TitlebarFocus::Button(button) => {
+                    run_titlebar_action(hwnd, state, UiaAction::Press(button));
+                    if button == TitlebarButton::Maximize {
+                        state
+                            .tab_manager
+                            .activate_and_foreground_selected(hwnd, content_top());
+                    }
+                }
             }
         }
-
-        let tab_rect = get_tab_rect(i, client_width);
-        if tab_rect.left > max_x {
-            break;
+        VK_DELETE => {
+            if let TitlebarFocus::Tab(id) = focus {
+                run_titlebar_action(hwnd, state, UiaAction::CloseTab(id));
+            }
         }
+        VK_ESCAPE => {
+            leave_titlebar_focus(hwnd, state);
+            state
+                .tab_manager
+                .activate_and_foreground_selected(hwnd, content_top());
+        }
+        _ => {}
+    }
+}
 
-        let is_selected = i == selected_index;
-        let is_hovered = matches!(hovered_tab, HoveredTab::Tab(idx) if idx == i);
-        let close_hovered = matches!(hovered_tab, HoveredTab::TabClose(idx) if idx == i);
-        let label = tab_manager.get_tab_label(i);
-        let icon = tab_manager.get_tab_icon(i);
+/// Request a graceful close of the tab at `index`; falls back to a forceful close if
+/// its window is not ready
+#[allow(unused_must_use)]
+unsafe fn close_tab_gracefully(hwnd: HWND, state: &mut WindowState, index: usize) {
+    if !state.tab_manager.request_close_tab(index) {
+        if state.tab_manager.is_empty() {
+            PostMessageW(hwnd, WM_CLOSE, WPARAM(0), LPARAM(0));
+        } else {
+            state.tab_manager.activate_selected(hwnd, content_top());
+            InvalidateRect(hwnd, None, false);
+        }
+    }
+}
 
-        paint_tab(
-            hdc,
-            &tab_rect,
-            &label,
-            icon,
-            is_selected,
-            is_hovered,
-            close_hovered,
-            background_color,
-        );
+/// Show the inline editor over the tab at `index` (only tabs shown in the tab bar)
+unsafe fn start_tab_rename(hwnd: HWND, state: &mut WindowState, index: usize) {
+    finish_tab_rename(hwnd, state, true);
+    let Some(tab_id) = state.tab_manager.get_tab_id(index) else {
+        return;
+    };
+    let mut client_rect = RECT::default();
+    if GetClientRect(hwnd, &mut client_rect).is_err() {
+        return;
+    }
+    let (visible_count, _) = calculate_visible_tabs(state.tab_manager.count(), client_rect.right);
+    if index >= visible_count {
+        return;
     }
 
-    // Paint overflow button if there are overflow tabs
-    if has_overflow {
-        let overflow_rect = get_overflow_button_rect(visible_count, client_width);
-        let overflow_count = tab_manager.count() - visible_count;
-        let is_hovered = matches!(hovered_tab, HoveredTab::OverflowButton);
-        let has_selected_overflow = selected_index >= visible_count;
-        let selected_icon = if has_selected_overflow {
-            tab_manager.get_tab_icon(selected_index)
-        } else {
-            None
-        };
-        paint_overflow_button(
-            hdc,
-            &overflow_rect,
-            overflow_count,
-            is_hovered,
-            has_selected_overflow,
-            selected_icon,
-        );
+    let tab_rect = get_tab_rect(index, client_rect.right);
+    let close_rect = get_tab_close_rect(&tab_rect);
+    let edit_rect = RECT {
+        left: tab_rect.left + 4,
+        top: tab_rect.top + 4,
+        right: close_rect.left - 2,
+        bottom: tab_rect.bottom - 4,
+    };
+
+    // Keep the keyboard in the wrapper instead of handing it to Neovide
+    let _ = KillTimer(hwnd, FOREGROUND_TIMER_ID);
+    let _ = SetForegroundWindow(hwnd);
+    let label = state.tab_manager.get_tab_label(index);
+    state.tab_rename = TabRenameEditor::show(hwnd, &edit_rect, &label, tab_id);
+}
+
+/// Close the inline tab editor, renaming the tab if `apply` is set and the name changed
+unsafe fn finish_tab_rename(hwnd: HWND, state: &mut WindowState, apply: bool) {
+    let Some(editor) = state.tab_rename.take() else {
+        return;
+    };
+    if apply {
+        if let (Some(name), Some(index)) = (
+            editor.changed_text(),
+            state.tab_manager.find_tab_by_id(editor.tab_id),
+        ) {
+            state.tab_manager.rename_tab(index, &name);
+        }
     }
+    drop(editor);
+    let _ = InvalidateRect(hwnd, None, false);
+    // Give the keyboard back to Neovide unless another application was activated
+    if GetForegroundWindow() == hwnd {
+        state
+            .tab_manager
+            .activate_and_foreground_selected(hwnd, content_top());
+    }
+}
 
-    // Paint new tab button
-    let new_tab_rect = get_new_tab_button_rect_ex(visible_count, has_overflow, client_width);
-    if new_tab_rect.right <= max_x {
-        let is_hovered = matches!(hovered_tab, HoveredTab::NewTabButton);
-        paint_new_tab_button(hdc, &new_tab_rect, is_hovered);
+/// Run the configured double-click action on the tab at `index`
+unsafe fn run_tab_double_click(hwnd: HWND, state: &mut WindowState, index: usize) {
+    match state.config.tab_double_click {
+        TabDoubleClickAction::Rename => start_tab_rename(hwnd, state, index),
+        TabDoubleClickAction::Duplicate => duplicate_tab(hwnd, state, index),
+        TabDoubleClickAction::TogglePin => toggle_tab_pin(hwnd, state, index),
+        TabDoubleClickAction::Close => close_tab_gracefully(hwnd, state, index),
     }
+}
+
+/// Launch queued Neovide processes into free spawn slots (failures become error tabs)
+unsafe fn launch_queued_tabs(hwnd: HWND, state: &mut WindowState) {
+    let Ok(rect) = get_content_rect(hwnd) else {
+        return;
+    };
+    let width = (rect.right - rect.left) as u32;
+    let height = (rect.bottom - rect.top) as u32;
 
-    // Paint dropdown button
-    let dropdown_rect = get_dropdown_button_rect_ex(visible_count, has_overflow, client_width);
-    if dropdown_rect.right <= max_x {
-        let is_hovered = matches!(hovered_tab, HoveredTab::ProfileDropdown);
-        paint_dropdown_button(hdc, &dropdown_rect, is_hovered);
+    if state.tab_manager.launch_queued(width, height, hwnd) {
+        let _ = InvalidateRect(hwnd, None, false);
     }
+}
 
-    // Draw line at the bottom of the tab bar with a gap for the selected tab
-    // This creates the illusion of physical tabbed pages
-    paint_tab_bar_bottom_line(hdc, tab_manager, client_width);
+/// Launch an error tab's Neovide again (its retry icon was clicked)
+#[allow(unused_must_use)]
+unsafe fn retry_tab(hwnd: HWND, state: &mut WindowState, index: usize) {
+    let Ok(rect) = get_content_rect(hwnd) else {
+        return;
+    };
+    let width = (rect.right - rect.left) as u32;
+    let height = (rect.bottom - rect.top) as u32;
 
-    // Second pass: paint the dragged tab at its visual position (on top of everything)
-    if let Some(drag) = drag_state {
-        if drag.is_active() {
-            let drag_index = drag.tab_index;
-            let visual_x = drag.get_visual_x();
+    if !state.tab_manager.retry_tab(index, width, height, hwnd) {
+        return;
+    }
+    state.tab_manager.select_tab(index);
+    state.tab_manager.activate_selected(hwnd, content_top());
+    if !state.loading_timer_active {
+        SetTimer(hwnd, LOADING_TIMER_ID, LOADING_FRAME_MS, None);
+        state.loading_timer_active = true;
+    }
+    InvalidateRect(hwnd, None, false);
+}
 
-            // Clamp the visual position to stay within the visible tab bar bounds
-            let min_x = TAB_BAR_LEFT_MARGIN;
-            let max_tab_x =
-                TAB_BAR_LEFT_MARGIN + ((visible_count.saturating_sub(1)) as i32 * TAB_WIDTH);
-            let clamped_x = visual_x.clamp(min_x, max_tab_x.max(min_x));
+/// Drop the tabs still waiting in the spawn queue (the startup "Cancel" action)
+#[allow(unused_must_use)]
+unsafe fn cancel_pending_tabs(hwnd: HWND, state: &mut WindowState) {
+    if state.tab_manager.cancel_queued_spawns() == 0 {
+        return;
+    }
+    if state.tab_manager.is_empty() {
+        PostMessageW(hwnd, WM_CLOSE, WPARAM(0), LPARAM(0));
+    } else {
+        state.tab_manager.activate_selected(hwnd, content_top());
+        InvalidateRect(hwnd, None, false);
+    }
+}
 
-            let drag_rect = RECT {
-                left: clamped_x,
-                top: TAB_VERTICAL_PADDING,
-                right: clamped_x + TAB_WIDTH,
-                bottom: TITLEBAR_HEIGHT - TAB_VERTICAL_PADDING,
-            };
+/// Window title for the taskbar: the configured `base` title and the active
+/// session, e.g. "neovide-tabs - work"
+fn window_title(base: &str, session_name: Option<&str>) -> String {
+    match session_name {
+        Some(name) => format!("{} - {}", base, name),
+        None => base.to_string(),
+    }
+}
 
-            let is_selected = drag_index == selected_index;
-            let label = tab_manager.get_tab_label(drag_index);
-            let icon = tab_manager.get_tab_icon(drag_index);
+/// Show the active session in the window title (taskbar and Alt-Tab)
+fn update_window_title(hwnd: HWND, base: &str, session_name: Option<&str>) {
+    let title = windows::core::HSTRING::from(window_title(base, session_name));
+    unsafe {
+        let _ = SetWindowTextW(hwnd, &title);
+    }
+}
 
-            // Dragged tab is never hovered (we're dragging it)
-            paint_tab(
-                hdc,
-                &drag_rect,
-                &label,
-                icon,
-                is_selected,
-                false,
-                false,
-                background_color,
+/// Load a named session, reporting a broken session file. Returns None if the
+/// session does not exist yet or could not be read.
+fn load_named_session(name: &str) -> Option<Session> {
+    match sessions::load_session(name) {
+        Ok(session) => session,
+        Err(e) => {
+            show_error(
+                &format!("Failed to load session '{}': {:#}", name, e),
+                "Error: Failed to Load Session",
             );
+            None
         }
     }
 }
 
-/// Paint the bottom line of the tab bar with a gap for the selected tab (or overflow button)
-#[allow(unused_must_use)]
-fn paint_tab_bar_bottom_line(
-    hdc: windows::Win32::Graphics::Gdi::HDC,
-    tab_manager: &TabManager,
-    client_width: i32,
-) {
-    unsafe {
-        let outline_pen = CreatePen(PS_SOLID, 1, COLORREF(rgb_to_colorref(TAB_OUTLINE_COLOR)));
-        let old_pen = SelectObject(hdc, HGDIOBJ(outline_pen.0));
+/// Save the open tabs as the active session (if any). Skipped while tabs are
+/// closing, so a half-closed tab set never replaces the saved one.
+fn save_active_session(state: &mut WindowState) {
+    let Some(name) = state.session_name.as_deref() else {
+        return;
+    };
+    if state.tab_manager.is_empty() || state.tab_manager.has_pending_close() {
+        return;
+    }
+    match sessions::save_session(name, &state.tab_manager.session_snapshot()) {
+        Ok(()) => state
+            .session_autosave
+            .mark_saved(state.tab_manager.session_generation(), Instant::now()),
+        Err(e) => eprintln!("Failed to save session '{}': {:#}", name, e),
+    }
+}
 
-        // The bottom line is at TITLEBAR_HEIGHT - 1 (bottom of tab area)
-        let line_y = TITLEBAR_HEIGHT - 1;
-        let line_start_x = 0;
-        let line_end_x = client_width;
+/// Keep the window cell's copy of the active session current, skipping the same
+/// states as `save_active_session`
+unsafe fn update_shutdown_session(hwnd: HWND, state: &WindowState) {
+    let Some(cell) = window_cell(hwnd) else {
+        return;
+    };
+    let mut saved = cell.shutdown_session.borrow_mut();
+    let Some(name) = state.session_name.as_deref() else {
+        *saved = None;
+        return;
+    };
+    if state.tab_manager.is_empty() || state.tab_manager.has_pending_close() {
+        return;
+    }
+    let generation = state.tab_manager.session_generation();
+    if saved
+        .as_ref()
+        .is_some_and(|(saved_name, saved_generation, _)| {
+            saved_name == name && *saved_generation == generation
+        })
+    {
+        return;
+    }
+    *saved = Some((
+        name.to_string(),
+        generation,
+        state.tab_manager.session_snapshot(),
+    ));
+}
 
-        // Determine where the gap should be
-        let selected_index = tab_manager.selected_index();
-        let (visible_count, has_overflow) =
-            calculate_visible_tabs(tab_manager.count(), client_width);
+/// Save the session as of the last process poll, for when Windows ends the
+/// session while a handler has the state and the process can't wait for it
+unsafe fn save_shutdown_session(hwnd: HWND) {
+    let Some(cell) = window_cell(hwnd) else {
+        return;
+    };
+    if let Some((name, _, session)) = cell.shutdown_session.borrow().as_ref()
+        && let Err(e) = sessions::save_session(name, session)
+    {
+        eprintln!("Failed to save session '{}': {:#}", name, e);
+    }
+}
 
-        // If selected tab is in overflow, gap is at the overflow button
-        // Otherwise, gap is at the selected tab
-        let gap_rect = if has_overflow && selected_index >= visible_count {
-            // Selected tab is in overflow - gap at overflow button
-            get_overflow_button_rect(visible_count, client_width)
-        } else {
-            // Selected tab is visible - gap at the selected tab
-            get_tab_rect(selected_index, client_width)
-        };
+/// Snapshot the active session if its tabs changed and the autosave interval passed
+fn autosave_session(state: &mut WindowState) {
+    let Some(interval) = state.config.session_autosave_interval else {
+        return;
+    };
+    if state.session_name.is_none() || state.pending_session.is_some() {
+        return;
+    }
+    if state.session_autosave.is_due(
+        state.tab_manager.session_generation(),
+        Instant::now(),
+        interval,
+    ) {
+        save_active_session(state);
+    }
+}
 
-        // Draw line from left edge to start of gap (connects with left side)
-        if gap_rect.left > line_start_x {
-            MoveToEx(hdc, line_start_x, line_y, None);
-            LineTo(hdc, gap_rect.left + 1, line_y);
+/// Open the tabs of a saved session, then a selected tab for `open_request` (if
+/// given), or a single tab with the default profile when
+/// neither opened a tab. Tabs whose profile no longer exists use the default
+/// profile. Returns the first launch error, if any.
+fn open_session_tabs(
+    tab_manager: &mut TabManager<NeovideProcess>,
+    config: &Config,
+    session: Option<&Session>,
+    open_request: Option<&OpenRequest>,
+    width: u32,
+    height: u32,
+    hwnd: HWND,
+) -> Option<anyhow::Error> {
+    let mut first_error = None;
+    if let Some(session) = session {
+        let mut restored = Vec::with_capacity(session.tabs.len());
+        for session_tab in &session.tabs {
+            let (profile_index, profile) = config
+                .find_profile_by_name(&session_tab.profile)
+                .unwrap_or_else(|| {
+                    eprintln!(
+                        "Session: profile '{}' not found, using the default profile",
+                        session_tab.profile
+                    );
+                    (0, config.default_profile())
+                });
+            match tab_manager.create_tab_in_directory(
+                width,
+                height,
+                hwnd,
+                profile,
+                profile_index,
+                &session_tab.working_directory,
+                &[],
+            ) {
+                Ok(index) => {
+                    tab_manager.restore_tab_state(index, session_tab);
+                    restored.push(Some(index));
+                }
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                    restored.push(None);
+                }
+            }
         }
+        tab_manager.finish_session_restore(session, &restored);
+    }
 
-        // Draw line from end of gap to right edge (connects with right side)
-        if gap_rect.right < line_end_x {
-            MoveToEx(hdc, gap_rect.right - 1, line_y, None);
-            LineTo(hdc, line_end_x, line_y);
+    if let Some(request) = open_request {
+        let (directory, files) = request.new_tab();
+        let (profile_index, profile) = request_profile(config, request);
+        if let Err(e) = tab_manager.create_tab_in_directory(
+            width,
+            height,
+            hwnd,
+            profile,
+            profile_index,
+            &directory,
+            &files,
+        ) {
+            first_error.get_or_insert(e);
         }
+    }
 
-        SelectObject(hdc, old_pen);
-        DeleteObject(HGDIOBJ(outline_pen.0));
+    if tab_manager.is_empty() {
+        if let Err(e) = tab_manager.create_tab(width, height, hwnd, config.default_profile(), 0) {
+            first_error.get_or_insert(e);
+        }
     }
+    first_error
 }
 
-/// Paint the title bar content to a device context
-#[allow(unused_must_use, clippy::too_many_arguments)]
-fn paint_titlebar_content(
-    hwnd: HWND,
-    hdc: windows::Win32::Graphics::Gdi::HDC,
-    client_rect: &RECT,
-    background_color: u32,
-    hovered_button: HoveredButton,
-    hovered_tab: HoveredTab,
-    tab_manager: &TabManager,
-    dropdown_state: DropdownState,
-    profiles: &[Profile],
-) {
-    unsafe {
-        let client_width = client_rect.right;
-
-        // Fill entire client area with background color
-        let bg_colorref = COLORREF(rgb_to_colorref(background_color));
-        let bg_brush = CreateSolidBrush(bg_colorref);
-        FillRect(hdc, client_rect, bg_brush);
-        DeleteObject(HGDIOBJ(bg_brush.0));
+/// Register the global hotkeys of `config`; returns their IDs
+unsafe fn register_hotkeys(hwnd: HWND, config: &Config) -> Vec<i32> {
+    let mut ids = Vec::new();
+
+    // Register tab hotkeys
+    let tab_hotkey_ids = hotkeys::register_tab_hotkeys(hwnd, &config.hotkeys.tab);
+    ids.extend(tab_hotkey_ids);
+
+    // Register profile hotkeys
+    let profile_hotkey_ids = hotkeys::register_profile_hotkeys(hwnd, &config.profiles);
+    ids.extend(profile_hotkey_ids);
+
+    // Register the "New tab in folder..." hotkey (if configured)
+    ids.extend(hotkeys::register_action_hotkey(
+        hwnd,
+        hotkeys::NEW_TAB_IN_FOLDER_HOTKEY_ID,
+        config.hotkeys.new_tab_in_folder.as_deref(),
+    ));
+
+    // Register the task manager hotkey (if configured)
+    ids.extend(hotkeys::register_action_hotkey(
+        hwnd,
+        hotkeys::TASK_MANAGER_HOTKEY_ID,
+        config.hotkeys.task_manager.as_deref(),
+    ));
+
+    // Register the bulk close hotkeys (if configured)
+    ids.extend(hotkeys::register_action_hotkey(
+        hwnd,
+        hotkeys::CLOSE_OTHER_TABS_HOTKEY_ID,
+        config.hotkeys.close_other_tabs.as_deref(),
+    ));
+    ids.extend(hotkeys::register_action_hotkey(
+        hwnd,
+        hotkeys::CLOSE_TABS_TO_RIGHT_HOTKEY_ID,
+        config.hotkeys.close_tabs_to_right.as_deref(),
+    ));
+
+    // Register the tab history hotkeys (if configured)
+    ids.extend(hotkeys::register_action_hotkey(
+        hwnd,
+        hotkeys::TAB_BACK_HOTKEY_ID,
+        config.hotkeys.tab_back.as_deref(),
+    ));
+    ids.extend(hotkeys::register_action_hotkey(
+        hwnd,
+        hotkeys::TAB_FORWARD_HOTKEY_ID,
+        config.hotkeys.tab_forward.as_deref(),
+    ));
+
+    // Register the tab bar focus hotkey (if configured)
+    ids.extend(hotkeys::register_action_hotkey(
+        hwnd,
+        hotkeys::FOCUS_TAB_BAR_HOTKEY_ID,
+        config.hotkeys.focus_tab_bar.as_deref(),
+    ));
+
+    // Register the tab cycling hotkeys (if configured)
+    ids.extend(hotkeys::register_action_hotkey(
+        hwnd,
+        hotkeys::NEXT_TAB_HOTKEY_ID,
+        config.hotkeys.next_tab.as_deref(),
+    ));
+    ids.extend(hotkeys::register_action_hotkey(
+        hwnd,
+        hotkeys::PREV_TAB_HOTKEY_ID,
+        config.hotkeys.prev_tab.as_deref(),
+    ));
+
+    // Register the debug HUD hotkey (if configured)
+    ids.extend(hotkeys::register_action_hotkey(
+        hwnd,
+        hotkeys::DEBUG_HUD_HOTKEY_ID,
+        config.hotkeys.debug_hud.as_deref(),
+    ));
+    ids
+}
 
-        // Paint tab bar
-        paint_tab_bar(
-            hdc,
-            tab_manager,
-            hovered_tab,
-            client_width,
-            background_color,
-        );
+/// Put things right after the machine resumed from sleep: pick up recreated
+/// Neovide windows, move the visible one back into place (and in front of the
+/// wrapper while it is active) and register the hotkeys again
+unsafe fn resume_from_sleep(hwnd: HWND, state: &mut WindowState) {
+    state.tab_manager.revalidate_windows();
+    reactivate_selected(hwnd, state);
+    hotkeys::unregister_all_hotkeys(hwnd, &state.registered_hotkeys);
+    state.registered_hotkeys = register_hotkeys(hwnd, &state.config);
+    let _ = InvalidateRect(hwnd, None, false);
+}
 
-        // Get button rectangles
-        let (minimize_rect, maximize_rect, close_rect) = get_button_rects(client_width);
+/// Move the selected tab's Neovide window into place, and bring it in front of
+/// the wrapper if the wrapper (or Neovide) is active
+unsafe fn reactivate_selected(hwnd: HWND, state: &mut WindowState) {
+    if IsIconic(hwnd).as_bool() {
+        return;
+    }
+    let foreground = GetForegroundWindow();
+    if foreground == hwnd || state.tab_manager.owns_window(foreground) {
+        state.tab_manager.activate_selected(hwnd, content_top());
+    } else {
+        state
+            .tab_manager
+            .update_selected_position(hwnd, content_top());
+    }
+}
 
-        // Draw button backgrounds for hover states
-        let hover_brush = CreateSolidBrush(COLORREF(rgb_to_colorref(0x3d3d3d)));
-        let close_hover_brush = CreateSolidBrush(COLORREF(rgb_to_colorref(0xe81123))); // Red for close
+/// Start or stop auto-hiding the title bar. It starts out shown; once it stops,
+/// Neovide goes back below it.
+unsafe fn set_auto_hide(hwnd: HWND, state: &mut WindowState, enabled: bool) {
+    if enabled {
+        state.auto_hide = Some(AutoHide::new(Instant::now()));
+        SetTimer(hwnd, AUTO_HIDE_TIMER_ID, AUTO_HIDE_POLL_MS, None);
+    } else {
+        state.auto_hide = None;
+        let _ = KillTimer(hwnd, AUTO_HIDE_TIMER_ID);
+        layout::set_content_top(None);
+        state
+            .tab_manager
+            .update_selected_position(hwnd, content_top());
+    }
+}
 
-        match hovered_button {
-            HoveredButton::Minimize => {
-                FillRect(hdc, &minimize_rect, hover_brush);
-            }
-            HoveredButton::Maximize => {
-                FillRect(hdc, &maximize_rect, hover_brush);
-            }
-            HoveredButton::Close => {
-                FillRect(hdc, &close_rect, close_hover_brush);
-            }
-            HoveredButton::None => {}
+/// Slide the auto-hidden title bar in or out a step, moving Neovide with it, and
+/// poll faster while it slides
+unsafe fn update_auto_hide(hwnd: HWND, state: &mut WindowState) {
+    let wanted = titlebar_wanted(hwnd, state);
+    let animate = !state.tab_manager.reduce_motion();
+    let Some(auto_hide) = state.auto_hide.as_mut() else {
+        return;
+    };
+    let was_sliding = auto_hide.is_sliding();
+    if auto_hide.update(wanted, animate, Instant::now()) {
+        layout::set_content_top(Some(auto_hide.content_top(titlebar_height())));
+        if !IsIconic(hwnd).as_bool() {
+            state
+                .tab_manager
+                .update_selected_position(hwnd, content_top());
         }
+    }
+    let sliding = auto_hide.is_sliding();
+    if sliding != was_sliding {
+        let interval = if sliding {
+            AUTO_HIDE_FRAME_MS
+        } else {
+            AUTO_HIDE_POLL_MS
+        };
+        SetTimer(hwnd, AUTO_HIDE_TIMER_ID, interval, None);
+    }
+}
 
-        DeleteObject(HGDIOBJ(hover_brush.0));
-        DeleteObject(HGDIOBJ(close_hover_brush.0));
+/// Whether the auto-hidden title bar should show: the pointer is over what shows
+/// of it, Ctrl+Alt is held while neovide-tabs is active, or a popup, tab drag or
+/// rename is using it
+unsafe fn titlebar_wanted(hwnd: HWND, state: &WindowState) -> bool {
+    if state.dropdown_hwnd.is_some()
+        || state.overflow_hwnd.is_some()
+        || state.tab_rename.is_some()
+        || state.tab_manager.drag_state.is_some()
+    {
+        return true;
+    }
+    let reveal_keys = GetKeyState(VK_CONTROL.0 as i32) < 0 && GetKeyState(VK_MENU.0 as i32) < 0;
+    (reveal_keys && title_in_view(hwnd, state)) || pointer_over_title_bar(hwnd)
+}
 
-        // Draw button icons using simple lines (white color)
-        let pen = CreatePen(PS_SOLID, 1, COLORREF(0x00FFFFFF));
-        let old_pen = SelectObject(hdc, HGDIOBJ(pen.0));
+/// Stop the process polling and the quick title refresh (workstation locked),
+/// or start the polling again. Neovide windows that closed meanwhile are
+/// noticed once it runs again.
+unsafe fn set_polling_paused(hwnd: HWND, state: &mut WindowState, paused: bool) {
+    if paused == state.polling_paused {
+        return;
+    }
+    state.polling_paused = paused;
+    if paused {
+        let _ = KillTimer(hwnd, PROCESS_POLL_TIMER_ID);
+        let _ = KillTimer(hwnd, TITLE_BOOST_TIMER_ID);
+        state.title_boost = false;
+    } else {
+        SetTimer(
+            hwnd,
+            PROCESS_POLL_TIMER_ID,
+            state.config.advanced.process_poll_interval_ms,
+            None,
+        );
+    }
+}
 
-        // Minimize button: horizontal line
-        let min_cx = (minimize_rect.left + minimize_rect.right) / 2;
-        let min_cy = (minimize_rect.top + minimize_rect.bottom) / 2;
-        MoveToEx(hdc, min_cx - 5, min_cy, None);
-        LineTo(hdc, min_cx + 6, min_cy);
+/// Close the tabs gracefully for a shutdown or logoff, so unsaved buffers get
+/// their save prompts, and hold the shutdown until they are gone
+#[allow(unused_must_use)]
+unsafe fn begin_ending_session(hwnd: HWND, state: &mut WindowState) {
+    if state.ending_session {
+        return;
+    }
+    // The close sequence runs on the process polling
+    set_polling_paused(hwnd, state, false);
+    state.ending_session =
+        ShutdownBlockReasonCreate(hwnd, w!("Saving Neovim sessions\u{2026}")).is_ok();
+    PostMessageW(hwnd, WM_CLOSE, WPARAM(0), LPARAM(0));
+}
 
-        // Maximize/Restore button
-        let max_cx = (maximize_rect.left + maximize_rect.right) / 2;
-        let max_cy = (maximize_rect.top + maximize_rect.bottom) / 2;
+/// Let Windows shut down without waiting for the tabs again (a save prompt was
+/// cancelled, or the shutdown was)
+unsafe fn end_shutdown_block(hwnd: HWND, state: &mut WindowState) {
+    if std::mem::take(&mut state.ending_session) {
+        ShutdownBlockReasonDestroy(hwnd).ok();
+    }
+}
 
-        let is_maximized = IsZoomed(hwnd).as_bool();
-        if is_maximized {
-            // Draw restore icon (two overlapping rectangles)
-            // Back rectangle (smaller, offset up-right)
-            MoveToEx(hdc, max_cx - 3, max_cy - 5, None);
-            LineTo(hdc, max_cx + 5, max_cy - 5);
-            MoveToEx(hdc, max_cx + 5, max_cy - 5, None);
-            LineTo(hdc, max_cx + 5, max_cy - 2);
-            // Front rectangle
-            MoveToEx(hdc, max_cx - 5, max_cy - 2, None);
-            LineTo(hdc, max_cx + 3, max_cy - 2);
-            LineTo(hdc, max_cx + 3, max_cy + 6);
-            LineTo(hdc, max_cx - 5, max_cy + 6);
-            LineTo(hdc, max_cx - 5, max_cy - 2);
-        } else {
-            // Draw maximize icon (single rectangle)
-            MoveToEx(hdc, max_cx - 5, max_cy - 5, None);
-            LineTo(hdc, max_cx + 5, max_cy - 5);
-            LineTo(hdc, max_cx + 5, max_cy + 5);
-            LineTo(hdc, max_cx - 5, max_cy + 5);
-            LineTo(hdc, max_cx - 5, max_cy - 5);
-        }
+/// Switch to another named session: save the current one and close its tabs; the
+/// target session's tabs open once they are gone (see `open_pending_session`)
+#[allow(unused_must_use)]
+unsafe fn switch_session(hwnd: HWND, state: &mut WindowState, name: String) {
+    if state.session_name.as_deref() == Some(name.as_str()) || state.pending_session.is_some() {
+        return;
+    }
+    save_active_session(state);
+    state.pending_session = Some(name);
+    state.tab_manager.request_close_all();
 
-        // Close button: X
-        let close_cx = (close_rect.left + close_rect.right) / 2;
-        let close_cy = (close_rect.top + close_rect.bottom) / 2;
-        let _ = MoveToEx(hdc, close_cx - 5, close_cy - 5, None);
-        let _ = LineTo(hdc, close_cx + 6, close_cy + 6);
-        let _ = MoveToEx(hdc, close_cx + 5, close_cy - 5, None);
-        let _ = LineTo(hdc, close_cx - 6, close_cy + 6);
+    // Tabs without a ready window are closed right away
+    if state.tab_manager.is_empty() {
+        open_pending_session(hwnd, state);
+    } else {
+        InvalidateRect(hwnd, None, false);
+    }
+}
 
-        let _ = SelectObject(hdc, old_pen);
-        let _ = DeleteObject(HGDIOBJ(pen.0));
+/// Open the session a switch is waiting for. Returns false if no switch is pending.
+#[allow(unused_must_use)]
+unsafe fn open_pending_session(hwnd: HWND, state: &mut WindowState) -> bool {
+    let Some(name) = state.pending_session.take() else {
+        return false;
+    };
+    let Ok(rect) = get_content_rect(hwnd) else {
+        return false;
+    };
+    let width = (rect.right - rect.left) as u32;
+    let height = (rect.bottom - rect.top) as u32;
+
+    let session = load_named_session(&name);
+    if let Some(e) = open_session_tabs(
+        &mut state.tab_manager,
+        &state.config,
+        session.as_ref(),
+        None,
+        width,
+        height,
+        hwnd,
+    ) {
+        let error_msg = format!("Failed to launch Neovide: {}", e);
+        show_error(&error_msg, "Error: Failed to Launch Neovide");
+    }
+    if state.tab_manager.is_empty() {
+        return false;
+    }
 
-        // Note: Dropdown menu is now rendered as a separate popup window,
-        // so we don't paint it here anymore.
-        let _ = dropdown_state; // Silence unused warning
-        let _ = profiles; // Silence unused warning
+    update_window_title(hwnd, &state.config.window_title, Some(&name));
+    state.session_name = Some(name);
+    update_widgets(hwnd, state);
+    state.session_autosave = Autosave::new(state.tab_manager.session_generation(), Instant::now());
+    if !state.loading_timer_active {
+        SetTimer(hwnd, LOADING_TIMER_ID, LOADING_FRAME_MS, None);
+        state.loading_timer_active = true;
     }
+    InvalidateRect(hwnd, None, false);
+    true
 }
 
-/// Paint the title bar using double-buffering to prevent flicker
-#[allow(unused_must_use, clippy::too_many_arguments)]
-fn paint_titlebar(
-    hwnd: HWND,
-    ps: &PAINTSTRUCT,
-    background_color: u32,
-    hovered_button: HoveredButton,
-    hovered_tab: HoveredTab,
-    tab_manager: &TabManager,
-    dropdown_state: DropdownState,
-    profiles: &[Profile],
-) {
-    unsafe {
-        let hdc = ps.hdc;
-
-        // Get client rect
-        let mut client_rect = RECT::default();
-        if GetClientRect(hwnd, &mut client_rect).is_err() {
-            return;
+/// Show the session menu (saved sessions, the active one checked) at the cursor
+#[allow(unused_must_use)]
+unsafe fn show_session_menu(hwnd: HWND, state: &mut WindowState) {
+    let mut names = sessions::list_sessions();
+    if let Some(active) = &state.session_name {
+        if !names.contains(active) {
+            names.push(active.clone());
+            names.sort();
         }
+    }
 
-        let width = client_rect.right - client_rect.left;
-        let height = client_rect.bottom - client_rect.top;
+    let Ok(menu) = CreatePopupMenu() else {
+        return;
+    };
+    if names.is_empty() {
+        AppendMenuW(
+            menu,
+            MF_STRING | MF_GRAYED,
+            0,
+            w!("No saved sessions (start with --session <name>)"),
+        );
+    }
+    for (i, name) in names.iter().enumerate() {
+        let mut flags = MF_STRING;
+        if state.session_name.as_ref() == Some(name) {
+            flags |= MF_CHECKED;
+        }
+        let label = windows::core::HSTRING::from(name.as_str());
+        // Command IDs start at 1; 0 means the menu was dismissed
+        AppendMenuW(menu, flags, i + 1, &label);
+    }
 
-        // Create off-screen buffer for double-buffering
-        let mem_dc = CreateCompatibleDC(hdc);
-        let mem_bitmap = CreateCompatibleBitmap(hdc, width, height);
-        let old_bitmap = SelectObject(mem_dc, HGDIOBJ(mem_bitmap.0));
+    let mut cursor = POINT::default();
+    GetCursorPos(&mut cursor);
+    SetForegroundWindow(hwnd);
+    let command = TrackPopupMenu(
+        menu,
+        TPM_RETURNCMD | TPM_NONOTIFY,
+        cursor.x,
+        cursor.y,
+        0,
+        hwnd,
+        None,
+    );
+    DestroyMenu(menu);
+
+    match (command.0 as usize)
+        .checked_sub(1)
+        .and_then(|i| names.get(i))
+    {
+        Some(name) => switch_session(hwnd, state, name.clone()),
+        None => {
+            // Menu dismissed - give the keyboard back to Neovide
+            state
+                .tab_manager
+                .activate_and_foreground_selected(hwnd, content_top());
+        }
+    }
+}
 
-        // Paint everything to the off-screen buffer
-        paint_titlebar_content(
-            hwnd,
-            mem_dc,
-            &client_rect,
-            background_color,
-            hovered_button,
-            hovered_tab,
-            tab_manager,
-            dropdown_state,
-            profiles,
-        );
+/// Show the startup profile chooser once the profile icons are decoded, unless a
+/// tab was opened meanwhile (e.g. a folder from Explorer)
+unsafe fn show_pending_profile_prompt(hwnd: HWND, state: &mut WindowState) {
+    if !state.profile_prompt_pending || icons_pending() {
+        return;
+    }
+    state.profile_prompt_pending = false;
+    if state.tab_manager.is_empty() {
+        show_profile_prompt(hwnd, state);
+    }
+}
 
-        // Copy the off-screen buffer to the screen in one operation
-        BitBlt(hdc, 0, 0, width, height, mem_dc, 0, 0, SRCCOPY);
+/// Ask which profile the first tab opens: the profiles with their icons, centered
+/// over the content area, with the last choice in bold. Dismissing the chooser
+/// opens the last choice (the first profile if there is none).
+#[allow(unused_must_use)]
+unsafe fn show_profile_prompt(hwnd: HWND, state: &mut WindowState) {
+    let profiles = state.config.profiles.clone();
+    let last_choice = sessions::load_startup_profile()
+        .and_then(|name| profiles.iter().position(|profile| profile.name == name))
+        .unwrap_or(0);
+
+    let mut command = 0;
+    if let Ok(menu) = CreatePopupMenu() {
+        AppendMenuW(menu, MF_STRING | MF_GRAYED, 0, w!("Open profile"));
+        AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null());
+        // Command IDs start at 1; 0 means the menu was dismissed
+        append_profile_items(menu, &state.config, |i| i + 1, true);
+        SetMenuDefaultItem(menu, (last_choice + 1) as u32, 0);
+
+        let rect = get_content_rect(hwnd).unwrap_or_default();
+        let mut center = POINT {
+            x: (rect.left + rect.right) / 2,
+            y: (rect.top + rect.bottom) / 2,
+        };
+        ClientToScreen(hwnd, &mut center);
+        SetForegroundWindow(hwnd);
+        command = TrackPopupMenu(
+            menu,
+            TPM_RETURNCMD | TPM_NONOTIFY | TPM_CENTERALIGN | TPM_VCENTERALIGN,
+            center.x,
+            center.y,
+            0,
+            hwnd,
+            None,
+        )
+        .0 as usize;
+        DestroyMenu(menu);
+    }
 
-        // Clean up
-        SelectObject(mem_dc, old_bitmap);
-        DeleteObject(HGDIOBJ(mem_bitmap.0));
-        DeleteDC(mem_dc);
+    let index = match command.checked_sub(1).filter(|&i| i < profiles.len()) {
+        Some(index) => {
+            if let Err(e) = sessions::save_startup_profile(&profiles[index].name) {
+                eprintln!("Failed to remember the startup profile: {}", e);
+            }
+            index
+        }
+        None => last_choice,
+    };
+    // A tab may have been opened while the chooser was shown
+    if state.tab_manager.is_empty() {
+        let profile = &profiles[index];
+        if let Some(monitor) = profile.monitor.as_ref().and_then(find_monitor) {
+            move_to_monitor(hwnd, state, &monitor);
+        }
+        create_profile_tab(hwnd, state, profile, index, &profile.working_directory, &[]);
     }
 }
 
 /// Window procedure callback
-#[allow(unused_must_use)]
 unsafe extern "system" fn window_proc(
     hwnd: HWND,
     msg: u32,
     wparam: WPARAM,
     lparam: LPARAM,
 ) -> LRESULT {
+    if let Some(cell) = window_cell(hwnd)
+        && cell.state.try_borrow_mut().is_err()
+    {
+        if is_deferrable(msg, wparam) {
+            return defer_message(hwnd, cell, msg, wparam, lparam);
+        }
+        if msg == WM_PAINT {
+            // Painting an empty tab bar would flash: validate and repaint later
+            cell.deferred
+                .borrow_mut()
+                .push_back(Deferred::Run(Box::new(|hwnd, _| {
+                    let _ = InvalidateRect(hwnd, None, false);
+                })));
+            return DefWindowProcW(hwnd, msg, wparam, lparam);
+        }
+    }
+    let result = handle_message(hwnd, msg, wparam, lparam);
+    run_deferred(hwnd);
+    result
+}
+
+/// Handle a window message; see `WindowCell` for when the state is unavailable
+#[allow(unused_must_use)]
+unsafe fn handle_message(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
     match msg {
         WM_CREATE => {
             // Get background color and config from thread-local storage
@@ -2281,35 +5777,99 @@ unsafe extern "system" fn window_proc(
                 .with(|c| c.borrow_mut().take())
                 .unwrap_or_default();
 
-            // Create tab manager and initial tab
+            let session_name = INITIAL_SESSION.with(|c| c.borrow_mut().take());
+            let open_request = INITIAL_OPEN_REQUEST.with(|c| c.borrow_mut().take());
+
+            // Put the window back where it was on the connected displays
+            let window_layouts = WindowLayouts::load();
+            let monitor_setup = monitors::setup_key(&monitors::enumerate());
+            if config.remember_window_layout
+                && let Some(layout) = window_layouts.get(&monitor_setup)
+            {
+                apply_window_layout(hwnd, layout);
+            }
+
+            // Open on the display chosen for the first tab's profile, before the
+            // start state maximizes it there
+            let first_profile = open_request
+                .as_ref()
+                .and_then(|request| request.profile())
+                .and_then(|name| config.find_profile_by_name(name))
+                .map_or_else(|| config.default_profile(), |(_, profile)| profile);
+            if let Some(monitor) = config.monitor_for(first_profile).and_then(find_monitor) {
+                move_wrapper_to_monitor(hwnd, &monitor);
+            }
+
+            // Take the start state before the first tabs are sized, so a maximized
+            // wrapper starts Neovide at the maximized content size
+            match config.start_state {
+                StartState::Normal => {}
+                StartState::Maximized => {
+                    ShowWindow(hwnd, SW_SHOWMAXIMIZED);
+                }
+                StartState::Minimized => {
+                    ShowWindow(hwnd, SW_SHOWMINNOACTIVE);
+                }
+            }
+
+            // Decode the profile icons in the background before the first paint needs them
+            set_icon_dpi(GetDpiForWindow(hwnd));
+            preload_profile_icons(hwnd, &config);
+
+            // Ask for the first tab's profile only when there is a choice and
+            // nothing else decides what opens
+            let session = session_name.as_deref().and_then(load_named_session);
+            let profile_prompt = config.prompt_profile_on_start
+                && config.profiles.len() > 1
+                && open_request.is_none()
+                && session.is_none();
+
+            // Create tab manager and initial tabs
             let mut tab_manager = TabManager::new();
+            tab_manager.set_max_concurrent_spawns(config.max_concurrent_spawns);
+            tab_manager.set_title_fallback(config.title_fallback.clone());
+            tab_manager.set_attention_forwarding(config.tab_attention);
+            tab_manager.set_project_icons(config.project_icons);
+            tab_manager.set_git_status(config.git_status);
+            tab_manager.set_reduce_motion(reduce_motion(&config));
 
             // Get content area dimensions (below title bar)
             if let Ok(rect) = get_content_rect(hwnd) {
                 let width = (rect.right - rect.left) as u32;
                 let height = (rect.bottom - rect.top) as u32;
 
-                // Create initial tab with Neovide process using default profile
-                let default_profile = config.default_profile();
-                match tab_manager.create_tab(width, height, hwnd, default_profile, 0) {
-                    Ok(_) => {}
-                    Err(e) => {
-                        let error_msg = format!("Failed to launch Neovide: {}", e);
-                        show_error(&error_msg, "Error: Failed to Launch Neovide");
-                    }
+                // Restore the named session's tabs, or create the initial tab
+                // using the default profile (or the one picked in the profile
+                // chooser, which is shown once the window is up)
+                if profile_prompt {
+                    PostMessageW(hwnd, WM_PROFILE_PROMPT, WPARAM(0), LPARAM(0));
+                } else if let Some(e) = open_session_tabs(
+                    &mut tab_manager,
+                    &config,
+                    session.as_ref(),
+                    open_request.as_ref(),
+                    width,
+                    height,
+                    hwnd,
+                ) {
+                    let error_msg = format!("Failed to launch Neovide: {}", e);
+                    show_error(&error_msg, "Error: Failed to Launch Neovide");
                 }
             }
+            update_window_title(hwnd, &config.window_title, session_name.as_deref());
 
-            // Register global hotkeys
-            let mut registered_hotkeys = Vec::new();
+            // Folders opened from Explorer arrive as WM_COPYDATA from an unelevated
+            // process, which UIPI would drop when the wrapper runs as administrator
+            ChangeWindowMessageFilterEx(hwnd, WM_COPYDATA, MSGFLT_ALLOW, None);
 
-            // Register tab hotkeys
-            let tab_hotkey_ids = hotkeys::register_tab_hotkeys(hwnd, &config.hotkeys.tab);
-            registered_hotkeys.extend(tab_hotkey_ids);
+            // Register global hotkeys
+            let registered_hotkeys = register_hotkeys(hwnd, &config);
 
-            // Register profile hotkeys
-            let profile_hotkey_ids = hotkeys::register_profile_hotkeys(hwnd, &config.profiles);
-            registered_hotkeys.extend(profile_hotkey_ids);
+            // Follow foreground changes, plus external moves in FancyZones and
+            // tiling window manager modes
+            let mut win_event_hooks = WinEventHooks::install(hwnd);
+            win_event_hooks.set_location_tracking(config.tracks_window_locations());
+            tiling::apply_ignore_rules(config.tiling_wm);
 
             // Start the config file watcher for hot-reload
             let config_watcher = ConfigWatcher::start(hwnd);
@@ -2317,10 +5877,12 @@ unsafe extern "system" fn window_proc(
                 eprintln!("Warning: Failed to start config file watcher");
             }
 
-            let state = Box::new(WindowState {
-                tab_manager,
+            let tab_numbers = TabNumberBadges::new(&config);
+            let memory_warning = MemoryWarning::new(config.memory_warning_threshold);
+            let mut state = WindowState {
                 config,
                 in_size_move: false,
+                position_update_pending: false,
                 background_color,
                 hovered_button: HoveredButton::None,
                 hovered_tab: HoveredTab::None,
@@ -2330,12 +5892,77 @@ unsafe extern "system" fn window_proc(
                 overflow_hwnd: None,
                 registered_hotkeys,
                 config_watcher,
+                loading_timer_active: true,
+                resize_grips: ResizeGrips::create(hwnd, content_inset()),
+                win_event_hooks,
+                tab_tooltip: create_tab_tooltip(hwnd),
+                tooltip_tab: None,
+                tab_previews: HashMap::new(),
+                last_preview_capture: None,
+                preview_tab: None,
+                preview_hwnd: None,
+                task_manager_hwnd: None,
+                output_view: None,
+                session_autosave: Autosave::new(tab_manager.session_generation(), Instant::now()),
+                tab_manager,
+                session_name,
+                pending_session: None,
+                ending_session: false,
+                profile_prompt_pending: profile_prompt,
+                tab_rename: None,
+                titlebar_focus: None,
+                // Whoever started us (e.g. a terminal) until another app is used
+                previous_foreground: Some(GetForegroundWindow())
+                    .filter(|foreground| is_restorable_foreground(*foreground)),
+                update: None,
+                clock_text: String::new(),
+                memory_warning,
+                memory_text: String::new(),
+                debug_hud: INITIAL_DEBUG_HUD.with(|c| c.get()).then(DebugHud::new),
+                tab_numbers,
+                tab_switcher: None,
+                always_on_top: false,
+                touch: None,
+                wheel_swipe: WheelSwipe::default(),
+                title_boost: false,
+                polling_paused: false,
+                auto_hide: None,
+                window_layouts,
+                monitor_setup: Some(monitor_setup),
+            };
+            let check_updates = state.config.check_updates;
+            let poll_interval = state.config.advanced.process_poll_interval_ms;
+            update_widgets(hwnd, &mut state);
+            // Watch the tab hotkey modifiers if the numbers show while they are held
+            state.tab_numbers.update_timer(hwnd);
+            update_system_menu(hwnd, &state);
+            let cell = Box::new(WindowCell {
+                state: RefCell::new(state),
+                deferred: RefCell::new(VecDeque::new()),
+                tooltip_text: RefCell::new(Vec::new()),
+                shutdown_session: RefCell::new(None),
             });
-            let state_ptr = Box::into_raw(state);
-            SetWindowLongPtrW(hwnd, GWLP_USERDATA, state_ptr as isize);
+            SetWindowLongPtrW(hwnd, GWLP_USERDATA, Box::into_raw(cell) as isize);
 
             // Start the process polling timer to detect when Neovide processes exit
-            SetTimer(hwnd, PROCESS_POLL_TIMER_ID, PROCESS_POLL_INTERVAL_MS, None);
+            SetTimer(hwnd, PROCESS_POLL_TIMER_ID, poll_interval, None);
+
+            // Pause it while the workstation is locked
+            if let Err(e) = WTSRegisterSessionNotification(hwnd, NOTIFY_FOR_THIS_SESSION) {
+                eprintln!("Failed to register for session notifications: {}", e);
+            }
+
+            // Animate the startup indicator until the first Neovide window is ready
+            SetTimer(hwnd, LOADING_TIMER_ID, LOADING_FRAME_MS, None);
+
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
+                set_auto_hide(hwnd, state, state.config.auto_hide_titlebar);
+            }
+
+            if check_updates {
+                updates::check_in_background(hwnd);
+            }
 
             LRESULT(0)
         }
@@ -2356,6 +5983,12 @@ unsafe extern "system" fn window_proc(
                         (*params).rgrc[0].top += frame_y + padding;
                         (*params).rgrc[0].right -= frame_x + padding;
                         (*params).rgrc[0].bottom -= frame_y + padding;
+
+                        // Leave the strip that reveals an auto-hide taskbar uncovered
+                        leave_autohide_taskbar_edges(
+                            &mut (*params).rgrc[0],
+                            &autohide_taskbar_edges(hwnd),
+                        );
                     }
                     // When not maximized, don't adjust - let client area extend to
                     // the full window bounds. We handle resize hit-testing ourselves.
@@ -2389,43 +6022,22 @@ unsafe extern "system" fn window_proc(
                     let client_width = client_rect.right;
                     let client_height = client_rect.bottom;
 
-                    // Check resize borders first (when not maximized)
+                    // Check resize borders first (when not maximized). Below the title
+                    // bar the whole content inset acts as resize border.
                     if !IsZoomed(hwnd).as_bool() {
-                        let border_width = 8;
-
-                        // Top edge
-                        if pt.y <= border_width {
-                            if pt.x <= border_width {
-                                return LRESULT(HTTOPLEFT as isize);
-                            } else if pt.x >= client_width - border_width {
-                                return LRESULT(HTTOPRIGHT as isize);
-                            }
-                            return LRESULT(HTTOP as isize);
-                        }
-
-                        // Bottom edge
-                        if pt.y >= client_height - border_width {
-                            if pt.x <= border_width {
-                                return LRESULT(HTBOTTOMLEFT as isize);
-                            } else if pt.x >= client_width - border_width {
-                                return LRESULT(HTBOTTOMRIGHT as isize);
-                            }
-                            return LRESULT(HTBOTTOM as isize);
-                        }
-
-                        // Left edge
-                        if pt.x <= border_width {
-                            return LRESULT(HTLEFT as isize);
-                        }
-
-                        // Right edge
-                        if pt.x >= client_width - border_width {
-                            return LRESULT(HTRIGHT as isize);
+                        if let Some(code) = grips::resize_hit_test(
+                            pt.x,
+                            pt.y,
+                            client_width,
+                            client_height,
+                            content_inset(),
+                        ) {
+                            return LRESULT(code as isize);
                         }
                     }
 
                     // Check if in title bar area
-                    if pt.y >= 0 && pt.y < TITLEBAR_HEIGHT {
+                    if pt.y >= 0 && pt.y < titlebar_height() {
                         // Check window control buttons first - return HTCLIENT so we handle
                         // them ourselves (prevents Windows from drawing default buttons)
                         let button = hit_test_buttons(pt.x, pt.y, client_width);
@@ -2437,10 +6049,14 @@ unsafe extern "system" fn window_proc(
                             }
                             HoveredButton::None => {
                                 // Check tab bar area
-                                let state_ptr =
-                                    GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut WindowState;
-                                if !state_ptr.is_null() {
-                                    let state = &*state_ptr;
+                                if let Some(guard) = window_state(hwnd) {
+                                    let state = &*guard;
+                                    if let Some(
+                                        Widget::Memory | Widget::Update | Widget::Settings,
+                                    ) = hit_test_widgets(pt.x, pt.y, client_width)
+                                    {
+                                        return LRESULT(HTCLIENT as isize);
+                                    }
                                     let tab_hit = hit_test_tab_bar(
                                         pt.x,
                                         pt.y,
@@ -2450,13 +6066,20 @@ unsafe extern "system" fn window_proc(
                                     match tab_hit {
                                         TabHitResult::Tab(_)
                                         | TabHitResult::TabClose(_)
+                                        | TabHitResult::TabRetry(_)
                                         | TabHitResult::NewTabButton
                                         | TabHitResult::ProfileDropdown
-                                        | TabHitResult::DropdownItem(_)
                                         | TabHitResult::OverflowButton => {
                                             // These are handled as client area clicks
                                             return LRESULT(HTCLIENT as isize);
                                         }
+                                        // While closed tabs leave empty slots the title
+                                        // bar stays client area, so leaving it is noticed
+                                        TabHitResult::Caption | TabHitResult::None
+                                            if layout::tabs_frozen() =>
+                                        {
+                                            return LRESULT(HTCLIENT as isize);
+                                        }
                                         TabHitResult::Caption | TabHitResult::None => {
                                             return LRESULT(HTCAPTION as isize);
                                         }
@@ -2489,16 +6112,18 @@ unsafe extern "system" fn window_proc(
 
         WM_MOUSELEAVE => {
             // Handle client area mouse leave
-            let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut WindowState;
-            if !state_ptr.is_null() {
-                let state = &mut *state_ptr;
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
                 state.tracking_mouse = false;
 
-                // Clear hover states
-                let needs_repaint = state.hovered_tab != HoveredTab::None
+                // Clear hover states; the tabs close the slots of closed tabs
+                let needs_repaint = layout::unfreeze_tabs()
+                    || state.hovered_tab != HoveredTab::None
                     || state.hovered_button != HoveredButton::None;
                 state.hovered_tab = HoveredTab::None;
                 state.hovered_button = HoveredButton::None;
+                set_tooltip_tab(hwnd, state, None, 0);
+                set_preview_tab(hwnd, state, None);
 
                 if needs_repaint {
                     let mut client_rect = RECT::default();
@@ -2507,7 +6132,7 @@ unsafe extern "system" fn window_proc(
                             left: 0,
                             top: 0,
                             right: client_rect.right,
-                            bottom: TITLEBAR_HEIGHT,
+                            bottom: titlebar_height(),
                         };
                         InvalidateRect(hwnd, Some(&titlebar_rect), false);
                     }
@@ -2517,13 +6142,18 @@ unsafe extern "system" fn window_proc(
         }
 
         WM_PAINT => {
-            let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut WindowState;
-
             let mut ps = PAINTSTRUCT::default();
             BeginPaint(hwnd, &mut ps);
 
-            if !state_ptr.is_null() {
-                let state = &*state_ptr;
+            if let Some(mut state) = window_state(hwnd) {
+                let paint_started = Instant::now();
+                let hud_lines = state.debug_hud.as_ref().map(|hud| {
+                    let selected = state.tab_manager.selected_index();
+                    let tabs: Vec<_> = (0..state.tab_manager.count())
+                        .filter_map(|i| Some((state.tab_manager.tab_state(i)?, i == selected)))
+                        .collect();
+                    hud.lines(&tabs)
+                });
                 paint_titlebar(
                     hwnd,
                     &ps,
@@ -2533,10 +6163,21 @@ unsafe extern "system" fn window_proc(
                     &state.tab_manager,
                     state.dropdown_state,
                     &state.config.profiles,
+                    state.session_name.as_deref(),
+                    &state.clock_text,
+                    &state.memory_text,
+                    hud_lines.as_ref().map(|lines| &lines[..]),
+                    state
+                        .titlebar_focus
+                        .and_then(|focus| titlebar_focus_rect(hwnd, focus)),
+                    state.tab_numbers.shown(),
                 );
+                if let Some(hud) = state.debug_hud.as_mut() {
+                    hud.record_paint(paint_started.elapsed());
+                }
             } else {
                 // Fallback with empty tab manager
-                let empty_manager = TabManager::new();
+                let empty_manager = TabManager::<NeovideProcess>::new();
                 let empty_profiles: Vec<Profile> = vec![];
                 paint_titlebar(
                     hwnd,
@@ -2547,6 +6188,12 @@ unsafe extern "system" fn window_proc(
                     &empty_manager,
                     DropdownState::Closed,
                     &empty_profiles,
+                    None,
+                    "",
+                    "",
+                    None,
+                    None,
+                    None,
                 );
             }
 
@@ -2561,31 +6208,30 @@ unsafe extern "system" fn window_proc(
 
         WM_ENTERSIZEMOVE => {
             // User started dragging or resizing - set flag and cancel any pending timers
-            let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut WindowState;
-            if !state_ptr.is_null() {
-                let state = &mut *state_ptr;
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
                 state.in_size_move = true;
                 KillTimer(hwnd, FOREGROUND_TIMER_ID).ok();
                 KillTimer(hwnd, POSITION_UPDATE_TIMER_ID).ok();
+                state.position_update_pending = false;
             }
             DefWindowProcW(hwnd, msg, wparam, lparam)
         }
 
         WM_EXITSIZEMOVE => {
-            // User finished dragging or resizing - now reposition all Neovide windows
-            // and bring the selected one to the foreground
-            let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut WindowState;
-            if !state_ptr.is_null() {
-                let state = &mut *state_ptr;
+            // User finished dragging or resizing - now reposition the selected Neovide
+            // window and bring it to the foreground. Hidden tabs are moved when selected.
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
                 state.in_size_move = false;
-                // Update positions for all tabs (so switching tabs later works correctly)
+                remember_window_layout(hwnd, state);
                 state
                     .tab_manager
-                    .update_all_positions(hwnd, TITLEBAR_HEIGHT);
+                    .update_selected_position(hwnd, content_top());
                 // Activate the selected tab (show + bring to foreground)
                 state
                     .tab_manager
-                    .activate_and_foreground_selected(hwnd, TITLEBAR_HEIGHT);
+                    .activate_and_foreground_selected(hwnd, content_top());
             }
             DefWindowProcW(hwnd, msg, wparam, lparam)
         }
@@ -2594,19 +6240,40 @@ unsafe extern "system" fn window_proc(
             // Bring selected tab's Neovide to foreground when wrapper is activated
             // Use a short delay to allow WM_ENTERSIZEMOVE to fire first if this is a drag
             let activated = (wparam.0 & 0xFFFF) != 0; // WA_INACTIVE = 0
-            if activated {
-                let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut WindowState;
-                if !state_ptr.is_null() {
-                    let state = &*state_ptr;
-                    if state.tab_manager.is_selected_ready() {
-                        // Schedule delayed foreground activation
-                        SetTimer(hwnd, FOREGROUND_TIMER_ID, FOREGROUND_DELAY_MS, None);
+            // E.g. closing a dialog opened by a handler activates the wrapper again
+            // while that handler still has the state
+            with_state_or_defer(hwnd, move |hwnd, state| {
+                if activated {
+                    // While a tab is being renamed or the titlebar has the focus ring,
+                    // the keyboard stays with the wrapper
+                    if state.tab_manager.is_selected_ready()
+                        && state.tab_rename.is_none()
+                        && state.titlebar_focus.is_none()
+                    {
+                        if state.tab_manager.reduce_motion() || state.config.tiling_wm.is_enabled()
+                        {
+                            // No delay without animations, or when a tiling window
+                            // manager (not a drag) activated the wrapper: bring it to
+                            // the front now
+                            state
+                                .tab_manager
+                                .activate_and_foreground_selected(hwnd, content_top());
+                        } else {
+                            // Schedule delayed foreground activation
+                            SetTimer(
+                                hwnd,
+                                FOREGROUND_TIMER_ID,
+                                state.config.advanced.foreground_delay_ms,
+                                None,
+                            );
+                        }
                     }
+                } else {
+                    // Deactivating - cancel any pending foreground timer
+                    KillTimer(hwnd, FOREGROUND_TIMER_ID).ok();
+                    leave_titlebar_focus(hwnd, state);
                 }
-            } else {
-                // Deactivating - cancel any pending foreground timer
-                KillTimer(hwnd, FOREGROUND_TIMER_ID).ok();
-            }
+            });
             DefWindowProcW(hwnd, msg, wparam, lparam)
         }
 
@@ -2615,34 +6282,118 @@ unsafe extern "system" fn window_proc(
                 KillTimer(hwnd, FOREGROUND_TIMER_ID).ok();
 
                 // Now check if we're in a size/move operation
-                let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut WindowState;
-                if !state_ptr.is_null() {
-                    let state = &*state_ptr;
-                    if !state.in_size_move {
+                if let Some(guard) = window_state(hwnd) {
+                    let state = &*guard;
+                    if !state.in_size_move
+                        && state.tab_rename.is_none()
+                        && state.titlebar_focus.is_none()
+                    {
                         // Use activate which checks position first, then brings to foreground
                         state
                             .tab_manager
-                            .activate_and_foreground_selected(hwnd, TITLEBAR_HEIGHT);
+                            .activate_and_foreground_selected(hwnd, content_top());
                     }
                 }
             } else if wparam.0 == POSITION_UPDATE_TIMER_ID {
                 KillTimer(hwnd, POSITION_UPDATE_TIMER_ID).ok();
 
                 // Deferred position update for external tools (e.g., FancyZones)
-                let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut WindowState;
-                if !state_ptr.is_null() {
-                    let state = &*state_ptr;
-                    if !state.in_size_move {
+                if let Some(mut guard) = window_state(hwnd) {
+                    let state = &mut *guard;
+                    state.position_update_pending = false;
+                    // A minimized wrapper has no content area to move Neovide into
+                    if !state.in_size_move && !IsIconic(hwnd).as_bool() {
                         state
                             .tab_manager
-                            .update_all_positions(hwnd, TITLEBAR_HEIGHT);
+                            .update_selected_position(hwnd, content_top());
+                    }
+                }
+            } else if wparam.0 == LOADING_TIMER_ID {
+                // Repaint the startup indicator; stop once the selected Neovide is ready
+                if let Some(mut guard) = window_state(hwnd) {
+                    let state = &mut *guard;
+                    if state.tab_manager.selected_startup_elapsed().is_none() {
+                        KillTimer(hwnd, LOADING_TIMER_ID).ok();
+                        state.loading_timer_active = false;
+                    }
+                    if let Ok(content_rect) = get_content_rect(hwnd) {
+                        InvalidateRect(hwnd, Some(&content_rect), false);
+                    }
+                }
+            } else if wparam.0 == TAB_SWITCHER_TIMER_ID {
+                if let Some(mut guard) = window_state(hwnd) {
+                    let state = &mut *guard;
+                    let released = state
+                        .tab_switcher
+                        .as_ref()
+                        .is_none_or(|switcher| !hotkeys::modifiers_held(switcher.modifiers));
+                    if released {
+                        commit_tab_switcher(hwnd, state);
+                    }
+                } else {
+                    KillTimer(hwnd, TAB_SWITCHER_TIMER_ID).ok();
+                }
+            } else if wparam.0 == TITLE_BOOST_TIMER_ID {
+                if let Some(mut guard) = window_state(hwnd) {
+                    let state = &mut *guard;
+                    if state.tab_manager.update_selected_tab_title() {
+                        InvalidateRect(hwnd, None, false);
+                    }
+                    update_title_boost(hwnd, state);
+                }
+            } else if wparam.0 == MONITOR_SETUP_TIMER_ID {
+                KillTimer(hwnd, MONITOR_SETUP_TIMER_ID).ok();
+                if let Some(mut guard) = window_state(hwnd) {
+                    enter_monitor_setup(hwnd, &mut guard);
+                }
+            } else if wparam.0 == AUTO_HIDE_TIMER_ID {
+                if let Some(mut guard) = window_state(hwnd) {
+                    update_auto_hide(hwnd, &mut guard);
+                }
+            } else if wparam.0 == PREVIEW_TIMER_ID {
+                KillTimer(hwnd, PREVIEW_TIMER_ID).ok();
+                if let Some(mut guard) = window_state(hwnd) {
+                    let state = &mut *guard;
+                    show_tab_preview(hwnd, state);
+                }
+            } else if wparam.0 == TAB_NUMBERS_TIMER_ID {
+                if let Some(mut state) = window_state(hwnd)
+                    && state.tab_numbers.poll()
+                {
+                    let mut client_rect = RECT::default();
+                    if GetClientRect(hwnd, &mut client_rect).is_ok() {
+                        let titlebar_rect = RECT {
+                            left: 0,
+                            top: 0,
+                            right: client_rect.right,
+                            bottom: titlebar_height(),
+                        };
+                        InvalidateRect(hwnd, Some(&titlebar_rect), false);
+                    }
+                }
+            } else if wparam.0 == ATTENTION_TIMER_ID {
+                // Animate the attention pulse; stop once no tab is pulsing
+                if let Some(guard) = window_state(hwnd) {
+                    let state = &*guard;
+                    if !state.tab_manager.has_attention_pulse() {
+                        KillTimer(hwnd, ATTENTION_TIMER_ID).ok();
+                    }
+                    let mut client_rect = RECT::default();
+                    if GetClientRect(hwnd, &mut client_rect).is_ok() {
+                        let titlebar_rect = RECT {
+                            left: 0,
+                            top: 0,
+                            right: client_rect.right,
+                            bottom: titlebar_height(),
+                        };
+                        InvalidateRect(hwnd, Some(&titlebar_rect), false);
                     }
                 }
             } else if wparam.0 == PROCESS_POLL_TIMER_ID {
                 // Poll for exited Neovide processes and refresh tab title
-                let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut WindowState;
-                if !state_ptr.is_null() {
-                    let state = &mut *state_ptr;
+                let poll_started = Instant::now();
+                if let Some(mut guard) = window_state(hwnd) {
+                    let state = &mut *guard;
                     let mut should_close = false;
                     let mut needs_repaint = false;
 
@@ -2653,33 +6404,173 @@ unsafe extern "system" fn window_proc(
                         // Remove exited tabs (indices are in reverse order for safe removal)
                         for index in exited_indices {
                             if state.tab_manager.remove_exited_tab(index) {
-                                // This was the last tab
-                                should_close = true;
+                                // This was the last tab - unless a session switch
+                                // was waiting for it, which opens the new tabs now
+                                should_close = !open_pending_session(hwnd, state);
                                 break;
                             }
+                            // Keep the other tabs where they are while the pointer
+                            // may be about to close the next one
+                            if pointer_over_title_bar(hwnd) {
+                                layout::freeze_closed_tab(index, state.tab_manager.count());
+                            }
                             needs_repaint = true;
                         }
 
                         // If there are more tabs pending close, continue the sequence
                         // This activates the next tab and sends WM_CLOSE to it
                         if !should_close && state.tab_manager.has_pending_close() {
-                            state.tab_manager.activate_selected(hwnd, TITLEBAR_HEIGHT);
+                            state.tab_manager.activate_selected(hwnd, content_top());
                             state.tab_manager.continue_close_sequence();
                         }
                     }
 
-                    // Periodically refresh the selected tab's title (for %t token updates)
-                    if !should_close && state.tab_manager.update_selected_tab_title() {
+                    // Tabs whose Neovide refused to close wait for the user's decision
+                    if !should_close {
+                        resolve_blocked_closes(hwnd, state);
+                    }
+
+                    // Cancelling a save prompt keeps the app (and the current session) open
+                    if !should_close && state.tab_manager.handle_cancelled_closes() {
+                        state.pending_session = None;
+                        end_shutdown_block(hwnd, state);
+                        InvalidateRect(hwnd, None, false);
+                    }
+
+                    // Start queued Neovide processes if earlier ones exited while starting
+                    if !should_close && state.tab_manager.has_queued_spawns() {
+                        launch_queued_tabs(hwnd, state);
+                    }
+
+                    // Snapshot the active session now and then so a crash does not lose it
+                    if !should_close {
+                        autosave_session(state);
+                        update_shutdown_session(hwnd, state);
+                    }
+
+                    // Periodically refresh the selected tab's title (for %t token
+                    // updates), unless nobody is looking at it
+                    if !should_close
+                        && title_in_view(hwnd, state)
+                        && state.tab_manager.update_selected_tab_title()
+                    {
+                        needs_repaint = true;
+                    }
+
+                    // Follow the title closely for a while after a tab switch
+                    if !should_close {
+                        update_title_boost(hwnd, state);
+                    }
+
+                    // Show what Neovide wrote since the last poll
+                    if let Some(view) = state.output_view.as_mut() {
+                        view.refresh();
+                    }
+
+                    // Pick up finished git status checks and start due ones
+                    if !should_close && state.tab_manager.update_git_statuses() {
                         needs_repaint = true;
                     }
 
+                    // Keep the clock widget current
+                    if state.config.widgets.clock {
+                        let time = clock_text();
+                        if time != state.clock_text {
+                            state.clock_text = time;
+                            needs_repaint = true;
+                        }
+                    }
+
+                    // Tabs that were selected or opened in the meantime pause or
+                    // resume their animations
+                    if !should_close {
+                        state.tab_manager.update_animation_pauses();
+                    }
+
+                    // Capture the selected tab for the previews of background tabs
+                    if !should_close {
+                        update_tab_previews(hwnd, state);
+                    }
+
+                    // Windows a Neovide opened besides its main one follow their tab
+                    if !should_close {
+                        state.tab_manager.track_extra_windows();
+                    }
+
+                    // Badge tabs whose Neovide window stopped (or resumed) responding
+                    let responsiveness_changed =
+                        !should_close && state.tab_manager.update_responsiveness();
+                    if responsiveness_changed {
+                        InvalidateRect(hwnd, None, false);
+                    }
+
+                    // Sample per-tab memory and CPU usage for the tooltip and task manager
+                    if !should_close
+                        && (state
+                            .tab_manager
+                            .update_resource_usage(process::process_table)
+                            || needs_repaint
+                            || responsiveness_changed)
+                    {
+                        refresh_usage_views(hwnd, state);
+                    }
+
+                    // Offer to hibernate tabs while they use too much memory
+                    if !should_close {
+                        let total = state.tab_manager.total_memory();
+                        if state.memory_warning.update(total) {
+                            update_widgets(hwnd, state);
+                        }
+                        let text = format_memory(total);
+                        if state.memory_warning.is_visible() && text != state.memory_text {
+                            state.memory_text = text;
+                            InvalidateRect(hwnd, None, false);
+                        }
+                    }
+
+                    // Suspend tabs that have been in the background for too long
+                    if let Some(idle) = state.config.suspend_background_after {
+                        if !should_close && state.tab_manager.suspend_idle_background_tabs(idle) {
+                            InvalidateRect(hwnd, None, false);
+                        }
+                    }
+
+                    // A newly selected tab may still be starting - show the startup indicator
+                    if !should_close
+                        && !state.loading_timer_active
+                        && state.tab_manager.selected_startup_elapsed().is_some()
+                    {
+                        SetTimer(hwnd, LOADING_TIMER_ID, LOADING_FRAME_MS, None);
+                        state.loading_timer_active = true;
+                    }
+
+                    // Refresh the debug HUD's numbers along with the poll
+                    if let Some(hud) = state.debug_hud.as_mut() {
+                        hud.record_poll(
+                            poll_started,
+                            Duration::from_millis(
+                                state.config.advanced.process_poll_interval_ms as u64,
+                            ),
+                        );
+                        let mut client_rect = RECT::default();
+                        if GetClientRect(hwnd, &mut client_rect).is_ok() {
+                            let titlebar_rect = RECT {
+                                left: 0,
+                                top: 0,
+                                right: client_rect.right,
+                                bottom: titlebar_height(),
+                            };
+                            InvalidateRect(hwnd, Some(&titlebar_rect), false);
+                        }
+                    }
+
                     if should_close {
                         // Last tab's process exited - close the application
                         KillTimer(hwnd, PROCESS_POLL_TIMER_ID).ok();
                         PostMessageW(hwnd, WM_CLOSE, WPARAM(0), LPARAM(0)).ok();
                     } else if needs_repaint {
                         // Activate the newly selected tab and repaint
-                        state.tab_manager.activate_selected(hwnd, TITLEBAR_HEIGHT);
+                        state.tab_manager.activate_selected(hwnd, content_top());
                         InvalidateRect(hwnd, None, false);
                     }
                 }
@@ -2690,21 +6581,17 @@ unsafe extern "system" fn window_proc(
         WM_WINDOWPOSCHANGED => {
             // Handle programmatic window position/size changes (e.g., from FancyZones)
             // Only schedule update if we're not in a manual size/move operation
-            // Use a timer to debounce and avoid interfering with resize operations
-            let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut WindowState;
-            if !state_ptr.is_null() {
-                let state = &*state_ptr;
-                if !state.in_size_move && !state.tab_manager.is_empty() {
-                    // Schedule a deferred position update - this will be cancelled
-                    // if more WM_WINDOWPOSCHANGED messages arrive, effectively debouncing
-                    SetTimer(
-                        hwnd,
-                        POSITION_UPDATE_TIMER_ID,
-                        POSITION_UPDATE_DELAY_MS,
-                        None,
-                    );
+            // Z-order and activation changes don't move the content area
+            let pos = &*(lparam.0 as *const WINDOWPOS);
+            let moved = !(pos.flags.contains(SWP_NOMOVE) && pos.flags.contains(SWP_NOSIZE));
+            // Handlers move the window too (e.g. restoring it), while they have the state
+            with_state_or_defer(hwnd, move |hwnd, state| {
+                // Keep the resize grips glued to the window edges
+                state.resize_grips.update();
+                if moved && !state.in_size_move && !state.tab_manager.is_empty() {
+                    schedule_position_update(hwnd, state);
                 }
-            }
+            });
             // Must call DefWindowProcW to get WM_SIZE and WM_MOVE messages
             DefWindowProcW(hwnd, msg, wparam, lparam)
         }
@@ -2712,6 +6599,23 @@ unsafe extern "system" fn window_proc(
         WM_SIZE => {
             // Invalidate the window to repaint title bar (maximize/restore button may change)
             InvalidateRect(hwnd, None, false);
+            // Remember a window that was maximized or restored; moves and resizes
+            // are remembered when they end
+            if matches!(wparam.0 as u32, SIZE_MAXIMIZED | SIZE_RESTORED)
+                && let Some(mut guard) = window_state(hwnd)
+            {
+                let state = &mut *guard;
+                let remembered = state
+                    .monitor_setup
+                    .as_deref()
+                    .and_then(|setup| state.window_layouts.get(setup));
+                if !state.in_size_move
+                    && remembered.map(|layout| layout.maximized)
+                        != Some(wparam.0 as u32 == SIZE_MAXIMIZED)
+                {
+                    remember_window_layout(hwnd, state);
+                }
+            }
             DefWindowProcW(hwnd, msg, wparam, lparam)
         }
 
@@ -2725,21 +6629,78 @@ unsafe extern "system" fn window_proc(
             LRESULT(0)
         }
 
+        WM_SYSCOMMAND => {
+            // Wrapper actions added to the window menu (see update_system_menu)
+            if let Some(mut state) = window_state(hwnd)
+                && run_system_menu_command(hwnd, &mut state, wparam.0 & 0xFFF0)
+            {
+                return LRESULT(0);
+            }
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        }
+
+        WM_QUERYENDSESSION => {
+            // Windows is shutting down or logging off: close the tabs gracefully,
+            // so unsaved buffers get their save prompts, and hold the shutdown
+            // until they are gone. Critical shutdowns can't wait.
+            let critical = lparam.0 as u32 & ENDSESSION_CRITICAL != 0;
+            let Some(mut guard) = window_state(hwnd) else {
+                // A handler up the stack has the state (e.g. a menu or dialog in
+                // its modal loop): hold the shutdown and start closing the tabs
+                // once it returns. A critical shutdown kills the process before
+                // that, so save the session as of the last poll now.
+                if critical {
+                    save_shutdown_session(hwnd);
+                    return LRESULT(1);
+                }
+                with_state_or_defer(hwnd, |hwnd, state| begin_ending_session(hwnd, state));
+                return LRESULT(0);
+            };
+            let state = &mut *guard;
+            if critical || state.tab_manager.is_empty() {
+                save_active_session(state);
+                return LRESULT(1);
+            }
+            begin_ending_session(hwnd, state);
+            LRESULT(0)
+        }
+
+        WM_ENDSESSION => {
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
+                if wparam.0 != 0 {
+                    // The session ends now, whether or not the tabs have closed;
+                    // the process may be terminated once this returns
+                    save_active_session(state);
+                } else {
+                    // Shutdown cancelled; the tabs that already closed stay closed
+                    end_shutdown_block(hwnd, state);
+                }
+            } else if wparam.0 != 0 {
+                // A handler up the stack has the state and won't return in time
+                save_shutdown_session(hwnd);
+            }
+            LRESULT(0)
+        }
+
         WM_CLOSE => {
             // Request graceful close for all Neovide windows
             // Process polling will detect exits and close app when last tab is removed
-            let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut WindowState;
-            if !state_ptr.is_null() {
-                let state = &mut *state_ptr;
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
+                // Closing the app cancels a session switch in progress
+                state.pending_session = None;
+                save_active_session(state);
+                remember_window_layout(hwnd, state);
                 state.tab_manager.request_close_all();
 
                 // If all tabs were forcefully closed (none had ready windows),
                 // the tab manager is now empty and we should close immediately
                 if state.tab_manager.is_empty() {
                     KillTimer(hwnd, PROCESS_POLL_TIMER_ID).ok();
-                    let state = Box::from_raw(state_ptr);
-                    drop(state);
-                    SetWindowLongPtrW(hwnd, GWLP_USERDATA, 0);
+                    restore_previous_foreground(state);
+                    // The state is freed in WM_NCDESTROY
+                    drop(guard);
                     DestroyWindow(hwnd).ok();
                 }
                 // Otherwise, process polling will handle closing when all processes exit
@@ -2752,38 +6713,89 @@ unsafe extern "system" fn window_proc(
 
         WM_DESTROY => {
             // Unregister all global hotkeys
-            let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut WindowState;
-            if !state_ptr.is_null() {
-                let state = &*state_ptr;
+            if let Some(guard) = window_state(hwnd) {
+                let state = &*guard;
                 hotkeys::unregister_all_hotkeys(hwnd, &state.registered_hotkeys);
             }
+            // Disconnect UI Automation clients from the titlebar elements
+            UiaReturnRawElementProvider(hwnd, WPARAM(0), LPARAM(0), None);
+            let _ = WTSUnRegisterSessionNotification(hwnd);
             PostQuitMessage(0);
             LRESULT(0)
         }
 
+        WM_NCDESTROY => {
+            // The last message: free the state, unless a handler destroyed the
+            // window while it had the state (then it is left to the process exit)
+            let cell = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut WindowCell;
+            SetWindowLongPtrW(hwnd, GWLP_USERDATA, 0);
+            if !cell.is_null() && (*cell).state.try_borrow_mut().is_ok() {
+                drop(Box::from_raw(cell));
+            }
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        }
+
         WM_HOTKEY => {
             let hotkey_id = wparam.0 as i32;
 
-            let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut WindowState;
-            if !state_ptr.is_null() {
-                let state = &mut *state_ptr;
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
 
                 // Bring window to foreground first (restore if minimized)
                 bring_window_to_foreground(hwnd);
 
-                if hotkeys::is_tab_hotkey(hotkey_id) {
+                if hotkey_id == hotkeys::NEW_TAB_IN_FOLDER_HOTKEY_ID {
+                    // Handled like the dropdown entry so the picker runs outside this handler
+                    PostMessageW(hwnd, WM_APP + 5, WPARAM(0), LPARAM(0)).ok();
+                } else if hotkey_id == hotkeys::TASK_MANAGER_HOTKEY_ID {
+                    toggle_task_manager(hwnd, state);
+                } else if hotkey_id == hotkeys::FOCUS_TAB_BAR_HOTKEY_ID {
+                    focus_tab_bar(hwnd, state);
+                } else if hotkey_id == hotkeys::DEBUG_HUD_HOTKEY_ID {
+                    state.debug_hud = match state.debug_hud {
+                        Some(_) => None,
+                        None => Some(DebugHud::new()),
+                    };
+                    InvalidateRect(hwnd, None, false);
+                } else if hotkey_id == hotkeys::CLOSE_OTHER_TABS_HOTKEY_ID
+                    || hotkey_id == hotkeys::CLOSE_TABS_TO_RIGHT_HOTKEY_ID
+                {
+                    let selected = state.tab_manager.selected_index();
+                    let to_right = hotkey_id == hotkeys::CLOSE_TABS_TO_RIGHT_HOTKEY_ID;
+                    close_other_tabs(hwnd, state, selected, to_right);
+                } else if hotkey_id == hotkeys::NEXT_TAB_HOTKEY_ID
+                    || hotkey_id == hotkeys::PREV_TAB_HOTKEY_ID
+                {
+                    cycle_tabs(hwnd, state, hotkey_id == hotkeys::NEXT_TAB_HOTKEY_ID);
+                } else if hotkey_id == hotkeys::TAB_BACK_HOTKEY_ID
+                    || hotkey_id == hotkeys::TAB_FORWARD_HOTKEY_ID
+                {
+                    let changed = if hotkey_id == hotkeys::TAB_BACK_HOTKEY_ID {
+                        state.tab_manager.history_back()
+                    } else {
+                        state.tab_manager.history_forward()
+                    };
+                    if changed {
+                        state.tab_manager.activate_selected(hwnd, content_top());
+                        InvalidateRect(hwnd, None, false);
+                    } else {
+                        state
+                            .tab_manager
+                            .activate_and_foreground_selected(hwnd, content_top());
+                    }
+                } else if hotkeys::is_tab_hotkey(hotkey_id) {
                     // Tab activation hotkey (1-10)
                     if let Some(tab_index) = hotkeys::tab_index_from_hotkey_id(hotkey_id) {
                         if tab_index < state.tab_manager.count() {
                             // Tab exists - select it
                             if state.tab_manager.select_tab(tab_index) {
-                                state.tab_manager.activate_selected(hwnd, TITLEBAR_HEIGHT);
+                                state.tab_manager.activate_selected(hwnd, content_top());
                                 InvalidateRect(hwnd, None, false);
                             } else {
                                 // Already selected - just ensure foreground
                                 state
                                     .tab_manager
-                                    .activate_and_foreground_selected(hwnd, TITLEBAR_HEIGHT);
+                                    .activate_and_foreground_selected(hwnd, content_top());
                             }
                         }
                         // If tab doesn't exist, do nothing (no error)
@@ -2797,42 +6809,17 @@ unsafe extern "system" fn window_proc(
                         {
                             // Activate existing tab
                             if state.tab_manager.select_tab(existing_tab) {
-                                state.tab_manager.activate_selected(hwnd, TITLEBAR_HEIGHT);
+                                state.tab_manager.activate_selected(hwnd, content_top());
                                 InvalidateRect(hwnd, None, false);
                             } else {
-                                state
-                                    .tab_manager
-                                    .activate_and_foreground_selected(hwnd, TITLEBAR_HEIGHT);
-                            }
-                        } else if let Some(profile) = state.config.get_profile(profile_index) {
-                            // Create new tab with this profile
-                            if let Ok(rect) = get_content_rect(hwnd) {
-                                let width = (rect.right - rect.left) as u32;
-                                let height = (rect.bottom - rect.top) as u32;
-                                let profile = profile.clone();
-
-                                match state.tab_manager.create_tab(
-                                    width,
-                                    height,
-                                    hwnd,
-                                    &profile,
-                                    profile_index,
-                                ) {
-                                    Ok(_) => {
-                                        // Hide other tabs
-                                        for (i, tab) in state.tab_manager.iter() {
-                                            if i != state.tab_manager.selected_index() {
-                                                tab.process.hide();
-                                            }
-                                        }
-                                        InvalidateRect(hwnd, None, false);
-                                    }
-                                    Err(e) => {
-                                        let error_msg = format!("Failed to create new tab: {}", e);
-                                        show_error(&error_msg, "Error: Failed to Create Tab");
-                                    }
-                                }
+                                state
+                                    .tab_manager
+                                    .activate_and_foreground_selected(hwnd, content_top());
                             }
+                        } else if let Some(profile) = state.config.get_profile(profile_index) {
+                            // Create new tab with this profile
+                            let profile = profile.clone();
+                            open_profile_tab(hwnd, state, &profile, profile_index, None);
                         }
                         // If profile doesn't exist, do nothing (no error)
                     }
@@ -2841,17 +6828,159 @@ unsafe extern "system" fn window_proc(
             LRESULT(0)
         }
 
+        // Touch and pen: taps on the tab bar's elements go through the mouse
+        // handlers (with larger targets in the title bar); the rest, e.g. dragging
+        // the window by its caption, is left to Windows. With swipes on, a contact
+        // on a tab is held back until it is a swipe, a tap or a press and hold.
+        WM_POINTERDOWN => {
+            if let Some(mut pt) = touch_contact(hwnd, wparam, lparam)
+                && let Some(tab_count) = window_state(hwnd).map(|state| state.tab_manager.count())
+            {
+                let mut client_rect = RECT::default();
+                if (0..titlebar_height()).contains(&pt.y)
+                    && GetClientRect(hwnd, &mut client_rect).is_ok()
+                {
+                    let (x, y) = touch_hit_point(pt.x, pt.y, tab_count, client_rect.right);
+                    pt = POINT { x, y };
+                }
+                let mut screen_pt = pt;
+                ClientToScreen(hwnd, &mut screen_pt);
+                let screen_lparam = LPARAM(
+                    (((screen_pt.y as u16 as u32) << 16) | screen_pt.x as u16 as u32) as isize,
+                );
+                let hit = window_proc(hwnd, WM_NCHITTEST, WPARAM(0), screen_lparam);
+                if hit.0 == HTCLIENT as isize
+                    && let Some(mut state) = window_state(hwnd)
+                {
+                    let on_tab = matches!(
+                        hit_test_tabs(pt.x, pt.y, &state.tab_manager, client_rect.right),
+                        TabHitResult::Tab(_)
+                    );
+                    let replaying =
+                        !on_tab || state.config.swipe_sensitivity.touch_distance().is_none();
+                    state.touch = Some(TouchContact {
+                        pointer: pointer_id(wparam),
+                        start: pt,
+                        started: Instant::now(),
+                        replaying,
+                        swiped: false,
+                    });
+                    // The mouse handlers need the state
+                    drop(state);
+                    if replaying {
+                        replay_pointer_as_mouse(hwnd, msg, wparam, pt, window_proc);
+                    }
+                    return LRESULT(0);
+                }
+            }
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        }
+
+        WM_POINTERUPDATE | WM_POINTERUP => {
+            if let Some(mut guard) = window_state(hwnd)
+                && guard.touch.as_ref().map(|touch| touch.pointer) == Some(pointer_id(wparam))
+                && let Some(mut pt) = touch_contact(hwnd, wparam, lparam)
+            {
+                let state = &mut *guard;
+                // Mouse messages to replay once the state is released
+                let mut replays = Vec::new();
+                let Some(touch) = state.touch.as_mut() else {
+                    return LRESULT(0);
+                };
+                if touch.swiped {
+                    if msg == WM_POINTERUP {
+                        state.touch = None;
+                    }
+                    return LRESULT(0);
+                }
+                if !touch.replaying {
+                    let start = touch.start;
+                    if msg == WM_POINTERUP {
+                        // A tap
+                        state.touch = None;
+                        replays = vec![(WM_POINTERDOWN, start), (msg, start)];
+                    } else if let Some(direction) = gestures::touch_swipe(
+                        pt.x - start.x,
+                        pt.y - start.y,
+                        state.config.swipe_sensitivity,
+                    ) {
+                        touch.swiped = true;
+                        select_adjacent_tab(hwnd, state, direction == SwipeDirection::Next);
+                    } else if touch.started.elapsed() >= Duration::from_millis(TOUCH_HOLD_MS) {
+                        // Held: press the tab, so moving on drags it
+                        touch.replaying = true;
+                        replays = vec![(WM_POINTERDOWN, start), (msg, pt)];
+                    }
+                } else {
+                    if msg == WM_POINTERUP {
+                        state.touch = None;
+                        // Release on the element the tap started on (e.g. a close button)
+                        let mut client_rect = RECT::default();
+                        if (0..titlebar_height()).contains(&pt.y)
+                            && GetClientRect(hwnd, &mut client_rect).is_ok()
+                        {
+                            let (x, y) = touch_hit_point(
+                                pt.x,
+                                pt.y,
+                                state.tab_manager.count(),
+                                client_rect.right,
+                            );
+                            pt = POINT { x, y };
+                        }
+                    }
+                    replays.push((msg, pt));
+                }
+                drop(guard);
+                for (replay_msg, replay_pt) in replays {
+                    replay_pointer_as_mouse(hwnd, replay_msg, wparam, replay_pt, window_proc);
+                }
+                return LRESULT(0);
+            }
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        }
+
+        WM_POINTERCAPTURECHANGED => {
+            // The contact was cancelled (e.g. taken over by a system gesture)
+            if let Some(mut state) = window_state(hwnd)
+                && state.touch.take().is_some()
+            {
+                if state.tab_manager.drag_state.take().is_some() {
+                    ReleaseCapture().ok();
+                    InvalidateRect(hwnd, None, false);
+                }
+            }
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        }
+
         WM_LBUTTONDOWN => {
             let x = (lparam.0 & 0xFFFF) as i16 as i32;
             let y = ((lparam.0 >> 16) & 0xFFFF) as i16 as i32;
 
-            let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut WindowState;
-            if !state_ptr.is_null() {
-                let state = &mut *state_ptr;
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
+                // The mouse takes over from keyboard navigation
+                leave_titlebar_focus(hwnd, state);
+                hide_tab_preview(hwnd, state);
                 let mut client_rect = RECT::default();
                 if GetClientRect(hwnd, &mut client_rect).is_ok() {
                     let client_width = client_rect.right;
 
+                    // The startup indicator's "Cancel" button (content area is
+                    // uncovered while the selected tab is starting)
+                    if state.tab_manager.has_queued_spawns()
+                        && state.tab_manager.selected_startup_elapsed().is_some()
+                    {
+                        let cancel_rect = get_startup_cancel_rect(&client_rect);
+                        if x >= cancel_rect.left
+                            && x < cancel_rect.right
+                            && y >= cancel_rect.top
+                            && y < cancel_rect.bottom
+                        {
+                            cancel_pending_tabs(hwnd, state);
+                            return LRESULT(0);
+                        }
+                    }
+
                     // Check window control buttons first
                     let button = hit_test_buttons(x, y, client_width);
                     match button {
@@ -2886,7 +7015,48 @@ unsafe extern "system" fn window_proc(
                         HoveredButton::None => {}
                     }
 
-                    let tab_hit = hit_test_tab_bar(x, y, state.tab_manager.count(), client_width);
+                    match hit_test_widgets(x, y, client_width) {
+                        Some(Widget::Memory) => {
+                            hide_dropdown_popup(hwnd, state);
+                            hide_overflow_popup(hwnd, state);
+                            let on_dismiss = get_widget_rects(client_width)
+                                .into_iter()
+                                .find(|(widget, _)| *widget == Widget::Memory)
+                                .is_some_and(|(_, rect)| {
+                                    let dismiss = get_memory_dismiss_rect(&rect.into());
+                                    x >= dismiss.left && x < dismiss.right
+                                });
+                            if on_dismiss {
+                                state.memory_warning.dismiss();
+                                update_widgets(hwnd, state);
+                            } else {
+                                hibernate_tabs(hwnd, state);
+                            }
+                            return LRESULT(0);
+                        }
+                        Some(Widget::Update) => {
+                            hide_dropdown_popup(hwnd, state);
+                            hide_overflow_popup(hwnd, state);
+                            if let Some(release) = &state.update {
+                                updates::open_release_page(release);
+                            }
+                            return LRESULT(0);
+                        }
+                        Some(Widget::Settings) => {
+                            hide_dropdown_popup(hwnd, state);
+                            hide_overflow_popup(hwnd, state);
+                            if let Some((_, rect)) = get_widget_rects(client_width)
+                                .into_iter()
+                                .find(|(widget, _)| *widget == Widget::Settings)
+                            {
+                                show_settings_menu(hwnd, state, &rect.into());
+                            }
+                            return LRESULT(0);
+                        }
+                        _ => {}
+                    }
+
+                    let tab_hit = hit_test_tabs(x, y, &state.tab_manager, client_width);
 
                     match tab_hit {
                         TabHitResult::Tab(index) => {
@@ -2904,6 +7074,11 @@ unsafe extern "system" fn window_proc(
                             // Capture mouse for drag tracking
                             SetCapture(hwnd);
                         }
+                        TabHitResult::TabRetry(index) => {
+                            hide_dropdown_popup(hwnd, state);
+                            hide_overflow_popup(hwnd, state);
+                            retry_tab(hwnd, state, index);
+                        }
                         TabHitResult::TabClose(index) => {
                             // Close popups if open
                             hide_dropdown_popup(hwnd, state);
@@ -2919,7 +7094,7 @@ unsafe extern "system" fn window_proc(
                                     PostMessageW(hwnd, WM_CLOSE, WPARAM(0), LPARAM(0)).ok();
                                 } else {
                                     // Activate the newly selected tab
-                                    state.tab_manager.activate_selected(hwnd, TITLEBAR_HEIGHT);
+                                    state.tab_manager.activate_selected(hwnd, content_top());
                                     InvalidateRect(hwnd, None, false);
                                 }
                             }
@@ -2930,35 +7105,8 @@ unsafe extern "system" fn window_proc(
                             hide_dropdown_popup(hwnd, state);
                             hide_overflow_popup(hwnd, state);
                             // Create new tab with default profile
-                            if let Ok(rect) = get_content_rect(hwnd) {
-                                let width = (rect.right - rect.left) as u32;
-                                let height = (rect.bottom - rect.top) as u32;
-
-                                let default_profile = state.config.default_profile().clone();
-                                match state.tab_manager.create_tab(
-                                    width,
-                                    height,
-                                    hwnd,
-                                    &default_profile,
-                                    0,
-                                ) {
-                                    Ok(_) => {
-                                        // Hide other tabs immediately
-                                        // The new tab will be activated by the spawner thread
-                                        // once the window is ready
-                                        for (i, tab) in state.tab_manager.iter() {
-                                            if i != state.tab_manager.selected_index() {
-                                                tab.process.hide();
-                                            }
-                                        }
-                                        InvalidateRect(hwnd, None, false);
-                                    }
-                                    Err(e) => {
-                                        let error_msg = format!("Failed to create new tab: {}", e);
-                                        show_error(&error_msg, "Error: Failed to Create Tab");
-                                    }
-                                }
-                            }
+                            let default_profile = state.config.default_profile().clone();
+                            open_profile_tab(hwnd, state, &default_profile, 0, None);
                         }
                         TabHitResult::ProfileDropdown => {
                             // Close overflow popup if open
@@ -2999,13 +7147,47 @@ unsafe extern "system" fn window_proc(
             LRESULT(0)
         }
 
+        // Middle, right and back/forward buttons on the tab bar run their
+        // `mouse_bindings` (right-clicking a tab opens its menu by default)
+        WM_MBUTTONUP | WM_RBUTTONUP | WM_XBUTTONUP => {
+            let pt = POINT {
+                x: (lparam.0 & 0xFFFF) as i16 as i32,
+                y: ((lparam.0 >> 16) & 0xFFFF) as i16 as i32,
+            };
+            if let Some(mut state) = window_state(hwnd)
+                && let Some(button) = bound_mouse_button(msg, wparam)
+                && run_mouse_binding(hwnd, &mut state, button, pt)
+            {
+                // WM_XBUTTONUP is answered with TRUE
+                return LRESULT((msg == WM_XBUTTONUP) as isize);
+            }
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        }
+
+        // The same over the empty title bar, which is the window's caption
+        WM_NCMBUTTONUP | WM_NCRBUTTONUP | WM_NCXBUTTONUP
+            if (wparam.0 & 0xFFFF) as u32 == HTCAPTION =>
+        {
+            let mut pt = POINT {
+                x: (lparam.0 & 0xFFFF) as i16 as i32,
+                y: ((lparam.0 >> 16) & 0xFFFF) as i16 as i32,
+            };
+            if let Some(mut state) = window_state(hwnd)
+                && let Some(button) = bound_mouse_button(msg, wparam)
+                && ScreenToClient(hwnd, &mut pt).as_bool()
+                && run_mouse_binding(hwnd, &mut state, button, pt)
+            {
+                return LRESULT((msg == WM_NCXBUTTONUP) as isize);
+            }
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        }
+
         WM_LBUTTONUP => {
             let _x = (lparam.0 & 0xFFFF) as i16 as i32;
             let _y = ((lparam.0 >> 16) & 0xFFFF) as i16 as i32;
 
-            let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut WindowState;
-            if !state_ptr.is_null() {
-                let state = &mut *state_ptr;
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
 
                 if let Some(drag) = state.tab_manager.drag_state.take() {
                     ReleaseCapture().ok();
@@ -3018,13 +7200,13 @@ unsafe extern "system" fn window_proc(
                         // This was a click, not a drag - select the tab
                         if state.tab_manager.select_tab(drag.tab_index) {
                             // Selection changed - activate with proper position check
-                            state.tab_manager.activate_selected(hwnd, TITLEBAR_HEIGHT);
+                            state.tab_manager.activate_selected(hwnd, content_top());
                             InvalidateRect(hwnd, None, false);
                         } else {
                             // Already selected - just ensure it's in foreground (no reposition)
                             state
                                 .tab_manager
-                                .activate_and_foreground_selected(hwnd, TITLEBAR_HEIGHT);
+                                .activate_and_foreground_selected(hwnd, content_top());
                         }
                     }
                 }
@@ -3032,18 +7214,50 @@ unsafe extern "system" fn window_proc(
             LRESULT(0)
         }
 
+        // Two-finger touchpad swipes over the title bar switch tabs
+        WM_MOUSEHWHEEL => {
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
+                let mut pt = POINT {
+                    x: (lparam.0 & 0xFFFF) as i16 as i32,
+                    y: ((lparam.0 >> 16) & 0xFFFF) as i16 as i32,
+                };
+                if ScreenToClient(hwnd, &mut pt).as_bool() && (0..titlebar_height()).contains(&pt.y)
+                {
+                    let delta = (wparam.0 >> 16) as i16 as i32;
+                    let sensitivity = state.config.swipe_sensitivity;
+                    if let Some(direction) =
+                        state.wheel_swipe.add(delta, Instant::now(), sensitivity)
+                    {
+                        select_adjacent_tab(hwnd, state, direction == SwipeDirection::Next);
+                    }
+                    return LRESULT(0);
+                }
+            }
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        }
+
         WM_MOUSEMOVE => {
             let x = (lparam.0 & 0xFFFF) as i16 as i32;
             let y = ((lparam.0 >> 16) & 0xFFFF) as i16 as i32;
 
-            let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut WindowState;
-            if !state_ptr.is_null() {
-                let state = &mut *state_ptr;
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
+
+                // The slots of closed tabs close once the pointer leaves the title bar
+                if y >= titlebar_height() && layout::unfreeze_tabs() {
+                    InvalidateRect(hwnd, None, false);
+                }
 
                 // Check if we're dragging and extract needed info
                 let drag_info = if let Some(ref mut drag) = state.tab_manager.drag_state {
                     drag.current_x = x;
                     if drag.is_active() {
+                        // Tabs are reordered without the slots of closed tabs
+                        if layout::unfreeze_tabs() {
+                            drag.tab_start_left = get_tab_rect(drag.tab_index, 0).left;
+                            InvalidateRect(hwnd, None, false);
+                        }
                         Some((drag.tab_index, drag.get_visual_x()))
                     } else {
                         None
@@ -3064,7 +7278,11 @@ unsafe extern "system" fn window_proc(
                             visual_x,
                             tab_count,
                             client_width,
-                        ) {
+                        )
+                        .filter(|target| {
+                            // Pinned tabs stay in front of the unpinned ones
+                            state.tab_manager.can_move_tab(current_tab_index, *target)
+                        }) {
                             // Perform the swap
                             state.tab_manager.move_tab(current_tab_index, target_index);
 
@@ -3099,164 +7317,516 @@ unsafe extern "system" fn window_proc(
                         }
 
                         // Hit test the tab bar (dropdown popup handles its own mouse tracking)
-                        let tab_hit =
-                            hit_test_tab_bar(x, y, state.tab_manager.count(), client_width);
+                        let tab_hit = hit_test_tabs(x, y, &state.tab_manager, client_width);
+                        let widget_hit = hit_test_widgets(x, y, client_width);
                         let new_hover = match tab_hit {
+                            _ if widget_hit == Some(Widget::Memory) => {
+                                let on_dismiss = get_widget_rects(client_width)
+                                    .into_iter()
+                                    .find(|(widget, _)| *widget == Widget::Memory)
+                                    .is_some_and(|(_, rect)| {
+                                        x >= get_memory_dismiss_rect(&rect.into()).left
+                                    });
+                                if on_dismiss {
+                                    HoveredTab::MemoryDismiss
+                                } else {
+                                    HoveredTab::MemoryBanner
+                                }
+                            }
+                            _ if widget_hit == Some(Widget::Update) => HoveredTab::UpdateBadge,
+                            _ if widget_hit == Some(Widget::Settings) => HoveredTab::SettingsButton,
                             TabHitResult::Tab(i) => HoveredTab::Tab(i),
                             TabHitResult::TabClose(i) => HoveredTab::TabClose(i),
+                            TabHitResult::TabRetry(i) => HoveredTab::TabRetry(i),
                             TabHitResult::NewTabButton => HoveredTab::NewTabButton,
                             TabHitResult::ProfileDropdown => HoveredTab::ProfileDropdown,
                             TabHitResult::OverflowButton => HoveredTab::OverflowButton,
                             _ => HoveredTab::None,
                         };
 
-                        if new_hover != state.hovered_tab {
-                            state.hovered_tab = new_hover;
-                            needs_repaint = true;
-                        }
+                        if new_hover != state.hovered_tab {
+                            state.hovered_tab = new_hover;
+                            needs_repaint = true;
+                        }
+
+                        // The tooltip describes whichever tab is under the mouse
+                        let tooltip_tab = match tab_hit {
+                            TabHitResult::Tab(i)
+                            | TabHitResult::TabClose(i)
+                            | TabHitResult::TabRetry(i) => Some(i),
+                            _ => None,
+                        };
+                        set_tooltip_tab(hwnd, state, tooltip_tab, client_width);
+                        // Background tabs also show what they looked like
+                        let preview_tab =
+                            tooltip_tab.filter(|&i| i != state.tab_manager.selected_index());
+                        set_preview_tab(hwnd, state, preview_tab);
+
+                        if needs_repaint {
+                            let titlebar_rect = RECT {
+                                left: 0,
+                                top: 0,
+                                right: client_rect.right,
+                                bottom: titlebar_height(),
+                            };
+                            InvalidateRect(hwnd, Some(&titlebar_rect), false);
+                        }
+                    }
+                }
+
+                // Track mouse to get WM_MOUSELEAVE
+                if !state.tracking_mouse {
+                    let mut tme = TRACKMOUSEEVENT {
+                        cbSize: std::mem::size_of::<TRACKMOUSEEVENT>() as u32,
+                        dwFlags: TME_LEAVE,
+                        hwndTrack: hwnd,
+                        dwHoverTime: 0,
+                    };
+                    TrackMouseEvent(&mut tme);
+                    state.tracking_mouse = true;
+                }
+            }
+            LRESULT(0)
+        }
+
+        // WM_APP: Profile selected from dropdown popup (wparam = profile index)
+        WM_APP => {
+            let profile_index = wparam.0;
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
+                state.dropdown_hwnd = None; // Popup already destroyed itself
+                state.dropdown_state = DropdownState::Closed;
+
+                if let Some(profile) = state.config.get_profile(profile_index) {
+                    let profile = profile.clone();
+                    open_profile_tab(hwnd, state, &profile, profile_index, None);
+                }
+                InvalidateRect(hwnd, None, false);
+            }
+            LRESULT(0)
+        }
+
+        // WM_APP + 1: Dropdown popup closed (lost focus or click outside)
+        msg if msg == WM_APP + 1 => {
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
+                state.dropdown_hwnd = None; // Popup already destroyed itself
+                state.dropdown_state = DropdownState::Closed;
+                InvalidateRect(hwnd, None, false);
+            }
+            LRESULT(0)
+        }
+
+        // WM_APP + 2: Overflow tab selected (wparam = tab index)
+        msg if msg == WM_APP + 2 => {
+            let tab_index = wparam.0;
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
+                state.overflow_hwnd = None; // Popup already destroyed itself
+
+                // Select the tab
+                if state.tab_manager.select_tab(tab_index) {
+                    // Hide all other tabs and activate the selected one
+                    state.tab_manager.activate_selected(hwnd, content_top());
+                }
+                InvalidateRect(hwnd, None, false);
+            }
+            LRESULT(0)
+        }
+
+        // WM_APP + 3: Overflow popup closed (lost focus or click outside)
+        msg if msg == WM_APP + 3 => {
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
+                state.overflow_hwnd = None; // Popup already destroyed itself
+                // A popup with a filter box had the keyboard; hand it back to
+                // Neovide unless another application took it
+                let foreground = GetForegroundWindow();
+                if foreground.is_invalid() || foreground == hwnd {
+                    state.tab_manager.bring_selected_to_foreground();
+                }
+                InvalidateRect(hwnd, None, false);
+            }
+            LRESULT(0)
+        }
+
+        // WM_APP + 4: Overflow tab close button clicked (wparam = tab index)
+        msg if msg == WM_APP + 4 => {
+            let tab_index = wparam.0;
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
+                state.overflow_hwnd = None; // Popup already destroyed itself
+
+                // Request graceful close - sends WM_CLOSE to Neovide window
+                // Process polling will detect when process exits and remove the tab
+                // If window not ready, falls back to forceful close
+                let graceful = state.tab_manager.request_close_tab(tab_index);
+                if !graceful {
+                    // Forceful close occurred - tab already removed
+                    // Check if that was the last tab
+                    if state.tab_manager.is_empty() {
+                        PostMessageW(hwnd, WM_CLOSE, WPARAM(0), LPARAM(0)).ok();
+                    } else {
+                        // Activate the newly selected tab
+                        state.tab_manager.activate_selected(hwnd, content_top());
+                    }
+                }
+                // If graceful, do nothing - process polling handles tab removal
+                InvalidateRect(hwnd, None, false);
+            }
+            LRESULT(0)
+        }
+
+        // Kill button clicked in the task manager popup (wparam = tab ID)
+        WM_TASK_MANAGER_KILL => {
+            let tab_id = wparam.0;
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
+                let owner = state.task_manager_hwnd.unwrap_or(hwnd);
+                force_close_tab(hwnd, state, tab_id, owner);
+            }
+            LRESULT(0)
+        }
+
+        WM_SESSION_MENU => {
+            if let Some(mut state) = window_state(hwnd) {
+                show_session_menu(hwnd, &mut state);
+            }
+            LRESULT(0)
+        }
+
+        WM_PROFILE_PROMPT => {
+            if let Some(mut state) = window_state(hwnd) {
+                show_pending_profile_prompt(hwnd, &mut state);
+            }
+            LRESULT(0)
+        }
+
+        WM_PROFILE_GROUP_MENU => {
+            show_profile_group_menu(hwnd, wparam.0);
+            LRESULT(0)
+        }
+
+        WM_NEOVIDE_WINDOW_READY => {
+            // A Neovide window was found: place it (and any others found meanwhile),
+            // then let the next queued tab start in the freed spawn slot
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
+                // A minimized wrapper shows the selected window once it is restored
+                if state.tab_manager.place_ready_windows(
+                    hwnd,
+                    content_top(),
+                    !IsIconic(hwnd).as_bool(),
+                ) {
+                    InvalidateRect(hwnd, None, false);
+                }
+                launch_queued_tabs(hwnd, state);
+            }
+            LRESULT(0)
+        }
+
+        WM_NEOVIDE_WINDOW_FAILED => {
+            // A Neovide window never appeared: show its tab as an error tab, then
+            // let the next queued tab start in the freed spawn slot
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
+                if state.tab_manager.mark_window_failures() {
+                    state.tab_manager.activate_selected(hwnd, content_top());
+                    InvalidateRect(hwnd, None, false);
+                }
+                launch_queued_tabs(hwnd, state);
+            }
+            LRESULT(0)
+        }
+
+        WM_LBUTTONDBLCLK => {
+            let x = (lparam.0 & 0xFFFF) as i16 as i32;
+            let y = ((lparam.0 >> 16) & 0xFFFF) as i16 as i32;
+
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
+                let mut client_rect = RECT::default();
+                if GetClientRect(hwnd, &mut client_rect).is_ok() {
+                    if let TabHitResult::Tab(index) =
+                        hit_test_tabs(x, y, &state.tab_manager, client_rect.right)
+                    {
+                        hide_dropdown_popup(hwnd, state);
+                        hide_overflow_popup(hwnd, state);
+                        run_tab_double_click(hwnd, state, index);
+                        return LRESULT(0);
+                    }
+                }
+            }
+            // Anywhere else the second click of a double-click is a plain click
+            window_proc(hwnd, WM_LBUTTONDOWN, wparam, lparam)
+        }
+
+        WM_GETOBJECT if lparam.0 as i32 == UiaRootObjectId => {
+            // Expose the custom-drawn titlebar to screen readers
+            UiaReturnRawElementProvider(hwnd, wparam, lparam, &uia::root_provider(hwnd))
+        }
+
+        WM_KEYDOWN => {
+            // Keyboard navigation of the titlebar (after the focus_tab_bar hotkey)
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
+                if let Some(focus) = state.titlebar_focus {
+                    handle_titlebar_key(hwnd, state, focus, VIRTUAL_KEY(wparam.0 as u16));
+                    return LRESULT(0);
+                }
+            }
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        }
+
+        WM_UIA_INVOKE => {
+            if let Some(action) = UiaAction::from_params(wparam, lparam)
+                && let Some(mut state) = window_state(hwnd)
+            {
+                run_titlebar_action(hwnd, &mut state, action);
+            }
+            LRESULT(0)
+        }
+
+        // WM_SETTINGCHANGE: Follow Windows' "Animation effects" setting unless
+        // `reduce_motion` is set
+        WM_SETTINGCHANGE => {
+            if wparam.0 as u32 == SPI_SETCLIENTAREAANIMATION.0
+                && let Some(mut state) = window_state(hwnd)
+            {
+                let reduce = reduce_motion(&state.config);
+                state.tab_manager.set_reduce_motion(reduce);
+                let _ = InvalidateRect(hwnd, None, false);
+            }
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        }
+
+        // WM_DISPLAYCHANGE: A display was added, removed or changed resolution. Hidden
+        // Neovide windows may be left on a display that is gone; move them when shown,
+        // and the visible one once Windows has moved the wrapper.
+        WM_DISPLAYCHANGE => {
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
+                // The "Move to monitor" submenu lists the displays
+                update_system_menu(hwnd, state);
+                // Windows moves the windows off displays that are gone; don't take
+                // that for the window's place until it is put back in its own
+                state.monitor_setup = None;
+                SetTimer(hwnd, MONITOR_SETUP_TIMER_ID, MONITOR_SETUP_DELAY_MS, None);
+                state.tab_manager.mark_positions_dirty();
+                if !state.tab_manager.is_empty() {
+                    schedule_position_update(hwnd, state);
+                }
+            }
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        }
 
-                        if needs_repaint {
-                            let titlebar_rect = RECT {
-                                left: 0,
-                                top: 0,
-                                right: client_rect.right,
-                                bottom: TITLEBAR_HEIGHT,
-                            };
-                            InvalidateRect(hwnd, Some(&titlebar_rect), false);
-                        }
+        // WM_WTSSESSION_CHANGE: The workstation was locked or unlocked. Nobody
+        // sees the tabs while it is locked, so the polling pauses until unlock.
+        WM_WTSSESSION_CHANGE => {
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
+                match wparam.0 as u32 {
+                    WTS_SESSION_LOCK => set_polling_paused(hwnd, state, true),
+                    WTS_SESSION_UNLOCK => {
+                        set_polling_paused(hwnd, state, false);
+                        // The wrapper sometimes comes back active with its Neovide
+                        // window behind it
+                        reactivate_selected(hwnd, state);
+                        InvalidateRect(hwnd, None, false);
                     }
+                    _ => {}
                 }
+            }
+            LRESULT(0)
+        }
 
-                // Track mouse to get WM_MOUSELEAVE
-                if !state.tracking_mouse {
-                    let mut tme = TRACKMOUSEEVENT {
-                        cbSize: std::mem::size_of::<TRACKMOUSEEVENT>() as u32,
-                        dwFlags: TME_LEAVE,
-                        hwndTrack: hwnd,
-                        dwHoverTime: 0,
-                    };
-                    TrackMouseEvent(&mut tme);
-                    state.tracking_mouse = true;
+        // WM_POWERBROADCAST: Back from sleep. Neovide windows may have been
+        // recreated or left out of place, and the global hotkeys dropped.
+        WM_POWERBROADCAST => {
+            if wparam.0 as u32 == PBT_APMRESUMEAUTOMATIC {
+                if let Some(mut guard) = window_state(hwnd) {
+                    resume_from_sleep(hwnd, &mut guard);
                 }
             }
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        }
+
+        // WM_DPICHANGED: Moved to a monitor with a different scale - render the
+        // icons at the matching size
+        WM_DPICHANGED => {
+            set_icon_dpi(((wparam.0 >> 16) & 0xFFFF) as u32);
+            if let Some(state) = window_state(hwnd) {
+                preload_profile_icons(hwnd, &state.config);
+            }
+            let _ = InvalidateRect(hwnd, None, false);
             LRESULT(0)
         }
 
-        // WM_APP: Profile selected from dropdown popup (wparam = profile index)
-        WM_APP => {
-            let profile_index = wparam.0;
-            let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut WindowState;
-            if !state_ptr.is_null() {
-                let state = &mut *state_ptr;
-                state.dropdown_hwnd = None; // Popup already destroyed itself
-                state.dropdown_state = DropdownState::Closed;
+        // WM_ICONS_DECODED: The preload thread finished an icon
+        WM_ICONS_DECODED => {
+            if receive_decoded_icons() {
+                let _ = InvalidateRect(hwnd, None, false);
+            }
+            if let Some(mut state) = window_state(hwnd) {
+                show_pending_profile_prompt(hwnd, &mut state);
+            }
+            LRESULT(0)
+        }
+
+        // WM_UPDATE_AVAILABLE: The startup check found a newer release
+        WM_UPDATE_AVAILABLE => {
+            if let Some(mut state) = window_state(hwnd) {
+                state.update = updates::available_update();
+                update_widgets(hwnd, &mut state);
+                let _ = InvalidateRect(hwnd, None, false);
+            }
+            LRESULT(0)
+        }
 
-                if let Ok(rect) = get_content_rect(hwnd) {
-                    let width = (rect.right - rect.left) as u32;
-                    let height = (rect.bottom - rect.top) as u32;
+        WM_TAB_RENAME_DONE => {
+            if let Some(mut state) = window_state(hwnd) {
+                finish_tab_rename(hwnd, &mut state, wparam.0 != 0);
+            }
+            LRESULT(0)
+        }
 
-                    if let Some(profile) = state.config.get_profile(profile_index) {
-                        let profile = profile.clone();
-                        match state.tab_manager.create_tab(
-                            width,
-                            height,
-                            hwnd,
-                            &profile,
-                            profile_index,
-                        ) {
-                            Ok(_) => {
-                                for (i, tab) in state.tab_manager.iter() {
-                                    if i != state.tab_manager.selected_index() {
-                                        tab.process.hide();
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                let error_msg = format!("Failed to create new tab: {}", e);
-                                show_error(&error_msg, "Error: Failed to Create Tab");
-                            }
-                        }
+        WM_COPYDATA => {
+            // A folder or file opened through another neovide-tabs process
+            let Some(request) = explorer::open_request_from_copydata(lparam) else {
+                return DefWindowProcW(hwnd, msg, wparam, lparam);
+            };
+            if window_cell(hwnd).is_none() {
+                return LRESULT(0);
+            }
+            // The message's data is copied out by now, so this can wait for the state
+            with_state_or_defer(hwnd, move |hwnd, state| {
+                handle_open_request(hwnd, state, &request);
+                bring_window_to_foreground(hwnd);
+            });
+            LRESULT(1)
+        }
+
+        WM_TAB_ATTENTION => {
+            // A background tab's Neovim asked for attention: pulse the tab
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
+                if state.tab_manager.request_attention(wparam.0) {
+                    if state.config.tab_attention_sound {
+                        windows::Win32::System::Diagnostics::Debug::MessageBeep(MB_ICONINFORMATION)
+                            .ok();
                     }
+                    SetTimer(hwnd, ATTENTION_TIMER_ID, ATTENTION_FRAME_MS, None);
                 }
-                InvalidateRect(hwnd, None, false);
             }
             LRESULT(0)
         }
 
-        // WM_APP + 1: Dropdown popup closed (lost focus or click outside)
-        msg if msg == WM_APP + 1 => {
-            let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut WindowState;
-            if !state_ptr.is_null() {
-                let state = &mut *state_ptr;
-                state.dropdown_hwnd = None; // Popup already destroyed itself
-                state.dropdown_state = DropdownState::Closed;
-                InvalidateRect(hwnd, None, false);
+        // Task manager popup closed (by the user or via the hotkey)
+        WM_TASK_MANAGER_CLOSED => {
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
+                // Ignore a late notification from a popup that was already replaced
+                if state
+                    .task_manager_hwnd
+                    .is_some_and(|popup_hwnd| !IsWindow(popup_hwnd).as_bool())
+                {
+                    state.task_manager_hwnd = None;
+                }
             }
             LRESULT(0)
         }
 
-        // WM_APP + 2: Overflow tab selected (wparam = tab index)
-        msg if msg == WM_APP + 2 => {
-            let tab_index = wparam.0;
-            let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut WindowState;
-            if !state_ptr.is_null() {
-                let state = &mut *state_ptr;
-                state.overflow_hwnd = None; // Popup already destroyed itself
-
-                // Select the tab
-                if state.tab_manager.select_tab(tab_index) {
-                    // Hide all other tabs and activate the selected one
-                    state.tab_manager.activate_selected(hwnd, TITLEBAR_HEIGHT);
+        WM_NOTIFY => {
+            let header = lparam.0 as *const NMHDR;
+            if !header.is_null()
+                && (*header).code == TTN_GETDISPINFOW
+                && let Some(cell) = window_cell(hwnd)
+            {
+                // While a handler has the state (refresh_usage_views asking for an
+                // update), the text it stored beforehand is used
+                if let Some(state) = window_state(hwnd) {
+                    if (*header).hwndFrom != state.tab_tooltip {
+                        return LRESULT(0);
+                    }
+                    store_tooltip_text(hwnd, &state);
                 }
-                InvalidateRect(hwnd, None, false);
+                let info = lparam.0 as *mut NMTTDISPINFOW;
+                (*info).lpszText = PWSTR(cell.tooltip_text.borrow_mut().as_mut_ptr());
             }
             LRESULT(0)
         }
 
-        // WM_APP + 3: Overflow popup closed (lost focus or click outside)
-        msg if msg == WM_APP + 3 => {
-            let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut WindowState;
-            if !state_ptr.is_null() {
-                let state = &mut *state_ptr;
-                state.overflow_hwnd = None; // Popup already destroyed itself
-                InvalidateRect(hwnd, None, false);
+        // WM_APP + 5: "New tab in folder..." chosen from the dropdown or via hotkey
+        msg if msg == WM_APP + 5 => {
+            let Some(initial_dir) = window_state(hwnd).map(|mut state| {
+                state.dropdown_hwnd = None; // Popup already destroyed itself (if it was open)
+                state.dropdown_state = DropdownState::Closed;
+                state.config.default_profile().working_directory.clone()
+            }) else {
+                return LRESULT(0);
+            };
+            InvalidateRect(hwnd, None, false);
+
+            // The picker runs a modal loop, so don't hold on to the state while it is open
+            let Some(folder) = dialogs::pick_folder(hwnd, Some(&initial_dir)) else {
+                return LRESULT(0);
+            };
+
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
+                // Default profile, with the working directory overridden for this tab only
+                let default_profile = state.config.default_profile().clone();
+                open_profile_tab(hwnd, state, &default_profile, 0, Some(&folder));
             }
             LRESULT(0)
         }
 
-        // WM_APP + 4: Overflow tab close button clicked (wparam = tab index)
-        msg if msg == WM_APP + 4 => {
-            let tab_index = wparam.0;
-            let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut WindowState;
-            if !state_ptr.is_null() {
-                let state = &mut *state_ptr;
-                state.overflow_hwnd = None; // Popup already destroyed itself
+        // Foreground window changed (wparam = new foreground HWND): only show the
+        // resize grips while the wrapper or one of its Neovide windows is active
+        msg if msg == WM_FOREGROUND_CHANGED => {
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
+                let foreground_hwnd = HWND(wparam.0 as *mut _);
+                let is_ours =
+                    foreground_hwnd == hwnd || state.tab_manager.owns_window(foreground_hwnd);
+                state.resize_grips.set_foreground(is_ours);
+                if !is_ours && is_restorable_foreground(foreground_hwnd) {
+                    state.previous_foreground = Some(foreground_hwnd);
+                }
+            }
+            LRESULT(0)
+        }
 
-                // Request graceful close - sends WM_CLOSE to Neovide window
-                // Process polling will detect when process exits and remove the tab
-                // If window not ready, falls back to forceful close
-                let graceful = state.tab_manager.request_close_tab(tab_index);
-                if !graceful {
-                    // Forceful close occurred - tab already removed
-                    // Check if that was the last tab
-                    if state.tab_manager.is_empty() {
-                        PostMessageW(hwnd, WM_CLOSE, WPARAM(0), LPARAM(0)).ok();
-                    } else {
-                        // Activate the newly selected tab
-                        state.tab_manager.activate_selected(hwnd, TITLEBAR_HEIGHT);
-                    }
+        // Wrapper or managed Neovide window moved/resized externally (wparam = HWND).
+        // Only posted while the FancyZones compatibility options are enabled.
+        msg if msg == WM_WINDOW_LOCATION_CHANGED => {
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
+                // Interactive drags are resynced in WM_EXITSIZEMOVE
+                if state.in_size_move || IsIconic(hwnd).as_bool() {
+                    return LRESULT(0);
+                }
+                let moved_hwnd = HWND(wparam.0 as *mut _);
+                let fancyzones = &state.config.fancyzones;
+                let tiling = state.config.tiling_wm.is_enabled();
+                let resync = if moved_hwnd == hwnd {
+                    fancyzones.enabled || tiling
+                } else {
+                    // A snapping tool or tiling window manager moved a Neovide window:
+                    // put it back in the wrapper
+                    (fancyzones.wrapper_only || tiling) && state.tab_manager.owns_window(moved_hwnd)
+                };
+                if resync {
+                    schedule_position_update(hwnd, state);
                 }
-                // If graceful, do nothing - process polling handles tab removal
-                InvalidateRect(hwnd, None, false);
             }
             LRESULT(0)
         }
 
         // WM_CONFIG_RELOAD: Config file changed - reload and apply new settings
         msg if msg == WM_CONFIG_RELOAD => {
-            let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut WindowState;
-            if !state_ptr.is_null() {
-                let state = &mut *state_ptr;
+            if let Some(mut guard) = window_state(hwnd) {
+                let state = &mut *guard;
 
                 // Try to reload the config
                 if let Some(new_config) = Config::reload() {
@@ -3266,27 +7836,152 @@ unsafe extern "system" fn window_proc(
                     }
 
                     // Re-register hotkeys if they changed
-                    // First unregister all existing hotkeys
                     hotkeys::unregister_all_hotkeys(hwnd, &state.registered_hotkeys);
-                    state.registered_hotkeys.clear();
-
-                    // Register new tab hotkeys
-                    let tab_hotkey_ids = hotkeys::register_tab_hotkeys(hwnd, &new_config.hotkeys.tab);
-                    state.registered_hotkeys.extend(tab_hotkey_ids);
+                    state.registered_hotkeys = register_hotkeys(hwnd, &new_config);
 
-                    // Register new profile hotkeys
-                    let profile_hotkey_ids =
-                        hotkeys::register_profile_hotkeys(hwnd, &new_config.profiles);
-                    state.registered_hotkeys.extend(profile_hotkey_ids);
+                    // Pick up the new tab hotkeys and tab_numbers mode
+                    state.tab_numbers = TabNumberBadges::new(&new_config);
+                    state.tab_numbers.update_timer(hwnd);
 
                     // Refresh existing tabs with updated profile data (name, icon, title format)
+                    state
+                        .tab_manager
+                        .set_title_fallback(new_config.title_fallback.clone());
+                    state
+                        .tab_manager
+                        .set_project_icons(new_config.project_icons);
+                    state.tab_manager.set_git_status(new_config.git_status);
+                    state
+                        .memory_warning
+                        .set_threshold(new_config.memory_warning_threshold);
+                    update_window_title(
+                        hwnd,
+                        &new_config.window_title,
+                        state.session_name.as_deref(),
+                    );
                     state.tab_manager.refresh_profiles(&new_config.profiles);
+                    state
+                        .tab_manager
+                        .set_max_concurrent_spawns(new_config.max_concurrent_spawns);
+                    state
+                        .tab_manager
+                        .set_attention_forwarding(new_config.tab_attention);
+                    state
+                        .tab_manager
+                        .set_reduce_motion(reduce_motion(&new_config));
+
+                    // Apply a changed content inset: move the Neovide windows and
+                    // show or hide the resize grips accordingly
+                    if new_config.content_inset != content_inset() {
+                        CONTENT_INSET.store(new_config.content_inset, Ordering::Relaxed);
+                        state
+                            .resize_grips
+                            .set_content_inset(new_config.content_inset);
+                        state
+                            .tab_manager
+                            .update_selected_position(hwnd, content_top());
+                    }
+
+                    // Move, restyle, show or hide the window buttons
+                    layout::set_window_controls(
+                        new_config.window_controls,
+                        new_config.traffic_lights,
+                    );
+                    layout::set_window_buttons(
+                        new_config.show_minimize,
+                        new_config.show_maximize,
+                        new_config.show_close,
+                    );
+
+                    // Start or stop auto-hiding the title bar
+                    if new_config.auto_hide_titlebar != state.auto_hide.is_some() {
+                        set_auto_hide(hwnd, state, new_config.auto_hide_titlebar);
+                    }
+
+                    // Hide or show the title bar: Neovide's area changes with it
+                    if new_config.hide_titlebar != layout::titlebar_hidden() {
+                        layout::set_titlebar_hidden(new_config.hide_titlebar);
+                        state.resize_grips.update();
+                        state
+                            .tab_manager
+                            .update_selected_position(hwnd, content_top());
+                        let _ = InvalidateRect(hwnd, None, true);
+                    }
+
+                    // Apply a changed tab layout: the title bar height changes with it
+                    if new_config.tab_style != tab_style() {
+                        layout::set_tab_style(new_config.tab_style);
+                        state.resize_grips.update();
+                        state
+                            .tab_manager
+                            .update_selected_position(hwnd, content_top());
+                        let _ = InvalidateRect(hwnd, None, true);
+                    }
+
+                    // Go to a newly chosen display
+                    if new_config.monitor != state.config.monitor
+                        && let Some(monitor) = new_config.monitor.as_ref().and_then(find_monitor)
+                    {
+                        move_to_monitor(hwnd, state, &monitor);
+                    }
+
+                    // Apply a changed Alt-Tab setting to new and existing Neovide windows
+                    if new_config.hide_neovide_from_alt_tab
+                        != state.config.hide_neovide_from_alt_tab
+                    {
+                        process::set_hide_from_alt_tab(new_config.hide_neovide_from_alt_tab);
+                        process::set_window_match(new_config.neovide_window.clone());
+                        hooks::set_hooks(new_config.hooks.clone());
+                        state
+                            .tab_manager
+                            .set_alt_tab_visible(!new_config.hide_neovide_from_alt_tab);
+                    }
+
+                    // Wake suspended tabs if background suspension was turned off
+                    if new_config.suspend_background_after.is_none() {
+                        state.tab_manager.resume_all();
+                    }
+
+                    // Recreate the fonts from the new family and size on the next paint
+                    fonts::set_ui_font(&new_config.font_family, new_config.font_size);
+
+                    // Start or stop following external moves (FancyZones and tiling
+                    // window manager modes)
+                    state
+                        .win_event_hooks
+                        .set_location_tracking(new_config.tracks_window_locations());
+                    if new_config.tiling_wm != state.config.tiling_wm {
+                        tiling::apply_ignore_rules(new_config.tiling_wm);
+                    }
+
+                    // Restart the process polling at a changed interval
+                    if !state.polling_paused
+                        && new_config.advanced.process_poll_interval_ms
+                            != state.config.advanced.process_poll_interval_ms
+                    {
+                        SetTimer(
+                            hwnd,
+                            PROCESS_POLL_TIMER_ID,
+                            new_config.advanced.process_poll_interval_ms,
+                            None,
+                        );
+                    }
+
+                    // Forget the tab previews if they were turned off
+                    if !new_config.tab_previews {
+                        hide_tab_preview(hwnd, state);
+                        state.tab_previews.clear();
+                        state.last_preview_capture = None;
+                    }
 
                     // Update the config
                     state.config = new_config;
+                    update_system_menu(hwnd, state);
+                    update_widgets(hwnd, state);
 
                     // Clear icon cache so new icons are loaded
                     clear_icon_cache();
+                    preload_profile_icons(hwnd, &state.config);
 
                     // Repaint to reflect changes
                     InvalidateRect(hwnd, None, false);
@@ -3300,6 +7995,76 @@ unsafe extern "system" fn window_proc(
     }
 }
 
+/// Start decoding the icons of all profiles (and the project type icons tabs may
+/// show instead) on a background thread
+fn preload_profile_icons(hwnd: HWND, config: &Config) {
+    let mut icon_paths: Vec<String> = Vec::new();
+    for profile in &config.profiles {
+        if !icon_paths.contains(&profile.icon) {
+            icon_paths.push(profile.icon.clone());
+        }
+    }
+    if config.project_icons {
+        icon_paths.extend(
+            ProjectType::ALL
+                .iter()
+                .map(|project| project.icon().to_string()),
+        );
+    }
+    preload_icons(hwnd, icon_paths);
+}
+
+/// Shell windows that get the focus while switching applications (taskbar, desktop,
+/// task switcher); the focus is never given back to them
+const SHELL_WINDOW_CLASSES: [&str; 7] = [
+    "Shell_TrayWnd",
+    "Shell_SecondaryTrayWnd",
+    "Progman",
+    "WorkerW",
+    "MultitaskingViewFrame",
+    "XamlExplorerHostIslandWindow",
+    "ForegroundStaging",
+];
+
+fn is_shell_window_class(class_name: &str) -> bool {
+    SHELL_WINDOW_CLASSES.contains(&class_name)
+}
+
+/// Whether `foreground` is another application's window the focus can go back to
+fn is_restorable_foreground(foreground: HWND) -> bool {
+    unsafe {
+        if foreground.is_invalid()
+            || !IsWindowVisible(foreground).as_bool()
+            || is_managed_window(foreground)
+        {
+            return false;
+        }
+        let mut process_id = 0u32;
+        GetWindowThreadProcessId(foreground, Some(&mut process_id));
+        if process_id == std::process::id() {
+            return false;
+        }
+        let mut buffer = [0u16; 256];
+        let len = GetClassNameW(foreground, &mut buffer);
+        !is_shell_window_class(&String::from_utf16_lossy(&buffer[..len as usize]))
+    }
+}
+
+/// Give the focus back to the application that was active before the wrapper (best
+/// effort: Windows refuses if another application took the focus meanwhile)
+fn restore_previous_foreground(state: &WindowState) {
+    if !state.config.restore_foreground_on_exit {
+        return;
+    }
+    if let Some(previous) = state.previous_foreground {
+        unsafe {
+            if IsWindow(previous).as_bool() && !IsIconic(previous).as_bool() {
+                let _ = SetForegroundWindow(previous);
+            }
+        }
+    }
+}
+
 /// Bring the wrapper window to the foreground, restoring it if minimized
 fn bring_window_to_foreground(hwnd: HWND) {
     unsafe {
@@ -3339,15 +8104,10 @@ pub fn show_neovide_not_found_error() {
     );
 }
 
-/// Get the title bar height (for use by other modules)
-#[allow(dead_code)]
-pub fn get_titlebar_height() -> i32 {
-    TITLEBAR_HEIGHT
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::layout::{BUTTON_WIDTH, TITLEBAR_HEIGHT};
 
     #[test]
     fn test_rgb_to_colorref() {
@@ -3358,6 +8118,61 @@ mod tests {
         assert_eq!(rgb_to_colorref(0x1a1b26), 0x261b1a); // Tokyo Night dark
     }
 
+    #[test]
+    fn test_blend_rgb() {
+        assert_eq!(blend_rgb(0x000000, 0xFFFFFF, 0), 0x000000);
+        assert_eq!(blend_rgb(0x000000, 0xFFFFFF, 255), 0xFFFFFF);
+        assert_eq!(blend_rgb(0x000000, 0xFF0000, 51), 0x330000);
+        // Amounts above 255 are clamped
+        assert_eq!(blend_rgb(0x1a1b26, 0xFFFFFF, 1000), 0xFFFFFF);
+    }
+
+    #[test]
+    fn test_leave_autohide_taskbar_edges() {
+        let monitor = RECT {
+            left: 0,
+            top: 0,
+            right: 1920,
+            bottom: 1080,
+        };
+        let mut rect = monitor;
+        leave_autohide_taskbar_edges(&mut rect, &[]);
+        assert_eq!(rect, monitor);
+
+        leave_autohide_taskbar_edges(&mut rect, &[ABE_BOTTOM, ABE_LEFT]);
+        assert_eq!(
+            rect,
+            RECT {
+                left: AUTOHIDE_TASKBAR_EDGE,
+                top: 0,
+                right: 1920,
+                bottom: 1080 - AUTOHIDE_TASKBAR_EDGE,
+            }
+        );
+    }
+
+    #[test]
+    fn test_window_title() {
+        assert_eq!(window_title("neovide-tabs", None), "neovide-tabs");
+        assert_eq!(
+            window_title("neovide-tabs", Some("work")),
+            "neovide-tabs - work"
+        );
+        assert_eq!(window_title("Editor", Some("work")), "Editor - work");
+    }
+
+    #[test]
+    fn test_loading_indicator_text() {
+        assert_eq!(
+            loading_indicator_text(Duration::from_millis(3400), None),
+            "Starting Neovide\u{2026} 3s"
+        );
+        assert_eq!(
+            loading_indicator_text(Duration::from_millis(3400), Some((2, 5))),
+            "Starting Neovide\u{2026} 2 of 5 tabs ready"
+        );
+    }
+
     #[test]
     fn test_button_rects() {
         let (min, max, close) = get_button_rects(1024);
@@ -3416,137 +8231,62 @@ mod tests {
     }
 
     #[test]
-    fn test_get_tab_rect() {
-        let tab0 = get_tab_rect(0, 1024);
-        assert_eq!(tab0.left, TAB_BAR_LEFT_MARGIN);
-        assert_eq!(tab0.right, TAB_BAR_LEFT_MARGIN + TAB_WIDTH);
-        assert_eq!(tab0.top, TAB_VERTICAL_PADDING);
-        assert_eq!(tab0.bottom, TITLEBAR_HEIGHT - TAB_VERTICAL_PADDING);
-
-        let tab1 = get_tab_rect(1, 1024);
-        assert_eq!(tab1.left, TAB_BAR_LEFT_MARGIN + TAB_WIDTH);
-        assert_eq!(tab1.right, TAB_BAR_LEFT_MARGIN + TAB_WIDTH * 2);
-    }
-
-    #[test]
-    fn test_get_new_tab_button_rect() {
-        let btn = get_new_tab_button_rect(0, 1024);
-        assert_eq!(btn.left, TAB_BAR_LEFT_MARGIN);
-        assert_eq!(btn.right, TAB_BAR_LEFT_MARGIN + NEW_TAB_BUTTON_WIDTH);
-
-        let btn = get_new_tab_button_rect(2, 1024);
-        assert_eq!(btn.left, TAB_BAR_LEFT_MARGIN + TAB_WIDTH * 2);
-    }
-
-    #[test]
-    fn test_hit_test_tab_bar() {
-        let width = 1024;
-        let tab_count = 2;
-        let y = (TAB_VERTICAL_PADDING + TITLEBAR_HEIGHT - TAB_VERTICAL_PADDING) / 2;
-
-        // First tab area
-        let x = TAB_BAR_LEFT_MARGIN + 20;
-        assert_eq!(
-            hit_test_tab_bar(x, y, tab_count, width),
-            TabHitResult::Tab(0)
-        );
-
-        // Second tab area
-        let x = TAB_BAR_LEFT_MARGIN + TAB_WIDTH + 20;
-        assert_eq!(
-            hit_test_tab_bar(x, y, tab_count, width),
-            TabHitResult::Tab(1)
-        );
-
-        // New tab button area
-        let x = TAB_BAR_LEFT_MARGIN + TAB_WIDTH * 2 + 10;
-        assert_eq!(
-            hit_test_tab_bar(x, y, tab_count, width),
-            TabHitResult::NewTabButton
-        );
-
-        // Caption area (between new tab button and window buttons)
-        let x = TAB_BAR_LEFT_MARGIN + TAB_WIDTH * 2 + NEW_TAB_BUTTON_WIDTH + 50;
-        assert_eq!(
-            hit_test_tab_bar(x, y, tab_count, width),
-            TabHitResult::Caption
-        );
+    fn test_is_shell_window_class() {
+        assert!(is_shell_window_class("Shell_TrayWnd"));
+        assert!(is_shell_window_class("Progman"));
+        assert!(!is_shell_window_class("CASCADIA_HOSTING_WINDOW_CLASS"));
+        assert!(!is_shell_window_class("shell_traywnd"));
     }
 
     #[test]
-    fn test_calculate_drop_index() {
-        let width = 1024;
-        let tab_count = 3;
-
-        // Position at first tab
-        assert_eq!(
-            calculate_drop_index(TAB_BAR_LEFT_MARGIN + 10, tab_count, width),
-            0
-        );
-
-        // Position at second tab
-        assert_eq!(
-            calculate_drop_index(TAB_BAR_LEFT_MARGIN + TAB_WIDTH + 10, tab_count, width),
-            1
-        );
+    fn test_titlebar_focus_order() {
+        let tab = |id, shown: bool| uia::TabItem {
+            id,
+            name: String::new(),
+            selected: false,
+            closable: true,
+            rect: shown.then(|| get_tab_rect(0, 800).into()),
+            close_rect: None,
+        };
+        let snapshot = uia::TabBarSnapshot {
+            tabs: vec![tab(3, true), tab(5, true), tab(8, false)],
+            buttons: vec![
+                (TitlebarButton::NewTab, RECT::default()),
+                (TitlebarButton::Close, RECT::default()),
+            ],
+            ..Default::default()
+        };
 
-        // Position at third tab
+        // Tabs in the overflow list are skipped
+        let items = titlebar_focus_items(&snapshot);
         assert_eq!(
-            calculate_drop_index(TAB_BAR_LEFT_MARGIN + TAB_WIDTH * 2 + 10, tab_count, width),
-            2
+            items,
+            vec![
+                TitlebarFocus::Tab(3),
+                TitlebarFocus::Tab(5),
+                TitlebarFocus::Button(TitlebarButton::NewTab),
+                TitlebarFocus::Button(TitlebarButton::Close),
+            ]
         );
 
-        // Position beyond last tab
+        let close = TitlebarFocus::Button(TitlebarButton::Close);
         assert_eq!(
-            calculate_drop_index(TAB_BAR_LEFT_MARGIN + TAB_WIDTH * 10, tab_count, width),
-            2
+            step_titlebar_focus(&items, TitlebarFocus::Tab(3), true),
+            Some(TitlebarFocus::Tab(5))
         );
-
-        // Position before first tab
-        assert_eq!(calculate_drop_index(0, tab_count, width), 0);
-    }
-
-    #[test]
-    fn test_calculate_swap_target() {
-        let width = 1024;
-        let tab_count = 3;
-
-        // Tab at index 0, visual position at its normal spot - no swap
-        let tab0_rect = get_tab_rect(0, width);
         assert_eq!(
-            calculate_swap_target(0, tab0_rect.left, tab_count, width),
-            None
+            step_titlebar_focus(&items, TitlebarFocus::Tab(3), false),
+            Some(close)
         );
-
-        // Tab at index 0, dragged right past center of tab 1 - should swap to index 1
-        let tab1_rect = get_tab_rect(1, width);
-        let tab1_center = (tab1_rect.left + tab1_rect.right) / 2;
-        // Position where tab 0's center is past tab 1's center
-        let visual_x = tab1_center - TAB_WIDTH / 2 + 1;
         assert_eq!(
-            calculate_swap_target(0, visual_x, tab_count, width),
-            Some(1)
+            step_titlebar_focus(&items, close, true),
+            Some(TitlebarFocus::Tab(3))
         );
-
-        // Tab at index 1, dragged left past center of tab 0 - should swap to index 0
-        let tab0_center = (tab0_rect.left + tab0_rect.right) / 2;
-        // Position where tab 1's center is past tab 0's center (to the left)
-        let visual_x = tab0_center - TAB_WIDTH / 2 - 1;
+        // A closed tab restarts from the first element
         assert_eq!(
-            calculate_swap_target(1, visual_x, tab_count, width),
-            Some(0)
+            step_titlebar_focus(&items, TitlebarFocus::Tab(8), true),
+            Some(TitlebarFocus::Tab(3))
         );
-
-        // Tab at index 0 (leftmost) - can't swap left
-        let visual_x = -50; // Far left
-        assert_eq!(calculate_swap_target(0, visual_x, tab_count, width), None);
-
-        // Tab at index 2 (rightmost with 3 tabs) - can't swap right
-        let tab2_rect = get_tab_rect(2, width);
-        let visual_x = tab2_rect.left + TAB_WIDTH * 2; // Far right
-        assert_eq!(calculate_swap_target(2, visual_x, tab_count, width), None);
-
-        // Single tab - no swaps possible
-        assert_eq!(calculate_swap_target(0, 0, 1, width), None);
+        assert_eq!(step_titlebar_focus(&[], close, true), None);
     }
 }
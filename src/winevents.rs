@@ -0,0 +1,169 @@
+//! WinEvent hooks used to follow other windows.
+//!
+//! Out-of-context hooks deliver their callbacks on the UI thread through the
+//! message loop. The callbacks only filter events and post them to the wrapper
+//! window, where the actual handling happens with access to the window state.
+
+#![cfg(target_os = "windows")]
+
+use std::sync::atomic::{AtomicIsize, Ordering};
+
+use windows::Win32::Foundation::{HANDLE, HWND, LPARAM, WPARAM};
+use windows::Win32::UI::Accessibility::{HWINEVENTHOOK, SetWinEventHook, UnhookWinEvent};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CHILDID_SELF, EVENT_OBJECT_LOCATIONCHANGE, EVENT_SYSTEM_FOREGROUND, EVENT_SYSTEM_MOVESIZEEND,
+    GetPropW, OBJID_WINDOW, PostMessageW, RemovePropW, SetPropW, WINEVENT_OUTOFCONTEXT, WM_APP,
+};
+use windows::core::{PCWSTR, w};
+
+/// Posted to the wrapper when the foreground window changes (wparam = new foreground HWND)
+pub const WM_FOREGROUND_CHANGED: u32 = WM_APP + 6;
+
+/// Posted to the wrapper when the wrapper or a managed Neovide window was moved or
+/// resized by someone else, e.g. FancyZones (wparam = HWND that moved)
+pub const WM_WINDOW_LOCATION_CHANGED: u32 = WM_APP + 7;
+
+/// Window property set on every Neovide window managed by neovide-tabs, so
+/// window managers, scripts and our own location hook can recognise them
+pub const MANAGED_WINDOW_PROP: PCWSTR = w!("NEOVIDE_TABS_MANAGED");
+
+/// Wrapper window that receives the posted notifications
+static NOTIFY_HWND: AtomicIsize = AtomicIsize::new(0);
+
+/// Installed WinEvent hooks for the wrapper window
+pub struct WinEventHooks {
+    foreground: HWINEVENTHOOK,
+    /// Move/size-end and location-change hooks (snap compatibility mode only)
+    location: Option<[HWINEVENTHOOK; 2]>,
+}
+
+/// Tag a Neovide window as managed by neovide-tabs
+pub fn mark_managed_window(hwnd: HWND) {
+    unsafe {
+        // Any non-null value marks the window; the data itself is unused
+        let flag = HANDLE(std::ptr::without_provenance_mut(1));
+        if let Err(e) = SetPropW(hwnd, MANAGED_WINDOW_PROP, flag) {
+            eprintln!("Failed to tag Neovide window as managed: {}", e);
+        }
+    }
+}
+
+/// Remove the tag once neovide-tabs stops managing the window (its tab closed,
+/// or Neovide replaced it). Does nothing for a window that is gone already.
+pub fn unmark_managed_window(hwnd: HWND) {
+    unsafe {
+        let _ = RemovePropW(hwnd, MANAGED_WINDOW_PROP);
+    }
+}
+
+/// Check whether a window carries the managed-window property
+pub fn is_managed_window(hwnd: HWND) -> bool {
+    unsafe { !GetPropW(hwnd, MANAGED_WINDOW_PROP).is_invalid() }
+}
+
+impl WinEventHooks {
+    /// Start tracking foreground changes for `owner`; location tracking is added
+    /// with `set_location_tracking`
+    pub fn install(owner: HWND) -> Self {
+        NOTIFY_HWND.store(owner.0 as isize, Ordering::Relaxed);
+        Self {
+            foreground: set_hook(EVENT_SYSTEM_FOREGROUND, EVENT_SYSTEM_FOREGROUND),
+            location: None,
+        }
+    }
+
+    /// Enable or disable the move/size and location-change hooks used by the
+    /// FancyZones compatibility mode
+    pub fn set_location_tracking(&mut self, enabled: bool) {
+        match (enabled, self.location.is_some()) {
+            (true, false) => {
+                self.location = Some([
+                    set_hook(EVENT_SYSTEM_MOVESIZEEND, EVENT_SYSTEM_MOVESIZEEND),
+                    set_hook(EVENT_OBJECT_LOCATIONCHANGE, EVENT_OBJECT_LOCATIONCHANGE),
+                ]);
+            }
+            (false, true) => {
+                for hook in self.location.take().into_iter().flatten() {
+                    unhook(hook);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Drop for WinEventHooks {
+    fn drop(&mut self) {
+        unhook(self.foreground);
+        for hook in self.location.take().into_iter().flatten() {
+            unhook(hook);
+        }
+        NOTIFY_HWND.store(0, Ordering::Relaxed);
+    }
+}
+
+fn set_hook(event_min: u32, event_max: u32) -> HWINEVENTHOOK {
+    unsafe {
+        let hook = SetWinEventHook(
+            event_min,
+            event_max,
+            None,
+            Some(win_event_proc),
+            0,
+            0,
+            WINEVENT_OUTOFCONTEXT,
+        );
+        if hook.is_invalid() {
+            eprintln!(
+                "Failed to install WinEvent hook for event 0x{:X}",
+                event_min
+            );
+        }
+        hook
+    }
+}
+
+fn unhook(hook: HWINEVENTHOOK) {
+    if !hook.is_invalid() {
+        unsafe {
+            let _ = UnhookWinEvent(hook);
+        }
+    }
+}
+
+/// Shared WinEvent callback: filters events and forwards them to the wrapper
+unsafe extern "system" fn win_event_proc(
+    _hook: HWINEVENTHOOK,
+    event: u32,
+    hwnd: HWND,
+    id_object: i32,
+    id_child: i32,
+    _event_thread: u32,
+    _event_time: u32,
+) {
+    let owner_raw = NOTIFY_HWND.load(Ordering::Relaxed);
+    if owner_raw == 0 {
+        return;
+    }
+    let owner = HWND(owner_raw as *mut _);
+
+    let message = match event {
+        EVENT_SYSTEM_FOREGROUND => WM_FOREGROUND_CHANGED,
+        EVENT_SYSTEM_MOVESIZEEND | EVENT_OBJECT_LOCATIONCHANGE => {
+            // Location changes fire for carets, cursors and every other window;
+            // only whole-window changes of the wrapper or managed Neovide windows matter
+            if id_object != OBJID_WINDOW.0
+                || id_child != CHILDID_SELF as i32
+                || (hwnd != owner && !is_managed_window(hwnd))
+            {
+                return;
+            }
+            WM_WINDOW_LOCATION_CHANGED
+        }
+        _ => return,
+    };
+
+    unsafe {
+        let _ = PostMessageW(owner, message, WPARAM(hwnd.0 as usize), LPARAM(0));
+    }
+}
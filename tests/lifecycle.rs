@@ -0,0 +1,312 @@
+//! Tab lifecycle tests against the fake Neovide (src/bin/fake-neovide.rs).
+//!
+//! Each test starts neovide-tabs with its own config and data directory and a
+//! PATH on which `neovide` is the fake, then drives it the way Windows would
+//! (hotkey and close messages, a second instance passing on --open-dir) and
+//! checks the fake windows and the events the fakes log. The tests create real
+//! windows, so they need an interactive desktop session and run one at a time.
+//! They and the fake are only built with `--features test-harness`.
+
+#![cfg(target_os = "windows")]
+
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::Path;
+use std::process::{Child, Command};
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use windows::Win32::Foundation::{BOOL, HWND, LPARAM, RECT, WPARAM};
+use windows::Win32::UI::WindowsAndMessaging::{
+    EnumWindows, GetClassNameW, GetWindowRect, GetWindowThreadProcessId, IsWindowVisible,
+    PostMessageW, WM_CLOSE, WM_HOTKEY,
+};
+
+#[path = "../src/testdir.rs"]
+mod testdir;
+
+use testdir::TempDir;
+
+const WRAPPER_EXE: &str = env!("CARGO_BIN_EXE_neovide-tabs");
+const FAKE_NEOVIDE_EXE: &str = env!("CARGO_BIN_EXE_fake-neovide");
+
+/// Class names of the wrapper window and of (fake) Neovide windows
+const WRAPPER_CLASS: &str = "NeovideTabsWindow";
+const NEOVIDE_CLASS: &str = "Window Class";
+
+/// Hotkey ID that selects the first tab (hotkeys::TAB_HOTKEY_BASE)
+const FIRST_TAB_HOTKEY_ID: usize = 1;
+
+/// How long to wait for the wrapper and the fakes to react
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Keeps the tests from running side by side: a second wrapper would take the
+/// other test's --open-dir requests
+static SERIAL: Mutex<()> = Mutex::new(());
+
+/// A running wrapper with its own directories; stops it and its fakes when dropped
+struct Harness {
+    dir: TempDir,
+    wrapper: Child,
+    _serial: MutexGuard<'static, ()>,
+}
+
+impl Harness {
+    /// Start the wrapper in a fresh directory named after `test`
+    fn start(test: &str) -> Self {
+        let serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = TempDir::new(test);
+        fs::create_dir_all(dir.path().join("bin")).unwrap();
+        fs::create_dir_all(dir.path().join("data")).unwrap();
+        fs::copy(FAKE_NEOVIDE_EXE, dir.path().join("bin").join("neovide.exe")).unwrap();
+        fs::write(
+            dir.path().join("config.jsonc"),
+            r#"{ "check_updates": false }"#,
+        )
+        .unwrap();
+
+        let harness = Harness {
+            wrapper: wrapper_command(dir.path()).spawn().unwrap(),
+            dir,
+            _serial: serial,
+        };
+        let pid = harness.wrapper.id();
+        wait_for("the wrapper window", || find_window(WRAPPER_CLASS, pid));
+        harness.wait_for_tabs(1);
+        harness
+    }
+
+    /// The wrapper's window
+    fn window(&self) -> HWND {
+        find_window(WRAPPER_CLASS, self.wrapper.id()).expect("wrapper window")
+    }
+
+    /// Open a tab in `dir` the way Explorer's "Open in neovide-tabs" does
+    fn open_dir(&self, dir: &Path) {
+        let status = wrapper_command(self.dir.path())
+            .arg("--open-dir")
+            .arg(dir)
+            .status()
+            .unwrap();
+        assert!(status.success(), "--open-dir exited with {}", status);
+    }
+
+    /// Lines the fakes logged, as (pid, event)
+    fn events(&self) -> Vec<(u32, String)> {
+        fs::read_to_string(self.dir.path().join("fake-neovide.log"))
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let (pid, event) = line.split_once(' ')?;
+                Some((pid.parse().ok()?, event.to_string()))
+            })
+            .collect()
+    }
+
+    /// Process IDs of the fakes started so far, in start order
+    fn fakes(&self) -> Vec<u32> {
+        self.events()
+            .into_iter()
+            .filter(|(_, event)| event.starts_with("started "))
+            .map(|(pid, _)| pid)
+            .collect()
+    }
+
+    /// Wait until `count` fakes have started and return their windows in start order
+    fn wait_for_tabs(&self, count: usize) -> Vec<HWND> {
+        wait_for(&format!("{} Neovide windows", count), || {
+            let fakes = self.fakes();
+            if fakes.len() < count {
+                return None;
+            }
+            fakes
+                .iter()
+                .map(|&pid| find_window(NEOVIDE_CLASS, pid))
+                .collect()
+        })
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        let _ = self.wrapper.kill();
+        let _ = self.wrapper.wait();
+        for pid in self.fakes() {
+            let _ = Command::new("taskkill")
+                .args(["/F", "/PID", &pid.to_string()])
+                .output();
+        }
+    }
+}
+
+/// The wrapper started with the harness directory's config, data and fake Neovide
+fn wrapper_command(dir: &Path) -> Command {
+    let mut path = OsString::from(dir.join("bin"));
+    if let Some(system_path) = env::var_os("PATH") {
+        path.push(";");
+        path.push(system_path);
+    }
+    let mut cmd = Command::new(WRAPPER_EXE);
+    cmd.arg("--config")
+        .arg(dir.join("config.jsonc"))
+        .arg("--data-dir")
+        .arg(dir.join("data"))
+        .arg("--skip-update-check")
+        .current_dir(dir)
+        .env("PATH", path)
+        .env("FAKE_NEOVIDE_LOG", dir.join("fake-neovide.log"));
+    cmd
+}
+
+/// Poll `check` until it returns a value; panics naming `what` after TIMEOUT
+fn wait_for<T>(what: &str, mut check: impl FnMut() -> Option<T>) -> T {
+    let started = Instant::now();
+    loop {
+        if let Some(value) = check() {
+            return value;
+        }
+        assert!(
+            started.elapsed() < TIMEOUT,
+            "Timed out waiting for {}",
+            what
+        );
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// Wait until only `shown` of `windows` is visible
+fn wait_for_selected(windows: &[HWND], shown: usize) {
+    wait_for(
+        &format!("tab {} to be the only visible one", shown + 1),
+        || {
+            windows
+                .iter()
+                .enumerate()
+                .all(|(i, &hwnd)| is_visible(hwnd) == (i == shown))
+                .then_some(())
+        },
+    );
+}
+
+/// Top-level window of class `class` belonging to process `pid`
+fn find_window(class: &str, pid: u32) -> Option<HWND> {
+    struct Search<'a> {
+        class: &'a str,
+        pid: u32,
+        found: Option<HWND>,
+    }
+
+    unsafe extern "system" fn callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        unsafe {
+            let search = &mut *(lparam.0 as *mut Search);
+            let mut pid = 0;
+            GetWindowThreadProcessId(hwnd, Some(&mut pid));
+            let mut buffer = [0u16; 256];
+            let len = GetClassNameW(hwnd, &mut buffer) as usize;
+            if pid == search.pid && String::from_utf16_lossy(&buffer[..len]) == search.class {
+                search.found = Some(hwnd);
+                return BOOL(0);
+            }
+            BOOL(1)
+        }
+    }
+
+    let mut search = Search {
+        class,
+        pid,
+        found: None,
+    };
+    unsafe {
+        let _ = EnumWindows(Some(callback), LPARAM(&mut search as *mut Search as isize));
+    }
+    search.found
+}
+
+fn is_visible(hwnd: HWND) -> bool {
+    unsafe { IsWindowVisible(hwnd).as_bool() }
+}
+
+fn window_rect(hwnd: HWND) -> RECT {
+    let mut rect = RECT::default();
+    unsafe { GetWindowRect(hwnd, &mut rect).unwrap() };
+    rect
+}
+
+#[test]
+fn test_spawn_and_adopt() {
+    let harness = Harness::start("adopt");
+    let neovide = harness.wait_for_tabs(1)[0];
+
+    // The wrapper shows the window and moves it below its title bar
+    wait_for("the Neovide window to be placed in the wrapper", || {
+        let outer = window_rect(harness.window());
+        let inner = window_rect(neovide);
+        (is_visible(neovide)
+            && inner.left >= outer.left
+            && inner.right <= outer.right
+            && inner.top > outer.top
+            && inner.bottom <= outer.bottom)
+            .then_some(())
+    });
+}
+
+#[test]
+fn test_switch_tabs() {
+    let harness = Harness::start("switch");
+    let project = harness.dir.path().join("project");
+    fs::create_dir_all(&project).unwrap();
+    harness.open_dir(&project);
+    let windows = harness.wait_for_tabs(2);
+
+    // The new tab starts in the folder and is selected
+    let second = harness.fakes()[1];
+    let started_in = harness
+        .events()
+        .into_iter()
+        .find(|(pid, event)| *pid == second && event.starts_with("started "))
+        .map(|(_, event)| event)
+        .unwrap();
+    assert!(started_in.ends_with("project"), "{}", started_in);
+    wait_for_selected(&windows, 1);
+
+    unsafe {
+        PostMessageW(
+            harness.window(),
+            WM_HOTKEY,
+            WPARAM(FIRST_TAB_HOTKEY_ID),
+            LPARAM(0),
+        )
+        .unwrap();
+    }
+    wait_for_selected(&windows, 0);
+}
+
+#[test]
+fn test_close_all() {
+    let mut harness = Harness::start("close");
+    let project = harness.dir.path().join("project");
+    fs::create_dir_all(&project).unwrap();
+    harness.open_dir(&project);
+    harness.wait_for_tabs(2);
+
+    // Closing the wrapper asks every Neovide to close, then exits once they did
+    unsafe {
+        PostMessageW(harness.window(), WM_CLOSE, WPARAM(0), LPARAM(0)).unwrap();
+    }
+    let status = wait_for("the wrapper to exit", || {
+        harness.wrapper.try_wait().unwrap()
+    });
+    assert!(status.success(), "wrapper exited with {}", status);
+
+    let events = harness.events();
+    for pid in harness.fakes() {
+        assert!(
+            events.contains(&(pid, "close".to_string())),
+            "Neovide {} was not asked to close: {:?}",
+            pid,
+            events
+        );
+    }
+}